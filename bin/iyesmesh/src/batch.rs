@@ -0,0 +1,255 @@
+//! Processing whole directory trees (`--recursive`).
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::prelude::*;
+use crate::util::cancel_token;
+
+/// A file to process, found by [`find_inputs`].
+#[derive(Debug)]
+pub struct BatchInput {
+    pub path: PathBuf,
+    /// The path relative to the input directory it was found in (or the
+    /// file name, for files given directly), for mirroring the tree.
+    pub relative: PathBuf,
+}
+
+impl BatchInput {
+    /// Where to write the output of this file: under `out_dir`, at the
+    /// same relative path, with the extension replaced. Its directory is
+    /// created if needed.
+    pub fn output_path(
+        &self,
+        out_dir: &Path,
+        extension: &str,
+    ) -> AnyResult<PathBuf> {
+        let path = out_dir.join(&self.relative).with_extension(extension);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("Cannot create directory {}", parent.display())
+            })?;
+        }
+        Ok(path)
+    }
+}
+
+/// Find the files to process: the input paths that are files (whatever
+/// their extension), and, in the input paths that are directories and
+/// their subdirectories, the files with one of `extensions` (ignoring
+/// case). Other files are skipped.
+///
+/// Symbolic links are followed, but each directory is only walked once, so
+/// links to a parent directory do not loop. Paths that cannot be read are
+/// returned as failures, instead of stopping the search.
+pub fn find_inputs(
+    paths: &[PathBuf],
+    extensions: &[&str],
+    verbose: bool,
+) -> (Vec<BatchInput>, Vec<(PathBuf, anyhow::Error)>) {
+    let mut walk = Walk {
+        extensions,
+        verbose,
+        visited: HashSet::new(),
+        inputs: vec![],
+        failures: vec![],
+        n_skipped: 0,
+    };
+    for path in paths {
+        match std::fs::metadata(path) {
+            Ok(metadata) if metadata.is_dir() => walk.dir(path, Path::new("")),
+            Ok(_) => walk.inputs.push(BatchInput {
+                path: path.clone(),
+                relative: path.file_name().unwrap_or_default().into(),
+            }),
+            Err(e) => walk.failures.push((
+                path.clone(),
+                anyhow::Error::from(e).context("Cannot open input"),
+            )),
+        }
+    }
+    if verbose && walk.n_skipped > 0 {
+        eprintln!(
+            "Skipped {} files without a {} extension.",
+            walk.n_skipped,
+            extensions.join("/")
+        );
+    }
+    (walk.inputs, walk.failures)
+}
+
+struct Walk<'a> {
+    extensions: &'a [&'a str],
+    verbose: bool,
+    /// Canonical paths of the directories walked so far.
+    visited: HashSet<PathBuf>,
+    inputs: Vec<BatchInput>,
+    failures: Vec<(PathBuf, anyhow::Error)>,
+    n_skipped: usize,
+}
+
+impl Walk<'_> {
+    fn dir(
+        &mut self,
+        path: &Path,
+        relative: &Path,
+    ) {
+        let entries = std::fs::canonicalize(path).and_then(|canonical| {
+            if !self.visited.insert(canonical) {
+                return Ok(None);
+            }
+            let mut entries = std::fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.file_name()))
+                .collect::<Result<Vec<_>, _>>()?;
+            // The order of `read_dir` depends on the file system.
+            entries.sort();
+            Ok(Some(entries))
+        });
+        let names = match entries {
+            Ok(Some(names)) => names,
+            Ok(None) => {
+                eprintln!(
+                    "Warning: {} was already searched (it is linked to \
+                     twice, or to one of its parents), skipping it.",
+                    path.display()
+                );
+                return;
+            }
+            Err(e) => {
+                let e = anyhow::Error::from(e).context("Cannot read directory");
+                self.failures.push((path.to_owned(), e));
+                return;
+            }
+        };
+        for name in names {
+            let path = path.join(&name);
+            let relative = relative.join(&name);
+            match std::fs::metadata(&path) {
+                Ok(metadata) if metadata.is_dir() => self.dir(&path, &relative),
+                Ok(_) if self.matches(&path) => {
+                    self.inputs.push(BatchInput { path, relative })
+                }
+                Ok(_) => {
+                    if self.verbose {
+                        eprintln!("Skipping {}.", path.display());
+                    }
+                    self.n_skipped += 1;
+                }
+                Err(e) => {
+                    // Like broken symbolic links.
+                    let e = anyhow::Error::from(e).context("Cannot open input");
+                    self.failures.push((path, e));
+                }
+            }
+        }
+    }
+
+    fn matches(
+        &self,
+        path: &Path,
+    ) -> bool {
+        let extension = path.extension().and_then(|e| e.to_str());
+        extension.is_some_and(|extension| {
+            self.extensions.iter().any(|e| e.eq_ignore_ascii_case(extension))
+        })
+    }
+}
+
+/// Run `f` on each item, on up to `jobs` threads, and pass the results to
+/// `done` (on this thread) in the order of the items, each as soon as the
+/// ones before it are done.
+pub fn map_ordered<T: Sync, R: Send>(
+    items: &[T],
+    jobs: usize,
+    f: impl Fn(&T) -> R + Sync,
+    mut done: impl FnMut(&T, R),
+) {
+    let jobs = jobs.clamp(1, items.len().max(1));
+    if jobs == 1 {
+        for item in items {
+            done(item, f(item));
+        }
+        return;
+    }
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            let (next, f, sender) = (&next, &f, sender.clone());
+            scope.spawn(move || {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else {
+                        break;
+                    };
+                    if sender.send((i, f(item))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        let mut finished = BTreeMap::new();
+        let mut n_done = 0;
+        for (i, result) in receiver {
+            finished.insert(i, result);
+            while let Some(result) = finished.remove(&n_done) {
+                done(&items[n_done], result);
+                n_done += 1;
+            }
+        }
+    });
+}
+
+/// Run `f` on each input (on up to `jobs` threads), print what it returns
+/// (in the order of the inputs), and collect the failures to report them
+/// all at the end, instead of stopping at the first one.
+///
+/// Stops early only if cancelled (with Ctrl-C). Fails if any input failed
+/// (including the `failures` of [`find_inputs`]).
+pub fn run_each(
+    inputs: &[BatchInput],
+    mut failures: Vec<(PathBuf, anyhow::Error)>,
+    jobs: usize,
+    f: impl Fn(&BatchInput) -> AnyResult<String> + Sync,
+) -> AnyResult<()> {
+    for (path, e) in failures.iter() {
+        println!("{}: FAILED: {:#}", path.display(), e);
+    }
+    let n_files = inputs.len() + failures.len();
+    let mut n_cancelled = 0;
+    map_ordered(
+        inputs,
+        jobs,
+        |input| {
+            // Files not started yet are skipped.
+            (!cancel_token().is_cancelled()).then(|| f(input))
+        },
+        |input, result| match result {
+            Some(Ok(message)) => print!("{}", message),
+            Some(Err(e)) => {
+                println!("{}: FAILED: {:#}", input.path.display(), e);
+                failures.push((input.path.clone(), e));
+            }
+            None => n_cancelled += 1,
+        },
+    );
+    if n_cancelled > 0 {
+        bail!("Cancelled, {} of {} files skipped.", n_cancelled, n_files);
+    }
+    let n_failed = failures.len();
+    println!(
+        "{} files: {} done, {} failed.",
+        n_files,
+        n_files - n_failed,
+        n_failed
+    );
+    if n_failed > 0 {
+        println!("Failed:");
+        for (path, _) in failures.iter() {
+            println!("  {}", path.display());
+        }
+        bail!("{} of {} files failed.", n_failed, n_files);
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use iyes_mesh::patch::{Patch, apply};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+
+#[derive(clap::Args, Debug)]
+pub struct ApplyPatchArgs {
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    /// Path to the file the patch was made from
+    old_file: PathBuf,
+    /// Path to the patch
+    patch_file: PathBuf,
+    /// Path where to save the patched file
+    out_file: PathBuf,
+}
+
+pub fn run(
+    _args_common: &CommonArgs,
+    args_cmd: &ApplyPatchArgs,
+) -> AnyResult<()> {
+    let old = crate::util::read_input(&args_cmd.old_file)
+        .context("Could not read old file")?;
+    let patch = crate::util::read_input(&args_cmd.patch_file)
+        .context("Could not read patch file")?;
+    let patch = Patch::from_bytes(&patch).context("Cannot decode patch")?;
+    let new = apply(&old, &patch).context("Cannot apply patch")?;
+    crate::util::write_bytes_file(
+        &args_cmd.out_file,
+        args_cmd.oarg.overwrite,
+        &new,
+    )
+}
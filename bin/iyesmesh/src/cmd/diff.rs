@@ -0,0 +1,610 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use iyes_mesh::HashMap;
+use iyes_mesh::checksum::ChecksumKind;
+use iyes_mesh::descriptor::{
+    AttributeEncoding, ColorSpace, IyesMeshDescriptor, MeshInfo, VertexFormat,
+    VertexUsage,
+};
+use iyes_mesh::header::IyesMeshHeader;
+use iyes_mesh::read::{
+    IyesMeshReader, IyesMeshReaderSettings, IyesMeshReaderWithData,
+};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{MeshSignature, mesh_signatures, named_buffers, read_input};
+
+/// Exit code if the contents of the files differ.
+const EXIT_DIFFERENT: i32 = 1;
+
+/// Maximum number of differing byte ranges reported per buffer.
+const MAX_RANGES: usize = 32;
+
+/// Differing bytes closer than this are reported as one range.
+const RANGE_MERGE_GAP: usize = 16;
+
+/// Maximum size of the table used to align the meshes of the files. Above
+/// it, meshes that are not at the start or end are paired by index.
+const MAX_ALIGN_CELLS: usize = 1 << 22;
+
+#[derive(clap::Args, Debug)]
+pub struct DiffArgs {
+    /// Also show the byte ranges that differ in each changed buffer
+    #[arg(long)]
+    bytes: bool,
+    /// Print the comparison as JSON, for use by scripts
+    ///
+    /// The fields are documented with `JsonDiff` in the source of this
+    /// command. Fields may be added, but are never renamed or removed.
+    #[arg(long)]
+    json: bool,
+    #[command(flatten)]
+    rarg: crate::ReadArgs,
+    /// Path to the old version of the file
+    old_file: PathBuf,
+    /// Path to the new version of the file
+    new_file: PathBuf,
+}
+
+/// A decoded input file.
+struct Loaded {
+    bytes: Vec<u8>,
+    header: IyesMeshHeader,
+    data: IyesMeshReaderWithData,
+}
+
+/// A difference between the contents of the two files.
+#[derive(serde::Serialize)]
+struct Change {
+    /// `mesh`, `attribute`, `instance attribute`, `indices` or `buffer`.
+    kind: &'static str,
+    /// The index of the mesh (in the new file, or in the old file if it
+    /// was removed), the usage of the attribute, or the name of the buffer
+    /// (as in `stats`). Empty for indices.
+    item: String,
+    /// Description of the change.
+    message: String,
+    /// For buffers, with `--bytes`: the ranges of bytes (start inclusive,
+    /// end exclusive) that differ, up to a limit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    byte_ranges: Option<Vec<[u64; 2]>>,
+    /// Number of differing ranges beyond the limit.
+    #[serde(skip_serializing_if = "is_zero")]
+    more_byte_ranges: usize,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+impl Change {
+    fn new(
+        kind: &'static str,
+        item: impl ToString,
+        message: String,
+    ) -> Self {
+        Self {
+            kind,
+            item: item.to_string(),
+            message,
+            byte_ranges: None,
+            more_byte_ranges: 0,
+        }
+    }
+}
+
+/// The document printed by `diff --json`.
+///
+/// Fields may be added, but are never renamed or removed.
+#[derive(serde::Serialize)]
+struct JsonDiff {
+    /// The files are byte-for-byte identical.
+    identical_files: bool,
+    /// The decoded contents are identical (`changes` is empty).
+    identical_content: bool,
+    /// Differences of the contents, at the level of the descriptor
+    /// (meshes, attributes, indices), then of the buffers.
+    changes: Vec<Change>,
+    /// Differences in how identical or different contents are encoded
+    /// (checksums, signature, compression), as human-readable text.
+    encoding_changes: Vec<String>,
+}
+
+pub fn run(
+    _args_common: &CommonArgs,
+    args_cmd: &DiffArgs,
+) -> AnyResult<()> {
+    let settings = IyesMeshReaderSettings::from(&args_cmd.rarg);
+    let old = load(&args_cmd.old_file, settings)
+        .context("Cannot decode old file")?;
+    let new = load(&args_cmd.new_file, settings)
+        .context("Cannot decode new file")?;
+
+    let changes = compare(&old, &new, args_cmd.bytes)?;
+    let mut encoding_changes = compare_encoding(&old, &new);
+    let identical_files = old.bytes == new.bytes;
+    if changes.is_empty() && encoding_changes.is_empty() && !identical_files {
+        encoding_changes.push("compressed data (recompressed)".to_owned());
+    }
+    let diff = JsonDiff {
+        identical_files,
+        identical_content: changes.is_empty(),
+        encoding_changes,
+        changes,
+    };
+    if args_cmd.json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        print_report(&diff);
+    }
+    if !diff.identical_content {
+        std::process::exit(EXIT_DIFFERENT);
+    }
+    Ok(())
+}
+
+fn load(
+    path: &Path,
+    settings: IyesMeshReaderSettings,
+) -> AnyResult<Loaded> {
+    let bytes = read_input(path).context("Could not read file")?;
+    let mut cursor = Cursor::new(&bytes[..]);
+    let reader = IyesMeshReader::init_with_settings(settings, &mut cursor)
+        .context("Cannot decode file metadata and initialize decoding")?;
+    let header = *reader.header();
+    let data = reader.read_all_data().context("Cannot decode data")?;
+    Ok(Loaded {
+        bytes,
+        header,
+        data,
+    })
+}
+
+/// Compare the decoded contents of the files.
+fn compare(
+    old: &Loaded,
+    new: &Loaded,
+    with_ranges: bool,
+) -> AnyResult<Vec<Change>> {
+    let (old_d, new_d) = (old.data.descriptor(), new.data.descriptor());
+    let old_buffers = old.data.into_flat_buffers()?;
+    let new_buffers = new.data.into_flat_buffers()?;
+    let mut changes = vec![];
+
+    let hash = ChecksumKind::RapidHash;
+    let old_meshes = mesh_signatures(&old.data, &old_buffers, hash)?;
+    let new_meshes = mesh_signatures(&new.data, &new_buffers, hash)?;
+    compare_meshes(old_d, new_d, &old_meshes, &new_meshes, &mut changes);
+    compare_attributes(
+        "attribute",
+        (&old_d.attributes, &new_d.attributes),
+        (old_d, new_d),
+        &mut changes,
+    );
+    compare_attributes(
+        "instance attribute",
+        (&old_d.instance_attributes, &new_d.instance_attributes),
+        (old_d, new_d),
+        &mut changes,
+    );
+    let index_format = |d: &IyesMeshDescriptor| d.indices.map(|i| i.format);
+    match (index_format(old_d), index_format(new_d)) {
+        (Some(a), Some(b)) if a != b => changes.push(Change::new(
+            "indices",
+            "",
+            format!("format {:?} -> {:?}", a, b),
+        )),
+        (None, Some(b)) => changes.push(Change::new(
+            "indices",
+            "",
+            format!("added ({:?})", b),
+        )),
+        (Some(_), None) => {
+            changes.push(Change::new("indices", "", "removed".to_owned()))
+        }
+        _ => {}
+    }
+
+    let old_named = named_buffers(old_d, &old_buffers);
+    let new_named = named_buffers(new_d, &new_buffers);
+    for (name, _, new_data) in new_named.iter() {
+        let Some((_, _, old_data)) = old_named.iter().find(|b| b.0 == *name)
+        else {
+            if name == "user data" {
+                let message = format!("added ({})", describe(new_data));
+                changes.push(Change::new("buffer", name, message));
+            }
+            continue;
+        };
+        if old_data == new_data {
+            continue;
+        }
+        let mut change = Change::new(
+            "buffer",
+            name,
+            format!(
+                "changed: {} -> {}",
+                describe(old_data),
+                describe(new_data)
+            ),
+        );
+        if with_ranges {
+            let ranges = differing_ranges(old_data, new_data);
+            change.more_byte_ranges = ranges.len().saturating_sub(MAX_RANGES);
+            change.byte_ranges =
+                Some(ranges.into_iter().take(MAX_RANGES).collect());
+        }
+        changes.push(change);
+    }
+    if old_buffers.user_data.is_some() && new_buffers.user_data.is_none() {
+        changes.push(Change::new(
+            "buffer",
+            "user data",
+            "removed".to_owned(),
+        ));
+    }
+    if old_d.user_data_nonce.is_some() != new_d.user_data_nonce.is_some() {
+        let message = if new_d.user_data_nonce.is_some() {
+            "now encrypted"
+        } else {
+            "no longer encrypted"
+        };
+        changes.push(Change::new("buffer", "user data", message.to_owned()));
+    }
+    Ok(changes)
+}
+
+/// Size and hash of a buffer, for the messages of changed buffers.
+fn describe(data: &[u8]) -> String {
+    format!(
+        "{} bytes (hash {:016x})",
+        data.len(),
+        ChecksumKind::RapidHash.checksum(data)
+    )
+}
+
+/// Whether two meshes have the same data, ignoring the attributes that
+/// only one of them has (in that format), which are reported per attribute.
+fn same_data(
+    a: &MeshSignature,
+    b: &MeshSignature,
+) -> bool {
+    let mut n_common = 0;
+    for (name, hash) in a {
+        if let Some((_, other)) = b.iter().find(|(n, _)| n == name) {
+            if hash != other {
+                return false;
+            }
+            n_common += 1;
+        }
+    }
+    n_common > 0 || (a.is_empty() && b.is_empty())
+}
+
+/// Pair the meshes of the two files, like the lines of a text diff.
+///
+/// The longest sequence of meshes with the same data is matched. The other
+/// meshes between two matches are paired in order (as changed meshes), and
+/// the extra ones are removed or added.
+fn align_meshes(
+    old: &[MeshSignature],
+    new: &[MeshSignature],
+) -> Vec<(Option<usize>, Option<usize>)> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(a, b)| same_data(a, b))
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| same_data(a, b))
+        .count();
+    let (n, m) = (old.len() - prefix - suffix, new.len() - prefix - suffix);
+    let mut matches: Vec<(usize, usize)> =
+        (0..prefix).map(|i| (i, i)).collect();
+    if n * m <= MAX_ALIGN_CELLS {
+        let same = |i: usize, j: usize| {
+            same_data(&old[prefix + i], &new[prefix + j])
+        };
+        // Length of the longest common sequence of the remaining meshes
+        // from `i` and `j` on, at `i * (m + 1) + j`.
+        let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i * (m + 1) + j] = if same(i, j) {
+                    lcs[(i + 1) * (m + 1) + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * (m + 1) + j].max(lcs[i * (m + 1) + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if same(i, j) {
+                matches.push((prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if lcs[(i + 1) * (m + 1) + j] >= lcs[i * (m + 1) + j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+    matches.extend(
+        (1..=suffix).rev().map(|k| (old.len() - k, new.len() - k)),
+    );
+
+    let mut r = vec![];
+    let (mut next_old, mut next_new) = (0, 0);
+    for (i, j) in matches.into_iter().chain([(old.len(), new.len())]) {
+        let gap_old = next_old..i;
+        let gap_new = next_new..j;
+        let n_paired = gap_old.len().min(gap_new.len());
+        let paired = gap_old.clone().zip(gap_new.clone());
+        r.extend(paired.map(|(a, b)| (Some(a), Some(b))));
+        r.extend(gap_old.skip(n_paired).map(|a| (Some(a), None)));
+        r.extend(gap_new.skip(n_paired).map(|b| (None, Some(b))));
+        if i < old.len() {
+            r.push((Some(i), Some(j)));
+        }
+        (next_old, next_new) = (i + 1, j + 1);
+    }
+    r
+}
+
+fn compare_meshes(
+    old_d: &IyesMeshDescriptor,
+    new_d: &IyesMeshDescriptor,
+    old_meshes: &[MeshSignature],
+    new_meshes: &[MeshSignature],
+    changes: &mut Vec<Change>,
+) {
+    let pairs = align_meshes(old_meshes, new_meshes);
+    // Added meshes with the same data as removed meshes were moved.
+    let mut removed: Vec<usize> = pairs
+        .iter()
+        .filter_map(|pair| match pair {
+            (Some(i), None) => Some(*i),
+            _ => None,
+        })
+        .collect();
+    let mut moved_from = HashMap::default();
+    for pair in pairs.iter() {
+        let (None, Some(j)) = *pair else {
+            continue;
+        };
+        let found = removed
+            .iter()
+            .position(|i| same_data(&old_meshes[*i], &new_meshes[j]));
+        if let Some(k) = found {
+            moved_from.insert(j, removed.remove(k));
+        }
+    }
+
+    for pair in pairs {
+        match pair {
+            (Some(i), None) => {
+                if removed.contains(&i) {
+                    let info = &old_d.meshes[i];
+                    let message = format!("removed ({})", describe_mesh(info));
+                    changes.push(Change::new("mesh", i, message));
+                }
+            }
+            (None, Some(j)) => {
+                let message = match moved_from.get(&j) {
+                    Some(i) => format!("moved from old mesh {}", i),
+                    None => {
+                        format!("added ({})", describe_mesh(&new_d.meshes[j]))
+                    }
+                };
+                changes.push(Change::new("mesh", j, message));
+            }
+            (Some(i), Some(j)) => {
+                let (old_info, new_info) = (&old_d.meshes[i], &new_d.meshes[j]);
+                let counts = [
+                    ("vertices", old_info.vertex_count, new_info.vertex_count),
+                    ("indices", old_info.index_count, new_info.index_count),
+                    (
+                        "instances",
+                        old_info.instance_count,
+                        new_info.instance_count,
+                    ),
+                ];
+                let mut parts: Vec<String> = counts
+                    .into_iter()
+                    .filter(|(_, a, b)| a != b)
+                    .map(|(what, a, b)| format!("{} {} -> {}", what, a, b))
+                    .collect();
+                let same = same_data(&old_meshes[i], &new_meshes[j]);
+                if parts.is_empty() && !same {
+                    parts.push("data changed".to_owned());
+                }
+                if !parts.is_empty() {
+                    if i != j {
+                        parts.push(format!("was old mesh {}", i));
+                    }
+                    changes.push(Change::new("mesh", j, parts.join(", ")));
+                }
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+}
+
+fn describe_mesh(info: &MeshInfo) -> String {
+    format!(
+        "{} vertices, {} indices, {} instances",
+        info.vertex_count, info.index_count, info.instance_count
+    )
+}
+
+fn compare_attributes(
+    kind: &'static str,
+    (old_attrs, new_attrs): (
+        &HashMap<VertexUsage, VertexFormat>,
+        &HashMap<VertexUsage, VertexFormat>,
+    ),
+    (old_d, new_d): (&IyesMeshDescriptor, &IyesMeshDescriptor),
+    changes: &mut Vec<Change>,
+) {
+    let mut usages: Vec<VertexUsage> =
+        old_attrs.keys().chain(new_attrs.keys()).copied().collect();
+    usages.sort();
+    usages.dedup();
+    for usage in usages {
+        let message = match (old_attrs.get(&usage), new_attrs.get(&usage)) {
+            (Some(a), None) => format!("removed ({:?})", a),
+            (None, Some(b)) => format!("added ({:?})", b),
+            (Some(a), Some(b)) => {
+                let mut parts = vec![];
+                if a != b {
+                    parts.push(format!("format {:?} -> {:?}", a, b));
+                }
+                let encoding = |d: &IyesMeshDescriptor| {
+                    d.attribute_encodings.get(&usage).copied()
+                };
+                let (ea, eb) = (encoding(old_d), encoding(new_d));
+                if ea != eb {
+                    parts.push(format!(
+                        "encoding {} -> {}",
+                        describe_encoding(ea),
+                        describe_encoding(eb)
+                    ));
+                }
+                let color_space = |d: &IyesMeshDescriptor| {
+                    d.color_spaces.get(&usage).copied()
+                };
+                let (ca, cb) = (color_space(old_d), color_space(new_d));
+                if ca != cb {
+                    parts.push(format!(
+                        "color space {} -> {}",
+                        describe_color_space(ca),
+                        describe_color_space(cb)
+                    ));
+                }
+                if parts.is_empty() {
+                    continue;
+                }
+                parts.join(", ")
+            }
+            (None, None) => unreachable!(),
+        };
+        changes.push(Change::new(kind, usage, message));
+    }
+}
+
+fn describe_encoding(encoding: Option<AttributeEncoding>) -> String {
+    encoding.map_or("none".to_owned(), |e| format!("{:?}", e))
+}
+
+fn describe_color_space(color_space: Option<ColorSpace>) -> String {
+    color_space.map_or("unspecified".to_owned(), |c| format!("{:?}", c))
+}
+
+/// The byte ranges that differ between two buffers. If their lengths
+/// differ, the extra bytes of the longer one are a differing range.
+fn differing_ranges(
+    a: &[u8],
+    b: &[u8],
+) -> Vec<[u64; 2]> {
+    let mut r: Vec<[u64; 2]> = vec![];
+    let mut push = |start: usize, end: usize| match r.last_mut() {
+        Some(last) if start - last[1] as usize <= RANGE_MERGE_GAP => {
+            last[1] = end as u64;
+        }
+        _ => r.push([start as u64, end as u64]),
+    };
+    for (i, (x, y)) in a.iter().zip(b).enumerate() {
+        if x != y {
+            push(i, i + 1);
+        }
+    }
+    let common = a.len().min(b.len());
+    if a.len() != b.len() {
+        push(common, a.len().max(b.len()));
+    }
+    r
+}
+
+/// Differences in how the files are encoded, other than their contents and
+/// their compressed data.
+fn compare_encoding(
+    old: &Loaded,
+    new: &Loaded,
+) -> Vec<String> {
+    let (old_d, new_d) = (old.data.descriptor(), new.data.descriptor());
+    let yes_no = |b: bool| if b { "yes" } else { "no" };
+    let checksum_name =
+        |id: u8| ChecksumKind::name_of_id(id).unwrap_or("unknown");
+    let items = [
+        (
+            "format version",
+            { old.header.version }.to_string(),
+            { new.header.version }.to_string(),
+        ),
+        (
+            "data checksum",
+            yes_no(old.header.data_checksum != 0).to_owned(),
+            yes_no(new.header.data_checksum != 0).to_owned(),
+        ),
+        (
+            "user data checksum",
+            yes_no(old_d.user_data_checksum.is_some()).to_owned(),
+            yes_no(new_d.user_data_checksum.is_some()).to_owned(),
+        ),
+        (
+            "checksum algorithm",
+            checksum_name(old_d.checksum_kind).to_owned(),
+            checksum_name(new_d.checksum_kind).to_owned(),
+        ),
+        (
+            "signed",
+            yes_no(old_d.signed).to_owned(),
+            yes_no(new_d.signed).to_owned(),
+        ),
+    ];
+    items
+        .into_iter()
+        .filter(|(_, a, b)| a != b)
+        .map(|(what, a, b)| format!("{}: {} -> {}", what, a, b))
+        .collect()
+}
+
+/// Print the comparison in a human-readable form.
+fn print_report(diff: &JsonDiff) {
+    for change in diff.changes.iter() {
+        if change.item.is_empty() {
+            println!("{}: {}", change.kind, change.message);
+        } else {
+            println!("{} {}: {}", change.kind, change.item, change.message);
+        }
+        if let Some(ranges) = &change.byte_ranges {
+            let ranges: Vec<String> =
+                ranges.iter().map(|[a, b]| format!("{}..{}", a, b)).collect();
+            println!("  differing bytes: {}", ranges.join(", "));
+            if change.more_byte_ranges > 0 {
+                println!("  ... and {} more ranges", change.more_byte_ranges);
+            }
+        }
+    }
+    if diff.identical_files {
+        println!("The files are identical.");
+        return;
+    }
+    if diff.identical_content {
+        println!("The contents are identical.");
+    } else {
+        println!("Differences in the contents: {}", diff.changes.len());
+    }
+    if !diff.encoding_changes.is_empty() {
+        println!("Encoding differences:");
+        for change in diff.encoding_changes.iter() {
+            println!("  {}", change);
+        }
+    }
+}
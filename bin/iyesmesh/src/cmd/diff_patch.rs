@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+
+use iyes_mesh::patch::diff;
+
+use crate::CommonArgs;
+use crate::prelude::*;
+
+#[derive(clap::Args, Debug)]
+pub struct DiffPatchArgs {
+    /// Zstd compression level the new file was written with
+    ///
+    /// Needed to make a compact patch, if the file was not written with
+    /// one of the presets (fastest, default or max).
+    #[arg(short, long, allow_negative_numbers = true)]
+    #[arg(value_parser = crate::util::parse_compression_level)]
+    level: Option<i32>,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    /// Path to the old version of the file
+    old_file: PathBuf,
+    /// Path to the new version of the file
+    new_file: PathBuf,
+    /// Path where to save the patch
+    patch_file: PathBuf,
+}
+
+pub fn run(
+    args_common: &CommonArgs,
+    args_cmd: &DiffPatchArgs,
+) -> AnyResult<()> {
+    let old = crate::util::read_input(&args_cmd.old_file)
+        .context("Could not read old file")?;
+    let new = crate::util::read_input(&args_cmd.new_file)
+        .context("Could not read new file")?;
+    let patch = diff(&old, &new, args_cmd.level)
+        .context("Cannot compare files")?;
+    if patch.is_verbatim() {
+        eprintln!(
+            "Warning! Cannot reproduce the compression of the new file \
+             (try --level). The patch contains all of its data."
+        );
+    }
+    let bytes = patch.to_bytes().context("Cannot encode patch")?;
+    crate::util::write_bytes_file(
+        &args_cmd.patch_file,
+        args_cmd.oarg.overwrite,
+        &bytes,
+    )?;
+    if args_common.verbose {
+        eprintln!(
+            "Patch size: {} bytes (new file: {} bytes).",
+            bytes.len(),
+            new.len(),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use iyes_mesh::descriptor::VertexUsage;
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{
+    decode_special_attributes, open_input, take_stdout, with_decoded,
+    write_bytes_file,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct DumpBufferArgs {
+    #[command(flatten)]
+    buffer: BufferArg,
+    /// Only dump the part of the buffer of this mesh
+    #[arg(short, long, value_parser = crate::util::parse_mesh_index)]
+    mesh: Option<usize>,
+    #[command(flatten)]
+    rarg: crate::ReadArgs,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    inpath: crate::InputPath,
+    #[command(flatten)]
+    outpath: crate::OutputPath,
+}
+
+#[derive(clap::Args, Debug)]
+#[group(required = true, multiple = false)]
+struct BufferArg {
+    /// Dump a vertex attribute (e.g. `position`, `custom:7`)
+    #[arg(long)]
+    attr: Option<VertexUsage>,
+    /// Dump the indices
+    #[arg(long)]
+    indices: bool,
+}
+
+pub fn run(
+    _args_common: &CommonArgs,
+    args_cmd: &DumpBufferArgs,
+) -> AnyResult<()> {
+    take_stdout(&args_cmd.outpath.out_file);
+    let mut infile = open_input(&args_cmd.inpath.in_file)
+        .context("Could not open input file")?;
+    let reader = IyesMeshReader::init_with_settings(
+        IyesMeshReaderSettings::from(&args_cmd.rarg),
+        &mut infile,
+    )
+    .context("Cannot decode file metadata and initialize decoding")?;
+    let descriptor = reader.descriptor();
+    let n_meshes = descriptor.meshes.len();
+    if let Some(index) = args_cmd.mesh
+        && index >= n_meshes
+    {
+        bail!(
+            "Mesh {} does not exist (the file has {} meshes)",
+            index,
+            n_meshes
+        );
+    }
+    if let Some(usage) = args_cmd.buffer.attr
+        && !descriptor.attributes.contains_key(&usage)
+    {
+        let mut present: Vec<_> = descriptor.attributes.keys().collect();
+        present.sort();
+        let present: Vec<_> = present.iter().map(|u| u.to_string()).collect();
+        bail!(
+            "The file has no attribute {} (attributes: {})",
+            usage,
+            present.join(", ")
+        );
+    }
+    if args_cmd.buffer.indices && descriptor.indices.is_none() {
+        bail!("The file has no indices");
+    }
+
+    let with_data =
+        reader.read_all_data().context("Cannot decode file data")?;
+    let flatbufs =
+        with_data.into_flat_buffers().context("Cannot decode file buffers")?;
+    let meshes = with_data
+        .into_split_meshes(&flatbufs)
+        .context("Cannot decode file meshes")?;
+    let decoded = decode_special_attributes(&with_data, &meshes);
+
+    let mut bytes = vec![];
+    let mut format_name = String::new();
+    let mut element_size = 1;
+    for (i, mesh) in meshes.meshes.iter().enumerate() {
+        if args_cmd.mesh.is_some_and(|index| index != i) {
+            continue;
+        }
+        let mesh = with_decoded(mesh, &decoded[i]);
+        let (name, size, data) = match args_cmd.buffer.attr {
+            Some(usage) => {
+                let (format, data) = mesh.attributes[&usage];
+                (format!("{:?}", format), format.size(), data)
+            }
+            None => {
+                let (format, data) = mesh.indices.unwrap();
+                (format!("{:?}", format), format.size(), data)
+            }
+        };
+        (format_name, element_size) = (name, size);
+        bytes.extend_from_slice(data);
+    }
+
+    write_bytes_file(
+        &args_cmd.outpath.out_file,
+        args_cmd.oarg.overwrite,
+        &bytes,
+    )?;
+    let what = match args_cmd.buffer.attr {
+        Some(_) => "vertices",
+        None => "indices",
+    };
+    infoln!(
+        "Wrote {} {} of format {} ({} bytes, little-endian).",
+        bytes.len() / element_size,
+        what,
+        format_name,
+        bytes.len()
+    );
+    Ok(())
+}
@@ -1,14 +1,30 @@
-use std::io::BufWriter;
-
 use iyes_mesh::HashSet;
+use iyes_mesh::convert::{can_convert, count_clamped, value_range};
+use iyes_mesh::descriptor::{
+    ColorSpace, IyesMeshDescriptor, VertexFormat, VertexUsage,
+};
+use iyes_mesh::mesh::{
+    AppliedTransform, CenterMode, MeshData, MeshDataRef, MeshError,
+    NormalMode, concatenate, downconvert_indices, generate_normals,
+    invert_normals, normalize_joint_weights, normalize_scale, recenter,
+    scrub_non_finite, split_connected_components, transform, transform_uvs,
+    weld,
+};
 use iyes_mesh::read::{
     IyesMeshReader, IyesMeshReaderSettings,
 };
-use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+use iyes_mesh::write::{
+    AttributeFilter, IyesMeshWriter, IyesMeshWriterSettings,
+};
 
 use crate::CommonArgs;
 use crate::prelude::*;
-use crate::util::load_user_data;
+use crate::util::{
+    AddAttr, compose_transform, copy_color_spaces, copy_instance_data,
+    copy_mesh_instances, copy_user_data, decode_special_attributes,
+    load_user_data, mul_matrix, open_input, print_write_plan, take_stdout,
+    with_decoded, write_output_file,
+};
 
 #[derive(clap::Args, Debug)]
 pub struct EditArgs {
@@ -18,15 +34,178 @@ pub struct EditArgs {
     /// If the file is not an IMA file, use its raw contents as-is.
     #[arg(short, long)]
     user_data: Option<Option<PathBuf>>,
-    /// If a user data file is provided, do not try to parse it as an IMA file
+    /// Do not try to parse the user data as an IMA file
     #[arg(long)]
     user_data_force_raw: bool,
+    /// Print info about the output file, without writing anything
+    #[arg(long)]
+    dry_run: bool,
     /// Delete existing user data
     #[arg(short = 'D', long)]
     drop_user_data: bool,
     /// Delete specific meshes
     #[arg(short = 'd', long)]
     drop_mesh: Vec<usize>,
+    /// Only keep these meshes (in their order in the input file)
+    #[arg(long, conflicts_with = "drop_mesh")]
+    #[arg(value_parser = crate::util::parse_mesh_index)]
+    keep_mesh: Vec<usize>,
+    /// Change the order of the meshes (e.g. `2,0,1`)
+    ///
+    /// The listed meshes come first, in the given order, followed by the
+    /// others in their original order. Indices are those of the input.
+    #[arg(long, value_delimiter = ',', value_name = "INDICES")]
+    #[arg(value_parser = crate::util::parse_mesh_index)]
+    order: Vec<usize>,
+    /// Only keep these vertex attributes (e.g. `position`, `custom:7`)
+    #[arg(long)]
+    keep_attr: Vec<VertexUsage>,
+    /// Delete these vertex attributes (e.g. `normal`, `custom:7`)
+    #[arg(long)]
+    drop_attr: Vec<VertexUsage>,
+    /// Add a vertex attribute from a raw file (e.g. `custom:3:float32:ao.bin`)
+    ///
+    /// The value is `<ATTRIBUTE>:<FORMAT>:<PATH>[:<MESH>]`. The file has the
+    /// little-endian values of the vertices of the mesh, or of all meshes,
+    /// one after the other, if no mesh is given. All meshes of a file have
+    /// the same attributes, so other meshes must get it too, or be deleted.
+    /// Done before --scrub-nan.
+    #[arg(long, value_parser = crate::util::parse_add_attr)]
+    add_attr: Vec<AddAttr>,
+    /// Allow --add-attr to replace attributes that meshes already have
+    #[arg(long)]
+    overwrite_attr: bool,
+    /// Store a vertex attribute in another format (e.g. `color=unorm8x4`)
+    #[arg(long, value_parser = crate::util::parse_attr_conversion)]
+    convert_attr: Vec<(VertexUsage, VertexFormat)>,
+    /// Apply the sRGB transfer function when converting colors
+    ///
+    /// Float colors are treated as linear, normalized colors as sRGB.
+    #[arg(long)]
+    srgb: bool,
+    /// Tag an attribute with a color space (e.g. `color=srgb`)
+    ///
+    /// One of `linear`, `srgb` or `unspecified`. This does not change the
+    /// data. Tags of the input file are kept otherwise.
+    #[arg(long, value_parser = crate::util::parse_color_space_tag)]
+    set_colorspace: Vec<(VertexUsage, Option<ColorSpace>)>,
+    /// Replace NaN and infinite float values (with 0 if unspecified)
+    ///
+    /// Done before everything else, except --add-attr.
+    #[arg(long, num_args = 0..=1, value_name = "VALUE")]
+    #[arg(default_missing_value = "0")]
+    scrub_nan: Option<f32>,
+    /// Rescale joint weights to sum to 1, removing invalid ones
+    ///
+    /// Done after --scrub-nan. Meshes without joint weights are unchanged.
+    #[arg(long)]
+    fix_weights: bool,
+    /// Replace each mesh with its connected components
+    ///
+    /// Done before welding. Triangles are connected if they share vertices
+    /// or vertex positions.
+    #[arg(long)]
+    split_components: bool,
+    /// Scale by a single factor, or per axis (`X,Y,Z`)
+    ///
+    /// Transforms are done after splitting, in the order: scale, rotate,
+    /// translate, matrix. Mirroring (negative scales) flips the winding.
+    #[arg(long, allow_hyphen_values = true)]
+    #[arg(value_parser = crate::util::parse_scale)]
+    scale: Option<[f32; 3]>,
+    /// Rotate around the X, Y and Z axes (`X,Y,Z`, in degrees)
+    #[arg(long, allow_hyphen_values = true, value_name = "X,Y,Z")]
+    #[arg(value_parser = crate::util::parse_vec3)]
+    rotate_deg: Option<[f32; 3]>,
+    /// Translate by `X,Y,Z`
+    #[arg(long, allow_hyphen_values = true, value_name = "X,Y,Z")]
+    #[arg(value_parser = crate::util::parse_vec3)]
+    translate: Option<[f32; 3]>,
+    /// Transform by a 4x4 matrix (16 comma-separated numbers, column-major)
+    #[arg(long, allow_hyphen_values = true)]
+    #[arg(value_parser = crate::util::parse_matrix)]
+    matrix: Option<[[f32; 4]; 4]>,
+    /// Only transform these meshes (default: all)
+    #[arg(long, visible_alias = "mesh")]
+    #[arg(value_parser = crate::util::parse_mesh_index)]
+    transform_mesh: Vec<usize>,
+    /// Move each mesh so that its center (`bounds` or `centroid`) is at
+    /// the origin
+    ///
+    /// Done after the other transforms. Prints the applied translation.
+    #[arg(long, num_args = 0..=1, value_name = "MODE")]
+    #[arg(default_missing_value = "bounds")]
+    #[arg(value_parser = crate::util::parse_center_mode)]
+    recenter: Option<CenterMode>,
+    /// Scale each mesh uniformly so that its largest extent is 1
+    ///
+    /// Done after --recenter. Prints the applied scale.
+    #[arg(long)]
+    unit_scale: bool,
+    /// Flip the V texture coordinate (V becomes 1 - V)
+    ///
+    /// Applies to all UV attributes, before --uv-transform.
+    #[arg(long)]
+    flip_uv_v: bool,
+    /// Scale and offset texture coordinates (`SX,SY,OX,OY`)
+    ///
+    /// Applies to all UV attributes: U becomes U * SX + OX, and V becomes
+    /// V * SY + OY.
+    #[arg(long, allow_hyphen_values = true, value_name = "SX,SY,OX,OY")]
+    #[arg(value_parser = crate::util::parse_vec4)]
+    uv_transform: Option<[f32; 4]>,
+    /// Merge duplicate vertices, optionally with a position tolerance
+    ///
+    /// Vertices are merged if all their attributes are identical, except
+    /// for positions, which may differ by up to the given value in every
+    /// axis. Implies --upconvert-indices.
+    #[arg(long, num_args = 0..=1, value_name = "EPSILON")]
+    weld: Option<Option<f32>>,
+    /// Generate normals (`flat` or `smooth`), replacing any existing ones
+    ///
+    /// Done after welding. Flat normals need unwelded vertices.
+    #[arg(long, value_name = "MODE")]
+    #[arg(value_parser = crate::util::parse_normal_mode)]
+    gen_normals: Option<NormalMode>,
+    /// Flip normals (and tangent handedness), without changing the winding
+    ///
+    /// Done after generating normals.
+    #[arg(long)]
+    invert_normals: bool,
+    /// Combine all meshes into a single mesh
+    ///
+    /// Done after generating normals. All meshes must have the same vertex
+    /// attributes.
+    #[arg(long)]
+    concat: bool,
+    /// Reorder triangles and vertices for rendering performance
+    ///
+    /// Done after everything else. Meshes must be indexed (see --weld).
+    #[cfg(feature = "meshopt")]
+    #[arg(long)]
+    optimize: bool,
+    /// Store indices as U16 if all indices of all meshes fit
+    ///
+    /// Done last. If some index does not fit, the indices are kept as
+    /// they are (see --strict).
+    #[arg(long)]
+    downconvert_indices: bool,
+    /// Fail if --downconvert-indices cannot convert the indices
+    #[arg(long, requires = "downconvert_indices")]
+    strict: bool,
+    /// Allow deleting the Position attribute
+    #[arg(long)]
+    force: bool,
+    /// Sign the output file with this Ed25519 private key
+    ///
+    /// The key is a PKCS#8 PEM file (as made by `openssl genpkey -algorithm
+    /// ed25519`), or the 32-byte secret key stored raw.
+    #[cfg(feature = "signing")]
+    #[arg(long, value_name = "KEY_FILE")]
+    sign: Option<PathBuf>,
+    #[cfg(feature = "encryption")]
+    #[command(flatten)]
+    key: crate::UserDataKeyArgs,
     #[command(flatten)]
     rarg: crate::ReadArgs,
     #[command(flatten)]
@@ -37,33 +216,112 @@ pub struct EditArgs {
     paths: crate::InOutPaths,
 }
 
+#[cfg_attr(not(feature = "meshopt"), allow(unused_variables))]
 pub fn run(
-    _args_common: &CommonArgs,
+    args_common: &CommonArgs,
     args_cmd: &EditArgs,
 ) -> AnyResult<()> {
-    let mut writer = IyesMeshWriter::new_with_settings(
-        IyesMeshWriterSettings::from(&args_cmd.warg),
-    );
+    take_stdout(args_cmd.paths.output()?);
+    let read_settings = IyesMeshReaderSettings {
+        #[cfg(feature = "encryption")]
+        user_data_key: args_cmd.key.load()?,
+        ..IyesMeshReaderSettings::from(&args_cmd.rarg)
+    };
+    let mut settings = IyesMeshWriterSettings {
+        #[cfg(feature = "encryption")]
+        user_data_key: read_settings.user_data_key,
+        #[cfg(feature = "signing")]
+        signing_key: args_cmd
+            .sign
+            .as_deref()
+            .map(crate::util::load_signing_key)
+            .transpose()?,
+        ..IyesMeshWriterSettings::from(&args_cmd.warg)
+    };
+    settings.srgb_colors = args_cmd.srgb;
+    settings.upconvert_indices |= args_cmd.weld.is_some();
+    let mut writer = IyesMeshWriter::new_with_settings(settings);
+    writer.set_attribute_filter(AttributeFilter {
+        keep: (!args_cmd.keep_attr.is_empty())
+            .then(|| args_cmd.keep_attr.iter().copied().collect()),
+        drop: args_cmd.drop_attr.iter().copied().collect(),
+        allow_dropping_position: args_cmd.force,
+    });
+    for &(usage, format) in args_cmd.convert_attr.iter() {
+        writer.set_attribute_conversion(usage, format);
+    }
     let new_user_data;
-    match &args_cmd.user_data {
-        Some(src) => {
-            new_user_data = load_user_data(
-                src.as_deref(),
-                IyesMeshReaderSettings::from(&args_cmd.rarg),
-                args_cmd.user_data_force_raw,
-            )?;
-            writer.set_user_data(&new_user_data);
-        }
-        None => {}
+    if let Some(src) = &args_cmd.user_data {
+        new_user_data = load_user_data(
+            src.as_deref(),
+            read_settings,
+            args_cmd.user_data_force_raw,
+        )?;
+        writer.set_user_data(&new_user_data);
     }
 
-    let mut infile = std::fs::File::open(&args_cmd.paths.in_file)
+    let mut infile = open_input(&args_cmd.paths.in_file)
         .context("Could not open input file")?;
-    let reader = IyesMeshReader::init_with_settings(
-        IyesMeshReaderSettings::from(&args_cmd.rarg),
-        &mut infile,
-    )
-    .context("Cannot decode file metadata and initialize decoding")?;
+    let reader = IyesMeshReader::init_with_settings(read_settings, &mut infile)
+        .context("Cannot decode file metadata and initialize decoding")?;
+    let n_meshes = reader.descriptor().meshes.len();
+    let listed = args_cmd
+        .keep_mesh
+        .iter()
+        .chain(args_cmd.order.iter())
+        .chain(args_cmd.transform_mesh.iter())
+        .chain(args_cmd.add_attr.iter().filter_map(|a| a.mesh.as_ref()));
+    if let Some(&i) = listed.clone().find(|&&i| i >= n_meshes) {
+        bail!("Mesh {} does not exist (the file has {} meshes)", i, n_meshes);
+    }
+    let attributes = &reader.descriptor().attributes;
+    if let Some(usage) =
+        args_cmd.drop_attr.iter().find(|u| !attributes.contains_key(u))
+    {
+        let mut present: Vec<_> = attributes.keys().collect();
+        present.sort();
+        let present: Vec<_> = present.iter().map(|u| u.to_string()).collect();
+        bail!(
+            "Cannot drop attribute {}, the file does not have it \
+             (attributes: {})",
+            usage,
+            present.join(", ")
+        );
+    }
+    let descriptor = reader.descriptor();
+    for &(usage, to) in args_cmd.convert_attr.iter() {
+        let Some(&format) = descriptor.attributes.get(&usage) else {
+            continue;
+        };
+        let from = descriptor
+            .attribute_encodings
+            .get(&usage)
+            .map_or(format, |e| e.decoded_format());
+        if !can_convert(from, to) {
+            let supported: Vec<_> = VertexFormat::ALL
+                .iter()
+                .filter(|&&f| f != from && can_convert(from, f))
+                .map(|f| format!("{:?}", f))
+                .collect();
+            bail!(
+                "Cannot convert {} from {:?} to {:?} (possible formats: {})",
+                usage,
+                from,
+                to,
+                supported.join(", ")
+            );
+        }
+    }
+    let mut ordered = HashSet::default();
+    if let Some(i) = args_cmd.order.iter().find(|&&i| !ordered.insert(i)) {
+        bail!("Mesh {} is listed more than once in --order", i);
+    }
+    let order: Vec<_> = args_cmd
+        .order
+        .iter()
+        .copied()
+        .chain((0..n_meshes).filter(|i| !ordered.contains(i)))
+        .collect();
     let with_data =
         reader.read_all_data().context("Cannot decode file data")?;
     let flatbufs =
@@ -71,14 +329,19 @@ pub fn run(
     let meshes = with_data
         .into_split_meshes(&flatbufs)
         .context("Cannot decode file meshes")?;
+    let decoded = decode_special_attributes(&with_data, &meshes);
+
+    copy_color_spaces(&mut writer, &with_data);
+    for &(usage, color_space) in args_cmd.set_colorspace.iter() {
+        match color_space {
+            Some(color_space) => writer.set_color_space(usage, color_space),
+            None => writer.clear_color_space(usage),
+        }
+    }
 
     match (args_cmd.drop_user_data, &args_cmd.user_data) {
         (false, None) => {
-            if let Some(data) = flatbufs.user_data {
-                writer.set_user_data(data);
-            } else {
-                writer.clear_user_data();
-            }
+            copy_user_data(&mut writer, &with_data, &flatbufs);
         }
         (true, None) => {
             writer.clear_user_data();
@@ -86,25 +349,457 @@ pub fn run(
         _ => {}
     }
 
+    let added: Vec<_> = args_cmd
+        .add_attr
+        .iter()
+        .map(|add| {
+            std::fs::read(&add.path).with_context(|| {
+                format!("Could not read attribute file {}", add.path.display())
+            })
+        })
+        .collect::<AnyResult<_>>()?;
     let drop_meshes: HashSet<_> = args_cmd.drop_mesh.iter().copied().collect();
-    for (i, m) in meshes.meshes.iter().enumerate() {
-        if drop_meshes.contains(&i) {
-            continue;
+    let keep_meshes: HashSet<_> = args_cmd.keep_mesh.iter().copied().collect();
+    let mut sources: Vec<_> = order
+        .into_iter()
+        .filter(|i| !drop_meshes.contains(i))
+        .filter(|i| keep_meshes.is_empty() || keep_meshes.contains(i))
+        .map(|i| (i, with_decoded(&meshes.meshes[i], &decoded[i])))
+        .collect();
+    for (add, bytes) in args_cmd.add_attr.iter().zip(added.iter()) {
+        add_attribute(
+            &mut sources,
+            with_data.descriptor(),
+            add,
+            bytes,
+            args_cmd.overwrite_attr,
+        )?;
+    }
+    let mut scrubbed = vec![];
+    if let Some(value) = args_cmd.scrub_nan {
+        for (i, mesh) in sources.iter() {
+            let (r, n_replaced) = scrub_non_finite(mesh, value)
+                .with_context(|| format!("Cannot scrub mesh {i}"))?;
+            if n_replaced > 0 {
+                infoln!("Mesh {i}: replaced {n_replaced} non-finite values");
+            }
+            scrubbed.push(Some(r));
+        }
+    }
+    let sources = replace_some(sources, &scrubbed);
+    let mut reweighted = vec![];
+    if args_cmd.fix_weights {
+        for (i, mesh) in sources.iter() {
+            if !mesh.attributes.contains_key(&VertexUsage::JointWeight) {
+                reweighted.push(None);
+                continue;
+            }
+            let r = normalize_joint_weights(mesh)
+                .with_context(|| format!("Cannot fix weights of mesh {i}"))?;
+            reweighted.push(Some(r));
+        }
+    }
+    let sources = replace_some(sources, &reweighted);
+    let mut split = vec![];
+    if args_cmd.split_components {
+        for (i, mesh) in sources.iter() {
+            let components = split_connected_components(mesh)
+                .with_context(|| format!("Cannot split mesh {i}"))?;
+            infoln!("Mesh {}: {} components", i, components.len());
+            split.extend(components.into_iter().map(|m| (*i, m)));
+        }
+    }
+    let sources: Vec<_> = if args_cmd.split_components {
+        split.iter().map(|(i, m)| (*i, m.as_mesh_ref())).collect()
+    } else {
+        sources
+    };
+    let mut transformed = vec![];
+    if let Some(matrix) = transform_matrix(args_cmd) {
+        if args_common.verbose {
+            eprintln!("Transform matrix:");
+            for row in 0..4 {
+                let row: Vec<_> =
+                    matrix.iter().map(|c| format!("{:10.4}", c[row])).collect();
+                eprintln!("  {}", row.join(" "));
+            }
+        }
+        for (i, mesh) in sources.iter() {
+            if !args_cmd.transform_mesh.is_empty()
+                && !args_cmd.transform_mesh.contains(i)
+            {
+                transformed.push(None);
+                continue;
+            }
+            let r = transform(mesh, matrix)
+                .with_context(|| format!("Cannot transform mesh {i}"))?;
+            transformed.push(Some(r));
+        }
+    }
+    let sources = replace_some(sources, &transformed);
+    let mut normalized = vec![];
+    if args_cmd.recenter.is_some() || args_cmd.unit_scale {
+        for (i, mesh) in sources.iter() {
+            let (r, applied) =
+                normalize_mesh(mesh, args_cmd.recenter, args_cmd.unit_scale)
+                    .with_context(|| format!("Cannot normalize mesh {i}"))?;
+            infoln!(
+                "Mesh {}: translated by {:?}, then scaled by {}",
+                i, applied.translation, applied.scale
+            );
+            normalized.push(Some(r));
+        }
+    }
+    let sources = replace_some(sources, &normalized);
+    let mut uv_transformed = vec![];
+    if args_cmd.flip_uv_v || args_cmd.uv_transform.is_some() {
+        let [sx, sy, ox, oy] =
+            args_cmd.uv_transform.unwrap_or([1.0, 1.0, 0.0, 0.0]);
+        for (i, mesh) in sources.iter() {
+            let mut r = None;
+            for &usage in VertexUsage::UVS {
+                let src =
+                    r.as_ref().map_or(mesh.clone(), MeshData::as_mesh_ref);
+                if !src.attributes.contains_key(&usage) {
+                    continue;
+                }
+                let t = transform_uvs(
+                    &src,
+                    usage,
+                    [sx, sy],
+                    [ox, oy],
+                    args_cmd.flip_uv_v,
+                )
+                .with_context(|| format!("Cannot transform UVs of mesh {i}"))?;
+                r = Some(t);
+            }
+            uv_transformed.push(r);
+        }
+    }
+    let sources = replace_some(sources, &uv_transformed);
+    let mut welded = vec![];
+    if let Some(epsilon) = args_cmd.weld {
+        for (i, mesh) in sources.iter() {
+            let r = weld(mesh, epsilon)
+                .with_context(|| format!("Cannot weld mesh {i}"))?;
+            infoln!(
+                "Mesh {}: {} -> {} vertices",
+                i,
+                mesh.n_vertices(),
+                r.n_vertices()
+            );
+            welded.push((*i, r));
+        }
+    }
+    let mut sources = if args_cmd.weld.is_some() {
+        welded.iter().map(|(i, m)| (*i, m.as_mesh_ref())).collect()
+    } else {
+        sources
+    };
+    let mut normals = vec![];
+    if let Some(mode) = args_cmd.gen_normals {
+        for (i, mesh) in sources.iter() {
+            normals.push(generate_normals(mesh, mode).with_context(|| {
+                format!("Cannot generate normals for mesh {i}")
+            })?);
+        }
+    }
+    for ((_, mesh), normals) in sources.iter_mut().zip(normals.iter()) {
+        mesh.attributes
+            .insert(VertexUsage::Normal, (VertexFormat::Float32x3, normals));
+    }
+    let mut inverted = vec![];
+    if args_cmd.invert_normals {
+        for (i, mesh) in sources.iter() {
+            match invert_normals(mesh) {
+                Ok(r) => inverted.push(Some(r)),
+                Err(e @ MeshError::NotSigned { .. }) => {
+                    eprintln!("Warning: Mesh {i}: {e}, not inverting normals");
+                    inverted.push(None);
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| {
+                        format!("Cannot invert normals of mesh {i}")
+                    });
+                }
+            }
+        }
+    }
+    let mut sources = replace_some(sources, &inverted);
+    let combined;
+    if args_cmd.concat {
+        let meshes: Vec<_> = sources.iter().map(|(_, m)| m.clone()).collect();
+        combined = concatenate(&meshes).context("Cannot concatenate meshes")?;
+        sources = vec![(0, combined.as_mesh_ref())];
+    }
+    #[cfg(feature = "meshopt")]
+    let optimized;
+    #[cfg(feature = "meshopt")]
+    if args_cmd.optimize {
+        optimized = optimize_meshes(args_common, &sources)?;
+        sources = optimized
+            .iter()
+            .map(|(i, m)| (*i, m.as_mesh_ref()))
+            .collect();
+    }
+    let downconverted;
+    if args_cmd.downconvert_indices {
+        downconverted = downconvert_meshes(&sources, args_cmd.strict)?;
+        if let Some(meshes) = &downconverted {
+            sources =
+                meshes.iter().map(|(i, m)| (*i, m.as_mesh_ref())).collect();
+        }
+    }
+    copy_instance_data(&mut writer, &with_data, &flatbufs)?;
+    let mut conversions =
+        vec![ConversionStats::default(); args_cmd.convert_attr.len()];
+    for (out_index, (i, mesh)) in sources.into_iter().enumerate() {
+        for (&(usage, to), stats) in
+            args_cmd.convert_attr.iter().zip(conversions.iter_mut())
+        {
+            if let Some(&(from, bytes)) = mesh.attributes.get(&usage) {
+                stats.add(from, to, bytes);
+            }
+        }
+        writer.add_mesh(mesh).context("Cannot use mesh for output")?;
+        if !args_cmd.concat {
+            copy_mesh_instances(&mut writer, &with_data, out_index, i)?;
         }
-        writer.add_mesh(m.clone()).context("Cannot use mesh for output")?;
     }
 
-    let outpath =
-        args_cmd.paths.out_file.as_ref().unwrap_or(&args_cmd.paths.in_file);
-    let outfile = if args_cmd.oarg.overwrite
-        || args_cmd.paths.out_file.is_none()
+    for (&(usage, to), stats) in
+        args_cmd.convert_attr.iter().zip(conversions.iter())
     {
-        std::fs::File::create(outpath).context("Could not open output file")?
-    } else {
-        std::fs::File::create_new(outpath)
-            .context("Could not open output file")?
+        stats.report(args_common, usage, to);
+    }
+
+    if args_cmd.dry_run {
+        let plan = writer.plan().context("Cannot use meshes for output")?;
+        print_write_plan(&plan);
+        return Ok(());
+    }
+
+    let outpath = args_cmd.paths.output()?;
+    write_output_file(
+        writer,
+        outpath,
+        args_cmd.oarg.overwrite || args_cmd.paths.out_file.is_none(),
+    )
+}
+
+/// Insert an attribute loaded for --add-attr into the meshes it is for.
+fn add_attribute<'a>(
+    sources: &mut [(usize, MeshDataRef<'a>)],
+    descriptor: &IyesMeshDescriptor,
+    add: &AddAttr,
+    bytes: &'a [u8],
+    overwrite: bool,
+) -> AnyResult<()> {
+    let targets: Vec<_> = match add.mesh {
+        Some(i) => vec![i],
+        None => (0..descriptor.meshes.len()).collect(),
     };
-    let mut bufout = BufWriter::new(outfile);
-    writer.write_to(&mut bufout).context("Cannot encode output file")?;
+    let n_vertices: usize = targets
+        .iter()
+        .map(|&i| descriptor.meshes[i].vertex_count as usize)
+        .sum();
+    let expected = n_vertices * add.format.size();
+    if bytes.len() != expected {
+        bail!(
+            "{} has {} bytes, expected {} ({} vertices of {} bytes)",
+            add.path.display(),
+            bytes.len(),
+            expected,
+            n_vertices,
+            add.format.size()
+        );
+    }
+    let mut offset = 0;
+    for i in targets {
+        let len =
+            descriptor.meshes[i].vertex_count as usize * add.format.size();
+        let data = &bytes[offset..offset + len];
+        offset += len;
+        // The mesh may have been deleted.
+        let Some((_, mesh)) = sources.iter_mut().find(|(j, _)| *j == i) else {
+            continue;
+        };
+        if !overwrite && mesh.attributes.contains_key(&add.usage) {
+            bail!(
+                "Mesh {} already has attribute {} (see --overwrite-attr)",
+                i,
+                add.usage
+            );
+        }
+        mesh.attributes.insert(add.usage, (add.format, data));
+    }
     Ok(())
 }
+
+/// Convert the indices of all meshes to U16, if they all fit.
+///
+/// Returns `None` (or an error, if `strict`) if some index does not fit.
+fn downconvert_meshes(
+    sources: &[(usize, MeshDataRef<'_>)],
+    strict: bool,
+) -> AnyResult<Option<Vec<(usize, MeshData)>>> {
+    let mut r = vec![];
+    for (i, mesh) in sources.iter() {
+        let converted = match downconvert_indices(mesh) {
+            Ok(converted) => converted,
+            Err(MeshError::NotIndexed) => MeshData::from(mesh),
+            Err(MeshError::IndexTooLarge { max_index }) if strict => {
+                bail!(
+                    "Cannot downconvert indices: mesh {} has index {}",
+                    i,
+                    max_index
+                );
+            }
+            Err(MeshError::IndexTooLarge { max_index }) => {
+                infoln!(
+                    "Mesh {}: index {} does not fit in 16 bits, not \
+                     downconverting indices",
+                    i, max_index
+                );
+                return Ok(None);
+            }
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("Cannot downconvert indices of mesh {i}")
+                });
+            }
+        };
+        r.push((*i, converted));
+    }
+    Ok(Some(r))
+}
+
+/// What --convert-attr did to an attribute, over all meshes.
+#[derive(Clone, Default)]
+struct ConversionStats {
+    from: Option<VertexFormat>,
+    n_clamped: usize,
+    size_before: usize,
+    size_after: usize,
+}
+
+impl ConversionStats {
+    fn add(
+        &mut self,
+        from: VertexFormat,
+        to: VertexFormat,
+        bytes: &[u8],
+    ) {
+        self.from = Some(from);
+        self.n_clamped += count_clamped(from, to, bytes);
+        self.size_before += bytes.len();
+        self.size_after += bytes.len() / from.size() * to.size();
+    }
+
+    fn report(
+        &self,
+        args_common: &CommonArgs,
+        usage: VertexUsage,
+        to: VertexFormat,
+    ) {
+        let Some(from) = self.from else {
+            return;
+        };
+        if self.n_clamped > 0
+            && let Some((min, max)) = value_range(to)
+        {
+            eprintln!(
+                "Warning: {}: {} values are outside of {}..={}, and were \
+                 clamped (converting to {:?})",
+                usage, self.n_clamped, min, max, to
+            );
+        }
+        if args_common.verbose {
+            eprintln!(
+                "{}: {:?} -> {:?}, {} -> {} bytes ({:+} bytes)",
+                usage,
+                from,
+                to,
+                self.size_before,
+                self.size_after,
+                self.size_after as i64 - self.size_before as i64
+            );
+        }
+    }
+}
+
+/// Replace the meshes that have a `Some` in `replacements`.
+///
+/// `replacements` is either empty or has one entry per mesh.
+fn replace_some<'a>(
+    sources: Vec<(usize, MeshDataRef<'a>)>,
+    replacements: &'a [Option<MeshData>],
+) -> Vec<(usize, MeshDataRef<'a>)> {
+    if replacements.is_empty() {
+        return sources;
+    }
+    sources
+        .into_iter()
+        .zip(replacements.iter())
+        .map(|((i, m), r)| (i, r.as_ref().map_or(m, MeshData::as_mesh_ref)))
+        .collect()
+}
+
+/// Recenter and/or scale a mesh.
+fn normalize_mesh(
+    mesh: &MeshDataRef<'_>,
+    center: Option<CenterMode>,
+    unit_scale: bool,
+) -> Result<(MeshData, AppliedTransform), MeshError> {
+    let mut applied = AppliedTransform::default();
+    let mut r = MeshData::from(mesh);
+    if let Some(mode) = center {
+        (r, applied) = recenter(mesh, mode)?;
+    }
+    if unit_scale {
+        (r, applied.scale) = normalize_scale(&r.as_mesh_ref())?;
+    }
+    Ok((r, applied))
+}
+
+/// The combined transform from the arguments, if any.
+fn transform_matrix(args_cmd: &EditArgs) -> Option<[[f32; 4]; 4]> {
+    if args_cmd.scale.is_none()
+        && args_cmd.rotate_deg.is_none()
+        && args_cmd.translate.is_none()
+        && args_cmd.matrix.is_none()
+    {
+        return None;
+    }
+    let m = compose_transform(
+        args_cmd.scale.unwrap_or([1.0; 3]),
+        args_cmd.rotate_deg.unwrap_or_default(),
+        args_cmd.translate.unwrap_or_default(),
+    );
+    Some(match args_cmd.matrix {
+        Some(matrix) => mul_matrix(matrix, m),
+        None => m,
+    })
+}
+
+#[cfg(feature = "meshopt")]
+fn optimize_meshes(
+    args_common: &CommonArgs,
+    meshes: &[(usize, MeshDataRef<'_>)],
+) -> AnyResult<Vec<(usize, MeshData)>> {
+    use iyes_mesh::meshopt::{OptimizePasses, acmr, optimize_mesh};
+
+    let mut r = vec![];
+    for (i, mesh) in meshes.iter() {
+        let optimized = optimize_mesh(mesh, OptimizePasses::default())
+            .with_context(|| format!("Cannot optimize mesh {i}"))?;
+        if args_common.verbose
+            && let (Some(before), Some(after)) =
+                (acmr(mesh), acmr(&optimized.as_mesh_ref()))
+        {
+            eprintln!("Mesh {i}: ACMR {before:.3} -> {after:.3}");
+        }
+        r.push((*i, optimized));
+    }
+    Ok(r)
+}
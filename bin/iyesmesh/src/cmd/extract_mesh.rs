@@ -0,0 +1,74 @@
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{
+    add_single_mesh, copy_color_spaces, copy_user_data,
+    decode_special_attributes, keep_encodings, open_input, write_output_file,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct ExtractMeshArgs {
+    /// Index of the mesh to extract
+    #[arg(short, long, value_parser = crate::util::parse_mesh_index)]
+    mesh: usize,
+    /// Copy the user data of the input file (still encrypted, if it is)
+    #[arg(long)]
+    keep_user_data: bool,
+    #[command(flatten)]
+    rarg: crate::ReadArgs,
+    #[command(flatten)]
+    warg: crate::WriteArgs,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    inpath: crate::InputPath,
+    #[command(flatten)]
+    outpath: crate::OutputPath,
+}
+
+pub fn run(
+    _args_common: &CommonArgs,
+    args_cmd: &ExtractMeshArgs,
+) -> AnyResult<()> {
+    let mut infile = open_input(&args_cmd.inpath.in_file)
+        .context("Could not open input file")?;
+    let reader = IyesMeshReader::init_with_settings(
+        IyesMeshReaderSettings::from(&args_cmd.rarg),
+        &mut infile,
+    )
+    .context("Cannot decode file metadata and initialize decoding")?;
+    let n_meshes = reader.descriptor().meshes.len();
+    let index = args_cmd.mesh;
+    if index >= n_meshes {
+        bail!(
+            "Mesh {} does not exist (the file has {} meshes)",
+            index,
+            n_meshes
+        );
+    }
+    let with_data =
+        reader.read_all_data().context("Cannot decode file data")?;
+    let flatbufs =
+        with_data.into_flat_buffers().context("Cannot decode file buffers")?;
+    let meshes = with_data
+        .into_split_meshes(&flatbufs)
+        .context("Cannot decode file meshes")?;
+    let decoded = decode_special_attributes(&with_data, &meshes);
+
+    let mut settings = IyesMeshWriterSettings::from(&args_cmd.warg);
+    keep_encodings(&mut settings, with_data.descriptor());
+    let mut writer = IyesMeshWriter::new_with_settings(settings);
+    copy_color_spaces(&mut writer, &with_data);
+    if args_cmd.keep_user_data {
+        copy_user_data(&mut writer, &with_data, &flatbufs);
+    }
+    add_single_mesh(&mut writer, &with_data, &meshes, &decoded, index)?;
+
+    write_output_file(
+        writer,
+        &args_cmd.outpath.out_file,
+        args_cmd.oarg.overwrite,
+    )
+}
@@ -5,9 +5,26 @@ use iyes_mesh::read::IyesMeshReaderSettings;
 
 use crate::CommonArgs;
 use crate::prelude::*;
+use crate::util::{is_stdout, open_input};
 
 #[derive(clap::Args, Debug)]
 pub struct ExtractUserDataArgs {
+    /// Do not verify the user data (or data) checksum
+    ///
+    /// Unlike --ignore-checksums, the file metadata is still verified.
+    #[arg(long)]
+    no_verify: bool,
+    /// Start at this byte of the user data
+    #[arg(long, default_value_t = 0)]
+    offset: usize,
+    /// Only extract this many bytes (default: up to the end)
+    #[arg(long)]
+    length: Option<usize>,
+    #[command(flatten)]
+    mode: OutputMode,
+    #[cfg(feature = "encryption")]
+    #[command(flatten)]
+    key: crate::UserDataKeyArgs,
     #[command(flatten)]
     rarg: crate::ReadArgs,
     #[command(flatten)]
@@ -18,20 +35,75 @@ pub struct ExtractUserDataArgs {
     outpath: crate::OptOutputPath,
 }
 
+/// How to write the user data (raw bytes by default).
+#[derive(clap::Args, Debug)]
+#[group(multiple = false)]
+struct OutputMode {
+    /// Write a hex dump, with offsets and printable characters
+    #[arg(long)]
+    hex: bool,
+    /// Write the user data encoded as base64
+    #[arg(long)]
+    base64: bool,
+    /// Write the user data as text, escaping invalid UTF-8 and control
+    /// characters
+    #[arg(long)]
+    string: bool,
+}
+
 pub fn run(
     _args_common: &CommonArgs,
     args_cmd: &ExtractUserDataArgs,
 ) -> AnyResult<()> {
-    let mut infile = std::fs::File::open(&args_cmd.inpath.in_file)
+    let mut infile = open_input(&args_cmd.inpath.in_file)
         .context("Could not open input file")?;
-    let reader = IyesMeshReader::init_with_settings(
-        IyesMeshReaderSettings::from(&args_cmd.rarg),
-        &mut infile,
-    )
+    let mut settings = IyesMeshReaderSettings {
+        #[cfg(feature = "encryption")]
+        user_data_key: args_cmd.key.load()?,
+        ..IyesMeshReaderSettings::from(&args_cmd.rarg)
+    };
+    if args_cmd.no_verify {
+        settings.verify_data_checksum = false;
+        settings.verify_user_data_checksum = false;
+    }
+    let reader = IyesMeshReader::init_with_settings(settings, &mut infile)
     .context("Cannot decode file metadata and initialize decoding")?;
     let userdata = reader.read_user_data()
         .context("Cannot decode user data")?;
-    if let Some(outpath) = &args_cmd.outpath.out_file {
+    if args_cmd.offset > userdata.len() {
+        bail!(
+            "Offset {} is past the end of the user data ({} bytes)",
+            args_cmd.offset,
+            userdata.len()
+        );
+    }
+    let end = match args_cmd.length {
+        Some(length) => args_cmd.offset.saturating_add(length),
+        None => userdata.len(),
+    };
+    if end > userdata.len() {
+        bail!(
+            "Bytes {}..{} are out of the user data ({} bytes)",
+            args_cmd.offset,
+            end,
+            userdata.len()
+        );
+    }
+    let userdata = &userdata[args_cmd.offset..end];
+    let mode = &args_cmd.mode;
+    let rendered = if mode.hex {
+        Some(hex_dump(userdata, args_cmd.offset))
+    } else if mode.base64 {
+        Some(base64(userdata) + "\n")
+    } else if mode.string {
+        Some(escape_string(userdata))
+    } else {
+        None
+    };
+    let userdata = rendered.as_ref().map_or(userdata, |r| r.as_bytes());
+    if let Some(outpath) =
+        args_cmd.outpath.out_file.as_deref().filter(|p| !is_stdout(p))
+    {
         let mut outfile = if args_cmd.oarg.overwrite {
             std::fs::File::create(outpath)
                 .context("Could not open output file")?
@@ -39,15 +111,82 @@ pub fn run(
             std::fs::File::create_new(outpath)
                 .context("Could not open output file")?
         };
-        outfile.write_all(&userdata)
+        outfile.write_all(userdata)
             .and_then(|_| outfile.flush())
             .and_then(|_| outfile.sync_all())
             .context("Could not write output")?;
     } else {
         let mut stdout = std::io::stdout().lock();
-        stdout.write_all(&userdata)
+        stdout.write_all(userdata)
             .and_then(|_| stdout.flush())
             .context("Could not write output")?;
     }
     Ok(())
 }
+
+/// Format bytes like `hexdump -C`, with offsets starting at `offset`.
+fn hex_dump(
+    bytes: &[u8],
+    offset: usize,
+) -> String {
+    let mut r = String::new();
+    for (i, line) in bytes.chunks(16).enumerate() {
+        r += &format!("{:08x}  ", offset + i * 16);
+        for j in 0..16 {
+            match line.get(j) {
+                Some(b) => r += &format!("{:02x} ", b),
+                None => r += "   ",
+            }
+            if j == 7 {
+                r.push(' ');
+            }
+        }
+        r += " |";
+        for &b in line {
+            r.push(if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            });
+        }
+        r += "|\n";
+    }
+    r += &format!("{:08x}\n", offset + bytes.len());
+    r
+}
+
+/// Encode bytes as standard base64, with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut r = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let byte = |i: usize| chunk.get(i).copied().unwrap_or(0);
+        let n = u32::from_be_bytes([0, byte(0), byte(1), byte(2)]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                r.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                r.push('=');
+            }
+        }
+    }
+    r
+}
+
+/// Decode bytes as UTF-8, replacing invalid sequences with U+FFFD and
+/// escaping control characters other than newlines.
+fn escape_string(bytes: &[u8]) -> String {
+    let mut r = String::new();
+    for c in String::from_utf8_lossy(bytes).chars() {
+        if c.is_control() && c != '\n' {
+            r.extend(c.escape_default());
+        } else {
+            r.push(c);
+        }
+    }
+    if !r.is_empty() && !r.ends_with('\n') {
+        r.push('\n');
+    }
+    r
+}
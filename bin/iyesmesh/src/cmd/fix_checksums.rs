@@ -0,0 +1,130 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use iyes_mesh::checksum::{ChecksumKind, ChecksummingReader, checksum_metadata};
+use iyes_mesh::header::IyesMeshHeader;
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{compressed_size, is_stdout, open_input};
+
+#[derive(clap::Args, Debug)]
+pub struct FixChecksumsArgs {
+    /// Remove the data checksum instead of recomputing it
+    ///
+    /// The metadata checksum is still recomputed.
+    #[arg(long)]
+    strip: bool,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    paths: crate::InOutPaths,
+}
+
+pub fn run(
+    _args_common: &CommonArgs,
+    args_cmd: &FixChecksumsArgs,
+) -> AnyResult<()> {
+    let mut file = match &args_cmd.paths.out_file {
+        Some(out_file) => {
+            if is_stdout(out_file) {
+                bail!(
+                    "Cannot write to stdout (`-`), the checksums are fixed \
+                     in the output file after copying it."
+                );
+            }
+            let mut infile = open_input(&args_cmd.paths.in_file)
+                .context("Could not open input file")?;
+            let mut options = std::fs::File::options();
+            options.read(true).write(true);
+            if args_cmd.oarg.overwrite {
+                options.create(true).truncate(true);
+            } else {
+                options.create_new(true);
+            }
+            let mut outfile =
+                options.open(out_file).context("Could not open output file")?;
+            std::io::copy(&mut infile, &mut outfile)
+                .context("Could not copy input file")?;
+            outfile
+        }
+        None => std::fs::File::options()
+            .read(true)
+            .write(true)
+            .open(args_cmd.paths.output()?)
+            .context("Could not open input file")?,
+    };
+    file.rewind()?;
+    let file_size = file
+        .metadata()
+        .context("Could not get size of file")?
+        .len();
+
+    let settings = IyesMeshReaderSettings {
+        verify_metadata_checksum: false,
+        verify_data_checksum: false,
+        verify_user_data_checksum: false,
+        ..Default::default()
+    };
+    let reader = IyesMeshReader::init_with_settings(settings, &mut file)
+        .context("Cannot decode file metadata")?;
+    let old_header = *reader.header();
+    let descriptor = reader.descriptor().clone();
+    let mut header = old_header;
+
+    if args_cmd.strip {
+        header.data_checksum = 0;
+    } else {
+        let kind = ChecksumKind::from_id(descriptor.checksum_kind)
+            .with_context(|| {
+                format!(
+                    "Checksum algorithm {} is not supported by this build",
+                    descriptor.checksum_kind
+                )
+            })?;
+        let len = compressed_size(&header, &descriptor, file_size);
+        file.seek(SeekFrom::Start(
+            (IyesMeshHeader::encoded_len() + header.descriptor_len as usize)
+                as u64,
+        ))?;
+        let mut reader = ChecksummingReader::with_kind(&mut file, kind, len);
+        let n = std::io::copy(&mut reader, &mut std::io::sink())
+            .context("Could not read compressed data")?;
+        if n < len {
+            bail!("File is truncated ({} of {} bytes of data)", n, len);
+        }
+        header.data_checksum = reader.finish();
+    }
+
+    let mut bytes_descriptor = vec![0; header.descriptor_len as usize];
+    file.seek(SeekFrom::Start(IyesMeshHeader::encoded_len() as u64))?;
+    file.read_exact(&mut bytes_descriptor)
+        .context("Could not read file metadata")?;
+    header.metadata_checksum = checksum_metadata(header, &bytes_descriptor);
+
+    println!(
+        "Metadata checksum: {:016x} -> {:016x}",
+        { old_header.metadata_checksum },
+        { header.metadata_checksum }
+    );
+    println!(
+        "Data checksum: {:016x} -> {:016x}",
+        { old_header.data_checksum },
+        { header.data_checksum }
+    );
+    if header == old_header {
+        println!("Checksums are already correct.");
+        return Ok(());
+    }
+    file.rewind()?;
+    file.write_all(header.as_bytes())
+        .and_then(|_| file.sync_all())
+        .context("Could not write file header")?;
+    if descriptor.signed {
+        eprintln!(
+            "Warning! The file is signed, and its signature is now invalid \
+             (re-sign it with `edit --sign`)."
+        );
+    }
+    Ok(())
+}
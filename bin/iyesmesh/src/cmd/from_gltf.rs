@@ -0,0 +1,630 @@
+use gltf::accessor::{DataType, Dimensions, Item, Iter};
+use gltf::mesh::util::{
+    ReadColors, ReadIndices, ReadJoints, ReadTexCoords, ReadWeights,
+};
+use gltf::mesh::{Mode, Reader, Semantic};
+use iyes_mesh::HashSet;
+use iyes_mesh::descriptor::{VertexFormat, VertexUsage};
+use iyes_mesh::mesh::{MeshData, transform};
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{
+    mul_matrix, print_write_plan, read_input, write_output_file,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct FromGltfArgs {
+    /// Import the scene with this index (default: the default scene of the
+    /// file, or the first one)
+    #[arg(long)]
+    scene: Option<usize>,
+    /// Only import the meshes of these nodes and their children (can be
+    /// repeated)
+    #[arg(long)]
+    node: Vec<usize>,
+    /// Bake the transforms of the nodes into the vertices
+    ///
+    /// Without this, node transforms are ignored and each glTF mesh is
+    /// imported once, even if several nodes use it. With this, there is an
+    /// IMA mesh per node that uses a glTF mesh.
+    #[arg(long)]
+    bake_transforms: bool,
+    /// Store the `extras` of the glTF file (JSON) as the user data
+    #[arg(long)]
+    extras_user_data: bool,
+    /// Print info about the output file, without writing anything
+    #[arg(long)]
+    dry_run: bool,
+    #[command(flatten)]
+    warg: crate::WriteArgs,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    inpath: crate::InputPath,
+    #[command(flatten)]
+    outpath: crate::OutputPath,
+}
+
+pub fn run(
+    args_common: &CommonArgs,
+    args_cmd: &FromGltfArgs,
+) -> AnyResult<()> {
+    let path = &args_cmd.inpath.in_file;
+    let (document, buffers) = load(path).context("Cannot load glTF file")?;
+
+    // Each IMA mesh is a primitive of a glTF mesh, used by a node.
+    let mut sources = vec![];
+    if document.scenes().len() == 0 && args_cmd.node.is_empty() {
+        // Files without scenes are just a library of meshes.
+        for mesh in document.meshes() {
+            sources.push((None, mesh, IDENTITY));
+        }
+    } else {
+        let roots = if args_cmd.node.is_empty() {
+            let index = args_cmd
+                .scene
+                .or(document.default_scene().map(|s| s.index()))
+                .unwrap_or(0);
+            let Some(scene) = document.scenes().nth(index) else {
+                bail!(
+                    "Scene {} does not exist (the file has {} scenes)",
+                    index,
+                    document.scenes().len()
+                );
+            };
+            scene.nodes().map(|n| n.index()).collect()
+        } else {
+            args_cmd.node.clone()
+        };
+        let mut visited = HashSet::default();
+        for root in roots {
+            let parent = parent_transform(&document, root)?;
+            let node = document.nodes().nth(root).unwrap();
+            collect_meshes(node, parent, &mut visited, &mut sources)?;
+        }
+    }
+    if !args_cmd.bake_transforms {
+        let mut seen = HashSet::default();
+        sources.retain(|(_, mesh, _)| seen.insert(mesh.index()));
+    }
+
+    let mut names = vec![];
+    let mut new_meshes = vec![];
+    let mut skipped = HashSet::default();
+    for (node, mesh, matrix) in sources.iter() {
+        for primitive in mesh.primitives() {
+            let name = format!(
+                "{}/{}/{}",
+                node.as_ref().map_or("-".to_owned(), node_label),
+                mesh_label(mesh),
+                primitive.index()
+            );
+            let mut data = primitive_data(&primitive, &buffers, &mut skipped)
+                .with_context(|| {
+                format!("Cannot import primitive {}", name)
+            })?;
+            if args_cmd.bake_transforms && *matrix != IDENTITY {
+                data = transform(&data.as_mesh_ref(), *matrix).with_context(
+                    || format!("Cannot transform primitive {}", name),
+                )?;
+            }
+            names.push(name);
+            new_meshes.push(data);
+        }
+    }
+    if !skipped.is_empty() {
+        let mut skipped: Vec<_> = skipped.into_iter().collect();
+        skipped.sort();
+        eprintln!(
+            "Warning: these attributes have no IMA equivalent, they are \
+             skipped: {}",
+            skipped.join(", ")
+        );
+    }
+    if new_meshes.is_empty() {
+        bail!("The glTF file has no meshes to import.");
+    }
+
+    let mut writer = IyesMeshWriter::new_with_settings(
+        IyesMeshWriterSettings::from(&args_cmd.warg),
+    );
+    let extras;
+    if args_cmd.extras_user_data {
+        let Some(value) = &document.as_json().extras else {
+            bail!("The glTF file has no `extras` to use as user data");
+        };
+        let value: serde_json::Value = serde_json::from_str(value.get())?;
+        extras = serde_json::to_vec(&value)?;
+        writer.set_user_data(&extras);
+    }
+    if let Err(report) =
+        writer.add_meshes(new_meshes.iter().map(MeshData::as_mesh_ref))
+    {
+        for rejected in report.rejected.iter() {
+            eprintln!("Primitive {} is incompatible:", names[rejected.index]);
+            for problem in rejected.problems.iter() {
+                eprintln!("  - {}", problem);
+            }
+        }
+        bail!(
+            "{} primitives cannot be used for output (all meshes in a file \
+             must have the same attributes).",
+            report.rejected.len()
+        );
+    }
+    if args_common.verbose {
+        for (i, name) in names.iter().enumerate() {
+            eprintln!("Mesh {}: {}", i, name);
+        }
+    }
+
+    if args_cmd.dry_run {
+        let plan = writer.plan().context("Cannot use meshes for output")?;
+        print_write_plan(&plan);
+        return Ok(());
+    }
+    write_output_file(
+        writer,
+        &args_cmd.outpath.out_file,
+        args_cmd.oarg.overwrite,
+    )
+}
+
+const IDENTITY: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// A glTF mesh, the node that uses it (if any) and the transform of the
+/// node.
+type MeshSource<'a> = (Option<gltf::Node<'a>>, gltf::Mesh<'a>, [[f32; 4]; 4]);
+
+/// Load a `.gltf` or `.glb` file, and the contents of its buffers.
+///
+/// The buffers are loaded here instead of with `gltf::import`, which also
+/// decodes all the images of the file.
+fn load(path: &Path) -> AnyResult<(gltf::Document, Vec<Vec<u8>>)> {
+    let bytes = read_input(path).context("Could not read input file")?;
+    let gltf::Gltf {
+        document,
+        mut blob,
+    } = gltf::Gltf::from_slice(&bytes)?;
+    let mut buffers = vec![];
+    for buffer in document.buffers() {
+        let i = buffer.index();
+        let data = match buffer.source() {
+            gltf::buffer::Source::Bin => blob.take().with_context(|| {
+                format!("Buffer {} has no URI, and there is no GLB data", i)
+            })?,
+            gltf::buffer::Source::Uri(uri) if uri.starts_with("data:") => {
+                let Some((_, data)) = uri.split_once(";base64,") else {
+                    bail!("Buffer {}: data URIs must be base64-encoded", i);
+                };
+                decode_base64(data)
+                    .with_context(|| format!("Buffer {}: bad base64", i))?
+            }
+            gltf::buffer::Source::Uri(uri) => {
+                let path = path.parent().unwrap_or(Path::new(".")).join(uri);
+                std::fs::read(&path).with_context(|| {
+                    format!("Could not read buffer file {}", path.display())
+                })?
+            }
+        };
+        if data.len() < buffer.length() {
+            bail!(
+                "Buffer {} has {} bytes, expected {}",
+                i,
+                data.len(),
+                buffer.length()
+            );
+        }
+        buffers.push(data);
+    }
+    Ok((document, buffers))
+}
+
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+    let s = s.trim_end_matches('=').as_bytes();
+    let mut r = Vec::with_capacity(s.len() * 3 / 4);
+    for chunk in s.chunks(4) {
+        let mut bits = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            bits |= (value(*c)? as u32) << (18 - 6 * i);
+        }
+        let n_bytes = match chunk.len() {
+            4 => 3,
+            3 => 2,
+            2 => 1,
+            _ => return None,
+        };
+        r.extend_from_slice(&bits.to_be_bytes()[1..1 + n_bytes]);
+    }
+    Some(r)
+}
+
+/// The IMA attribute of a glTF attribute (`_CUSTOM_3` is used for custom
+/// attribute 3).
+fn vertex_usage(semantic: &Semantic) -> Option<VertexUsage> {
+    let usage = match semantic {
+        Semantic::Positions => VertexUsage::Position,
+        Semantic::Normals => VertexUsage::Normal,
+        Semantic::Tangents => VertexUsage::Tangent,
+        Semantic::TexCoords(set) => VertexUsage::uv(*set as usize)?,
+        Semantic::Colors(0) => VertexUsage::Color,
+        Semantic::Joints(0) => VertexUsage::JointIndex,
+        Semantic::Joints(1) => VertexUsage::JointIndex1,
+        Semantic::Weights(0) => VertexUsage::JointWeight,
+        Semantic::Weights(1) => VertexUsage::JointWeight1,
+        Semantic::Extras(name) => {
+            VertexUsage::Custom(name.strip_prefix("CUSTOM_")?.parse().ok()?)
+        }
+        _ => return None,
+    };
+    Some(usage)
+}
+
+/// Check that an accessor has a type that the glTF spec allows for the
+/// attribute (the reader cannot read the others).
+fn check_accessor(
+    semantic: &Semantic,
+    accessor: &gltf::Accessor<'_>,
+) -> AnyResult<()> {
+    use DataType::{F32, U8, U16};
+    use Dimensions::{Vec2, Vec3, Vec4};
+    let (types, dimensions): (&[DataType], &[Dimensions]) = match semantic {
+        Semantic::Positions | Semantic::Normals => (&[F32], &[Vec3]),
+        Semantic::Tangents => (&[F32], &[Vec4]),
+        Semantic::TexCoords(_) => (&[U8, U16, F32], &[Vec2]),
+        Semantic::Colors(_) => (&[U8, U16, F32], &[Vec3, Vec4]),
+        Semantic::Joints(_) => (&[U8, U16], &[Vec4]),
+        Semantic::Weights(_) => (&[U8, U16, F32], &[Vec4]),
+        Semantic::Extras(_) => return Ok(()),
+    };
+    if !types.contains(&accessor.data_type())
+        || !dimensions.contains(&accessor.dimensions())
+    {
+        bail!(
+            "The accessor is {:?} of {:?}, which is not allowed here",
+            accessor.dimensions(),
+            accessor.data_type()
+        );
+    }
+    Ok(())
+}
+
+/// The closest vertex format to a glTF accessor type.
+///
+/// There are no 3-component formats with 8 or 16 bit components, so those
+/// use the 4-component formats (see [`custom_data`]).
+fn vertex_format(
+    data_type: DataType,
+    normalized: bool,
+    n_components: usize,
+) -> Option<VertexFormat> {
+    use VertexFormat::*;
+    let formats = match (data_type, normalized) {
+        (DataType::F32, _) => [Float32, Float32x2, Float32x3, Float32x4],
+        (DataType::U32, false) => [Uint32, Uint32x2, Uint32x3, Uint32x4],
+        (DataType::U16, true) => [Unorm16, Unorm16x2, Unorm16x4, Unorm16x4],
+        (DataType::U16, false) => [Uint16, Uint16x2, Uint16x4, Uint16x4],
+        (DataType::I16, true) => [Snorm16, Snorm16x2, Snorm16x4, Snorm16x4],
+        (DataType::I16, false) => [Sint16, Sint16x2, Sint16x4, Sint16x4],
+        (DataType::U8, true) => [Unorm8, Unorm8x2, Unorm8x4, Unorm8x4],
+        (DataType::U8, false) => [Uint8, Uint8x2, Uint8x4, Uint8x4],
+        (DataType::I8, true) => [Snorm8, Snorm8x2, Snorm8x4, Snorm8x4],
+        (DataType::I8, false) => [Sint8, Sint8x2, Sint8x4, Sint8x4],
+        _ => return None,
+    };
+    formats.get(n_components.checked_sub(1)?).copied()
+}
+
+fn node_label(node: &gltf::Node<'_>) -> String {
+    node.name().map_or_else(|| format!("node{}", node.index()), str::to_owned)
+}
+
+fn mesh_label(mesh: &gltf::Mesh<'_>) -> String {
+    mesh.name().map_or_else(|| format!("mesh{}", mesh.index()), str::to_owned)
+}
+
+/// The transform of the parents of a node, up to the root.
+fn parent_transform(
+    document: &gltf::Document,
+    node: usize,
+) -> AnyResult<[[f32; 4]; 4]> {
+    let n_nodes = document.nodes().len();
+    if node >= n_nodes {
+        bail!("Node {} does not exist (the file has {} nodes)", node, n_nodes);
+    }
+    let mut r = IDENTITY;
+    let mut current = node;
+    for _ in 0..n_nodes {
+        let parent = document
+            .nodes()
+            .find(|n| n.children().any(|c| c.index() == current));
+        let Some(parent) = parent else {
+            return Ok(r);
+        };
+        r = mul_matrix(parent.transform().matrix(), r);
+        current = parent.index();
+    }
+    bail!("The parents of node {} form a cycle", node);
+}
+
+/// Find the meshes used by a node and its children, with their
+/// transforms.
+fn collect_meshes<'a>(
+    node: gltf::Node<'a>,
+    parent: [[f32; 4]; 4],
+    visited: &mut HashSet<usize>,
+    out: &mut Vec<MeshSource<'a>>,
+) -> AnyResult<()> {
+    if !visited.insert(node.index()) {
+        bail!("Node {} is used more than once", node.index());
+    }
+    let matrix = mul_matrix(parent, node.transform().matrix());
+    if let Some(mesh) = node.mesh() {
+        out.push((Some(node.clone()), mesh, matrix));
+    }
+    for child in node.children() {
+        collect_meshes(child, matrix, visited, out)?;
+    }
+    Ok(())
+}
+
+/// The data of a primitive, as an IMA mesh.
+///
+/// Attributes without an IMA equivalent are added to `skipped`.
+fn primitive_data(
+    primitive: &gltf::Primitive<'_>,
+    buffers: &[Vec<u8>],
+    skipped: &mut HashSet<String>,
+) -> AnyResult<MeshData> {
+    if primitive.mode() != Mode::Triangles {
+        bail!(
+            "Only triangle lists are supported (mode is {:?})",
+            primitive.mode()
+        );
+    }
+    let get_buffer = |buffer: gltf::Buffer<'_>| {
+        buffers.get(buffer.index()).map(Vec::as_slice)
+    };
+    let reader = primitive.reader(get_buffer);
+    let mut r = MeshData::new();
+    for (semantic, accessor) in primitive.attributes() {
+        let name = semantic.to_string();
+        let Some(usage) = vertex_usage(&semantic) else {
+            skipped.insert(name);
+            continue;
+        };
+        let (format, data) =
+            vertex_data(&reader, get_buffer, &semantic, &accessor)
+                .with_context(|| format!("Cannot read attribute {}", name))?;
+        r.set_attribute_bytes(usage, format, &data)?;
+    }
+    if let Some(accessor) = primitive.indices() {
+        let (data_type, dimensions) =
+            (accessor.data_type(), accessor.dimensions());
+        if dimensions != Dimensions::Scalar
+            || !matches!(
+                data_type,
+                DataType::U8 | DataType::U16 | DataType::U32
+            )
+        {
+            bail!("Indices cannot be {:?} of {:?}", dimensions, data_type);
+        }
+        let indices =
+            (accessor.count() > 0).then(|| reader.read_indices()).flatten();
+        match indices {
+            Some(ReadIndices::U8(iter)) => {
+                r.set_indices_u16(&iter.map(u16::from).collect::<Vec<_>>());
+            }
+            Some(ReadIndices::U16(iter)) => {
+                r.set_indices_u16(&iter.collect::<Vec<_>>());
+            }
+            Some(ReadIndices::U32(iter)) => {
+                r.set_indices_u32(&iter.collect::<Vec<_>>());
+            }
+            None if accessor.count() == 0 => {
+                r.set_indices_u16(&[]);
+            }
+            None => bail!("The indices are out of range of their buffer"),
+        }
+    }
+    Ok(r)
+}
+
+/// The data of an attribute, in the closest vertex format.
+///
+/// 3-component colors with 8 or 16 bit components get an opaque alpha.
+fn vertex_data<'a, 's, F>(
+    reader: &Reader<'a, 's, F>,
+    get_buffer: F,
+    semantic: &Semantic,
+    accessor: &gltf::Accessor<'a>,
+) -> AnyResult<(VertexFormat, Vec<u8>)>
+where
+    F: Clone + Fn(gltf::Buffer<'a>) -> Option<&'s [u8]>,
+{
+    use VertexFormat::*;
+    check_accessor(semantic, accessor)?;
+    if accessor.count() == 0 {
+        let format = vertex_format(
+            accessor.data_type(),
+            accessor.normalized(),
+            accessor.dimensions().multiplicity(),
+        );
+        return Ok((
+            format.context("No vertex format for the accessor")?,
+            vec![],
+        ));
+    }
+    let r = match semantic {
+        Semantic::Positions => {
+            reader.read_positions().map(|i| (Float32x3, to_bytes(i)))
+        }
+        Semantic::Normals => {
+            reader.read_normals().map(|i| (Float32x3, to_bytes(i)))
+        }
+        Semantic::Tangents => {
+            reader.read_tangents().map(|i| (Float32x4, to_bytes(i)))
+        }
+        Semantic::TexCoords(set) => {
+            reader.read_tex_coords(*set).map(|uvs| match uvs {
+                ReadTexCoords::U8(i) => (Unorm8x2, to_bytes(i)),
+                ReadTexCoords::U16(i) => (Unorm16x2, to_bytes(i)),
+                ReadTexCoords::F32(i) => (Float32x2, to_bytes(i)),
+            })
+        }
+        Semantic::Colors(set) => {
+            reader.read_colors(*set).map(|colors| match colors {
+                ReadColors::RgbF32(i) => (Float32x3, to_bytes(i)),
+                ReadColors::RgbaF32(i) => (Float32x4, to_bytes(i)),
+                c @ (ReadColors::RgbU8(_) | ReadColors::RgbaU8(_)) => {
+                    (Unorm8x4, to_bytes(c.into_rgba_u8()))
+                }
+                c @ (ReadColors::RgbU16(_) | ReadColors::RgbaU16(_)) => {
+                    (Unorm16x4, to_bytes(c.into_rgba_u16()))
+                }
+            })
+        }
+        Semantic::Joints(set) => {
+            reader.read_joints(*set).map(|joints| match joints {
+                ReadJoints::U8(i) => (Uint8x4, to_bytes(i)),
+                ReadJoints::U16(i) => (Uint16x4, to_bytes(i)),
+            })
+        }
+        Semantic::Weights(set) => {
+            reader.read_weights(*set).map(|weights| match weights {
+                ReadWeights::U8(i) => (Unorm8x4, to_bytes(i)),
+                ReadWeights::U16(i) => (Unorm16x4, to_bytes(i)),
+                ReadWeights::F32(i) => (Float32x4, to_bytes(i)),
+            })
+        }
+        Semantic::Extras(_) => return custom_data(get_buffer, accessor),
+    };
+    r.context("The accessor is out of range of its buffer")
+}
+
+/// The data of a custom attribute, which can have any accessor type.
+///
+/// 3-component accessors with 8 or 16 bit components get a 4th component
+/// of 0.
+fn custom_data<'a, 's, F>(
+    get_buffer: F,
+    accessor: &gltf::Accessor<'a>,
+) -> AnyResult<(VertexFormat, Vec<u8>)>
+where
+    F: Clone + Fn(gltf::Buffer<'a>) -> Option<&'s [u8]>,
+{
+    let n_components = accessor.dimensions().multiplicity();
+    let Some(format) = vertex_format(
+        accessor.data_type(),
+        accessor.normalized(),
+        n_components,
+    ) else {
+        bail!(
+            "No vertex format for {:?} of {:?}{}",
+            accessor.dimensions(),
+            accessor.data_type(),
+            if accessor.normalized() {
+                " (normalized)"
+            } else {
+                ""
+            }
+        );
+    };
+    let data = match accessor.data_type() {
+        DataType::I8 => read_components::<i8, F>(get_buffer, accessor),
+        DataType::U8 => read_components::<u8, F>(get_buffer, accessor),
+        DataType::I16 => read_components::<i16, F>(get_buffer, accessor),
+        DataType::U16 => read_components::<u16, F>(get_buffer, accessor),
+        DataType::U32 => read_components::<u32, F>(get_buffer, accessor),
+        DataType::F32 => read_components::<f32, F>(get_buffer, accessor),
+    }
+    .context("The accessor is out of range of its buffer")?;
+    let element = accessor.data_type().size() * n_components;
+    if format.size() == element {
+        return Ok((format, data));
+    }
+    let mut r = Vec::with_capacity(accessor.count() * format.size());
+    for src in data.chunks_exact(element) {
+        r.extend_from_slice(src);
+        r.resize(r.len() + format.size() - element, 0);
+    }
+    Ok((format, r))
+}
+
+/// The elements of an accessor with components of type `T`, as
+/// little-endian bytes.
+fn read_components<'a, 's, T, F>(
+    get: F,
+    accessor: &gltf::Accessor<'a>,
+) -> Option<Vec<u8>>
+where
+    T: Component,
+    F: Clone + Fn(gltf::Buffer<'a>) -> Option<&'s [u8]>,
+{
+    let r = match accessor.dimensions() {
+        Dimensions::Scalar => {
+            to_bytes(Iter::<T>::new(accessor.clone(), get)?.map(|v| [v]))
+        }
+        Dimensions::Vec2 => {
+            to_bytes(Iter::<[T; 2]>::new(accessor.clone(), get)?)
+        }
+        Dimensions::Vec3 => {
+            to_bytes(Iter::<[T; 3]>::new(accessor.clone(), get)?)
+        }
+        Dimensions::Vec4 => {
+            to_bytes(Iter::<[T; 4]>::new(accessor.clone(), get)?)
+        }
+        _ => return None,
+    };
+    Some(r)
+}
+
+/// Types of the components of accessors.
+trait Component: Item + Copy {
+    fn extend_le(
+        self,
+        out: &mut Vec<u8>,
+    );
+}
+
+macro_rules! impl_component {
+    ($($t:ty),*) => {
+        $(impl Component for $t {
+            fn extend_le(
+                self,
+                out: &mut Vec<u8>,
+            ) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+        })*
+    };
+}
+
+impl_component!(i8, u8, i16, u16, u32, f32);
+
+fn to_bytes<T: Component, const N: usize>(
+    elements: impl Iterator<Item = [T; N]>
+) -> Vec<u8> {
+    let mut r = vec![];
+    for element in elements {
+        for c in element {
+            c.extend_le(&mut r);
+        }
+    }
+    r
+}
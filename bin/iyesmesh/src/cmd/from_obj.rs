@@ -1,16 +1,23 @@
-use std::io::{BufReader, BufWriter};
+use std::io::BufReader;
 
 use iyes_mesh::HashMap;
 use iyes_mesh::descriptor::{IndexFormat, VertexFormat, VertexUsage};
-use iyes_mesh::mesh::MeshDataRef;
+use iyes_mesh::mesh::{
+    MeshDataRef, NormalMode, generate_normals, transform, transform_uvs,
+};
 use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
 use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+use obj::raw::material::{MtlColor, MtlTextureMap, parse_mtl};
+use obj::raw::object::{Group, Polygon, Range};
 use obj::raw::{RawObj, parse_obj};
-use obj::{Obj, Position, TexturedVertex, Vertex};
 
 use crate::CommonArgs;
+use crate::batch::{find_inputs, run_each};
 use crate::prelude::*;
-use crate::util::load_user_data;
+use crate::util::{
+    copy_color_spaces, copy_instance_data, copy_mesh_instances, is_stdout,
+    load_user_data, open_input, write_output_file,
+};
 
 #[derive(clap::Args, Debug)]
 pub struct FromObjArgs {
@@ -20,12 +27,93 @@ pub struct FromObjArgs {
     /// If the file is not an IMA file, use its raw contents as-is.
     #[arg(short, long)]
     user_data: Option<Option<PathBuf>>,
-    /// If a user data file is provided, do not try to parse it as an IMA file
+    /// Do not try to parse the user data as an IMA file
     #[arg(long)]
     user_data_force_raw: bool,
     /// If the output IMA file exists, try to add the new mesh to it
     #[arg(short, long)]
     append: bool,
+    /// Which vertex attributes to import
+    ///
+    /// Every face must have them, or the import fails. `auto` takes the
+    /// most that all faces have, and warns if that drops some.
+    #[arg(long, value_enum, default_value_t = ObjLayout::Auto)]
+    layout: ObjLayout,
+    /// Size of the indices (`auto`, `16` or `32`)
+    ///
+    /// `auto` uses 16-bit indices if there are few enough vertices.
+    #[arg(long, value_enum, default_value_t = IndexSize::Auto)]
+    index_size: IndexSize,
+    /// Fail on polygons that are not convex, instead of triangulating them
+    ///
+    /// Polygons with more than 3 vertices are triangulated as fans (from
+    /// their first vertex), which is only correct if they are convex.
+    #[arg(long)]
+    triangulate_strict: bool,
+    /// Generate normals (`flat` or `smooth`), replacing any from the file
+    ///
+    /// Smooth normals follow the smoothing groups (`s`) of the file, if it
+    /// has any: they are only smooth within a group, and flat outside.
+    #[arg(long, value_name = "MODE")]
+    #[arg(value_parser = crate::util::parse_normal_mode)]
+    gen_normals: Option<NormalMode>,
+    /// Flip the V texture coordinate (V becomes 1 - V)
+    ///
+    /// For the layouts with texture coordinates (`ptn` and `pt`).
+    #[arg(long)]
+    flip_uv_v: bool,
+    /// Up axis of the file (like `z`), rotated to become +Y
+    ///
+    /// Conversions are done after generating normals.
+    #[arg(long, value_name = "AXIS", allow_hyphen_values = true)]
+    #[arg(value_parser = crate::util::parse_axis)]
+    up_axis: Option<[f32; 3]>,
+    /// Forward axis of the file, rotated to become +Z (default: `-y` if
+    /// the up axis is `z` or `-z`, like in Blender, `z` otherwise)
+    #[arg(long, value_name = "AXIS", allow_hyphen_values = true)]
+    #[arg(value_parser = crate::util::parse_axis)]
+    forward_axis: Option<[f32; 3]>,
+    /// The coordinates of the file are left-handed
+    ///
+    /// They are mirrored along X, which also flips the winding.
+    #[arg(long)]
+    left_handed: bool,
+    /// Scale factor (like `0.001` for millimeters to meters)
+    ///
+    /// Done after the axis conversion.
+    #[arg(long, allow_hyphen_values = true)]
+    scale: Option<f32>,
+    /// Import one mesh per material (`usemtl`), instead of one per file
+    ///
+    /// Meshes have no names, so the material of each mesh is stored as the
+    /// user data, in JSON. The fields are documented with `JsonMaterials`
+    /// in the source of this command. Fields may be added, but are never
+    /// renamed or removed.
+    #[arg(long, conflicts_with = "user_data")]
+    split_materials: bool,
+    /// Store the materials of the MTL files (colors, texture paths) as the
+    /// user data, in JSON
+    ///
+    /// Same JSON as with --split-materials. MTL files that cannot be loaded
+    /// are skipped, with a warning.
+    #[arg(long, conflicts_with = "user_data")]
+    mtl_to_userdata: bool,
+    /// Import each OBJ file on its own, including the `.obj` files in input
+    /// directories and their subdirectories (see --out-dir)
+    ///
+    /// All the paths are then input paths (none is the output file).
+    /// Failures are reported at the end, instead of stopping at the first.
+    #[arg(short, long, requires = "out_dir")]
+    recursive: bool,
+    /// With --recursive, write the IMA files to this directory, mirroring
+    /// the input tree
+    #[arg(long, value_name = "DIR", requires = "recursive")]
+    out_dir: Option<PathBuf>,
+    #[command(flatten)]
+    jobs: crate::JobsArgs,
+    #[cfg(feature = "encryption")]
+    #[command(flatten)]
+    key: crate::UserDataKeyArgs,
     #[command(flatten)]
     rarg: crate::ReadArgs,
     #[command(flatten)]
@@ -38,52 +126,155 @@ pub struct FromObjArgs {
     inpaths: crate::InputPaths,
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ObjLayout {
+    /// Try each of the others, in order (`pt` before `pn` with
+    /// --gen-normals)
+    Auto,
+    /// Positions, texture coordinates and normals
+    Ptn,
+    /// Positions and normals
+    Pn,
+    /// Positions and texture coordinates
+    Pt,
+    /// Positions only
+    P,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum IndexSize {
+    /// Try 16-bit, then 32-bit
+    Auto,
+    #[value(name = "16")]
+    U16,
+    #[value(name = "32")]
+    U32,
+}
+
 pub fn run(
-    _args_common: &CommonArgs,
+    args_common: &CommonArgs,
     args_cmd: &FromObjArgs,
 ) -> AnyResult<()> {
-    if args_cmd.inpaths.in_files.is_empty() {
-        bail!("No input files provided.");
-    }
-    let mut writer = IyesMeshWriter::new_with_settings(
-        IyesMeshWriterSettings::from(&args_cmd.warg),
-    );
-    let new_user_data;
-    match &args_cmd.user_data {
-        Some(src) => {
-            new_user_data = load_user_data(
-                src.as_deref(),
-                IyesMeshReaderSettings::from(&args_cmd.rarg),
-                args_cmd.user_data_force_raw,
-            )?;
-            writer.set_user_data(&new_user_data);
+    let read_settings = IyesMeshReaderSettings {
+        #[cfg(feature = "encryption")]
+        user_data_key: args_cmd.key.load()?,
+        ..IyesMeshReaderSettings::from(&args_cmd.rarg)
+    };
+    // Loaded once, as it may come from stdin.
+    let new_user_data = match &args_cmd.user_data {
+        Some(src) => Some(load_user_data(
+            src.as_deref(),
+            read_settings,
+            args_cmd.user_data_force_raw,
+        )?),
+        None => None,
+    };
+    let Some(out_dir) = &args_cmd.out_dir else {
+        if args_cmd.inpaths.in_files.is_empty() {
+            bail!("No input files provided.");
+        }
+        if args_cmd.append && is_stdout(&args_cmd.outpath.out_file) {
+            bail!("Cannot append to stdout (`-`).");
         }
-        None => {}
+        return import(
+            args_common,
+            args_cmd,
+            read_settings,
+            new_user_data.as_deref(),
+            &args_cmd.outpath.out_file,
+            &args_cmd.inpaths.in_files,
+        );
+    };
+    // There is no output file, all the paths are inputs.
+    let mut paths = vec![args_cmd.outpath.out_file.clone()];
+    paths.extend(args_cmd.inpaths.in_files.iter().cloned());
+    let (inputs, failures) =
+        find_inputs(&paths, &["obj"], args_common.verbose);
+    run_each(&inputs, failures, args_cmd.jobs.get(), |input| {
+        let out_file = input.output_path(out_dir, "ima")?;
+        import(
+            args_common,
+            args_cmd,
+            read_settings,
+            new_user_data.as_deref(),
+            &out_file,
+            std::slice::from_ref(&input.path),
+        )?;
+        Ok(format!("{} -> {}\n", input.path.display(), out_file.display()))
+    })
+}
+
+/// Import the OBJ files into one IMA file (one mesh per file, or per
+/// material with --split-materials).
+fn import(
+    args_common: &CommonArgs,
+    args_cmd: &FromObjArgs,
+    read_settings: IyesMeshReaderSettings,
+    new_user_data: Option<&[u8]>,
+    out_file: &Path,
+    in_files: &[PathBuf],
+) -> AnyResult<()> {
+    let mut writer = IyesMeshWriter::new_with_settings(IyesMeshWriterSettings {
+        #[cfg(feature = "encryption")]
+        user_data_key: read_settings.user_data_key,
+        ..IyesMeshWriterSettings::from(&args_cmd.warg)
+    });
+    if let Some(new_user_data) = new_user_data {
+        writer.set_user_data(new_user_data);
     }
 
+    let conversion = axis_conversion(args_cmd)?;
     let mut bufs = vec![];
     let mut new_meshes = vec![];
+    let mut materials = JsonMaterials::default();
 
-    for path in args_cmd.inpaths.in_files.iter() {
-        let mut bi = vec![];
-        let mut bp = vec![];
-        let mut bn = vec![];
-        let mut bt = vec![];
-        let infile = std::fs::File::open(&path)
-            .context("Cannot open input OBJ file")?;
+    for path in in_files.iter() {
+        let infile =
+            open_input(path).context("Cannot open input OBJ file")?;
         let bufr = BufReader::new(infile);
         let rawobj = parse_obj(bufr).context("Cannot parse OBJ file")?;
-        let ifmt = try_ptn16(rawobj.clone(), &mut bi, &mut bp, &mut bt, &mut bn)
-            .or_else(|_| {
-                try_ptn32(rawobj.clone(), &mut bi, &mut bp, &mut bt, &mut bn)
-            })
-            .or_else(|_| try_pn16(rawobj.clone(), &mut bi, &mut bp, &mut bn))
-            .or_else(|_| try_pn32(rawobj.clone(), &mut bi, &mut bp, &mut bn))
-            .or_else(|_| try_p16(rawobj.clone(), &mut bi, &mut bp))
-            .or_else(|_| try_p32(rawobj.clone(), &mut bi, &mut bp))
-            .context("OBJ file is not in any valid vertex format")?;
-
-        bufs.push((ifmt, bi, bp, bn, bt));
+        let parts = if args_cmd.split_materials {
+            split_materials(&rawobj)
+        } else {
+            vec![]
+        };
+        for (name, part) in parts {
+            let material = materials.use_material(&name);
+            let n_polygons = part.polygons.len();
+            let what = format!("{}, material `{}`", path.display(), name);
+            bufs.push(
+                obj_buffers(part, &what, conversion, args_common, args_cmd)
+                    .with_context(|| {
+                        format!("Cannot import material `{}`", name)
+                    })?,
+            );
+            if args_common.verbose {
+                eprintln!(
+                    "{}: material `{}`: {} polygons",
+                    path.display(),
+                    name,
+                    n_polygons
+                );
+            }
+            materials.meshes.push(JsonMaterialMesh {
+                mesh: 0,
+                input: path.display().to_string(),
+                material,
+            });
+        }
+        if args_cmd.mtl_to_userdata {
+            materials.load_libraries(path, &rawobj.material_libraries);
+        }
+        if !args_cmd.split_materials {
+            let what = path.display().to_string();
+            bufs.push(obj_buffers(
+                rawobj,
+                &what,
+                conversion,
+                args_common,
+                args_cmd,
+            )?);
+        }
     }
     for (ifmt, bi, bp, bn, bt) in bufs.iter() {
         let mut attributes = HashMap::default();
@@ -106,7 +297,7 @@ pub fn run(
                 .insert(VertexUsage::Uv0, (VertexFormat::Float32x2, bt.as_slice()));
         }
         let mesh = MeshDataRef {
-            indices: Some((*ifmt, &bi)),
+            indices: Some((*ifmt, bi)),
             attributes,
         };
 
@@ -116,11 +307,12 @@ pub fn run(
     let with_data;
     let flatbufs;
     let meshes;
+    let mut n_old_meshes = 0;
     if args_cmd.append {
-        let mut infile = std::fs::File::open(&args_cmd.outpath.out_file)
+        let mut infile = std::fs::File::open(out_file)
             .context("Could not open input file")?;
         let reader = IyesMeshReader::init_with_settings(
-            IyesMeshReaderSettings::from(&args_cmd.rarg),
+            read_settings,
             &mut infile,
         )
         .context("Cannot decode append file metadata and initialize decoding")?;
@@ -131,148 +323,731 @@ pub fn run(
         meshes = with_data
             .into_split_meshes(&flatbufs)
             .context("Cannot decode append file meshes")?;
-        for m in meshes.meshes.iter() {
+        copy_color_spaces(&mut writer, &with_data);
+        copy_instance_data(&mut writer, &with_data, &flatbufs)?;
+        for (i, m) in meshes.meshes.iter().enumerate() {
             writer.add_mesh(m.clone()).context("Cannot use old mesh for output")?;
+            copy_mesh_instances(&mut writer, &with_data, i, i)?;
         }
+        n_old_meshes = meshes.meshes.len();
     }
 
     for m in new_meshes {
         writer.add_mesh(m).context("New mesh is incompatible")?;
     }
+    let materials_json;
+    if args_cmd.split_materials || args_cmd.mtl_to_userdata {
+        for (i, mesh) in materials.meshes.iter_mut().enumerate() {
+            mesh.mesh = n_old_meshes + i;
+        }
+        materials_json = serde_json::to_vec(&materials)?;
+        writer.set_user_data(&materials_json);
+    }
 
-    let outfile = if args_cmd.oarg.overwrite {
-        std::fs::File::create(&args_cmd.outpath.out_file)
-            .context("Could not open output file")?
-    } else {
-        std::fs::File::create_new(&args_cmd.outpath.out_file)
-            .context("Could not open output file")?
-    };
-    let mut bufout = BufWriter::new(outfile);
-    writer.write_to(&mut bufout).context("Cannot encode output file")?;
-
-    Ok(())
+    write_output_file(writer, out_file, args_cmd.oarg.overwrite)
 }
 
-fn try_ptn16(
+/// The index format, then the index, position, normal and UV buffers (the
+/// last two may be empty).
+type ObjBuffers = (IndexFormat, Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>);
+
+/// Convert the data of an OBJ file (`what`, for messages) with the layout
+/// and index size of the arguments, or the first that works if `auto`,
+/// then bake the `conversion` transform.
+fn obj_buffers(
     rawobj: RawObj,
-    bi: &mut Vec<u8>,
-    bp: &mut Vec<u8>,
-    bt: &mut Vec<u8>,
-    bn: &mut Vec<u8>,
-) -> AnyResult<IndexFormat> {
-    let obj: Obj<TexturedVertex, u16> = Obj::new(rawobj.clone())?;
-    for i in obj.indices {
-        bi.extend_from_slice(&i.to_le_bytes());
+    what: &str,
+    conversion: Option<[[f32; 4]; 4]>,
+    args_common: &CommonArgs,
+    args_cmd: &FromObjArgs,
+) -> AnyResult<ObjBuffers> {
+    let verbose = args_common.verbose;
+    let layouts = match (args_cmd.layout, args_cmd.gen_normals) {
+        // Normals from the file would be replaced anyway.
+        (ObjLayout::Auto, Some(_)) => {
+            vec![ObjLayout::Ptn, ObjLayout::Pt, ObjLayout::Pn, ObjLayout::P]
+        }
+        (ObjLayout::Auto, None) => {
+            vec![ObjLayout::Ptn, ObjLayout::Pn, ObjLayout::Pt, ObjLayout::P]
+        }
+        (layout, _) => vec![layout],
+    };
+    let ifmts = match args_cmd.index_size {
+        IndexSize::Auto => vec![IndexFormat::U16, IndexFormat::U32],
+        IndexSize::U16 => vec![IndexFormat::U16],
+        IndexSize::U32 => vec![IndexFormat::U32],
+    };
+    if args_cmd.triangulate_strict {
+        check_convex(&rawobj)?;
     }
-    for v in obj.vertices {
-        bp.extend_from_slice(&v.position[0].to_le_bytes());
-        bp.extend_from_slice(&v.position[1].to_le_bytes());
-        bp.extend_from_slice(&v.position[2].to_le_bytes());
-        bt.extend_from_slice(&v.texture[0].to_le_bytes());
-        bt.extend_from_slice(&v.texture[1].to_le_bytes());
-        bn.extend_from_slice(&v.normal[0].to_le_bytes());
-        bn.extend_from_slice(&v.normal[1].to_le_bytes());
-        bn.extend_from_slice(&v.normal[2].to_le_bytes());
+    let mut rejected = vec![];
+    let mut chosen = None;
+    'layouts: for layout in layouts.iter().copied() {
+        let indexed = match index_polygons(&rawobj, layout) {
+            Ok(indexed) => indexed,
+            Err(e) => {
+                // Larger indices would not help.
+                rejected.push((layout, ifmts[0], e));
+                continue;
+            }
+        };
+        for ifmt in ifmts.iter().copied() {
+            let n_vertices = indexed.positions.len() / 12;
+            if ifmt == IndexFormat::U16 && n_vertices > u16::MAX as usize + 1 {
+                rejected.push((
+                    layout,
+                    ifmt,
+                    anyhow::anyhow!("Too many vertices ({})", n_vertices),
+                ));
+                continue;
+            }
+            chosen = Some((layout, ifmt, indexed));
+            break 'layouts;
+        }
+    }
+    let describe = |layout: ObjLayout, ifmt: IndexFormat| {
+        let name = match layout {
+            ObjLayout::Ptn => "positions, texture coordinates and normals",
+            ObjLayout::Pn => "positions and normals",
+            ObjLayout::Pt => "positions and texture coordinates",
+            _ => "positions only",
+        };
+        let bits = if ifmt == IndexFormat::U16 { 16 } else { 32 };
+        format!("{}, with {}-bit indices", name, bits)
+    };
+    let Some((layout, mut ifmt, indexed)) = chosen else {
+        let reasons: Vec<_> = rejected
+            .iter()
+            .map(|(layout, ifmt, e)| {
+                format!("\n  {}: {}", describe(*layout, *ifmt), e)
+            })
+            .collect();
+        bail!(
+            "Cannot import the OBJ file (see --layout and --index-size) \
+             as:{}",
+            reasons.concat()
+        );
+    };
+    if verbose {
+        // Printed at once, to not be mixed with other files (--jobs).
+        let mut log = format!("{}: {}\n", what, describe(layout, ifmt));
+        for (layout, ifmt, e) in rejected.iter() {
+            log += &format!("  Not {}: {}\n", describe(*layout, *ifmt), e);
+        }
+        let n_triangulated = rawobj
+            .polygons
+            .iter()
+            .filter(|p| polygon_vertices(p).len() > 3)
+            .count();
+        if n_triangulated > 0 {
+            log += &format!("  Triangulated {} polygons\n", n_triangulated);
+        }
+        eprint!("{}", log);
+    }
+    let Indexed {
+        indices,
+        positions: mut bp,
+        normals: mut bn,
+        uvs: mut bt,
+        triangle_polygons,
+    } = indexed;
+    let mut bi: Vec<u8> = match ifmt {
+        IndexFormat::U16 => {
+            indices.iter().flat_map(|i| (*i as u16).to_le_bytes()).collect()
+        }
+        IndexFormat::U32 => {
+            indices.iter().flat_map(|i| i.to_le_bytes()).collect()
+        }
+    };
+    let has_uvs = matches!(layout, ObjLayout::Ptn | ObjLayout::Pt);
+    let has_normals = matches!(layout, ObjLayout::Ptn | ObjLayout::Pn);
+    let dropped_uvs = !has_uvs && !rawobj.tex_coords.is_empty();
+    let dropped_normals = !has_normals
+        && !rawobj.normals.is_empty()
+        && args_cmd.gen_normals.is_none();
+    if args_cmd.layout == ObjLayout::Auto && (dropped_uvs || dropped_normals)
+    {
+        eprintln!(
+            "Warning: {}: the file has {}, but not for every face, so they \
+             are not imported (see --verbose and --layout)",
+            what,
+            match (dropped_uvs, dropped_normals) {
+                (true, true) => "texture coordinates and normals",
+                (true, false) => "texture coordinates",
+                _ => "normals",
+            }
+        );
     }
-    Ok(IndexFormat::U16)
-}
 
-fn try_ptn32(
-    rawobj: RawObj,
-    bi: &mut Vec<u8>,
-    bp: &mut Vec<u8>,
-    bt: &mut Vec<u8>,
-    bn: &mut Vec<u8>,
-) -> AnyResult<IndexFormat> {
-    let obj: Obj<TexturedVertex, u32> = Obj::new(rawobj.clone())?;
-    for i in obj.indices {
-        bi.extend_from_slice(&i.to_le_bytes());
+    let no_normals = !has_normals && rawobj.normals.is_empty();
+    if no_normals && args_cmd.gen_normals.is_none() {
+        eprintln!(
+            "Warning: {}: the file has no normals (see --gen-normals)",
+            what
+        );
     }
-    for v in obj.vertices {
-        bp.extend_from_slice(&v.position[0].to_le_bytes());
-        bp.extend_from_slice(&v.position[1].to_le_bytes());
-        bp.extend_from_slice(&v.position[2].to_le_bytes());
-        bt.extend_from_slice(&v.texture[0].to_le_bytes());
-        bt.extend_from_slice(&v.texture[1].to_le_bytes());
-        bn.extend_from_slice(&v.normal[0].to_le_bytes());
-        bn.extend_from_slice(&v.normal[1].to_le_bytes());
-        bn.extend_from_slice(&v.normal[2].to_le_bytes());
+
+    if let Some(mode) = args_cmd.gen_normals {
+        // Without smoothing groups, smooth normals are smooth everywhere,
+        // so vertices can stay shared.
+        if mode == NormalMode::Flat || !rawobj.smoothing_groups.is_empty() {
+            let polygon_groups = polygon_smoothing_groups(&rawobj);
+            let smoothing: Vec<_> = triangle_polygons
+                .iter()
+                .map(|p| polygon_groups[*p])
+                .collect();
+            split_normal_groups(
+                &smoothing,
+                mode,
+                args_cmd.index_size,
+                &mut ifmt,
+                &mut bi,
+                &mut bp,
+                &mut bt,
+            )?;
+        }
+        let mesh = MeshDataRef {
+            indices: Some((ifmt, &bi)),
+            attributes: [(
+                VertexUsage::Position,
+                (VertexFormat::Float32x3, bp.as_slice()),
+            )]
+            .into_iter()
+            .collect(),
+        };
+        bn = generate_normals(&mesh, mode)
+            .context("Cannot generate normals")?;
     }
-    Ok(IndexFormat::U32)
+
+    if args_cmd.flip_uv_v && bt.is_empty() {
+        eprintln!(
+            "Warning: {}: no texture coordinates are imported, so \
+             --flip-uv-v does nothing",
+            what
+        );
+    }
+    if args_cmd.flip_uv_v && !bt.is_empty() {
+        let mesh = MeshDataRef {
+            indices: None,
+            attributes: [(
+                VertexUsage::Uv0,
+                (VertexFormat::Float32x2, bt.as_slice()),
+            )]
+            .into_iter()
+            .collect(),
+        };
+        let flipped =
+            transform_uvs(&mesh, VertexUsage::Uv0, [1.0; 2], [0.0; 2], true)
+                .context("Cannot flip UVs")?;
+        bt = flipped.as_mesh_ref().attributes[&VertexUsage::Uv0]
+            .1
+            .to_vec();
+    }
+
+    if let Some(matrix) = conversion {
+        let mut attributes = HashMap::default();
+        let f32x3 = VertexFormat::Float32x3;
+        attributes.insert(VertexUsage::Position, (f32x3, &bp[..]));
+        if !bn.is_empty() {
+            attributes.insert(VertexUsage::Normal, (f32x3, &bn[..]));
+        }
+        let mesh = MeshDataRef {
+            indices: Some((ifmt, &bi)),
+            attributes,
+        };
+        let converted = transform(&mesh, matrix)
+            .context("Cannot convert the coordinates")?;
+        let converted = converted.as_mesh_ref();
+        // The vertices stay in order, as the mesh is indexed.
+        if let Some((_, indices)) = converted.indices {
+            bi = indices.to_vec();
+        }
+        bp = converted.attributes[&VertexUsage::Position].1.to_vec();
+        if let Some((_, normals)) =
+            converted.attributes.get(&VertexUsage::Normal)
+        {
+            bn = normals.to_vec();
+        }
+    }
+
+    Ok((ifmt, bi, bp, bn, bt))
 }
 
-fn try_pn16(
-    rawobj: RawObj,
-    bi: &mut Vec<u8>,
-    bp: &mut Vec<u8>,
-    bn: &mut Vec<u8>,
-) -> AnyResult<IndexFormat> {
-    let obj: Obj<Vertex, u16> = Obj::new(rawobj.clone())?;
-    for i in obj.indices {
-        bi.extend_from_slice(&i.to_le_bytes());
+/// The transform of --up-axis, --forward-axis, --left-handed and --scale,
+/// or `None` if there is nothing to do.
+///
+/// It maps the up axis to +Y and the forward axis to +Z, and the right
+/// axis (up × forward, or forward × up if left-handed) to +X.
+fn axis_conversion(
+    args_cmd: &FromObjArgs,
+) -> AnyResult<Option<[[f32; 4]; 4]>> {
+    let up = args_cmd.up_axis.unwrap_or([0.0, 1.0, 0.0]);
+    let forward = args_cmd.forward_axis.unwrap_or(if up[2] != 0.0 {
+        [0.0, -1.0, 0.0]
+    } else {
+        [0.0, 0.0, 1.0]
+    });
+    if (0..3).any(|i| up[i] != 0.0 && forward[i] != 0.0) {
+        bail!("The up and forward axes must be different");
     }
-    for v in obj.vertices {
-        bp.extend_from_slice(&v.position[0].to_le_bytes());
-        bp.extend_from_slice(&v.position[1].to_le_bytes());
-        bp.extend_from_slice(&v.position[2].to_le_bytes());
-        bn.extend_from_slice(&v.normal[0].to_le_bytes());
-        bn.extend_from_slice(&v.normal[1].to_le_bytes());
-        bn.extend_from_slice(&v.normal[2].to_le_bytes());
+    let cross = |a: [f32; 3], b: [f32; 3]| {
+        [
+            a[1] * b[2] - a[2] * b[1],
+            a[2] * b[0] - a[0] * b[2],
+            a[0] * b[1] - a[1] * b[0],
+        ]
+    };
+    let right = if args_cmd.left_handed {
+        cross(forward, up)
+    } else {
+        cross(up, forward)
+    };
+    let scale = args_cmd.scale.unwrap_or(1.0);
+    // Row r of the rotation is the file axis that becomes target axis r.
+    let rows = [right, up, forward];
+    let mut matrix = [[0.0; 4]; 4];
+    for (c, column) in matrix.iter_mut().take(3).enumerate() {
+        for (r, row) in rows.iter().enumerate() {
+            column[r] = row[c] * scale;
+        }
     }
-    Ok(IndexFormat::U16)
+    matrix[3][3] = 1.0;
+    let identity: [[f32; 4]; 4] =
+        std::array::from_fn(|c| std::array::from_fn(|r| (c == r) as u8 as f32));
+    Ok((matrix != identity).then_some(matrix))
 }
 
-fn try_pn32(
-    rawobj: RawObj,
+/// The part of the file of each material (by `usemtl`), in the order the
+/// materials are first used. Polygons before any `usemtl` have the
+/// material `""`.
+fn split_materials(rawobj: &RawObj) -> Vec<(String, RawObj)> {
+    let smoothing = polygon_smoothing_groups(rawobj);
+    let mut groups: Vec<_> = rawobj
+        .meshes
+        .iter()
+        .filter(|(_, group)| !group.polygons.is_empty())
+        .collect();
+    groups.sort_by_key(|(_, group)| group.polygons[0].start);
+    groups
+        .into_iter()
+        .map(|(name, group)| {
+            let indices: Vec<usize> =
+                group.polygons.iter().flat_map(|r| r.start..r.end).collect();
+            let mut smoothing_groups: HashMap<usize, Group> =
+                HashMap::default();
+            for (i, index) in indices.iter().enumerate() {
+                if let Some(g) = smoothing[*index] {
+                    let range = Range { start: i, end: i + 1 };
+                    smoothing_groups.entry(g).or_default().polygons.push(range);
+                }
+            }
+            let part = RawObj {
+                polygons: indices
+                    .iter()
+                    .map(|i| rawobj.polygons[*i].clone())
+                    .collect(),
+                points: vec![],
+                lines: vec![],
+                smoothing_groups: smoothing_groups.into_iter().collect(),
+                ..rawobj.clone()
+            };
+            (name.clone(), part)
+        })
+        .collect()
+}
+
+/// The smoothing group (`s`) of each polygon, if any.
+fn polygon_smoothing_groups(rawobj: &RawObj) -> Vec<Option<usize>> {
+    let mut r = vec![None; rawobj.polygons.len()];
+    for (g, group) in rawobj.smoothing_groups.iter() {
+        for range in group.polygons.iter() {
+            for p in r[range.start..range.end].iter_mut() {
+                *p = Some(*g);
+            }
+        }
+    }
+    r
+}
+
+/// Give each triangle its own vertices (`mode` is flat), or only share
+/// vertices within smoothing groups (`mode` is smooth; triangles without a
+/// group get their own, as they are flat), so that generated normals
+/// follow them. Only positions and UVs are kept, the normals are to be
+/// generated. `smoothing` is the smoothing group of each triangle.
+fn split_normal_groups(
+    smoothing: &[Option<usize>],
+    mode: NormalMode,
+    index_size: IndexSize,
+    ifmt: &mut IndexFormat,
     bi: &mut Vec<u8>,
     bp: &mut Vec<u8>,
-    bn: &mut Vec<u8>,
-) -> AnyResult<IndexFormat> {
-    let obj: Obj<Vertex, u32> = Obj::new(rawobj.clone())?;
-    for i in obj.indices {
-        bi.extend_from_slice(&i.to_le_bytes());
+    bt: &mut Vec<u8>,
+) -> AnyResult<()> {
+    let indices: Vec<u32> = match ifmt {
+        IndexFormat::U16 => bi
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as u32)
+            .collect(),
+        IndexFormat::U32 => bi
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    };
+    /// Triangles share a vertex if they are in the same group.
+    #[derive(PartialEq, Eq, Hash)]
+    enum NormalGroup {
+        Smoothing(usize),
+        Triangle(usize),
     }
-    for v in obj.vertices {
-        bp.extend_from_slice(&v.position[0].to_le_bytes());
-        bp.extend_from_slice(&v.position[1].to_le_bytes());
-        bp.extend_from_slice(&v.position[2].to_le_bytes());
-        bn.extend_from_slice(&v.normal[0].to_le_bytes());
-        bn.extend_from_slice(&v.normal[1].to_le_bytes());
-        bn.extend_from_slice(&v.normal[2].to_le_bytes());
+    let mut new_vertices: HashMap<(u32, NormalGroup), u32> = HashMap::default();
+    let mut new_indices = Vec::with_capacity(indices.len());
+    let (mut new_bp, mut new_bt) = (vec![], vec![]);
+    for (i, v) in indices.iter().enumerate() {
+        let t = i / 3;
+        let group = match (mode, smoothing.get(t).copied().flatten()) {
+            (NormalMode::Smooth, Some(g)) => NormalGroup::Smoothing(g),
+            _ => NormalGroup::Triangle(t),
+        };
+        let next = new_vertices.len() as u32;
+        let index = *new_vertices.entry((*v, group)).or_insert_with(|| {
+            let v = *v as usize;
+            new_bp.extend_from_slice(&bp[v * 12..(v + 1) * 12]);
+            if !bt.is_empty() {
+                new_bt.extend_from_slice(&bt[v * 8..(v + 1) * 8]);
+            }
+            next
+        });
+        new_indices.push(index);
     }
-    Ok(IndexFormat::U32)
+    let n_vertices = new_vertices.len();
+    *ifmt = if n_vertices <= u16::MAX as usize + 1 {
+        match index_size {
+            IndexSize::U32 => IndexFormat::U32,
+            _ => IndexFormat::U16,
+        }
+    } else if index_size == IndexSize::U16 {
+        bail!(
+            "Too many vertices for 16-bit indices with separate normals \
+             ({}), see --index-size",
+            n_vertices
+        );
+    } else {
+        IndexFormat::U32
+    };
+    *bi = match ifmt {
+        IndexFormat::U16 => new_indices
+            .iter()
+            .flat_map(|i| (*i as u16).to_le_bytes())
+            .collect(),
+        IndexFormat::U32 => {
+            new_indices.iter().flat_map(|i| i.to_le_bytes()).collect()
+        }
+    };
+    *bp = new_bp;
+    *bt = new_bt;
+    Ok(())
 }
 
-fn try_p16(
-    rawobj: RawObj,
-    bi: &mut Vec<u8>,
-    bp: &mut Vec<u8>,
-) -> AnyResult<IndexFormat> {
-    let obj: Obj<Position, u16> = Obj::new(rawobj.clone())?;
-    for i in obj.indices {
-        bi.extend_from_slice(&i.to_le_bytes());
+/// The user data written by `from-obj --split-materials` or
+/// `--mtl-to-userdata`.
+///
+/// Fields may be added, but are never renamed or removed.
+#[derive(Default, serde::Serialize)]
+struct JsonMaterials {
+    /// The materials used by the meshes, in the order they are first used,
+    /// then (with `--mtl-to-userdata`) the other materials of the MTL
+    /// files, sorted by name.
+    materials: Vec<JsonMaterial>,
+    /// The material of each new mesh (with `--split-materials`).
+    meshes: Vec<JsonMaterialMesh>,
+}
+
+/// A material in [`JsonMaterials::materials`]. Everything but the name is
+/// only known with `--mtl-to-userdata`, and omitted if not in the MTL file.
+#[derive(Default, serde::Serialize)]
+struct JsonMaterial {
+    name: String,
+    /// Colors (`Ka`, `Kd`, `Ks`, `Ke`), if they are RGB.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ambient: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diffuse: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    specular: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    emissive: Option<[f32; 3]>,
+    /// Opacity (`d`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dissolve: Option<f32>,
+    /// Shininess (`Ns`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    specular_exponent: Option<f32>,
+    /// Texture paths (`map_Ka`, `map_Kd`, ...), as in the MTL file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ambient_map: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diffuse_map: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    specular_map: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    emissive_map: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dissolve_map: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bump_map: Option<String>,
+}
+
+/// A mesh in [`JsonMaterials::meshes`].
+#[derive(serde::Serialize)]
+struct JsonMaterialMesh {
+    /// Index of the mesh in the output file.
+    mesh: usize,
+    /// Path of the input file, as given on the command line.
+    input: String,
+    /// Index in [`JsonMaterials::materials`], or null for the polygons
+    /// before any `usemtl`.
+    material: Option<usize>,
+}
+
+impl JsonMaterials {
+    /// The index of a material, added if new. `""` is no material.
+    fn use_material(
+        &mut self,
+        name: &str,
+    ) -> Option<usize> {
+        if name.is_empty() {
+            return None;
+        }
+        let index = self.materials.iter().position(|m| m.name == name);
+        Some(index.unwrap_or_else(|| {
+            self.materials.push(JsonMaterial {
+                name: name.to_owned(),
+                ..Default::default()
+            });
+            self.materials.len() - 1
+        }))
     }
-    for v in obj.vertices {
-        bp.extend_from_slice(&v.position[0].to_le_bytes());
-        bp.extend_from_slice(&v.position[1].to_le_bytes());
-        bp.extend_from_slice(&v.position[2].to_le_bytes());
+
+    /// Load the materials of the MTL files of an OBJ file (relative to it).
+    fn load_libraries(
+        &mut self,
+        obj_path: &Path,
+        libraries: &[String],
+    ) {
+        let dir = obj_path.parent().unwrap_or(Path::new(""));
+        for library in libraries {
+            let path = dir.join(library);
+            let mtl = std::fs::File::open(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|f| Ok(parse_mtl(BufReader::new(f))?));
+            let mtl = match mtl {
+                Ok(mtl) => mtl,
+                Err(e) => {
+                    eprintln!(
+                        "Warning: cannot load {}: {}, skipping it",
+                        path.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            let mut names: Vec<_> = mtl.materials.keys().collect();
+            names.sort();
+            for name in names {
+                let m = &mtl.materials[name];
+                let index = self.use_material(name).unwrap_or_default();
+                let rgb = |c: &Option<MtlColor>| match c {
+                    Some(MtlColor::Rgb(r, g, b)) => Some([*r, *g, *b]),
+                    _ => None,
+                };
+                let map = |m: &Option<MtlTextureMap>| {
+                    m.as_ref().map(|m| m.file.clone())
+                };
+                self.materials[index] = JsonMaterial {
+                    name: name.clone(),
+                    ambient: rgb(&m.ambient),
+                    diffuse: rgb(&m.diffuse),
+                    specular: rgb(&m.specular),
+                    emissive: rgb(&m.emissive),
+                    dissolve: m.dissolve,
+                    specular_exponent: m.specular_exponent,
+                    ambient_map: map(&m.ambient_map),
+                    diffuse_map: map(&m.diffuse_map),
+                    specular_map: map(&m.specular_map),
+                    emissive_map: map(&m.emissive_map),
+                    dissolve_map: map(&m.dissolve_map),
+                    bump_map: map(&m.bump_map),
+                };
+            }
+        }
     }
-    Ok(IndexFormat::U16)
 }
 
-fn try_p32(
-    rawobj: RawObj,
-    bi: &mut Vec<u8>,
-    bp: &mut Vec<u8>,
-) -> AnyResult<IndexFormat> {
-    let obj: Obj<Position, u32> = Obj::new(rawobj.clone())?;
-    for i in obj.indices {
-        bi.extend_from_slice(&i.to_le_bytes());
+/// The vertices and triangles of an OBJ file, with a layout.
+struct Indexed {
+    indices: Vec<u32>,
+    /// The buffers of the attributes (empty if not in the layout).
+    positions: Vec<u8>,
+    normals: Vec<u8>,
+    uvs: Vec<u8>,
+    /// The polygon of each triangle.
+    triangle_polygons: Vec<usize>,
+}
+
+/// Build the vertices of the layout from the polygons of the file, and
+/// triangulate them as fans from their first vertex (keeping the winding).
+///
+/// Polygons share a vertex if they use the same position, texture
+/// coordinates and normal (of those in the layout).
+fn index_polygons(
+    rawobj: &RawObj,
+    layout: ObjLayout,
+) -> AnyResult<Indexed> {
+    let with_uvs = matches!(layout, ObjLayout::Ptn | ObjLayout::Pt);
+    let with_normals = matches!(layout, ObjLayout::Ptn | ObjLayout::Pn);
+    let mut r = Indexed {
+        indices: Vec::with_capacity(rawobj.polygons.len() * 3),
+        positions: vec![],
+        normals: vec![],
+        uvs: vec![],
+        triangle_polygons: Vec::with_capacity(rawobj.polygons.len()),
+    };
+    let mut cache: HashMap<(usize, Option<usize>, Option<usize>), u32> =
+        HashMap::default();
+    let mut polygon_indices = vec![];
+    for (i, polygon) in rawobj.polygons.iter().enumerate() {
+        let face = i + 1;
+        let vertices = polygon_vertices(polygon);
+        if vertices.len() < 3 {
+            bail!("Face {} has less than 3 vertices", face);
+        }
+        polygon_indices.clear();
+        for (p, t, n) in vertices {
+            let t = match (with_uvs, t) {
+                (false, _) => None,
+                (true, Some(t)) => Some(t),
+                (true, None) => {
+                    bail!("Face {} has no texture coordinates", face)
+                }
+            };
+            let n = match (with_normals, n) {
+                (false, _) => None,
+                (true, Some(n)) => Some(n),
+                (true, None) => bail!("Face {} has no normals", face),
+            };
+            if let Some(index) = cache.get(&(p, t, n)) {
+                polygon_indices.push(*index);
+                continue;
+            }
+            let missing =
+                || format!("Face {} uses a vertex that does not exist", face);
+            let position = rawobj.positions.get(p).with_context(missing)?;
+            for c in [position.0, position.1, position.2] {
+                r.positions.extend_from_slice(&c.to_le_bytes());
+            }
+            if let Some(t) = t {
+                let uv = rawobj.tex_coords.get(t).with_context(missing)?;
+                for c in [uv.0, uv.1] {
+                    r.uvs.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+            if let Some(n) = n {
+                let normal = rawobj.normals.get(n).with_context(missing)?;
+                for c in [normal.0, normal.1, normal.2] {
+                    r.normals.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+            let index = u32::try_from(cache.len())
+                .context("Too many vertices for 32-bit indices")?;
+            cache.insert((p, t, n), index);
+            polygon_indices.push(index);
+        }
+        for k in 1..polygon_indices.len() - 1 {
+            r.indices.extend([
+                polygon_indices[0],
+                polygon_indices[k],
+                polygon_indices[k + 1],
+            ]);
+            r.triangle_polygons.push(i);
+        }
     }
-    for v in obj.vertices {
-        bp.extend_from_slice(&v.position[0].to_le_bytes());
-        bp.extend_from_slice(&v.position[1].to_le_bytes());
-        bp.extend_from_slice(&v.position[2].to_le_bytes());
+    Ok(r)
+}
+
+/// The position, texture coordinate and normal indices of the vertices of
+/// a polygon.
+fn polygon_vertices(
+    polygon: &Polygon,
+) -> Vec<(usize, Option<usize>, Option<usize>)> {
+    match polygon {
+        Polygon::P(v) => v.iter().map(|p| (*p, None, None)).collect(),
+        Polygon::PT(v) => v.iter().map(|(p, t)| (*p, Some(*t), None)).collect(),
+        Polygon::PN(v) => v.iter().map(|(p, n)| (*p, None, Some(*n))).collect(),
+        Polygon::PTN(v) => {
+            v.iter().map(|(p, t, n)| (*p, Some(*t), Some(*n))).collect()
+        }
     }
-    Ok(IndexFormat::U32)
+}
+
+/// Check that the polygons are convex (for --triangulate-strict), as fan
+/// triangulation is only correct for those.
+///
+/// A polygon is convex if all its corners turn the same way as the polygon
+/// overall (its normal by Newell's method). Straight corners are allowed.
+fn check_convex(rawobj: &RawObj) -> AnyResult<()> {
+    let sub = |a: [f32; 3], b: [f32; 3]| -> [f32; 3] {
+        std::array::from_fn(|c| a[c] - b[c])
+    };
+    let cross = |a: [f32; 3], b: [f32; 3]| {
+        [
+            a[1] * b[2] - a[2] * b[1],
+            a[2] * b[0] - a[0] * b[2],
+            a[0] * b[1] - a[1] * b[0],
+        ]
+    };
+    let dot = |a: [f32; 3], b: [f32; 3]| -> f32 {
+        (0..3).map(|c| a[c] * b[c]).sum()
+    };
+    let length = |a: [f32; 3]| dot(a, a).sqrt();
+    for (i, polygon) in rawobj.polygons.iter().enumerate() {
+        let vertices = polygon_vertices(polygon);
+        if vertices.len() <= 3 {
+            continue;
+        }
+        let points: Option<Vec<_>> = vertices
+            .iter()
+            .map(|(p, _, _)| rawobj.positions.get(*p))
+            .map(|v| v.map(|v| [v.0, v.1, v.2]))
+            .collect();
+        let Some(points) = points else {
+            // Reported when indexing.
+            continue;
+        };
+        let n = points.len();
+        let mut normal = [0.0; 3];
+        for k in 0..n {
+            let (a, b) = (points[k], points[(k + 1) % n]);
+            normal[0] += (a[1] - b[1]) * (a[2] + b[2]);
+            normal[1] += (a[2] - b[2]) * (a[0] + b[0]);
+            normal[2] += (a[0] - b[0]) * (a[1] + b[1]);
+        }
+        for k in 0..n {
+            let a = points[k];
+            let b = points[(k + 1) % n];
+            let c = points[(k + 2) % n];
+            let (u, v) = (sub(b, a), sub(c, b));
+            let turn = dot(cross(u, v), normal);
+            // Some tolerance for straight corners, which may turn a bit
+            // either way from rounding.
+            let tolerance = 1e-5 * length(u) * length(v) * length(normal);
+            if turn < -tolerance {
+                bail!(
+                    "Face {} is not convex, so it cannot be triangulated \
+                     (see --triangulate-strict)",
+                    i + 1
+                );
+            }
+        }
+    }
+    Ok(())
 }
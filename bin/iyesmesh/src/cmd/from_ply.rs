@@ -0,0 +1,488 @@
+use iyes_mesh::HashMap;
+use iyes_mesh::descriptor::{VertexFormat, VertexUsage};
+use iyes_mesh::mesh::MeshData;
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{read_input, write_output_file};
+
+#[derive(clap::Args, Debug)]
+pub struct FromPlyArgs {
+    /// Import unknown vertex properties as custom attributes (`custom:0`,
+    /// `custom:1`, ... in the order of the file), instead of skipping them
+    #[arg(long)]
+    custom_attrs: bool,
+    #[command(flatten)]
+    warg: crate::WriteArgs,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    outpath: crate::OutputPath,
+    #[command(flatten)]
+    inpaths: crate::InputPaths,
+}
+
+pub fn run(
+    args_common: &CommonArgs,
+    args_cmd: &FromPlyArgs,
+) -> AnyResult<()> {
+    if args_cmd.inpaths.in_files.is_empty() {
+        bail!("No input files provided.");
+    }
+    let mut new_meshes = vec![];
+    for path in args_cmd.inpaths.in_files.iter() {
+        let bytes = read_input(path).context("Cannot open input PLY file")?;
+        let ply = parse_ply(&bytes)
+            .with_context(|| format!("Cannot parse {}", path.display()))?;
+        let mesh = ply_mesh(&ply, args_cmd.custom_attrs, args_common.verbose)
+            .with_context(|| format!("Cannot import {}", path.display()))?;
+        new_meshes.push(mesh);
+    }
+
+    let mut writer = IyesMeshWriter::new_with_settings(
+        IyesMeshWriterSettings::from(&args_cmd.warg),
+    );
+    for (mesh, path) in new_meshes.iter().zip(args_cmd.inpaths.in_files.iter())
+    {
+        writer.add_mesh(mesh.as_mesh_ref()).with_context(|| {
+            format!("The mesh of {} is incompatible", path.display())
+        })?;
+    }
+    write_output_file(
+        writer,
+        &args_cmd.outpath.out_file,
+        args_cmd.oarg.overwrite,
+    )
+}
+
+/// The type of a PLY property (or of the count and items of a list).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> AnyResult<Self> {
+        let r = match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            other => bail!("Unknown property type `{}`", other),
+        };
+        Ok(r)
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    /// The vertex format of a custom attribute of this type.
+    fn vertex_format(self) -> VertexFormat {
+        match self {
+            Self::I8 => VertexFormat::Sint8,
+            Self::U8 => VertexFormat::Uint8,
+            Self::I16 => VertexFormat::Sint16,
+            Self::U16 => VertexFormat::Uint16,
+            Self::I32 => VertexFormat::Sint32,
+            Self::U32 => VertexFormat::Uint32,
+            Self::F32 | Self::F64 => VertexFormat::Float32,
+        }
+    }
+
+    /// The bytes of a value of this type, in the vertex format.
+    fn vertex_bytes(
+        self,
+        value: f64,
+        out: &mut Vec<u8>,
+    ) {
+        match self {
+            Self::I8 => out.extend_from_slice(&(value as i8).to_le_bytes()),
+            Self::U8 => out.push(value as u8),
+            Self::I16 => out.extend_from_slice(&(value as i16).to_le_bytes()),
+            Self::U16 => out.extend_from_slice(&(value as u16).to_le_bytes()),
+            Self::I32 => out.extend_from_slice(&(value as i32).to_le_bytes()),
+            Self::U32 => out.extend_from_slice(&(value as u32).to_le_bytes()),
+            Self::F32 | Self::F64 => {
+                out.extend_from_slice(&(value as f32).to_le_bytes())
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum PropertyKind {
+    Scalar(Scalar),
+    /// The type of the count, and of the items.
+    List(Scalar, Scalar),
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// The parts of a PLY file that are needed to import a mesh.
+struct Ply {
+    /// The properties of the vertices, and their values.
+    vertex_properties: Vec<(String, Scalar, Vec<f64>)>,
+    /// List properties of the vertices (which are skipped).
+    vertex_lists: Vec<String>,
+    /// The vertex indices of each face, if the file has faces.
+    faces: Option<Vec<Vec<u32>>>,
+}
+
+/// Reads the values of the elements of a PLY file.
+enum Body<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary {
+        data: &'a [u8],
+        big_endian: bool,
+    },
+}
+
+impl Body<'_> {
+    fn read(
+        &mut self,
+        ty: Scalar,
+    ) -> AnyResult<f64> {
+        match self {
+            Body::Ascii(tokens) => {
+                let token = tokens.next().context("The file ends too early")?;
+                token
+                    .parse()
+                    .with_context(|| format!("`{}` is not a number", token))
+            }
+            Body::Binary { data, big_endian } => {
+                let size = ty.size();
+                if data.len() < size {
+                    bail!("The file ends too early");
+                }
+                let mut bytes = [0; 8];
+                bytes[..size].copy_from_slice(&data[..size]);
+                *data = &data[size..];
+                if *big_endian {
+                    bytes[..size].reverse();
+                }
+                let r = match ty {
+                    Scalar::I8 => bytes[0] as i8 as f64,
+                    Scalar::U8 => bytes[0] as f64,
+                    Scalar::I16 => {
+                        i16::from_le_bytes([bytes[0], bytes[1]]) as f64
+                    }
+                    Scalar::U16 => {
+                        u16::from_le_bytes([bytes[0], bytes[1]]) as f64
+                    }
+                    Scalar::I32 => {
+                        i32::from_le_bytes(bytes[..4].try_into().unwrap())
+                            as f64
+                    }
+                    Scalar::U32 => {
+                        u32::from_le_bytes(bytes[..4].try_into().unwrap())
+                            as f64
+                    }
+                    Scalar::F32 => {
+                        f32::from_le_bytes(bytes[..4].try_into().unwrap())
+                            as f64
+                    }
+                    Scalar::F64 => f64::from_le_bytes(bytes),
+                };
+                Ok(r)
+            }
+        }
+    }
+}
+
+fn parse_ply(bytes: &[u8]) -> AnyResult<Ply> {
+    if !bytes.starts_with(b"ply") {
+        bail!("Not a PLY file");
+    }
+    let end = bytes
+        .windows(10)
+        .position(|w| w == b"end_header")
+        .context("The header has no `end_header`")?;
+    let body_start = bytes[end..]
+        .iter()
+        .position(|b| *b == b'\n')
+        .map_or(bytes.len(), |n| end + n + 1);
+    let header = std::str::from_utf8(&bytes[..end])
+        .context("The header is not text")?;
+
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    for line in header.lines().skip(1) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["format", name, _version] => format = Some(*name),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().context("Bad element count")?,
+                properties: vec![],
+            }),
+            ["property", "list", count, item, name] => elements
+                .last_mut()
+                .context("Property before any element")?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    kind: PropertyKind::List(
+                        Scalar::parse(count)?,
+                        Scalar::parse(item)?,
+                    ),
+                }),
+            ["property", ty, name] => elements
+                .last_mut()
+                .context("Property before any element")?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    kind: PropertyKind::Scalar(Scalar::parse(ty)?),
+                }),
+            _ => bail!("Unexpected header line `{}`", line),
+        }
+    }
+    let data = &bytes[body_start..];
+    let mut body = match format {
+        Some("ascii") => Body::Ascii(
+            std::str::from_utf8(data)
+                .context("The data is not text")?
+                .split_ascii_whitespace(),
+        ),
+        Some("binary_little_endian") => Body::Binary {
+            data,
+            big_endian: false,
+        },
+        Some("binary_big_endian") => Body::Binary {
+            data,
+            big_endian: true,
+        },
+        Some(other) => bail!("Unknown format `{}`", other),
+        None => bail!("The header has no format"),
+    };
+
+    let mut r = Ply {
+        vertex_properties: vec![],
+        vertex_lists: vec![],
+        faces: None,
+    };
+    for element in elements.iter() {
+        let is_vertex = element.name == "vertex";
+        let is_face = element.name == "face";
+        if is_vertex {
+            for property in element.properties.iter() {
+                match property.kind {
+                    PropertyKind::Scalar(ty) => r.vertex_properties.push((
+                        property.name.clone(),
+                        ty,
+                        Vec::with_capacity(element.count),
+                    )),
+                    PropertyKind::List(..) => {
+                        r.vertex_lists.push(property.name.clone())
+                    }
+                }
+            }
+        }
+        if is_face {
+            r.faces = Some(Vec::with_capacity(element.count));
+        }
+        for _ in 0..element.count {
+            let mut n_scalar = 0;
+            for property in element.properties.iter() {
+                match property.kind {
+                    PropertyKind::Scalar(ty) => {
+                        let value = body.read(ty)?;
+                        if is_vertex {
+                            r.vertex_properties[n_scalar].2.push(value);
+                        }
+                        n_scalar += 1;
+                    }
+                    PropertyKind::List(count_ty, item_ty) => {
+                        let count = body.read(count_ty)? as usize;
+                        let mut items = Vec::with_capacity(count);
+                        for _ in 0..count {
+                            items.push(body.read(item_ty)? as u32);
+                        }
+                        let is_indices = property.name == "vertex_indices"
+                            || property.name == "vertex_index";
+                        if is_face
+                            && is_indices
+                            && let Some(faces) = &mut r.faces
+                        {
+                            faces.push(items);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(r)
+}
+
+/// The IMA mesh of a PLY file.
+fn ply_mesh(
+    ply: &Ply,
+    custom_attrs: bool,
+    verbose: bool,
+) -> AnyResult<MeshData> {
+    let props: HashMap<&str, (Scalar, &[f64])> = ply
+        .vertex_properties
+        .iter()
+        .map(|(name, ty, values)| (name.as_str(), (*ty, values.as_slice())))
+        .collect();
+    let columns = |names: &[&str]| -> Option<Vec<(Scalar, &[f64])>> {
+        names.iter().map(|name| props.get(name).copied()).collect()
+    };
+    let floats = |columns: &[(Scalar, &[f64])]| -> Vec<u8> {
+        let n = columns[0].1.len();
+        (0..n)
+            .flat_map(|i| columns.iter().map(move |c| c.1[i] as f32))
+            .flat_map(f32::to_le_bytes)
+            .collect()
+    };
+
+    let mut r = MeshData::new();
+    let mut used = vec![];
+    let Some(position) = columns(&["x", "y", "z"]) else {
+        bail!("The vertices have no x, y and z");
+    };
+    let n_vertices = position[0].1.len();
+    r.set_attribute_bytes(
+        VertexUsage::Position,
+        VertexFormat::Float32x3,
+        &floats(&position),
+    )?;
+    used.extend(["x", "y", "z"]);
+    if let Some(normal) = columns(&["nx", "ny", "nz"]) {
+        r.set_attribute_bytes(
+            VertexUsage::Normal,
+            VertexFormat::Float32x3,
+            &floats(&normal),
+        )?;
+        used.extend(["nx", "ny", "nz"]);
+    }
+    for names in [["u", "v"], ["s", "t"], ["texture_u", "texture_v"]] {
+        if let Some(uv) = columns(&names) {
+            r.set_attribute_bytes(
+                VertexUsage::Uv0,
+                VertexFormat::Float32x2,
+                &floats(&uv),
+            )?;
+            used.extend(names);
+            break;
+        }
+    }
+    if let Some(rgb) = columns(&["red", "green", "blue"]) {
+        let alpha = props.get("alpha").copied();
+        let colors: Vec<u8> = (0..n_vertices)
+            .flat_map(|i| {
+                let [red, green, blue] =
+                    [0, 1, 2].map(|c| unorm8(rgb[c].0, rgb[c].1[i]));
+                let alpha = alpha.map_or(255, |(ty, v)| unorm8(ty, v[i]));
+                [red, green, blue, alpha]
+            })
+            .collect();
+        r.set_attribute_bytes(
+            VertexUsage::Color,
+            VertexFormat::Unorm8x4,
+            &colors,
+        )?;
+        used.extend(["red", "green", "blue", "alpha"]);
+    }
+
+    let mut skipped = ply.vertex_lists.clone();
+    let mut next_custom = 0;
+    for (name, ty, values) in ply.vertex_properties.iter() {
+        if used.contains(&name.as_str()) {
+            continue;
+        }
+        if !custom_attrs {
+            skipped.push(name.clone());
+            continue;
+        }
+        let usage = VertexUsage::Custom(next_custom);
+        next_custom += 1;
+        let mut data = Vec::with_capacity(values.len() * 4);
+        for value in values.iter() {
+            ty.vertex_bytes(*value, &mut data);
+        }
+        r.set_attribute_bytes(usage, ty.vertex_format(), &data)?;
+        if verbose {
+            eprintln!(
+                "Property {} is {} ({:?})",
+                name,
+                usage,
+                ty.vertex_format()
+            );
+        }
+    }
+    if !skipped.is_empty() {
+        eprintln!(
+            "Warning: skipping vertex properties {} (see --custom-attrs)",
+            skipped.join(", ")
+        );
+    }
+
+    let Some(faces) = &ply.faces else {
+        eprintln!(
+            "Warning: the file has no faces, the mesh has no indices (the \
+             vertices are a point cloud)."
+        );
+        return Ok(r);
+    };
+    let mut indices = vec![];
+    for face in faces.iter() {
+        if let Some(index) = face.iter().find(|i| **i as usize >= n_vertices) {
+            bail!(
+                "A face uses vertex {}, but there are {} vertices",
+                index,
+                n_vertices
+            );
+        }
+        // Triangulate polygons as fans.
+        for i in 1..face.len().saturating_sub(1) {
+            indices.extend([face[0], face[i], face[i + 1]]);
+        }
+    }
+    r.set_indices_u32(&indices);
+    Ok(r)
+}
+
+/// Convert a color component to 8 bits: integers are 0..=255 (or
+/// 0..=65535 for 16 bits), floats 0.0..=1.0.
+fn unorm8(
+    ty: Scalar,
+    value: f64,
+) -> u8 {
+    let value = match ty {
+        Scalar::F32 | Scalar::F64 => value * 255.0,
+        Scalar::U16 | Scalar::I16 => value / 257.0,
+        _ => value,
+    };
+    value.round().clamp(0.0, 255.0) as u8
+}
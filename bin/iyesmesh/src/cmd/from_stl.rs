@@ -0,0 +1,263 @@
+use iyes_mesh::descriptor::{VertexFormat, VertexUsage};
+use iyes_mesh::mesh::{MeshData, NormalMode, generate_normals, weld};
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{read_input, take_stdout, write_output_file};
+
+#[derive(clap::Args, Debug)]
+pub struct FromStlArgs {
+    /// Merge duplicate vertices into an indexed mesh, optionally with a
+    /// position tolerance
+    ///
+    /// Vertices are merged if all their attributes are identical, except
+    /// for positions, which may differ by up to the given value in every
+    /// axis. With the (flat) facet normals, only vertices of triangles
+    /// facing the same way can be merged, see --gen-normals.
+    #[arg(long, num_args = 0..=1, value_name = "EPSILON")]
+    weld: Option<Option<f32>>,
+    /// Discard the facet normals, and generate smooth normals instead
+    ///
+    /// Done after welding, which is implied (without a tolerance, unless
+    /// --weld gives one), as smooth normals need shared vertices.
+    #[arg(long)]
+    gen_normals: bool,
+    #[command(flatten)]
+    warg: crate::WriteArgs,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    outpath: crate::OutputPath,
+    #[command(flatten)]
+    inpaths: crate::InputPaths,
+}
+
+pub fn run(
+    args_common: &CommonArgs,
+    args_cmd: &FromStlArgs,
+) -> AnyResult<()> {
+    take_stdout(&args_cmd.outpath.out_file);
+    if args_cmd.inpaths.in_files.is_empty() {
+        bail!("No input files provided.");
+    }
+    let weld_epsilon = match (args_cmd.weld, args_cmd.gen_normals) {
+        (Some(epsilon), _) => Some(epsilon),
+        (None, true) => Some(None),
+        (None, false) => None,
+    };
+    let mut new_meshes = vec![];
+    for path in args_cmd.inpaths.in_files.iter() {
+        let bytes = read_input(path).context("Cannot open input STL file")?;
+        let (triangles, kind) = parse_stl(&bytes)
+            .with_context(|| format!("Cannot parse {}", path.display()))?;
+        if args_common.verbose {
+            eprintln!(
+                "{}: {} triangles ({})",
+                path.display(),
+                triangles.len(),
+                kind
+            );
+        }
+        let mut mesh = stl_mesh(&triangles, !args_cmd.gen_normals)?;
+        if let Some(epsilon) = weld_epsilon {
+            let welded = weld(&mesh.as_mesh_ref(), epsilon).with_context(
+                || format!("Cannot weld the mesh of {}", path.display()),
+            )?;
+            infoln!(
+                "{}: {} -> {} vertices",
+                path.display(),
+                mesh.n_vertices(),
+                welded.n_vertices()
+            );
+            mesh = welded;
+        }
+        if args_cmd.gen_normals {
+            let normals =
+                generate_normals(&mesh.as_mesh_ref(), NormalMode::Smooth)
+                    .with_context(|| {
+                        format!(
+                            "Cannot generate normals for {}",
+                            path.display()
+                        )
+                    })?;
+            mesh.set_attribute_bytes(
+                VertexUsage::Normal,
+                VertexFormat::Float32x3,
+                &normals,
+            )?;
+        }
+        new_meshes.push(mesh);
+    }
+
+    let mut writer = IyesMeshWriter::new_with_settings(
+        IyesMeshWriterSettings::from(&args_cmd.warg),
+    );
+    for (mesh, path) in new_meshes.iter().zip(args_cmd.inpaths.in_files.iter())
+    {
+        writer.add_mesh(mesh.as_mesh_ref()).with_context(|| {
+            format!("The mesh of {} is incompatible", path.display())
+        })?;
+    }
+    write_output_file(
+        writer,
+        &args_cmd.outpath.out_file,
+        args_cmd.oarg.overwrite,
+    )
+}
+
+/// A triangle of an STL file: the facet normal, then the vertices.
+type Facet = [[f32; 3]; 4];
+
+/// Parse a binary or ASCII STL file. Also returns which of the two it is.
+///
+/// Binary files may also begin with `solid` (their header is free-form),
+/// so they are recognized by their size matching the triangle count.
+fn parse_stl(bytes: &[u8]) -> AnyResult<(Vec<Facet>, &'static str)> {
+    if bytes.len() >= 84 {
+        let count = u32::from_le_bytes(bytes[80..84].try_into().unwrap());
+        if 84 + 50 * count as u64 == bytes.len() as u64 {
+            return Ok((parse_binary(&bytes[84..]), "binary"));
+        }
+    }
+    if !bytes.trim_ascii_start().starts_with(b"solid") {
+        bail!(
+            "Not an ASCII STL file (no `solid`), and not a binary one (the \
+             size does not match the triangle count)"
+        );
+    }
+    Ok((parse_ascii(bytes)?, "ASCII"))
+}
+
+fn parse_binary(bytes: &[u8]) -> Vec<Facet> {
+    bytes
+        .chunks_exact(50)
+        .map(|record| {
+            // 12 floats, then a 2-byte "attribute byte count" we ignore.
+            std::array::from_fn(|v| {
+                std::array::from_fn(|c| {
+                    let offset = (v * 3 + c) * 4;
+                    let bytes = record[offset..offset + 4].try_into().unwrap();
+                    f32::from_le_bytes(bytes)
+                })
+            })
+        })
+        .collect()
+}
+
+fn parse_ascii(bytes: &[u8]) -> AnyResult<Vec<Facet>> {
+    let text = std::str::from_utf8(bytes).context("Invalid text")?;
+    let mut facets = vec![];
+    let mut current: Option<(Facet, usize)> = None;
+    for (line_index, line) in text.lines().enumerate() {
+        let line_number = line_index + 1;
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        match keyword {
+            "facet" => {
+                if current.is_some() {
+                    bail!("Line {}: `facet` inside a facet", line_number);
+                }
+                if words.next() != Some("normal") {
+                    bail!("Line {}: expected `facet normal`", line_number);
+                }
+                let normal = parse_vec3(&mut words)
+                    .with_context(|| format!("Line {}", line_number))?;
+                current = Some(([normal, [0.0; 3], [0.0; 3], [0.0; 3]], 0));
+            }
+            "vertex" => {
+                let Some((facet, n_vertices)) = current.as_mut() else {
+                    bail!("Line {}: `vertex` outside a facet", line_number);
+                };
+                if *n_vertices == 3 {
+                    bail!(
+                        "Line {}: a facet has more than 3 vertices",
+                        line_number
+                    );
+                }
+                *n_vertices += 1;
+                facet[*n_vertices] = parse_vec3(&mut words)
+                    .with_context(|| format!("Line {}", line_number))?;
+            }
+            "endfacet" => {
+                match current.take() {
+                    Some((facet, 3)) => facets.push(facet),
+                    Some(_) => bail!(
+                        "Line {}: a facet has less than 3 vertices",
+                        line_number
+                    ),
+                    None => bail!("Line {}: `endfacet` alone", line_number),
+                }
+            }
+            "solid" | "endsolid" | "outer" | "endloop" => {}
+            other => {
+                bail!("Line {}: unknown keyword `{}`", line_number, other)
+            }
+        }
+    }
+    if current.is_some() {
+        bail!("The last facet is not finished");
+    }
+    Ok(facets)
+}
+
+fn parse_vec3<'a>(
+    words: &mut impl Iterator<Item = &'a str>,
+) -> AnyResult<[f32; 3]> {
+    let mut r = [0.0; 3];
+    for c in r.iter_mut() {
+        let word = words.next().context("Not enough values")?;
+        *c = word
+            .parse()
+            .with_context(|| format!("Invalid value `{}`", word))?;
+    }
+    Ok(r)
+}
+
+/// The non-indexed IMA mesh of the triangles. If `normals`, the facet
+/// normals become the normals of their vertices. Facets without a normal
+/// (zero, as some exporters write) get the normal of their triangle.
+fn stl_mesh(
+    facets: &[Facet],
+    normals: bool,
+) -> AnyResult<MeshData> {
+    let positions: Vec<[f32; 3]> =
+        facets.iter().flat_map(|f| [f[1], f[2], f[3]]).collect();
+    let mut r = MeshData::new();
+    r.set_positions(&positions)?;
+    if normals {
+        let facet_normals: Vec<[f32; 3]> = facets
+            .iter()
+            .flat_map(|f| {
+                let normal = normalize(f[0])
+                    .or_else(|| normalize(triangle_normal(f[1], f[2], f[3])))
+                    .unwrap_or_default();
+                [normal; 3]
+            })
+            .collect();
+        r.set_normals(&facet_normals)?;
+    }
+    Ok(r)
+}
+
+fn triangle_normal(
+    a: [f32; 3],
+    b: [f32; 3],
+    c: [f32; 3],
+) -> [f32; 3] {
+    let u: [f32; 3] = std::array::from_fn(|i| b[i] - a[i]);
+    let v: [f32; 3] = std::array::from_fn(|i| c[i] - a[i]);
+    [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ]
+}
+
+/// Normalize a vector, if it is not zero (or not finite).
+fn normalize(v: [f32; 3]) -> Option<[f32; 3]> {
+    let length = v.iter().map(|c| c * c).sum::<f32>().sqrt();
+    (length.is_finite() && length > 0.0).then(|| v.map(|c| c / length))
+}
@@ -0,0 +1,205 @@
+use std::io::Seek;
+use std::path::Path;
+
+use iyes_mesh::checksum::{ChecksumKind, ChecksummingReader, DataHasher};
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{
+    MeshSignature, mesh_signatures, named_buffers, open_input, print_table,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct HashArgs {
+    /// Hash algorithm (default: rapidhash)
+    #[arg(long, value_parser = crate::util::parse_checksum_kind)]
+    algo: Option<ChecksumKind>,
+    /// Print the hashes as JSON, for use by scripts
+    ///
+    /// The fields are documented with `JsonHashes` in the source of this
+    /// command. Fields may be added, but are never renamed or removed.
+    #[arg(long)]
+    json: bool,
+    #[command(flatten)]
+    rarg: crate::ReadArgs,
+    #[command(flatten)]
+    inpaths: crate::InputPaths,
+}
+
+/// The hashes of one file.
+struct Hashes {
+    file: u64,
+    payload: u64,
+    user_data: Option<u64>,
+    meshes: Vec<MeshSignature>,
+}
+
+pub fn run(
+    args_common: &CommonArgs,
+    args_cmd: &HashArgs,
+) -> AnyResult<()> {
+    if args_cmd.inpaths.in_files.is_empty() {
+        bail!("No input files provided.");
+    }
+    let kind = args_cmd.algo.unwrap_or(ChecksumKind::RapidHash);
+    let mut json = vec![];
+    for (n, path) in args_cmd.inpaths.in_files.iter().enumerate() {
+        let settings = IyesMeshReaderSettings::from(&args_cmd.rarg);
+        let hashes = hash_file(path, settings, kind)
+            .with_context(|| format!("Cannot hash {}", path.display()))?;
+        if args_cmd.json {
+            json.push(JsonHashes::new(path, kind, &hashes));
+            continue;
+        }
+        if n > 0 {
+            println!();
+        }
+        println!("{}:", path.display());
+        print_hashes(&hashes, kind, args_common.verbose);
+    }
+    if args_cmd.json {
+        println!("{}", serde_json::to_string_pretty(&json)?);
+    }
+    Ok(())
+}
+
+fn hash_file(
+    path: &Path,
+    settings: IyesMeshReaderSettings,
+    kind: ChecksumKind,
+) -> AnyResult<Hashes> {
+    let mut infile =
+        open_input(path).context("Could not open input file")?;
+    let file_size = infile.size()?;
+    let mut reader =
+        ChecksummingReader::with_kind(&mut infile, kind, file_size);
+    std::io::copy(&mut reader, &mut std::io::sink())
+        .context("Could not read input file")?;
+    let file = reader.finish();
+
+    infile.rewind()?;
+    let reader = IyesMeshReader::init_with_settings(settings, &mut infile)
+        .context("Cannot decode file metadata and initialize decoding")?;
+    let with_data =
+        reader.read_all_data().context("Cannot decode file data")?;
+    let buffers =
+        with_data.into_flat_buffers().context("Cannot decode file buffers")?;
+    let named = named_buffers(with_data.descriptor(), &buffers);
+    let len = named.iter().map(|(_, _, data)| data.len() as u64).sum();
+    let mut payload = DataHasher::with_kind(kind, len);
+    for (_, _, data) in named.iter() {
+        payload.update(data);
+    }
+    Ok(Hashes {
+        file,
+        payload: payload.finish(),
+        user_data: buffers.user_data.map(|data| kind.checksum(data)),
+        meshes: mesh_signatures(&with_data, &buffers, kind)
+            .context("Cannot decode file meshes")?,
+    })
+}
+
+/// Hash of a whole mesh: of the names and hashes of its buffers, which
+/// are sorted by name.
+fn mesh_hash(
+    signature: &MeshSignature,
+    kind: ChecksumKind,
+) -> u64 {
+    let mut bytes = vec![];
+    for (name, hash) in signature {
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(&hash.to_le_bytes());
+    }
+    kind.checksum(&bytes)
+}
+
+fn hex(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+/// Print the hashes of a file, with those of the buffers of each mesh if
+/// `verbose`.
+fn print_hashes(
+    hashes: &Hashes,
+    kind: ChecksumKind,
+    verbose: bool,
+) {
+    let mut rows = vec![
+        vec!["file".to_owned(), hex(hashes.file)],
+        vec!["payload".to_owned(), hex(hashes.payload)],
+        vec![
+            "user data".to_owned(),
+            hashes.user_data.map_or("-".to_owned(), hex),
+        ],
+    ];
+    for (i, signature) in hashes.meshes.iter().enumerate() {
+        let hash = mesh_hash(signature, kind);
+        rows.push(vec![format!("mesh {}", i), hex(hash)]);
+        if verbose {
+            for (name, hash) in signature {
+                rows.push(vec![format!("  {}", name), hex(*hash)]);
+            }
+        }
+    }
+    let header = format!("Hash ({})", kind);
+    print_table(&["Contents", &header], &rows, 2);
+}
+
+/// An element of the array printed by `hash --json`, for one input file.
+///
+/// Fields may be added, but are never renamed or removed. Hashes are 16
+/// hex digits.
+#[derive(serde::Serialize)]
+struct JsonHashes {
+    path: String,
+    /// Name of the hash algorithm.
+    algorithm: String,
+    /// Hash of the bytes of the whole file.
+    file: String,
+    /// Hash of the data once decompressed (user data included).
+    payload: String,
+    /// Hash of the user data, as stored (null if there is none).
+    user_data: Option<String>,
+    /// The meshes, in the order they are stored in.
+    meshes: Vec<JsonMesh>,
+}
+
+/// A mesh in [`JsonHashes::meshes`].
+#[derive(serde::Serialize)]
+struct JsonMesh {
+    /// Hash of the names and hashes of `buffers`, sorted by name.
+    hash: String,
+    /// The hash of each buffer of the mesh, by name (like `indices U16`,
+    /// `Position Float32x3`, `instance custom:3 Float32x4`, or `position
+    /// transform`).
+    buffers: std::collections::BTreeMap<String, String>,
+}
+
+impl JsonHashes {
+    fn new(
+        path: &Path,
+        kind: ChecksumKind,
+        hashes: &Hashes,
+    ) -> Self {
+        Self {
+            path: path.display().to_string(),
+            algorithm: kind.to_string(),
+            file: hex(hashes.file),
+            payload: hex(hashes.payload),
+            user_data: hashes.user_data.map(hex),
+            meshes: hashes
+                .meshes
+                .iter()
+                .map(|signature| JsonMesh {
+                    hash: hex(mesh_hash(signature, kind)),
+                    buffers: signature
+                        .iter()
+                        .map(|(name, hash)| (name.clone(), hex(*hash)))
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
@@ -1,11 +1,28 @@
+use iyes_mesh::checksum::ChecksumKind;
+use iyes_mesh::descriptor::{IyesMeshDescriptor, VertexFormat, VertexUsage};
+use iyes_mesh::header::IyesMeshHeader;
 use iyes_mesh::read::IyesMeshReader;
 use iyes_mesh::read::IyesMeshReaderSettings;
+use iyes_mesh::read::probe;
 
 use crate::CommonArgs;
 use crate::prelude::*;
+use crate::util::{compressed_size, open_input, print_table};
 
 #[derive(clap::Args, Debug)]
 pub struct InfoArgs {
+    /// Only print the header, without decoding or verifying the metadata
+    ///
+    /// Works even if the descriptor is corrupt or from an unsupported
+    /// version of the format.
+    #[arg(long)]
+    header_only: bool,
+    /// Print the metadata as JSON, for use by scripts
+    ///
+    /// The fields are documented with `JsonInfo` in the source of this
+    /// command. Fields may be added, but are never renamed or removed.
+    #[arg(long, conflicts_with = "header_only")]
+    json: bool,
     #[command(flatten)]
     rarg: crate::ReadArgs,
     #[command(flatten)]
@@ -13,18 +30,337 @@ pub struct InfoArgs {
 }
 
 pub fn run(
-    _args_common: &CommonArgs,
+    args_common: &CommonArgs,
     args_cmd: &InfoArgs,
 ) -> AnyResult<()> {
-    let mut infile = std::fs::File::open(&args_cmd.inpath.in_file)
+    if args_cmd.header_only {
+        let mut infile = open_input(&args_cmd.inpath.in_file)
+            .context("Could not open input file")?;
+        let info = probe(&mut infile).context("Cannot read file header")?;
+        println!("{:#?}", info);
+        if !info.is_version_supported() {
+            println!("Format version {} is not supported.", info.version);
+        }
+        return Ok(());
+    }
+    let mut infile = open_input(&args_cmd.inpath.in_file)
         .context("Could not open input file")?;
+    let file_size = infile.size()?;
     let reader = IyesMeshReader::init_with_settings(
         IyesMeshReaderSettings::from(&args_cmd.rarg),
         &mut infile,
     )
     .context("Cannot decode file metadata and initialize decoding")?;
 
-    println!("{:#?}", reader.descriptor());
-
+    let descriptor = reader.descriptor();
+    if args_cmd.json {
+        let info = JsonInfo::new(reader.header(), descriptor, file_size);
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        return Ok(());
+    }
+    print_report(
+        reader.header(),
+        descriptor,
+        file_size,
+        args_common.verbose,
+    );
     Ok(())
 }
+
+/// Print the metadata in a human-readable form.
+fn print_report(
+    header: &IyesMeshHeader,
+    descriptor: &IyesMeshDescriptor,
+    file_size: u64,
+    verbose: bool,
+) {
+    if verbose {
+        println!("Format version: {}", { header.version });
+        println!("Descriptor size: {} bytes", { header.descriptor_len });
+        println!("Metadata checksum: {:016x}", { header.metadata_checksum });
+        println!("Data checksum: {:016x}", { header.data_checksum });
+        if let Some(checksum) = descriptor.user_data_checksum {
+            println!("User data checksum: {:016x}", checksum);
+        }
+        println!(
+            "Checksum algorithm: {}",
+            ChecksumKind::name_of_id(descriptor.checksum_kind)
+                .unwrap_or("unknown")
+        );
+        println!("Signed: {}", if descriptor.signed { "yes" } else { "no" });
+        println!();
+    }
+
+    let vertex_stride: u64 =
+        descriptor.attributes.values().map(|f| f.size() as u64).sum();
+    let instance_stride: u64 = descriptor
+        .instance_attributes
+        .values()
+        .map(|f| f.size() as u64)
+        .sum();
+    let index_size =
+        descriptor.indices.map_or(0, |info| info.format.size() as u64);
+    let rows: Vec<_> = descriptor
+        .meshes
+        .iter()
+        .enumerate()
+        .map(|(i, mesh)| {
+            let raw_size = mesh.vertex_count as u64 * vertex_stride
+                + mesh.index_count as u64 * index_size
+                + mesh.instance_count as u64 * instance_stride;
+            vec![
+                i.to_string(),
+                mesh.vertex_count.to_string(),
+                mesh.index_count.to_string(),
+                mesh.instance_count.to_string(),
+                raw_size.to_string(),
+            ]
+        })
+        .collect();
+    println!("Meshes: {}", descriptor.meshes.len());
+    print_table(
+        &["Mesh", "Vertices", "Indices", "Instances", "Raw size"],
+        &rows,
+        0,
+    );
+
+    println!();
+    println!(
+        "Vertices: {} ({} bytes each)",
+        descriptor.n_vertices, vertex_stride
+    );
+    let rows: Vec<_> = descriptor
+        .attribute_order()
+        .into_iter()
+        .map(|usage| {
+            let format = descriptor.attributes[&usage];
+            let size = descriptor.compute_vertex_buf_size(usage).unwrap_or(0);
+            attribute_row(descriptor, usage, format, size as u64)
+        })
+        .collect();
+    print_table(&["Attribute", "Format", "Bytes", "Buffer size"], &rows, 2);
+    if !descriptor.instance_attributes.is_empty() {
+        println!();
+        println!(
+            "Instances: {} ({} bytes each)",
+            descriptor.n_instances, instance_stride
+        );
+        let rows: Vec<_> = descriptor
+            .instance_attribute_order()
+            .into_iter()
+            .map(|usage| {
+                let format = descriptor.instance_attributes[&usage];
+                let size =
+                    descriptor.compute_instance_buf_size(usage).unwrap_or(0);
+                attribute_row(descriptor, usage, format, size as u64)
+            })
+            .collect();
+        print_table(&["Attribute", "Format", "Bytes", "Buffer size"], &rows, 2);
+    }
+
+    println!();
+    match descriptor.indices {
+        Some(info) => println!(
+            "Index buffer: {} bytes ({} indices, {:?})",
+            descriptor.compute_index_buf_size().unwrap_or(0),
+            info.n_indices,
+            info.format
+        ),
+        None => println!("Index buffer: none"),
+    }
+    println!(
+        "User data: {} bytes{}",
+        descriptor.user_data_len,
+        if descriptor.user_data_nonce.is_some() {
+            " (encrypted)"
+        } else {
+            ""
+        }
+    );
+    println!(
+        "Total: {} bytes raw, {} bytes compressed, {} bytes file",
+        descriptor.compute_total_raw_data_size(),
+        compressed_size(header, descriptor, file_size),
+        file_size
+    );
+}
+
+/// A row of the attribute tables: usage, format (with notes), size of one
+/// element, and size of the buffer.
+fn attribute_row(
+    descriptor: &IyesMeshDescriptor,
+    usage: VertexUsage,
+    format: VertexFormat,
+    buffer_size: u64,
+) -> Vec<String> {
+    let mut notes = vec![];
+    if let Some(encoding) = descriptor.attribute_encodings.get(&usage) {
+        notes.push(format!("{:?}", encoding));
+    }
+    if let Some(color_space) = descriptor.color_spaces.get(&usage) {
+        notes.push(format!("{:?}", color_space));
+    }
+    if usage == VertexUsage::Position
+        && descriptor.meshes.iter().any(|m| m.position_transform.is_some())
+    {
+        notes.push("quantized".to_owned());
+    }
+    let mut format_text = format!("{:?}", format);
+    if !notes.is_empty() {
+        format_text = format!("{} ({})", format_text, notes.join(", "));
+    }
+    vec![
+        usage.to_string(),
+        format_text,
+        format.size().to_string(),
+        buffer_size.to_string(),
+    ]
+}
+
+/// The document printed by `info --json`.
+///
+/// Fields may be added, but are never renamed or removed. Formats are the
+/// names of the `VertexFormat` variants, and usages are formatted like on
+/// the command line (`Position`, `custom:7`, ...).
+#[derive(serde::Serialize)]
+struct JsonInfo {
+    /// Version of the file format.
+    format_version: u16,
+    /// Size of the whole file, in bytes.
+    file_size: u64,
+    /// Size of the compressed data, in bytes.
+    compressed_size: u64,
+    /// Size of the data once decompressed, in bytes (user data included).
+    uncompressed_size: u64,
+    has_data_checksum: bool,
+    has_user_data_checksum: bool,
+    /// Name of the algorithm of the data checksums (`unknown` if this
+    /// build does not know it).
+    checksum_algorithm: &'static str,
+    signed: bool,
+    /// Length of the user data as stored (including the authentication tag,
+    /// if encrypted).
+    user_data_len: u32,
+    user_data_encrypted: bool,
+    /// Total number of vertices, over all meshes.
+    vertex_count: u32,
+    /// Total number of indices, over all meshes (null if not indexed).
+    index_count: Option<u32>,
+    /// `U16` or `U32` (null if not indexed).
+    index_format: Option<String>,
+    /// Size of the index buffer, in bytes (null if not indexed).
+    index_buffer_size: Option<u64>,
+    /// Number of elements in each instance attribute buffer.
+    instance_count: u32,
+    meshes: Vec<JsonMesh>,
+    /// Vertex attributes, in the order they are stored in.
+    attributes: Vec<JsonAttribute>,
+    /// Instance attributes, in the order they are stored in.
+    instance_attributes: Vec<JsonAttribute>,
+}
+
+/// A mesh in [`JsonInfo::meshes`].
+#[derive(serde::Serialize)]
+struct JsonMesh {
+    first_vertex: u32,
+    vertex_count: u32,
+    /// First index of the mesh (zero if not indexed).
+    first_index: u32,
+    /// Number of indices of the mesh (zero if not indexed).
+    index_count: u32,
+    first_instance: u32,
+    instance_count: u32,
+    /// Whether the positions are quantized relative to the mesh bounds.
+    quantized_positions: bool,
+}
+
+/// An attribute in [`JsonInfo::attributes`].
+#[derive(serde::Serialize)]
+struct JsonAttribute {
+    usage: String,
+    /// Format of the stored data.
+    format: String,
+    /// Special encoding of the data (like `Octahedral`), if any.
+    encoding: Option<String>,
+    /// `Linear` or `Srgb`, if specified.
+    color_space: Option<String>,
+    /// Size of the buffer, in bytes.
+    buffer_size: u64,
+}
+
+impl JsonInfo {
+    fn new(
+        header: &IyesMeshHeader,
+        descriptor: &IyesMeshDescriptor,
+        file_size: u64,
+    ) -> Self {
+        let attribute = |usage: VertexUsage, format: VertexFormat, n: u32| {
+            JsonAttribute {
+                usage: usage.to_string(),
+                format: format!("{:?}", format),
+                encoding: descriptor
+                    .attribute_encodings
+                    .get(&usage)
+                    .map(|encoding| format!("{:?}", encoding)),
+                color_space: descriptor
+                    .color_spaces
+                    .get(&usage)
+                    .map(|color_space| format!("{:?}", color_space)),
+                buffer_size: format.size() as u64 * n as u64,
+            }
+        };
+        Self {
+            format_version: header.version,
+            file_size,
+            compressed_size: compressed_size(header, descriptor, file_size),
+            uncompressed_size: descriptor.compute_total_raw_data_size(),
+            has_data_checksum: header.data_checksum != 0,
+            has_user_data_checksum: descriptor.user_data_checksum.is_some(),
+            checksum_algorithm: ChecksumKind::name_of_id(
+                descriptor.checksum_kind,
+            )
+            .unwrap_or("unknown"),
+            signed: descriptor.signed,
+            user_data_len: descriptor.user_data_len,
+            user_data_encrypted: descriptor.user_data_nonce.is_some(),
+            vertex_count: descriptor.n_vertices,
+            index_count: descriptor.indices.map(|info| info.n_indices),
+            index_format: descriptor
+                .indices
+                .map(|info| format!("{:?}", info.format)),
+            index_buffer_size: descriptor
+                .compute_index_buf_size()
+                .map(|size| size as u64),
+            instance_count: descriptor.n_instances,
+            meshes: descriptor
+                .meshes
+                .iter()
+                .map(|mesh| JsonMesh {
+                    first_vertex: mesh.first_vertex,
+                    vertex_count: mesh.vertex_count,
+                    first_index: mesh.first_index,
+                    index_count: mesh.index_count,
+                    first_instance: mesh.first_instance,
+                    instance_count: mesh.instance_count,
+                    quantized_positions: mesh.position_transform.is_some(),
+                })
+                .collect(),
+            attributes: descriptor
+                .attribute_order()
+                .into_iter()
+                .map(|usage| {
+                    let format = descriptor.attributes[&usage];
+                    attribute(usage, format, descriptor.n_vertices)
+                })
+                .collect(),
+            instance_attributes: descriptor
+                .instance_attribute_order()
+                .into_iter()
+                .map(|usage| {
+                    let format = descriptor.instance_attributes[&usage];
+                    attribute(usage, format, descriptor.n_instances)
+                })
+                .collect(),
+        }
+    }
+}
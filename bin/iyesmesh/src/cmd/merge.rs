@@ -1,11 +1,20 @@
-use std::io::BufWriter;
-
-use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
+use iyes_mesh::HashMap;
+use iyes_mesh::checksum::ChecksumKind;
+use iyes_mesh::descriptor::{ColorSpace, VertexFormat, VertexUsage};
+use iyes_mesh::mesh::{MeshDataRef, concatenate};
+use iyes_mesh::read::{
+    IyesMeshReader, IyesMeshReaderSettings, IyesMeshReaderWithData,
+};
 use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
 
 use crate::CommonArgs;
+use crate::batch::map_ordered;
 use crate::prelude::*;
-use crate::util::load_user_data;
+use crate::util::{
+    MeshSignature, decode_special_attributes, load_user_data, mesh_signatures,
+    open_input, print_table, print_write_plan, take_stdout, with_decoded,
+    write_output_file,
+};
 
 #[derive(clap::Args, Debug)]
 pub struct MergeArgs {
@@ -15,9 +24,51 @@ pub struct MergeArgs {
     /// If the file is not an IMA file, use its raw contents as-is.
     #[arg(short, long)]
     user_data: Option<Option<PathBuf>>,
-    /// If a user data file is provided, do not try to parse it as an IMA file
+    /// Do not try to parse the user data as an IMA file
     #[arg(long)]
     user_data_force_raw: bool,
+    /// Combine all meshes into a single mesh
+    ///
+    /// All meshes must have the same vertex attributes (see `edit
+    /// --keep-attr`). The mesh boundaries are not preserved (see
+    /// --concat-manifest), and the instances are dropped.
+    #[arg(long)]
+    concat: bool,
+    /// Store where each mesh ended up in the combined mesh as the user
+    /// data, in JSON
+    ///
+    /// The fields are documented with `JsonManifest` in the source of this
+    /// command. Fields may be added, but are never renamed or removed.
+    #[arg(long, requires = "concat", conflicts_with = "user_data")]
+    concat_manifest: bool,
+    /// Only take these meshes from an input file, in this order (like
+    /// `a.ima:0,2`; can be repeated)
+    ///
+    /// Meshes are identified by their index, they have no names. Input
+    /// files without a selection are used whole.
+    #[arg(long, value_name = "FILE:MESHES", value_parser = parse_selection)]
+    select: Vec<(PathBuf, Vec<usize>)>,
+    /// Skip meshes identical to one already added
+    ///
+    /// Meshes are identical if they have the same indices, attributes,
+    /// instances and position quantization.
+    #[arg(long)]
+    dedup: bool,
+    /// Print which output mesh each input mesh ended up as
+    #[arg(long, requires = "dedup", conflicts_with = "concat")]
+    dedup_report: bool,
+    /// Print info about the output file, without writing anything
+    #[arg(long)]
+    dry_run: bool,
+    /// Copy the compressed data of the inputs instead of recompressing it
+    ///
+    /// This needs the inputs to store each buffer as a separate zstd frame,
+    /// which the format does not support yet. Until then, this warns and
+    /// merges normally.
+    #[arg(long)]
+    fast: bool,
+    #[command(flatten)]
+    jobs: crate::JobsArgs,
     #[command(flatten)]
     rarg: crate::ReadArgs,
     #[command(flatten)]
@@ -34,6 +85,7 @@ pub fn run(
     _args_common: &CommonArgs,
     args_cmd: &MergeArgs,
 ) -> AnyResult<()> {
+    take_stdout(&args_cmd.outpath.out_file);
     if args_cmd.inpaths.in_files.is_empty() {
         bail!("No input files provided.");
     }
@@ -41,34 +93,52 @@ pub fn run(
         IyesMeshWriterSettings::from(&args_cmd.warg),
     );
     let new_user_data;
-    match &args_cmd.user_data {
-        Some(src) => {
-            new_user_data = load_user_data(
-                src.as_deref(),
-                IyesMeshReaderSettings::from(&args_cmd.rarg),
-                args_cmd.user_data_force_raw,
-            )?;
-            writer.set_user_data(&new_user_data);
+    if let Some(src) = &args_cmd.user_data {
+        new_user_data = load_user_data(
+            src.as_deref(),
+            IyesMeshReaderSettings::from(&args_cmd.rarg),
+            args_cmd.user_data_force_raw,
+        )?;
+        writer.set_user_data(&new_user_data);
+    }
+
+    for (path, _) in args_cmd.select.iter() {
+        if !args_cmd.inpaths.in_files.contains(path) {
+            bail!("--select {}: not an input file", path.display());
         }
-        None => {}
     }
 
     let mut in_data = vec![];
+    let mut in_selected = vec![];
     let mut in_parsed = vec![];
+    let mut in_decoded = vec![];
 
-    for inpath in args_cmd.inpaths.in_files.iter() {
-        let mut infile =
-            std::fs::File::open(inpath).context("Could not open input file")?;
-        let reader = IyesMeshReader::init_with_settings(
-            IyesMeshReaderSettings::from(&args_cmd.rarg),
-            &mut infile,
-        )
-        .context("Cannot decode file metadata and initialize decoding")?;
-        let with_data =
-            reader.read_all_data().context("Cannot decode file data")?;
+    let mut read = vec![];
+    map_ordered(
+        &args_cmd.inpaths.in_files,
+        args_cmd.jobs.get(),
+        |inpath| read_input(args_cmd, inpath),
+        |_, result| read.push(result),
+    );
+    for result in read {
+        let (with_data, selected) = result?;
         in_data.push(with_data);
+        in_selected.push(selected);
+    }
+    if args_cmd.fast {
+        eprintln!(
+            "Warning: --fast: the inputs do not store their buffers as \
+             separate frames, recompressing them."
+        );
+    }
+
+    let color_spaces =
+        merge_color_spaces(&in_data, &args_cmd.inpaths.in_files)?;
+    for (usage, color_space) in color_spaces {
+        writer.set_color_space(usage, color_space);
     }
 
+    let mut in_signatures = vec![];
     for with_data in in_data.iter() {
         let flatbufs = with_data
             .into_flat_buffers()
@@ -76,24 +146,395 @@ pub fn run(
         let meshes = with_data
             .into_split_meshes(&flatbufs)
             .context("Cannot decode file meshes")?;
+        if args_cmd.dedup {
+            in_signatures.push(
+                mesh_signatures(with_data, &flatbufs, ChecksumKind::RapidHash)
+                    .context("Cannot decode file meshes")?,
+            );
+        }
+        in_decoded.push(decode_special_attributes(with_data, &meshes));
         in_parsed.push(meshes);
     }
 
-    for src in in_parsed.iter() {
-        for m in src.meshes.iter() {
-            writer.add_mesh(m.clone()).context("Cannot use mesh for output")?;
+    if args_cmd.dedup {
+        let n_before: usize = in_selected.iter().map(Vec::len).sum();
+        let rows = dedup(&mut in_selected, &in_signatures);
+        let n_after: usize = in_selected.iter().map(Vec::len).sum();
+        infoln!("Skipped {} duplicate meshes.", n_before - n_after);
+        if args_cmd.dedup_report {
+            let rows: Vec<_> = rows
+                .into_iter()
+                .map(|(input, mesh, out_index, duplicate)| {
+                    vec![
+                        args_cmd.inpaths.in_files[input].display().to_string(),
+                        mesh.to_string(),
+                        out_index.to_string(),
+                        if duplicate { "yes" } else { "no" }.to_owned(),
+                    ]
+                })
+                .collect();
+            print_table(
+                &["Input", "Mesh", "Output mesh", "Duplicate"],
+                &rows,
+                1,
+            );
+        }
+    }
+
+    if args_cmd.concat {
+        let meshes: Vec<_> = in_parsed
+            .iter()
+            .zip(in_decoded.iter())
+            .zip(in_selected.iter())
+            .flat_map(|((src, decoded), selected)| {
+                selected
+                    .iter()
+                    .map(|&i| with_decoded(&src.meshes[i], &decoded[i]))
+            })
+            .collect();
+        if in_data.iter().any(|d| d.descriptor().n_instances > 0) {
+            eprintln!(
+                "Warning: the instances of the input meshes are dropped, the \
+                 combined mesh cannot keep them."
+            );
+        }
+        let combined =
+            concatenate(&meshes).context("Cannot concatenate meshes")?;
+        writer
+            .add_mesh(combined.as_mesh_ref())
+            .context("Cannot use mesh for output")?;
+        let manifest;
+        if args_cmd.concat_manifest {
+            let sources = in_selected.iter().enumerate().flat_map(|(n, s)| {
+                s.iter().map(move |&i| (&args_cmd.inpaths.in_files[n], i))
+            });
+            manifest =
+                serde_json::to_vec(&JsonManifest::new(sources, &meshes))?;
+            writer.set_user_data(&manifest);
+        }
+        return finish(writer, args_cmd);
+    }
+
+    let (instance_data, first_instances) =
+        merge_instances(&in_data, &args_cmd.inpaths.in_files)?;
+    let n_instances = first_instances.last().copied().unwrap_or(0);
+    for (usage, (format, bytes)) in instance_data.iter() {
+        writer
+            .set_instance_data(*usage, *format, bytes, n_instances)
+            .context("Cannot use instance data for output")?;
+    }
+
+
+    let mut n_rejected = 0;
+    for (((src, decoded), selected), inpath) in in_parsed
+        .iter()
+        .zip(in_decoded.iter())
+        .zip(in_selected.iter())
+        .zip(args_cmd.inpaths.in_files.iter())
+    {
+        let meshes = selected
+            .iter()
+            .map(|&i| with_decoded(&src.meshes[i], &decoded[i]));
+        if let Err(report) = writer.add_meshes(meshes) {
+            for rejected in report.rejected.iter() {
+                eprintln!(
+                    "{}: mesh {} is incompatible:",
+                    inpath.display(),
+                    selected[rejected.index]
+                );
+                for problem in rejected.problems.iter() {
+                    eprintln!("  - {}", problem);
+                }
+            }
+            n_rejected += report.rejected.len();
+        }
+    }
+    if n_rejected > 0 {
+        bail!("{} meshes cannot be used for output.", n_rejected);
+    }
+    let mut out_index = 0;
+    for ((with_data, selected), first_instance) in in_data
+        .iter()
+        .zip(in_selected.iter())
+        .zip(first_instances)
+    {
+        for &i in selected.iter() {
+            let info = &with_data.descriptor().meshes[i];
+            let start = first_instance + info.first_instance;
+            let range = start..start + info.instance_count;
+            writer
+                .set_mesh_instances(out_index, range)
+                .context("Cannot set instance range")?;
+            out_index += 1;
+        }
+    }
+
+    finish(writer, args_cmd)
+}
+
+/// Read and decompress an input file, and find which of its meshes to
+/// take. Done for several files at the same time (see --jobs).
+fn read_input(
+    args_cmd: &MergeArgs,
+    inpath: &Path,
+) -> AnyResult<(IyesMeshReaderWithData, Vec<usize>)> {
+    let mut infile = open_input(inpath).with_context(|| {
+        format!("Could not open input file {}", inpath.display())
+    })?;
+    let reader = IyesMeshReader::init_with_settings(
+        IyesMeshReaderSettings::from(&args_cmd.rarg),
+        &mut infile,
+    )
+    .with_context(|| {
+        format!(
+            "Cannot decode metadata of {} and initialize decoding",
+            inpath.display()
+        )
+    })?;
+    let selected = selected_meshes(
+        &args_cmd.select,
+        inpath,
+        reader.descriptor().meshes.len(),
+    )?;
+    let with_data = reader.read_all_data().with_context(|| {
+        format!("Cannot decode data of {}", inpath.display())
+    })?;
+    Ok((with_data, selected))
+}
+
+/// The user data written by `merge --concat-manifest`.
+///
+/// Fields may be added, but are never renamed or removed.
+#[derive(serde::Serialize)]
+struct JsonManifest {
+    /// The input meshes, in the order they are in the combined mesh.
+    meshes: Vec<JsonManifestMesh>,
+}
+
+/// A mesh in [`JsonManifest::meshes`].
+#[derive(serde::Serialize)]
+struct JsonManifestMesh {
+    /// Path of the input file, as given on the command line.
+    input: String,
+    /// Index of the mesh in the input file.
+    mesh: usize,
+    /// Range of the vertices of the mesh in the combined mesh.
+    first_vertex: usize,
+    vertex_count: usize,
+    /// Range of the indices of the mesh in the combined mesh (if it has
+    /// indices). The indices are already offset by `first_vertex`.
+    first_index: usize,
+    index_count: usize,
+}
+
+impl JsonManifest {
+    fn new<'a>(
+        sources: impl Iterator<Item = (&'a PathBuf, usize)>,
+        meshes: &[MeshDataRef<'_>],
+    ) -> Self {
+        let mut first_vertex = 0;
+        let mut first_index = 0;
+        let meshes = sources
+            .zip(meshes)
+            .map(|((path, index), mesh)| {
+                let vertex_count = mesh.n_vertices();
+                let index_count =
+                    mesh.iter_indices().map_or(vertex_count, |i| i.count());
+                let r = JsonManifestMesh {
+                    input: path.display().to_string(),
+                    mesh: index,
+                    first_vertex,
+                    vertex_count,
+                    first_index,
+                    index_count,
+                };
+                first_vertex += vertex_count;
+                first_index += index_count;
+                r
+            })
+            .collect();
+        Self { meshes }
+    }
+}
+
+/// Parse a mesh selection like `a.ima:0,2`.
+///
+/// The path may contain `:`, the selection is after the last one.
+fn parse_selection(s: &str) -> Result<(PathBuf, Vec<usize>), String> {
+    let (path, meshes) = s
+        .rsplit_once(':')
+        .ok_or_else(|| "expected <FILE>:<MESHES>".to_owned())?;
+    if path.is_empty() {
+        return Err("expected <FILE>:<MESHES>".to_owned());
+    }
+    let meshes = meshes
+        .split(',')
+        .map(crate::util::parse_mesh_index)
+        .collect::<Result<_, _>>()?;
+    Ok((path.into(), meshes))
+}
+
+/// The indices of the meshes to take from the input file at `inpath`,
+/// which has `n_meshes` meshes.
+fn selected_meshes(
+    select: &[(PathBuf, Vec<usize>)],
+    inpath: &Path,
+    n_meshes: usize,
+) -> AnyResult<Vec<usize>> {
+    let mut selections = select.iter().filter(|(path, _)| path == inpath);
+    let Some((_, selected)) = selections.next() else {
+        return Ok((0..n_meshes).collect());
+    };
+    if selections.next().is_some() {
+        bail!("{} is selected from more than once", inpath.display());
+    }
+    for (n, &index) in selected.iter().enumerate() {
+        if index >= n_meshes {
+            bail!(
+                "{}: mesh {} does not exist (the file has {} meshes)",
+                inpath.display(),
+                index,
+                n_meshes
+            );
+        }
+        if selected[..n].contains(&index) {
+            bail!(
+                "{}: mesh {} is selected more than once",
+                inpath.display(),
+                index
+            );
         }
     }
+    Ok(selected.clone())
+}
 
-    let outfile = if args_cmd.oarg.overwrite {
-        std::fs::File::create(&args_cmd.outpath.out_file)
-            .context("Could not open output file")?
-    } else {
-        std::fs::File::create_new(&args_cmd.outpath.out_file)
-            .context("Could not open output file")?
+/// Remove the meshes with the same signature as an earlier one from the
+/// meshes to take from each input.
+///
+/// Returns, for each mesh taken from the inputs: the input, the mesh, the
+/// index of the output mesh it is (or is a duplicate of), and whether it
+/// is a duplicate.
+fn dedup(
+    in_selected: &mut [Vec<usize>],
+    in_signatures: &[Vec<MeshSignature>],
+) -> Vec<(usize, usize, usize, bool)> {
+    let mut kept: HashMap<&MeshSignature, usize> = HashMap::default();
+    let mut rows = vec![];
+    for (input, (selected, signatures)) in
+        in_selected.iter_mut().zip(in_signatures).enumerate()
+    {
+        selected.retain(|&mesh| {
+            let n_kept = kept.len();
+            let out_index = *kept.entry(&signatures[mesh]).or_insert(n_kept);
+            let duplicate = out_index != n_kept;
+            rows.push((input, mesh, out_index, duplicate));
+            !duplicate
+        });
+    }
+    rows
+}
+
+/// The color space tags of the output, if the inputs agree.
+///
+/// Inputs that have a tagged attribute without the tag are assumed to use
+/// the same color space (with a warning).
+fn merge_color_spaces(
+    in_data: &[IyesMeshReaderWithData],
+    in_files: &[PathBuf],
+) -> AnyResult<HashMap<VertexUsage, ColorSpace>> {
+    let mut r: HashMap<VertexUsage, (ColorSpace, &PathBuf)> =
+        HashMap::default();
+    for (with_data, inpath) in in_data.iter().zip(in_files.iter()) {
+        for (usage, color_space) in with_data.descriptor().color_spaces.iter()
+        {
+            let (existing, existing_path) =
+                *r.entry(*usage).or_insert((*color_space, inpath));
+            if existing != *color_space {
+                bail!(
+                    "Attribute {} is {:?} in {}, but {:?} in {}.",
+                    usage,
+                    existing,
+                    existing_path.display(),
+                    color_space,
+                    inpath.display(),
+                );
+            }
+        }
+    }
+    for (with_data, inpath) in in_data.iter().zip(in_files.iter()) {
+        let descriptor = with_data.descriptor();
+        for (usage, (color_space, _)) in r.iter() {
+            if descriptor.attributes.contains_key(usage)
+                && !descriptor.color_spaces.contains_key(usage)
+            {
+                eprintln!(
+                    "Warning: {}: attribute {} has no color space, assuming \
+                     {:?}",
+                    inpath.display(),
+                    usage,
+                    color_space,
+                );
+            }
+        }
+    }
+    Ok(r.into_iter().map(|(usage, (cs, _))| (usage, cs)).collect())
+}
+
+type InstanceData = HashMap<VertexUsage, (VertexFormat, Vec<u8>)>;
+
+/// The per-instance attributes of the output, and the first instance of
+/// each input in them.
+///
+/// The instances of all inputs are appended in order, so they must all
+/// have the same instance attributes. The returned list has an extra
+/// entry at the end, with the total number of instances.
+fn merge_instances(
+    in_data: &[IyesMeshReaderWithData],
+    in_files: &[PathBuf],
+) -> AnyResult<(InstanceData, Vec<u32>)> {
+    let mut r = InstanceData::default();
+    let mut first_instances = vec![0];
+    let Some(first) = in_data.first() else {
+        return Ok((r, first_instances));
     };
-    let mut bufout = BufWriter::new(outfile);
-    writer.write_to(&mut bufout).context("Cannot encode output file")?;
+    let formats = &first.descriptor().instance_attributes;
+    for (with_data, inpath) in in_data.iter().zip(in_files.iter()) {
+        let descriptor = with_data.descriptor();
+        if descriptor.instance_attributes != *formats {
+            bail!(
+                "{} has different instance attributes than {}.",
+                inpath.display(),
+                in_files[0].display(),
+            );
+        }
+        let flatbufs = with_data
+            .into_flat_buffers()
+            .context("Cannot decode file buffers")?;
+        for (usage, (format, bytes)) in flatbufs.buf_instances.iter() {
+            r.entry(*usage)
+                .or_insert((*format, vec![]))
+                .1
+                .extend_from_slice(bytes);
+        }
+        let n = first_instances.last().unwrap() + descriptor.n_instances;
+        first_instances.push(n);
+    }
+    Ok((r, first_instances))
+}
+
+fn finish(
+    writer: IyesMeshWriter<'_>,
+    args_cmd: &MergeArgs,
+) -> AnyResult<()> {
+    if args_cmd.dry_run {
+        let plan = writer.plan().context("Cannot use meshes for output")?;
+        print_write_plan(&plan);
+        return Ok(());
+    }
 
-    Ok(())
+    write_output_file(
+        writer,
+        &args_cmd.outpath.out_file,
+        args_cmd.oarg.overwrite,
+    )
 }
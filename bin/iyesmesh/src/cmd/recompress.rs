@@ -0,0 +1,140 @@
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+
+use crate::CommonArgs;
+use crate::batch::{find_inputs, run_each};
+use crate::prelude::*;
+use crate::util::{
+    FileSizes, copy_color_spaces, copy_instance_data, copy_mesh_instances,
+    copy_user_data, decode_special_attributes, format_size_change, is_signing,
+    keep_encodings, open_input, print_size_change, take_stdout, with_decoded,
+    write_output_file_sizes,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct RecompressArgs {
+    /// Sign the output file with this Ed25519 private key
+    ///
+    /// The signature of the input file cannot be kept, so the output is
+    /// not signed otherwise.
+    #[cfg(feature = "signing")]
+    #[arg(long, value_name = "KEY_FILE")]
+    sign: Option<PathBuf>,
+    /// Recompress the `.ima` files in the input directory and its
+    /// subdirectories (in place, unless --out-dir is given)
+    ///
+    /// Failures are reported at the end, instead of stopping at the first.
+    #[arg(short, long, conflicts_with = "out_file")]
+    recursive: bool,
+    /// With --recursive, write the files to this directory instead,
+    /// mirroring the input tree
+    #[arg(long, value_name = "DIR", requires = "recursive")]
+    out_dir: Option<PathBuf>,
+    #[command(flatten)]
+    jobs: crate::JobsArgs,
+    #[command(flatten)]
+    rarg: crate::ReadArgs,
+    #[command(flatten)]
+    warg: crate::WriteArgs,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    paths: crate::InOutPaths,
+}
+
+pub fn run(
+    args_common: &CommonArgs,
+    args_cmd: &RecompressArgs,
+) -> AnyResult<()> {
+    if !args_cmd.recursive {
+        take_stdout(args_cmd.paths.output()?);
+        let (before, after) = recompress(
+            args_cmd,
+            &args_cmd.paths.in_file,
+            args_cmd.paths.out_file.as_deref(),
+        )?;
+        print_size_change(&before, &after);
+        return Ok(());
+    }
+    let (inputs, failures) = find_inputs(
+        std::slice::from_ref(&args_cmd.paths.in_file),
+        &["ima"],
+        args_common.verbose,
+    );
+    run_each(&inputs, failures, args_cmd.jobs.get(), |input| {
+        let out_file = match &args_cmd.out_dir {
+            Some(dir) => Some(input.output_path(dir, "ima")?),
+            None => None,
+        };
+        let (before, after) =
+            recompress(args_cmd, &input.path, out_file.as_deref())?;
+        let header = match &out_file {
+            Some(out_file) => {
+                format!("{} -> {}", input.path.display(), out_file.display())
+            }
+            None => input.path.display().to_string(),
+        };
+        Ok(format!("{}:\n{}", header, format_size_change(&before, &after)))
+    })
+}
+
+/// Recompress a file, to `out_file`, or in place. Returns the sizes before
+/// and after.
+fn recompress(
+    args_cmd: &RecompressArgs,
+    in_file: &Path,
+    out_file: Option<&Path>,
+) -> AnyResult<(FileSizes, FileSizes)> {
+    let read_settings = IyesMeshReaderSettings::from(&args_cmd.rarg);
+    let mut infile =
+        open_input(in_file).context("Could not open input file")?;
+    let file_size = infile.size()?;
+    let reader = IyesMeshReader::init_with_settings(read_settings, &mut infile)
+        .context("Cannot decode file metadata and initialize decoding")?;
+    let before = FileSizes::new(&reader, file_size);
+    let with_data =
+        reader.read_all_data().context("Cannot decode file data")?;
+    let flatbufs =
+        with_data.into_flat_buffers().context("Cannot decode file buffers")?;
+    let meshes = with_data
+        .into_split_meshes(&flatbufs)
+        .context("Cannot decode file meshes")?;
+    let decoded = decode_special_attributes(&with_data, &meshes);
+
+    let descriptor = with_data.descriptor();
+    let mut settings = IyesMeshWriterSettings {
+        #[cfg(feature = "signing")]
+        signing_key: args_cmd
+            .sign
+            .as_deref()
+            .map(crate::util::load_signing_key)
+            .transpose()?,
+        ..IyesMeshWriterSettings::from(&args_cmd.warg)
+    };
+    keep_encodings(&mut settings, descriptor);
+    if descriptor.signed && !is_signing(&settings) {
+        eprintln!(
+            "Warning! {} is signed, but the output will not be (see --sign).",
+            in_file.display()
+        );
+    }
+
+    let mut writer = IyesMeshWriter::new_with_settings(settings);
+    copy_color_spaces(&mut writer, &with_data);
+    copy_user_data(&mut writer, &with_data, &flatbufs);
+    copy_instance_data(&mut writer, &with_data, &flatbufs)?;
+    for (i, mesh) in meshes.meshes.iter().enumerate() {
+        writer
+            .add_mesh(with_decoded(mesh, &decoded[i]))
+            .context("Cannot use mesh for output")?;
+        copy_mesh_instances(&mut writer, &with_data, i, i)?;
+    }
+
+    let after = write_output_file_sizes(
+        writer,
+        out_file.unwrap_or(in_file),
+        args_cmd.oarg.overwrite || out_file.is_none(),
+        read_settings,
+    )?;
+    Ok((before, after))
+}
@@ -0,0 +1,89 @@
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{
+    copy_color_spaces, copy_instance_data, copy_mesh_instances, copy_user_data,
+    decode_special_attributes, open_input, take_stdout, with_decoded,
+    write_output_file,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct RecoverArgs {
+    #[command(flatten)]
+    rarg: crate::ReadArgs,
+    #[command(flatten)]
+    warg: crate::WriteArgs,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    inpath: crate::InputPath,
+    #[command(flatten)]
+    outpath: crate::OutputPath,
+}
+
+pub fn run(
+    _args_common: &CommonArgs,
+    args_cmd: &RecoverArgs,
+) -> AnyResult<()> {
+    take_stdout(&args_cmd.outpath.out_file);
+    let mut infile = open_input(&args_cmd.inpath.in_file)
+        .context("Could not open input file")?;
+    let reader = IyesMeshReader::init_with_settings(
+        IyesMeshReaderSettings::from(&args_cmd.rarg),
+        &mut infile,
+    )
+    .context("Cannot decode file metadata and initialize decoding")?;
+    let (with_data, recovery) =
+        reader.recover_data().context("Cannot decode file data")?;
+    if let Some(e) = &recovery.error {
+        infoln!("Decompression failed: {}", e);
+    }
+    infoln!(
+        "Recovered {} of {} bytes of data.",
+        recovery.recovered_len, recovery.total_len
+    );
+    let flatbufs =
+        with_data.into_flat_buffers().context("Cannot decode file buffers")?;
+    let meshes = with_data
+        .into_split_meshes(&flatbufs)
+        .context("Cannot decode file meshes")?;
+    let decoded = decode_special_attributes(&with_data, &meshes);
+
+    let mut writer = IyesMeshWriter::new_with_settings(
+        IyesMeshWriterSettings::from(&args_cmd.warg),
+    );
+    copy_color_spaces(&mut writer, &with_data);
+    if recovery.user_data {
+        copy_user_data(&mut writer, &with_data, &flatbufs);
+    } else {
+        infoln!("User data: lost");
+    }
+    copy_instance_data(&mut writer, &with_data, &flatbufs)?;
+    let mut n_salvaged = 0;
+    for (i, lost) in recovery.lost.iter().enumerate() {
+        if !lost.is_empty() {
+            infoln!("Mesh {}: lost {:?}", i, lost);
+            continue;
+        }
+        writer
+            .add_mesh(with_decoded(&meshes.meshes[i], &decoded[i]))
+            .with_context(|| format!("Cannot add mesh {i}"))?;
+        copy_mesh_instances(&mut writer, &with_data, n_salvaged, i)?;
+        n_salvaged += 1;
+    }
+    infoln!(
+        "Salvaged {} of {} meshes.",
+        n_salvaged,
+        recovery.lost.len()
+    );
+    if n_salvaged == 0 {
+        bail!("No mesh could be recovered.");
+    }
+    write_output_file(
+        writer,
+        &args_cmd.outpath.out_file,
+        args_cmd.oarg.overwrite,
+    )
+}
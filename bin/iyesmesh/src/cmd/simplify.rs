@@ -0,0 +1,119 @@
+use iyes_mesh::meshopt::{SimplifySettings, simplify};
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{
+    copy_color_spaces, copy_instance_data, copy_mesh_instances, copy_user_data,
+    decode_special_attributes, open_input, print_write_plan, take_stdout,
+    with_decoded, write_output_file,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct SimplifyArgs {
+    /// Fraction of the triangles to aim for
+    #[arg(short, long, default_value_t = 0.5)]
+    ratio: f32,
+    /// Maximum deviation from the original surface, relative to mesh size
+    #[arg(short = 'e', long, default_value_t = 0.01)]
+    max_error: f32,
+    /// Do not move vertices on the border of the mesh
+    #[arg(long)]
+    lock_border: bool,
+    /// Keep the original meshes and add the simplified ones after them
+    ///
+    /// Without this flag, the meshes are replaced.
+    #[arg(short, long)]
+    append: bool,
+    /// Print info about the output file, without writing anything
+    #[arg(long)]
+    dry_run: bool,
+    #[command(flatten)]
+    rarg: crate::ReadArgs,
+    #[command(flatten)]
+    warg: crate::WriteArgs,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    paths: crate::InOutPaths,
+}
+
+pub fn run(
+    _args_common: &CommonArgs,
+    args_cmd: &SimplifyArgs,
+) -> AnyResult<()> {
+    take_stdout(args_cmd.paths.output()?);
+    let mut writer = IyesMeshWriter::new_with_settings(
+        IyesMeshWriterSettings::from(&args_cmd.warg),
+    );
+    let settings = SimplifySettings {
+        target_ratio: args_cmd.ratio,
+        max_error: args_cmd.max_error,
+        lock_border: args_cmd.lock_border,
+    };
+
+    let mut infile = open_input(&args_cmd.paths.in_file)
+        .context("Could not open input file")?;
+    let reader = IyesMeshReader::init_with_settings(
+        IyesMeshReaderSettings::from(&args_cmd.rarg),
+        &mut infile,
+    )
+    .context("Cannot decode file metadata and initialize decoding")?;
+    let with_data =
+        reader.read_all_data().context("Cannot decode file data")?;
+    let flatbufs =
+        with_data.into_flat_buffers().context("Cannot decode file buffers")?;
+    let meshes = with_data
+        .into_split_meshes(&flatbufs)
+        .context("Cannot decode file meshes")?;
+    let decoded = decode_special_attributes(&with_data, &meshes);
+    copy_color_spaces(&mut writer, &with_data);
+    copy_instance_data(&mut writer, &with_data, &flatbufs)?;
+    copy_user_data(&mut writer, &with_data, &flatbufs);
+
+    let sources: Vec<_> = meshes
+        .meshes
+        .iter()
+        .zip(decoded.iter())
+        .map(|(m, d)| with_decoded(m, d))
+        .collect();
+    let mut simplified = vec![];
+    for (i, mesh) in sources.iter().enumerate() {
+        let r = simplify(mesh, settings)
+            .with_context(|| format!("Cannot simplify mesh {i}"))?;
+        infoln!(
+            "Mesh {}: {} -> {} triangles",
+            i,
+            mesh.n_indices().unwrap_or(0) / 3,
+            r.as_mesh_ref().n_indices().unwrap_or(0) / 3,
+        );
+        simplified.push(r);
+    }
+    if args_cmd.append {
+        for (i, mesh) in sources.into_iter().enumerate() {
+            writer.add_mesh(mesh).context("Cannot use mesh for output")?;
+            copy_mesh_instances(&mut writer, &with_data, i, i)?;
+        }
+    }
+    for (i, mesh) in simplified.iter().enumerate() {
+        writer
+            .add_mesh(mesh.as_mesh_ref())
+            .context("Cannot use mesh for output")?;
+        let index = writer.n_meshes() - 1;
+        copy_mesh_instances(&mut writer, &with_data, index, i)?;
+    }
+
+    if args_cmd.dry_run {
+        let plan = writer.plan().context("Cannot use meshes for output")?;
+        print_write_plan(&plan);
+        return Ok(());
+    }
+
+    let outpath = args_cmd.paths.output()?;
+    write_output_file(
+        writer,
+        outpath,
+        args_cmd.oarg.overwrite || args_cmd.paths.out_file.is_none(),
+    )
+}
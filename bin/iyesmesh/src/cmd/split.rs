@@ -0,0 +1,153 @@
+use iyes_mesh::HashMap;
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{
+    add_single_mesh, copy_color_spaces, copy_user_data,
+    decode_special_attributes, keep_encodings, open_input, write_output_file,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct SplitArgs {
+    /// Directory to save the output files in (created if needed)
+    #[arg(short, long, default_value = ".")]
+    dir: PathBuf,
+    /// Name of the output files
+    ///
+    /// `{index}` is replaced with the index of the mesh.
+    #[arg(short, long, default_value = "mesh_{index}.ima")]
+    template: String,
+    /// Which output files get the user data of the input file (still
+    /// encrypted, if it is)
+    #[arg(long, value_enum, default_value_t = UserDataMode::None)]
+    user_data: UserDataMode,
+    #[command(flatten)]
+    rarg: crate::ReadArgs,
+    #[command(flatten)]
+    warg: crate::WriteArgs,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    inpath: crate::InputPath,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum UserDataMode {
+    /// Do not copy the user data
+    None,
+    /// Copy the user data into the file of the first mesh only
+    First,
+    /// Copy the user data into every output file
+    Every,
+}
+
+pub fn run(
+    args_common: &CommonArgs,
+    args_cmd: &SplitArgs,
+) -> AnyResult<()> {
+    let mut infile = open_input(&args_cmd.inpath.in_file)
+        .context("Could not open input file")?;
+    let reader = IyesMeshReader::init_with_settings(
+        IyesMeshReaderSettings::from(&args_cmd.rarg),
+        &mut infile,
+    )
+    .context("Cannot decode file metadata and initialize decoding")?;
+    let n_meshes = reader.descriptor().meshes.len();
+    if n_meshes == 0 {
+        eprintln!("The file has no meshes, nothing to do.");
+        return Ok(());
+    }
+
+    // Check all the names before writing anything.
+    let outpaths = (0..n_meshes)
+        .map(|i| expand_template(&args_cmd.template, i))
+        .map(|name| name.map(|name| args_cmd.dir.join(name)))
+        .collect::<AnyResult<Vec<_>>>()?;
+    let mut seen = HashMap::default();
+    for (i, path) in outpaths.iter().enumerate() {
+        if let Some(first) = seen.insert(path, i) {
+            bail!(
+                "Template `{}` gives the same name `{}` to meshes {} and {}",
+                args_cmd.template,
+                path.display(),
+                first,
+                i
+            );
+        }
+        if !args_cmd.oarg.overwrite && path.exists() {
+            bail!(
+                "Output file `{}` already exists (see --overwrite)",
+                path.display()
+            );
+        }
+    }
+
+    let with_data =
+        reader.read_all_data().context("Cannot decode file data")?;
+    let flatbufs =
+        with_data.into_flat_buffers().context("Cannot decode file buffers")?;
+    let meshes = with_data
+        .into_split_meshes(&flatbufs)
+        .context("Cannot decode file meshes")?;
+    let decoded = decode_special_attributes(&with_data, &meshes);
+
+    std::fs::create_dir_all(&args_cmd.dir)
+        .context("Could not create output directory")?;
+    let mut settings = IyesMeshWriterSettings::from(&args_cmd.warg);
+    keep_encodings(&mut settings, with_data.descriptor());
+    for (i, outpath) in outpaths.iter().enumerate() {
+        if args_common.verbose {
+            eprintln!("Writing mesh {} to {} ...", i, outpath.display());
+        }
+        let mut writer = IyesMeshWriter::new_with_settings(settings);
+        copy_color_spaces(&mut writer, &with_data);
+        let keep_user_data = match args_cmd.user_data {
+            UserDataMode::None => false,
+            UserDataMode::First => i == 0,
+            UserDataMode::Every => true,
+        };
+        if keep_user_data {
+            copy_user_data(&mut writer, &with_data, &flatbufs);
+        }
+        add_single_mesh(&mut writer, &with_data, &meshes, &decoded, i)
+            .with_context(|| format!("Cannot split mesh {}", i))?;
+        write_output_file(writer, outpath, args_cmd.oarg.overwrite)
+            .with_context(|| format!("Cannot save mesh {}", i))?;
+    }
+    println!("Wrote {} files.", n_meshes);
+    Ok(())
+}
+
+/// Name of the output file of the mesh at `index`.
+fn expand_template(
+    template: &str,
+    index: usize,
+) -> AnyResult<String> {
+    let mut r = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        r.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            bail!("Unclosed `{{` in template `{}`", template);
+        };
+        match &rest[start + 1..start + len] {
+            "index" => r.push_str(&index.to_string()),
+            "name" => bail!(
+                "Template `{}` uses `{{name}}`, but meshes have no names \
+                 (use `{{index}}`)",
+                template
+            ),
+            other => bail!(
+                "Unknown placeholder `{{{}}}` in template `{}` (expected \
+                 `{{index}}`)",
+                other,
+                template
+            ),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    r.push_str(rest);
+    Ok(r)
+}
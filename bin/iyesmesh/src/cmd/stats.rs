@@ -0,0 +1,326 @@
+use std::io::Write;
+
+use iyes_mesh::descriptor::IyesMeshDescriptor;
+use iyes_mesh::read::IyesMeshReader;
+use iyes_mesh::read::IyesMeshReaderSettings;
+use iyes_mesh::write::IyesMeshWriterSettings;
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{compressed_size, named_buffers, open_input, print_table};
+
+#[derive(clap::Args, Debug)]
+pub struct StatsArgs {
+    /// Zstd compression level to estimate the share of each buffer with
+    /// (default: the default level of the writer)
+    #[arg(short, long, allow_negative_numbers = true)]
+    #[arg(value_parser = crate::util::parse_compression_level)]
+    level: Option<i32>,
+    /// Print the statistics as JSON, for use by scripts
+    ///
+    /// The fields are documented with `JsonStats` in the source of this
+    /// command. Fields may be added, but are never renamed or removed.
+    #[arg(long)]
+    json: bool,
+    #[command(flatten)]
+    rarg: crate::ReadArgs,
+    #[command(flatten)]
+    inpath: crate::InputPath,
+}
+
+/// A buffer of the decoded data, with its compressed size when compressed
+/// on its own.
+struct BufferStats {
+    name: String,
+    format: Option<String>,
+    raw_size: u64,
+    compressed_alone: u64,
+}
+
+pub fn run(
+    args_common: &CommonArgs,
+    args_cmd: &StatsArgs,
+) -> AnyResult<()> {
+    let mut infile = open_input(&args_cmd.inpath.in_file)
+        .context("Could not open input file")?;
+    let file_size = infile.size()?;
+    let reader = IyesMeshReader::init_with_settings(
+        IyesMeshReaderSettings::from(&args_cmd.rarg),
+        &mut infile,
+    )
+    .context("Cannot decode file metadata and initialize decoding")?;
+    let compressed_size =
+        compressed_size(reader.header(), reader.descriptor(), file_size);
+    let reader = reader.read_all_data().context("Cannot decode data")?;
+    let descriptor = reader.descriptor();
+    let buffers = reader
+        .into_flat_buffers()
+        .context("Cannot split the data into buffers")?;
+
+    let level = args_cmd
+        .level
+        .unwrap_or(IyesMeshWriterSettings::default().compression_level);
+    let mut stats = vec![];
+    for (name, format, data) in named_buffers(descriptor, &buffers) {
+        if args_common.verbose {
+            eprintln!("Compressing {} ({} bytes) ...", name, data.len());
+        }
+        stats.push(BufferStats {
+            name,
+            format,
+            raw_size: data.len() as u64,
+            compressed_alone: compress_alone(data, level)
+                .context("Cannot compress buffer")?,
+        });
+    }
+
+    let summary = Summary::new(descriptor, &stats, compressed_size, file_size);
+    if args_cmd.json {
+        let json = JsonStats::new(&summary, &stats, level);
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+    print_report(&summary, &stats, level);
+    Ok(())
+}
+
+/// Size of the data once compressed as a separate zstd stream.
+fn compress_alone(
+    data: &[u8],
+    level: i32,
+) -> std::io::Result<u64> {
+    let mut encoder =
+        iyes_mesh::io::new_zstd_encoder(vec![], level, data.len() as u64)?;
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?.len() as u64)
+}
+
+/// Totals over the whole file.
+struct Summary {
+    file_size: u64,
+    compressed_size: u64,
+    raw_size: u64,
+    n_vertices: u32,
+    n_triangles: u64,
+    n_instances: u32,
+    /// Size of the vertex attribute buffers, user data excluded.
+    vertex_data_size: u64,
+    /// Sum of the compressed sizes of the buffers, compressed on their own.
+    compressed_alone: u64,
+}
+
+impl Summary {
+    fn new(
+        descriptor: &IyesMeshDescriptor,
+        stats: &[BufferStats],
+        compressed_size: u64,
+        file_size: u64,
+    ) -> Self {
+        let n_triangles = descriptor
+            .meshes
+            .iter()
+            .map(|mesh| {
+                let n = if descriptor.indices.is_some() {
+                    mesh.index_count
+                } else {
+                    mesh.vertex_count
+                };
+                n as u64 / 3
+            })
+            .sum();
+        Self {
+            file_size,
+            compressed_size,
+            raw_size: stats.iter().map(|s| s.raw_size).sum(),
+            n_vertices: descriptor.n_vertices,
+            n_triangles,
+            n_instances: descriptor.n_instances,
+            vertex_data_size: descriptor
+                .attributes
+                .values()
+                .map(|f| f.size() as u64 * descriptor.n_vertices as u64)
+                .sum(),
+            compressed_alone: stats.iter().map(|s| s.compressed_alone).sum(),
+        }
+    }
+
+    fn ratio(&self) -> f64 {
+        ratio(self.raw_size, self.compressed_size)
+    }
+
+    /// Approximate share of the compressed data taken by a buffer, in
+    /// percent: its compressed size on its own, relative to the sum over
+    /// all buffers.
+    fn share_of(
+        &self,
+        stats: &BufferStats,
+    ) -> f64 {
+        percent(stats.compressed_alone, self.compressed_alone)
+    }
+
+    /// Raw and compressed size of the vertex attributes of one vertex. The
+    /// compressed size assumes they compress as well as the whole data.
+    fn bytes_per_vertex(&self) -> Option<(f64, f64)> {
+        (self.n_vertices > 0).then(|| {
+            let n = self.n_vertices as f64;
+            let raw = self.vertex_data_size as f64;
+            let compressed =
+                self.compressed_size as f64 * raw / self.raw_size.max(1) as f64;
+            (raw / n, compressed / n)
+        })
+    }
+}
+
+fn percent(
+    part: u64,
+    total: u64,
+) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+fn ratio(
+    raw: u64,
+    compressed: u64,
+) -> f64 {
+    if compressed == 0 {
+        0.0
+    } else {
+        raw as f64 / compressed as f64
+    }
+}
+
+/// Print the statistics in a human-readable form.
+fn print_report(
+    summary: &Summary,
+    stats: &[BufferStats],
+    level: i32,
+) {
+    let rows: Vec<_> = stats
+        .iter()
+        .map(|s| {
+            vec![
+                s.name.clone(),
+                s.format.clone().unwrap_or_default(),
+                s.raw_size.to_string(),
+                format!("{:.1}%", percent(s.raw_size, summary.raw_size)),
+                s.compressed_alone.to_string(),
+                format!("{:.1}x", ratio(s.raw_size, s.compressed_alone)),
+                format!("{:.1}%", summary.share_of(s)),
+            ]
+        })
+        .collect();
+    print_table(
+        &[
+            "Buffer", "Format", "Raw size", "Raw %", "Alone", "Ratio", "Share",
+        ],
+        &rows,
+        2,
+    );
+    println!();
+    println!(
+        "Alone: size of the buffer compressed on its own (level {}).",
+        level
+    );
+    println!("Share: approximate share of the compressed data, from the sizes");
+    println!("alone (buffers compress differently together).");
+
+    println!();
+    println!(
+        "Size: {} bytes raw, {} bytes compressed ({:.2}x), {} bytes file",
+        summary.raw_size,
+        summary.compressed_size,
+        summary.ratio(),
+        summary.file_size
+    );
+    println!(
+        "Vertices: {}, triangles: {}, instances: {}",
+        summary.n_vertices, summary.n_triangles, summary.n_instances
+    );
+    if let Some((raw, compressed)) = summary.bytes_per_vertex() {
+        println!(
+            "Bytes per vertex: {:.2} raw, {:.2} compressed (approximate)",
+            raw, compressed
+        );
+    }
+}
+
+/// The document printed by `stats --json`.
+///
+/// Fields may be added, but are never renamed or removed.
+#[derive(serde::Serialize)]
+struct JsonStats {
+    /// Size of the whole file, in bytes.
+    file_size: u64,
+    /// Size of the compressed data, in bytes.
+    compressed_size: u64,
+    /// Size of the data once decompressed, in bytes (user data included).
+    raw_size: u64,
+    /// `raw_size / compressed_size`.
+    compression_ratio: f64,
+    vertex_count: u32,
+    /// Number of triangles, over all meshes.
+    triangle_count: u64,
+    instance_count: u32,
+    /// Size of the vertex attributes of one vertex (null if there are no
+    /// vertices).
+    raw_bytes_per_vertex: Option<f64>,
+    /// Approximate compressed size of the vertex attributes of one vertex
+    /// (null if there are no vertices).
+    compressed_bytes_per_vertex: Option<f64>,
+    /// Zstd level the buffers were compressed with, for `buffers`.
+    level: i32,
+    /// The buffers, in the order they are stored in.
+    buffers: Vec<JsonBuffer>,
+}
+
+/// A buffer in [`JsonStats::buffers`].
+#[derive(serde::Serialize)]
+struct JsonBuffer {
+    /// `user data`, `indices`, the usage of a vertex attribute, or
+    /// `instance ` followed by the usage of an instance attribute.
+    name: String,
+    /// Format of the indices or attribute (null for user data).
+    format: Option<String>,
+    /// Size of the buffer, in bytes.
+    raw_size: u64,
+    /// Size of the buffer once compressed on its own, in bytes.
+    compressed_size_alone: u64,
+    /// Approximate share of the compressed data, in percent.
+    compressed_share: f64,
+}
+
+impl JsonStats {
+    fn new(
+        summary: &Summary,
+        stats: &[BufferStats],
+        level: i32,
+    ) -> Self {
+        let per_vertex = summary.bytes_per_vertex();
+        Self {
+            file_size: summary.file_size,
+            compressed_size: summary.compressed_size,
+            raw_size: summary.raw_size,
+            compression_ratio: summary.ratio(),
+            vertex_count: summary.n_vertices,
+            triangle_count: summary.n_triangles,
+            instance_count: summary.n_instances,
+            raw_bytes_per_vertex: per_vertex.map(|v| v.0),
+            compressed_bytes_per_vertex: per_vertex.map(|v| v.1),
+            level,
+            buffers: stats
+                .iter()
+                .map(|s| JsonBuffer {
+                    name: s.name.clone(),
+                    format: s.format.clone(),
+                    raw_size: s.raw_size,
+                    compressed_size_alone: s.compressed_alone,
+                    compressed_share: summary.share_of(s),
+                })
+                .collect(),
+        }
+    }
+}
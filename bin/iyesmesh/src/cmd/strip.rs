@@ -0,0 +1,99 @@
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{
+    FileSizes, copy_color_spaces, copy_instance_data, copy_mesh_instances,
+    copy_user_data, decode_special_attributes, is_signing, keep_encodings,
+    open_input, print_size_change, take_stdout, with_decoded,
+    write_output_file_sizes,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct StripArgs {
+    /// Keep the user data
+    #[arg(long)]
+    keep_user_data: bool,
+    /// Keep the data checksum
+    #[arg(long)]
+    keep_checksum: bool,
+    /// Sign the output file with this Ed25519 private key
+    ///
+    /// The signature of the input file cannot be kept, so the output is
+    /// not signed otherwise.
+    #[cfg(feature = "signing")]
+    #[arg(long, value_name = "KEY_FILE")]
+    sign: Option<PathBuf>,
+    #[command(flatten)]
+    rarg: crate::ReadArgs,
+    #[command(flatten)]
+    warg: crate::WriteArgs,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    paths: crate::InOutPaths,
+}
+
+pub fn run(
+    _args_common: &CommonArgs,
+    args_cmd: &StripArgs,
+) -> AnyResult<()> {
+    take_stdout(args_cmd.paths.output()?);
+    let read_settings = IyesMeshReaderSettings::from(&args_cmd.rarg);
+    let mut infile = open_input(&args_cmd.paths.in_file)
+        .context("Could not open input file")?;
+    let file_size = infile.size()?;
+    let reader = IyesMeshReader::init_with_settings(read_settings, &mut infile)
+        .context("Cannot decode file metadata and initialize decoding")?;
+    let before = FileSizes::new(&reader, file_size);
+    let with_data =
+        reader.read_all_data().context("Cannot decode file data")?;
+    let flatbufs =
+        with_data.into_flat_buffers().context("Cannot decode file buffers")?;
+    let meshes = with_data
+        .into_split_meshes(&flatbufs)
+        .context("Cannot decode file meshes")?;
+    let decoded = decode_special_attributes(&with_data, &meshes);
+
+    let descriptor = with_data.descriptor();
+    let mut settings = IyesMeshWriterSettings {
+        #[cfg(feature = "signing")]
+        signing_key: args_cmd
+            .sign
+            .as_deref()
+            .map(crate::util::load_signing_key)
+            .transpose()?,
+        ..IyesMeshWriterSettings::from(&args_cmd.warg)
+    };
+    settings.write_data_checksum &= args_cmd.keep_checksum;
+    keep_encodings(&mut settings, descriptor);
+    if descriptor.signed && !is_signing(&settings) {
+        eprintln!(
+            "Warning! The input file is signed, but the output will not be \
+             (see --sign)."
+        );
+    }
+
+    let mut writer = IyesMeshWriter::new_with_settings(settings);
+    copy_color_spaces(&mut writer, &with_data);
+    if args_cmd.keep_user_data {
+        copy_user_data(&mut writer, &with_data, &flatbufs);
+    }
+    copy_instance_data(&mut writer, &with_data, &flatbufs)?;
+    for (i, mesh) in meshes.meshes.iter().enumerate() {
+        writer
+            .add_mesh(with_decoded(mesh, &decoded[i]))
+            .context("Cannot use mesh for output")?;
+        copy_mesh_instances(&mut writer, &with_data, i, i)?;
+    }
+
+    let after = write_output_file_sizes(
+        writer,
+        args_cmd.paths.output()?,
+        args_cmd.oarg.overwrite || args_cmd.paths.out_file.is_none(),
+        read_settings,
+    )?;
+    print_size_change(&before, &after);
+    Ok(())
+}
@@ -0,0 +1,530 @@
+use std::collections::BTreeMap;
+
+use iyes_mesh::convert::{convert_vertex_data, iter_f32, srgb_to_linear};
+use iyes_mesh::descriptor::{
+    ColorSpace, IndexFormat, VertexFormat, VertexUsage,
+};
+use iyes_mesh::mesh::MeshDataRef;
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
+use serde_json::{Value, json};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{
+    decode_special_attributes, is_stdout, open_input, with_decoded,
+    write_bytes_file,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct ToGltfArgs {
+    /// Where to put the user data of the IMA file
+    #[arg(long, value_enum, default_value_t = UserDataMode::None)]
+    user_data: UserDataMode,
+    /// Fail instead of converting or skipping what glTF cannot store
+    #[arg(long)]
+    strict: bool,
+    #[cfg(feature = "encryption")]
+    #[command(flatten)]
+    key: crate::UserDataKeyArgs,
+    #[command(flatten)]
+    rarg: crate::ReadArgs,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    inpath: crate::InputPath,
+    /// Path where to save the output file
+    ///
+    /// A `.glb` file if it has that extension, or is `-` (stdout).
+    /// Otherwise, a `.gltf` file with the data in a `.bin` file next to it
+    /// (with the same name).
+    out_file: PathBuf,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum UserDataMode {
+    /// Do not export the user data
+    None,
+    /// Store the user data as the `extras` of the glTF file (it must be
+    /// JSON)
+    Extras,
+    /// Store the user data in a buffer view named `user_data`
+    Buffer,
+}
+
+/// glTF accessor component types.
+const BYTE: u32 = 5120;
+const UNSIGNED_BYTE: u32 = 5121;
+const SHORT: u32 = 5122;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+
+/// glTF buffer view targets.
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+pub fn run(
+    args_common: &CommonArgs,
+    args_cmd: &ToGltfArgs,
+) -> AnyResult<()> {
+    let mut infile = open_input(&args_cmd.inpath.in_file)
+        .context("Could not open input file")?;
+    let settings = IyesMeshReaderSettings {
+        #[cfg(feature = "encryption")]
+        user_data_key: args_cmd.key.load()?,
+        ..IyesMeshReaderSettings::from(&args_cmd.rarg)
+    };
+    let reader = IyesMeshReader::init_with_settings(settings, &mut infile)
+        .context("Cannot decode file metadata and initialize decoding")?;
+    let with_data =
+        reader.read_all_data().context("Cannot decode file data")?;
+    let flatbufs =
+        with_data.into_flat_buffers().context("Cannot decode file buffers")?;
+    let meshes = with_data
+        .into_split_meshes(&flatbufs)
+        .context("Cannot decode file meshes")?;
+    let decoded = decode_special_attributes(&with_data, &meshes);
+
+    // What cannot be exported as-is, by attribute.
+    let mut problems = BTreeMap::new();
+    if with_data.descriptor().n_instances > 0 {
+        problems.insert(None, "glTF files cannot store instances".to_owned());
+    }
+    let color_spaces = &with_data.descriptor().color_spaces;
+    let mut out = GltfBuilder::default();
+    for (i, mesh) in meshes.meshes.iter().enumerate() {
+        let mesh = with_decoded(mesh, &decoded[i]);
+        if let Err(e) = mesh.iter_triangles() {
+            bail!("Mesh {} is not a triangle list: {}", i, e);
+        }
+        let mut attributes = BTreeMap::new();
+        let mut usages: Vec<_> = mesh.attributes.keys().copied().collect();
+        usages.sort();
+        for usage in usages {
+            let (format, bytes) = mesh.attributes[&usage];
+            let srgb = usage == VertexUsage::Color
+                && color_spaces.get(&usage) == Some(&ColorSpace::Srgb);
+            let mut target = export_format(usage, format);
+            if srgb && target.is_some_and(|t| float_components(t).is_none()) {
+                // glTF colors are linear, converting sRGB ones to floats
+                // keeps their precision.
+                target = Some(VertexFormat::Float32x4);
+            }
+            let data = target.and_then(|t| export_data(format, t, bytes));
+            let (Some(target), Some(mut data)) = (target, data) else {
+                problems.insert(
+                    Some(usage),
+                    format!("{} ({:?}) has no glTF equivalent", usage, format),
+                );
+                continue;
+            };
+            if target != format {
+                problems.insert(
+                    Some(usage),
+                    format!(
+                        "{} is converted from {:?} to {:?}",
+                        usage, format, target
+                    ),
+                );
+            }
+            if srgb {
+                linearize_colors(target, &mut data);
+                problems.insert(
+                    Some(usage),
+                    "Color is converted from sRGB to linear, like glTF colors \
+                     are"
+                        .to_owned(),
+                );
+            }
+            let accessor = out.add_attribute(usage, target, &data);
+            attributes.insert(attribute_name(usage), accessor);
+        }
+        let indices = mesh.indices.map(|(format, bytes)| {
+            let component_type = match format {
+                IndexFormat::U16 => UNSIGNED_SHORT,
+                IndexFormat::U32 => UNSIGNED_INT,
+            };
+            let view = out.add_view(bytes, None, ELEMENT_ARRAY_BUFFER);
+            out.add_accessor(json!({
+                "bufferView": view,
+                "componentType": component_type,
+                "count": bytes.len() / format.size(),
+                "type": "SCALAR",
+            }))
+        });
+        let mut primitive = json!({ "attributes": attributes, "mode": 4 });
+        if let Some(indices) = indices {
+            primitive["indices"] = json!(indices);
+        }
+        let name = format!("mesh_{}", i);
+        out.meshes.push(json!({ "name": name, "primitives": [primitive] }));
+        out.nodes.push(json!({ "name": name, "mesh": i }));
+    }
+
+    if !problems.is_empty() {
+        if args_cmd.strict {
+            for problem in problems.values() {
+                eprintln!("  - {}", problem);
+            }
+            bail!("The file cannot be exported to glTF as-is (see --strict).");
+        }
+        for problem in problems.values() {
+            eprintln!("Warning: {}", problem);
+        }
+    }
+
+    let mut extras = None;
+    if args_cmd.user_data != UserDataMode::None {
+        let user_data = with_data
+            .decode_user_data()
+            .context("Cannot decode user data")?;
+        match (user_data, args_cmd.user_data) {
+            (None, _) => eprintln!("Warning: the file has no user data."),
+            (Some(data), UserDataMode::Extras) => {
+                let value: Value =
+                    serde_json::from_slice(&data).context(
+                        "The user data is not JSON, it cannot be the `extras` \
+                         (see --user-data buffer)",
+                    )?;
+                extras = Some(value);
+            }
+            (Some(data), _) => {
+                let view = out.add_view(&data, None, 0);
+                out.views[view]["name"] = json!("user_data");
+            }
+        }
+    }
+
+    let (n_meshes, n_bytes) = (out.meshes.len(), out.bin.len());
+    // A `.gltf` file on stdout could not refer to its `.bin` file.
+    let glb = is_stdout(&args_cmd.out_file)
+        || args_cmd
+            .out_file
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("glb"));
+    let bin_path = args_cmd.out_file.with_extension("bin");
+    let mut buffer = json!({ "byteLength": n_bytes });
+    if !glb {
+        let name = bin_path.file_name().unwrap().to_string_lossy();
+        buffer["uri"] = json!(name);
+    }
+    let mut gltf = json!({
+        "asset": {
+            "version": "2.0",
+            "generator": format!("iyesmesh {}", env!("CARGO_PKG_VERSION")),
+        },
+        "scene": 0,
+        "scenes": [{ "nodes": (0..n_meshes).collect::<Vec<_>>() }],
+        "nodes": out.nodes,
+        "meshes": out.meshes,
+        "accessors": out.accessors,
+        "bufferViews": out.views,
+        "buffers": [buffer],
+    });
+    if let Some(extras) = extras {
+        gltf["extras"] = extras;
+    }
+
+    if glb {
+        let json = serde_json::to_vec(&gltf)?;
+        write_bytes_file(
+            &args_cmd.out_file,
+            args_cmd.oarg.overwrite,
+            &glb_bytes(json, out.bin),
+        )?;
+    } else {
+        let json = serde_json::to_string_pretty(&gltf)?;
+        write_bytes_file(&bin_path, args_cmd.oarg.overwrite, &out.bin)?;
+        write_bytes_file(
+            &args_cmd.out_file,
+            args_cmd.oarg.overwrite,
+            json.as_bytes(),
+        )?;
+    }
+    if args_common.verbose {
+        eprintln!(
+            "Wrote {} meshes, {} bytes of buffer data.",
+            n_meshes, n_bytes
+        );
+    }
+    Ok(())
+}
+
+/// The glTF attribute of an IMA attribute (custom attributes use the
+/// application-specific `_CUSTOM_<id>`).
+fn attribute_name(usage: VertexUsage) -> String {
+    let name = match usage {
+        VertexUsage::Custom(id) => return format!("_CUSTOM_{}", id),
+        VertexUsage::Position => "POSITION",
+        VertexUsage::Normal => "NORMAL",
+        VertexUsage::Tangent => "TANGENT",
+        VertexUsage::Uv0 => "TEXCOORD_0",
+        VertexUsage::Uv1 => "TEXCOORD_1",
+        VertexUsage::Uv2 => "TEXCOORD_2",
+        VertexUsage::Uv3 => "TEXCOORD_3",
+        VertexUsage::Uv4 => "TEXCOORD_4",
+        VertexUsage::Uv5 => "TEXCOORD_5",
+        VertexUsage::Uv6 => "TEXCOORD_6",
+        VertexUsage::Uv7 => "TEXCOORD_7",
+        VertexUsage::Color => "COLOR_0",
+        VertexUsage::JointIndex => "JOINTS_0",
+        VertexUsage::JointIndex1 => "JOINTS_1",
+        VertexUsage::JointWeight => "WEIGHTS_0",
+        VertexUsage::JointWeight1 => "WEIGHTS_1",
+    };
+    name.to_owned()
+}
+
+/// The format to export an attribute in: one that glTF allows for it, as
+/// close as possible to `format`.
+///
+/// Returns `None` if there is none.
+fn export_format(
+    usage: VertexUsage,
+    format: VertexFormat,
+) -> Option<VertexFormat> {
+    use VertexFormat::*;
+    let allowed: &[VertexFormat] = match usage {
+        VertexUsage::Position | VertexUsage::Normal => &[Float32x3],
+        VertexUsage::Tangent => &[Float32x4],
+        VertexUsage::Uv0
+        | VertexUsage::Uv1
+        | VertexUsage::Uv2
+        | VertexUsage::Uv3
+        | VertexUsage::Uv4
+        | VertexUsage::Uv5
+        | VertexUsage::Uv6
+        | VertexUsage::Uv7 => &[Float32x2, Unorm8x2, Unorm16x2],
+        VertexUsage::Color => &[Float32x4, Float32x3, Unorm8x4, Unorm16x4],
+        VertexUsage::JointIndex | VertexUsage::JointIndex1 => {
+            return [Uint8x4, Uint16x4].contains(&format).then_some(format);
+        }
+        VertexUsage::JointWeight | VertexUsage::JointWeight1 => {
+            &[Float32x4, Unorm8x4, Unorm16x4]
+        }
+        VertexUsage::Custom(_) => {
+            return match format {
+                Float16 | Float64 => Some(Float32),
+                Float16x2 | Float64x2 => Some(Float32x2),
+                Float64x3 => Some(Float32x3),
+                Float16x4 | Float64x4 => Some(Float32x4),
+                Unorm8x4Bgra => Some(Unorm8x4),
+                Unorm10_10_10_2 => Some(Unorm16x4),
+                Sint32 | Sint32x2 | Sint32x3 | Sint32x4 | Uint32
+                | Uint32x2 | Uint32x3 | Uint32x4 => None,
+                other => Some(other),
+            };
+        }
+    };
+    if allowed.contains(&format) {
+        return Some(format);
+    }
+    if format == Unorm8x4Bgra && allowed.contains(&Unorm8x4) {
+        return Some(Unorm8x4);
+    }
+    // Otherwise, decode as floats (with the first components only).
+    allowed.iter().copied().find(|f| float_components(*f).is_some())
+}
+
+/// The number of components of a Float32 format.
+fn float_components(format: VertexFormat) -> Option<usize> {
+    match format {
+        VertexFormat::Float32 => Some(1),
+        VertexFormat::Float32x2 => Some(2),
+        VertexFormat::Float32x3 => Some(3),
+        VertexFormat::Float32x4 => Some(4),
+        _ => None,
+    }
+}
+
+/// Convert attribute data to the format from [`export_format`].
+fn export_data(
+    format: VertexFormat,
+    target: VertexFormat,
+    bytes: &[u8],
+) -> Option<Vec<u8>> {
+    if format == target {
+        return Some(bytes.to_vec());
+    }
+    let floats: Option<Vec<f32>> = match float_components(target) {
+        Some(1) => iter_f32::<1>(format, bytes).map(|i| i.flatten().collect()),
+        Some(2) => iter_f32::<2>(format, bytes).map(|i| i.flatten().collect()),
+        Some(3) => iter_f32::<3>(format, bytes).map(|i| i.flatten().collect()),
+        Some(4) => iter_f32::<4>(format, bytes).map(|i| i.flatten().collect()),
+        _ => None,
+    };
+    if let Some(floats) = floats {
+        return Some(floats.iter().flat_map(|v| v.to_le_bytes()).collect());
+    }
+    let mut out = vec![];
+    convert_vertex_data(format, target, bytes, &mut out).then_some(out)
+}
+
+/// Apply [`srgb_to_linear`] to the first 3 components of Float32x3 or
+/// Float32x4 colors.
+fn linearize_colors(
+    format: VertexFormat,
+    data: &mut [u8],
+) {
+    let n = float_components(format).unwrap();
+    for color in data.chunks_exact_mut(n * 4) {
+        for v in color.chunks_exact_mut(4).take(3) {
+            let linear = srgb_to_linear(f32::from_le_bytes(
+                v.try_into().unwrap(),
+            ) as f64);
+            v.copy_from_slice(&(linear as f32).to_le_bytes());
+        }
+    }
+}
+
+/// The glTF component type, normalization and type of an exported format.
+fn accessor_type(format: VertexFormat) -> (u32, bool, &'static str) {
+    use VertexFormat::*;
+    match format {
+        Float32 => (FLOAT, false, "SCALAR"),
+        Float32x2 => (FLOAT, false, "VEC2"),
+        Float32x3 => (FLOAT, false, "VEC3"),
+        Float32x4 => (FLOAT, false, "VEC4"),
+        Sint8 => (BYTE, false, "SCALAR"),
+        Sint8x2 => (BYTE, false, "VEC2"),
+        Sint8x4 => (BYTE, false, "VEC4"),
+        Snorm8 => (BYTE, true, "SCALAR"),
+        Snorm8x2 => (BYTE, true, "VEC2"),
+        Snorm8x4 => (BYTE, true, "VEC4"),
+        Uint8 => (UNSIGNED_BYTE, false, "SCALAR"),
+        Uint8x2 => (UNSIGNED_BYTE, false, "VEC2"),
+        Uint8x4 => (UNSIGNED_BYTE, false, "VEC4"),
+        Unorm8 => (UNSIGNED_BYTE, true, "SCALAR"),
+        Unorm8x2 => (UNSIGNED_BYTE, true, "VEC2"),
+        Unorm8x4 => (UNSIGNED_BYTE, true, "VEC4"),
+        Sint16 => (SHORT, false, "SCALAR"),
+        Sint16x2 => (SHORT, false, "VEC2"),
+        Sint16x4 => (SHORT, false, "VEC4"),
+        Snorm16 => (SHORT, true, "SCALAR"),
+        Snorm16x2 => (SHORT, true, "VEC2"),
+        Snorm16x4 => (SHORT, true, "VEC4"),
+        Uint16 => (UNSIGNED_SHORT, false, "SCALAR"),
+        Uint16x2 => (UNSIGNED_SHORT, false, "VEC2"),
+        Uint16x4 => (UNSIGNED_SHORT, false, "VEC4"),
+        Unorm16 => (UNSIGNED_SHORT, true, "SCALAR"),
+        Unorm16x2 => (UNSIGNED_SHORT, true, "VEC2"),
+        Unorm16x4 => (UNSIGNED_SHORT, true, "VEC4"),
+        other => unreachable!("{:?} is never exported", other),
+    }
+}
+
+/// The contents of the glTF file being written.
+#[derive(Default)]
+struct GltfBuilder {
+    bin: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+    meshes: Vec<Value>,
+    nodes: Vec<Value>,
+}
+
+impl GltfBuilder {
+    /// Append data to the buffer, and add a buffer view of it (`target` 0
+    /// for none). Returns the index of the view.
+    fn add_view(
+        &mut self,
+        data: &[u8],
+        stride: Option<usize>,
+        target: u32,
+    ) -> usize {
+        self.bin.resize(self.bin.len().next_multiple_of(4), 0);
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": self.bin.len(),
+            "byteLength": data.len(),
+        });
+        if let Some(stride) = stride {
+            view["byteStride"] = json!(stride);
+        }
+        if target != 0 {
+            view["target"] = json!(target);
+        }
+        self.bin.extend_from_slice(data);
+        self.views.push(view);
+        self.views.len() - 1
+    }
+
+    fn add_accessor(
+        &mut self,
+        accessor: Value,
+    ) -> usize {
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    /// Add a vertex attribute, returning the index of its accessor.
+    ///
+    /// glTF requires the elements of vertex attributes to be aligned to 4
+    /// bytes, so smaller elements are padded.
+    fn add_attribute(
+        &mut self,
+        usage: VertexUsage,
+        format: VertexFormat,
+        data: &[u8],
+    ) -> usize {
+        let size = format.size();
+        let count = data.len() / size;
+        let stride = size.next_multiple_of(4);
+        let view = if stride == size {
+            self.add_view(data, None, ARRAY_BUFFER)
+        } else {
+            let mut padded = Vec::with_capacity(count * stride);
+            for element in data.chunks_exact(size) {
+                padded.extend_from_slice(element);
+                padded.resize(padded.len() + stride - size, 0);
+            }
+            self.add_view(&padded, Some(stride), ARRAY_BUFFER)
+        };
+        let (component_type, normalized, kind) = accessor_type(format);
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": component_type,
+            "count": count,
+            "type": kind,
+        });
+        if normalized {
+            accessor["normalized"] = json!(true);
+        }
+        if usage == VertexUsage::Position {
+            // glTF requires the bounds of positions.
+            let mesh = MeshDataRef {
+                indices: None,
+                attributes: [(usage, (format, data))].into_iter().collect(),
+            };
+            if let Some((min, max)) = mesh.position_bounds() {
+                accessor["min"] = json!(min);
+                accessor["max"] = json!(max);
+            }
+        }
+        self.add_accessor(accessor)
+    }
+}
+
+const GLB_CHUNK_JSON: u32 = 0x4E4F534A;
+const GLB_CHUNK_BIN: u32 = 0x004E4942;
+
+/// The bytes of a GLB file.
+fn glb_bytes(
+    mut json: Vec<u8>,
+    mut bin: Vec<u8>,
+) -> Vec<u8> {
+    json.resize(json.len().next_multiple_of(4), b' ');
+    bin.resize(bin.len().next_multiple_of(4), 0);
+    let length = 12 + 8 + json.len() + 8 + bin.len();
+    let mut r = Vec::with_capacity(length);
+    r.extend_from_slice(b"glTF");
+    r.extend_from_slice(&2u32.to_le_bytes());
+    r.extend_from_slice(&(length as u32).to_le_bytes());
+    r.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    r.extend_from_slice(&GLB_CHUNK_JSON.to_le_bytes());
+    r.extend_from_slice(&json);
+    r.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+    r.extend_from_slice(&GLB_CHUNK_BIN.to_le_bytes());
+    r.extend_from_slice(&bin);
+    r
+}
@@ -0,0 +1,153 @@
+use std::fmt::Write;
+
+use iyes_mesh::HashSet;
+use iyes_mesh::descriptor::VertexUsage;
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{
+    decode_special_attributes, open_input, with_decoded, write_bytes_file,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct ToObjArgs {
+    /// Only export this mesh
+    #[arg(short, long, value_parser = crate::util::parse_mesh_index)]
+    mesh: Option<usize>,
+    /// Flip the V texture coordinate (V becomes 1 - V)
+    #[arg(long)]
+    flip_uv_v: bool,
+    #[command(flatten)]
+    rarg: crate::ReadArgs,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    inpath: crate::InputPath,
+    #[command(flatten)]
+    outpath: crate::OutputPath,
+}
+
+/// The attributes that OBJ files can store.
+const OBJ_ATTRIBUTES: [VertexUsage; 3] =
+    [VertexUsage::Position, VertexUsage::Normal, VertexUsage::Uv0];
+
+pub fn run(
+    args_common: &CommonArgs,
+    args_cmd: &ToObjArgs,
+) -> AnyResult<()> {
+    let mut infile = open_input(&args_cmd.inpath.in_file)
+        .context("Could not open input file")?;
+    let reader = IyesMeshReader::init_with_settings(
+        IyesMeshReaderSettings::from(&args_cmd.rarg),
+        &mut infile,
+    )
+    .context("Cannot decode file metadata and initialize decoding")?;
+    let n_meshes = reader.descriptor().meshes.len();
+    if let Some(index) = args_cmd.mesh
+        && index >= n_meshes
+    {
+        bail!(
+            "Mesh {} does not exist (the file has {} meshes)",
+            index,
+            n_meshes
+        );
+    }
+    let with_data =
+        reader.read_all_data().context("Cannot decode file data")?;
+    let flatbufs =
+        with_data.into_flat_buffers().context("Cannot decode file buffers")?;
+    let meshes = with_data
+        .into_split_meshes(&flatbufs)
+        .context("Cannot decode file meshes")?;
+    let decoded = decode_special_attributes(&with_data, &meshes);
+
+    let mut skipped = HashSet::default();
+    let mut out = String::new();
+    // OBJ indices are 1-based, and count the elements of all the meshes
+    // before.
+    let mut base = 1;
+    for (i, mesh) in meshes.meshes.iter().enumerate() {
+        if args_cmd.mesh.is_some_and(|index| index != i) {
+            continue;
+        }
+        let mesh = with_decoded(mesh, &decoded[i]);
+        let Some(positions) = mesh.positions_f32() else {
+            bail!("Mesh {} has no positions (in a float format)", i);
+        };
+        if let Some((_, index)) =
+            mesh.find_out_of_range_indices().first().copied()
+        {
+            bail!("Mesh {} has an out of range index: {}", i, index);
+        }
+        let triangles = mesh
+            .iter_triangles()
+            .with_context(|| format!("Mesh {} is not a triangle list", i))?;
+        let normals = mesh.normals_f32();
+        let uvs = mesh.uvs_f32(VertexUsage::Uv0);
+        for usage in mesh.attributes.keys() {
+            let stored = match usage {
+                VertexUsage::Position => true,
+                VertexUsage::Normal => normals.is_some(),
+                VertexUsage::Uv0 => uvs.is_some(),
+                _ => false,
+            };
+            if !stored {
+                skipped.insert(*usage);
+            }
+        }
+        let (has_normals, has_uvs) = (normals.is_some(), uvs.is_some());
+
+        writeln!(out, "o mesh_{}", i)?;
+        for [x, y, z] in positions {
+            writeln!(out, "v {} {} {}", x, y, z)?;
+        }
+        for [u, v] in uvs.into_iter().flatten() {
+            let v = if args_cmd.flip_uv_v { 1.0 - v } else { v };
+            writeln!(out, "vt {} {}", u, v)?;
+        }
+        for [x, y, z] in normals.into_iter().flatten() {
+            writeln!(out, "vn {} {} {}", x, y, z)?;
+        }
+        for triangle in triangles {
+            out.push('f');
+            for index in triangle {
+                let index = base + index as usize;
+                match (has_uvs, has_normals) {
+                    (true, true) => write!(out, " {0}/{0}/{0}", index)?,
+                    (true, false) => write!(out, " {0}/{0}", index)?,
+                    (false, true) => write!(out, " {0}//{0}", index)?,
+                    (false, false) => write!(out, " {}", index)?,
+                }
+            }
+            out.push('\n');
+        }
+        base += mesh.n_vertices();
+        if args_common.verbose {
+            eprintln!("Mesh {}: {} vertices", i, mesh.n_vertices());
+        }
+    }
+
+    if !skipped.is_empty() {
+        let mut skipped: Vec<_> = skipped.into_iter().collect();
+        skipped.sort();
+        let skipped: Vec<_> = skipped.iter().map(|u| u.to_string()).collect();
+        eprintln!(
+            "Warning: OBJ files cannot store these attributes (only {}), \
+             they are skipped: {}",
+            OBJ_ATTRIBUTES.map(|u| u.to_string()).join(", "),
+            skipped.join(", ")
+        );
+    }
+    if with_data.descriptor().n_instances > 0 {
+        eprintln!(
+            "Warning: OBJ files cannot store instances, they are skipped."
+        );
+    }
+
+    write_bytes_file(
+        &args_cmd.outpath.out_file,
+        args_cmd.oarg.overwrite,
+        out.as_bytes(),
+    )
+}
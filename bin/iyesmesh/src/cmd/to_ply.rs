@@ -0,0 +1,311 @@
+use std::fmt::Write;
+
+use iyes_mesh::convert::linear_to_srgb;
+use iyes_mesh::descriptor::{ColorSpace, VertexUsage};
+use iyes_mesh::mesh::{MeshDataRef, concatenate};
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{
+    decode_special_attributes, open_input, with_decoded, write_bytes_file,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct ToPlyArgs {
+    /// Only export this mesh (by default, all meshes are combined into
+    /// one, with a comment in the header saying where each one is)
+    #[arg(short, long, value_parser = crate::util::parse_mesh_index)]
+    mesh: Option<usize>,
+    /// Write an ASCII file, instead of binary little-endian
+    #[arg(long)]
+    ascii: bool,
+    /// Only write the vertices, without faces (for point clouds, like
+    /// those imported from PLY files without faces)
+    #[arg(long)]
+    points: bool,
+    #[command(flatten)]
+    rarg: crate::ReadArgs,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    inpath: crate::InputPath,
+    #[command(flatten)]
+    outpath: crate::OutputPath,
+}
+
+/// The attributes that PLY files can store.
+const PLY_ATTRIBUTES: [VertexUsage; 4] = [
+    VertexUsage::Position,
+    VertexUsage::Normal,
+    VertexUsage::Uv0,
+    VertexUsage::Color,
+];
+
+pub fn run(
+    _args_common: &CommonArgs,
+    args_cmd: &ToPlyArgs,
+) -> AnyResult<()> {
+    let mut infile = open_input(&args_cmd.inpath.in_file)
+        .context("Could not open input file")?;
+    let reader = IyesMeshReader::init_with_settings(
+        IyesMeshReaderSettings::from(&args_cmd.rarg),
+        &mut infile,
+    )
+    .context("Cannot decode file metadata and initialize decoding")?;
+    let n_meshes = reader.descriptor().meshes.len();
+    if let Some(index) = args_cmd.mesh
+        && index >= n_meshes
+    {
+        bail!(
+            "Mesh {} does not exist (the file has {} meshes)",
+            index,
+            n_meshes
+        );
+    }
+    let with_data =
+        reader.read_all_data().context("Cannot decode file data")?;
+    let flatbufs =
+        with_data.into_flat_buffers().context("Cannot decode file buffers")?;
+    let meshes = with_data
+        .into_split_meshes(&flatbufs)
+        .context("Cannot decode file meshes")?;
+    let decoded = decode_special_attributes(&with_data, &meshes);
+
+    let mut comments = vec![];
+    let mut selected = vec![];
+    let (mut first_vertex, mut first_face) = (0, 0);
+    for (i, mesh) in meshes.meshes.iter().enumerate() {
+        if args_cmd.mesh.is_some_and(|index| index != i) {
+            continue;
+        }
+        let mesh = with_decoded(mesh, &decoded[i]);
+        if let Some((_, index)) =
+            mesh.find_out_of_range_indices().first().copied()
+        {
+            bail!("Mesh {} has an out of range index: {}", i, index);
+        }
+        let mut comment = format!(
+            "mesh {}: vertices {}..{}",
+            i,
+            first_vertex,
+            first_vertex + mesh.n_vertices(),
+        );
+        let mut n_faces = 0;
+        if !args_cmd.points {
+            n_faces = mesh
+                .iter_triangles()
+                .with_context(|| {
+                    format!(
+                        "Mesh {} is not a triangle list (see --points)",
+                        i
+                    )
+                })?
+                .count();
+            let faces = first_face..first_face + n_faces;
+            write!(comment, ", faces {}..{}", faces.start, faces.end)?;
+        }
+        comments.push(comment);
+        first_vertex += mesh.n_vertices();
+        first_face += n_faces;
+        selected.push(mesh);
+    }
+    let combined;
+    let mesh = match selected.as_slice() {
+        [mesh] => mesh.clone(),
+        _ => {
+            combined =
+                concatenate(&selected).context("Cannot combine the meshes")?;
+            combined.as_mesh_ref()
+        }
+    };
+    let linear_colors = with_data
+        .descriptor()
+        .color_spaces
+        .get(&VertexUsage::Color)
+        == Some(&ColorSpace::Linear);
+    let ply = PlyMesh::new(&mesh, linear_colors, args_cmd.points)?;
+
+    let mut skipped: Vec<_> = mesh
+        .attributes
+        .keys()
+        .filter(|usage| !ply.stores(**usage))
+        .collect();
+    if !skipped.is_empty() {
+        skipped.sort();
+        let skipped: Vec<_> = skipped.iter().map(|u| u.to_string()).collect();
+        eprintln!(
+            "Warning: PLY files cannot store these attributes (only {}), \
+             they are skipped: {}",
+            PLY_ATTRIBUTES.map(|u| u.to_string()).join(", "),
+            skipped.join(", ")
+        );
+    }
+    if with_data.descriptor().n_instances > 0 {
+        eprintln!(
+            "Warning: PLY files cannot store instances, they are skipped."
+        );
+    }
+
+    let bytes = ply.to_bytes(&comments, args_cmd.ascii)?;
+    write_bytes_file(
+        &args_cmd.outpath.out_file,
+        args_cmd.oarg.overwrite,
+        &bytes,
+    )
+}
+
+/// The data of the PLY file, decoded.
+struct PlyMesh {
+    positions: Vec<[f32; 3]>,
+    normals: Option<Vec<[f32; 3]>>,
+    uvs: Option<Vec<[f32; 2]>>,
+    colors: Option<Vec<[u8; 4]>>,
+    faces: Option<Vec<[u32; 3]>>,
+}
+
+impl PlyMesh {
+    /// Decode the data of a mesh. Colors are converted to 8 bits, with the
+    /// sRGB transfer function if `linear_colors`.
+    fn new(
+        mesh: &MeshDataRef<'_>,
+        linear_colors: bool,
+        points: bool,
+    ) -> AnyResult<Self> {
+        let Some(positions) = mesh.positions_f32() else {
+            bail!("The meshes have no positions (in a float format)");
+        };
+        let colors = mesh.colors_f32().map(|i| i.collect::<Vec<_>>()).or_else(
+            || {
+                let rgb = mesh.attribute_f32::<3>(VertexUsage::Color)?;
+                Some(rgb.map(|[r, g, b]| [r, g, b, 1.0]).collect())
+            },
+        );
+        let colors = colors.map(|colors| {
+            colors
+                .into_iter()
+                .map(|c| {
+                    std::array::from_fn(|i| {
+                        let mut v = c[i] as f64;
+                        if linear_colors && i < 3 {
+                            v = linear_to_srgb(v);
+                        }
+                        (v * 255.0).round().clamp(0.0, 255.0) as u8
+                    })
+                })
+                .collect()
+        });
+        Ok(Self {
+            positions: positions.collect(),
+            normals: mesh.normals_f32().map(Iterator::collect),
+            uvs: mesh.uvs_f32(VertexUsage::Uv0).map(Iterator::collect),
+            colors,
+            faces: if points {
+                None
+            } else {
+                Some(mesh.iter_triangles()?.collect())
+            },
+        })
+    }
+
+    /// Whether the PLY file gets the attribute.
+    fn stores(&self, usage: VertexUsage) -> bool {
+        match usage {
+            VertexUsage::Position => true,
+            VertexUsage::Normal => self.normals.is_some(),
+            VertexUsage::Uv0 => self.uvs.is_some(),
+            VertexUsage::Color => self.colors.is_some(),
+            _ => false,
+        }
+    }
+
+    fn to_bytes(
+        &self,
+        comments: &[String],
+        ascii: bool,
+    ) -> AnyResult<Vec<u8>> {
+        let mut header = String::new();
+        writeln!(header, "ply")?;
+        let format = if ascii { "ascii" } else { "binary_little_endian" };
+        writeln!(header, "format {} 1.0", format)?;
+        writeln!(
+            header,
+            "comment written by iyesmesh {}",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        for comment in comments {
+            writeln!(header, "comment {}", comment)?;
+        }
+        writeln!(header, "element vertex {}", self.positions.len())?;
+        // In the order they are written.
+        let mut properties =
+            vec![("float", "x"), ("float", "y"), ("float", "z")];
+        if self.normals.is_some() {
+            properties.extend([
+                ("float", "nx"),
+                ("float", "ny"),
+                ("float", "nz"),
+            ]);
+        }
+        if self.uvs.is_some() {
+            properties.extend([("float", "s"), ("float", "t")]);
+        }
+        if self.colors.is_some() {
+            properties.extend([
+                ("uchar", "red"),
+                ("uchar", "green"),
+                ("uchar", "blue"),
+                ("uchar", "alpha"),
+            ]);
+        }
+        for (ty, name) in properties {
+            writeln!(header, "property {} {}", ty, name)?;
+        }
+        if let Some(faces) = &self.faces {
+            writeln!(header, "element face {}", faces.len())?;
+            writeln!(header, "property list uchar uint vertex_indices")?;
+        }
+        writeln!(header, "end_header")?;
+
+        let mut out = header.into_bytes();
+        let mut line = String::new();
+        for i in 0..self.positions.len() {
+            let mut floats = self.positions[i].to_vec();
+            if let Some(normals) = &self.normals {
+                floats.extend(normals[i]);
+            }
+            if let Some(uvs) = &self.uvs {
+                floats.extend(uvs[i]);
+            }
+            let color = self.colors.as_ref().map(|c| c[i]);
+            if ascii {
+                line.clear();
+                for v in floats {
+                    write!(line, "{} ", v)?;
+                }
+                for v in color.into_iter().flatten() {
+                    write!(line, "{} ", v)?;
+                }
+                out.extend_from_slice(line.trim_end().as_bytes());
+                out.push(b'\n');
+            } else {
+                for v in floats {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+                out.extend(color.into_iter().flatten());
+            }
+        }
+        for [a, b, c] in self.faces.iter().flatten() {
+            if ascii {
+                let line = format!("3 {} {} {}\n", a, b, c);
+                out.extend_from_slice(line.as_bytes());
+            } else {
+                out.push(3);
+                for v in [a, b, c] {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+            }
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt::Write;
+
+use iyes_mesh::descriptor::VertexUsage;
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings};
+
+use crate::CommonArgs;
+use crate::prelude::*;
+use crate::util::{
+    decode_special_attributes, open_input, with_decoded, write_bytes_file,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct ToStlArgs {
+    /// Only export this mesh (by default, the triangles of all meshes are
+    /// written together)
+    #[arg(short, long, value_parser = crate::util::parse_mesh_index)]
+    mesh: Option<usize>,
+    /// Write an ASCII file, instead of binary
+    #[arg(long)]
+    ascii: bool,
+    #[command(flatten)]
+    rarg: crate::ReadArgs,
+    #[command(flatten)]
+    oarg: crate::OutputArgs,
+    #[command(flatten)]
+    inpath: crate::InputPath,
+    #[command(flatten)]
+    outpath: crate::OutputPath,
+}
+
+pub fn run(
+    args_common: &CommonArgs,
+    args_cmd: &ToStlArgs,
+) -> AnyResult<()> {
+    let mut infile = open_input(&args_cmd.inpath.in_file)
+        .context("Could not open input file")?;
+    let reader = IyesMeshReader::init_with_settings(
+        IyesMeshReaderSettings::from(&args_cmd.rarg),
+        &mut infile,
+    )
+    .context("Cannot decode file metadata and initialize decoding")?;
+    let n_meshes = reader.descriptor().meshes.len();
+    if let Some(index) = args_cmd.mesh
+        && index >= n_meshes
+    {
+        bail!(
+            "Mesh {} does not exist (the file has {} meshes)",
+            index,
+            n_meshes
+        );
+    }
+    let with_data =
+        reader.read_all_data().context("Cannot decode file data")?;
+    let flatbufs =
+        with_data.into_flat_buffers().context("Cannot decode file buffers")?;
+    let meshes = with_data
+        .into_split_meshes(&flatbufs)
+        .context("Cannot decode file meshes")?;
+    let decoded = decode_special_attributes(&with_data, &meshes);
+
+    let mut triangles: Vec<[[f32; 3]; 3]> = vec![];
+    let mut skipped = vec![];
+    for (i, mesh) in meshes.meshes.iter().enumerate() {
+        if args_cmd.mesh.is_some_and(|index| index != i) {
+            continue;
+        }
+        let mesh = with_decoded(mesh, &decoded[i]);
+        let Some(positions) = mesh.positions_f32() else {
+            bail!("Mesh {} has no positions (in a float format)", i);
+        };
+        let positions: Vec<_> = positions.collect();
+        if let Some((_, index)) =
+            mesh.find_out_of_range_indices().first().copied()
+        {
+            bail!("Mesh {} has an out of range index: {}", i, index);
+        }
+        let mesh_triangles = mesh
+            .iter_triangles()
+            .with_context(|| format!("Mesh {} is not a triangle list", i))?;
+        let n_before = triangles.len();
+        triangles.extend(
+            mesh_triangles.map(|t| t.map(|index| positions[index as usize])),
+        );
+        for usage in mesh.attributes.keys() {
+            // Normals are not skipped, STL files have (flat) normals.
+            if !matches!(usage, VertexUsage::Position | VertexUsage::Normal)
+                && !skipped.contains(usage)
+            {
+                skipped.push(*usage);
+            }
+        }
+        if args_common.verbose {
+            eprintln!("Mesh {}: {} triangles", i, triangles.len() - n_before);
+        }
+    }
+
+    if !skipped.is_empty() {
+        skipped.sort();
+        let skipped: Vec<_> = skipped.iter().map(|u| u.to_string()).collect();
+        eprintln!(
+            "Warning: STL files only store positions (and facet normals), \
+             these attributes are skipped: {}",
+            skipped.join(", ")
+        );
+    }
+    if with_data.descriptor().n_instances > 0 {
+        eprintln!(
+            "Warning: STL files cannot store instances, they are skipped."
+        );
+    }
+
+    let bytes = if args_cmd.ascii {
+        stl_ascii(&triangles)?.into_bytes()
+    } else {
+        stl_binary(&triangles)?
+    };
+    write_bytes_file(
+        &args_cmd.outpath.out_file,
+        args_cmd.oarg.overwrite,
+        &bytes,
+    )
+}
+
+/// A binary STL file: an 80-byte header, the triangle count, then 50 bytes
+/// per triangle (normal, vertices, and a zero "attribute byte count").
+fn stl_binary(triangles: &[[[f32; 3]; 3]]) -> AnyResult<Vec<u8>> {
+    let Ok(count) = u32::try_from(triangles.len()) else {
+        bail!("Too many triangles for an STL file: {}", triangles.len());
+    };
+    // Must not begin with `solid`, to not look like an ASCII file.
+    let mut out = format!(
+        "binary STL written by iyesmesh {}",
+        env!("CARGO_PKG_VERSION")
+    )
+    .into_bytes();
+    out.resize(80, b' ');
+    out.extend_from_slice(&count.to_le_bytes());
+    for t in triangles {
+        for v in std::iter::once(facet_normal(t)).chain(t.iter().copied()) {
+            for c in v {
+                out.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        out.extend_from_slice(&0u16.to_le_bytes());
+    }
+    Ok(out)
+}
+
+fn stl_ascii(triangles: &[[[f32; 3]; 3]]) -> AnyResult<String> {
+    let mut out = String::new();
+    writeln!(out, "solid iyesmesh")?;
+    for t in triangles {
+        let [x, y, z] = facet_normal(t);
+        writeln!(out, "  facet normal {} {} {}", x, y, z)?;
+        writeln!(out, "    outer loop")?;
+        for [x, y, z] in t {
+            writeln!(out, "      vertex {} {} {}", x, y, z)?;
+        }
+        writeln!(out, "    endloop")?;
+        writeln!(out, "  endfacet")?;
+    }
+    writeln!(out, "endsolid iyesmesh")?;
+    Ok(out)
+}
+
+/// The unit normal of a triangle (counter-clockwise), or zero if it is
+/// degenerate.
+fn facet_normal([a, b, c]: &[[f32; 3]; 3]) -> [f32; 3] {
+    let u: [f32; 3] = std::array::from_fn(|i| b[i] - a[i]);
+    let v: [f32; 3] = std::array::from_fn(|i| c[i] - a[i]);
+    let n = [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+    let length = n.iter().map(|c| c * c).sum::<f32>().sqrt();
+    if length.is_finite() && length > 0.0 {
+        // Adding zero turns -0 into 0.
+        n.map(|c| c / length + 0.0)
+    } else {
+        [0.0; 3]
+    }
+}
@@ -1,66 +1,401 @@
+use std::io::Seek;
+
+use iyes_mesh::HashMap;
+use iyes_mesh::checksum::ChecksumKind;
+use iyes_mesh::descriptor::{IyesMeshDescriptor, VertexUsage};
+use iyes_mesh::mesh::{MeshDataRef, check_joint_weights, find_non_finite};
 use iyes_mesh::read::IyesMeshReader;
 use iyes_mesh::read::IyesMeshReaderSettings;
 
 use crate::CommonArgs;
+use crate::batch::{find_inputs, map_ordered};
 use crate::prelude::*;
+use crate::util::{InputFile, open_input};
+
+/// Allowed error of the sum of joint weights, enough for Unorm8x4 data
+/// rounded to nearest.
+const JOINT_WEIGHT_TOLERANCE: f32 = 0.01;
 
 #[derive(clap::Args, Debug)]
 pub struct VerifyArgs {
+    /// Also check the mesh data for invalid values
+    ///
+    /// Errors (which fail verification): mesh ranges exceeding the buffers
+    /// of the file, out-of-range indices, index or vertex counts that do
+    /// not make whole triangles, NaN and infinite float values. Warnings:
+    /// joint weights that do not sum to 1.
+    #[arg(long)]
+    deep: bool,
+    /// Require the file to be signed with the key matching this public key
+    ///
+    /// The key is a PEM file (as made by `openssl pkey -pubout`), or the
+    /// 32-byte Ed25519 public key stored raw.
+    #[cfg(feature = "signing")]
+    #[arg(long, value_name = "KEY_FILE")]
+    pubkey: Option<std::path::PathBuf>,
+    /// Also verify the `.ima` files in input directories and their
+    /// subdirectories
+    #[arg(short, long)]
+    recursive: bool,
+    #[command(flatten)]
+    jobs: crate::JobsArgs,
     #[command(flatten)]
     inarg: crate::ReadArgs,
     #[command(flatten)]
-    inpath: crate::InputPath,
+    inpaths: crate::InputPaths,
 }
 
+/// Exit code if some files could not be opened.
+const EXIT_CANNOT_OPEN: i32 = 3;
+
 pub fn run(
     args_common: &CommonArgs,
     args_cmd: &VerifyArgs,
 ) -> AnyResult<()> {
-    let mut settings = IyesMeshReaderSettings {
+    let settings = IyesMeshReaderSettings {
         verify_metadata_checksum: true,
         verify_data_checksum: true,
+        verify_user_data_checksum: true,
+        allow_trailing_data: args_cmd.inarg.allow_trailing_data,
+        #[cfg(feature = "encryption")]
+        user_data_key: None,
+        #[cfg(feature = "signing")]
+        verifying_key: args_cmd
+            .pubkey
+            .as_deref()
+            .map(crate::util::load_verifying_key)
+            .transpose()?,
     };
-    if args_cmd.inarg.ignore_checksums {
-        if let Err(e) = try_run(args_common, args_cmd, settings) {
-            eprintln!("Error! {:#}", e);
-            eprintln!("Warning! Trying again without checksum verification.");
-            settings.verify_metadata_checksum = false;
-            settings.verify_data_checksum = false;
-            try_run(args_common, args_cmd, settings)
-        } else {
-            Ok(())
+    if args_cmd.inpaths.in_files.is_empty() {
+        bail!("No input files provided.");
+    }
+    let (mut n_passed, mut n_failed, mut n_cannot_open) = (0, 0, 0);
+    // To list them again at the end, with --recursive.
+    let mut failed = vec![];
+    let paths = if args_cmd.recursive {
+        let (inputs, failures) = find_inputs(
+            &args_cmd.inpaths.in_files,
+            &["ima"],
+            args_common.verbose,
+        );
+        for (path, e) in failures.iter() {
+            println!("{}: CANNOT OPEN: {:#}", path.display(), e);
+            n_cannot_open += 1;
+            failed.push(path.clone());
         }
+        inputs.into_iter().map(|input| input.path).collect()
     } else {
-        try_run(args_common, args_cmd, settings)
+        args_cmd.inpaths.in_files.clone()
+    };
+    let n_files = paths.len() + n_cannot_open;
+    map_ordered(
+        &paths,
+        args_cmd.jobs.get(),
+        |path| verify_file(args_common, args_cmd, settings, path),
+        |path, (outcome, log)| {
+            eprint!("{}", log.err);
+            print!("{}", log.out);
+            match outcome {
+                Outcome::Passed => n_passed += 1,
+                Outcome::Failed => n_failed += 1,
+                Outcome::CannotOpen => n_cannot_open += 1,
+            }
+            if outcome != Outcome::Passed {
+                failed.push(path.clone());
+            }
+        },
+    );
+    if n_files > 1 || args_cmd.recursive {
+        print!("{} files: {} passed, {} failed", n_files, n_passed, n_failed);
+        if n_cannot_open > 0 {
+            print!(", {} could not be opened", n_cannot_open);
+        }
+        println!(".");
+    }
+    if args_cmd.recursive && !failed.is_empty() {
+        println!("Failed:");
+        for path in failed.iter() {
+            println!("  {}", path.display());
+        }
+    }
+    if n_cannot_open > 0 {
+        std::process::exit(EXIT_CANNOT_OPEN);
+    }
+    if n_failed > 0 {
+        bail!("{} of {} files failed verification.", n_failed, n_files);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Passed,
+    Failed,
+    CannotOpen,
+}
+
+/// What verifying a file prints, to print it in the order of the files,
+/// even if they are verified at the same time (see --jobs).
+#[derive(Default)]
+struct Log {
+    /// For stdout.
+    out: String,
+    /// For stderr.
+    err: String,
+}
+
+impl Log {
+    fn out(
+        &mut self,
+        line: std::fmt::Arguments<'_>,
+    ) {
+        self.out += &format!("{}\n", line);
+    }
+
+    fn err(
+        &mut self,
+        line: std::fmt::Arguments<'_>,
+    ) {
+        self.err += &format!("{}\n", line);
     }
 }
 
-pub fn try_run(
+/// Verify one file (again without checksums if they are wrong, with
+/// --ignore-checksums).
+fn verify_file(
     args_common: &CommonArgs,
     args_cmd: &VerifyArgs,
     settings: IyesMeshReaderSettings,
-) -> AnyResult<()> {
-    let mut file = std::fs::File::open(&args_cmd.inpath.in_file)
-        .context("Could not open input file")?;
-    let reader = IyesMeshReader::init_with_settings(settings, &mut file)
+    path: &Path,
+) -> (Outcome, Log) {
+    let mut log = Log::default();
+    if args_common.verbose {
+        log.err(format_args!("Verifying {}...", path.display()));
+    }
+    let mut file = match open_input(path) {
+        Ok(file) => file,
+        Err(e) => {
+            log.out(format_args!("{}: CANNOT OPEN: {:#}", path.display(), e));
+            return (Outcome::CannotOpen, log);
+        }
+    };
+    let mut result =
+        try_run(args_common, args_cmd, settings, path, &mut file, &mut log);
+    if args_cmd.inarg.ignore_checksums
+        && let Err(e) = &result
+    {
+        log.err(format_args!("Error! {:#}", e));
+        log.err(format_args!(
+            "Warning! Trying again without checksum verification."
+        ));
+        let settings = IyesMeshReaderSettings {
+            verify_metadata_checksum: false,
+            verify_data_checksum: false,
+            verify_user_data_checksum: false,
+            ..settings
+        };
+        result =
+            try_run(args_common, args_cmd, settings, path, &mut file, &mut log)
+                .map(|status| format!("{}, not verified", status));
+    }
+    match result {
+        Ok(status) => {
+            log.out(format_args!(
+                "{}: OK (checksum: {})",
+                path.display(),
+                status
+            ));
+            (Outcome::Passed, log)
+        }
+        Err(e) => {
+            log.out(format_args!("{}: FAILED: {:#}", path.display(), e));
+            (Outcome::Failed, log)
+        }
+    }
+}
+
+/// Verify one file, returning the name of its checksum algorithm.
+fn try_run(
+    args_common: &CommonArgs,
+    args_cmd: &VerifyArgs,
+    settings: IyesMeshReaderSettings,
+    path: &Path,
+    file: &mut InputFile,
+    log: &mut Log,
+) -> AnyResult<String> {
+    file.rewind()?;
+    let reader = IyesMeshReader::init_with_settings(settings, file)
         .context("Cannot decode file metadata and initialize decoding")?;
     if args_common.verbose {
-        eprintln!("File metadata OK.");
+        log.err(format_args!("File metadata OK."));
+    }
+    if args_cmd.deep {
+        // The meshes cannot be split from the data if their ranges are
+        // wrong, so this is checked up front.
+        let mut findings = vec![];
+        check_mesh_ranges(reader.descriptor(), &mut findings);
+        report_findings(path, &findings, log)?;
     }
+    let id = reader.descriptor().checksum_kind;
+    let algorithm = match ChecksumKind::name_of_id(id) {
+        Some(name) if ChecksumKind::from_id(id).is_some() => name.to_owned(),
+        Some(name) => format!("{} (not supported)", name),
+        None => format!("unknown (id {})", id),
+    };
     let with_data = reader.read_all_data()
         .context("Cannot decode file data")?;
     if args_common.verbose {
-        eprintln!("File data successfully decoded.");
+        log.err(format_args!("File data successfully decoded."));
     }
     let bufs = with_data.into_flat_buffers()
         .context("Cannot parse file data as flat buffers")?;
     if args_common.verbose {
-        eprintln!("File data successfully parsed as flat buffers.");
+        log.err(format_args!("File data successfully parsed as flat buffers."));
     }
-    let _meshes = with_data.into_split_meshes(&bufs)
+    let meshes = with_data.into_split_meshes(&bufs)
         .context("Cannot parse file data as split meshes")?;
     if args_common.verbose {
-        eprintln!("File data successfully parsed as split meshes.");
+        log.err(format_args!("File data successfully parsed as split meshes."));
+    }
+    if !args_cmd.deep {
+        return Ok(algorithm);
+    }
+    let mut findings = vec![];
+    for (i, mesh) in meshes.meshes.iter().enumerate() {
+        check_mesh_data(i, mesh, &mut findings);
+    }
+    report_findings(path, &findings, log)?;
+    if args_common.verbose {
+        log.err(format_args!("Mesh data OK."));
+    }
+    let n_warnings = findings.len();
+    if n_warnings > 0 {
+        return Ok(format!("{}, {} warnings", algorithm, n_warnings));
+    }
+    Ok(algorithm)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Severity {
+    /// The file cannot be used as-is.
+    Error,
+    /// Suspicious data that engines usually cope with.
+    Warning,
+}
+
+/// A problem found by the deep checks.
+struct Finding {
+    severity: Severity,
+    mesh: usize,
+    usage: Option<VertexUsage>,
+    message: String,
+}
+
+/// Print the findings, and fail if any of them is an error.
+fn report_findings(
+    path: &Path,
+    findings: &[Finding],
+    log: &mut Log,
+) -> AnyResult<()> {
+    for finding in findings {
+        let severity = match finding.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let usage = finding
+            .usage
+            .map(|usage| format!(", {}", usage))
+            .unwrap_or_default();
+        log.out(format_args!(
+            "{}: {}: mesh {}{}: {}",
+            path.display(),
+            severity,
+            finding.mesh,
+            usage,
+            finding.message
+        ));
+    }
+    let n_errors = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+    if n_errors > 0 {
+        bail!("Found {} errors in the mesh data.", n_errors);
     }
     Ok(())
 }
+
+/// Check the ranges of the meshes against the buffers of the file.
+fn check_mesh_ranges(
+    descriptor: &IyesMeshDescriptor,
+    findings: &mut Vec<Finding>,
+) {
+    for (mesh, error) in descriptor.check_mesh_ranges() {
+        findings.push(Finding {
+            severity: Severity::Error,
+            mesh,
+            usage: None,
+            message: error.to_string(),
+        });
+    }
+}
+
+/// Check the data of a mesh.
+fn check_mesh_data(
+    i: usize,
+    mesh: &MeshDataRef<'_>,
+    findings: &mut Vec<Finding>,
+) {
+    let mut push = |severity, usage, message| {
+        findings.push(Finding {
+            severity,
+            mesh: i,
+            usage,
+            message,
+        });
+    };
+    let bad = mesh.find_out_of_range_indices();
+    if let Some((position, index)) = bad.first() {
+        push(
+            Severity::Error,
+            None,
+            format!(
+                "{} indices out of range for {} vertices (first: {} at {})",
+                bad.len(),
+                mesh.n_vertices(),
+                index,
+                position
+            ),
+        );
+    }
+    if let Err(e) = mesh.iter_triangles() {
+        push(Severity::Error, None, e.to_string());
+    }
+    let mut counts = HashMap::default();
+    for (usage, _, _) in find_non_finite(mesh) {
+        *counts.entry(usage).or_insert(0) += 1;
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by_key(|(usage, _)| *usage);
+    for (usage, count) in counts {
+        let message = format!("{} non-finite values", count);
+        push(Severity::Error, Some(usage), message);
+    }
+    let usage = VertexUsage::JointWeight;
+    if mesh.attributes.contains_key(&usage) {
+        match check_joint_weights(mesh, JOINT_WEIGHT_TOLERANCE) {
+            Ok(bad) if bad.is_empty() => {}
+            Ok(bad) => {
+                let message = format!(
+                    "{} vertices with joint weights not summing to 1",
+                    bad.len()
+                );
+                push(Severity::Warning, Some(usage), message);
+            }
+            Err(e) => {
+                let message = format!("Cannot check joint weights: {}", e);
+                push(Severity::Warning, Some(usage), message);
+            }
+        }
+    }
+}
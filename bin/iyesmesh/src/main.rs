@@ -1,3 +1,4 @@
+use iyes_mesh::checksum::ChecksumKind;
 use iyes_mesh::{read::IyesMeshReaderSettings, write::IyesMeshWriterSettings};
 
 use crate::prelude::*;
@@ -9,20 +10,67 @@ mod prelude {
     pub use anyhow::{Context, Result as AnyResult, bail};
 }
 
+/// Like `println!`, for messages: they go to stderr instead when the
+/// output is written to stdout (see [`util::take_stdout`]).
+macro_rules! infoln {
+    ($($arg:tt)*) => {
+        if $crate::util::stdout_taken() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
 mod cmd {
+    pub mod apply_patch;
+    pub mod diff;
+    pub mod diff_patch;
+    pub mod dump_buffer;
     pub mod edit;
+    pub mod extract_mesh;
     pub mod extract_user_data;
+    pub mod fix_checksums;
+    pub mod hash;
     pub mod info;
     pub mod verify;
     pub mod merge;
+    pub mod recompress;
+    pub mod recover;
+    pub mod split;
+    pub mod stats;
+    pub mod strip;
+    #[cfg(feature = "meshopt")]
+    pub mod simplify;
+    #[cfg(feature = "gltf")]
+    pub mod from_gltf;
     #[cfg(feature = "obj")]
     pub mod from_obj;
+    #[cfg(feature = "ply")]
+    pub mod from_ply;
+    #[cfg(feature = "stl")]
+    pub mod from_stl;
+    #[cfg(feature = "gltf")]
+    pub mod to_gltf;
+    #[cfg(feature = "obj")]
+    pub mod to_obj;
+    #[cfg(feature = "ply")]
+    pub mod to_ply;
+    #[cfg(feature = "stl")]
+    pub mod to_stl;
 }
 
+mod batch;
 mod util;
 
 #[derive(clap::Parser, Debug)]
 #[command(about = "Tool for working with MineWars data files.")]
+#[command(after_help = "Input file paths can be `-`, to read the file from \
+                        stdin (once per command).\n\n\
+                        Output file paths can be `-`, to write the file to \
+                        stdout. IMA files are then encoded in memory first \
+                        (to fill in the checksums), and messages go to \
+                        stderr.")]
 struct Cli {
     #[command(flatten)]
     common: CommonArgs,
@@ -41,14 +89,30 @@ struct CommonArgs {
 #[derive(clap::Args, Debug)]
 struct WriteArgs {
     /// Zstd compression level (default: max)
-    #[arg(short, long)]
+    #[arg(short, long, allow_negative_numbers = true)]
+    #[arg(value_parser = util::parse_compression_level)]
     level: Option<i32>,
     /// Do not write data checksum into file (faster)
     #[arg(long)]
     no_data_checksum: bool,
+    /// Checksum algorithm (default: rapidhash)
+    #[arg(long, value_parser = util::parse_checksum_kind)]
+    checksum: Option<ChecksumKind>,
     /// Convert index data from U16 to U32 if needed
     #[arg(long)]
     upconvert_indices: bool,
+    /// Store positions as 16-bit values relative to each mesh's bounds
+    ///
+    /// Input files with quantized positions are always decoded first,
+    /// so without this flag, they are written as Float32x3.
+    #[arg(long)]
+    quantize_positions: bool,
+    /// Store normals and tangents octahedral-encoded in 4 bytes each
+    ///
+    /// Input files with encoded normals or tangents are always decoded
+    /// first, so without this flag, they are written as floats.
+    #[arg(long)]
+    octahedral_normals: bool,
 }
 
 #[derive(clap::Args, Debug)]
@@ -56,6 +120,27 @@ struct ReadArgs {
     /// Try to process files even if checksums are wrong
     #[arg(long)]
     ignore_checksums: bool,
+    /// Ignore extra data after the data declared by the file metadata
+    #[arg(long)]
+    allow_trailing_data: bool,
+}
+
+#[cfg(feature = "encryption")]
+#[derive(clap::Args, Debug)]
+struct UserDataKeyArgs {
+    /// File with the key to encrypt/decrypt the user data with
+    ///
+    /// The key is 32 bytes, stored raw or as 64 hex digits.
+    #[arg(long)]
+    user_data_key_file: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct JobsArgs {
+    /// Number of files to process at the same time (default: the number
+    /// of CPU cores)
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<std::num::NonZeroUsize>,
 }
 
 #[derive(clap::Args, Debug)]
@@ -67,33 +152,34 @@ struct OutputArgs {
 
 #[derive(clap::Args, Debug)]
 struct InputPath {
-    /// Path to the input file
+    /// Path to the input file (`-` for stdin)
     in_file: PathBuf,
 }
 
 #[derive(clap::Args, Debug)]
 struct InputPaths {
-    /// Path to the input files
+    /// Path to the input files (`-` for stdin, once)
     in_files: Vec<PathBuf>,
 }
 
 #[derive(clap::Args, Debug)]
 struct OutputPath {
-    /// Path where to save the output file
+    /// Path where to save the output file (`-` for stdout)
     out_file: PathBuf,
 }
 
 #[derive(clap::Args, Debug)]
 struct InOutPaths {
-    /// Path to the input file
+    /// Path to the input file (`-` for stdin)
     in_file: PathBuf,
-    /// Path to the output file (if unspecified, overwrite the input)
+    /// Path to the output file (if unspecified, overwrite the input; `-`
+    /// for stdout)
     out_file: Option<PathBuf>,
 }
 
 #[derive(clap::Args, Debug)]
 struct OptOutputPath {
-    /// Path where to save the output file (stdout if unspecified)
+    /// Path where to save the output file (stdout if unspecified or `-`)
     out_file: Option<PathBuf>,
 }
 
@@ -103,17 +189,89 @@ enum CliCommand {
     Version,
     /// Show general info about the file
     Info(cmd::info::InfoArgs),
-    /// Try decoding the file to check for errors
+    /// Try decoding files to check for errors
+    ///
+    /// Each file is verified independently. Exits with code 2 if any file
+    /// failed verification, or 3 if any file could not be opened.
     Verify(cmd::verify::VerifyArgs),
+    /// Decode a file and show how much each buffer takes, raw and compressed
+    Stats(cmd::stats::StatsArgs),
+    /// Compare the decoded contents of two files
+    ///
+    /// Exits with code 0 if the contents are identical (even if the files
+    /// are encoded differently), 1 if they differ, or 2 on errors.
+    Diff(cmd::diff::DiffArgs),
+    /// Print hashes of the contents of files (for caching)
+    ///
+    /// Hashes the whole file, the decompressed data, the user data, and
+    /// each mesh. They do not depend on the compression of the data, or on
+    /// the order of the attributes in the metadata.
+    Hash(cmd::hash::HashArgs),
+    /// Recompute the checksums in the header of a file, after editing it
+    ///
+    /// The data checksum is computed over the compressed data as it is,
+    /// and only the header is rewritten. The user data checksum (part of
+    /// the metadata) is not fixed.
+    FixChecksums(cmd::fix_checksums::FixChecksumsArgs),
     /// Load a file, make some changes, save the changes
-    Edit(cmd::edit::EditArgs),
+    Edit(Box<cmd::edit::EditArgs>),
     /// Decode the user data from a file
     ExtractUserData(cmd::extract_user_data::ExtractUserDataArgs),
+    /// Save one mesh of a file as a file of its own
+    ExtractMesh(cmd::extract_mesh::ExtractMeshArgs),
+    /// Save the raw data of a vertex attribute or of the indices to a file
+    ///
+    /// The data is little-endian, with no header. Quantized or encoded
+    /// attributes are decoded.
+    DumpBuffer(cmd::dump_buffer::DumpBufferArgs),
     /// Load several files, save a file with their combined meshes
     Merge(cmd::merge::MergeArgs),
+    /// Save each mesh of a file as a file of its own
+    Split(cmd::split::SplitArgs),
+    /// Decode a file and encode it again with other write settings
+    ///
+    /// Everything else is kept: meshes, attributes (including quantized or
+    /// encoded ones), user data, instances and color spaces.
+    Recompress(cmd::recompress::RecompressArgs),
+    /// Remove what is not needed at runtime (for shipping builds)
+    ///
+    /// Removes the user data and the data checksum (see the --keep-*
+    /// options), and the signature. The file is recompressed at the level
+    /// of the write settings (the max by default).
+    Strip(cmd::strip::StripArgs),
+    /// Save the meshes that can still be decoded from a corrupted file
+    Recover(cmd::recover::RecoverArgs),
+    /// Compare two versions of a file, save a patch from one to the other
+    DiffPatch(cmd::diff_patch::DiffPatchArgs),
+    /// Apply a patch made by diff-patch, save the new version of the file
+    ApplyPatch(cmd::apply_patch::ApplyPatchArgs),
+    /// Reduce the number of triangles of the meshes in a file (for LODs)
+    #[cfg(feature = "meshopt")]
+    Simplify(cmd::simplify::SimplifyArgs),
+    /// Import from glTF format (`.gltf` or `.glb`)
+    #[cfg(feature = "gltf")]
+    FromGltf(cmd::from_gltf::FromGltfArgs),
     /// Import from OBJ format
     #[cfg(feature = "obj")]
     FromObj(cmd::from_obj::FromObjArgs),
+    /// Import from PLY format (ASCII or binary)
+    #[cfg(feature = "ply")]
+    FromPly(cmd::from_ply::FromPlyArgs),
+    /// Import from STL format (ASCII or binary)
+    #[cfg(feature = "stl")]
+    FromStl(cmd::from_stl::FromStlArgs),
+    /// Export to glTF format (`.gltf` and `.bin`, or `.glb`)
+    #[cfg(feature = "gltf")]
+    ToGltf(cmd::to_gltf::ToGltfArgs),
+    /// Export to OBJ format
+    #[cfg(feature = "obj")]
+    ToObj(cmd::to_obj::ToObjArgs),
+    /// Export to PLY format (binary or ASCII)
+    #[cfg(feature = "ply")]
+    ToPly(cmd::to_ply::ToPlyArgs),
+    /// Export to STL format (binary or ASCII)
+    #[cfg(feature = "stl")]
+    ToStl(cmd::to_stl::ToStlArgs),
 }
 
 impl From<&ReadArgs> for IyesMeshReaderSettings {
@@ -121,6 +279,44 @@ impl From<&ReadArgs> for IyesMeshReaderSettings {
         Self {
             verify_metadata_checksum: !args.ignore_checksums,
             verify_data_checksum: !args.ignore_checksums,
+            verify_user_data_checksum: !args.ignore_checksums,
+            allow_trailing_data: args.allow_trailing_data,
+            #[cfg(feature = "encryption")]
+            user_data_key: None,
+            #[cfg(feature = "signing")]
+            verifying_key: None,
+        }
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl UserDataKeyArgs {
+    fn load(&self) -> AnyResult<Option<[u8; 32]>> {
+        self.user_data_key_file
+            .as_deref()
+            .map(util::load_user_data_key)
+            .transpose()
+    }
+}
+
+impl JobsArgs {
+    fn get(&self) -> usize {
+        self.jobs
+            .or_else(|| std::thread::available_parallelism().ok())
+            .map_or(1, |n| n.get())
+    }
+}
+
+impl InOutPaths {
+    /// The output path: the input path if unspecified, which cannot be
+    /// stdin.
+    fn output(&self) -> AnyResult<&Path> {
+        match &self.out_file {
+            Some(out_file) => Ok(out_file),
+            None if util::is_stdin(&self.in_file) => {
+                bail!("The input is stdin (`-`), an output path is needed.")
+            }
+            None => Ok(&self.in_file),
         }
     }
 }
@@ -131,7 +327,12 @@ impl From<&WriteArgs> for IyesMeshWriterSettings {
         Self {
             upconvert_indices: args.upconvert_indices,
             write_data_checksum: !args.no_data_checksum,
+            checksum_kind: args.checksum.unwrap_or(default.checksum_kind),
             compression_level: args.level.unwrap_or(default.compression_level),
+            quantize_positions: args.quantize_positions,
+            octahedral_normals: args.octahedral_normals,
+            octahedral_tangents: args.octahedral_normals,
+            ..default
         }
     }
 }
@@ -147,21 +348,60 @@ fn run_command(cli: &Cli) -> AnyResult<()> {
         }
         CliCommand::Info(args) => cmd::info::run(&cli.common, args),
         CliCommand::Verify(args) => cmd::verify::run(&cli.common, args),
+        CliCommand::Stats(args) => cmd::stats::run(&cli.common, args),
+        CliCommand::Diff(args) => cmd::diff::run(&cli.common, args),
+        CliCommand::Hash(args) => cmd::hash::run(&cli.common, args),
         CliCommand::ExtractUserData(args) => {
             cmd::extract_user_data::run(&cli.common, args)
         }
+        CliCommand::ExtractMesh(args) => {
+            cmd::extract_mesh::run(&cli.common, args)
+        }
+        CliCommand::DumpBuffer(args) => {
+            cmd::dump_buffer::run(&cli.common, args)
+        }
         CliCommand::Edit(args) => cmd::edit::run(&cli.common, args),
         CliCommand::Merge(args) => cmd::merge::run(&cli.common, args),
+        CliCommand::Split(args) => cmd::split::run(&cli.common, args),
+        CliCommand::FixChecksums(args) => {
+            cmd::fix_checksums::run(&cli.common, args)
+        }
+        CliCommand::Recompress(args) => {
+            cmd::recompress::run(&cli.common, args)
+        }
+        CliCommand::Strip(args) => cmd::strip::run(&cli.common, args),
+        CliCommand::Recover(args) => cmd::recover::run(&cli.common, args),
+        CliCommand::DiffPatch(args) => cmd::diff_patch::run(&cli.common, args),
+        CliCommand::ApplyPatch(args) => {
+            cmd::apply_patch::run(&cli.common, args)
+        }
+        #[cfg(feature = "meshopt")]
+        CliCommand::Simplify(args) => cmd::simplify::run(&cli.common, args),
+        #[cfg(feature = "gltf")]
+        CliCommand::FromGltf(args) => cmd::from_gltf::run(&cli.common, args),
         #[cfg(feature = "obj")]
         CliCommand::FromObj(args) => cmd::from_obj::run(&cli.common, args),
+        #[cfg(feature = "ply")]
+        CliCommand::FromPly(args) => cmd::from_ply::run(&cli.common, args),
+        #[cfg(feature = "stl")]
+        CliCommand::FromStl(args) => cmd::from_stl::run(&cli.common, args),
+        #[cfg(feature = "gltf")]
+        CliCommand::ToGltf(args) => cmd::to_gltf::run(&cli.common, args),
+        #[cfg(feature = "obj")]
+        CliCommand::ToObj(args) => cmd::to_obj::run(&cli.common, args),
+        #[cfg(feature = "ply")]
+        CliCommand::ToPly(args) => cmd::to_ply::run(&cli.common, args),
+        #[cfg(feature = "stl")]
+        CliCommand::ToStl(args) => cmd::to_stl::run(&cli.common, args),
     }
 }
 
 fn print_version() {
     eprintln!(
-        "{} version {}. Works with file format version {}.",
+        "{} version {}. Works with file format versions {} to {}.",
         env!("CARGO_PKG_NAME"),
         env!("CARGO_PKG_VERSION"),
+        iyes_mesh::MIN_FORMAT_VERSION,
         iyes_mesh::FORMAT_VERSION,
     );
     eprintln!();
@@ -175,6 +415,10 @@ fn main() {
         print_version();
     }
 
+    if let Err(e) = util::install_ctrlc_handler() {
+        eprintln!("Warning: {:#}", e);
+    }
+
     if let Err(e) = run_command(&cli) {
         eprintln!("Error: {:#}", e);
         std::process::exit(2);
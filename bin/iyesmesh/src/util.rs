@@ -1,6 +1,20 @@
-use std::io::Read;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use iyes_mesh::read::{is_iyes_mesh_file, IyesMeshReader, IyesMeshReaderSettings};
+use iyes_mesh::cancel::CancelToken;
+use iyes_mesh::checksum::ChecksumKind;
+use iyes_mesh::descriptor::{
+    ColorSpace, IyesMeshDescriptor, VertexFormat, VertexUsage,
+};
+use iyes_mesh::header::IyesMeshHeader;
+use iyes_mesh::io::ReadSeek;
+use iyes_mesh::mesh::{CenterMode, MeshDataRef, NormalMode};
+use iyes_mesh::read::{
+    detect_format, DecodedBuffers, DecodedMeshes, IyesMeshReader,
+    IyesMeshReaderSettings, IyesMeshReaderWithData,
+};
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings, WritePlan};
 
 use crate::prelude::*;
 
@@ -10,17 +24,29 @@ pub fn load_user_data(
     force_raw_file: bool,
 ) -> AnyResult<Vec<u8>> {
     let mut new_user_data = vec![];
-    match &src {
+    match src.filter(|path| !is_stdin(path)) {
         None => {
+            take_stdin()?;
             std::io::stdin()
                 .lock()
                 .read_to_end(&mut new_user_data)
                 .context("Could not read user data from stdin")?;
+            if !force_raw_file && detect_format(&mut new_user_data.as_slice())?
+            {
+                new_user_data = IyesMeshReader::init_with_settings(
+                    settings,
+                    &mut std::io::Cursor::new(new_user_data),
+                )
+                .and_then(|r| r.read_user_data())
+                .context("Cannot extract user data from user data IMA file")?;
+            }
         }
         Some(path) => {
-            let mut udfile = std::fs::File::open(path)
-                .context("Could not open user data file")?;
-            if !force_raw_file && is_iyes_mesh_file(&mut udfile)
+            let mut udfile = std::io::BufReader::new(
+                std::fs::File::open(path)
+                    .context("Could not open user data file")?,
+            );
+            if !force_raw_file && detect_format(&mut udfile)
                 .context("Cannot autodetect file format")?
             {
                 new_user_data = IyesMeshReader::init_with_settings(
@@ -37,3 +63,896 @@ pub fn load_user_data(
     }
     Ok(new_user_data)
 }
+
+/// Above this size, stdin is kept in a temporary file instead of memory.
+const STDIN_MEMORY_LIMIT: u64 = 256 << 20;
+
+/// Whether the input path means stdin (`-`).
+pub fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Whether the output path means stdout (`-`).
+pub fn is_stdout(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Set by [`take_stdout`].
+static STDOUT_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Call with the output path before printing anything: if it is stdout,
+/// messages (printed with `infoln!`) go to stderr from then on, to not
+/// end up in the output.
+pub fn take_stdout(path: &Path) {
+    if is_stdout(path) {
+        STDOUT_TAKEN.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Whether the output is written to stdout, see [`take_stdout`].
+pub fn stdout_taken() -> bool {
+    STDOUT_TAKEN.load(Ordering::Relaxed)
+}
+
+/// Fail if stdin was already read (as an input, or for the user data).
+fn take_stdin() -> AnyResult<()> {
+    static TAKEN: AtomicBool = AtomicBool::new(false);
+    if TAKEN.swap(true, Ordering::Relaxed) {
+        bail!("stdin (`-`) can only be read once");
+    }
+    Ok(())
+}
+
+/// An input file, opened by [`open_input`].
+pub enum InputFile {
+    /// A file, or stdin too large for memory, in an anonymous temporary
+    /// file.
+    File(std::fs::File),
+    Stdin(std::io::Cursor<Vec<u8>>),
+}
+
+/// Open an input file, or read stdin if the path is `-`.
+///
+/// Reading needs to seek (checksums are verified before decoding), so
+/// stdin is read whole: into memory, or into a temporary file if it is
+/// large.
+pub fn open_input(path: &Path) -> AnyResult<InputFile> {
+    if !is_stdin(path) {
+        return Ok(InputFile::File(std::fs::File::open(path)?));
+    }
+    take_stdin()?;
+    let mut stdin = std::io::stdin().lock();
+    let mut bytes = vec![];
+    (&mut stdin)
+        .take(STDIN_MEMORY_LIMIT + 1)
+        .read_to_end(&mut bytes)
+        .context("Could not read stdin")?;
+    if bytes.len() as u64 <= STDIN_MEMORY_LIMIT {
+        return Ok(InputFile::Stdin(std::io::Cursor::new(bytes)));
+    }
+    // Already unlinked, so it goes away with the process
+    let mut file = tempfile::tempfile()
+        .context("Could not create a temporary file for stdin")?;
+    file.write_all(&bytes)
+        .and_then(|_| std::io::copy(&mut stdin, &mut file))
+        .context("Could not copy stdin to a temporary file")?;
+    file.rewind()?;
+    Ok(InputFile::File(file))
+}
+
+/// Read a whole input file, or stdin if the path is `-`, for the inputs
+/// that are parsed from memory.
+pub fn read_input(path: &Path) -> AnyResult<Vec<u8>> {
+    if !is_stdin(path) {
+        return Ok(std::fs::read(path)?);
+    }
+    take_stdin()?;
+    let mut bytes = vec![];
+    std::io::stdin()
+        .lock()
+        .read_to_end(&mut bytes)
+        .context("Could not read stdin")?;
+    Ok(bytes)
+}
+
+impl InputFile {
+    /// The size of the file, or of the data read from stdin.
+    pub fn size(&self) -> AnyResult<u64> {
+        match self {
+            Self::File(file) => Ok(file
+                .metadata()
+                .context("Could not get size of input file")?
+                .len()),
+            Self::Stdin(cursor) => Ok(cursor.get_ref().len() as u64),
+        }
+    }
+}
+
+impl Read for InputFile {
+    fn read(
+        &mut self,
+        buf: &mut [u8],
+    ) -> std::io::Result<usize> {
+        match self {
+            Self::File(file) => file.read(buf),
+            Self::Stdin(cursor) => cursor.read(buf),
+        }
+    }
+}
+
+impl Seek for InputFile {
+    fn seek(
+        &mut self,
+        pos: SeekFrom,
+    ) -> std::io::Result<u64> {
+        match self {
+            Self::File(file) => file.seek(pos),
+            Self::Stdin(cursor) => cursor.seek(pos),
+        }
+    }
+}
+
+/// Read a user data encryption key, stored raw or as hex digits.
+#[cfg(feature = "encryption")]
+pub fn load_user_data_key(path: &Path) -> AnyResult<[u8; 32]> {
+    let bytes = std::fs::read(path).context("Could not read key file")?;
+    if let Ok(key) = bytes.as_slice().try_into() {
+        return Ok(key);
+    }
+    let hex = bytes.trim_ascii();
+    if hex.len() != 64 {
+        bail!("Key file must have 32 bytes, or 64 hex digits.");
+    }
+    let mut key = [0; 32];
+    for (k, digits) in key.iter_mut().zip(hex.chunks_exact(2)) {
+        let digits = std::str::from_utf8(digits).unwrap_or("");
+        *k = u8::from_str_radix(digits, 16)
+            .context("Key file has invalid hex digits")?;
+    }
+    Ok(key)
+}
+
+/// Load an Ed25519 secret key, as PKCS#8 PEM or 32 raw bytes.
+#[cfg(feature = "signing")]
+pub fn load_signing_key(path: &Path) -> AnyResult<[u8; 32]> {
+    let bytes = std::fs::read(path).context("Could not read key file")?;
+    if let Ok(key) = bytes.as_slice().try_into() {
+        return Ok(key);
+    }
+    let pem = std::str::from_utf8(&bytes).context("Key file is not PEM")?;
+    Ok(iyes_mesh::signing::signing_key_from_pem(pem)?)
+}
+
+/// Load an Ed25519 public key, as PEM or 32 raw bytes.
+#[cfg(feature = "signing")]
+pub fn load_verifying_key(path: &Path) -> AnyResult<[u8; 32]> {
+    let bytes = std::fs::read(path).context("Could not read key file")?;
+    if let Ok(key) = bytes.as_slice().try_into() {
+        return Ok(key);
+    }
+    let pem = std::str::from_utf8(&bytes).context("Key file is not PEM")?;
+    Ok(iyes_mesh::signing::verifying_key_from_pem(pem)?)
+}
+
+pub fn parse_compression_level(s: &str) -> Result<i32, String> {
+    let range = IyesMeshWriterSettings::compression_level_range();
+    let level: i32 = s.parse().map_err(|e| format!("{}", e))?;
+    if !range.contains(&level) {
+        return Err(format!(
+            "compression level must be in range {}..={}",
+            range.start(),
+            range.end(),
+        ));
+    }
+    Ok(level)
+}
+
+/// Parse the name of a checksum algorithm supported by this build.
+pub fn parse_checksum_kind(s: &str) -> Result<ChecksumKind, String> {
+    s.parse().map_err(|_| {
+        let names: Vec<_> =
+            ChecksumKind::ALL.iter().map(|kind| kind.name()).collect();
+        format!("expected one of: {}", names.join(", "))
+    })
+}
+
+/// Parse the index of a mesh.
+pub fn parse_mesh_index(s: &str) -> Result<usize, String> {
+    s.parse().map_err(|_| {
+        format!(
+            "`{}` is not a mesh index (meshes are identified by their index, \
+             they have no names)",
+            s
+        )
+    })
+}
+
+/// An attribute to load from a raw file (see `edit --add-attr`).
+#[derive(Clone, Debug)]
+pub struct AddAttr {
+    pub usage: VertexUsage,
+    pub format: VertexFormat,
+    pub path: PathBuf,
+    /// The mesh to add it to (default: all, one after the other).
+    pub mesh: Option<usize>,
+}
+
+/// Parse an attribute to add like `uv1:float32x2:uv.bin` or
+/// `custom:3:float32:ao.bin:2`.
+///
+/// The path may contain `:`, it ends with `:<mesh index>` only if that
+/// is a number.
+pub fn parse_add_attr(s: &str) -> Result<AddAttr, String> {
+    let expected = || "expected <ATTRIBUTE>:<FORMAT>:<PATH>[:<MESH>]";
+    let (mut usage, mut rest) = s.split_once(':').ok_or_else(expected)?;
+    if usage.eq_ignore_ascii_case("custom") {
+        let (id, r) = rest.split_once(':').ok_or_else(expected)?;
+        usage = &s[..usage.len() + 1 + id.len()];
+        rest = r;
+    }
+    let (format, mut path) = rest.split_once(':').ok_or_else(expected)?;
+    let mut mesh = None;
+    if let Some((p, index)) = path.rsplit_once(':')
+        && let Ok(index) = index.parse()
+    {
+        path = p;
+        mesh = Some(index);
+    }
+    if path.is_empty() {
+        return Err(expected().to_owned());
+    }
+    Ok(AddAttr {
+        usage: usage.parse().map_err(|e| format!("{}", e))?,
+        format: format.parse().map_err(|e| format!("{}", e))?,
+        path: path.into(),
+        mesh,
+    })
+}
+
+/// Parse an attribute conversion like `color=unorm8x4`.
+pub fn parse_attr_conversion(
+    s: &str,
+) -> Result<(VertexUsage, VertexFormat), String> {
+    let (usage, format) = s
+        .split_once('=')
+        .ok_or_else(|| "expected <ATTRIBUTE>=<FORMAT>".to_owned())?;
+    let usage = usage.parse().map_err(|e| format!("{}", e))?;
+    let format = format.parse().map_err(|e| format!("{}", e))?;
+    Ok((usage, format))
+}
+
+/// Parse a color space tag like `color=srgb` (or `color=unspecified`).
+pub fn parse_color_space_tag(
+    s: &str,
+) -> Result<(VertexUsage, Option<ColorSpace>), String> {
+    let (usage, color_space) = s
+        .split_once('=')
+        .ok_or_else(|| "expected <ATTRIBUTE>=<COLORSPACE>".to_owned())?;
+    let usage = usage.parse().map_err(|e| format!("{}", e))?;
+    if color_space.eq_ignore_ascii_case("unspecified") {
+        return Ok((usage, None));
+    }
+    let color_space = color_space.parse().map_err(|e| format!("{}", e))?;
+    Ok((usage, Some(color_space)))
+}
+
+/// Parse a normal generation mode (`flat` or `smooth`).
+pub fn parse_normal_mode(s: &str) -> Result<NormalMode, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "flat" => Ok(NormalMode::Flat),
+        "smooth" => Ok(NormalMode::Smooth),
+        _ => Err("expected `flat` or `smooth`".to_owned()),
+    }
+}
+
+/// Parse a recentering mode (`bounds` or `centroid`).
+pub fn parse_center_mode(s: &str) -> Result<CenterMode, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "bounds" => Ok(CenterMode::Bounds),
+        "centroid" => Ok(CenterMode::Centroid),
+        _ => Err("expected `bounds` or `centroid`".to_owned()),
+    }
+}
+
+/// Parse comma-separated floats, like `1,2,3`.
+fn parse_floats<const N: usize>(s: &str) -> Result<[f32; N], String> {
+    let values = s
+        .split(',')
+        .map(|v| v.trim().parse::<f32>().map_err(|e| format!("{}", e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    values
+        .try_into()
+        .map_err(|_| format!("expected {} comma-separated numbers", N))
+}
+
+/// Parse a vector like `1,2,3`.
+pub fn parse_vec3(s: &str) -> Result<[f32; 3], String> {
+    parse_floats(s)
+}
+
+/// Parse an axis (`x`, `y`, `z`, optionally with a sign, like `-z`), as a
+/// unit vector.
+#[cfg(feature = "obj")]
+pub fn parse_axis(s: &str) -> Result<[f32; 3], String> {
+    let lower = s.to_ascii_lowercase();
+    let (sign, name) = match lower.strip_prefix('-') {
+        Some(name) => (-1.0, name),
+        None => (1.0, lower.strip_prefix('+').unwrap_or(&lower)),
+    };
+    let mut r = [0.0; 3];
+    match name {
+        "x" => r[0] = sign,
+        "y" => r[1] = sign,
+        "z" => r[2] = sign,
+        _ => return Err("expected `x`, `y` or `z` (like `-z`)".to_owned()),
+    }
+    Ok(r)
+}
+
+/// Parse a vector like `1,2,3,4`.
+pub fn parse_vec4(s: &str) -> Result<[f32; 4], String> {
+    parse_floats(s)
+}
+
+/// Parse a scale: a single (uniform) factor, or `X,Y,Z`.
+pub fn parse_scale(s: &str) -> Result<[f32; 3], String> {
+    if s.contains(',') {
+        parse_floats(s)
+    } else {
+        parse_floats::<1>(s).map(|[v]| [v; 3])
+    }
+}
+
+/// Parse a column-major 4x4 matrix, as 16 comma-separated numbers.
+pub fn parse_matrix(s: &str) -> Result<[[f32; 4]; 4], String> {
+    let v: [f32; 16] = parse_floats(s)?;
+    Ok(std::array::from_fn(|c| std::array::from_fn(|r| v[c * 4 + r])))
+}
+
+/// Build a column-major matrix that scales, then rotates around X, Y and Z
+/// (in that order, angles in degrees), then translates.
+pub fn compose_transform(
+    scale: [f32; 3],
+    rotate_deg: [f32; 3],
+    translate: [f32; 3],
+) -> [[f32; 4]; 4] {
+    let [x, y, z] = rotate_deg.map(|a| a.to_radians());
+    let rx = [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, x.cos(), x.sin(), 0.0],
+        [0.0, -x.sin(), x.cos(), 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+    let ry = [
+        [y.cos(), 0.0, -y.sin(), 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [y.sin(), 0.0, y.cos(), 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+    let rz = [
+        [z.cos(), z.sin(), 0.0, 0.0],
+        [-z.sin(), z.cos(), 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+    let s = [
+        [scale[0], 0.0, 0.0, 0.0],
+        [0.0, scale[1], 0.0, 0.0],
+        [0.0, 0.0, scale[2], 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+    let mut m = mul_matrix(rz, mul_matrix(ry, mul_matrix(rx, s)));
+    m[3] = [translate[0], translate[1], translate[2], 1.0];
+    m
+}
+
+/// Multiply two column-major 4x4 matrices.
+pub fn mul_matrix(
+    a: [[f32; 4]; 4],
+    b: [[f32; 4]; 4],
+) -> [[f32; 4]; 4] {
+    std::array::from_fn(|c| {
+        std::array::from_fn(|r| (0..4).map(|k| a[k][r] * b[c][k]).sum())
+    })
+}
+
+/// Token that gets cancelled when the user presses Ctrl-C.
+pub fn cancel_token() -> &'static CancelToken {
+    static TOKEN: OnceLock<CancelToken> = OnceLock::new();
+    TOKEN.get_or_init(CancelToken::new)
+}
+
+/// Make Ctrl-C abort long-running operations gracefully.
+///
+/// Pressing it a second time exits immediately.
+pub fn install_ctrlc_handler() -> AnyResult<()> {
+    ctrlc::set_handler(|| {
+        let token = cancel_token();
+        if token.is_cancelled() {
+            std::process::exit(130);
+        }
+        token.cancel();
+    })
+    .context("Could not install Ctrl-C handler")
+}
+
+/// Encode the output file.
+///
+/// The file is opened for reading too, so that the checksums can be
+/// computed by reading the data back, instead of buffering it.
+/// If encoding fails (or is cancelled), the incomplete file is deleted.
+/// If the path is `-`, the file is encoded in memory, then written to
+/// stdout.
+pub fn write_output_file(
+    mut writer: IyesMeshWriter<'_>,
+    path: &Path,
+    overwrite: bool,
+) -> AnyResult<()> {
+    if is_stdout(path) {
+        return write_stdout(&encode_in_memory(writer)?);
+    }
+    let mut options = std::fs::File::options();
+    options.read(true).write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    let mut outfile =
+        options.open(path).context("Could not open output file")?;
+    writer.set_cancel_token(cancel_token().clone());
+    if let Err(e) = writer.write_to_readable(&mut outfile) {
+        drop(outfile);
+        std::fs::remove_file(path).ok();
+        return Err(e).context("Cannot encode output file");
+    }
+    Ok(())
+}
+
+/// Save the output file, like [`write_output_file`], and read back its
+/// sizes.
+pub fn write_output_file_sizes(
+    writer: IyesMeshWriter<'_>,
+    path: &Path,
+    overwrite: bool,
+    settings: IyesMeshReaderSettings,
+) -> AnyResult<FileSizes> {
+    if !is_stdout(path) {
+        write_output_file(writer, path, overwrite)?;
+        return FileSizes::read(path, settings);
+    }
+    let bytes = encode_in_memory(writer)?;
+    write_stdout(&bytes)?;
+    let file_size = bytes.len() as u64;
+    FileSizes::decode(&mut std::io::Cursor::new(bytes), file_size, settings)
+}
+
+/// Encode the output file into memory, to write it to stdout: the writer
+/// needs to seek back to fill in the checksums, and stdout cannot.
+fn encode_in_memory(mut writer: IyesMeshWriter<'_>) -> AnyResult<Vec<u8>> {
+    let mut out = std::io::Cursor::new(vec![]);
+    writer.set_cancel_token(cancel_token().clone());
+    writer
+        .write_to_readable(&mut out)
+        .context("Cannot encode output file")?;
+    Ok(out.into_inner())
+}
+
+/// Write the output to stdout, when its path is `-`.
+fn write_stdout(bytes: &[u8]) -> AnyResult<()> {
+    STDOUT_TAKEN.store(true, Ordering::Relaxed);
+    let mut stdout = std::io::BufWriter::new(std::io::stdout().lock());
+    stdout
+        .write_all(bytes)
+        .and_then(|_| stdout.flush())
+        .context("Could not write output to stdout")
+}
+
+/// Save the output file from bytes that are already encoded.
+pub fn write_bytes_file(
+    path: &Path,
+    overwrite: bool,
+    bytes: &[u8],
+) -> AnyResult<()> {
+    if is_stdout(path) {
+        return write_stdout(bytes);
+    }
+    let mut outfile = if overwrite {
+        std::fs::File::create(path).context("Could not open output file")?
+    } else {
+        std::fs::File::create_new(path)
+            .context("Could not open output file")?
+    };
+    outfile
+        .write_all(bytes)
+        .and_then(|_| outfile.sync_all())
+        .context("Could not write output")
+}
+
+/// Print the result of a `--dry-run`.
+pub fn print_write_plan(plan: &WritePlan) {
+    println!("{:#?}", plan.descriptor);
+    println!("Descriptor size: {} bytes", plan.descriptor_len);
+    println!(
+        "Uncompressed data size: {} bytes",
+        plan.total_uncompressed_len
+    );
+    println!(
+        "Index upconversion: {}",
+        if plan.upconverting_indices { "yes" } else { "no" }
+    );
+}
+
+/// Decoded data of a mesh attribute, to replace what was read from a file.
+pub type DecodedAttribute = (VertexUsage, VertexFormat, Vec<u8>);
+
+/// Decode all attributes that are quantized or specially encoded.
+///
+/// The writer only accepts plain float data for quantization and
+/// encoding, and does not preserve the encoding of its input, so meshes
+/// loaded from a file must be converted back first.
+pub fn decode_special_attributes(
+    data: &IyesMeshReaderWithData,
+    meshes: &DecodedMeshes,
+) -> Vec<Vec<DecodedAttribute>> {
+    let encodings = &data.descriptor().attribute_encodings;
+    let to_bytes = |v: &[f32]| v.iter().flat_map(|v| v.to_le_bytes()).collect();
+    (0..meshes.meshes.len())
+        .map(|i| {
+            let mut r = vec![];
+            if data.descriptor().meshes[i].position_transform.is_some()
+                && let Some(p) = data.decode_positions_f32(meshes, i)
+            {
+                let bytes = to_bytes(p.as_flattened());
+                r.push((VertexUsage::Position, VertexFormat::Float32x3, bytes));
+            }
+            if encodings.contains_key(&VertexUsage::Normal)
+                && let Some(n) = data.decode_normals_f32(meshes, i)
+            {
+                let bytes = to_bytes(n.as_flattened());
+                r.push((VertexUsage::Normal, VertexFormat::Float32x3, bytes));
+            }
+            if encodings.contains_key(&VertexUsage::Tangent)
+                && let Some(t) = data.decode_tangents_f32(meshes, i)
+            {
+                let bytes = to_bytes(t.as_flattened());
+                r.push((VertexUsage::Tangent, VertexFormat::Float32x4, bytes));
+            }
+            r
+        })
+        .collect()
+}
+
+/// Make the writer encode the attributes that are encoded in an input file
+/// (see [`decode_special_attributes`]) the same way again.
+pub fn keep_encodings(
+    settings: &mut IyesMeshWriterSettings,
+    descriptor: &IyesMeshDescriptor,
+) {
+    let encodings = &descriptor.attribute_encodings;
+    settings.quantize_positions |=
+        descriptor.meshes.iter().any(|m| m.position_transform.is_some());
+    settings.octahedral_normals |= encodings.contains_key(&VertexUsage::Normal);
+    settings.octahedral_tangents |=
+        encodings.contains_key(&VertexUsage::Tangent);
+}
+
+/// Keep the user data of an input file (still encrypted, if it is).
+pub fn copy_user_data<'s>(
+    writer: &mut IyesMeshWriter<'s>,
+    data: &IyesMeshReaderWithData,
+    buffers: &DecodedBuffers<'s>,
+) {
+    let Some(user_data) = buffers.user_data else {
+        writer.clear_user_data();
+        return;
+    };
+    match data.descriptor().user_data_nonce {
+        Some(nonce) => writer.set_encrypted_user_data(user_data, nonce),
+        None => writer.set_user_data(user_data),
+    }
+}
+
+/// The buffers of the decoded data, in the order they are stored in, with
+/// a name and the name of their format.
+///
+/// The names are `user data`, `indices`, the usage of each vertex
+/// attribute, and `instance ` followed by the usage of each instance
+/// attribute.
+pub fn named_buffers<'s>(
+    descriptor: &IyesMeshDescriptor,
+    buffers: &DecodedBuffers<'s>,
+) -> Vec<(String, Option<String>, &'s [u8])> {
+    let mut r = vec![];
+    if let Some(user_data) = buffers.user_data {
+        r.push(("user data".to_owned(), None, user_data));
+    }
+    if let Some((format, data)) = buffers.buf_index {
+        r.push(("indices".to_owned(), Some(format!("{:?}", format)), data));
+    }
+    for usage in descriptor.attribute_order() {
+        let (format, data) = buffers.buf_attrs[&usage];
+        r.push((usage.to_string(), Some(format!("{:?}", format)), data));
+    }
+    for usage in descriptor.instance_attribute_order() {
+        let (format, data) = buffers.buf_instances[&usage];
+        let name = format!("instance {}", usage);
+        r.push((name, Some(format!("{:?}", format)), data));
+    }
+    r
+}
+
+/// Tag the output with the color spaces recorded in an input file.
+pub fn copy_color_spaces(
+    writer: &mut IyesMeshWriter<'_>,
+    data: &IyesMeshReaderWithData,
+) {
+    for (usage, color_space) in data.descriptor().color_spaces.iter() {
+        writer.set_color_space(*usage, *color_space);
+    }
+}
+
+/// Copy the per-instance attributes of an input file to the output.
+///
+/// Meshes added to the writer use all instances, unless their range is set
+/// with [`copy_mesh_instances`].
+pub fn copy_instance_data<'s>(
+    writer: &mut IyesMeshWriter<'s>,
+    data: &IyesMeshReaderWithData,
+    buffers: &DecodedBuffers<'s>,
+) -> AnyResult<()> {
+    let n_instances = data.descriptor().n_instances;
+    for (usage, (format, bytes)) in buffers.buf_instances.iter() {
+        writer
+            .set_instance_data(*usage, *format, bytes, n_instances)
+            .with_context(|| format!("Cannot copy instance data {usage:?}"))?;
+    }
+    Ok(())
+}
+
+/// Give an output mesh the instance range of a mesh of the input file.
+pub fn copy_mesh_instances(
+    writer: &mut IyesMeshWriter<'_>,
+    data: &IyesMeshReaderWithData,
+    out_index: usize,
+    in_index: usize,
+) -> AnyResult<()> {
+    let info = &data.descriptor().meshes[in_index];
+    let range =
+        info.first_instance..info.first_instance + info.instance_count;
+    writer
+        .set_mesh_instances(out_index, range)
+        .context("Cannot set instance range")?;
+    Ok(())
+}
+
+/// Hashes of the data of a mesh (its indices, vertices and instances, and
+/// the quantization of its positions), to find meshes with the same data.
+pub type MeshSignature = Vec<(String, u64)>;
+
+/// The [`MeshSignature`] of each mesh, with the given algorithm.
+///
+/// The hashes are sorted by name, so they do not depend on the order of
+/// the attributes.
+pub fn mesh_signatures(
+    data: &IyesMeshReaderWithData,
+    buffers: &DecodedBuffers<'_>,
+    hash: ChecksumKind,
+) -> AnyResult<Vec<MeshSignature>> {
+    let split = data.into_split_meshes(buffers)?;
+    let r = split
+        .meshes
+        .iter()
+        .zip(&split.instances)
+        .zip(&data.descriptor().meshes)
+        .map(|((mesh, instances), info)| {
+            let mut sig = vec![];
+            if let Some((format, bytes)) = mesh.indices {
+                let name = format!("indices {:?}", format);
+                sig.push((name, hash.checksum(bytes)));
+            }
+            for (usage, (format, bytes)) in mesh.attributes.iter() {
+                let name = format!("{} {:?}", usage, format);
+                sig.push((name, hash.checksum(bytes)));
+            }
+            for (usage, (format, bytes)) in instances.iter() {
+                let name = format!("instance {} {:?}", usage, format);
+                sig.push((name, hash.checksum(bytes)));
+            }
+            if let Some(transform) = info.position_transform {
+                let bytes: Vec<u8> = transform
+                    .offset
+                    .iter()
+                    .chain(&transform.scale)
+                    .flat_map(|v| v.to_le_bytes())
+                    .collect();
+                let name = "position transform".to_owned();
+                sig.push((name, hash.checksum(&bytes)));
+            }
+            sig.sort();
+            sig
+        })
+        .collect();
+    Ok(r)
+}
+
+/// Add mesh `index` of the input as the only mesh of the output, with its
+/// own instances.
+pub fn add_single_mesh<'s>(
+    writer: &mut IyesMeshWriter<'s>,
+    data: &IyesMeshReaderWithData,
+    meshes: &DecodedMeshes<'s>,
+    decoded: &'s [Vec<DecodedAttribute>],
+    index: usize,
+) -> AnyResult<()> {
+    let n_instances = data.descriptor().meshes[index].instance_count;
+    for (usage, (format, bytes)) in meshes.instances[index].iter() {
+        writer
+            .set_instance_data(*usage, *format, bytes, n_instances)
+            .with_context(|| format!("Cannot copy instance data {usage:?}"))?;
+    }
+    writer
+        .add_mesh(with_decoded(&meshes.meshes[index], &decoded[index]))
+        .context("Cannot use mesh for output")?;
+    Ok(())
+}
+
+/// Copy of `mesh` with the given attributes replaced.
+pub fn with_decoded<'a>(
+    mesh: &MeshDataRef<'a>,
+    decoded: &'a [DecodedAttribute],
+) -> MeshDataRef<'a> {
+    let mut mesh = mesh.clone();
+    for (usage, format, bytes) in decoded {
+        mesh.attributes.insert(*usage, (*format, bytes));
+    }
+    mesh
+}
+
+/// Print rows with aligned columns. The first `n_text` columns are
+/// left-aligned, the others (numbers) right-aligned.
+pub fn print_table(
+    header: &[&str],
+    rows: &[Vec<String>],
+    n_text: usize,
+) {
+    let widths: Vec<usize> = (0..header.len())
+        .map(|c| {
+            rows.iter()
+                .map(|row| row[c].len())
+                .chain([header[c].len()])
+                .max()
+                .unwrap()
+        })
+        .collect();
+    let header: Vec<String> = header.iter().map(|h| h.to_string()).collect();
+    for row in [&header].into_iter().chain(rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(c, (cell, w))| {
+                if c < n_text {
+                    format!("{:<w$}", cell)
+                } else {
+                    format!("{:>w$}", cell)
+                }
+            })
+            .collect();
+        infoln!("  {}", cells.join("  ").trim_end());
+    }
+}
+
+/// Size of the compressed data, from the size of the file.
+pub fn compressed_size(
+    header: &IyesMeshHeader,
+    descriptor: &IyesMeshDescriptor,
+    file_size: u64,
+) -> u64 {
+    let mut size = file_size
+        - IyesMeshHeader::encoded_len() as u64
+        - header.descriptor_len as u64;
+    if descriptor.signed {
+        size = size.saturating_sub(iyes_mesh::SIGNATURE_LEN as u64);
+    }
+    size
+}
+
+/// Whether the output file will be signed.
+#[cfg(feature = "signing")]
+pub fn is_signing(settings: &IyesMeshWriterSettings) -> bool {
+    settings.signing_key.is_some()
+}
+
+#[cfg(not(feature = "signing"))]
+pub fn is_signing(_settings: &IyesMeshWriterSettings) -> bool {
+    false
+}
+
+/// Sizes of a file, to report how much a command changed them.
+pub struct FileSizes {
+    pub file: u64,
+    pub compressed: u64,
+    pub raw: u64,
+}
+
+impl FileSizes {
+    /// Sizes of a file of `file_size` bytes, from its metadata.
+    pub fn new(
+        reader: &IyesMeshReader<'_>,
+        file_size: u64,
+    ) -> Self {
+        Self {
+            file: file_size,
+            compressed: compressed_size(
+                reader.header(),
+                reader.descriptor(),
+                file_size,
+            ),
+            raw: reader.descriptor().compute_total_raw_data_size(),
+        }
+    }
+
+    /// Read the sizes of a file (only its metadata is decoded).
+    pub fn read(
+        path: &Path,
+        settings: IyesMeshReaderSettings,
+    ) -> AnyResult<Self> {
+        let mut file =
+            std::fs::File::open(path).context("Could not open output file")?;
+        let file_size = file
+            .metadata()
+            .context("Could not get size of output file")?
+            .len();
+        Self::decode(&mut file, file_size, settings)
+    }
+
+    fn decode(
+        read: &mut dyn ReadSeek,
+        file_size: u64,
+        settings: IyesMeshReaderSettings,
+    ) -> AnyResult<Self> {
+        let reader = IyesMeshReader::init_with_settings(settings, read)
+            .context("Cannot decode metadata of output file")?;
+        Ok(Self::new(&reader, file_size))
+    }
+
+    fn ratio(&self) -> f64 {
+        self.raw as f64 / self.compressed.max(1) as f64
+    }
+}
+
+/// Print the sizes of a file before and after it was changed.
+pub fn print_size_change(
+    before: &FileSizes,
+    after: &FileSizes,
+) {
+    let text = format_size_change(before, after);
+    if stdout_taken() {
+        eprint!("{}", text);
+    } else {
+        print!("{}", text);
+    }
+}
+
+/// The lines printed by [`print_size_change`].
+pub fn format_size_change(
+    before: &FileSizes,
+    after: &FileSizes,
+) -> String {
+    let mut r = String::new();
+    for (what, sizes) in [("Before", before), ("After", after)] {
+        r += &format!(
+            "{}: {} bytes ({:.2}x compression)\n",
+            what,
+            sizes.file,
+            sizes.ratio()
+        );
+    }
+    r += &format!(
+        "Size change: {:+} bytes ({:.1}% of before)\n",
+        after.file as i64 - before.file as i64,
+        after.file as f64 * 100.0 / before.file.max(1) as f64
+    );
+    r
+}
@@ -0,0 +1,182 @@
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use iyes_mesh::HashMap;
+use iyes_mesh::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+use iyes_mesh::mesh::MeshDataRef;
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderWithData};
+use iyes_mesh::write::IyesMeshWriter;
+
+pub fn iyesmesh() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_iyesmesh"))
+}
+
+/// Run the CLI and check that it succeeded.
+pub fn run(args: &[&str]) -> Output {
+    let output = iyesmesh().args(args).output().unwrap();
+    assert!(
+        output.status.success(),
+        "iyesmesh {args:?} failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+/// A temporary directory for the files of a test.
+pub struct TestDir(tempfile::TempDir);
+
+impl TestDir {
+    pub fn new() -> Self {
+        Self(tempfile::tempdir().unwrap())
+    }
+
+    pub fn path(
+        &self,
+        name: &str,
+    ) -> PathBuf {
+        self.0.path().join(name)
+    }
+
+    /// The path of a file in the directory, as a string for `run`.
+    pub fn arg(
+        &self,
+        name: &str,
+    ) -> String {
+        self.path(name).to_str().unwrap().to_owned()
+    }
+}
+
+/// Triangles of a `size` x `size` grid, with heights depending on `seed`.
+pub fn grid_mesh(
+    size: u16,
+    seed: u64,
+) -> (Vec<[f32; 3]>, Vec<u16>) {
+    let mut rng = seed;
+    let mut positions = vec![];
+    for y in 0..size {
+        for x in 0..size {
+            rng = rng
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let height = (rng >> 40) as f32 / (1 << 24) as f32;
+            positions.push([x as f32, height, y as f32]);
+        }
+    }
+    let mut indices = vec![];
+    for y in 0..(size - 1) {
+        for x in 0..(size - 1) {
+            let i = y * size + x;
+            indices.extend([i, i + size, i + 1, i + 1, i + size, i + size + 1]);
+        }
+    }
+    (positions, indices)
+}
+
+/// The cube of `examples/simple_encode.rs`.
+pub fn cube_mesh() -> (Vec<[f32; 3]>, Vec<u16>) {
+    let positions = vec![
+        [-1.0, -1.0, 1.0],
+        [1.0, -1.0, 1.0],
+        [1.0, 1.0, 1.0],
+        [-1.0, 1.0, 1.0],
+        [-1.0, -1.0, -1.0],
+        [1.0, -1.0, -1.0],
+        [1.0, 1.0, -1.0],
+        [-1.0, 1.0, -1.0],
+    ];
+    let indices = vec![
+        0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4, 4, 0, 3, 3, 7, 4, 1, 5, 6, 6, 2, 1,
+        3, 2, 6, 6, 7, 3, 4, 5, 1, 1, 0, 4,
+    ];
+    (positions, indices)
+}
+
+/// Encode meshes with positions and U16 indices into a file.
+pub fn write_meshes(
+    path: &Path,
+    meshes: &[(Vec<[f32; 3]>, Vec<u16>)],
+) {
+    let data: Vec<_> = meshes
+        .iter()
+        .map(|(positions, indices)| {
+            let positions: Vec<u8> = positions
+                .iter()
+                .flatten()
+                .flat_map(|c| c.to_le_bytes())
+                .collect();
+            let indices: Vec<u8> =
+                indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+            (positions, indices)
+        })
+        .collect();
+    let mut writer = IyesMeshWriter::new();
+    for (positions, indices) in data.iter() {
+        let mut attributes = HashMap::default();
+        attributes.insert(
+            VertexUsage::Position,
+            (VertexFormat::Float32x3, positions.as_slice()),
+        );
+        writer
+            .add_mesh(MeshDataRef {
+                indices: Some((IndexFormat::U16, indices.as_slice())),
+                attributes,
+            })
+            .unwrap();
+    }
+    let mut file = std::fs::File::create(path).unwrap();
+    writer.write_to(&mut file).unwrap();
+}
+
+/// Write a test file with a couple of grid meshes.
+pub fn write_test_file(
+    path: &Path,
+    seed: u64,
+) {
+    write_meshes(path, &[grid_mesh(8, seed), grid_mesh(5, seed + 1)]);
+}
+
+/// Read a whole file, verifying its checksums.
+pub fn decode_file(path: &Path) -> IyesMeshReaderWithData {
+    decode_bytes(&std::fs::read(path).unwrap())
+}
+
+pub fn decode_bytes(bytes: &[u8]) -> IyesMeshReaderWithData {
+    let mut read = std::io::Cursor::new(bytes);
+    IyesMeshReader::init(&mut read).unwrap().read_all_data().unwrap()
+}
+
+/// The positions of each mesh of a decoded file.
+pub fn mesh_positions(data: &IyesMeshReaderWithData) -> Vec<Vec<[f32; 3]>> {
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    meshes
+        .meshes
+        .iter()
+        .map(|mesh| {
+            let (format, bytes) = mesh.attributes[&VertexUsage::Position];
+            assert_eq!(format, VertexFormat::Float32x3);
+            bytes
+                .chunks_exact(12)
+                .map(|v| {
+                    let c = |i: usize| {
+                        f32::from_le_bytes(v[i..(i + 4)].try_into().unwrap())
+                    };
+                    [c(0), c(4), c(8)]
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The indices of each mesh of a decoded file.
+pub fn mesh_indices(data: &IyesMeshReaderWithData) -> Vec<Vec<u32>> {
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    meshes
+        .meshes
+        .iter()
+        .map(|mesh| mesh.iter_indices().unwrap().collect())
+        .collect()
+}
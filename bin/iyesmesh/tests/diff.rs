@@ -0,0 +1,130 @@
+mod common;
+use common::*;
+
+fn diff(args: &[&str]) -> (Option<i32>, String) {
+    let output = iyesmesh().arg("diff").args(args).output().unwrap();
+    (output.status.code(), String::from_utf8(output.stdout).unwrap())
+}
+
+fn diff_json(
+    old: &str,
+    new: &str,
+) -> (Option<i32>, serde_json::Value) {
+    let (code, stdout) = diff(&["--json", old, new]);
+    (code, serde_json::from_str(&stdout).unwrap())
+}
+
+#[test]
+fn identical_contents() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("a.ima"), 1);
+    let (code, stdout) = diff(&[&dir.arg("a.ima"), &dir.arg("a.ima")]);
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "The files are identical.\n");
+
+    // Only the compression differs
+    run(&[
+        "recompress",
+        "--level",
+        "1",
+        &dir.arg("a.ima"),
+        &dir.arg("fast.ima"),
+    ]);
+    let (code, json) = diff_json(&dir.arg("a.ima"), &dir.arg("fast.ima"));
+    assert_eq!(code, Some(0));
+    assert_eq!(json["identical_files"], false);
+    assert_eq!(json["identical_content"], true);
+    assert_eq!(json["changes"], serde_json::json!([]));
+    assert_eq!(
+        json["encoding_changes"],
+        serde_json::json!(["compressed data (recompressed)"])
+    );
+
+    run(&[
+        "recompress",
+        "--no-data-checksum",
+        &dir.arg("a.ima"),
+        &dir.arg("no_checksum.ima"),
+    ]);
+    let (code, stdout) =
+        diff(&[&dir.arg("a.ima"), &dir.arg("no_checksum.ima")]);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        stdout,
+        "The contents are identical.\nEncoding differences:\n  \
+         data checksum: yes -> no\n"
+    );
+}
+
+#[test]
+fn changed_meshes() {
+    let dir = TestDir::new();
+    let meshes = [grid_mesh(4, 1), grid_mesh(5, 2), grid_mesh(6, 3)];
+    write_meshes(&dir.path("old.ima"), &meshes);
+    // The first mesh is removed, and the last one changed
+    let mut changed = meshes[2].clone();
+    changed.0[3][1] = 10.0;
+    write_meshes(&dir.path("new.ima"), &[meshes[1].clone(), changed]);
+
+    let (code, json) = diff_json(&dir.arg("old.ima"), &dir.arg("new.ima"));
+    assert_eq!(code, Some(1));
+    assert_eq!(json["identical_content"], false);
+    let changes: Vec<_> = json["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| {
+            (
+                c["kind"].as_str().unwrap(),
+                c["item"].as_str().unwrap(),
+                c["message"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        changes[0],
+        ("mesh", "0", "removed (16 vertices, 54 indices, 0 instances)")
+    );
+    assert_eq!(changes[1], ("mesh", "1", "data changed, was old mesh 2"));
+    assert_eq!(changes[2].0, "buffer");
+    assert_eq!(changes[2].1, "indices");
+    assert_eq!(changes[3].1, "Position");
+    let message = "changed: 924 bytes (hash ";
+    assert!(changes[3].2.starts_with(message), "{changes:?}");
+    assert!(changes[3].2.contains(") -> 732 bytes (hash "), "{changes:?}");
+    assert_eq!(changes.len(), 4, "{changes:?}");
+}
+
+#[test]
+fn changed_bytes() {
+    let dir = TestDir::new();
+    let meshes = [grid_mesh(4, 1), grid_mesh(5, 2)];
+    write_meshes(&dir.path("old.ima"), &meshes);
+    let mut changed = meshes.clone();
+    changed[1].0[3][1] = 10.0;
+    write_meshes(&dir.path("new.ima"), &changed);
+
+    let (code, stdout) =
+        diff(&["--bytes", &dir.arg("old.ima"), &dir.arg("new.ima")]);
+    assert_eq!(code, Some(1));
+    // The Y coordinate of vertex 3 of the second mesh (of 16 + 3 vertices)
+    let start = (16 + 3) * 12 + 4;
+    assert!(
+        stdout.contains(&format!(
+            "  differing bytes: {}..{}\n",
+            start,
+            start + 4
+        )),
+        "{stdout}"
+    );
+    assert!(stdout.contains("mesh 1: data changed\n"), "{stdout}");
+    assert!(stdout.ends_with("Differences in the contents: 2\n"), "{stdout}");
+}
+
+#[test]
+fn errors() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("a.ima"), 1);
+    let (code, _) = diff(&[&dir.arg("a.ima"), &dir.arg("missing.ima")]);
+    assert_eq!(code, Some(2));
+}
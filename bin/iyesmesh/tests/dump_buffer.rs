@@ -0,0 +1,76 @@
+mod common;
+use common::*;
+
+#[test]
+fn dump_cube_positions() {
+    let dir = TestDir::new();
+    let (positions, indices) = cube_mesh();
+    write_meshes(&dir.path("cube.ima"), &[(positions.clone(), indices)]);
+    let output = run(&[
+        "dump-buffer",
+        "--attr",
+        "position",
+        &dir.arg("cube.ima"),
+        &dir.arg("positions.bin"),
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        "Wrote 8 vertices of format Float32x3 (96 bytes, little-endian).\n"
+    );
+    let expected: Vec<u8> =
+        positions.iter().flatten().flat_map(|c| c.to_le_bytes()).collect();
+    assert_eq!(std::fs::read(dir.path("positions.bin")).unwrap(), expected);
+}
+
+#[test]
+fn dump_indices_of_one_mesh() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("grids.ima"), 1);
+    let (_, indices) = grid_mesh(5, 2);
+    // To stdout, with the message on stderr
+    let output = run(&[
+        "dump-buffer",
+        "--indices",
+        "--mesh",
+        "1",
+        &dir.arg("grids.ima"),
+        "-",
+    ]);
+    let expected: Vec<u8> =
+        indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+    assert_eq!(output.stdout, expected);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Wrote 96 indices of format U16 (192 bytes"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn dump_buffer_errors() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("grids.ima"), 1);
+    for (args, error) in [
+        (
+            &["--attr", "normal"][..],
+            "The file has no attribute Normal (attributes: Position)",
+        ),
+        (
+            &["--indices", "--mesh", "2"],
+            "Mesh 2 does not exist (the file has 2 meshes)",
+        ),
+    ] {
+        let output = iyesmesh()
+            .arg("dump-buffer")
+            .args(args)
+            .arg(dir.arg("grids.ima"))
+            .arg(dir.arg("out.bin"))
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(error), "{stderr}");
+        assert!(!dir.path("out.bin").exists());
+    }
+}
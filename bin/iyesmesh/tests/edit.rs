@@ -0,0 +1,981 @@
+use iyes_mesh::HashMap;
+use iyes_mesh::descriptor::{
+    ColorSpace, IndexFormat, VertexFormat, VertexUsage,
+};
+use iyes_mesh::mesh::MeshDataRef;
+use iyes_mesh::read::IyesMeshReaderWithData;
+use iyes_mesh::write::IyesMeshWriter;
+
+mod common;
+use common::*;
+
+/// Write a grid mesh with positions, normals, UVs and colors.
+fn write_with_attributes(path: &std::path::Path) {
+    let (positions, indices) = grid_mesh(6, 1);
+    let to_bytes = |v: &[f32]| -> Vec<u8> {
+        v.iter().flat_map(|c| c.to_le_bytes()).collect()
+    };
+    let normals = to_bytes(&[0.0, 1.0, 0.0].repeat(positions.len()));
+    let uvs = to_bytes(&[0.5, 0.5].repeat(positions.len()));
+    let colors = to_bytes(&[0.5, 0.0, 2.0, 0.5].repeat(positions.len()));
+    let positions = to_bytes(positions.as_flattened());
+    let indices: Vec<u8> =
+        indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+    let mut attributes = HashMap::default();
+    attributes.insert(
+        VertexUsage::Position,
+        (VertexFormat::Float32x3, positions.as_slice()),
+    );
+    attributes.insert(
+        VertexUsage::Normal,
+        (VertexFormat::Float32x3, normals.as_slice()),
+    );
+    attributes
+        .insert(VertexUsage::Uv0, (VertexFormat::Float32x2, uvs.as_slice()));
+    attributes.insert(
+        VertexUsage::Color,
+        (VertexFormat::Float32x4, colors.as_slice()),
+    );
+    let mut writer = IyesMeshWriter::new();
+    writer
+        .add_mesh(MeshDataRef {
+            indices: Some((IndexFormat::U16, indices.as_slice())),
+            attributes,
+        })
+        .unwrap();
+    let mut file = std::fs::File::create(path).unwrap();
+    writer.write_to(&mut file).unwrap();
+}
+
+fn attributes(data: &IyesMeshReaderWithData) -> Vec<VertexUsage> {
+    let mut r: Vec<_> = data.descriptor().attributes.keys().copied().collect();
+    r.sort_by_key(|u| u.to_string());
+    r
+}
+
+#[test]
+fn keep_and_drop_attributes() {
+    let dir = TestDir::new();
+    write_with_attributes(&dir.path("in.ima"));
+    let original = decode_file(&dir.path("in.ima"));
+
+    run(&[
+        "edit",
+        "--keep-attr",
+        "position",
+        &dir.arg("in.ima"),
+        &dir.arg("kept.ima"),
+    ]);
+    let kept = decode_file(&dir.path("kept.ima"));
+    assert_eq!(attributes(&kept), [VertexUsage::Position]);
+    assert_eq!(mesh_positions(&kept), mesh_positions(&original));
+
+    run(&[
+        "edit",
+        "--drop-attr",
+        "normal",
+        &dir.arg("in.ima"),
+        &dir.arg("dropped.ima"),
+    ]);
+    let dropped = decode_file(&dir.path("dropped.ima"));
+    assert_eq!(
+        attributes(&dropped),
+        [VertexUsage::Color, VertexUsage::Position, VertexUsage::Uv0]
+    );
+
+    let output = iyesmesh()
+        .args(["edit", "--drop-attr", "position", &dir.arg("in.ima")])
+        .arg(dir.arg("no_positions.ima"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(!dir.path("no_positions.ima").exists());
+}
+
+#[test]
+fn quantize_positions() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("in.ima"), 1);
+    run(&[
+        "edit",
+        "--quantize-positions",
+        &dir.arg("in.ima"),
+        &dir.arg("out.ima"),
+    ]);
+    let original = decode_file(&dir.path("in.ima"));
+    let quantized = decode_file(&dir.path("out.ima"));
+    assert_eq!(
+        quantized.descriptor().attributes[&VertexUsage::Position],
+        VertexFormat::Unorm16x4
+    );
+    let buffers = quantized.into_flat_buffers().unwrap();
+    let split = quantized.into_split_meshes(&buffers).unwrap();
+    for (i, expected) in mesh_positions(&original).iter().enumerate() {
+        let decoded = quantized.decode_positions_f32(&split, i).unwrap();
+        for (d, e) in decoded.iter().zip(expected) {
+            // The grids are at most 8 units wide
+            assert!((0..3).all(|c| (d[c] - e[c]).abs() < 8.0 / 65535.0));
+        }
+    }
+
+    // Quantized input is decoded, unless quantizing again
+    run(&["edit", &dir.arg("out.ima"), &dir.arg("float.ima")]);
+    let float = decode_file(&dir.path("float.ima"));
+    assert_eq!(
+        float.descriptor().attributes[&VertexUsage::Position],
+        VertexFormat::Float32x3
+    );
+}
+
+#[test]
+fn convert_colors_to_srgb() {
+    let dir = TestDir::new();
+    write_with_attributes(&dir.path("in.ima"));
+    for (srgb, expected) in
+        [(false, [128, 0, 255, 128]), (true, [188, 0, 255, 128])]
+    {
+        let out = format!("out_{srgb}.ima");
+        let mut args = vec!["edit", "--convert-attr", "color=unorm8x4"];
+        if srgb {
+            args.push("--srgb");
+        }
+        let (infile, outfile) = (dir.arg("in.ima"), dir.arg(&out));
+        args.extend([infile.as_str(), outfile.as_str()]);
+        run(&args);
+
+        let data = decode_file(&dir.path(&out));
+        let buffers = data.into_flat_buffers().unwrap();
+        let meshes = data.into_split_meshes(&buffers).unwrap();
+        let (format, bytes) = meshes.meshes[0].attributes[&VertexUsage::Color];
+        assert_eq!(format, VertexFormat::Unorm8x4);
+        assert!(bytes.chunks_exact(4).all(|c| c == expected), "srgb: {srgb}");
+    }
+}
+
+#[test]
+fn weld_vertices() {
+    let dir = TestDir::new();
+    let soup = |(positions, indices): (Vec<[f32; 3]>, Vec<u16>)| {
+        let soup: Vec<[f32; 3]> =
+            indices.iter().map(|i| positions[*i as usize]).collect();
+        (soup, (0..indices.len() as u16).collect::<Vec<_>>())
+    };
+    let meshes = [soup(grid_mesh(4, 1)), soup(grid_mesh(3, 2))];
+    write_meshes(&dir.path("in.ima"), &meshes);
+    // Without an epsilon, --weld must not be followed by the paths
+    let output =
+        run(&["edit", &dir.arg("in.ima"), &dir.arg("out.ima"), "--weld"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Mesh 0: 54 -> 16 vertices"), "{stdout}");
+    assert!(stdout.contains("Mesh 1: 24 -> 9 vertices"), "{stdout}");
+
+    let data = decode_file(&dir.path("out.ima"));
+    let positions = mesh_positions(&data);
+    let buffers = data.into_flat_buffers().unwrap();
+    let welded = data.into_split_meshes(&buffers).unwrap();
+    for ((mesh, positions), (soup, _)) in
+        welded.meshes.iter().zip(&positions).zip(&meshes)
+    {
+        let corners: Vec<[f32; 3]> = mesh
+            .iter_indices()
+            .unwrap()
+            .map(|i| positions[i as usize])
+            .collect();
+        assert_eq!(&corners, soup);
+    }
+}
+
+#[test]
+fn generate_normals() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("in.ima"), 1);
+    run(&[
+        "edit",
+        "--gen-normals",
+        "smooth",
+        &dir.arg("in.ima"),
+        &dir.arg("out.ima"),
+    ]);
+    let data = decode_file(&dir.path("out.ima"));
+    assert_eq!(
+        mesh_positions(&data),
+        mesh_positions(&decode_file(&dir.path("in.ima")))
+    );
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    for mesh in meshes.meshes.iter() {
+        let (format, bytes) = mesh.attributes[&VertexUsage::Normal];
+        assert_eq!(format, VertexFormat::Float32x3);
+        for n in bytes.chunks_exact(12) {
+            let n: Vec<f32> = n
+                .chunks_exact(4)
+                .map(|c| f32::from_le_bytes(c.try_into().unwrap()))
+                .collect();
+            // Unit length, and up for the grids
+            let len = n.iter().map(|c| c * c).sum::<f32>().sqrt();
+            assert!((len - 1.0).abs() < 1e-5, "{n:?}");
+            assert!(n[1] > 0.0, "{n:?}");
+        }
+    }
+}
+
+#[test]
+fn split_components() {
+    let dir = TestDir::new();
+    // Two grids side by side in the first mesh, one in the second
+    let (mut positions, mut indices) = grid_mesh(4, 1);
+    let (other, other_indices) = grid_mesh(3, 2);
+    let n = positions.len() as u16;
+    positions.extend(other.iter().map(|p| [p[0] + 10.0, p[1], p[2]]));
+    indices.extend(other_indices.iter().map(|i| i + n));
+    write_meshes(&dir.path("in.ima"), &[(positions, indices), grid_mesh(5, 3)]);
+    let output = run(&[
+        "edit",
+        "--split-components",
+        &dir.arg("in.ima"),
+        &dir.arg("out.ima"),
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Mesh 0: 2 components"), "{stdout}");
+    assert!(stdout.contains("Mesh 1: 1 components"), "{stdout}");
+
+    let split = mesh_positions(&decode_file(&dir.path("out.ima")));
+    let moved: Vec<[f32; 3]> =
+        other.iter().map(|p| [p[0] + 10.0, p[1], p[2]]).collect();
+    assert_eq!(split, [grid_mesh(4, 1).0, moved, grid_mesh(5, 3).0]);
+}
+
+#[test]
+fn transform_selected_meshes() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("in.ima"), 1);
+    run(&[
+        "edit",
+        "--scale",
+        "-2,2,2",
+        "--translate",
+        "1,0,-1",
+        "--transform-mesh",
+        "1",
+        &dir.arg("in.ima"),
+        &dir.arg("out.ima"),
+    ]);
+    let input = decode_file(&dir.path("in.ima"));
+    let output = decode_file(&dir.path("out.ima"));
+    let (positions, indices) = (mesh_positions(&input), mesh_indices(&input));
+    let transformed = mesh_positions(&output);
+    assert_eq!(transformed[0], positions[0]);
+    let expected: Vec<[f32; 3]> = positions[1]
+        .iter()
+        .map(|p| [1.0 - 2.0 * p[0], 2.0 * p[1], 2.0 * p[2] - 1.0])
+        .collect();
+    assert_eq!(transformed[1], expected);
+    // Mirrored, so the winding is flipped
+    let flipped: Vec<u32> =
+        indices[1].chunks_exact(3).flat_map(|t| [t[0], t[2], t[1]]).collect();
+    assert_eq!(mesh_indices(&output), [indices[0].clone(), flipped]);
+}
+
+/// Write a 4x4 grid mesh with normals.
+fn write_with_normals(
+    path: &std::path::Path,
+    format: VertexFormat,
+    normals: &[u8],
+) {
+    let (positions, indices) = grid_mesh(4, 1);
+    let positions: Vec<u8> =
+        positions.as_flattened().iter().flat_map(|c| c.to_le_bytes()).collect();
+    let indices: Vec<u8> =
+        indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+    let mut attributes = HashMap::default();
+    attributes.insert(
+        VertexUsage::Position,
+        (VertexFormat::Float32x3, positions.as_slice()),
+    );
+    attributes.insert(VertexUsage::Normal, (format, normals));
+    let mut writer = IyesMeshWriter::new();
+    writer
+        .add_mesh(MeshDataRef {
+            indices: Some((IndexFormat::U16, indices.as_slice())),
+            attributes,
+        })
+        .unwrap();
+    let mut file = std::fs::File::create(path).unwrap();
+    writer.write_to(&mut file).unwrap();
+}
+
+#[test]
+fn invert_normals() {
+    let dir = TestDir::new();
+    let normals: Vec<u8> = [0.0f32, 1.0, 0.0]
+        .repeat(16)
+        .iter()
+        .flat_map(|c| c.to_le_bytes())
+        .collect();
+    write_with_normals(&dir.path("in.ima"), VertexFormat::Float32x3, &normals);
+    let output = run(&[
+        "edit",
+        "--invert-normals",
+        &dir.arg("in.ima"),
+        &dir.arg("out.ima"),
+    ]);
+    assert!(output.stderr.is_empty());
+    let data = decode_file(&dir.path("out.ima"));
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    let inverted: Vec<f32> = meshes.meshes[0].attributes[&VertexUsage::Normal]
+        .1
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes(c.try_into().unwrap()))
+        .collect();
+    assert_eq!(inverted, [0.0, -1.0, 0.0].repeat(16));
+
+    // Unsigned normals cannot be negated, and are kept
+    let normals = [128, 255, 128, 0].repeat(16);
+    write_with_normals(
+        &dir.path("unorm.ima"),
+        VertexFormat::Unorm8x4,
+        &normals,
+    );
+    let output = run(&[
+        "edit",
+        "--invert-normals",
+        &dir.arg("unorm.ima"),
+        &dir.arg("unorm_out.ima"),
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Warning: Mesh 0: "), "{stderr}");
+    let data = decode_file(&dir.path("unorm_out.ima"));
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    assert_eq!(
+        meshes.meshes[0].attributes[&VertexUsage::Normal],
+        (VertexFormat::Unorm8x4, normals.as_slice())
+    );
+
+    // Meshes without normals are an error
+    write_test_file(&dir.path("no_normals.ima"), 1);
+    let output = iyesmesh()
+        .args([
+            "edit",
+            "--invert-normals",
+            &dir.arg("no_normals.ima"),
+            &dir.arg("no_normals_out.ima"),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Cannot invert normals of mesh 0"), "{stderr}");
+}
+
+#[test]
+fn transform_uvs() {
+    let dir = TestDir::new();
+    write_with_attributes(&dir.path("in.ima"));
+    run(&[
+        "edit",
+        "--flip-uv-v",
+        "--uv-transform",
+        "2,4,0.25,-1",
+        &dir.arg("in.ima"),
+        &dir.arg("out.ima"),
+    ]);
+    let data = decode_file(&dir.path("out.ima"));
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    let uvs: Vec<[f32; 2]> =
+        meshes.meshes[0].uvs_f32(VertexUsage::Uv0).unwrap().collect();
+    // From 0.5, 0.5: U * 2 + 0.25, then (1 - V) * 4 - 1
+    assert_eq!(uvs, [[1.25, 1.0]; 36]);
+}
+
+#[test]
+fn recenter_and_unit_scale() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("in.ima"), 1);
+    let output = run(&[
+        "edit",
+        "--recenter",
+        "bounds",
+        "--unit-scale",
+        &dir.arg("in.ima"),
+        &dir.arg("out.ima"),
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // The grids span 0..=7 and 0..=4 in X and Z
+    assert!(stdout.contains("Mesh 0: translated by [-3.5, "), "{stdout}");
+    assert!(stdout.contains("then scaled by 0.14285715"), "{stdout}");
+    assert!(stdout.contains("Mesh 1: translated by [-2.0, "), "{stdout}");
+    assert!(stdout.contains("then scaled by 0.25"), "{stdout}");
+
+    for positions in mesh_positions(&decode_file(&dir.path("out.ima"))) {
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        for p in positions.iter() {
+            min = std::array::from_fn(|i| min[i].min(p[i]));
+            max = std::array::from_fn(|i| max[i].max(p[i]));
+        }
+        for i in 0..3 {
+            assert!((min[i] + max[i]).abs() < 1e-6, "{min:?} {max:?}");
+        }
+        assert_eq!((max[0] - min[0], max[2] - min[2]), (1.0, 1.0));
+    }
+}
+
+#[test]
+fn set_colorspace() {
+    let dir = TestDir::new();
+    write_with_attributes(&dir.path("in.ima"));
+    run(&[
+        "edit",
+        "--set-colorspace",
+        "color=srgb",
+        &dir.arg("in.ima"),
+        &dir.arg("srgb.ima"),
+    ]);
+    let data = decode_file(&dir.path("srgb.ima"));
+    assert_eq!(
+        data.descriptor().color_spaces.get(&VertexUsage::Color),
+        Some(&ColorSpace::Srgb)
+    );
+    let output = run(&["info", &dir.arg("srgb.ima")]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Float32x4 (Srgb)"), "{stdout}");
+
+    // Kept by other edits, and removed with `unspecified`
+    run(&[
+        "edit",
+        "--drop-attr",
+        "uv0",
+        &dir.arg("srgb.ima"),
+        &dir.arg("kept.ima"),
+    ]);
+    let data = decode_file(&dir.path("kept.ima"));
+    assert_eq!(
+        data.descriptor().color_spaces.get(&VertexUsage::Color),
+        Some(&ColorSpace::Srgb)
+    );
+    run(&[
+        "edit",
+        "--set-colorspace",
+        "color=unspecified",
+        &dir.arg("srgb.ima"),
+        &dir.arg("cleared.ima"),
+    ]);
+    let data = decode_file(&dir.path("cleared.ima"));
+    assert!(data.descriptor().color_spaces.is_empty());
+}
+
+/// Run an edit that is expected to fail, returning its error message.
+fn edit_error(args: &[&str]) -> String {
+    let output = iyesmesh().arg("edit").args(args).output().unwrap();
+    assert_eq!(output.status.code(), Some(2), "{args:?}");
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn keep_meshes() {
+    let dir = TestDir::new();
+    let meshes: Vec<_> = (0..4).map(|i| grid_mesh(3 + i, i as u64)).collect();
+    write_meshes(&dir.path("in.ima"), &meshes);
+    run(&[
+        "edit",
+        &dir.arg("in.ima"),
+        &dir.arg("out.ima"),
+        "--keep-mesh",
+        "3",
+        "--keep-mesh",
+        "1",
+    ]);
+    // In their order in the input file
+    let kept = decode_file(&dir.path("out.ima"));
+    assert_eq!(
+        mesh_positions(&kept),
+        [meshes[1].0.clone(), meshes[3].0.clone()]
+    );
+
+    let stderr = edit_error(&[
+        &dir.arg("in.ima"),
+        &dir.arg("conflict.ima"),
+        "--keep-mesh",
+        "1",
+        "--drop-mesh",
+        "2",
+    ]);
+    assert!(stderr.contains("cannot be used with"), "{stderr}");
+    let stderr = edit_error(&[
+        &dir.arg("in.ima"),
+        &dir.arg("missing.ima"),
+        "--keep-mesh",
+        "4",
+    ]);
+    assert!(
+        stderr.contains("Mesh 4 does not exist (the file has 4 meshes)"),
+        "{stderr}"
+    );
+    assert!(!dir.path("conflict.ima").exists());
+    assert!(!dir.path("missing.ima").exists());
+}
+
+#[test]
+fn reorder_meshes() {
+    let dir = TestDir::new();
+    let meshes: Vec<_> = (0..4).map(|i| grid_mesh(3 + i, i as u64)).collect();
+    write_meshes(&dir.path("in.ima"), &meshes);
+    for (order, expected) in [("2,0,3,1", [2, 0, 3, 1]), ("3,1", [3, 1, 0, 2])]
+    {
+        let out = format!("{}.ima", order.replace(',', "_"));
+        run(&["edit", &dir.arg("in.ima"), &dir.arg(&out), "--order", order]);
+        let reordered = decode_file(&dir.path(&out));
+        let expected_positions: Vec<_> =
+            expected.iter().map(|&i| meshes[i].0.clone()).collect();
+        assert_eq!(mesh_positions(&reordered), expected_positions, "{order}");
+        let expected_indices: Vec<Vec<u32>> = expected
+            .iter()
+            .map(|&i| meshes[i].1.iter().map(|&i| i as u32).collect())
+            .collect();
+        assert_eq!(mesh_indices(&reordered), expected_indices, "{order}");
+    }
+
+    // Reordering composes with dropping meshes
+    run(&[
+        "edit",
+        &dir.arg("in.ima"),
+        &dir.arg("dropped.ima"),
+        "--order",
+        "3,2",
+        "--drop-mesh",
+        "2",
+    ]);
+    let dropped = decode_file(&dir.path("dropped.ima"));
+    assert_eq!(
+        mesh_positions(&dropped),
+        [
+            meshes[3].0.clone(),
+            meshes[0].0.clone(),
+            meshes[1].0.clone()
+        ]
+    );
+
+    for (order, error) in [
+        ("1,2,1", "Mesh 1 is listed more than once in --order"),
+        ("0,4", "Mesh 4 does not exist (the file has 4 meshes)"),
+    ] {
+        let stderr = edit_error(&[
+            &dir.arg("in.ima"),
+            &dir.arg("bad.ima"),
+            "--order",
+            order,
+        ]);
+        assert!(stderr.contains(error), "{stderr}");
+        assert!(!dir.path("bad.ima").exists());
+    }
+}
+
+#[test]
+fn drop_attributes() {
+    let dir = TestDir::new();
+    write_with_attributes(&dir.path("in.ima"));
+    run(&[
+        "edit",
+        "--drop-attr",
+        "color",
+        "--drop-attr",
+        "uv0",
+        &dir.arg("in.ima"),
+        &dir.arg("dropped.ima"),
+    ]);
+    let original = decode_file(&dir.path("in.ima"));
+    let dropped = decode_file(&dir.path("dropped.ima"));
+    assert_eq!(
+        attributes(&dropped),
+        [VertexUsage::Normal, VertexUsage::Position]
+    );
+    // 36 vertices without 16 bytes of color and 8 bytes of UVs
+    let sizes = |data: &IyesMeshReaderWithData| {
+        data.descriptor().compute_all_vertex_buf_sizes()
+    };
+    assert_eq!(sizes(&original) - sizes(&dropped), 36 * (16 + 8));
+    assert!(
+        std::fs::metadata(dir.path("dropped.ima")).unwrap().len()
+            < std::fs::metadata(dir.path("in.ima")).unwrap().len()
+    );
+    assert_eq!(mesh_positions(&dropped), mesh_positions(&original));
+
+    run(&[
+        "edit",
+        "--drop-attr",
+        "position",
+        "--force",
+        &dir.arg("in.ima"),
+        &dir.arg("forced.ima"),
+    ]);
+    let forced = decode_file(&dir.path("forced.ima"));
+    assert_eq!(
+        attributes(&forced),
+        [VertexUsage::Color, VertexUsage::Normal, VertexUsage::Uv0]
+    );
+
+    let stderr = edit_error(&[
+        "--drop-attr",
+        "tangent",
+        &dir.arg("in.ima"),
+        &dir.arg("unknown.ima"),
+    ]);
+    assert!(
+        stderr.contains(
+            "Cannot drop attribute Tangent, the file does not have it \
+             (attributes: Position, Normal, Uv0, Color)"
+        ),
+        "{stderr}"
+    );
+    assert!(!dir.path("unknown.ima").exists());
+}
+
+/// The bytes of an attribute of each mesh of a decoded file.
+fn attribute_bytes(
+    data: &IyesMeshReaderWithData,
+    usage: VertexUsage,
+) -> Vec<(VertexFormat, Vec<u8>)> {
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    meshes
+        .meshes
+        .iter()
+        .map(|mesh| {
+            let (format, bytes) = mesh.attributes[&usage];
+            (format, bytes.to_vec())
+        })
+        .collect()
+}
+
+#[test]
+fn add_attribute_from_file() {
+    let dir = TestDir::new();
+    // 64 and 25 vertices
+    write_test_file(&dir.path("in.ima"), 1);
+    let ao: Vec<u8> =
+        (0..89).flat_map(|i| (i as f32 / 89.0).to_le_bytes()).collect();
+    std::fs::write(dir.path("ao.bin"), &ao).unwrap();
+    let ao_arg = format!("custom:3:float32:{}", dir.arg("ao.bin"));
+    run(&[
+        "edit",
+        "--add-attr",
+        &ao_arg,
+        &dir.arg("in.ima"),
+        &dir.arg("all.ima"),
+    ]);
+    // Split between the meshes by their vertex counts
+    let all = decode_file(&dir.path("all.ima"));
+    assert_eq!(
+        attribute_bytes(&all, VertexUsage::Custom(3)),
+        [
+            (VertexFormat::Float32, ao[..64 * 4].to_vec()),
+            (VertexFormat::Float32, ao[64 * 4..].to_vec())
+        ]
+    );
+    assert_eq!(
+        mesh_positions(&all),
+        mesh_positions(&decode_file(&dir.path("in.ima")))
+    );
+
+    // Only for the second mesh, so the first one has to go
+    std::fs::write(dir.path("ao1.bin"), &ao[64 * 4..]).unwrap();
+    run(&[
+        "edit",
+        "--add-attr",
+        &format!("custom:3:float32:{}:1", dir.arg("ao1.bin")),
+        "--keep-mesh",
+        "1",
+        &dir.arg("in.ima"),
+        &dir.arg("one.ima"),
+    ]);
+    let one = decode_file(&dir.path("one.ima"));
+    assert_eq!(
+        attribute_bytes(&one, VertexUsage::Custom(3)),
+        [(VertexFormat::Float32, ao[64 * 4..].to_vec())]
+    );
+
+    let stderr = edit_error(&[
+        "--add-attr",
+        &format!("custom:3:float32:{}:0", dir.arg("ao1.bin")),
+        &dir.arg("in.ima"),
+        &dir.arg("short.ima"),
+    ]);
+    assert!(
+        stderr.contains(&format!(
+            "{} has 100 bytes, expected 256 (64 vertices of 4 bytes)",
+            dir.arg("ao1.bin")
+        )),
+        "{stderr}"
+    );
+
+    // Replacing attributes needs --overwrite-attr
+    let positions: Vec<u8> =
+        (0..89 * 3).flat_map(|_| 1f32.to_le_bytes()).collect();
+    std::fs::write(dir.path("positions.bin"), &positions).unwrap();
+    let positions_arg =
+        format!("position:float32x3:{}", dir.arg("positions.bin"));
+    let stderr = edit_error(&[
+        "--add-attr",
+        &positions_arg,
+        &dir.arg("in.ima"),
+        &dir.arg("replaced.ima"),
+    ]);
+    assert!(
+        stderr.contains(
+            "Mesh 0 already has attribute Position (see --overwrite-attr)"
+        ),
+        "{stderr}"
+    );
+    assert!(!dir.path("replaced.ima").exists());
+    run(&[
+        "edit",
+        "--add-attr",
+        &positions_arg,
+        "--overwrite-attr",
+        &dir.arg("in.ima"),
+        &dir.arg("replaced.ima"),
+    ]);
+    let replaced = decode_file(&dir.path("replaced.ima"));
+    assert_eq!(
+        mesh_positions(&replaced),
+        [vec![[1.0; 3]; 64], vec![[1.0; 3]; 25]]
+    );
+}
+
+#[cfg(feature = "f16")]
+#[test]
+fn convert_uvs_to_half_precision() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("grids.ima"), 1);
+    let uvs: Vec<f32> = (0..89 * 2).map(|i| (i as f32 * 0.37).sin()).collect();
+    let bytes: Vec<u8> = uvs.iter().flat_map(|c| c.to_le_bytes()).collect();
+    std::fs::write(dir.path("uvs.bin"), &bytes).unwrap();
+    run(&[
+        "edit",
+        "--add-attr",
+        &format!("uv1:float32x2:{}", dir.arg("uvs.bin")),
+        &dir.arg("grids.ima"),
+        &dir.arg("in.ima"),
+    ]);
+
+    let output = run(&[
+        "--verbose",
+        "edit",
+        "--convert-attr",
+        "uv1=float16x2",
+        &dir.arg("in.ima"),
+        &dir.arg("half.ima"),
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "Uv1: Float32x2 -> Float16x2, 712 -> 356 bytes (-356 bytes)"
+        ),
+        "{stderr}"
+    );
+    let half = decode_file(&dir.path("half.ima"));
+    assert!(
+        attribute_bytes(&half, VertexUsage::Uv1)
+            .iter()
+            .all(|(format, _)| *format == VertexFormat::Float16x2)
+    );
+
+    run(&[
+        "edit",
+        "--convert-attr",
+        "uv1=float32x2",
+        &dir.arg("half.ima"),
+        &dir.arg("back.ima"),
+    ]);
+    let back = decode_file(&dir.path("back.ima"));
+    let converted: Vec<f32> = attribute_bytes(&back, VertexUsage::Uv1)
+        .iter()
+        .flat_map(|(format, bytes)| {
+            assert_eq!(*format, VertexFormat::Float32x2);
+            bytes
+                .chunks_exact(4)
+                .map(|c| f32::from_le_bytes(c.try_into().unwrap()))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    assert_eq!(converted.len(), uvs.len());
+    for (c, u) in converted.iter().zip(&uvs) {
+        // Half of the spacing of half-precision values (11 bits of mantissa)
+        assert!((c - u).abs() <= u.abs() * 2f32.powi(-11) + 1e-7, "{c} {u}");
+    }
+}
+
+#[cfg(feature = "f16")]
+#[test]
+fn convert_attr_errors_and_clamping() {
+    let dir = TestDir::new();
+    write_with_attributes(&dir.path("in.ima"));
+    let stderr = edit_error(&[
+        "--convert-attr",
+        "uv0=uint32x2",
+        &dir.arg("in.ima"),
+        &dir.arg("out.ima"),
+    ]);
+    assert!(
+        stderr.contains(
+            "Cannot convert Uv0 from Float32x2 to Uint32x2 (possible formats: "
+        ),
+        "{stderr}"
+    );
+    assert!(stderr.contains("Float16x2"), "{stderr}");
+    assert!(!dir.path("out.ima").exists());
+
+    // The blue channel of the colors is 2.0
+    let output = run(&[
+        "edit",
+        "--convert-attr",
+        "color=unorm16x4",
+        &dir.arg("in.ima"),
+        &dir.arg("out.ima"),
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "Warning: Color: 36 values are outside of 0..=1, and were clamped"
+        ),
+        "{stderr}"
+    );
+}
+
+/// Write a mesh with U32 indices, whose largest index is `max_index`.
+fn write_u32_indices(
+    path: &std::path::Path,
+    max_index: u32,
+) {
+    let n_vertices = max_index as usize + 1;
+    let positions: Vec<u8> = (0..n_vertices * 3)
+        .flat_map(|i| ((i / 3) as f32).to_le_bytes())
+        .collect();
+    let indices: Vec<u8> = [0, max_index - 1, max_index, max_index, 1, 0]
+        .iter()
+        .flat_map(|i: &u32| i.to_le_bytes())
+        .collect();
+    let mut attributes = HashMap::default();
+    attributes.insert(
+        VertexUsage::Position,
+        (VertexFormat::Float32x3, positions.as_slice()),
+    );
+    let mut writer = IyesMeshWriter::new();
+    writer
+        .add_mesh(MeshDataRef {
+            indices: Some((IndexFormat::U32, indices.as_slice())),
+            attributes,
+        })
+        .unwrap();
+    let mut file = std::fs::File::create(path).unwrap();
+    writer.write_to(&mut file).unwrap();
+}
+
+#[test]
+fn downconvert_indices_at_the_limit() {
+    let dir = TestDir::new();
+    let index_format = |name: &str| {
+        decode_file(&dir.path(name)).descriptor().indices.map(|i| i.format)
+    };
+
+    write_u32_indices(&dir.path("fits.ima"), 65535);
+    run(&[
+        "edit",
+        "--downconvert-indices",
+        "--strict",
+        &dir.arg("fits.ima"),
+        &dir.arg("fits_u16.ima"),
+    ]);
+    assert_eq!(index_format("fits_u16.ima"), Some(IndexFormat::U16));
+    let converted = decode_file(&dir.path("fits_u16.ima"));
+    assert_eq!(mesh_indices(&converted), [[0, 65534, 65535, 65535, 1, 0]]);
+
+    write_u32_indices(&dir.path("too_large.ima"), 65536);
+    let output = run(&[
+        "edit",
+        "--downconvert-indices",
+        &dir.arg("too_large.ima"),
+        &dir.arg("kept.ima"),
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains(
+            "Mesh 0: index 65536 does not fit in 16 bits, not downconverting \
+             indices"
+        ),
+        "{stdout}"
+    );
+    assert_eq!(index_format("kept.ima"), Some(IndexFormat::U32));
+
+    let stderr = edit_error(&[
+        "--downconvert-indices",
+        "--strict",
+        &dir.arg("too_large.ima"),
+        &dir.arg("strict.ima"),
+    ]);
+    assert!(
+        stderr.contains("Cannot downconvert indices: mesh 0 has index 65536"),
+        "{stderr}"
+    );
+    assert!(!dir.path("strict.ima").exists());
+}
+
+fn assert_close(
+    a: &[[f32; 3]],
+    b: &[[f32; 3]],
+) {
+    assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter().zip(b) {
+        assert!((0..3).all(|c| (a[c] - b[c]).abs() < 1e-6), "{a:?} {b:?}");
+    }
+}
+
+#[test]
+fn transform_cube() {
+    let dir = TestDir::new();
+    let (positions, indices) = cube_mesh();
+    write_meshes(&dir.path("cube.ima"), &[(positions.clone(), indices)]);
+
+    // Centimeters to meters
+    let output = run(&[
+        "--verbose",
+        "edit",
+        "--scale",
+        "0.01",
+        &dir.arg("cube.ima"),
+        &dir.arg("meters.ima"),
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "Transform matrix:\n      0.0100     0.0000     0.0000     0.0000\n"
+        ),
+        "{stderr}"
+    );
+    let meters = mesh_positions(&decode_file(&dir.path("meters.ima")));
+    let expected: Vec<_> =
+        positions.iter().map(|p| p.map(|c| c * 0.01)).collect();
+    assert_close(&meters[0], &expected);
+
+    // Scaled, then rotated around Y, then translated, as a single transform
+    run(&[
+        "edit",
+        "--translate",
+        "0,0,5",
+        "--rotate-deg",
+        "0,90,0",
+        "--scale",
+        "2",
+        &dir.arg("cube.ima"),
+        &dir.arg("composed.ima"),
+    ]);
+    let composed = mesh_positions(&decode_file(&dir.path("composed.ima")));
+    let expected: Vec<_> = positions
+        .iter()
+        .map(|p| [2.0 * p[2], 2.0 * p[1], 5.0 - 2.0 * p[0]])
+        .collect();
+    assert_close(&composed[0], &expected);
+}
@@ -0,0 +1,86 @@
+use iyes_mesh::descriptor::VertexUsage;
+
+mod common;
+use common::*;
+
+/// A file with three meshes and some user data.
+fn write_three_meshes(dir: &TestDir) -> Vec<(Vec<[f32; 3]>, Vec<u16>)> {
+    let meshes = vec![grid_mesh(4, 1), grid_mesh(6, 2), grid_mesh(5, 3)];
+    write_meshes(&dir.path("plain.ima"), &meshes);
+    std::fs::write(dir.path("user.txt"), "user data").unwrap();
+    run(&[
+        "edit",
+        "--user-data",
+        &dir.arg("user.txt"),
+        &dir.arg("plain.ima"),
+        &dir.arg("three.ima"),
+    ]);
+    meshes
+}
+
+#[test]
+fn extract_one_mesh() {
+    let dir = TestDir::new();
+    let meshes = write_three_meshes(&dir);
+    run(&[
+        "extract-mesh",
+        "--mesh",
+        "1",
+        &dir.arg("three.ima"),
+        &dir.arg("one.ima"),
+    ]);
+    let one = decode_file(&dir.path("one.ima"));
+    assert_eq!(mesh_positions(&one), [meshes[1].0.clone()]);
+    let indices: Vec<u32> = meshes[1].1.iter().map(|&i| i as u32).collect();
+    assert_eq!(mesh_indices(&one), [indices]);
+    assert_eq!(one.decode_user_data().unwrap(), None);
+
+    // The buffers are the slices of the ones of the original
+    let three = decode_file(&dir.path("three.ima"));
+    let mesh = &three.descriptor().meshes[1];
+    let buffers = three.into_flat_buffers().unwrap();
+    let (_, positions) = buffers.buf_attrs[&VertexUsage::Position];
+    let first = mesh.first_vertex as usize * 12;
+    let last = first + mesh.vertex_count as usize * 12;
+    let one_buffers = one.into_flat_buffers().unwrap();
+    let (_, one_positions) = one_buffers.buf_attrs[&VertexUsage::Position];
+    assert!(one_positions == &positions[first..last]);
+
+    run(&[
+        "extract-mesh",
+        "--mesh",
+        "2",
+        "--keep-user-data",
+        &dir.arg("three.ima"),
+        &dir.arg("two.ima"),
+    ]);
+    let two = decode_file(&dir.path("two.ima"));
+    assert_eq!(mesh_positions(&two), [meshes[2].0.clone()]);
+    assert_eq!(
+        two.decode_user_data().unwrap().as_deref(),
+        Some(&b"user data"[..])
+    );
+}
+
+#[test]
+fn extract_missing_mesh() {
+    let dir = TestDir::new();
+    write_three_meshes(&dir);
+    let output = iyesmesh()
+        .args([
+            "extract-mesh",
+            "--mesh",
+            "3",
+            &dir.arg("three.ima"),
+            &dir.arg("out.ima"),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Mesh 3 does not exist (the file has 3 meshes)"),
+        "{stderr}"
+    );
+    assert!(!dir.path("out.ima").exists());
+}
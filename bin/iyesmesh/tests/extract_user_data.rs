@@ -0,0 +1,273 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::Stdio;
+
+use iyes_mesh::HashMap;
+use iyes_mesh::checksum::ChecksumKind;
+use iyes_mesh::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+use iyes_mesh::mesh::MeshDataRef;
+use iyes_mesh::write::IyesMeshWriter;
+
+mod common;
+use common::*;
+
+/// Write a file with a grid mesh and the given user data.
+fn write_with_user_data(
+    path: &Path,
+    user_data: &[u8],
+) {
+    let (positions, indices) = grid_mesh(8, 1);
+    let positions: Vec<u8> =
+        positions.iter().flatten().flat_map(|c| c.to_le_bytes()).collect();
+    let indices: Vec<u8> =
+        indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+    let mut attributes = HashMap::default();
+    attributes.insert(
+        VertexUsage::Position,
+        (VertexFormat::Float32x3, positions.as_slice()),
+    );
+    let mut writer = IyesMeshWriter::new();
+    writer
+        .add_mesh(MeshDataRef {
+            indices: Some((IndexFormat::U16, indices.as_slice())),
+            attributes,
+        })
+        .unwrap();
+    writer.set_user_data(user_data);
+    let mut file = std::fs::File::create(path).unwrap();
+    writer.write_to(&mut file).unwrap();
+}
+
+/// User data that does not compress, so that it is stored as-is.
+fn noise(len: usize) -> Vec<u8> {
+    let mut rng = 7u64;
+    (0..len)
+        .map(|_| {
+            rng = rng
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (rng >> 56) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn info_prints_the_user_data_checksum() {
+    let dir = TestDir::new();
+    let user_data = b"some user data";
+    write_with_user_data(&dir.path("a.iyesmesh"), user_data);
+    let output = run(&["--verbose", "info", &dir.arg("a.iyesmesh")]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let kind = ChecksumKind::from_id(
+        decode_file(&dir.path("a.iyesmesh")).descriptor().checksum_kind,
+    )
+    .unwrap();
+    let expected =
+        format!("User data checksum: {:016x}", kind.checksum(user_data));
+    assert!(stdout.contains(&expected), "{stdout}");
+}
+
+#[test]
+fn extract_verifies_the_user_data() {
+    let dir = TestDir::new();
+    // More than a zstd block, so that at least one is stored raw
+    let user_data = noise(300_000);
+    write_with_user_data(&dir.path("a.iyesmesh"), &user_data);
+    run(&[
+        "extract-user-data",
+        &dir.arg("a.iyesmesh"),
+        &dir.arg("a.bin"),
+    ]);
+    assert!(std::fs::read(dir.path("a.bin")).unwrap() == user_data);
+
+    let mut bytes = std::fs::read(dir.path("a.iyesmesh")).unwrap();
+    let window = &user_data[150_000..150_032];
+    let at = bytes.windows(window.len()).position(|w| w == window).unwrap();
+    bytes[at] ^= 0xff;
+    std::fs::write(dir.path("b.iyesmesh"), &bytes).unwrap();
+
+    let output = iyesmesh()
+        .args([
+            "extract-user-data",
+            &dir.arg("b.iyesmesh"),
+            &dir.arg("b.bin"),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Cannot decode user data"), "{stderr}");
+
+    run(&[
+        "extract-user-data",
+        "--no-verify",
+        &dir.arg("b.iyesmesh"),
+        &dir.arg("c.bin"),
+    ]);
+    let extracted = std::fs::read(dir.path("c.bin")).unwrap();
+    assert_eq!(extracted.len(), user_data.len());
+    assert!(extracted != user_data);
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn user_data_key_file() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("a.iyesmesh"), 1);
+    std::fs::write(dir.path("user.txt"), "secret balance data").unwrap();
+    std::fs::write(dir.path("key.hex"), "ab".repeat(32) + "\n").unwrap();
+    run(&[
+        "edit",
+        &dir.arg("a.iyesmesh"),
+        &dir.arg("b.iyesmesh"),
+        "--user-data",
+        &dir.arg("user.txt"),
+        "--user-data-key-file",
+        &dir.arg("key.hex"),
+    ]);
+    let stored = decode_file(&dir.path("b.iyesmesh"));
+    assert!(stored.descriptor().user_data_nonce.is_some());
+
+    // The same key, stored raw
+    std::fs::write(dir.path("key.bin"), [0xab; 32]).unwrap();
+    run(&[
+        "extract-user-data",
+        &dir.arg("b.iyesmesh"),
+        &dir.arg("user.out"),
+        "--user-data-key-file",
+        &dir.arg("key.bin"),
+    ]);
+    assert_eq!(
+        std::fs::read(dir.path("user.out")).unwrap(),
+        b"secret balance data"
+    );
+
+    std::fs::write(dir.path("wrong.bin"), [0xcd; 32]).unwrap();
+    for key in [None, Some("wrong.bin")] {
+        let mut args =
+            vec!["extract-user-data".to_owned(), dir.arg("b.iyesmesh")];
+        if let Some(key) = key {
+            args.extend(["--user-data-key-file".to_owned(), dir.arg(key)]);
+        }
+        let output = iyesmesh().args(&args).output().unwrap();
+        assert!(!output.status.success(), "{key:?}");
+    }
+}
+
+/// User data from stdin is taken out of IMA files too, unless forced raw.
+#[test]
+fn user_data_from_stdin() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("a.ima"), 1);
+    write_with_user_data(&dir.path("source.ima"), b"piped user data");
+    let source = std::fs::read(dir.path("source.ima")).unwrap();
+
+    for (out, force_raw, expected) in [
+        ("b.ima", false, &b"piped user data"[..]),
+        ("c.ima", true, &source[..]),
+    ] {
+        let mut args = vec![
+            "edit".to_owned(),
+            "--user-data=-".to_owned(),
+            dir.arg("a.ima"),
+            dir.arg(out),
+        ];
+        if force_raw {
+            args.push("--user-data-force-raw".to_owned());
+        }
+        let mut child = iyesmesh()
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(&source).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let with_data = decode_file(&dir.path(out));
+        assert_eq!(
+            with_data.decode_user_data().unwrap().as_deref(),
+            Some(expected)
+        );
+    }
+}
+
+#[test]
+fn output_modes() {
+    let dir = TestDir::new();
+    let blob = b"Level 1\x00\x01\x02\xff\tspawn: [1, 2]\n\xe2\x82\xac";
+    write_with_user_data(&dir.path("a.ima"), blob);
+    let extract = |args: &[&str]| {
+        let mut all = vec!["extract-user-data"];
+        all.extend(args);
+        let path = dir.arg("a.ima");
+        all.push(&path);
+        String::from_utf8(run(&all).stdout).unwrap()
+    };
+
+    assert_eq!(
+        extract(&["--hex"]),
+        "00000000  4c 65 76 65 6c 20 31 00  01 02 ff 09 73 70 61 77  \
+         |Level 1.....spaw|\n\
+         00000010  6e 3a 20 5b 31 2c 20 32  5d 0a e2 82 ac           \
+         |n: [1, 2]....|\n\
+         0000001d\n"
+    );
+    assert_eq!(
+        extract(&["--base64"]),
+        "TGV2ZWwgMQABAv8Jc3Bhd246IFsxLCAyXQrigqw=\n"
+    );
+    assert_eq!(
+        extract(&["--string"]),
+        "Level 1\\u{0}\\u{1}\\u{2}\u{fffd}\\tspawn: [1, 2]\n\u{20ac}\n"
+    );
+    assert_eq!(
+        extract(&["--hex", "--offset", "4", "--length", "12"]),
+        "00000004  6c 20 31 00 01 02 ff 09  73 70 61 77              \
+         |l 1.....spaw|\n\
+         00000010\n"
+    );
+    // Raw bytes by default, even if they are not valid UTF-8
+    let output = run(&[
+        "extract-user-data",
+        "--offset",
+        "23",
+        "--length",
+        "5",
+        &dir.arg("a.ima"),
+    ]);
+    assert_eq!(output.stdout, b"2]\n\xe2\x82");
+
+    // The same when written to a file
+    run(&[
+        "extract-user-data",
+        "--base64",
+        "--offset",
+        "26",
+        &dir.arg("a.ima"),
+        &dir.arg("euro.txt"),
+    ]);
+    assert_eq!(std::fs::read(dir.path("euro.txt")).unwrap(), b"4oKs\n");
+
+    for (args, error) in [
+        (
+            ["--offset", "30"],
+            "Offset 30 is past the end of the user data (29 bytes)",
+        ),
+        (["--length", "30"], "Bytes 0..30 are out of the user data (29 bytes)"),
+    ] {
+        let output = iyesmesh()
+            .arg("extract-user-data")
+            .args(args)
+            .arg(dir.arg("a.ima"))
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(error), "{stderr}");
+    }
+}
@@ -0,0 +1,76 @@
+use iyes_mesh::header::IyesMeshHeader;
+
+mod common;
+use common::*;
+
+/// See `info.rs` for the contents of the fixture.
+const FIXTURE: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/two_grids.ima");
+
+fn header(bytes: &[u8]) -> IyesMeshHeader {
+    IyesMeshHeader::from_bytes(&bytes[..IyesMeshHeader::encoded_len()]).unwrap()
+}
+
+/// Write a copy of the fixture with wrong checksums in its header.
+fn write_broken(path: &std::path::Path) {
+    let mut bytes = std::fs::read(FIXTURE).unwrap();
+    let mut broken = header(&bytes);
+    broken.metadata_checksum ^= 1;
+    broken.data_checksum ^= 1;
+    bytes[..IyesMeshHeader::encoded_len()].copy_from_slice(broken.as_bytes());
+    std::fs::write(path, bytes).unwrap();
+}
+
+fn verify_passes(path: &str) -> bool {
+    let output = iyesmesh().args(["verify", path]).output().unwrap();
+    output.status.success()
+}
+
+#[test]
+fn fix_checksums_in_place() {
+    let dir = TestDir::new();
+    write_broken(&dir.path("a.ima"));
+    assert!(!verify_passes(&dir.arg("a.ima")));
+
+    let output = run(&["fix-checksums", &dir.arg("a.ima")]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Data checksum: "), "{stdout}");
+    assert!(verify_passes(&dir.arg("a.ima")));
+    assert!(
+        std::fs::read(dir.path("a.ima")).unwrap()
+            == std::fs::read(FIXTURE).unwrap()
+    );
+
+    let output = run(&["fix-checksums", &dir.arg("a.ima")]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Checksums are already correct."), "{stdout}");
+}
+
+#[test]
+fn fix_checksums_to_another_file() {
+    let dir = TestDir::new();
+    write_broken(&dir.path("a.ima"));
+    let broken = std::fs::read(dir.path("a.ima")).unwrap();
+    run(&["fix-checksums", &dir.arg("a.ima"), &dir.arg("b.ima")]);
+    assert!(std::fs::read(dir.path("a.ima")).unwrap() == broken);
+    assert!(verify_passes(&dir.arg("b.ima")));
+
+    // The header is the only difference
+    let fixed = std::fs::read(dir.path("b.ima")).unwrap();
+    let header_len = IyesMeshHeader::encoded_len();
+    assert!(fixed[header_len..] == broken[header_len..]);
+}
+
+#[test]
+fn strip_data_checksum() {
+    let dir = TestDir::new();
+    write_broken(&dir.path("a.ima"));
+    run(&["fix-checksums", "--strip", &dir.arg("a.ima")]);
+    let bytes = std::fs::read(dir.path("a.ima")).unwrap();
+    assert_eq!({ header(&bytes).data_checksum }, 0);
+    assert!(verify_passes(&dir.arg("a.ima")));
+    assert_eq!(
+        mesh_positions(&decode_bytes(&bytes)),
+        mesh_positions(&decode_file(FIXTURE.as_ref()))
+    );
+}
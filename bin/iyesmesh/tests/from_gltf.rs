@@ -0,0 +1,143 @@
+#![cfg(feature = "gltf")]
+
+use iyes_mesh::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+
+mod common;
+use common::*;
+
+/// A GLB file with two nodes in its scene: `plain`, using the `triangle`
+/// mesh, and `moved` (translated by 10 on X), with a child `child` (scaled
+/// by 2) using the `quad` mesh. The positions of the quad are a sparse
+/// accessor, moving its last vertex to (5, 5, 5).
+///
+/// The primitives have all the standard attributes, as normalized U16
+/// UVs, RGB U8 colors (strided), U8 joints and float weights, plus a
+/// U16x3 `_CUSTOM_3` attribute and an `_UNKNOWN` attribute. The file
+/// `extras` are `{"game":"test","level":3}`.
+const SCENE: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/scene.glb");
+
+fn import(
+    dir: &TestDir,
+    args: &[&str],
+) -> (std::process::Output, iyes_mesh::read::IyesMeshReaderWithData) {
+    let out = dir.arg("out.ima");
+    let mut all = vec!["from-gltf"];
+    all.extend_from_slice(args);
+    all.extend([SCENE, out.as_str()]);
+    let output = run(&all);
+    (output, decode_file(&dir.path("out.ima")))
+}
+
+/// The elements of an attribute of a mesh, as little-endian components of
+/// `size` bytes.
+fn elements(
+    bytes: &[u8],
+    format: VertexFormat,
+    size: usize,
+) -> Vec<Vec<u32>> {
+    bytes
+        .chunks_exact(format.size())
+        .map(|e| {
+            e.chunks_exact(size)
+                .map(|c| {
+                    let mut b = [0; 4];
+                    b[..size].copy_from_slice(c);
+                    u32::from_le_bytes(b)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn import_scene() {
+    let dir = TestDir::new();
+    let (output, data) = import(&dir, &[]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("_UNKNOWN"));
+
+    let positions = mesh_positions(&data);
+    assert_eq!(positions.len(), 2);
+    assert_eq!(
+        positions[0],
+        [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+    );
+    assert_eq!(positions[1].len(), 4);
+    assert_eq!(positions[1][3], [5.0, 5.0, 5.0]);
+    assert_eq!(positions[1][2], [0.0, 1.0, 0.0]);
+
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    let quad = &meshes.meshes[1];
+    let (format, indices) = quad.indices.unwrap();
+    assert_eq!(format, IndexFormat::U16);
+    assert_eq!(elements(indices, VertexFormat::Uint16, 2).len(), 6);
+
+    let expect = |usage, format: VertexFormat, size, vertex, values: &[u32]| {
+        let (found, bytes) = quad.attributes[&usage];
+        assert_eq!(found, format, "{usage:?}");
+        assert_eq!(elements(bytes, format, size)[vertex], values, "{usage:?}");
+    };
+    expect(VertexUsage::Uv0, VertexFormat::Unorm16x2, 2, 2, &[2000, 65535]);
+    expect(VertexUsage::Color, VertexFormat::Unorm8x4, 1, 3, &[255, 3, 0, 255]);
+    expect(VertexUsage::JointIndex, VertexFormat::Uint8x4, 1, 0, &[0, 1, 0, 0]);
+    expect(VertexUsage::Custom(3), VertexFormat::Uint16x4, 2, 1, &[1, 2, 3, 0]);
+    let weights = [0.75f32, 0.25, 0.0, 0.0].map(f32::to_bits);
+    expect(VertexUsage::JointWeight, VertexFormat::Float32x4, 4, 0, &weights);
+    let normal = [0.0f32, 0.0, 1.0].map(f32::to_bits);
+    expect(VertexUsage::Normal, VertexFormat::Float32x3, 4, 3, &normal);
+    assert_eq!(quad.attributes.len(), 7);
+    assert_eq!(data.decode_user_data().unwrap(), None);
+}
+
+#[test]
+fn bake_transforms() {
+    let dir = TestDir::new();
+    let (_, data) = import(&dir, &["--bake-transforms"]);
+    let positions = mesh_positions(&data);
+    assert_eq!(positions.len(), 2);
+    assert_eq!(positions[0][1], [1.0, 0.0, 0.0]);
+    assert_eq!(
+        positions[1],
+        [
+            [10.0, 0.0, 0.0],
+            [12.0, 0.0, 0.0],
+            [10.0, 2.0, 0.0],
+            [20.0, 10.0, 10.0]
+        ]
+    );
+}
+
+#[test]
+fn select_node() {
+    let dir = TestDir::new();
+    let (_, data) = import(&dir, &["--node", "2"]);
+    let positions = mesh_positions(&data);
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions[0][3], [5.0, 5.0, 5.0]);
+
+    // the transforms of the parents of the node apply too
+    let (_, data) = import(&dir, &["--node", "2", "--bake-transforms", "-o"]);
+    assert_eq!(mesh_positions(&data)[0][3], [20.0, 10.0, 10.0]);
+}
+
+#[test]
+fn extras_user_data() {
+    let dir = TestDir::new();
+    let (_, data) = import(&dir, &["--extras-user-data"]);
+    assert_eq!(
+        data.decode_user_data().unwrap().as_deref(),
+        Some(br#"{"game":"test","level":3}"#.as_slice())
+    );
+}
+
+#[test]
+fn missing_scene() {
+    let dir = TestDir::new();
+    let output = iyesmesh()
+        .args(["from-gltf", "--scene", "5", SCENE, &dir.arg("out.ima")])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Scene 5"));
+}
@@ -0,0 +1,615 @@
+#![cfg(feature = "obj")]
+
+use iyes_mesh::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+
+mod common;
+use common::*;
+
+/// A unit square facing +Y, without normals.
+const SQUARE_OBJ: &str = "\
+v 0 0 0
+v 1 0 0
+v 1 0 1
+v 0 0 1
+f 1 4 3 2
+";
+
+#[test]
+fn generate_normals() {
+    let dir = TestDir::new();
+    std::fs::write(dir.path("square.obj"), SQUARE_OBJ).unwrap();
+    // The output comes first
+    run(&["from-obj", &dir.arg("plain.ima"), &dir.arg("square.obj")]);
+    let plain = decode_file(&dir.path("plain.ima"));
+    let attributes = &plain.descriptor().attributes;
+    assert!(!attributes.contains_key(&VertexUsage::Normal));
+
+    for mode in ["flat", "smooth"] {
+        let out = format!("{mode}.ima");
+        run(&[
+            "from-obj",
+            "--gen-normals",
+            mode,
+            &dir.arg(&out),
+            &dir.arg("square.obj"),
+        ]);
+        let data = decode_file(&dir.path(&out));
+        let buffers = data.into_flat_buffers().unwrap();
+        let meshes = data.into_split_meshes(&buffers).unwrap();
+        let (format, bytes) = meshes.meshes[0].attributes[&VertexUsage::Normal];
+        assert_eq!(format, VertexFormat::Float32x3);
+        let up: Vec<u8> =
+            [0.0f32, 1.0, 0.0].iter().flat_map(|c| c.to_le_bytes()).collect();
+        assert!(bytes.chunks_exact(12).all(|n| n == up), "{mode}");
+    }
+}
+
+/// A square of two triangles with the material `red`, then a triangle with
+/// `blue`. The MTL file also has an `unused` material.
+const TWO_MATERIALS: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/two_materials.obj");
+
+#[test]
+fn split_materials() {
+    let dir = TestDir::new();
+    run(&["from-obj", &dir.arg("one.ima"), TWO_MATERIALS]);
+    let one = decode_file(&dir.path("one.ima"));
+    assert_eq!(mesh_indices(&one).len(), 1);
+    assert_eq!(one.decode_user_data().unwrap(), None);
+
+    let output = run(&[
+        "--verbose",
+        "from-obj",
+        "--split-materials",
+        &dir.arg("split.ima"),
+        TWO_MATERIALS,
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("material `red`: 2 polygons"), "{stderr}");
+    assert!(stderr.contains("material `blue`: 1 polygons"), "{stderr}");
+    let split = decode_file(&dir.path("split.ima"));
+    let indices = mesh_indices(&split);
+    assert_eq!(indices.iter().map(Vec::len).collect::<Vec<_>>(), [6, 3]);
+    assert_eq!(
+        mesh_positions(&split)[1],
+        [[2.0, 0.0, 0.0], [2.0, 0.0, 1.0], [3.0, 0.0, 0.0]]
+    );
+    let json: serde_json::Value =
+        serde_json::from_slice(&split.decode_user_data().unwrap().unwrap())
+            .unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "materials": [{"name": "red"}, {"name": "blue"}],
+            "meshes": [
+                {"mesh": 0, "input": TWO_MATERIALS, "material": 0},
+                {"mesh": 1, "input": TWO_MATERIALS, "material": 1},
+            ],
+        })
+    );
+
+    // Appended meshes are numbered after the old ones
+    run(&[
+        "from-obj",
+        "--split-materials",
+        "--mtl-to-userdata",
+        "--append",
+        "--overwrite",
+        &dir.arg("one.ima"),
+        TWO_MATERIALS,
+    ]);
+    let appended = decode_file(&dir.path("one.ima"));
+    assert_eq!(mesh_indices(&appended).len(), 3);
+    let json: serde_json::Value =
+        serde_json::from_slice(&appended.decode_user_data().unwrap().unwrap())
+            .unwrap();
+    assert_eq!(
+        json["materials"],
+        serde_json::json!([
+            {
+                "name": "red",
+                "diffuse": [1.0, 0.0, 0.0],
+                "diffuse_map": "red.png",
+            },
+            {"name": "blue", "diffuse": [0.0, 0.0, 1.0], "dissolve": 0.5},
+            {"name": "unused", "specular_exponent": 10.0},
+        ])
+    );
+    assert_eq!(json["meshes"][0]["mesh"], 1);
+    assert_eq!(json["meshes"][1]["mesh"], 2);
+}
+
+#[test]
+fn missing_mtl_file() {
+    let dir = TestDir::new();
+    let obj = std::fs::read_to_string(TWO_MATERIALS).unwrap();
+    std::fs::write(dir.path("a.obj"), obj).unwrap();
+    let output = run(&[
+        "from-obj",
+        "--mtl-to-userdata",
+        &dir.arg("a.ima"),
+        &dir.arg("a.obj"),
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(&format!(
+            "Warning: cannot load {}: ",
+            dir.path("two_materials.mtl").display()
+        )),
+        "{stderr}"
+    );
+    let data = decode_file(&dir.path("a.ima"));
+    assert_eq!(
+        data.decode_user_data().unwrap().as_deref(),
+        Some(&br#"{"materials":[],"meshes":[]}"#[..])
+    );
+}
+
+/// Two triangles with normals, only the first with texture coordinates.
+const PARTIAL_UVS_OBJ: &str = "\
+v 0 0 0
+v 1 0 0
+v 1 0 1
+v 0 0 1
+vt 0 0
+vt 1 0
+vt 1 1
+vn 0 1 0
+f 1/1/1 3/3/1 2/2/1
+f 1//1 4//1 3//1
+";
+
+/// Import with some arguments, and expect a failure. Returns stderr.
+fn from_obj_error(args: &[&str]) -> String {
+    let output = iyesmesh().arg("from-obj").args(args).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn choose_layout() {
+    let dir = TestDir::new();
+    std::fs::write(dir.path("a.obj"), PARTIAL_UVS_OBJ).unwrap();
+    let obj = dir.arg("a.obj");
+
+    let output = run(&["--verbose", "from-obj", &dir.arg("auto.ima"), &obj]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(&format!(
+            "{obj}: positions and normals, with 16-bit indices\n  Not \
+             positions, texture coordinates and normals, with 16-bit \
+             indices: Face 2 has no texture coordinates\n"
+        )),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains(&format!(
+            "Warning: {obj}: the file has texture coordinates, but not for \
+             every face, so they are not imported (see --verbose and \
+             --layout)"
+        )),
+        "{stderr}"
+    );
+    let auto = decode_file(&dir.path("auto.ima"));
+    let attributes = &auto.descriptor().attributes;
+    assert!(attributes.contains_key(&VertexUsage::Normal));
+    assert!(!attributes.contains_key(&VertexUsage::Uv0));
+
+    // Without the warning when forced
+    let output = run(&[
+        "from-obj",
+        "--layout",
+        "p",
+        "--index-size",
+        "32",
+        &dir.arg("p.ima"),
+        &obj,
+    ]);
+    assert!(!String::from_utf8(output.stderr).unwrap().contains("Warning"));
+    let p = decode_file(&dir.path("p.ima"));
+    assert_eq!(p.descriptor().attributes.len(), 1);
+    assert_eq!(
+        p.descriptor().indices.map(|i| i.format),
+        Some(IndexFormat::U32)
+    );
+
+    let stderr =
+        from_obj_error(&["--layout", "ptn", &dir.arg("ptn.ima"), &obj]);
+    assert!(
+        stderr.contains(
+            "Cannot import the OBJ file (see --layout and --index-size) \
+             as:\n  positions, texture coordinates and normals, with \
+             16-bit indices: Face 2 has no texture coordinates"
+        ),
+        "{stderr}"
+    );
+    std::fs::write(dir.path("b.obj"), SQUARE_OBJ).unwrap();
+    let stderr = from_obj_error(&[
+        "--layout",
+        "pn",
+        &dir.arg("pn.ima"),
+        &dir.arg("b.obj"),
+    ]);
+    assert!(stderr.contains("Face 1 has no normals"), "{stderr}");
+}
+
+#[test]
+fn choose_index_size() {
+    let dir = TestDir::new();
+    // Separate triangles, with a few vertices too many for 16-bit indices
+    let n_triangles = 21846;
+    let mut obj = String::new();
+    for i in 0..n_triangles {
+        obj += &format!("v {i} 0 0\nv {i} 0 1\nv {i} 1 0\n");
+    }
+    for i in 0..n_triangles {
+        let v = i * 3 + 1;
+        obj += &format!("f {} {} {}\n", v, v + 1, v + 2);
+    }
+    std::fs::write(dir.path("a.obj"), obj).unwrap();
+
+    let output = run(&[
+        "--verbose",
+        "from-obj",
+        &dir.arg("auto.ima"),
+        &dir.arg("a.obj"),
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("a.obj: positions only, with 32-bit indices\n"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains(
+            "\n  Not positions only, with 16-bit indices: Too many vertices \
+             (65538)\n"
+        ),
+        "{stderr}"
+    );
+    let auto = decode_file(&dir.path("auto.ima"));
+    assert_eq!(
+        auto.descriptor().indices.map(|i| i.format),
+        Some(IndexFormat::U32)
+    );
+
+    let stderr = from_obj_error(&[
+        "--index-size",
+        "16",
+        &dir.arg("u16.ima"),
+        &dir.arg("a.obj"),
+    ]);
+    assert!(
+        stderr.contains(
+            "positions only, with 16-bit indices: Too many vertices (65538)"
+        ),
+        "{stderr}"
+    );
+}
+
+/// The cube of `examples/simple_encode.rs` as six quads, wound
+/// counter-clockwise seen from outside, without normals. `{s}` is replaced
+/// by the smoothing group of each face.
+const CUBE_OBJ: &str = "\
+v -1 -1 1
+v 1 -1 1
+v 1 1 1
+v -1 1 1
+v -1 -1 -1
+v 1 -1 -1
+v 1 1 -1
+v -1 1 -1
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+s {1}
+f 1/1 2/2 3/3 4/4
+s {2}
+f 6/1 5/2 8/3 7/4
+s {3}
+f 5/1 1/2 4/3 8/4
+s {4}
+f 2/1 6/2 7/3 3/4
+s {5}
+f 4/1 3/2 7/3 8/4
+s {6}
+f 5/1 6/2 2/3 1/4
+";
+
+/// The positions and normals of the only mesh of a decoded file.
+fn positions_and_normals(path: &std::path::Path) -> Vec<([f32; 3], [f32; 3])> {
+    let data = decode_file(path);
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    let (format, bytes) = meshes.meshes[0].attributes[&VertexUsage::Normal];
+    assert_eq!(format, VertexFormat::Float32x3);
+    let normals = bytes.chunks_exact(12).map(|v| {
+        std::array::from_fn(|c| {
+            f32::from_le_bytes(v[c * 4..c * 4 + 4].try_into().unwrap())
+        })
+    });
+    mesh_positions(&data)[0].iter().copied().zip(normals).collect()
+}
+
+#[test]
+fn generate_cube_normals() {
+    let dir = TestDir::new();
+    let without_uvs = CUBE_OBJ
+        .lines()
+        .filter(|l| !l.starts_with("vt"))
+        .map(|l| l.replace("/1", "").replace("/2", "").replace("/3", ""))
+        .map(|l| l.replace("/4", "") + "\n")
+        .collect::<String>();
+    let smoothing = |obj: &str, groups: [&str; 6]| {
+        let mut obj = obj.to_owned();
+        for (i, group) in groups.iter().enumerate() {
+            obj = obj.replace(&format!("{{{}}}", i + 1), group);
+        }
+        obj
+    };
+    let no_groups = ["off"; 6];
+    let own_groups = ["1", "2", "3", "4", "5", "6"];
+    // The sides together, the top and bottom flat
+    let sides = ["1", "1", "1", "1", "off", "off"];
+    for (name, obj, mode, n_vertices) in [
+        ("flat", smoothing(&without_uvs, no_groups), "flat", 36),
+        ("smooth", smoothing(&without_uvs, no_groups), "smooth", 8),
+        ("own", smoothing(&without_uvs, own_groups), "smooth", 24),
+        ("sides", smoothing(&without_uvs, sides), "smooth", 20),
+        ("uvs", smoothing(CUBE_OBJ, no_groups), "flat", 36),
+    ] {
+        std::fs::write(dir.path(&format!("{name}.obj")), obj).unwrap();
+        run(&[
+            "from-obj",
+            "--gen-normals",
+            mode,
+            &dir.arg(&format!("{name}.ima")),
+            &dir.arg(&format!("{name}.obj")),
+        ]);
+        let path = dir.path(&format!("{name}.ima"));
+        let vertices = positions_and_normals(&path);
+        assert_eq!(vertices.len(), n_vertices, "{name}");
+        for (position, normal) in vertices {
+            let dot = |a: [f32; 3], b: [f32; 3]| -> f32 {
+                (0..3).map(|c| a[c] * b[c]).sum()
+            };
+            assert!((dot(normal, normal) - 1.0).abs() < 1e-5, "{name}");
+            // Outwards
+            assert!(dot(normal, position) > 0.0, "{name}");
+            let axis_aligned =
+                normal.iter().filter(|c| **c == 0.0).count() == 2;
+            let expected = match name {
+                "smooth" => !axis_aligned,
+                // Horizontal on the sides, even at the corners
+                "sides" => axis_aligned || normal[1] == 0.0,
+                _ => axis_aligned,
+            };
+            assert!(expected, "{name}: {position:?} {normal:?}");
+        }
+        let data = decode_file(&path);
+        let attributes = &data.descriptor().attributes;
+        assert_eq!(attributes.contains_key(&VertexUsage::Uv0), name == "uvs");
+    }
+}
+
+/// A triangle facing +Z.
+const TRIANGLE_OBJ: &str = "\
+v 1 2 3
+v 4 5 6
+v 7 8 10
+vn 0 0 1
+f 1//1 2//1 3//1
+";
+
+#[test]
+fn convert_axes() {
+    let dir = TestDir::new();
+    std::fs::write(dir.path("a.obj"), TRIANGLE_OBJ).unwrap();
+    let import = |name: &str, args: &[&str]| {
+        let out = format!("{name}.ima");
+        let mut all = vec!["from-obj"];
+        all.extend(args);
+        let (out_arg, in_arg) = (dir.arg(&out), dir.arg("a.obj"));
+        all.extend([out_arg.as_str(), in_arg.as_str()]);
+        run(&all);
+        let path = dir.path(&out);
+        let indices = mesh_indices(&decode_file(&path)).remove(0);
+        (positions_and_normals(&path), indices)
+    };
+
+    let (vertices, indices) = import("default", &[]);
+    assert_eq!(vertices[0], ([1.0, 2.0, 3.0], [0.0, 0.0, 1.0]));
+    assert_eq!(indices, [0, 1, 2]);
+
+    // Z-up, Y-back (like Blender): Z becomes Y, and Y becomes -Z
+    let (vertices, indices) = import("z_up", &["--up-axis", "z"]);
+    assert_eq!(
+        vertices,
+        [
+            ([1.0, 3.0, -2.0], [0.0, 1.0, 0.0]),
+            ([4.0, 6.0, -5.0], [0.0, 1.0, 0.0]),
+            ([7.0, 10.0, -8.0], [0.0, 1.0, 0.0]),
+        ]
+    );
+    assert_eq!(indices, [0, 1, 2]);
+
+    let (vertices, _) =
+        import("forward", &["--up-axis", "z", "--forward-axis", "y"]);
+    assert_eq!(vertices[0], ([-1.0, 3.0, 2.0], [0.0, 1.0, 0.0]));
+
+    // A reflection (X is forward × up, so it stays), which flips the
+    // winding, to keep facing the same side
+    let (vertices, indices) =
+        import("left", &["--up-axis", "-z", "--left-handed"]);
+    assert_eq!(vertices[0], ([1.0, -3.0, -2.0], [0.0, -1.0, 0.0]));
+    assert_eq!(indices, [0, 2, 1]);
+
+    // Scaled after the axis conversion, the normals stay normalized
+    let (vertices, _) = import("scale", &["--up-axis", "z", "--scale", "0.5"]);
+    assert_eq!(vertices[0], ([0.5, 1.5, -1.0], [0.0, 1.0, 0.0]));
+
+    let stderr = from_obj_error(&[
+        "--up-axis",
+        "z",
+        "--forward-axis",
+        "-z",
+        &dir.arg("bad.ima"),
+        &dir.arg("a.obj"),
+    ]);
+    assert!(
+        stderr.contains("The up and forward axes must be different"),
+        "{stderr}"
+    );
+}
+
+/// A unit square facing +Y, with texture coordinates and normals.
+const TEXTURED_SQUARE_OBJ: &str = "\
+v 0 0 0
+v 1 0 0
+v 1 0 1
+v 0 0 1
+vt 0 0.25
+vt 1 0.25
+vt 1 1
+vt 0 1
+vn 0 1 0
+f 1/1/1 4/4/1 3/3/1 2/2/1
+";
+
+/// The texture coordinates of the only mesh of a decoded file.
+fn uvs(path: &std::path::Path) -> Vec<[f32; 2]> {
+    let data = decode_file(path);
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    let (format, bytes) = meshes.meshes[0].attributes[&VertexUsage::Uv0];
+    assert_eq!(format, VertexFormat::Float32x2);
+    bytes
+        .chunks_exact(8)
+        .map(|v| {
+            std::array::from_fn(|c| {
+                f32::from_le_bytes(v[c * 4..c * 4 + 4].try_into().unwrap())
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn flip_uv_v() {
+    let dir = TestDir::new();
+    std::fs::write(dir.path("a.obj"), TEXTURED_SQUARE_OBJ).unwrap();
+    run(&["from-obj", &dir.arg("plain.ima"), &dir.arg("a.obj")]);
+    let plain = uvs(&dir.path("plain.ima"));
+    assert_eq!(plain, [[0.0, 0.25], [0.0, 1.0], [1.0, 1.0], [1.0, 0.25]]);
+
+    // The same with any other conversion
+    for (name, args) in [
+        ("flip", &[][..]),
+        ("flip_z_up", &["--up-axis", "z", "--scale", "2"][..]),
+        ("flip_normals", &["--gen-normals", "smooth"][..]),
+    ] {
+        let out = format!("{name}.ima");
+        let mut all = vec!["from-obj", "--flip-uv-v"];
+        all.extend(args);
+        let (out_arg, in_arg) = (dir.arg(&out), dir.arg("a.obj"));
+        all.extend([out_arg.as_str(), in_arg.as_str()]);
+        let output = run(&all);
+        assert!(output.stderr.is_empty(), "{name}");
+        let flipped = uvs(&dir.path(&out));
+        let expected: Vec<_> =
+            plain.iter().map(|[u, v]| [*u, 1.0 - v]).collect();
+        assert_eq!(flipped, expected, "{name}");
+    }
+
+    // Nothing to flip without texture coordinates
+    let output = run(&[
+        "from-obj",
+        "--flip-uv-v",
+        "--layout",
+        "pn",
+        &dir.arg("pn.ima"),
+        &dir.arg("a.obj"),
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "a.obj: no texture coordinates are imported, so --flip-uv-v \
+             does nothing"
+        ),
+        "{stderr}"
+    );
+    let pn = decode_file(&dir.path("pn.ima"));
+    assert!(!pn.descriptor().attributes.contains_key(&VertexUsage::Uv0));
+}
+
+#[test]
+fn triangulate_quads() {
+    let dir = TestDir::new();
+    // Without smoothing groups
+    let cube: String = CUBE_OBJ
+        .lines()
+        .filter(|l| !l.starts_with('s'))
+        .map(|l| l.to_owned() + "\n")
+        .collect();
+    std::fs::write(dir.path("cube.obj"), &cube).unwrap();
+    let output = run(&[
+        "--verbose",
+        "from-obj",
+        "--layout",
+        "p",
+        "--triangulate-strict",
+        &dir.arg("cube.ima"),
+        &dir.arg("cube.obj"),
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("  Triangulated 6 polygons\n"), "{stderr}");
+
+    let data = decode_file(&dir.path("cube.ima"));
+    let positions = &mesh_positions(&data)[0];
+    let indices = &mesh_indices(&data)[0];
+    assert_eq!(positions.len(), 8);
+    assert_eq!(indices.len(), 36);
+    // Fans from the first vertex of each quad, in the same winding
+    let (cube_positions, _) = cube_mesh();
+    let expected: Vec<[f32; 3]> = cube
+        .lines()
+        .filter_map(|l| l.strip_prefix("f "))
+        .flat_map(|face| {
+            let quad: Vec<usize> = face
+                .split(' ')
+                .map(|v| v.split('/').next().unwrap().parse().unwrap())
+                .collect();
+            [quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]
+        })
+        .map(|v| cube_positions[v - 1])
+        .collect();
+    let corners: Vec<[f32; 3]> =
+        indices.iter().map(|i| positions[*i as usize]).collect();
+    assert_eq!(corners, expected);
+}
+
+#[test]
+fn triangulate_concave() {
+    let dir = TestDir::new();
+    // An arrowhead, with a reflex corner at its third vertex
+    std::fs::write(
+        dir.path("a.obj"),
+        "v 0 0 0\nv 2 0 0\nv 1 0 0.5\nv 1 0 2\nf 1 2 3 4\n",
+    )
+    .unwrap();
+    run(&["from-obj", &dir.arg("fan.ima"), &dir.arg("a.obj")]);
+    let fan = decode_file(&dir.path("fan.ima"));
+    assert_eq!(mesh_indices(&fan), [[0, 1, 2, 0, 2, 3]]);
+
+    let stderr = from_obj_error(&[
+        "--triangulate-strict",
+        &dir.arg("strict.ima"),
+        &dir.arg("a.obj"),
+    ]);
+    assert!(
+        stderr.contains(
+            "Face 1 is not convex, so it cannot be triangulated (see \
+             --triangulate-strict)"
+        ),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,139 @@
+#![cfg(feature = "ply")]
+
+use iyes_mesh::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+
+mod common;
+use common::*;
+
+/// The same unit square facing +Y, with normals, colors, UVs and an
+/// unknown `quality` property, in both PLY flavors.
+const FIXTURES: [&str; 2] = [
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/square_ascii.ply"),
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/square_binary.ply"),
+];
+
+fn f32_bytes(v: &[f32]) -> Vec<u8> {
+    v.iter().flat_map(|c| c.to_le_bytes()).collect()
+}
+
+#[test]
+fn import_square() {
+    let dir = TestDir::new();
+    for (n, fixture) in FIXTURES.into_iter().enumerate() {
+        let out = format!("{n}.ima");
+        let output = run(&["from-ply", &dir.arg(&out), fixture]);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.contains(
+                "Warning: skipping vertex properties quality (see \
+                 --custom-attrs)"
+            ),
+            "{stderr}"
+        );
+
+        let data = decode_file(&dir.path(&out));
+        assert_eq!(
+            data.descriptor().indices.map(|i| i.format),
+            Some(IndexFormat::U32)
+        );
+        assert_eq!(
+            mesh_positions(&data),
+            [[
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 0.0, 1.0],
+                [0.0, 0.0, 1.0]
+            ]]
+        );
+        // The quad, as a fan of triangles
+        assert_eq!(mesh_indices(&data), [[0, 3, 2, 0, 2, 1]]);
+        let buffers = data.into_flat_buffers().unwrap();
+        let meshes = data.into_split_meshes(&buffers).unwrap();
+        let attributes = &meshes.meshes[0].attributes;
+        let normals = f32_bytes(&[0.0, 1.0, 0.0].repeat(4));
+        assert_eq!(
+            attributes[&VertexUsage::Normal],
+            (VertexFormat::Float32x3, &normals[..])
+        );
+        let colors = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255]
+            .into_iter()
+            .chain([255; 4])
+            .collect::<Vec<u8>>();
+        assert_eq!(
+            attributes[&VertexUsage::Color],
+            (VertexFormat::Unorm8x4, &colors[..])
+        );
+        let uvs = f32_bytes(&[0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0]);
+        assert_eq!(
+            attributes[&VertexUsage::Uv0],
+            (VertexFormat::Float32x2, &uvs[..])
+        );
+        assert_eq!(attributes.len(), 4);
+    }
+}
+
+#[test]
+fn custom_attributes() {
+    let dir = TestDir::new();
+    run(&[
+        "from-ply",
+        "--custom-attrs",
+        &dir.arg("out.ima"),
+        FIXTURES[1],
+    ]);
+    let data = decode_file(&dir.path("out.ima"));
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    let quality = f32_bytes(&[0.5, 0.25, 1.0, 0.0]);
+    assert_eq!(
+        meshes.meshes[0].attributes[&VertexUsage::Custom(0)],
+        (VertexFormat::Float32, &quality[..])
+    );
+}
+
+#[test]
+fn point_cloud() {
+    let dir = TestDir::new();
+    let ply = "\
+ply
+format ascii 1.0
+element vertex 3
+property double x
+property double y
+property double z
+end_header
+0 0 0
+1 2 3
+-1 -2 -3
+";
+    std::fs::write(dir.path("points.ply"), ply).unwrap();
+    let output =
+        run(&["from-ply", &dir.arg("out.ima"), &dir.arg("points.ply")]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("the file has no faces"), "{stderr}");
+    let data = decode_file(&dir.path("out.ima"));
+    assert!(data.descriptor().indices.is_none());
+    assert_eq!(
+        mesh_positions(&data),
+        [[[0.0, 0.0, 0.0], [1.0, 2.0, 3.0], [-1.0, -2.0, -3.0]]]
+    );
+}
+
+#[test]
+fn bad_faces() {
+    let dir = TestDir::new();
+    let ascii = std::fs::read_to_string(FIXTURES[0]).unwrap();
+    let bad = ascii.replace("4 0 3 2 1", "3 0 3 4");
+    std::fs::write(dir.path("bad.ply"), bad).unwrap();
+    let output = iyesmesh()
+        .args(["from-ply", &dir.arg("out.ima"), &dir.arg("bad.ply")])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("A face uses vertex 4, but there are 4 vertices"),
+        "{stderr}"
+    );
+    assert!(!dir.path("out.ima").exists());
+}
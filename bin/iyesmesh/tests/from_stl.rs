@@ -0,0 +1,124 @@
+#![cfg(feature = "stl")]
+
+use iyes_mesh::descriptor::{VertexFormat, VertexUsage};
+
+mod common;
+use common::*;
+
+/// The cube of `examples/simple_encode.rs`, as 12 facets with axis-aligned
+/// normals. The header of the binary one begins with `solid`.
+const FIXTURES: [&str; 2] = [
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/cube_ascii.stl"),
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/cube_binary.stl"),
+];
+
+/// The normals of the only mesh of a decoded file.
+fn normals(path: &std::path::Path) -> Vec<[f32; 3]> {
+    let data = decode_file(path);
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    let (format, bytes) = meshes.meshes[0].attributes[&VertexUsage::Normal];
+    assert_eq!(format, VertexFormat::Float32x3);
+    bytes
+        .chunks_exact(12)
+        .map(|v| {
+            std::array::from_fn(|c| {
+                f32::from_le_bytes(v[c * 4..c * 4 + 4].try_into().unwrap())
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn import_cube() {
+    let dir = TestDir::new();
+    let (cube_positions, _) = cube_mesh();
+    for (fixture, kind) in FIXTURES.into_iter().zip(["ASCII", "binary"]) {
+        let out = format!("{kind}.ima");
+        let output = run(&["--verbose", "from-stl", &dir.arg(&out), fixture]);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.contains(&format!("{fixture}: 12 triangles ({kind})")),
+            "{stderr}"
+        );
+
+        let data = decode_file(&dir.path(&out));
+        assert!(data.descriptor().indices.is_none());
+        let positions = &mesh_positions(&data)[0];
+        assert_eq!(positions.len(), 36);
+        assert!(positions.iter().all(|p| cube_positions.contains(p)));
+        let normals = normals(&dir.path(&out));
+        assert_eq!(normals.len(), 36);
+        // The facet normal, for each vertex of the facet
+        for (triangle, normals) in normals.chunks_exact(3).enumerate() {
+            assert!(normals.iter().all(|n| *n == normals[0]));
+            assert_eq!(
+                normals[0].iter().map(|c| c.abs()).sum::<f32>(),
+                1.0,
+                "{triangle}"
+            );
+        }
+    }
+}
+
+#[test]
+fn weld_vertices() {
+    let dir = TestDir::new();
+    for (n, fixture) in FIXTURES.into_iter().enumerate() {
+        // The flat normals keep the 6 faces apart
+        let out = format!("weld{n}.ima");
+        // Last, as its value is optional
+        run(&["from-stl", &dir.arg(&out), fixture, "--weld"]);
+        let data = decode_file(&dir.path(&out));
+        assert_eq!(mesh_positions(&data)[0].len(), 24);
+        assert_eq!(mesh_indices(&data)[0].len(), 36);
+
+        let out = format!("epsilon{n}.ima");
+        run(&["from-stl", "--weld=0.001", &dir.arg(&out), fixture]);
+        assert_eq!(mesh_positions(&decode_file(&dir.path(&out)))[0].len(), 24);
+
+        // Without them, only the corners remain, with smooth normals
+        let out = format!("smooth{n}.ima");
+        run(&["from-stl", "--gen-normals", &dir.arg(&out), fixture]);
+        let data = decode_file(&dir.path(&out));
+        assert_eq!(mesh_positions(&data)[0].len(), 8);
+        assert_eq!(mesh_indices(&data)[0].len(), 36);
+        let normals = normals(&dir.path(&out));
+        // Not axis-aligned anymore, but still normalized
+        assert_eq!(normals.len(), 8);
+        for normal in normals {
+            let length = normal.iter().map(|c| c * c).sum::<f32>().sqrt();
+            assert!((length - 1.0).abs() < 1e-5, "{normal:?}");
+            assert!(normal.iter().all(|c| c.abs() < 1.0), "{normal:?}");
+        }
+    }
+}
+
+#[test]
+fn not_stl() {
+    let dir = TestDir::new();
+    std::fs::write(dir.path("a.stl"), "some text\n").unwrap();
+    let mut truncated = std::fs::read(FIXTURES[1]).unwrap();
+    truncated.truncate(truncated.len() - 10);
+    std::fs::write(dir.path("b.stl"), truncated).unwrap();
+    std::fs::write(
+        dir.path("c.stl"),
+        "solid bad\n  facet normal 0 0 1\n    vertex 0 0\n",
+    )
+    .unwrap();
+    for (file, error) in [
+        ("a.stl", "Not an ASCII STL file (no `solid`), and not a binary one"),
+        // Its header begins with `solid`, so it is taken for ASCII
+        ("b.stl", "Invalid text"),
+        ("c.stl", "Line 3"),
+    ] {
+        let output = iyesmesh()
+            .args(["from-stl", &dir.arg("out.ima"), &dir.arg(file)])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(error), "{file}: {stderr}");
+        assert!(!dir.path("out.ima").exists());
+    }
+}
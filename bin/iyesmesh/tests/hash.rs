@@ -0,0 +1,84 @@
+use iyes_mesh::checksum::ChecksumKind;
+
+mod common;
+use common::*;
+
+/// See `info.rs` for the contents of the fixture.
+const FIXTURE: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/two_grids.ima");
+
+fn hash_json(args: &[&str]) -> serde_json::Value {
+    let mut all = vec!["hash", "--json"];
+    all.extend(args);
+    serde_json::from_slice(&run(&all).stdout).unwrap()
+}
+
+#[test]
+fn hashes_ignore_the_compression() {
+    let dir = TestDir::new();
+    run(&["recompress", "--level", "1", FIXTURE, &dir.arg("fast.ima")]);
+    let json = hash_json(&[FIXTURE, &dir.arg("fast.ima")]);
+    let [max, fast] = [&json[0], &json[1]];
+    assert_eq!(max["path"], FIXTURE);
+    assert_eq!(max["algorithm"], "rapidhash");
+    let file = std::fs::read(FIXTURE).unwrap();
+    assert_eq!(
+        max["file"],
+        format!("{:016x}", ChecksumKind::RapidHash.checksum(&file))
+    );
+    assert_eq!(
+        max["user_data"],
+        format!(
+            "{:016x}",
+            ChecksumKind::RapidHash.checksum(b"fixture user data")
+        )
+    );
+    assert_ne!(max["file"], fast["file"]);
+    assert_eq!(max["payload"], fast["payload"]);
+    assert_eq!(max["user_data"], fast["user_data"]);
+    assert_eq!(max["meshes"], fast["meshes"]);
+
+    let meshes = max["meshes"].as_array().unwrap();
+    assert_eq!(meshes.len(), 2);
+    let buffers: Vec<_> =
+        meshes[0]["buffers"].as_object().unwrap().keys().collect();
+    assert_eq!(buffers, ["Position Float32x3", "indices U16"]);
+    assert_ne!(meshes[0]["hash"], meshes[1]["hash"]);
+}
+
+/// Meshes hash the same in any file.
+#[test]
+fn mesh_hashes_across_files() {
+    let dir = TestDir::new();
+    run(&["extract-mesh", "--mesh", "1", FIXTURE, &dir.arg("one.ima")]);
+    let both = hash_json(&[FIXTURE]);
+    let one = hash_json(&[&dir.arg("one.ima")]);
+    assert_eq!(one[0]["meshes"][0], both[0]["meshes"][1]);
+    assert_ne!(one[0]["payload"], both[0]["payload"]);
+    assert_eq!(one[0]["user_data"], serde_json::Value::Null);
+}
+
+#[cfg(feature = "xxh3")]
+#[test]
+fn hash_report() {
+    let output = run(&["--verbose", "hash", "--algo", "xxh3", FIXTURE]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let json = hash_json(&["--algo", "xxh3", FIXTURE]);
+    assert_eq!(json[0]["algorithm"], "xxh3");
+    assert!(stdout.starts_with(&format!("{FIXTURE}:\n")), "{stdout}");
+    assert!(stdout.contains("Hash (xxh3)"), "{stdout}");
+    for (name, hash) in [
+        ("payload", &json[0]["payload"]),
+        ("mesh 1", &json[0]["meshes"][1]["hash"]),
+        ("  indices U16", &json[0]["meshes"][1]["buffers"]["indices U16"]),
+    ] {
+        let hash = hash.as_str().unwrap();
+        assert!(
+            stdout
+                .lines()
+                .any(|l| l.starts_with(&format!("  {name} "))
+                    && l.ends_with(hash)),
+            "{name}: {stdout}"
+        );
+    }
+}
@@ -0,0 +1,77 @@
+mod common;
+use common::*;
+
+/// The header is printed even if the rest of the file is corrupted.
+#[test]
+fn header_only() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("a.ima"), 1);
+    let mut bytes = std::fs::read(dir.path("a.ima")).unwrap();
+    // Everything after the header
+    bytes[24..].fill(0xff);
+    std::fs::write(dir.path("corrupted.ima"), &bytes).unwrap();
+
+    let output = iyesmesh()
+        .args(["info", &dir.arg("corrupted.ima")])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let output = run(&["info", "--header-only", &dir.arg("corrupted.ima")]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let version = format!("version: {},", iyes_mesh::FORMAT_VERSION);
+    assert!(stdout.contains(&version), "{stdout}");
+    assert!(!stdout.contains("not supported"), "{stdout}");
+
+    std::fs::write(dir.path("short.ima"), &bytes[..10]).unwrap();
+    let output = iyesmesh()
+        .args(["info", "--header-only", &dir.arg("short.ima")])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Cannot read file header"), "{stderr}");
+}
+
+/// `data/two_grids.ima` has two grid meshes (8x8 and 5x5 vertices) with
+/// positions and U16 indices, and 17 bytes of user data.
+const FIXTURE: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/two_grids.ima");
+
+/// The field names of the JSON document are stable: compare the output
+/// with a snapshot.
+#[test]
+fn json_snapshot() {
+    let output = run(&["info", "--json", FIXTURE]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, include_str!("data/two_grids.json"));
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["meshes"].as_array().unwrap().len(), 2);
+    assert_eq!(json["attributes"][0]["buffer_size"], 89 * 12);
+}
+
+#[test]
+fn report() {
+    let output = run(&["info", FIXTURE]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, include_str!("data/two_grids.txt"));
+    assert!(!stdout.contains("Data checksum"), "{stdout}");
+
+    // With the header fields first
+    let output = run(&["--verbose", "info", FIXTURE]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let (header, report) = stdout.split_once("\n\n").unwrap();
+    assert_eq!(report, include_str!("data/two_grids.txt"));
+    let header: Vec<_> = header.lines().collect();
+    assert_eq!(
+        header,
+        [
+            "Format version: 2",
+            "Descriptor size: 54 bytes",
+            "Metadata checksum: 1cb0fd2dadaf1130",
+            "Data checksum: b949f69b1a6f820f",
+            "User data checksum: 3dc0019fbdf60f88",
+            "Checksum algorithm: rapidhash",
+            "Signed: no",
+        ]
+    );
+}
@@ -0,0 +1,107 @@
+mod common;
+use common::*;
+
+/// Write a few files in `in/`, the third one corrupted. Returns their
+/// names.
+fn write_files(dir: &TestDir) -> Vec<String> {
+    std::fs::create_dir_all(dir.path("in")).unwrap();
+    let names: Vec<_> = (0..6).map(|i| format!("in/{i}.ima")).collect();
+    for (seed, name) in names.iter().enumerate() {
+        write_test_file(&dir.path(name), seed as u64 * 2);
+    }
+    let mut bytes = std::fs::read(dir.path(&names[2])).unwrap();
+    *bytes.last_mut().unwrap() ^= 0xff;
+    std::fs::write(dir.path(&names[2]), bytes).unwrap();
+    names
+}
+
+/// Run the CLI with `--jobs`, returning its exit code, stdout and stderr.
+fn run_jobs(
+    jobs: &str,
+    args: &[String],
+) -> (Option<i32>, String, String) {
+    let output = iyesmesh()
+        .arg("--verbose")
+        .arg(args[0].as_str())
+        .args(["--jobs", jobs])
+        .args(&args[1..])
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn verify_in_parallel() {
+    let dir = TestDir::new();
+    let names = write_files(&dir);
+    let mut args = vec!["verify".to_owned()];
+    args.extend(names.iter().map(|name| dir.arg(name)));
+    let one = run_jobs("1", &args);
+    assert_eq!(one.0, Some(2));
+    assert!(
+        one.1.contains(&format!("{}: FAILED", dir.arg("in/2.ima"))),
+        "{}",
+        one.1
+    );
+    assert!(one.1.contains("6 files: 5 passed, 1 failed."), "{}", one.1);
+    // The same output, in the same order
+    assert_eq!(run_jobs("4", &args), one);
+}
+
+#[test]
+fn merge_in_parallel() {
+    let dir = TestDir::new();
+    let mut names = write_files(&dir);
+    names.remove(2);
+    for (jobs, out) in [("1", "one.ima"), ("4", "four.ima")] {
+        let mut args = vec!["merge".to_owned(), dir.arg(out)];
+        args.extend(names.iter().map(|name| dir.arg(name)));
+        let (code, _, stderr) = run_jobs(jobs, &args);
+        assert_eq!(code, Some(0), "{stderr}");
+    }
+    let one = std::fs::read(dir.path("one.ima")).unwrap();
+    assert_eq!(one, std::fs::read(dir.path("four.ima")).unwrap());
+    assert_eq!(mesh_indices(&decode_bytes(&one)).len(), 10);
+
+    // Errors name the right file
+    let mut args = vec!["merge".to_owned(), dir.arg("bad.ima")];
+    args.extend(write_files(&dir).iter().map(|name| dir.arg(name)));
+    let (code, _, stderr) = run_jobs("4", &args);
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains(&dir.arg("in/2.ima")), "{stderr}");
+    assert!(!dir.path("bad.ima").exists());
+}
+
+#[test]
+fn recompress_in_parallel() {
+    let dir = TestDir::new();
+    write_files(&dir);
+    let mut outputs = vec![];
+    for (jobs, out_dir) in [("1", "one"), ("4", "four")] {
+        let args = [
+            "recompress",
+            "-r",
+            "--level",
+            "1",
+            "--out-dir",
+            &dir.arg(out_dir),
+            &dir.arg("in"),
+        ]
+        .map(str::to_owned);
+        let (code, stdout, stderr) = run_jobs(jobs, &args);
+        assert_eq!(code, Some(2));
+        assert!(stdout.contains("6 files: 5 done, 1 failed."), "{stdout}");
+        outputs.push((stdout.replace(&dir.arg(out_dir), "OUT"), stderr));
+    }
+    assert_eq!(outputs[0], outputs[1]);
+    for i in [0, 1, 3, 4, 5] {
+        assert_eq!(
+            std::fs::read(dir.path(&format!("one/{i}.ima"))).unwrap(),
+            std::fs::read(dir.path(&format!("four/{i}.ima"))).unwrap()
+        );
+    }
+}
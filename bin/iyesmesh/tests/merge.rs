@@ -0,0 +1,502 @@
+use iyes_mesh::HashMap;
+use iyes_mesh::descriptor::{
+    ColorSpace, IndexFormat, VertexFormat, VertexUsage,
+};
+use iyes_mesh::mesh::MeshDataRef;
+use iyes_mesh::write::IyesMeshWriter;
+
+mod common;
+use common::*;
+
+#[test]
+fn fast_merge_matches_normal_merge() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("a.ima"), 1);
+    write_test_file(&dir.path("b.ima"), 10);
+
+    run(&[
+        "merge",
+        &dir.arg("slow.ima"),
+        &dir.arg("a.ima"),
+        &dir.arg("b.ima"),
+    ]);
+    let output = run(&[
+        "merge",
+        "--fast",
+        &dir.arg("fast.ima"),
+        &dir.arg("a.ima"),
+        &dir.arg("b.ima"),
+    ]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Warning"));
+
+    let slow = decode_file(&dir.path("slow.ima"));
+    let fast = decode_file(&dir.path("fast.ima"));
+    assert_eq!(mesh_positions(&fast), mesh_positions(&slow));
+    assert_eq!(mesh_positions(&fast).len(), 4);
+    assert_eq!(
+        std::fs::read(dir.path("fast.ima")).unwrap(),
+        std::fs::read(dir.path("slow.ima")).unwrap()
+    );
+}
+
+#[test]
+fn dry_run_writes_nothing() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("a.ima"), 1);
+    write_test_file(&dir.path("b.ima"), 10);
+
+    let output = run(&[
+        "merge",
+        "--dry-run",
+        &dir.arg("out.ima"),
+        &dir.arg("a.ima"),
+        &dir.arg("b.ima"),
+    ]);
+    assert!(!dir.path("out.ima").exists());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Positions and U16 indices of two 8x8 and two 5x5 grids
+    let expected =
+        2 * (8 * 8 * 12 + 7 * 7 * 6 * 2 + 5 * 5 * 12 + 4 * 4 * 6 * 2);
+    assert!(
+        stdout.contains(&format!("Uncompressed data size: {expected} bytes")),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Index upconversion: no"), "{stdout}");
+}
+
+#[test]
+fn incompatible_meshes_are_reported() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("a.ima"), 1);
+    // A mesh without indices
+    let (positions, _) = grid_mesh(4, 5);
+    let positions: Vec<u8> =
+        positions.iter().flatten().flat_map(|c| c.to_le_bytes()).collect();
+    let mut attributes = HashMap::default();
+    attributes.insert(
+        VertexUsage::Position,
+        (VertexFormat::Float32x3, positions.as_slice()),
+    );
+    let mut writer = IyesMeshWriter::new();
+    writer
+        .add_mesh(MeshDataRef {
+            indices: None,
+            attributes,
+        })
+        .unwrap();
+    let mut file = std::fs::File::create(dir.path("b.ima")).unwrap();
+    writer.write_to(&mut file).unwrap();
+
+    let output = iyesmesh()
+        .args([
+            "merge",
+            &dir.arg("out.ima"),
+            &dir.arg("a.ima"),
+            &dir.arg("b.ima"),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("b.ima: mesh 0 is incompatible"), "{stderr}");
+    assert!(stderr.contains("Indices are None, expected Some(U16)"));
+    assert!(stderr.contains("1 meshes cannot be used"));
+    assert!(!dir.path("out.ima").exists());
+}
+
+#[test]
+fn concat_with_manifest() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("a.ima"), 1);
+    write_test_file(&dir.path("b.ima"), 10);
+
+    run(&[
+        "merge",
+        "--concat",
+        "--concat-manifest",
+        &dir.arg("out.ima"),
+        &dir.arg("a.ima"),
+        &dir.arg("b.ima"),
+    ]);
+    let data = decode_file(&dir.path("out.ima"));
+    let combined = mesh_positions(&data);
+    assert_eq!(combined.len(), 1);
+    let inputs = [
+        mesh_positions(&decode_file(&dir.path("a.ima"))),
+        mesh_positions(&decode_file(&dir.path("b.ima"))),
+    ]
+    .concat();
+    assert_eq!(combined[0], inputs.concat());
+
+    let manifest = data.decode_user_data().unwrap().unwrap();
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&manifest).unwrap();
+    let meshes = manifest["meshes"].as_array().unwrap();
+    let ranges: Vec<_> = meshes
+        .iter()
+        .map(|m| {
+            let field = |name: &str| m[name].as_u64().unwrap();
+            (
+                m["input"].as_str().unwrap().ends_with("b.ima"),
+                field("mesh"),
+                field("first_vertex"),
+                field("vertex_count"),
+                field("first_index"),
+                field("index_count"),
+            )
+        })
+        .collect();
+    let (big, small) = (7 * 7 * 6, 4 * 4 * 6);
+    assert_eq!(
+        ranges,
+        [
+            (false, 0, 0, 64, 0, big),
+            (false, 1, 64, 25, big, small),
+            (true, 0, 89, 64, big + small, big),
+            (true, 1, 153, 25, 2 * big + small, small),
+        ]
+    );
+}
+
+/// Write a triangle with Unorm8x4 colors, tagged with a color space.
+fn write_colored(
+    path: &std::path::Path,
+    color_space: Option<ColorSpace>,
+) {
+    let positions: Vec<u8> =
+        (0..9).flat_map(|c| (c as f32).to_le_bytes()).collect();
+    let colors = [255, 128, 0, 255].repeat(3);
+    let mut attributes = HashMap::default();
+    attributes.insert(
+        VertexUsage::Position,
+        (VertexFormat::Float32x3, positions.as_slice()),
+    );
+    attributes.insert(
+        VertexUsage::Color,
+        (VertexFormat::Unorm8x4, colors.as_slice()),
+    );
+    let mut writer = IyesMeshWriter::new();
+    writer
+        .add_mesh(MeshDataRef {
+            indices: None,
+            attributes,
+        })
+        .unwrap();
+    if let Some(color_space) = color_space {
+        writer.set_color_space(VertexUsage::Color, color_space);
+    }
+    let mut file = std::fs::File::create(path).unwrap();
+    writer.write_to(&mut file).unwrap();
+}
+
+#[test]
+fn color_spaces_must_agree() {
+    let dir = TestDir::new();
+    write_colored(&dir.path("linear.ima"), Some(ColorSpace::Linear));
+    write_colored(&dir.path("srgb.ima"), Some(ColorSpace::Srgb));
+    write_colored(&dir.path("untagged.ima"), None);
+
+    let output = iyesmesh()
+        .args([
+            "merge",
+            &dir.arg("out.ima"),
+            &dir.arg("linear.ima"),
+            &dir.arg("srgb.ima"),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Attribute Color is Linear in "), "{stderr}");
+    assert!(!dir.path("out.ima").exists());
+
+    // Untagged inputs are assumed to match
+    let output = run(&[
+        "merge",
+        &dir.arg("out.ima"),
+        &dir.arg("srgb.ima"),
+        &dir.arg("untagged.ima"),
+    ]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("untagged.ima: attribute Color has no color space"),
+        "{stderr}"
+    );
+    let data = decode_file(&dir.path("out.ima"));
+    assert_eq!(
+        data.descriptor().color_spaces.get(&VertexUsage::Color),
+        Some(&ColorSpace::Srgb)
+    );
+}
+
+/// Write a test file whose meshes use the first `n` instances and the
+/// last one, with an instance color each.
+fn write_with_instances(
+    path: &std::path::Path,
+    n: u32,
+) {
+    let meshes = [grid_mesh(4, 1), grid_mesh(3, 2)];
+    let data: Vec<_> = meshes
+        .iter()
+        .map(|(positions, indices)| {
+            let positions: Vec<u8> = positions
+                .as_flattened()
+                .iter()
+                .flat_map(|c| c.to_le_bytes())
+                .collect();
+            let indices: Vec<u8> =
+                indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+            (positions, indices)
+        })
+        .collect();
+    let colors: Vec<u8> = (0..=n).flat_map(|i| [i as u8, 0, 0, 255]).collect();
+    let mut writer = IyesMeshWriter::new();
+    for (positions, indices) in data.iter() {
+        let mut attributes = HashMap::default();
+        attributes.insert(
+            VertexUsage::Position,
+            (VertexFormat::Float32x3, positions.as_slice()),
+        );
+        writer
+            .add_mesh(MeshDataRef {
+                indices: Some((IndexFormat::U16, indices.as_slice())),
+                attributes,
+            })
+            .unwrap();
+    }
+    writer
+        .set_instance_data(
+            VertexUsage::Color,
+            VertexFormat::Unorm8x4,
+            &colors,
+            n + 1,
+        )
+        .unwrap();
+    writer.set_mesh_instances(0, 0..n).unwrap();
+    writer.set_mesh_instances(1, n..n + 1).unwrap();
+    let mut file = std::fs::File::create(path).unwrap();
+    writer.write_to(&mut file).unwrap();
+}
+
+#[test]
+fn merge_instances() {
+    let dir = TestDir::new();
+    write_with_instances(&dir.path("a.ima"), 3);
+    write_with_instances(&dir.path("b.ima"), 5);
+
+    let output = run(&["info", &dir.arg("a.ima")]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Instances: 4 (4 bytes each)"), "{stdout}");
+
+    run(&[
+        "merge",
+        &dir.arg("out.ima"),
+        &dir.arg("a.ima"),
+        &dir.arg("b.ima"),
+    ]);
+    let data = decode_file(&dir.path("out.ima"));
+    let descriptor = data.descriptor();
+    assert_eq!(descriptor.n_instances, 10);
+    let ranges: Vec<_> = descriptor
+        .meshes
+        .iter()
+        .map(|m| (m.first_instance, m.instance_count))
+        .collect();
+    assert_eq!(ranges, [(0, 3), (3, 1), (4, 5), (9, 1)]);
+    let buffers = data.into_flat_buffers().unwrap();
+    let reds: Vec<u8> = buffers.buf_instances[&VertexUsage::Color]
+        .1
+        .chunks_exact(4)
+        .map(|c| c[0])
+        .collect();
+    assert_eq!(reds, [0, 1, 2, 3, 0, 1, 2, 3, 4, 5]);
+
+    // Instances cannot be merged with files without them
+    write_test_file(&dir.path("plain.ima"), 1);
+    let output = iyesmesh()
+        .args([
+            "merge",
+            &dir.arg("mixed.ima"),
+            &dir.arg("a.ima"),
+            &dir.arg("plain.ima"),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("different instance attributes"), "{stderr}");
+}
+
+#[test]
+fn merge_selected_meshes() {
+    let dir = TestDir::new();
+    let a: Vec<_> = (0..3).map(|i| grid_mesh(3 + i, i as u64)).collect();
+    let b = vec![grid_mesh(4, 10), grid_mesh(2, 11)];
+    write_meshes(&dir.path("a.ima"), &a);
+    write_meshes(&dir.path("b.ima"), &b);
+
+    run(&[
+        "merge",
+        "--select",
+        &format!("{}:2,0", dir.arg("a.ima")),
+        &dir.arg("out.ima"),
+        &dir.arg("a.ima"),
+        &dir.arg("b.ima"),
+    ]);
+    let merged = decode_file(&dir.path("out.ima"));
+    let expected = [&a[2], &a[0], &b[0], &b[1]];
+    assert_eq!(
+        mesh_positions(&merged),
+        expected.map(|(positions, _)| positions.clone())
+    );
+    let indices: Vec<Vec<u32>> = expected
+        .iter()
+        .map(|(_, indices)| indices.iter().map(|&i| i as u32).collect())
+        .collect();
+    assert_eq!(mesh_indices(&merged), indices);
+
+    for (select, error) in [
+        (
+            format!("{}:1,3", dir.arg("a.ima")),
+            format!(
+                "{}: mesh 3 does not exist (the file has 3 meshes)",
+                dir.arg("a.ima")
+            ),
+        ),
+        (
+            format!("{}:1,1", dir.arg("b.ima")),
+            format!("{}: mesh 1 is selected more than once", dir.arg("b.ima")),
+        ),
+        (
+            format!("{}:0", dir.arg("c.ima")),
+            format!("--select {}: not an input file", dir.arg("c.ima")),
+        ),
+    ] {
+        let output = iyesmesh()
+            .args(["merge", "--select", &select, &dir.arg("bad.ima")])
+            .args([dir.arg("a.ima"), dir.arg("b.ima")])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(&error), "{stderr}");
+        assert!(!dir.path("bad.ima").exists());
+    }
+}
+
+#[test]
+fn merge_with_dedup() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("a.ima"), 1);
+    let output = run(&[
+        "merge",
+        "--dedup",
+        &dir.arg("itself.ima"),
+        &dir.arg("a.ima"),
+        &dir.arg("a.ima"),
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Skipped 2 duplicate meshes."), "{stdout}");
+    let original = decode_file(&dir.path("a.ima"));
+    let merged = decode_file(&dir.path("itself.ima"));
+    assert_eq!(mesh_positions(&merged), mesh_positions(&original));
+
+    // The second mesh of b is the first one of a
+    write_meshes(&dir.path("b.ima"), &[grid_mesh(3, 5), grid_mesh(8, 1)]);
+    let output = run(&[
+        "merge",
+        "--dedup",
+        "--dedup-report",
+        &dir.arg("out.ima"),
+        &dir.arg("a.ima"),
+        &dir.arg("b.ima"),
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Skipped 1 duplicate meshes."), "{stdout}");
+    let rows: Vec<Vec<&str>> = stdout
+        .lines()
+        .skip_while(|l| !l.contains("Output mesh"))
+        .skip(1)
+        .take(4)
+        .map(|l| l.split_whitespace().collect())
+        .collect();
+    let (a, b) = (dir.arg("a.ima"), dir.arg("b.ima"));
+    assert_eq!(
+        rows,
+        [
+            [a.as_str(), "0", "0", "no"],
+            [&a, "1", "1", "no"],
+            [&b, "0", "2", "no"],
+            [&b, "1", "0", "yes"],
+        ],
+        "{stdout}"
+    );
+    let merged = mesh_positions(&decode_file(&dir.path("out.ima")));
+    assert_eq!(
+        merged,
+        [grid_mesh(8, 1).0, grid_mesh(5, 2).0, grid_mesh(3, 5).0]
+    );
+}
+
+#[test]
+fn concat_rebases_indices() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("a.ima"), 1);
+    write_test_file(&dir.path("b.ima"), 10);
+    run(&[
+        "merge",
+        "--concat",
+        &dir.arg("out.ima"),
+        &dir.arg("a.ima"),
+        &dir.arg("b.ima"),
+    ]);
+    let data = decode_file(&dir.path("out.ima"));
+    let descriptor = data.descriptor();
+    assert_eq!(descriptor.meshes.len(), 1);
+    assert_eq!(descriptor.meshes[0].vertex_count, 2 * 89);
+    assert_eq!(descriptor.meshes[0].index_count, 2 * 390);
+    assert_eq!(descriptor.indices.map(|i| i.format), Some(IndexFormat::U16));
+
+    // The first triangle of the second input uses its own vertices
+    let combined = &mesh_indices(&data)[0];
+    let b = mesh_indices(&decode_file(&dir.path("b.ima")));
+    assert_eq!(combined[390..393], [b[0][0] + 89, b[0][1] + 89, b[0][2] + 89]);
+    let rebased: Vec<u32> =
+        [b[0].clone(), b[1].iter().map(|i| i + 64).collect()]
+            .concat()
+            .iter()
+            .map(|i| i + 89)
+            .collect();
+    assert_eq!(combined[390..], rebased);
+}
+
+#[test]
+fn concat_upconverts_indices() {
+    let dir = TestDir::new();
+    // 40000 vertices each, too many for U16 indices together
+    write_meshes(&dir.path("a.ima"), &[grid_mesh(200, 1)]);
+    write_meshes(&dir.path("b.ima"), &[grid_mesh(200, 2)]);
+    run(&[
+        "merge",
+        "--concat",
+        "--level",
+        "1",
+        &dir.arg("out.ima"),
+        &dir.arg("a.ima"),
+        &dir.arg("b.ima"),
+    ]);
+    let data = decode_file(&dir.path("out.ima"));
+    assert_eq!(
+        data.descriptor().indices.map(|i| i.format),
+        Some(IndexFormat::U32)
+    );
+    let (_, indices) = grid_mesh(200, 2);
+    let combined = &mesh_indices(&data)[0];
+    assert_eq!(combined.len(), 2 * indices.len());
+    assert!(
+        combined[indices.len()..]
+            .iter()
+            .zip(&indices)
+            .all(|(c, i)| *c == *i as u32 + 40000)
+    );
+}
@@ -0,0 +1,97 @@
+#![cfg(feature = "meshopt")]
+
+mod common;
+use common::*;
+
+/// The triangles of each mesh of a file, as sorted position triples.
+fn triangles(path: &std::path::Path) -> Vec<Vec<[[u32; 3]; 3]>> {
+    let data = decode_file(path);
+    let positions = mesh_positions(&data);
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    meshes
+        .meshes
+        .iter()
+        .zip(&positions)
+        .map(|(mesh, positions)| {
+            let mut r: Vec<[[u32; 3]; 3]> = mesh
+                .iter_triangles()
+                .unwrap()
+                .map(|t| {
+                    let mut t =
+                        t.map(|i| positions[i as usize].map(f32::to_bits));
+                    let first = (0..3).min_by_key(|i| t[*i]).unwrap();
+                    t.rotate_left(first);
+                    t
+                })
+                .collect();
+            r.sort();
+            r
+        })
+        .collect()
+}
+
+/// A grid with its triangles in reverse order.
+fn reversed_grid(
+    size: u16,
+    seed: u64,
+) -> (Vec<[f32; 3]>, Vec<u16>) {
+    let (positions, indices) = grid_mesh(size, seed);
+    let indices = indices.chunks_exact(3).rev().flatten().copied().collect();
+    (positions, indices)
+}
+
+#[test]
+fn optimize_meshes() {
+    let dir = TestDir::new();
+    write_meshes(
+        &dir.path("in.ima"),
+        &[reversed_grid(20, 1), reversed_grid(7, 2)],
+    );
+    let output = run(&[
+        "-v",
+        "edit",
+        "--optimize",
+        &dir.arg("in.ima"),
+        &dir.arg("out.ima"),
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Mesh 0: ACMR "), "{stderr}");
+    assert!(stderr.contains("Mesh 1: ACMR "), "{stderr}");
+    assert_eq!(triangles(&dir.path("out.ima")), triangles(&dir.path("in.ima")));
+}
+
+#[test]
+fn simplify_meshes() {
+    let dir = TestDir::new();
+    // Flat, so that the simplification is not limited by the error
+    let flat = |(positions, indices): (Vec<[f32; 3]>, Vec<u16>)| {
+        let positions = positions.iter().map(|p| [p[0], 0.0, p[2]]).collect();
+        (positions, indices)
+    };
+    write_meshes(&dir.path("in.ima"), &[flat(grid_mesh(21, 1))]);
+    let output = run(&[
+        "simplify",
+        "--ratio",
+        "0.25",
+        &dir.arg("in.ima"),
+        &dir.arg("out.ima"),
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Mesh 0: 800 -> "), "{stdout}");
+    let simplified = triangles(&dir.path("out.ima"));
+    assert_eq!(simplified.len(), 1);
+    assert!((180..=200).contains(&simplified[0].len()));
+
+    run(&[
+        "simplify",
+        "--ratio",
+        "0.25",
+        "--append",
+        &dir.arg("in.ima"),
+        &dir.arg("lod.ima"),
+    ]);
+    let original = triangles(&dir.path("in.ima"));
+    let with_lod = triangles(&dir.path("lod.ima"));
+    assert_eq!(with_lod, [original[0].clone(), simplified[0].clone()]);
+}
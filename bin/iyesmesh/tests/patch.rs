@@ -0,0 +1,44 @@
+mod common;
+use common::*;
+
+#[test]
+fn diff_and_apply_patch() {
+    let dir = TestDir::new();
+    let meshes: Vec<_> = (0..20).map(|seed| grid_mesh(10, seed)).collect();
+    write_meshes(&dir.path("old.ima"), &meshes);
+    let mut changed = meshes.clone();
+    changed[5] = grid_mesh(10, 100);
+    write_meshes(&dir.path("new.ima"), &changed);
+
+    run(&[
+        "diff-patch",
+        &dir.arg("old.ima"),
+        &dir.arg("new.ima"),
+        &dir.arg("patch.bin"),
+    ]);
+    run(&[
+        "apply-patch",
+        &dir.arg("old.ima"),
+        &dir.arg("patch.bin"),
+        &dir.arg("out.ima"),
+    ]);
+    let new = std::fs::read(dir.path("new.ima")).unwrap();
+    assert!(std::fs::read(dir.path("out.ima")).unwrap() == new);
+    let patch_len = std::fs::metadata(dir.path("patch.bin")).unwrap().len();
+    assert!(patch_len * 4 < new.len() as u64, "{patch_len}");
+
+    // The patch only applies to the file it was made from
+    let output = iyesmesh()
+        .args([
+            "apply-patch",
+            &dir.arg("new.ima"),
+            &dir.arg("patch.bin"),
+            &dir.arg("wrong.ima"),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("different file"), "{stderr}");
+    assert!(!dir.path("wrong.ima").exists());
+}
@@ -0,0 +1,76 @@
+use iyes_mesh::header::IyesMeshHeader;
+
+mod common;
+use common::*;
+
+#[test]
+fn invalid_level_reports_range() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("a.ima"), 1);
+    let output = iyesmesh()
+        .args(["recompress", "--level", "9000", &dir.arg("a.ima")])
+        .args([dir.arg("b.ima")])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("must be in range"), "{stderr}");
+    assert!(!dir.path("b.ima").exists());
+}
+
+/// See `info.rs` for the contents of the fixture.
+const FIXTURE: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/two_grids.ima");
+
+#[test]
+fn recompress_keeps_the_contents() {
+    let dir = TestDir::new();
+    let output =
+        run(&["recompress", "--level", "1", FIXTURE, &dir.arg("fast.ima")]);
+    let before = decode_file(FIXTURE.as_ref());
+    let after = decode_file(&dir.path("fast.ima"));
+    assert_eq!(
+        format!("{:?}", before.descriptor()),
+        format!("{:?}", after.descriptor())
+    );
+    assert_eq!(mesh_positions(&before), mesh_positions(&after));
+    assert_eq!(mesh_indices(&before), mesh_indices(&after));
+    assert_eq!(
+        before.decode_user_data().unwrap(),
+        after.decode_user_data().unwrap()
+    );
+
+    let size = std::fs::metadata(dir.path("fast.ima")).unwrap().len();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines[0], "Before: 968 bytes (2.10x compression)");
+    assert!(
+        lines[1].starts_with(&format!("After: {size} bytes (")),
+        "{stdout}"
+    );
+    assert_eq!(
+        lines[2],
+        format!(
+            "Size change: {:+} bytes ({:.1}% of before)",
+            size as i64 - 968,
+            size as f64 * 100.0 / 968.0
+        )
+    );
+}
+
+#[test]
+fn recompress_in_place() {
+    let dir = TestDir::new();
+    std::fs::copy(FIXTURE, dir.path("a.ima")).unwrap();
+    run(&["recompress", "--no-data-checksum", &dir.arg("a.ima")]);
+    let bytes = std::fs::read(dir.path("a.ima")).unwrap();
+    assert!(bytes != std::fs::read(FIXTURE).unwrap());
+    let header_len = IyesMeshHeader::encoded_len();
+    let header = IyesMeshHeader::from_bytes(&bytes[..header_len]).unwrap();
+    assert_eq!({ header.data_checksum }, 0);
+    let after = decode_bytes(&bytes);
+    assert_eq!(
+        mesh_positions(&after),
+        mesh_positions(&decode_file(FIXTURE.as_ref()))
+    );
+}
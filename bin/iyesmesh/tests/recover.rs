@@ -0,0 +1,49 @@
+mod common;
+use common::*;
+
+#[test]
+fn recover_truncated_file() {
+    let dir = TestDir::new();
+    // Large enough to be compressed in several zstd blocks
+    let meshes: Vec<_> = (0..4).map(|seed| grid_mesh(150, seed)).collect();
+    write_meshes(&dir.path("full.ima"), &meshes);
+    let bytes = std::fs::read(dir.path("full.ima")).unwrap();
+
+    let mut salvaged = vec![];
+    for (i, end) in [bytes.len() * 2 / 3, bytes.len()].into_iter().enumerate() {
+        let truncated = format!("truncated{i}.ima");
+        let recovered = format!("recovered{i}.ima");
+        std::fs::write(dir.path(&truncated), &bytes[..end]).unwrap();
+        let output =
+            run(&["recover", &dir.arg(&truncated), &dir.arg(&recovered)]);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        run(&["verify", "--deep", &dir.arg(&recovered)]);
+        let positions = mesh_positions(&decode_file(&dir.path(&recovered)));
+        for (m, positions) in positions.iter().enumerate() {
+            assert!(*positions == meshes[m].0, "mesh {m}");
+        }
+        assert!(
+            stdout
+                .contains(&format!("Salvaged {} of 4 meshes", positions.len())),
+            "{stdout}"
+        );
+        salvaged.push(positions.len());
+    }
+    assert!(salvaged[0] > 0 && salvaged[0] < 4, "{salvaged:?}");
+    assert_eq!(salvaged[1], 4);
+
+    // Nothing to salvage
+    let header_only = &bytes[..(bytes.len() / 20)];
+    std::fs::write(dir.path("header_only.ima"), header_only).unwrap();
+    let output = iyesmesh()
+        .args([
+            "recover",
+            &dir.arg("header_only.ima"),
+            &dir.arg("nothing.ima"),
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("No mesh could be recovered"), "{stderr}");
+}
@@ -0,0 +1,158 @@
+use std::path::Path;
+
+mod common;
+use common::*;
+
+/// A tree of IMA files, with a corrupted one and a file to skip:
+///
+/// ```text
+/// tree/a.ima
+/// tree/sub/bad.ima
+/// tree/sub/deeper/c.IMA
+/// tree/sub/notes.txt
+/// tree/sub/z.ima
+/// ```
+fn write_tree(root: &Path) {
+    std::fs::create_dir_all(root.join("sub/deeper")).unwrap();
+    write_test_file(&root.join("a.ima"), 1);
+    write_test_file(&root.join("sub/z.ima"), 2);
+    write_test_file(&root.join("sub/deeper/c.IMA"), 3);
+    write_test_file(&root.join("sub/bad.ima"), 4);
+    let mut bytes = std::fs::read(root.join("sub/bad.ima")).unwrap();
+    *bytes.last_mut().unwrap() ^= 0xff;
+    std::fs::write(root.join("sub/bad.ima"), bytes).unwrap();
+    std::fs::write(root.join("sub/notes.txt"), "not a mesh").unwrap();
+}
+
+#[test]
+fn verify_tree() {
+    let dir = TestDir::new();
+    write_tree(&dir.path("tree"));
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(dir.path("tree"), dir.path("tree/sub/loop"))
+        .unwrap();
+
+    let output = iyesmesh()
+        .args(["--verbose", "verify", "-r", &dir.arg("tree")])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    // In the order of the (sorted) tree
+    let files: Vec<_> = stdout
+        .lines()
+        .filter_map(|l| l.strip_prefix(&dir.arg("tree")))
+        .filter_map(|l| l.split_once(": "))
+        .map(|(path, _)| path)
+        .collect();
+    assert_eq!(
+        files,
+        ["/a.ima", "/sub/bad.ima", "/sub/deeper/c.IMA", "/sub/z.ima"]
+    );
+    assert!(
+        stdout.contains(&format!(
+            "4 files: 3 passed, 1 failed.\nFailed:\n  {}\n",
+            dir.path("tree/sub/bad.ima").display()
+        )),
+        "{stdout}"
+    );
+    assert!(
+        stderr.contains("Skipped 1 files without a ima extension."),
+        "{stderr}"
+    );
+    #[cfg(unix)]
+    assert!(
+        stderr.contains(&format!(
+            "Warning: {} was already searched",
+            dir.arg("tree/sub/loop")
+        )),
+        "{stderr}"
+    );
+
+    // Files given directly are verified whatever their extension
+    std::fs::copy(dir.path("tree/a.ima"), dir.path("a.mesh")).unwrap();
+    run(&["verify", "-r", &dir.arg("a.mesh")]);
+}
+
+#[test]
+fn recompress_tree() {
+    let dir = TestDir::new();
+    write_tree(&dir.path("tree"));
+    let output = iyesmesh()
+        .args([
+            "recompress",
+            "-r",
+            "--out-dir",
+            &dir.arg("out"),
+            &dir.arg("tree"),
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains(&format!(
+            "{}: FAILED: ",
+            dir.path("tree/sub/bad.ima").display()
+        )),
+        "{stdout}"
+    );
+    assert!(stdout.contains("4 files: 3 done, 1 failed."), "{stdout}");
+
+    // The tree is mirrored, with the extension replaced
+    for (input, output) in [
+        ("a.ima", "a.ima"),
+        ("sub/z.ima", "sub/z.ima"),
+        ("sub/deeper/c.IMA", "sub/deeper/c.ima"),
+    ] {
+        let input = decode_file(&dir.path(&format!("tree/{input}")));
+        let output = decode_file(&dir.path(&format!("out/{output}")));
+        assert_eq!(mesh_positions(&input), mesh_positions(&output));
+        assert_eq!(mesh_indices(&input), mesh_indices(&output));
+    }
+    assert!(!dir.path("out/sub/bad.ima").exists());
+    assert!(!dir.path("out/sub/notes.txt").exists());
+}
+
+#[cfg(feature = "obj")]
+#[test]
+fn import_obj_tree() {
+    let dir = TestDir::new();
+    let triangle = "v 0 0 0\nv 1 0 0\nv 0 0 1\nf 1 3 2\n";
+    std::fs::create_dir_all(dir.path("tree/sub")).unwrap();
+    std::fs::write(dir.path("tree/a.obj"), triangle).unwrap();
+    std::fs::write(dir.path("tree/sub/b.OBJ"), triangle).unwrap();
+    std::fs::write(dir.path("tree/sub/bad.obj"), "f 1 2 3\n").unwrap();
+    std::fs::write(dir.path("tree/sub/c.ima"), "not an OBJ file").unwrap();
+
+    // All the paths are inputs, including the first
+    std::fs::write(dir.path("d.obj"), triangle).unwrap();
+    let output = iyesmesh()
+        .args([
+            "from-obj",
+            "-r",
+            "--out-dir",
+            &dir.arg("out"),
+            &dir.arg("d.obj"),
+            &dir.arg("tree"),
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("4 files: 3 done, 1 failed."), "{stdout}");
+    assert!(
+        stdout.contains(&format!(
+            "Failed:\n  {}\n",
+            dir.path("tree/sub/bad.obj").display()
+        )),
+        "{stdout}"
+    );
+    for output in ["d.ima", "a.ima", "sub/b.ima"] {
+        let data = decode_file(&dir.path(&format!("out/{output}")));
+        assert_eq!(mesh_indices(&data), [[0, 1, 2]]);
+    }
+    assert!(!dir.path("out/sub/bad.ima").exists());
+    assert!(!dir.path("out/sub/c.ima").exists());
+}
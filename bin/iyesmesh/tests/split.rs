@@ -0,0 +1,120 @@
+mod common;
+use common::*;
+
+/// A file with three meshes and some user data.
+fn write_three_meshes(dir: &TestDir) -> Vec<(Vec<[f32; 3]>, Vec<u16>)> {
+    let meshes = vec![grid_mesh(4, 1), grid_mesh(6, 2), grid_mesh(5, 3)];
+    write_meshes(&dir.path("plain.ima"), &meshes);
+    std::fs::write(dir.path("user.txt"), "user data").unwrap();
+    run(&[
+        "edit",
+        "--user-data",
+        &dir.arg("user.txt"),
+        &dir.arg("plain.ima"),
+        &dir.arg("three.ima"),
+    ]);
+    meshes
+}
+
+#[test]
+fn split_meshes() {
+    let dir = TestDir::new();
+    let meshes = write_three_meshes(&dir);
+    let output = run(&[
+        "split",
+        "--dir",
+        &dir.arg("out"),
+        "--template",
+        "part_{index}.ima",
+        "--user-data",
+        "first",
+        &dir.arg("three.ima"),
+    ]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "Wrote 3 files.\n");
+    for (i, (positions, indices)) in meshes.iter().enumerate() {
+        let part = decode_file(&dir.path(&format!("out/part_{i}.ima")));
+        assert_eq!(mesh_positions(&part), std::slice::from_ref(positions));
+        let indices: Vec<u32> = indices.iter().map(|&i| i as u32).collect();
+        assert_eq!(mesh_indices(&part), [indices]);
+        let user_data = part.decode_user_data().unwrap();
+        if i == 0 {
+            assert_eq!(user_data.as_deref(), Some(&b"user data"[..]));
+        } else {
+            assert_eq!(user_data, None);
+        }
+    }
+}
+
+#[test]
+fn split_user_data() {
+    let dir = TestDir::new();
+    write_three_meshes(&dir);
+    for (mode, expected) in [
+        ("none", [false, false, false]),
+        ("first", [true, false, false]),
+        ("every", [true, true, true]),
+    ] {
+        run(&[
+            "split",
+            "--dir",
+            &dir.arg(mode),
+            "--user-data",
+            mode,
+            &dir.arg("three.ima"),
+        ]);
+        for (i, expected) in expected.into_iter().enumerate() {
+            let part = decode_file(&dir.path(&format!("{mode}/mesh_{i}.ima")));
+            let user_data = part.decode_user_data().unwrap();
+            assert_eq!(user_data.is_some(), expected, "{mode} {i}");
+        }
+    }
+}
+
+/// Bad names are found before any file is written.
+#[test]
+fn split_errors() {
+    let dir = TestDir::new();
+    write_three_meshes(&dir);
+    for (template, error) in [
+        ("same.ima", "gives the same name `{dir}/same.ima` to meshes 0 and 1"),
+        ("{name}.ima", "meshes have no names"),
+        ("{index.ima", "Unclosed `{` in template"),
+        ("{mesh}.ima", "Unknown placeholder `{mesh}`"),
+    ] {
+        let out = dir.arg("out");
+        let output = iyesmesh()
+            .args(["split", "--dir", &out, "--template", template])
+            .arg(dir.arg("three.ima"))
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(2), "{template}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        let error = error.replace("{dir}", &out);
+        assert!(stderr.contains(&error), "{stderr}");
+        assert!(!dir.path("out").exists(), "{template}");
+    }
+
+    // An existing output stops the split before the first file is written
+    std::fs::create_dir(dir.path("out")).unwrap();
+    std::fs::write(dir.path("out/mesh_2.ima"), "").unwrap();
+    let output = iyesmesh()
+        .args(["split", "--dir", &dir.arg("out"), &dir.arg("three.ima")])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("already exists"), "{stderr}");
+    assert!(!dir.path("out/mesh_0.ima").exists());
+
+    run(&[
+        "split",
+        "--dir",
+        &dir.arg("out"),
+        "--overwrite",
+        &dir.arg("three.ima"),
+    ]);
+    assert_eq!(
+        mesh_positions(&decode_file(&dir.path("out/mesh_2.ima"))).len(),
+        1
+    );
+}
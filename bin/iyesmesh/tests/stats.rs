@@ -0,0 +1,82 @@
+mod common;
+use common::*;
+
+/// See `info.rs` for the contents of the fixture.
+const FIXTURE: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/two_grids.ima");
+
+#[test]
+fn stats_json() {
+    let output = run(&["stats", "--json", FIXTURE]);
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["file_size"], 968);
+    assert_eq!(json["compressed_size"], 890);
+    assert_eq!(json["raw_size"], 17 + 780 + 1068);
+    assert_eq!(json["compression_ratio"], 1865.0 / 890.0);
+    assert_eq!(json["vertex_count"], 64 + 25);
+    assert_eq!(json["triangle_count"], (294 + 96) / 3);
+    assert_eq!(json["instance_count"], 0);
+    assert_eq!(json["raw_bytes_per_vertex"], 12.0);
+    // The share of the compressed data of the positions
+    assert_eq!(
+        json["compressed_bytes_per_vertex"],
+        890.0 * 1068.0 / 1865.0 / 89.0
+    );
+
+    let buffers = json["buffers"].as_array().unwrap();
+    let names: Vec<_> =
+        buffers.iter().map(|b| b["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["user data", "indices", "Position"]);
+    let raw_sizes: Vec<_> =
+        buffers.iter().map(|b| b["raw_size"].as_u64().unwrap()).collect();
+    assert_eq!(raw_sizes, [17, 780, 1068]);
+    assert_eq!(buffers[0]["format"], serde_json::Value::Null);
+    assert_eq!(buffers[1]["format"], "U16");
+    assert_eq!(buffers[2]["format"], "Float32x3");
+    let alone: Vec<_> = buffers
+        .iter()
+        .map(|b| b["compressed_size_alone"].as_u64().unwrap())
+        .collect();
+    let total: u64 = alone.iter().sum();
+    for (buffer, alone) in buffers.iter().zip(&alone) {
+        let share = buffer["compressed_share"].as_f64().unwrap();
+        assert!((share - *alone as f64 * 100.0 / total as f64).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn stats_level() {
+    let json = |level: &str| {
+        let output = run(&["stats", "--json", "--level", level, FIXTURE]);
+        let json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).unwrap();
+        json
+    };
+    let fast = json("1");
+    let small = json("22");
+    assert_eq!(fast["level"], 1);
+    assert_eq!(small["level"], 22);
+    // The level only changes the estimates, not the file
+    assert_eq!(fast["compressed_size"], small["compressed_size"]);
+}
+
+#[test]
+fn stats_report() {
+    let output = run(&["stats", FIXTURE]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = stdout.lines().collect();
+    assert!(lines[0].split_whitespace().eq([
+        "Buffer", "Format", "Raw", "size", "Raw", "%", "Alone", "Ratio",
+        "Share"
+    ]));
+    assert!(lines[2].starts_with("  indices    U16             780  41.8%"));
+    assert!(lines[3].starts_with("  Position   Float32x3      1068  57.3%"));
+    for line in [
+        "Size: 1865 bytes raw, 890 bytes compressed (2.10x), 968 bytes file",
+        "Vertices: 89, triangles: 130, instances: 0",
+        "Bytes per vertex: 12.00 raw, 5.73 compressed (approximate)",
+    ] {
+        assert!(lines.contains(&line), "{stdout}");
+    }
+}
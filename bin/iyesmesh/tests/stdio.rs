@@ -0,0 +1,155 @@
+use std::io::Write;
+use std::process::{Output, Stdio};
+
+mod common;
+use common::*;
+
+const FIXTURE: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/two_grids.ima");
+
+const TRIANGLE_OBJ: &str = "v 0 0 0\nv 1 0 0\nv 0 0 1\nf 1 3 2\n";
+
+/// Run the CLI with `input` piped to its stdin.
+fn run_with_stdin(
+    args: &[&str],
+    input: &[u8],
+) -> Output {
+    let mut child = iyesmesh()
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let input = input.to_vec();
+    // Written on another thread, in case the CLI does not read it all.
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output().unwrap();
+    let _ = writer.join().unwrap();
+    output
+}
+
+#[test]
+fn info_and_verify_from_stdin() {
+    let fixture = std::fs::read(FIXTURE).unwrap();
+    let from_path = run(&["info", FIXTURE]);
+    let from_stdin = run_with_stdin(&["info", "-"], &fixture);
+    assert!(from_stdin.status.success());
+    assert_eq!(from_stdin.stdout, from_path.stdout);
+
+    let output = run_with_stdin(&["verify", "-"], &fixture);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("-: OK"), "{stdout}");
+
+    // The checksums are verified as with files
+    let mut corrupted = fixture.clone();
+    *corrupted.last_mut().unwrap() ^= 0xff;
+    let output = run_with_stdin(&["verify", "-"], &corrupted);
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("-: FAILED"), "{stdout}");
+}
+
+#[test]
+fn extract_from_stdin() {
+    let dir = TestDir::new();
+    let fixture = std::fs::read(FIXTURE).unwrap();
+    let output = run_with_stdin(&["extract-user-data", "-"], &fixture);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"fixture user data");
+
+    let output = run_with_stdin(
+        &["extract-mesh", "--mesh", "1", "-", &dir.arg("one.ima")],
+        &fixture,
+    );
+    assert!(output.status.success());
+    let fixture_positions = mesh_positions(&decode_bytes(&fixture));
+    assert_eq!(
+        mesh_positions(&decode_file(&dir.path("one.ima"))),
+        fixture_positions[1..]
+    );
+}
+
+#[test]
+fn stdin_only_once() {
+    let dir = TestDir::new();
+    let fixture = std::fs::read(FIXTURE).unwrap();
+    let output =
+        run_with_stdin(&["merge", &dir.arg("out.ima"), "-", "-"], &fixture);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("stdin (`-`) can only be read once"), "{stderr}");
+    assert!(!dir.path("out.ima").exists());
+
+    // Also counting the user data
+    let output = run_with_stdin(
+        &["edit", "--user-data=-", "-", &dir.arg("out.ima")],
+        &fixture,
+    );
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("stdin (`-`) can only be read once"), "{stderr}");
+}
+
+/// With `-` as the output, stdout is only the output file, and the same as
+/// written to a file, even with --verbose.
+#[test]
+fn output_to_stdout() {
+    let dir = TestDir::new();
+    std::fs::write(dir.path("a.obj"), TRIANGLE_OBJ).unwrap();
+    let obj = dir.arg("a.obj");
+    // Replaced by the output path
+    const OUT: &str = "{out}";
+    let mut commands = vec![
+        ("edit", vec!["edit", "--drop-user-data", FIXTURE, OUT]),
+        ("merge", vec!["merge", OUT, FIXTURE, FIXTURE]),
+        ("recompress", vec!["recompress", "--level", "1", FIXTURE, OUT]),
+    ];
+    if cfg!(feature = "obj") {
+        commands.push(("from-obj", vec!["from-obj", OUT, &obj]));
+    }
+    for (name, args) in commands {
+        let path = dir.arg(&format!("{name}.ima"));
+        let [_, output] = [path.as_str(), "-"].map(|out| {
+            let args = args.iter().map(|a| {
+                if *a == OUT {
+                    out
+                } else {
+                    a
+                }
+            });
+            run(&std::iter::once("--verbose").chain(args).collect::<Vec<_>>())
+        });
+        assert!(output.stdout == std::fs::read(&path).unwrap(), "{name}");
+        // The messages are still there
+        assert!(!output.stderr.is_empty(), "{name}");
+    }
+}
+
+#[test]
+fn pipe_through() {
+    let fixture = std::fs::read(FIXTURE).unwrap();
+    let output =
+        run_with_stdin(&["edit", "--drop-user-data", "-", "-"], &fixture);
+    assert!(output.status.success());
+    let data = decode_bytes(&output.stdout);
+    assert_eq!(data.decode_user_data().unwrap(), None);
+    assert_eq!(mesh_positions(&data), mesh_positions(&decode_bytes(&fixture)));
+}
+
+#[cfg(feature = "obj")]
+#[test]
+fn cannot_append_to_stdout() {
+    let dir = TestDir::new();
+    std::fs::write(dir.path("a.obj"), TRIANGLE_OBJ).unwrap();
+    let output = iyesmesh()
+        .args(["from-obj", "--append", "-", &dir.arg("a.obj")])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Cannot append to stdout (`-`)."), "{stderr}");
+}
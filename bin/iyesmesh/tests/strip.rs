@@ -0,0 +1,59 @@
+use iyes_mesh::header::IyesMeshHeader;
+
+mod common;
+use common::*;
+
+/// See `info.rs` for the contents of the fixture.
+const FIXTURE: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/two_grids.ima");
+
+fn data_checksum(path: &std::path::Path) -> u64 {
+    let bytes = std::fs::read(path).unwrap();
+    IyesMeshHeader::from_bytes(&bytes[..IyesMeshHeader::encoded_len()])
+        .unwrap()
+        .data_checksum
+}
+
+#[test]
+fn strip_everything() {
+    let dir = TestDir::new();
+    let output = run(&["strip", FIXTURE, &dir.arg("stripped.ima")]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let size = std::fs::metadata(dir.path("stripped.ima")).unwrap().len();
+    assert!(stdout.starts_with("Before: 968 bytes ("), "{stdout}");
+    assert!(stdout.contains(&format!("After: {size} bytes (")), "{stdout}");
+    assert!(
+        stdout.contains(&format!("Size change: {:+} bytes", size as i64 - 968)),
+        "{stdout}"
+    );
+
+    let original = decode_file(FIXTURE.as_ref());
+    let stripped = decode_file(&dir.path("stripped.ima"));
+    assert_eq!(stripped.decode_user_data().unwrap(), None);
+    assert_eq!(stripped.descriptor().user_data_checksum, None);
+    assert_eq!(data_checksum(&dir.path("stripped.ima")), 0);
+    assert_eq!(mesh_positions(&stripped), mesh_positions(&original));
+    assert_eq!(mesh_indices(&stripped), mesh_indices(&original));
+}
+
+#[test]
+fn strip_keeping_some() {
+    let dir = TestDir::new();
+    run(&[
+        "strip",
+        "--keep-user-data",
+        "--keep-checksum",
+        FIXTURE,
+        &dir.arg("kept.ima"),
+    ]);
+    let kept = decode_file(&dir.path("kept.ima"));
+    assert_eq!(
+        kept.decode_user_data().unwrap().as_deref(),
+        Some(&b"fixture user data"[..])
+    );
+    assert_ne!(data_checksum(&dir.path("kept.ima")), 0);
+    assert_eq!(
+        mesh_positions(&kept),
+        mesh_positions(&decode_file(FIXTURE.as_ref()))
+    );
+}
@@ -0,0 +1,260 @@
+#![cfg(feature = "gltf")]
+
+use iyes_mesh::HashMap;
+use iyes_mesh::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+use iyes_mesh::mesh::MeshDataRef;
+use iyes_mesh::write::IyesMeshWriter;
+
+mod common;
+use common::*;
+
+fn f32_bytes(v: &[f32]) -> Vec<u8> {
+    v.iter().flat_map(|c| c.to_le_bytes()).collect()
+}
+
+#[test]
+fn uv_sets_round_trip() {
+    let dir = TestDir::new();
+    let (positions, indices) = grid_mesh(3, 1);
+    let positions = f32_bytes(positions.as_flattened());
+    let indices: Vec<u8> =
+        indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+    let uvs: Vec<Vec<u8>> = (0..4)
+        .map(|set| {
+            let uvs: Vec<f32> =
+                (0..18).map(|c| c as f32 / 16.0 + set as f32).collect();
+            f32_bytes(&uvs)
+        })
+        .collect();
+    let mut attributes = HashMap::default();
+    attributes.insert(
+        VertexUsage::Position,
+        (VertexFormat::Float32x3, positions.as_slice()),
+    );
+    for (set, uvs) in uvs.iter().enumerate() {
+        attributes.insert(
+            VertexUsage::uv(set).unwrap(),
+            (VertexFormat::Float32x2, uvs.as_slice()),
+        );
+    }
+    let mesh = MeshDataRef {
+        indices: Some((IndexFormat::U16, indices.as_slice())),
+        attributes,
+    };
+    let mut writer = IyesMeshWriter::new();
+    writer.add_mesh(mesh.clone()).unwrap();
+    let mut file = std::fs::File::create(dir.path("in.ima")).unwrap();
+    writer.write_to(&mut file).unwrap();
+
+    let output = run(&["info", &dir.arg("in.ima")]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    for set in 0..4 {
+        assert!(stdout.contains(&format!("Uv{set} ")), "{stdout}");
+    }
+
+    run(&["to-gltf", &dir.arg("in.ima"), &dir.arg("out.gltf")]);
+    let gltf = std::fs::read_to_string(dir.path("out.gltf")).unwrap();
+    assert!(gltf.contains("\"TEXCOORD_3\""), "{gltf}");
+    run(&["from-gltf", &dir.arg("out.gltf"), &dir.arg("back.ima")]);
+    let data = decode_file(&dir.path("back.ima"));
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    assert_eq!(meshes.meshes[0].attributes, mesh.attributes);
+}
+
+#[test]
+fn eight_influences_round_trip() {
+    let dir = TestDir::new();
+    let (positions, indices) = grid_mesh(3, 1);
+    let positions = f32_bytes(positions.as_flattened());
+    let indices: Vec<u8> =
+        indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+    let joints: Vec<Vec<u8>> = (0..2u16)
+        .map(|set| {
+            (0..36u16).flat_map(|c| (c + set * 100).to_le_bytes()).collect()
+        })
+        .collect();
+    let weights = f32_bytes(&[0.125; 36]);
+    let mut attributes = HashMap::default();
+    attributes.insert(
+        VertexUsage::Position,
+        (VertexFormat::Float32x3, positions.as_slice()),
+    );
+    for (set, (index, weight)) in [
+        (VertexUsage::JointIndex, VertexUsage::JointWeight),
+        (VertexUsage::JointIndex1, VertexUsage::JointWeight1),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        attributes
+            .insert(index, (VertexFormat::Uint16x4, joints[set].as_slice()));
+        attributes
+            .insert(weight, (VertexFormat::Float32x4, weights.as_slice()));
+    }
+    let mesh = MeshDataRef {
+        indices: Some((IndexFormat::U16, indices.as_slice())),
+        attributes,
+    };
+    let mut writer = IyesMeshWriter::new();
+    writer.add_mesh(mesh.clone()).unwrap();
+    let mut file = std::fs::File::create(dir.path("in.ima")).unwrap();
+    writer.write_to(&mut file).unwrap();
+
+    let output = run(&["info", &dir.arg("in.ima")]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("JointIndex1 "), "{stdout}");
+    assert!(stdout.contains("JointWeight1 "), "{stdout}");
+    let output = run(&["verify", "--deep", &dir.arg("in.ima")]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("warning"), "{stdout}");
+
+    run(&["to-gltf", &dir.arg("in.ima"), &dir.arg("out.gltf")]);
+    let gltf = std::fs::read_to_string(dir.path("out.gltf")).unwrap();
+    assert!(gltf.contains("\"JOINTS_1\""), "{gltf}");
+    assert!(gltf.contains("\"WEIGHTS_1\""), "{gltf}");
+    run(&["from-gltf", &dir.arg("out.gltf"), &dir.arg("back.ima")]);
+    let data = decode_file(&dir.path("back.ima"));
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    assert_eq!(meshes.meshes[0].attributes, mesh.attributes);
+    run(&["verify", "--deep", &dir.arg("back.ima")]);
+}
+
+/// Two grid meshes, with JSON user data.
+fn write_with_json_user_data(dir: &TestDir) {
+    write_test_file(&dir.path("grids.ima"), 1);
+    std::fs::write(dir.path("extras.json"), r#"{"level":3,"name":"grids"}"#)
+        .unwrap();
+    run(&[
+        "edit",
+        "--user-data",
+        &dir.arg("extras.json"),
+        &dir.arg("grids.ima"),
+        &dir.arg("in.ima"),
+    ]);
+}
+
+#[test]
+fn export_glb() {
+    let dir = TestDir::new();
+    write_with_json_user_data(&dir);
+    run(&[
+        "to-gltf",
+        "--user-data",
+        "extras",
+        &dir.arg("in.ima"),
+        &dir.arg("out.glb"),
+    ]);
+    let glb = std::fs::read(dir.path("out.glb")).unwrap();
+    assert_eq!(&glb[..4], b"glTF");
+    let gltf = gltf::Gltf::from_slice(&glb).unwrap();
+    assert_eq!(gltf.meshes().len(), 2);
+    let extras = gltf.as_json().extras.as_ref().unwrap().get();
+    let extras: serde_json::Value = serde_json::from_str(extras).unwrap();
+    assert_eq!(extras, serde_json::json!({ "level": 3, "name": "grids" }));
+
+    // The bounds of the positions
+    let input = mesh_positions(&decode_file(&dir.path("in.ima")));
+    for (mesh, positions) in gltf.meshes().zip(&input) {
+        let primitive = mesh.primitives().next().unwrap();
+        let accessor = primitive.get(&gltf::Semantic::Positions).unwrap();
+        assert_eq!(accessor.count(), positions.len());
+        let min: Vec<f32> = (0..3)
+            .map(|c| positions.iter().map(|p| p[c]).fold(f32::MAX, f32::min))
+            .collect();
+        let max: Vec<f32> = (0..3)
+            .map(|c| positions.iter().map(|p| p[c]).fold(f32::MIN, f32::max))
+            .collect();
+        let bound = |value: Option<serde_json::Value>| -> Vec<f32> {
+            let value = value.unwrap();
+            let value = value.as_array().unwrap();
+            value.iter().map(|c| c.as_f64().unwrap() as f32).collect()
+        };
+        assert_eq!(bound(accessor.min()), min);
+        assert_eq!(bound(accessor.max()), max);
+        let indices = primitive.indices().unwrap();
+        assert_eq!(indices.data_type(), gltf::accessor::DataType::U16);
+    }
+
+    run(&["from-gltf", &dir.arg("out.glb"), &dir.arg("back.ima")]);
+    let back = decode_file(&dir.path("back.ima"));
+    assert_eq!(mesh_positions(&back), input);
+    assert_eq!(
+        mesh_indices(&back),
+        mesh_indices(&decode_file(&dir.path("in.ima")))
+    );
+}
+
+#[test]
+fn export_gltf_with_bin() {
+    let dir = TestDir::new();
+    write_with_json_user_data(&dir);
+    run(&[
+        "to-gltf",
+        "--user-data",
+        "buffer",
+        &dir.arg("in.ima"),
+        &dir.arg("out.gltf"),
+    ]);
+    let gltf = std::fs::read(dir.path("out.gltf")).unwrap();
+    let bin = std::fs::read(dir.path("out.bin")).unwrap();
+    let gltf = gltf::Gltf::from_slice(&gltf).unwrap();
+    let buffer = gltf.buffers().next().unwrap();
+    assert!(matches!(buffer.source(), gltf::buffer::Source::Uri("out.bin")));
+    assert_eq!(buffer.length(), bin.len());
+    let view = gltf.views().find(|v| v.name() == Some("user_data")).unwrap();
+    let user_data = &bin[view.offset()..view.offset() + view.length()];
+    assert_eq!(user_data, br#"{"level":3,"name":"grids"}"#);
+}
+
+#[test]
+fn strict_export() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("grids.ima"), 1);
+    let values: Vec<u8> =
+        (0..89).flat_map(|i| (i as f64).to_le_bytes()).collect();
+    std::fs::write(dir.path("values.bin"), &values).unwrap();
+    run(&[
+        "edit",
+        "--add-attr",
+        &format!("custom:1:float64:{}", dir.arg("values.bin")),
+        &dir.arg("grids.ima"),
+        &dir.arg("in.ima"),
+    ]);
+
+    let output = iyesmesh()
+        .args(["to-gltf", "--strict", &dir.arg("in.ima")])
+        .arg(dir.arg("strict.glb"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("  - custom:1 is converted from Float64 to Float32"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("(see --strict)"), "{stderr}");
+    assert!(!dir.path("strict.glb").exists());
+
+    let output = run(&["to-gltf", &dir.arg("in.ima"), &dir.arg("out.glb")]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr
+            .contains("Warning: custom:1 is converted from Float64 to Float32"),
+        "{stderr}"
+    );
+    run(&["from-gltf", &dir.arg("out.glb"), &dir.arg("back.ima")]);
+    let back = decode_file(&dir.path("back.ima"));
+    let buffers = back.into_flat_buffers().unwrap();
+    let meshes = back.into_split_meshes(&buffers).unwrap();
+    let values: Vec<u8> =
+        (0..89).flat_map(|i| (i as f32).to_le_bytes()).collect();
+    let (first, second) = values.split_at(64 * 4);
+    for (mesh, expected) in meshes.meshes.iter().zip([first, second]) {
+        assert_eq!(
+            mesh.attributes[&VertexUsage::Custom(1)],
+            (VertexFormat::Float32, expected)
+        );
+    }
+}
@@ -0,0 +1,130 @@
+#![cfg(feature = "obj")]
+
+use iyes_mesh::descriptor::VertexUsage;
+use iyes_mesh::read::IyesMeshReaderWithData;
+
+mod common;
+use common::*;
+
+/// A unit square facing +Y, with UVs and normals.
+const SQUARE_OBJ: &str = "\
+v 0 0 0
+v 1 0 0
+v 1 0 1
+v 0 0 1
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 1 0
+f 1/1/1 4/4/1 3/3/1 2/2/1
+";
+
+/// The attributes and indices of each mesh, as bytes.
+fn buffers(data: &IyesMeshReaderWithData) -> Vec<Vec<(String, Vec<u8>)>> {
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    meshes
+        .meshes
+        .iter()
+        .map(|mesh| {
+            let mut r: Vec<_> = mesh
+                .attributes
+                .iter()
+                .map(|(usage, (format, bytes))| {
+                    (format!("{usage} {format:?}"), bytes.to_vec())
+                })
+                .collect();
+            r.sort();
+            let indices: Vec<u8> = mesh
+                .iter_indices()
+                .unwrap()
+                .flat_map(|i| i.to_le_bytes())
+                .collect();
+            r.push(("indices".to_owned(), indices));
+            r
+        })
+        .collect()
+}
+
+#[test]
+fn obj_round_trip() {
+    let dir = TestDir::new();
+    std::fs::write(dir.path("square.obj"), SQUARE_OBJ).unwrap();
+    run(&["from-obj", &dir.arg("a.ima"), &dir.arg("square.obj")]);
+    run(&["to-obj", &dir.arg("a.ima"), &dir.arg("b.obj")]);
+    run(&["from-obj", &dir.arg("c.ima"), &dir.arg("b.obj")]);
+
+    let a = decode_file(&dir.path("a.ima"));
+    let c = decode_file(&dir.path("c.ima"));
+    let mut attributes: Vec<_> =
+        a.descriptor().attributes.keys().copied().collect();
+    attributes.sort();
+    assert_eq!(
+        attributes,
+        [VertexUsage::Position, VertexUsage::Normal, VertexUsage::Uv0]
+    );
+    assert_eq!(buffers(&a), buffers(&c));
+
+    let obj = std::fs::read_to_string(dir.path("b.obj")).unwrap();
+    assert!(obj.starts_with("o mesh_0\nv "), "{obj}");
+    assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 2);
+    assert!(obj.contains("\nf 1/1/1 "), "{obj}");
+}
+
+#[test]
+fn export_several_meshes() {
+    let dir = TestDir::new();
+    // 64 and 25 vertices
+    write_test_file(&dir.path("grids.ima"), 1);
+    let colors: Vec<u8> =
+        (0..89 * 4).flat_map(|_| 1f32.to_le_bytes()).collect();
+    std::fs::write(dir.path("colors.bin"), &colors).unwrap();
+    run(&[
+        "edit",
+        "--add-attr",
+        &format!("color:float32x4:{}", dir.arg("colors.bin")),
+        &dir.arg("grids.ima"),
+        &dir.arg("colored.ima"),
+    ]);
+    let output = run(&["to-obj", &dir.arg("colored.ima"), &dir.arg("all.obj")]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "Warning: OBJ files cannot store these attributes (only \
+             Position, Normal, Uv0), they are skipped: Color"
+        ),
+        "{stderr}"
+    );
+
+    let obj = std::fs::read_to_string(dir.path("all.obj")).unwrap();
+    let lines: Vec<_> = obj.lines().collect();
+    let groups: Vec<_> =
+        lines.iter().copied().filter(|l| l.starts_with("o ")).collect();
+    assert_eq!(groups, ["o mesh_0", "o mesh_1"]);
+    assert_eq!(lines.iter().filter(|l| l.starts_with("v ")).count(), 89);
+    // The indices of the second mesh come after the vertices of the first
+    let second = lines.iter().position(|l| *l == "o mesh_1").unwrap();
+    let first_face = lines[second..].iter().find(|l| l.starts_with("f "));
+    assert_eq!(first_face, Some(&"f 65 70 66"));
+
+    run(&[
+        "to-obj",
+        "--mesh",
+        "1",
+        &dir.arg("grids.ima"),
+        &dir.arg("one.obj"),
+    ]);
+    let obj = std::fs::read_to_string(dir.path("one.obj")).unwrap();
+    assert!(obj.starts_with("o mesh_1\n"), "{obj}");
+    assert!(obj.contains("\nf 1 6 2\n"), "{obj}");
+    run(&["from-obj", &dir.arg("one.ima"), &dir.arg("one.obj")]);
+    // Vertices are imported in the order the faces use them
+    let corners = |name: &str, mesh: usize| -> Vec<[f32; 3]> {
+        let data = decode_file(&dir.path(name));
+        let positions = &mesh_positions(&data)[mesh];
+        let indices = &mesh_indices(&data)[mesh];
+        indices.iter().map(|&i| positions[i as usize]).collect()
+    };
+    assert_eq!(corners("one.ima", 0), corners("grids.ima", 1));
+}
@@ -0,0 +1,132 @@
+#![cfg(feature = "ply")]
+
+use iyes_mesh::descriptor::{VertexFormat, VertexUsage};
+use iyes_mesh::read::IyesMeshReaderWithData;
+
+mod common;
+use common::*;
+
+/// Write two grids (64 and 25 vertices) with Float32x4 colors and UVs to
+/// `in.ima`. Returns the colors and the bytes of the UVs.
+fn write_colored(dir: &TestDir) -> (Vec<f32>, Vec<u8>) {
+    write_test_file(&dir.path("grids.ima"), 1);
+    let colors: Vec<f32> =
+        (0..89 * 4).map(|i| (i as f32 * 0.61).sin().abs()).collect();
+    let bytes: Vec<u8> = colors.iter().flat_map(|c| c.to_le_bytes()).collect();
+    std::fs::write(dir.path("colors.bin"), bytes).unwrap();
+    let uvs: Vec<u8> =
+        (0..89 * 2).flat_map(|i| (i as f32 / 178.0).to_le_bytes()).collect();
+    std::fs::write(dir.path("uvs.bin"), &uvs).unwrap();
+    run(&[
+        "edit",
+        "--add-attr",
+        &format!("color:float32x4:{}", dir.arg("colors.bin")),
+        "--add-attr",
+        &format!("uv0:float32x2:{}", dir.arg("uvs.bin")),
+        &dir.arg("grids.ima"),
+        &dir.arg("in.ima"),
+    ]);
+    (colors, uvs)
+}
+
+/// An attribute of the only mesh of a decoded file.
+fn attribute(
+    data: &IyesMeshReaderWithData,
+    usage: VertexUsage,
+) -> (VertexFormat, Vec<u8>) {
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    let (format, bytes) = meshes.meshes[0].attributes[&usage];
+    (format, bytes.to_vec())
+}
+
+#[test]
+fn ply_round_trip() {
+    let dir = TestDir::new();
+    let (colors, uvs) = write_colored(&dir);
+    let input = decode_file(&dir.path("in.ima"));
+    // The meshes are combined into one
+    let positions = mesh_positions(&input).concat();
+    let indices: Vec<u32> = mesh_indices(&input)
+        .iter()
+        .zip([0, 64])
+        .flat_map(|(indices, base)| indices.iter().map(move |i| i + base))
+        .collect();
+
+    for ascii in [false, true] {
+        let (ply, back) = (format!("{ascii}.ply"), format!("{ascii}.ima"));
+        let mut args = vec!["to-ply"];
+        if ascii {
+            args.push("--ascii");
+        }
+        let (inpath, plypath) = (dir.arg("in.ima"), dir.arg(&ply));
+        args.extend([inpath.as_str(), plypath.as_str()]);
+        run(&args);
+        run(&["from-ply", &dir.arg(&back), &dir.arg(&ply)]);
+
+        let back = decode_file(&dir.path(&back));
+        assert_eq!(mesh_positions(&back), std::slice::from_ref(&positions));
+        assert_eq!(mesh_indices(&back), std::slice::from_ref(&indices));
+        assert_eq!(
+            attribute(&back, VertexUsage::Uv0),
+            (VertexFormat::Float32x2, uvs.clone())
+        );
+        let (format, bytes) = attribute(&back, VertexUsage::Color);
+        assert_eq!(format, VertexFormat::Unorm8x4);
+        assert_eq!(bytes.len(), colors.len());
+        for (stored, color) in bytes.iter().zip(&colors) {
+            let stored = *stored as f32 / 255.0;
+            assert!((stored - color).abs() <= 0.5 / 255.0, "{ascii}");
+        }
+    }
+}
+
+#[test]
+fn ply_header() {
+    let dir = TestDir::new();
+    write_colored(&dir);
+    run(&["to-ply", "--ascii", &dir.arg("in.ima"), &dir.arg("all.ply")]);
+    let ply = std::fs::read_to_string(dir.path("all.ply")).unwrap();
+    let (header, body) = ply.split_once("end_header\n").unwrap();
+    let header: Vec<_> = header.lines().skip(3).collect();
+    assert_eq!(
+        header,
+        [
+            "comment mesh 0: vertices 0..64, faces 0..98",
+            "comment mesh 1: vertices 64..89, faces 98..130",
+            "element vertex 89",
+            "property float x",
+            "property float y",
+            "property float z",
+            "property float s",
+            "property float t",
+            "property uchar red",
+            "property uchar green",
+            "property uchar blue",
+            "property uchar alpha",
+            "element face 130",
+            "property list uchar uint vertex_indices",
+        ]
+    );
+    let lines: Vec<_> = body.lines().collect();
+    assert_eq!(lines.len(), 89 + 130);
+    assert!(lines[..89].iter().all(|l| l.split(' ').count() == 9));
+    assert!(lines[89..].iter().all(|l| l.starts_with("3 ")));
+
+    run(&[
+        "to-ply",
+        "--mesh",
+        "1",
+        "--points",
+        &dir.arg("in.ima"),
+        &dir.arg("points.ply"),
+    ]);
+    let ply = std::fs::read(dir.path("points.ply")).unwrap();
+    let end = ply.windows(11).position(|w| w == b"end_header\n").unwrap();
+    let header = std::str::from_utf8(&ply[..end]).unwrap();
+    assert!(header.contains("comment mesh 1: vertices 0..25\n"), "{header}");
+    assert!(header.contains("element vertex 25\n"), "{header}");
+    assert!(!header.contains("element face"), "{header}");
+    // Binary: 5 floats and 4 bytes per vertex
+    assert_eq!(ply.len() - end - 11, 25 * (5 * 4 + 4));
+}
@@ -0,0 +1,117 @@
+#![cfg(feature = "stl")]
+
+mod common;
+use common::*;
+
+/// The facets of a binary STL file, checking its size.
+fn binary_facets(bytes: &[u8]) -> Vec<[[f32; 3]; 4]> {
+    let count = u32::from_le_bytes(bytes[80..84].try_into().unwrap());
+    assert_eq!(bytes.len(), 84 + 50 * count as usize);
+    bytes[84..]
+        .chunks_exact(50)
+        .map(|record| {
+            assert_eq!(record[48..], [0, 0]);
+            std::array::from_fn(|v| {
+                std::array::from_fn(|c| {
+                    let offset = (v * 3 + c) * 4;
+                    f32::from_le_bytes(
+                        record[offset..offset + 4].try_into().unwrap(),
+                    )
+                })
+            })
+        })
+        .collect()
+}
+
+/// The vertices of the triangles of the cube, without sharing any.
+fn cube_mesh_soup() -> Vec<[f32; 3]> {
+    let (positions, indices) = cube_mesh();
+    indices.iter().map(|i| positions[*i as usize]).collect()
+}
+
+#[test]
+fn export_cube() {
+    let dir = TestDir::new();
+    write_meshes(&dir.path("cube.ima"), &[cube_mesh()]);
+    run(&["to-stl", &dir.arg("cube.ima"), &dir.arg("cube.stl")]);
+    let bytes = std::fs::read(dir.path("cube.stl")).unwrap();
+    assert!(!bytes.starts_with(b"solid"));
+    let facets = binary_facets(&bytes);
+    assert_eq!(facets.len(), 12);
+    let soup: Vec<_> = facets.iter().flat_map(|f| &f[1..]).copied().collect();
+    assert_eq!(soup, cube_mesh_soup());
+    for [normal, ..] in facets.iter() {
+        let mut sorted = normal.map(f32::abs);
+        sorted.sort_by(f32::total_cmp);
+        assert_eq!(sorted, [0.0, 0.0, 1.0], "{normal:?}");
+        // No negative zeros
+        assert!(normal.iter().all(|c| *c != 0.0 || c.is_sign_positive()));
+    }
+
+    run(&[
+        "to-stl",
+        "--ascii",
+        &dir.arg("cube.ima"),
+        &dir.arg("ascii.stl"),
+    ]);
+    let text = std::fs::read_to_string(dir.path("ascii.stl")).unwrap();
+    assert!(text.starts_with("solid iyesmesh\n"), "{text}");
+    assert!(text.ends_with("endsolid iyesmesh\n"), "{text}");
+    assert_eq!(text.matches("endfacet").count(), 12);
+    assert!(text.contains(
+        "  facet normal 0 0 1\n    outer loop\n      vertex -1 -1 1\n      \
+         vertex 1 -1 1\n      vertex 1 1 1\n    endloop\n  endfacet\n"
+    ));
+
+    // Both read back as the same triangles
+    run(&["from-stl", &dir.arg("a.ima"), &dir.arg("cube.stl")]);
+    run(&["from-stl", &dir.arg("b.ima"), &dir.arg("ascii.stl")]);
+    let a = mesh_positions(&decode_file(&dir.path("a.ima")));
+    let b = mesh_positions(&decode_file(&dir.path("b.ima")));
+    assert_eq!(a, [cube_mesh_soup()]);
+    assert_eq!(a, b);
+}
+
+#[test]
+fn export_meshes() {
+    let dir = TestDir::new();
+    write_test_file(&dir.path("a.ima"), 1);
+    let output = run(&[
+        "--verbose",
+        "to-stl",
+        &dir.arg("a.ima"),
+        &dir.arg("all.stl"),
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Mesh 0: 98 triangles"), "{stderr}");
+    assert!(stderr.contains("Mesh 1: 32 triangles"), "{stderr}");
+    let all = binary_facets(&std::fs::read(dir.path("all.stl")).unwrap());
+    assert_eq!(all.len(), 130);
+
+    run(&[
+        "to-stl",
+        "--mesh",
+        "1",
+        &dir.arg("a.ima"),
+        &dir.arg("one.stl"),
+    ]);
+    let one = binary_facets(&std::fs::read(dir.path("one.stl")).unwrap());
+    assert_eq!(one, all[98..]);
+
+    let output = iyesmesh()
+        .args([
+            "to-stl",
+            "--mesh",
+            "2",
+            &dir.arg("a.ima"),
+            &dir.arg("x.stl"),
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Mesh 2 does not exist (the file has 2 meshes)"),
+        "{stderr}"
+    );
+}
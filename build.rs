@@ -0,0 +1,41 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "ffi")]
+    {
+        generate_c_header();
+        compile_c_checks();
+    }
+}
+
+/// Generate the header for the `ffi` module into `OUT_DIR`.
+#[cfg(feature = "ffi")]
+fn generate_c_header() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    let config = cbindgen::Config {
+        language: cbindgen::Language::C,
+        include_guard: Some("IYESMESH_H".into()),
+        usize_is_size_t: true,
+        ..Default::default()
+    };
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src("src/ffi.rs")
+        .generate()
+        .expect("Failed to generate C header")
+        .write_to_file(format!("{out_dir}/iyesmesh.h"));
+}
+
+/// Compile the C program of `tests/ffi.rs` against the generated header,
+/// and link it into the tests.
+#[cfg(feature = "ffi")]
+fn compile_c_checks() {
+    println!("cargo:rerun-if-changed=tests/ffi/check.c");
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    cc::Build::new()
+        .file("tests/ffi/check.c")
+        .include(&out_dir)
+        .cargo_metadata(false)
+        .compile("iyesmesh_check");
+    println!("cargo:rustc-link-arg-tests={out_dir}/libiyesmesh_check.a");
+}
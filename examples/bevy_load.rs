@@ -0,0 +1,69 @@
+//! Load `test.ima` (as written by the `simple_encode` example) with Bevy's
+//! asset server, and print the meshes it contains.
+//!
+//! Run with `--features bevy_loader`. In a full Bevy app, the mesh handles
+//! can be used directly with `Mesh3d`.
+
+use bevy_app::{App, AppExit, TaskPoolPlugin, Update};
+use bevy_asset::{
+    AssetApp, AssetPlugin, AssetServer, Assets, Handle, LoadState,
+};
+use bevy_ecs::prelude::*;
+use bevy_mesh::Mesh;
+use iyes_mesh::bevy_loader::{IyesMeshAsset, IyesMeshAssetPlugin};
+
+#[derive(Resource)]
+struct Loading(Handle<IyesMeshAsset>);
+
+fn main() -> AppExit {
+    let mut app = App::new();
+    app.add_plugins((
+        TaskPoolPlugin::default(),
+        AssetPlugin {
+            file_path: ".".into(),
+            ..Default::default()
+        },
+        IyesMeshAssetPlugin::default(),
+    ));
+    app.init_asset::<Mesh>();
+    app.add_systems(Update, print_meshes);
+    let handle = app.world().resource::<AssetServer>().load("test.ima");
+    app.insert_resource(Loading(handle));
+    loop {
+        app.update();
+        if let Some(exit) = app.should_exit() {
+            return exit;
+        }
+    }
+}
+
+fn print_meshes(
+    loading: Res<Loading>,
+    server: Res<AssetServer>,
+    files: Res<Assets<IyesMeshAsset>>,
+    meshes: Res<Assets<Mesh>>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    if let Some(LoadState::Failed(error)) = server.get_load_state(&loading.0) {
+        eprintln!("Failed to load test.ima: {error}");
+        exit.write(AppExit::error());
+        return;
+    }
+    let Some(file) = files.get(&loading.0) else {
+        return;
+    };
+    for (i, handle) in file.meshes.iter().enumerate() {
+        let Some(mesh) = meshes.get(handle) else {
+            return;
+        };
+        println!(
+            "mesh{i}: {} vertices, {} indices",
+            mesh.count_vertices(),
+            mesh.indices().map_or(0, |i| i.len()),
+        );
+    }
+    if let Some(user_data) = &file.user_data {
+        println!("user data: {:?}", String::from_utf8_lossy(user_data));
+    }
+    exit.write(AppExit::Success);
+}
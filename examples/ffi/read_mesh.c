@@ -0,0 +1,82 @@
+/*
+ * Print the contents of an IMA file using the C API.
+ *
+ * Build the library with:
+ *   cargo rustc --release --lib --features ffi --crate-type staticlib
+ * and compile this program with the generated header (see `ffi::C_HEADER`)
+ * in the include path, linking `target/release/libiyes_mesh.a`.
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "iyesmesh.h"
+
+static unsigned char *read_file(const char *path, size_t *len) {
+    FILE *f = fopen(path, "rb");
+    if (!f) {
+        return NULL;
+    }
+    fseek(f, 0, SEEK_END);
+    *len = (size_t)ftell(f);
+    fseek(f, 0, SEEK_SET);
+    unsigned char *bytes = malloc(*len);
+    if (bytes && fread(bytes, 1, *len, f) != *len) {
+        free(bytes);
+        bytes = NULL;
+    }
+    fclose(f);
+    return bytes;
+}
+
+int main(int argc, char **argv) {
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s <file.ima>\n", argv[0]);
+        return 2;
+    }
+    size_t len;
+    unsigned char *bytes = read_file(argv[1], &len);
+    if (!bytes) {
+        fprintf(stderr, "cannot read %s\n", argv[1]);
+        return 1;
+    }
+    IyesMeshHandle *handle = iyesmesh_open(bytes, len);
+    free(bytes);
+    if (!handle) {
+        fprintf(stderr, "error: %s\n", iyesmesh_last_error_message());
+        return 1;
+    }
+
+    size_t n_meshes = iyesmesh_mesh_count(handle);
+    for (size_t m = 0; m < n_meshes; m++) {
+        printf("mesh %zu: %zu vertices, %zu indices\n", m,
+               iyesmesh_vertex_count(handle, m),
+               iyesmesh_index_count(handle, m));
+        int32_t format =
+            iyesmesh_attribute_format(handle, m, IYESMESH_USAGE_POSITION);
+        if (format < 0) {
+            continue;
+        }
+        const char *format_name = iyesmesh_format_name(format);
+        if (strcmp(format_name, "Float32x3") == 0) {
+            size_t size = iyesmesh_copy_attribute(
+                handle, m, IYESMESH_USAGE_POSITION, NULL, 0);
+            float *positions = malloc(size);
+            iyesmesh_copy_attribute(
+                handle, m, IYESMESH_USAGE_POSITION,
+                (uint8_t *)positions, size);
+            printf("  first position: %f %f %f\n",
+                   positions[0], positions[1], positions[2]);
+            free(positions);
+        } else {
+            printf("  positions: %s\n", format_name);
+        }
+    }
+
+    size_t user_data_len = iyesmesh_copy_user_data(handle, NULL, 0);
+    printf("%zu bytes of user data\n", user_data_len);
+
+    iyesmesh_close(handle);
+    return 0;
+}
@@ -1,46 +1,45 @@
 use std::io::BufWriter;
 
-use iyes_mesh::{descriptor::*, mesh::MeshDataRef, write::*};
-use rapidhash::RapidHashMap;
+use iyes_mesh::{mesh::MeshData, write::*};
 
-static POSITIONS: &[f32] = &[
+static POSITIONS: &[[f32; 3]] = &[
     // Front face
-    -1.0, -1.0,  1.0,   1.0, -1.0,  1.0,
-     1.0,  1.0,  1.0,  -1.0,  1.0,  1.0,
+    [-1.0, -1.0,  1.0], [ 1.0, -1.0,  1.0],
+    [ 1.0,  1.0,  1.0], [-1.0,  1.0,  1.0],
     // Back face
-    -1.0, -1.0, -1.0,   1.0, -1.0, -1.0,
-     1.0,  1.0, -1.0,  -1.0,  1.0, -1.0,
+    [-1.0, -1.0, -1.0], [ 1.0, -1.0, -1.0],
+    [ 1.0,  1.0, -1.0], [-1.0,  1.0, -1.0],
 ];
 
-static NORMALS: &[f32] = &[
+static NORMALS: &[[f32; 3]] = &[
     // Front face
-     0.0,  0.0,  1.0,   0.0,  0.0,  1.0,
-     0.0,  0.0,  1.0,   0.0,  0.0,  1.0,
+    [ 0.0,  0.0,  1.0], [ 0.0,  0.0,  1.0],
+    [ 0.0,  0.0,  1.0], [ 0.0,  0.0,  1.0],
     // Back face
-     0.0,  0.0, -1.0,   0.0,  0.0, -1.0,
-     0.0,  0.0, -1.0,   0.0,  0.0, -1.0,
+    [ 0.0,  0.0, -1.0], [ 0.0,  0.0, -1.0],
+    [ 0.0,  0.0, -1.0], [ 0.0,  0.0, -1.0],
 ];
 
-static UVS: &[f32] = &[
+static UVS: &[[f32; 2]] = &[
     // Front face
-     0.0,  0.0,   0.0,  1.0,
-     1.0,  0.0,   1.0,  1.0,
+    [0.0, 0.0], [0.0, 1.0],
+    [1.0, 0.0], [1.0, 1.0],
     // Back face
-     1.0,  1.0,   1.0,  0.0,
-     0.0,  1.0,   0.0,  0.0,
+    [1.0, 1.0], [1.0, 0.0],
+    [0.0, 1.0], [0.0, 0.0],
 ];
 
-static COLORS: &[f32] = &[
+static COLORS: &[[f32; 4]] = &[
     // Front face
-     0.0,  0.0,  0.0,  1.0,
-     1.0,  0.0,  0.0,  1.0,
-     0.0,  1.0,  0.0,  1.0,
-     0.0,  0.0,  1.0,  1.0,
+    [0.0, 0.0, 0.0, 1.0],
+    [1.0, 0.0, 0.0, 1.0],
+    [0.0, 1.0, 0.0, 1.0],
+    [0.0, 0.0, 1.0, 1.0],
     // Back face
-     1.0,  1.0,  1.0,  1.0,
-     0.0,  1.0,  1.0,  1.0,
-     1.0,  0.0,  1.0,  1.0,
-     1.0,  1.0,  0.0,  1.0,
+    [1.0, 1.0, 1.0, 1.0],
+    [0.0, 1.0, 1.0, 1.0],
+    [1.0, 0.0, 1.0, 1.0],
+    [1.0, 1.0, 0.0, 1.0],
 ];
 
 static INDICES: &[u16] = &[
@@ -60,32 +59,17 @@ static INDICES: &[u16] = &[
 
 fn main() -> anyhow::Result<()> {
     let userdata = b"Hello World!";
-    let mut attributes = RapidHashMap::default();
-    attributes.insert(
-        VertexUsage::Position,
-        (VertexFormat::Float32x3, bytemuck::cast_slice(POSITIONS))
-    );
-    attributes.insert(
-        VertexUsage::Normal,
-        (VertexFormat::Float32x3, bytemuck::cast_slice(NORMALS))
-    );
-    attributes.insert(
-        VertexUsage::Uv0,
-        (VertexFormat::Float32x2, bytemuck::cast_slice(UVS))
-    );
-    attributes.insert(
-        VertexUsage::Color,
-        (VertexFormat::Float32x4, bytemuck::cast_slice(COLORS))
-    );
-    let meshref = MeshDataRef {
-        indices: Some((IndexFormat::U16, bytemuck::cast_slice(INDICES))),
-        attributes,
-    };
+    let mut mesh = MeshData::new();
+    mesh.set_positions(POSITIONS)?
+        .set_normals(NORMALS)?
+        .set_uv0(UVS)?
+        .set_colors_f32(COLORS)?
+        .set_indices_u16(INDICES);
     let file = std::fs::File::create("test.ima")?;
     let mut bufw = BufWriter::new(file);
     IyesMeshWriter::new()
-        .with_mesh(meshref)?
+        .with_mesh(mesh.as_mesh_ref())?
         .with_user_data(userdata)
-        .write_to(&mut bufw)?;
+        .write_to_stream(&mut bufw)?;
     Ok(())
 }
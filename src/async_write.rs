@@ -0,0 +1,171 @@
+//! Encoding into async (`tokio`) sinks.
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::io::*;
+use crate::mesh::MeshDataRef;
+use crate::write::*;
+
+/// Wrapper around [`IyesMeshWriter`] for writing to an [`AsyncWrite`].
+///
+/// Behaves identically to the sync writer and produces identical files.
+///
+/// Note that compression is CPU-heavy and happens on the calling task.
+/// With the data checksum enabled, the whole compressed payload is
+/// buffered in memory (like the sync writer). With it disabled, the
+/// output is streamed out chunk by chunk, with bounded memory usage.
+pub struct AsyncIyesMeshWriter<'s> {
+    inner: IyesMeshWriter<'s>,
+}
+
+impl<'s> From<IyesMeshWriter<'s>> for AsyncIyesMeshWriter<'s> {
+    fn from(inner: IyesMeshWriter<'s>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Default for AsyncIyesMeshWriter<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'s> AsyncIyesMeshWriter<'s> {
+    pub fn new() -> Self {
+        IyesMeshWriter::new().into()
+    }
+
+    pub fn new_with_settings(settings: IyesMeshWriterSettings) -> Self {
+        IyesMeshWriter::new_with_settings(settings).into()
+    }
+
+    /// Access the underlying sync writer, to configure it.
+    pub fn inner(&mut self) -> &mut IyesMeshWriter<'s> {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> IyesMeshWriter<'s> {
+        self.inner
+    }
+
+    pub fn with_user_data(
+        mut self,
+        user_data: &'s [u8],
+    ) -> Self {
+        self.inner.set_user_data(user_data);
+        self
+    }
+
+    pub fn with_mesh(
+        mut self,
+        mesh: MeshDataRef<'s>,
+    ) -> Result<Self, WriteError> {
+        self.inner.add_mesh(mesh)?;
+        Ok(self)
+    }
+
+    pub async fn write_to<W: AsyncWrite + Unpin>(
+        &mut self,
+        write: &mut W,
+    ) -> Result<(), WriteError> {
+        let w = &mut self.inner;
+        let PreparedFile {
+            descriptor,
+            bytes_descriptor,
+            encrypted_user_data,
+            mut header,
+            total_uncompressed_len,
+            ..
+        } = w.prepare()?;
+        let mut signer = FileSigner::new(&w.settings);
+        if w.settings.write_data_checksum {
+            let mut comprbuf = vec![];
+            let encoder = new_zstd_encoder(
+                &mut comprbuf,
+                w.settings.compression_level,
+                total_uncompressed_len,
+            )?;
+            w.do_encode_data(
+                &descriptor,
+                encrypted_user_data.as_deref(),
+                encoder,
+                total_uncompressed_len,
+            )?;
+            w.report_progress(
+                WritePhase::Checksumming,
+                total_uncompressed_len,
+                total_uncompressed_len,
+            );
+            header.data_checksum = w.settings.checksum_kind.checksum(&comprbuf);
+            header.metadata_checksum =
+                crate::checksum::checksum_metadata(header, &bytes_descriptor);
+            w.report_progress(
+                WritePhase::Flushing,
+                total_uncompressed_len,
+                total_uncompressed_len,
+            );
+            write.write_all(header.as_bytes()).await?;
+            write.write_all(&bytes_descriptor).await?;
+            write.write_all(&comprbuf).await?;
+            signer.update(header.as_bytes());
+            signer.update(&bytes_descriptor);
+            signer.update(&comprbuf);
+        } else {
+            header.metadata_checksum =
+                crate::checksum::checksum_metadata(header, &bytes_descriptor);
+            write.write_all(header.as_bytes()).await?;
+            write.write_all(&bytes_descriptor).await?;
+            signer.update(header.as_bytes());
+            signer.update(&bytes_descriptor);
+            let mut encoder = new_zstd_encoder(
+                Vec::new(),
+                w.settings.compression_level,
+                total_uncompressed_len,
+            )?;
+            let mut processed = 0;
+            w.report_progress(
+                WritePhase::Compressing,
+                processed,
+                total_uncompressed_len,
+            );
+            let mut scratch = vec![];
+            let segments = w
+                .payload_segments(&descriptor, encrypted_user_data.as_deref());
+            for segment in segments {
+                let bytes = segment.prepare(&mut scratch);
+                for chunk in bytes.chunks(ENCODE_CHUNK_SIZE) {
+                    w.check_cancelled()?;
+                    std::io::Write::write_all(&mut encoder, chunk)?;
+                    processed += chunk.len() as u64;
+                    w.report_progress(
+                        WritePhase::Compressing,
+                        processed,
+                        total_uncompressed_len,
+                    );
+                    let out = encoder.get_mut();
+                    write.write_all(out).await?;
+                    signer.update(out);
+                    out.clear();
+                }
+            }
+            let out = encoder.finish()?;
+            w.report_progress(
+                WritePhase::Flushing,
+                total_uncompressed_len,
+                total_uncompressed_len,
+            );
+            write.write_all(&out).await?;
+            signer.update(&out);
+        }
+        if let Some(signature) = signer.finish() {
+            write.write_all(&signature).await?;
+        }
+        write.flush().await?;
+        w.report_progress(
+            WritePhase::Done,
+            total_uncompressed_len,
+            total_uncompressed_len,
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,292 @@
+//! Conversion of Bevy meshes (from `bevy_mesh`, re-exported by `bevy`).
+//!
+//! Bevy's standard attributes map to the named [`VertexUsage`]s. Bevy has
+//! no attributes for UV sets after the second one, or for a second set of
+//! joints, so this module defines [`ATTRIBUTE_UV_2`] to [`ATTRIBUTE_UV_7`],
+//! [`ATTRIBUTE_JOINT_INDEX_1`] and [`ATTRIBUTE_JOINT_WEIGHT_1`] for them.
+//! Any other attributes must be given a [`VertexUsage::Custom`] id with a
+//! [`CustomAttributeMap`].
+
+use bevy_asset::RenderAssetUsages;
+use bevy_mesh::{
+    Indices, Mesh, MeshAccessError, MeshVertexAttribute, PrimitiveTopology,
+    VertexAttributeValues, VertexFormat as BevyVertexFormat,
+};
+
+use crate::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+use crate::mesh::{MeshData, MeshDataRef, MeshError};
+use crate::view::{ViewError, view_of};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConvertError {
+    #[error("Cannot access Bevy mesh data: {0}")]
+    Access(#[from] MeshAccessError),
+    #[error("Unsupported primitive topology {0:?} (must be a triangle list)")]
+    Topology(PrimitiveTopology),
+    #[error("No custom usage id for Bevy attribute {0:?}")]
+    UnmappedAttribute(&'static str),
+    #[error("Invalid mesh data: {0}")]
+    Mesh(#[from] MeshError),
+    #[error("Invalid mesh data: {0}")]
+    View(#[from] ViewError),
+}
+
+/// Correspondence between Bevy attributes and [`VertexUsage::Custom`] ids.
+#[derive(Debug, Clone, Default)]
+pub struct CustomAttributeMap {
+    entries: Vec<(u32, MeshVertexAttribute)>,
+}
+
+impl CustomAttributeMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map the Bevy attribute to `VertexUsage::Custom(id)`.
+    pub fn with(
+        mut self,
+        id: u32,
+        attribute: MeshVertexAttribute,
+    ) -> Self {
+        self.insert(id, attribute);
+        self
+    }
+
+    pub fn insert(
+        &mut self,
+        id: u32,
+        attribute: MeshVertexAttribute,
+    ) {
+        self.entries.retain(|(i, a)| *i != id && a.id != attribute.id);
+        self.entries.push((id, attribute));
+    }
+
+    pub fn custom_id(
+        &self,
+        attribute: &MeshVertexAttribute,
+    ) -> Option<u32> {
+        self.entries
+            .iter()
+            .find(|(_, a)| a.id == attribute.id)
+            .map(|(i, _)| *i)
+    }
+
+    pub fn attribute(
+        &self,
+        id: u32,
+    ) -> Option<MeshVertexAttribute> {
+        self.entries.iter().find(|(i, _)| *i == id).map(|(_, a)| *a)
+    }
+}
+
+/// Bevy attribute for [`VertexUsage::Uv2`].
+pub const ATTRIBUTE_UV_2: MeshVertexAttribute =
+    extra_attribute("Vertex_Uv_2", 2, BevyVertexFormat::Float32x2);
+/// Bevy attribute for [`VertexUsage::Uv3`].
+pub const ATTRIBUTE_UV_3: MeshVertexAttribute =
+    extra_attribute("Vertex_Uv_3", 3, BevyVertexFormat::Float32x2);
+/// Bevy attribute for [`VertexUsage::Uv4`].
+pub const ATTRIBUTE_UV_4: MeshVertexAttribute =
+    extra_attribute("Vertex_Uv_4", 4, BevyVertexFormat::Float32x2);
+/// Bevy attribute for [`VertexUsage::Uv5`].
+pub const ATTRIBUTE_UV_5: MeshVertexAttribute =
+    extra_attribute("Vertex_Uv_5", 5, BevyVertexFormat::Float32x2);
+/// Bevy attribute for [`VertexUsage::Uv6`].
+pub const ATTRIBUTE_UV_6: MeshVertexAttribute =
+    extra_attribute("Vertex_Uv_6", 6, BevyVertexFormat::Float32x2);
+/// Bevy attribute for [`VertexUsage::Uv7`].
+pub const ATTRIBUTE_UV_7: MeshVertexAttribute =
+    extra_attribute("Vertex_Uv_7", 7, BevyVertexFormat::Float32x2);
+/// Bevy attribute for [`VertexUsage::JointIndex1`].
+pub const ATTRIBUTE_JOINT_INDEX_1: MeshVertexAttribute = extra_attribute(
+    "Vertex_JointIndex_1",
+    16,
+    BevyVertexFormat::Uint16x4,
+);
+/// Bevy attribute for [`VertexUsage::JointWeight1`].
+pub const ATTRIBUTE_JOINT_WEIGHT_1: MeshVertexAttribute = extra_attribute(
+    "Vertex_JointWeight_1",
+    17,
+    BevyVertexFormat::Float32x4,
+);
+
+/// Ids of the attributes defined here (arbitrary, like Bevy's custom
+/// attribute ids, plus an offset per attribute).
+const EXTRA_ATTRIBUTE_ID_BASE: u64 = 0x1e5_3e54_0000;
+
+const fn extra_attribute(
+    name: &'static str,
+    offset: u64,
+    format: BevyVertexFormat,
+) -> MeshVertexAttribute {
+    MeshVertexAttribute::new(name, EXTRA_ATTRIBUTE_ID_BASE + offset, format)
+}
+
+/// The Bevy attributes with a named [`VertexUsage`] equivalent.
+pub const STANDARD_ATTRIBUTES: &[(VertexUsage, MeshVertexAttribute)] = &[
+    (VertexUsage::Position, Mesh::ATTRIBUTE_POSITION),
+    (VertexUsage::Normal, Mesh::ATTRIBUTE_NORMAL),
+    (VertexUsage::Tangent, Mesh::ATTRIBUTE_TANGENT),
+    (VertexUsage::Uv0, Mesh::ATTRIBUTE_UV_0),
+    (VertexUsage::Uv1, Mesh::ATTRIBUTE_UV_1),
+    (VertexUsage::Uv2, ATTRIBUTE_UV_2),
+    (VertexUsage::Uv3, ATTRIBUTE_UV_3),
+    (VertexUsage::Uv4, ATTRIBUTE_UV_4),
+    (VertexUsage::Uv5, ATTRIBUTE_UV_5),
+    (VertexUsage::Uv6, ATTRIBUTE_UV_6),
+    (VertexUsage::Uv7, ATTRIBUTE_UV_7),
+    (VertexUsage::Color, Mesh::ATTRIBUTE_COLOR),
+    (VertexUsage::JointIndex, Mesh::ATTRIBUTE_JOINT_INDEX),
+    (VertexUsage::JointWeight, Mesh::ATTRIBUTE_JOINT_WEIGHT),
+    (VertexUsage::JointIndex1, ATTRIBUTE_JOINT_INDEX_1),
+    (VertexUsage::JointWeight1, ATTRIBUTE_JOINT_WEIGHT_1),
+];
+
+/// Convert a Bevy mesh with only standard attributes.
+///
+/// See [`from_bevy_mesh_with`].
+pub fn from_bevy_mesh(mesh: &Mesh) -> Result<MeshData, ConvertError> {
+    from_bevy_mesh_with(mesh, &CustomAttributeMap::default())
+}
+
+/// Convert a Bevy mesh, using `custom` for non-standard attributes.
+///
+/// The mesh must be a triangle list, and its data must still be available
+/// in the main world.
+pub fn from_bevy_mesh_with(
+    mesh: &Mesh,
+    custom: &CustomAttributeMap,
+) -> Result<MeshData, ConvertError> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return Err(ConvertError::Topology(mesh.primitive_topology()));
+    }
+    let mut r = MeshData::new();
+    for (attribute, values) in mesh.try_attributes()? {
+        let usage = match STANDARD_ATTRIBUTES
+            .iter()
+            .find(|(_, a)| a.id == attribute.id)
+        {
+            Some((usage, _)) => *usage,
+            None => VertexUsage::Custom(
+                custom
+                    .custom_id(attribute)
+                    .ok_or(ConvertError::UnmappedAttribute(attribute.name))?,
+            ),
+        };
+        let format = BevyVertexFormat::from(values).into();
+        r.set_attribute_bytes(usage, format, values.get_bytes())?;
+    }
+    match mesh.try_indices_option()? {
+        Some(Indices::U16(indices)) => {
+            r.set_indices_u16(indices);
+        }
+        Some(Indices::U32(indices)) => {
+            r.set_indices_u32(indices);
+        }
+        None => {}
+    }
+    Ok(r)
+}
+
+/// Convert a mesh into a Bevy mesh (a triangle list).
+///
+/// Attributes keep their format: standard attributes stored in a different
+/// format than Bevy's default are inserted with the same attribute id, but
+/// the actual format. Custom attributes are converted if they are in
+/// `custom`, and skipped otherwise.
+pub fn to_bevy_mesh(
+    mesh: &MeshDataRef<'_>,
+    custom: &CustomAttributeMap,
+    asset_usage: RenderAssetUsages,
+) -> Result<Mesh, ConvertError> {
+    let mut r = Mesh::new(PrimitiveTopology::TriangleList, asset_usage);
+    for (usage, (format, bytes)) in mesh.attributes.iter() {
+        let attribute = match usage {
+            VertexUsage::Custom(id) => match custom.attribute(*id) {
+                Some(attribute) => attribute,
+                None => continue,
+            },
+            usage => STANDARD_ATTRIBUTES
+                .iter()
+                .find(|(u, _)| u == usage)
+                .map(|(_, a)| *a)
+                .unwrap(),
+        };
+        let attribute = MeshVertexAttribute {
+            format: (*format).into(),
+            ..attribute
+        };
+        r.try_insert_attribute(attribute, attribute_values(*format, bytes)?)?;
+    }
+    if let Some((format, bytes)) = mesh.indices {
+        let indices = match format {
+            IndexFormat::U16 => Indices::U16(view_of(2, bytes)?.to_vec()),
+            IndexFormat::U32 => Indices::U32(view_of(4, bytes)?.to_vec()),
+        };
+        r.try_insert_indices(indices)?;
+    }
+    Ok(r)
+}
+
+/// Copy vertex data into Bevy's representation of its format.
+fn attribute_values(
+    format: VertexFormat,
+    bytes: &[u8],
+) -> Result<VertexAttributeValues, ViewError> {
+    macro_rules! values {
+        ($($name:ident),* $(,)?) => {
+            match format {
+                $(VertexFormat::$name => VertexAttributeValues::$name(
+                    view_of(format.size(), bytes)?.to_vec(),
+                ),)*
+            }
+        };
+    }
+    Ok(values!(
+        Float16,
+        Float32,
+        Float64,
+        Float16x2,
+        Float16x4,
+        Float32x2,
+        Float32x3,
+        Float32x4,
+        Float64x2,
+        Float64x3,
+        Float64x4,
+        Sint8,
+        Sint8x2,
+        Sint8x4,
+        Sint16,
+        Sint32,
+        Sint16x2,
+        Sint16x4,
+        Sint32x2,
+        Sint32x3,
+        Sint32x4,
+        Snorm8,
+        Snorm8x2,
+        Snorm8x4,
+        Snorm16,
+        Snorm16x2,
+        Snorm16x4,
+        Uint8,
+        Uint8x2,
+        Uint8x4,
+        Uint16,
+        Uint32,
+        Uint16x2,
+        Uint16x4,
+        Uint32x2,
+        Uint32x3,
+        Uint32x4,
+        Unorm8,
+        Unorm8x2,
+        Unorm8x4,
+        Unorm8x4Bgra,
+        Unorm16,
+        Unorm10_10_10_2,
+        Unorm16x2,
+        Unorm16x4,
+    ))
+}
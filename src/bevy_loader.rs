@@ -0,0 +1,399 @@
+//! Loading and saving of IMA files as Bevy assets.
+//!
+//! Add [`IyesMeshAssetPlugin`] to the app to load `.ima` files as
+//! [`IyesMeshAsset`]s. Every mesh in the file is a labeled sub-asset
+//! (`"mesh0"`, `"mesh1"`, ...), so individual meshes can be loaded as
+//! `"path/to/file.ima#mesh0"`.
+//!
+//! Quantized positions and specially encoded normals/tangents are decoded
+//! to floats, because Bevy's renderer expects them in that form.
+//!
+//! For asset processing, [`IyesMeshSaver`] writes an [`IyesMeshAsset`]
+//! back to the format, and [`MeshesToIma`] converts the meshes of any
+//! other asset (such as a glTF file) into an IMA file.
+
+use std::any::Any;
+use std::io::Cursor;
+use std::marker::PhantomData;
+
+use bevy_app::{App, Plugin};
+use bevy_asset::io::{AsyncWriteExt, Reader, Writer};
+use bevy_asset::processor::LoadTransformAndSave;
+use bevy_asset::saver::{AssetSaver, SavedAsset};
+use bevy_asset::transformer::{AssetTransformer, TransformedAsset};
+use bevy_asset::{
+    Asset, AssetApp, AssetLoader, AssetPath, Handle, LoadContext,
+    RenderAssetUsages,
+};
+use bevy_mesh::Mesh;
+use bevy_reflect::TypePath;
+use serde::{Deserialize, Serialize};
+
+use crate::HashSet;
+use crate::bevy::{
+    ConvertError, CustomAttributeMap, from_bevy_mesh_with, to_bevy_mesh,
+};
+use crate::descriptor::VertexUsage;
+use crate::mesh::MeshData;
+use crate::read::{IyesMeshReader, IyesMeshReaderSettings, ReadError};
+use crate::write::{
+    AttributeFilter, IyesMeshWriter, IyesMeshWriterSettings, WriteError,
+};
+
+/// Registers [`IyesMeshAsset`] and its loader.
+///
+/// The [`Mesh`] asset type must be registered separately (Bevy's default
+/// plugins already do so).
+#[derive(Default)]
+pub struct IyesMeshAssetPlugin {
+    /// How to convert custom attributes (see [`to_bevy_mesh`]).
+    pub custom_attributes: CustomAttributeMap,
+}
+
+impl Plugin for IyesMeshAssetPlugin {
+    fn build(
+        &self,
+        app: &mut App,
+    ) {
+        app.init_asset::<IyesMeshAsset>();
+        app.register_asset_loader(IyesMeshLoader {
+            custom_attributes: self.custom_attributes.clone(),
+        });
+    }
+}
+
+/// The contents of an IMA file.
+#[derive(Asset, TypePath, Debug)]
+pub struct IyesMeshAsset {
+    /// The meshes, in file order.
+    #[dependency]
+    pub meshes: Vec<Handle<Mesh>>,
+    /// The user data (`None` if it is encrypted and no key was given).
+    pub user_data: Option<Vec<u8>>,
+}
+
+/// Loader settings, mirroring [`IyesMeshReaderSettings`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct IyesMeshLoaderSettings {
+    pub verify_metadata_checksum: bool,
+    pub verify_data_checksum: bool,
+    pub verify_user_data_checksum: bool,
+    pub allow_trailing_data: bool,
+    #[cfg(feature = "encryption")]
+    pub user_data_key: Option<[u8; 32]>,
+    #[cfg(feature = "signing")]
+    pub verifying_key: Option<[u8; 32]>,
+    pub asset_usage: RenderAssetUsages,
+}
+
+impl Default for IyesMeshLoaderSettings {
+    fn default() -> Self {
+        let reader = IyesMeshReaderSettings::default();
+        Self {
+            verify_metadata_checksum: reader.verify_metadata_checksum,
+            verify_data_checksum: reader.verify_data_checksum,
+            verify_user_data_checksum: reader.verify_user_data_checksum,
+            allow_trailing_data: reader.allow_trailing_data,
+            #[cfg(feature = "encryption")]
+            user_data_key: reader.user_data_key,
+            #[cfg(feature = "signing")]
+            verifying_key: reader.verifying_key,
+            asset_usage: RenderAssetUsages::default(),
+        }
+    }
+}
+
+impl From<IyesMeshLoaderSettings> for IyesMeshReaderSettings {
+    fn from(settings: IyesMeshLoaderSettings) -> Self {
+        Self {
+            verify_metadata_checksum: settings.verify_metadata_checksum,
+            verify_data_checksum: settings.verify_data_checksum,
+            verify_user_data_checksum: settings.verify_user_data_checksum,
+            allow_trailing_data: settings.allow_trailing_data,
+            #[cfg(feature = "encryption")]
+            user_data_key: settings.user_data_key,
+            #[cfg(feature = "signing")]
+            verifying_key: settings.verifying_key,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum IyesMeshLoaderError {
+    #[error("I/O Error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Cannot read IMA file: {0}")]
+    Read(#[from] ReadError),
+    #[error("Cannot convert mesh {index}: {error}")]
+    Convert { index: usize, error: ConvertError },
+}
+
+#[derive(TypePath)]
+pub struct IyesMeshLoader {
+    custom_attributes: CustomAttributeMap,
+}
+
+impl AssetLoader for IyesMeshLoader {
+    type Asset = IyesMeshAsset;
+    type Settings = IyesMeshLoaderSettings;
+    type Error = IyesMeshLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut cursor = Cursor::new(&bytes[..]);
+        let reader = IyesMeshReader::init_with_settings(
+            (*settings).into(),
+            &mut cursor,
+        )?;
+        let data = reader.read_all_data()?;
+        let buffers = data.into_flat_buffers()?;
+        let split = data.into_split_meshes(&buffers)?;
+        let mut meshes = Vec::with_capacity(split.meshes.len());
+        for (index, mesh) in split.meshes.iter().enumerate() {
+            let convert_error =
+                |error| IyesMeshLoaderError::Convert { index, error };
+            let mut bevy_mesh = to_bevy_mesh(
+                mesh,
+                &self.custom_attributes,
+                settings.asset_usage,
+            )
+            .map_err(convert_error)?;
+            let info = &data.descriptor().meshes[index];
+            if info.position_transform.is_some()
+                && let Some(positions) =
+                    data.decode_positions_f32(&split, index)
+            {
+                bevy_mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+            }
+            let encodings = &data.descriptor().attribute_encodings;
+            if encodings.contains_key(&VertexUsage::Normal)
+                && let Some(normals) = data.decode_normals_f32(&split, index)
+            {
+                bevy_mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+            }
+            if encodings.contains_key(&VertexUsage::Tangent)
+                && let Some(tangents) = data.decode_tangents_f32(&split, index)
+            {
+                bevy_mesh.insert_attribute(Mesh::ATTRIBUTE_TANGENT, tangents);
+            }
+            let label = format!("mesh{index}");
+            meshes.push(load_context.add_labeled_asset(label, bevy_mesh));
+        }
+        // Without the key, the meshes can still be used.
+        let user_data = match data.decode_user_data() {
+            Err(ReadError::UserDataEncrypted) => None,
+            r => r?,
+        };
+        Ok(IyesMeshAsset { meshes, user_data })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ima"]
+    }
+}
+
+/// Settings for [`IyesMeshSaver`], a serializable subset of
+/// [`IyesMeshWriterSettings`] and the attribute filter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IyesMeshSaverSettings {
+    pub compression_level: i32,
+    pub write_data_checksum: bool,
+    pub quantize_positions: bool,
+    pub octahedral_normals: bool,
+    pub octahedral_tangents: bool,
+    /// If set, only these attributes are written.
+    pub keep_attributes: Option<Vec<VertexUsage>>,
+    /// These attributes are never written.
+    pub drop_attributes: Vec<VertexUsage>,
+    /// Store U32 indices as U16, if all the meshes allow it.
+    pub downconvert_indices: bool,
+}
+
+impl Default for IyesMeshSaverSettings {
+    fn default() -> Self {
+        let writer = IyesMeshWriterSettings::default();
+        Self {
+            compression_level: writer.compression_level,
+            write_data_checksum: writer.write_data_checksum,
+            quantize_positions: writer.quantize_positions,
+            octahedral_normals: writer.octahedral_normals,
+            octahedral_tangents: writer.octahedral_tangents,
+            keep_attributes: None,
+            drop_attributes: Vec::new(),
+            downconvert_indices: true,
+        }
+    }
+}
+
+impl IyesMeshSaverSettings {
+    pub fn writer_settings(&self) -> IyesMeshWriterSettings {
+        IyesMeshWriterSettings {
+            upconvert_indices: true,
+            write_data_checksum: self.write_data_checksum,
+            compression_level: self.compression_level,
+            quantize_positions: self.quantize_positions,
+            octahedral_normals: self.octahedral_normals,
+            octahedral_tangents: self.octahedral_tangents,
+            ..Default::default()
+        }
+    }
+
+    pub fn attribute_filter(&self) -> AttributeFilter {
+        AttributeFilter {
+            keep: self
+                .keep_attributes
+                .as_ref()
+                .map(|keep| keep.iter().copied().collect()),
+            drop: self.drop_attributes.iter().copied().collect::<HashSet<_>>(),
+            allow_dropping_position: false,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum IyesMeshSaverError {
+    #[error("I/O Error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Cannot write IMA file: {0}")]
+    Write(#[from] WriteError),
+    #[error("Cannot convert mesh {index}: {error}")]
+    Convert { index: usize, error: ConvertError },
+    #[error("Mesh {0} is not a sub-asset of the saved asset")]
+    MissingMesh(usize),
+}
+
+/// Writes an [`IyesMeshAsset`] and its meshes as an IMA file.
+///
+/// The meshes must be labeled sub-assets of the saved asset, and are
+/// written in the order of [`IyesMeshAsset::meshes`].
+#[derive(TypePath, Default)]
+pub struct IyesMeshSaver {
+    /// How to convert custom attributes (see [`from_bevy_mesh_with`]).
+    pub custom_attributes: CustomAttributeMap,
+}
+
+impl AssetSaver for IyesMeshSaver {
+    type Asset = IyesMeshAsset;
+    type Settings = IyesMeshSaverSettings;
+    type OutputLoader = IyesMeshLoader;
+    type Error = IyesMeshSaverError;
+
+    async fn save(
+        &self,
+        writer: &mut Writer,
+        asset: SavedAsset<'_, '_, Self::Asset>,
+        settings: &Self::Settings,
+        _asset_path: AssetPath<'_>,
+    ) -> Result<IyesMeshLoaderSettings, Self::Error> {
+        let mut meshes = Vec::with_capacity(asset.meshes.len());
+        for (index, handle) in asset.meshes.iter().enumerate() {
+            let mesh = asset
+                .get_labeled_by_id::<Mesh>(handle)
+                .ok_or(IyesMeshSaverError::MissingMesh(index))?;
+            let mesh = from_bevy_mesh_with(&mesh, &self.custom_attributes)
+                .map_err(|error| IyesMeshSaverError::Convert { index, error })?;
+            meshes.push(mesh);
+        }
+        if settings.downconvert_indices {
+            downconvert_indices(&mut meshes);
+        }
+        let mut ima = IyesMeshWriter::new_with_settings(
+            settings.writer_settings(),
+        );
+        ima.set_attribute_filter(settings.attribute_filter());
+        for mesh in meshes.iter() {
+            ima.add_mesh(mesh.as_mesh_ref())?;
+        }
+        if let Some(user_data) = &asset.user_data {
+            ima.set_user_data(user_data);
+        }
+        let mut bytes = Vec::new();
+        ima.write_to_stream(&mut bytes)?;
+        writer.write_all(&bytes).await?;
+        Ok(IyesMeshLoaderSettings::default())
+    }
+}
+
+/// Convert all indices to U16, unless some mesh has too many vertices.
+fn downconvert_indices(meshes: &mut [MeshData]) {
+    let fits = meshes.iter().all(|mesh| {
+        mesh.as_mesh_ref()
+            .iter_indices()
+            .is_none_or(|mut i| i.all(|i| i <= u16::MAX as u32))
+    });
+    if !fits {
+        return;
+    }
+    for mesh in meshes.iter_mut() {
+        let indices: Option<Vec<u16>> = mesh
+            .as_mesh_ref()
+            .iter_indices()
+            .map(|i| i.map(|i| i as u16).collect());
+        if let Some(indices) = indices {
+            mesh.set_indices_u16(&indices);
+        }
+    }
+}
+
+/// Collects the meshes of any asset into an [`IyesMeshAsset`].
+///
+/// All [`Mesh`] sub-assets (such as the primitives of a glTF file) are
+/// included, ordered by their label. Other sub-assets are kept, but not
+/// saved by [`IyesMeshSaver`]. If the input is an [`IyesMeshAsset`], its
+/// user data is kept too.
+#[derive(TypePath)]
+pub struct CollectMeshes<A> {
+    marker: PhantomData<fn() -> A>,
+}
+
+impl<A> Default for CollectMeshes<A> {
+    fn default() -> Self {
+        Self {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<A: Asset> AssetTransformer for CollectMeshes<A> {
+    type AssetInput = A;
+    type AssetOutput = IyesMeshAsset;
+    type Settings = ();
+    type Error = std::convert::Infallible;
+
+    async fn transform<'a>(
+        &'a self,
+        asset: TransformedAsset<A>,
+        _settings: &'a Self::Settings,
+    ) -> Result<TransformedAsset<IyesMeshAsset>, Self::Error> {
+        let mut labels: Vec<&str> = asset.iter_labels().collect();
+        labels.sort_unstable();
+        let meshes = labels
+            .into_iter()
+            .filter_map(|label| asset.get_handle::<_, Mesh>(label))
+            .collect();
+        let user_data = (asset.get() as &dyn Any)
+            .downcast_ref::<IyesMeshAsset>()
+            .and_then(|ima| ima.user_data.clone());
+        Ok(asset.replace_asset(IyesMeshAsset { meshes, user_data }))
+    }
+}
+
+/// Asset processor converting the meshes of assets loaded by `L` to IMA.
+///
+/// Register it with
+/// [`register_asset_processor`](AssetApp::register_asset_processor), and
+/// use it for the source files (e.g. with
+/// [`set_default_asset_processor`](AssetApp::set_default_asset_processor)).
+/// The meshes are written using the [`IyesMeshSaverSettings`] from the
+/// `.meta` file of each asset.
+pub type MeshesToIma<L> = LoadTransformAndSave<
+    L,
+    CollectMeshes<<L as AssetLoader>::Asset>,
+    IyesMeshSaver,
+>;
@@ -0,0 +1,25 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Handle for cooperatively aborting long-running encode/decode operations.
+///
+/// Clones share the same state; cancelling any of them cancels all.
+/// The reader/writer checks the token between chunks of data, so
+/// cancellation takes effect with a small delay.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation of any operations using this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
@@ -1,3 +1,5 @@
+use std::io::{Read, Write};
+
 use crate::header::IyesMeshHeader;
 
 #[inline(always)]
@@ -11,8 +13,658 @@ pub fn checksum_metadata(
     encoded_descriptor: &[u8],
 ) -> u64 {
     let hasher = rapidhash::RapidInlineHasher::default_const();
-    let hasher = hasher.write_const(&encoded_descriptor);
+    let hasher = hasher.write_const(encoded_descriptor);
     let hasher = hasher.write_const(&header.descriptor_len.to_le_bytes());
     let hasher = hasher.write_const(&header.data_checksum.to_le_bytes());
     hasher.finish_const()
 }
+
+/// Algorithm of the data checksums of a file.
+///
+/// This covers the data checksum in the header and the user data checksum
+/// in the descriptor. The metadata checksum always uses RapidHash. The
+/// algorithms other than RapidHash each need the cargo feature of the same
+/// name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ChecksumKind {
+    /// RapidHash with the default seed.
+    #[default]
+    RapidHash,
+    /// 64-bit XXH3 with the default seed.
+    #[cfg(feature = "xxh3")]
+    Xxh3,
+    /// CRC-32C (Castagnoli), zero-extended to 64 bits.
+    #[cfg(feature = "crc32c")]
+    Crc32c,
+    /// The first 8 bytes of the BLAKE3 hash, read as little-endian.
+    ///
+    /// Unlike the others, BLAKE3 is a cryptographic hash, but a 64-bit
+    /// checksum only resists brute force so well. Use
+    /// [signatures](crate::signing) to detect deliberate tampering.
+    #[cfg(feature = "blake3")]
+    Blake3,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown checksum algorithm: {0:?}")]
+pub struct ParseChecksumKindError(pub String);
+
+impl ChecksumKind {
+    /// The algorithms supported by this build.
+    pub const ALL: &[ChecksumKind] = &[
+        ChecksumKind::RapidHash,
+        #[cfg(feature = "xxh3")]
+        ChecksumKind::Xxh3,
+        #[cfg(feature = "crc32c")]
+        ChecksumKind::Crc32c,
+        #[cfg(feature = "blake3")]
+        ChecksumKind::Blake3,
+    ];
+
+    /// The id stored in the descriptor.
+    pub const fn id(self) -> u8 {
+        match self {
+            ChecksumKind::RapidHash => 0,
+            #[cfg(feature = "xxh3")]
+            ChecksumKind::Xxh3 => 1,
+            #[cfg(feature = "crc32c")]
+            ChecksumKind::Crc32c => 2,
+            #[cfg(feature = "blake3")]
+            ChecksumKind::Blake3 => 3,
+        }
+    }
+
+    /// The algorithm with the given id, if it is supported by this build.
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.iter().find(|kind| kind.id() == id).copied()
+    }
+
+    /// The name of the algorithm with the given id, even if it is not
+    /// supported by this build.
+    pub fn name_of_id(id: u8) -> Option<&'static str> {
+        match id {
+            0 => Some("rapidhash"),
+            1 => Some("xxh3"),
+            2 => Some("crc32c"),
+            3 => Some("blake3"),
+            _ => None,
+        }
+    }
+
+    /// Whether the length of the data must be known before hashing it
+    /// incrementally (see [`DataHasher`]).
+    pub const fn needs_len(self) -> bool {
+        matches!(self, ChecksumKind::RapidHash)
+    }
+
+    pub fn name(self) -> &'static str {
+        Self::name_of_id(self.id()).unwrap()
+    }
+
+    /// Compute the checksum of some data with this algorithm.
+    pub fn checksum(
+        self,
+        data: &[u8],
+    ) -> u64 {
+        match self {
+            ChecksumKind::RapidHash => checksum_data(data),
+            #[cfg(feature = "xxh3")]
+            ChecksumKind::Xxh3 => xxhash_rust::xxh3::xxh3_64(data),
+            #[cfg(feature = "crc32c")]
+            ChecksumKind::Crc32c => crc32c::crc32c(data) as u64,
+            #[cfg(feature = "blake3")]
+            ChecksumKind::Blake3 => blake3_u64(&blake3::hash(data)),
+        }
+    }
+}
+
+impl std::fmt::Display for ChecksumKind {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Parses the [name](ChecksumKind::name) of a supported algorithm
+/// (case-insensitive).
+impl std::str::FromStr for ChecksumKind {
+    type Err = ParseChecksumKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ChecksumKind::ALL
+            .iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| ParseChecksumKindError(s.to_owned()))
+    }
+}
+
+#[cfg(feature = "blake3")]
+fn blake3_u64(hash: &blake3::Hash) -> u64 {
+    u64::from_le_bytes(hash.as_bytes()[..8].try_into().unwrap())
+}
+
+const RAPID_SECRET: [u64; 3] =
+    [0x2d358dccaa6c78a5, 0x8bb84b93962eacc9, 0x4b33a62ed433d4a3];
+
+#[inline(always)]
+const fn rapid_mum(
+    a: u64,
+    b: u64,
+) -> (u64, u64) {
+    let r = a as u128 * b as u128;
+    (r as u64, (r >> 64) as u64)
+}
+
+#[inline(always)]
+const fn rapid_mix(
+    a: u64,
+    b: u64,
+) -> u64 {
+    let (a, b) = rapid_mum(a, b);
+    a ^ b
+}
+
+#[inline(always)]
+fn read_u64(
+    slice: &[u8],
+    offset: usize,
+) -> u64 {
+    u64::from_le_bytes(slice[offset..(offset + 8)].try_into().unwrap())
+}
+
+/// Incremental RapidHash, with the total length known in advance.
+#[derive(Clone)]
+struct RapidHasher {
+    len: u64,
+    seen: u64,
+    /// How many bytes have gone through the main 96-byte block loop.
+    hashed: u64,
+    seed: u64,
+    see1: u64,
+    see2: u64,
+    buf: [u8; 96],
+    buf_len: usize,
+    last16: [u8; 16],
+}
+
+impl RapidHasher {
+    fn new(len: u64) -> Self {
+        let seed = rapidhash::RAPID_SEED;
+        let seed =
+            seed ^ rapid_mix(seed ^ RAPID_SECRET[0], RAPID_SECRET[1]) ^ len;
+        Self {
+            len,
+            seen: 0,
+            hashed: 0,
+            seed,
+            see1: seed,
+            see2: seed,
+            buf: [0; 96],
+            buf_len: 0,
+            last16: [0; 16],
+        }
+    }
+
+    /// The number of bytes covered by the main 96-byte block loop.
+    fn blocks_end(&self) -> u64 {
+        if self.len < 96 {
+            0
+        } else {
+            self.len / 96 * 96
+        }
+    }
+
+    fn process_96(
+        &mut self,
+        block: &[u8],
+    ) {
+        self.process_48(&block[..48]);
+        self.process_48(&block[48..]);
+    }
+
+    fn process_48(
+        &mut self,
+        block: &[u8],
+    ) {
+        self.seed = rapid_mix(
+            read_u64(block, 0) ^ RAPID_SECRET[0],
+            read_u64(block, 8) ^ self.seed,
+        );
+        self.see1 = rapid_mix(
+            read_u64(block, 16) ^ RAPID_SECRET[1],
+            read_u64(block, 24) ^ self.see1,
+        );
+        self.see2 = rapid_mix(
+            read_u64(block, 32) ^ RAPID_SECRET[2],
+            read_u64(block, 40) ^ self.see2,
+        );
+    }
+
+    fn update(
+        &mut self,
+        mut data: &[u8],
+    ) {
+        debug_assert!(self.seen + data.len() as u64 <= self.len);
+        self.seen += data.len() as u64;
+        if data.len() >= 16 {
+            self.last16.copy_from_slice(&data[(data.len() - 16)..]);
+        } else {
+            self.last16.copy_within(data.len().., 0);
+            self.last16[(16 - data.len())..].copy_from_slice(data);
+        }
+        let blocks_end = self.blocks_end();
+        while !data.is_empty() {
+            if self.hashed < blocks_end && self.buf_len == 0 {
+                // process whole blocks directly, without copying
+                let n_blocks = ((blocks_end - self.hashed) / 96)
+                    .min(data.len() as u64 / 96)
+                    as usize;
+                if n_blocks > 0 {
+                    let (blocks, rest) = data.split_at(n_blocks * 96);
+                    for block in blocks.chunks_exact(96) {
+                        self.process_96(block);
+                    }
+                    self.hashed += blocks.len() as u64;
+                    data = rest;
+                    continue;
+                }
+            }
+            let n = (96 - self.buf_len).min(data.len());
+            self.buf[self.buf_len..(self.buf_len + n)]
+                .copy_from_slice(&data[..n]);
+            self.buf_len += n;
+            data = &data[n..];
+            if self.hashed < blocks_end && self.buf_len == 96 {
+                let block = self.buf;
+                self.process_96(&block);
+                self.hashed += 96;
+                self.buf_len = 0;
+            }
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        debug_assert_eq!(self.seen, self.len);
+        if self.len < 96 {
+            return checksum_data(&self.buf[..self.buf_len]);
+        }
+        let mut seed = self.seed;
+        let mut see1 = self.see1;
+        let mut see2 = self.see2;
+        let mut slice = &self.buf[..self.buf_len];
+        if slice.len() >= 48 {
+            seed = rapid_mix(
+                read_u64(slice, 0) ^ RAPID_SECRET[0],
+                read_u64(slice, 8) ^ seed,
+            );
+            see1 = rapid_mix(
+                read_u64(slice, 16) ^ RAPID_SECRET[1],
+                read_u64(slice, 24) ^ see1,
+            );
+            see2 = rapid_mix(
+                read_u64(slice, 32) ^ RAPID_SECRET[2],
+                read_u64(slice, 40) ^ see2,
+            );
+            slice = &slice[48..];
+        }
+        seed ^= see1 ^ see2;
+        if slice.len() > 16 {
+            seed = rapid_mix(
+                read_u64(slice, 0) ^ RAPID_SECRET[2],
+                read_u64(slice, 8) ^ seed ^ RAPID_SECRET[1],
+            );
+            if slice.len() > 32 {
+                seed = rapid_mix(
+                    read_u64(slice, 16) ^ RAPID_SECRET[2],
+                    read_u64(slice, 24) ^ seed,
+                );
+            }
+        }
+        let a = read_u64(&self.last16, 0) ^ RAPID_SECRET[1];
+        let b = read_u64(&self.last16, 8) ^ seed;
+        let (a, b) = rapid_mum(a, b);
+        rapid_mix(a ^ RAPID_SECRET[0] ^ self.len, b ^ RAPID_SECRET[1])
+    }
+}
+
+/// Incremental version of [`checksum_data`] (or [`ChecksumKind::checksum`]).
+///
+/// RapidHash mixes the total length of the input into its initial state,
+/// so it must be known in advance. Feeding exactly `len` bytes (split
+/// into any number of `update` calls) produces the same value as
+/// `checksum_data` over the concatenated bytes. Feeding any other number of
+/// bytes gives a meaningless value (and panics in debug builds).
+#[derive(Clone)]
+pub struct DataHasher {
+    remaining: u64,
+    state: HasherState,
+}
+
+#[derive(Clone)]
+enum HasherState {
+    RapidHash(RapidHasher),
+    #[cfg(feature = "xxh3")]
+    Xxh3(Box<xxhash_rust::xxh3::Xxh3>),
+    #[cfg(feature = "crc32c")]
+    Crc32c(u32),
+    #[cfg(feature = "blake3")]
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl DataHasher {
+    /// Start hashing `len` bytes with RapidHash.
+    pub fn new(len: u64) -> Self {
+        Self::with_kind(ChecksumKind::RapidHash, len)
+    }
+
+    /// Start hashing `len` bytes with the given algorithm.
+    pub fn with_kind(
+        kind: ChecksumKind,
+        len: u64,
+    ) -> Self {
+        let state = match kind {
+            ChecksumKind::RapidHash => {
+                HasherState::RapidHash(RapidHasher::new(len))
+            }
+            #[cfg(feature = "xxh3")]
+            ChecksumKind::Xxh3 => {
+                HasherState::Xxh3(Box::new(xxhash_rust::xxh3::Xxh3::new()))
+            }
+            #[cfg(feature = "crc32c")]
+            ChecksumKind::Crc32c => HasherState::Crc32c(0),
+            #[cfg(feature = "blake3")]
+            ChecksumKind::Blake3 => {
+                HasherState::Blake3(Box::new(blake3::Hasher::new()))
+            }
+        };
+        Self {
+            remaining: len,
+            state,
+        }
+    }
+
+    /// Start hashing data of unknown length with the given algorithm.
+    ///
+    /// Returns `None` if the algorithm needs the length in advance (see
+    /// [`ChecksumKind::needs_len`]).
+    pub fn without_len(kind: ChecksumKind) -> Option<Self> {
+        if kind.needs_len() {
+            return None;
+        }
+        Some(Self::with_kind(kind, u64::MAX))
+    }
+
+    /// The number of bytes still expected.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    pub fn update(
+        &mut self,
+        data: &[u8],
+    ) {
+        debug_assert!(data.len() as u64 <= self.remaining);
+        self.remaining -= data.len() as u64;
+        match &mut self.state {
+            HasherState::RapidHash(h) => h.update(data),
+            #[cfg(feature = "xxh3")]
+            HasherState::Xxh3(h) => h.update(data),
+            #[cfg(feature = "crc32c")]
+            HasherState::Crc32c(crc) => {
+                *crc = crc32c::crc32c_append(*crc, data);
+            }
+            #[cfg(feature = "blake3")]
+            HasherState::Blake3(h) => {
+                h.update(data);
+            }
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        match &self.state {
+            HasherState::RapidHash(h) => h.finish(),
+            #[cfg(feature = "xxh3")]
+            HasherState::Xxh3(h) => h.digest(),
+            #[cfg(feature = "crc32c")]
+            HasherState::Crc32c(crc) => *crc as u64,
+            #[cfg(feature = "blake3")]
+            HasherState::Blake3(h) => blake3_u64(&h.finalize()),
+        }
+    }
+}
+
+/// Reader that computes the [`checksum_data`] of everything read through
+/// it.
+///
+/// Like [`DataHasher`], it needs the total length in advance. It reads at
+/// most `len` bytes from the inner reader.
+pub struct ChecksummingReader<R> {
+    inner: R,
+    hasher: DataHasher,
+}
+
+impl<R> ChecksummingReader<R> {
+    pub fn new(
+        inner: R,
+        len: u64,
+    ) -> Self {
+        Self::with_kind(inner, ChecksumKind::RapidHash, len)
+    }
+
+    pub fn with_kind(
+        inner: R,
+        kind: ChecksumKind,
+        len: u64,
+    ) -> Self {
+        Self {
+            inner,
+            hasher: DataHasher::with_kind(kind, len),
+        }
+    }
+
+    /// The number of bytes still to be read.
+    pub fn remaining(&self) -> u64 {
+        self.hasher.remaining()
+    }
+
+    /// The checksum, once all `len` bytes have been read.
+    pub fn finish(&self) -> u64 {
+        self.hasher.finish()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for ChecksummingReader<R> {
+    fn read(
+        &mut self,
+        buf: &mut [u8],
+    ) -> std::io::Result<usize> {
+        let max = (buf.len() as u64).min(self.remaining()) as usize;
+        let n = self.inner.read(&mut buf[..max])?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Writer that computes the [`checksum_data`] of everything written
+/// through it.
+///
+/// Like [`DataHasher`], it needs the total length in advance, unless the
+/// algorithm allows [`without_len`](Self::without_len). Writing more than
+/// `len` bytes fails.
+pub struct ChecksummingWriter<W> {
+    inner: W,
+    hasher: DataHasher,
+}
+
+impl<W> ChecksummingWriter<W> {
+    pub fn new(
+        inner: W,
+        len: u64,
+    ) -> Self {
+        Self::with_kind(inner, ChecksumKind::RapidHash, len)
+    }
+
+    pub fn with_kind(
+        inner: W,
+        kind: ChecksumKind,
+        len: u64,
+    ) -> Self {
+        Self {
+            inner,
+            hasher: DataHasher::with_kind(kind, len),
+        }
+    }
+
+    /// Hash data of unknown length, if the algorithm allows it (see
+    /// [`DataHasher::without_len`]).
+    pub fn without_len(
+        inner: W,
+        kind: ChecksumKind,
+    ) -> Option<Self> {
+        Some(Self {
+            inner,
+            hasher: DataHasher::without_len(kind)?,
+        })
+    }
+
+    /// The number of bytes still to be written.
+    pub fn remaining(&self) -> u64 {
+        self.hasher.remaining()
+    }
+
+    /// The checksum, once all `len` bytes have been written.
+    pub fn finish(&self) -> u64 {
+        self.hasher.finish()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ChecksummingWriter<W> {
+    fn write(
+        &mut self,
+        buf: &[u8],
+    ) -> std::io::Result<usize> {
+        if buf.len() as u64 > self.remaining() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "more data than the declared length",
+            ));
+        }
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_data(len: usize) -> Vec<u8> {
+        let mut rng = 0x1234u64;
+        (0..len)
+            .map(|_| {
+                rng = rng
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (rng >> 56) as u8
+            })
+            .collect()
+    }
+
+    /// Feeding the data in chunks of any size must give the same value as
+    /// hashing it in one go, whatever the block boundaries.
+    #[test]
+    fn data_hasher_matches_checksum_data() {
+        let data = test_data(1000);
+        let lens = (0..=300).chain([383, 384, 385, 480, 500, 959, 960, 1000]);
+        for len in lens {
+            let data = &data[..len];
+            let expected = checksum_data(data);
+            for chunk_size in [1, 7, 16, 17, 47, 48, 49, 95, 96, 97, 200] {
+                let mut hasher = DataHasher::new(len as u64);
+                for chunk in data.chunks(chunk_size) {
+                    hasher.update(chunk);
+                }
+                assert_eq!(hasher.remaining(), 0);
+                assert_eq!(
+                    hasher.finish(),
+                    expected,
+                    "len {len}, chunks of {chunk_size}"
+                );
+            }
+        }
+    }
+
+    /// Chunks of varying sizes, so that they end at every offset within a
+    /// block.
+    #[test]
+    fn data_hasher_uneven_chunks() {
+        let data = test_data(5000);
+        let mut hasher = DataHasher::new(data.len() as u64);
+        let mut rest = &data[..];
+        let mut chunk_size = 0;
+        while !rest.is_empty() {
+            chunk_size = chunk_size % 100 + 1;
+            let (chunk, next) = rest.split_at(chunk_size.min(rest.len()));
+            hasher.update(chunk);
+            rest = next;
+        }
+        assert_eq!(hasher.finish(), checksum_data(&data));
+    }
+
+    #[test]
+    fn kinds_match_one_shot() {
+        let data = test_data(1000);
+        for &kind in ChecksumKind::ALL {
+            for len in [0, 1, 16, 17, 96, 97, 1000] {
+                let data = &data[..len];
+                let mut hasher = DataHasher::with_kind(kind, len as u64);
+                for chunk in data.chunks(47) {
+                    hasher.update(chunk);
+                }
+                assert_eq!(hasher.finish(), kind.checksum(data), "{kind}");
+                if let Some(mut hasher) = DataHasher::without_len(kind) {
+                    hasher.update(data);
+                    assert_eq!(hasher.finish(), kind.checksum(data), "{kind}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn checksumming_reader_and_writer() {
+        let data = test_data(1000);
+        let expected = checksum_data(&data);
+        let mut reader = ChecksummingReader::new(&data[..], 1000);
+        std::io::copy(&mut reader, &mut std::io::sink()).unwrap();
+        assert_eq!(reader.finish(), expected);
+        let mut writer = ChecksummingWriter::new(vec![], 1000);
+        writer.write_all(&data).unwrap();
+        assert_eq!(writer.finish(), expected);
+        assert!(writer.write_all(&[0]).is_err());
+        assert_eq!(writer.into_inner(), data);
+    }
+}
@@ -0,0 +1,512 @@
+//! Numeric conversion of vertex data between formats.
+//!
+//! Conversions are done component-wise, so both formats must have the
+//! same number of components. Floating-point and normalized formats can be
+//! converted between each other (values are clamped to the range of
+//! normalized targets). Integer formats can be converted between each
+//! other (values are clamped to the range of the target).
+//!
+//! For colors, [`convert_color_data`] can additionally apply the sRGB
+//! transfer function, so that float data is linear and normalized data is
+//! sRGB-encoded.
+//!
+//! Float16 formats can only be converted with the `f16` feature.
+
+use crate::descriptor::VertexFormat;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scalar {
+    F16,
+    F32,
+    F64,
+    Unorm8,
+    Snorm8,
+    Unorm16,
+    Snorm16,
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    Plain(Scalar, usize),
+    /// Unorm8x4 with the R and B components swapped.
+    Bgra8,
+    /// 10/10/10/2 bits, with R in the least-significant bits.
+    Packed1010102,
+}
+
+impl Scalar {
+    const fn size(self) -> usize {
+        match self {
+            Scalar::Unorm8 | Scalar::Snorm8 | Scalar::U8 | Scalar::I8 => 1,
+            Scalar::F16
+            | Scalar::Unorm16
+            | Scalar::Snorm16
+            | Scalar::U16
+            | Scalar::I16 => 2,
+            Scalar::F32 | Scalar::U32 | Scalar::I32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    const fn is_float(self) -> bool {
+        matches!(self, Scalar::F16 | Scalar::F32 | Scalar::F64)
+    }
+
+    const fn is_signed(self) -> bool {
+        matches!(
+            self,
+            Scalar::F16
+                | Scalar::F32
+                | Scalar::F64
+                | Scalar::Snorm8
+                | Scalar::Snorm16
+                | Scalar::I8
+                | Scalar::I16
+                | Scalar::I32
+        )
+    }
+
+    const fn is_integer(self) -> bool {
+        matches!(
+            self,
+            Scalar::U8
+                | Scalar::I8
+                | Scalar::U16
+                | Scalar::I16
+                | Scalar::U32
+                | Scalar::I32
+        )
+    }
+
+    fn read(
+        self,
+        b: &[u8],
+    ) -> f64 {
+        match self {
+            #[cfg(feature = "f16")]
+            Scalar::F16 => f64::from(half::f16::from_le_bytes([b[0], b[1]])),
+            #[cfg(not(feature = "f16"))]
+            Scalar::F16 => unreachable!(),
+            Scalar::F32 => f32::from_le_bytes(b[..4].try_into().unwrap()) as f64,
+            Scalar::F64 => f64::from_le_bytes(b[..8].try_into().unwrap()),
+            Scalar::Unorm8 => b[0] as f64 / u8::MAX as f64,
+            Scalar::Snorm8 => (b[0] as i8 as f64 / i8::MAX as f64).max(-1.0),
+            Scalar::Unorm16 => {
+                u16::from_le_bytes([b[0], b[1]]) as f64 / u16::MAX as f64
+            }
+            Scalar::Snorm16 => {
+                (i16::from_le_bytes([b[0], b[1]]) as f64 / i16::MAX as f64)
+                    .max(-1.0)
+            }
+            Scalar::U8 => b[0] as f64,
+            Scalar::I8 => b[0] as i8 as f64,
+            Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::U32 => u32::from_le_bytes(b[..4].try_into().unwrap()) as f64,
+            Scalar::I32 => i32::from_le_bytes(b[..4].try_into().unwrap()) as f64,
+        }
+    }
+
+    /// Range of the values that can be stored, `None` for floats.
+    const fn range(self) -> Option<(f64, f64)> {
+        match self {
+            Scalar::F16 | Scalar::F32 | Scalar::F64 => None,
+            Scalar::Unorm8 | Scalar::Unorm16 => Some((0.0, 1.0)),
+            Scalar::Snorm8 | Scalar::Snorm16 => Some((-1.0, 1.0)),
+            Scalar::U8 => Some((0.0, u8::MAX as f64)),
+            Scalar::I8 => Some((i8::MIN as f64, i8::MAX as f64)),
+            Scalar::U16 => Some((0.0, u16::MAX as f64)),
+            Scalar::I16 => Some((i16::MIN as f64, i16::MAX as f64)),
+            Scalar::U32 => Some((0.0, u32::MAX as f64)),
+            Scalar::I32 => Some((i32::MIN as f64, i32::MAX as f64)),
+        }
+    }
+
+    /// Append the value to `out`, rounding to nearest and clamping to the
+    /// representable range. NaN becomes zero for non-float targets.
+    fn write(
+        self,
+        v: f64,
+        out: &mut Vec<u8>,
+    ) {
+        // float to int `as` casts saturate, so only normalized formats need
+        // explicit clamping
+        match self {
+            #[cfg(feature = "f16")]
+            Scalar::F16 => out.extend_from_slice(
+                &half::f16::from_f64(v).to_le_bytes(),
+            ),
+            #[cfg(not(feature = "f16"))]
+            Scalar::F16 => unreachable!(),
+            Scalar::F32 => out.extend_from_slice(&(v as f32).to_le_bytes()),
+            Scalar::F64 => out.extend_from_slice(&v.to_le_bytes()),
+            Scalar::Unorm8 => out.push(unorm(v, u8::MAX as f64) as u8),
+            Scalar::Snorm8 => {
+                out.push(snorm(v, i8::MAX as f64) as i8 as u8);
+            }
+            Scalar::Unorm16 => out.extend_from_slice(
+                &(unorm(v, u16::MAX as f64) as u16).to_le_bytes(),
+            ),
+            Scalar::Snorm16 => out.extend_from_slice(
+                &(snorm(v, i16::MAX as f64) as i16).to_le_bytes(),
+            ),
+            Scalar::U8 => out.push(v.round() as u8),
+            Scalar::I8 => out.push(v.round() as i8 as u8),
+            Scalar::U16 => {
+                out.extend_from_slice(&(v.round() as u16).to_le_bytes())
+            }
+            Scalar::I16 => {
+                out.extend_from_slice(&(v.round() as i16).to_le_bytes())
+            }
+            Scalar::U32 => {
+                out.extend_from_slice(&(v.round() as u32).to_le_bytes())
+            }
+            Scalar::I32 => {
+                out.extend_from_slice(&(v.round() as i32).to_le_bytes())
+            }
+        }
+    }
+}
+
+fn unorm(
+    v: f64,
+    max: f64,
+) -> f64 {
+    (v.clamp(0.0, 1.0) * max).round()
+}
+
+fn snorm(
+    v: f64,
+    max: f64,
+) -> f64 {
+    (v.clamp(-1.0, 1.0) * max).round()
+}
+
+impl Layout {
+    const fn of(format: VertexFormat) -> Layout {
+        use Layout::Plain;
+        use VertexFormat as F;
+        match format {
+            F::Float16 => Plain(Scalar::F16, 1),
+            F::Float16x2 => Plain(Scalar::F16, 2),
+            F::Float16x4 => Plain(Scalar::F16, 4),
+            F::Float32 => Plain(Scalar::F32, 1),
+            F::Float32x2 => Plain(Scalar::F32, 2),
+            F::Float32x3 => Plain(Scalar::F32, 3),
+            F::Float32x4 => Plain(Scalar::F32, 4),
+            F::Float64 => Plain(Scalar::F64, 1),
+            F::Float64x2 => Plain(Scalar::F64, 2),
+            F::Float64x3 => Plain(Scalar::F64, 3),
+            F::Float64x4 => Plain(Scalar::F64, 4),
+            F::Sint8 => Plain(Scalar::I8, 1),
+            F::Sint8x2 => Plain(Scalar::I8, 2),
+            F::Sint8x4 => Plain(Scalar::I8, 4),
+            F::Sint16 => Plain(Scalar::I16, 1),
+            F::Sint16x2 => Plain(Scalar::I16, 2),
+            F::Sint16x4 => Plain(Scalar::I16, 4),
+            F::Sint32 => Plain(Scalar::I32, 1),
+            F::Sint32x2 => Plain(Scalar::I32, 2),
+            F::Sint32x3 => Plain(Scalar::I32, 3),
+            F::Sint32x4 => Plain(Scalar::I32, 4),
+            F::Snorm8 => Plain(Scalar::Snorm8, 1),
+            F::Snorm8x2 => Plain(Scalar::Snorm8, 2),
+            F::Snorm8x4 => Plain(Scalar::Snorm8, 4),
+            F::Snorm16 => Plain(Scalar::Snorm16, 1),
+            F::Snorm16x2 => Plain(Scalar::Snorm16, 2),
+            F::Snorm16x4 => Plain(Scalar::Snorm16, 4),
+            F::Uint8 => Plain(Scalar::U8, 1),
+            F::Uint8x2 => Plain(Scalar::U8, 2),
+            F::Uint8x4 => Plain(Scalar::U8, 4),
+            F::Uint16 => Plain(Scalar::U16, 1),
+            F::Uint16x2 => Plain(Scalar::U16, 2),
+            F::Uint16x4 => Plain(Scalar::U16, 4),
+            F::Uint32 => Plain(Scalar::U32, 1),
+            F::Uint32x2 => Plain(Scalar::U32, 2),
+            F::Uint32x3 => Plain(Scalar::U32, 3),
+            F::Uint32x4 => Plain(Scalar::U32, 4),
+            F::Unorm8 => Plain(Scalar::Unorm8, 1),
+            F::Unorm8x2 => Plain(Scalar::Unorm8, 2),
+            F::Unorm8x4 => Plain(Scalar::Unorm8, 4),
+            F::Unorm8x4Bgra => Layout::Bgra8,
+            F::Unorm16 => Plain(Scalar::Unorm16, 1),
+            F::Unorm16x2 => Plain(Scalar::Unorm16, 2),
+            F::Unorm16x4 => Plain(Scalar::Unorm16, 4),
+            F::Unorm10_10_10_2 => Layout::Packed1010102,
+        }
+    }
+
+    /// Float16 values can only be converted with the `f16` feature.
+    const fn is_supported(self) -> bool {
+        cfg!(feature = "f16") || !matches!(self, Layout::Plain(Scalar::F16, _))
+    }
+
+    const fn n_components(self) -> usize {
+        match self {
+            Layout::Plain(_, n) => n,
+            Layout::Bgra8 | Layout::Packed1010102 => 4,
+        }
+    }
+
+    const fn is_integer(self) -> bool {
+        match self {
+            Layout::Plain(scalar, _) => scalar.is_integer(),
+            Layout::Bgra8 | Layout::Packed1010102 => false,
+        }
+    }
+
+    const fn is_float(self) -> bool {
+        match self {
+            Layout::Plain(scalar, _) => scalar.is_float(),
+            Layout::Bgra8 | Layout::Packed1010102 => false,
+        }
+    }
+
+    const fn range(self) -> Option<(f64, f64)> {
+        match self {
+            Layout::Plain(scalar, _) => scalar.range(),
+            Layout::Bgra8 | Layout::Packed1010102 => Some((0.0, 1.0)),
+        }
+    }
+
+    fn read(
+        self,
+        b: &[u8],
+        out: &mut [f64; 4],
+    ) {
+        match self {
+            Layout::Plain(scalar, n) => {
+                let size = scalar.size();
+                for (i, v) in out.iter_mut().take(n).enumerate() {
+                    *v = scalar.read(&b[i * size..]);
+                }
+            }
+            Layout::Bgra8 => {
+                for (v, byte) in out.iter_mut().zip([b[2], b[1], b[0], b[3]]) {
+                    *v = byte as f64 / u8::MAX as f64;
+                }
+            }
+            Layout::Packed1010102 => {
+                let bits = u32::from_le_bytes(b[..4].try_into().unwrap());
+                out[0] = (bits & 0x3ff) as f64 / 1023.0;
+                out[1] = ((bits >> 10) & 0x3ff) as f64 / 1023.0;
+                out[2] = ((bits >> 20) & 0x3ff) as f64 / 1023.0;
+                out[3] = (bits >> 30) as f64 / 3.0;
+            }
+        }
+    }
+
+    fn write(
+        self,
+        v: &[f64; 4],
+        out: &mut Vec<u8>,
+    ) {
+        match self {
+            Layout::Plain(scalar, n) => {
+                for v in v.iter().take(n) {
+                    scalar.write(*v, out);
+                }
+            }
+            Layout::Bgra8 => {
+                for i in [2, 1, 0, 3] {
+                    out.push(unorm(v[i], u8::MAX as f64) as u8);
+                }
+            }
+            Layout::Packed1010102 => {
+                let bits = unorm(v[0], 1023.0) as u32
+                    | (unorm(v[1], 1023.0) as u32) << 10
+                    | (unorm(v[2], 1023.0) as u32) << 20
+                    | (unorm(v[3], 3.0) as u32) << 30;
+                out.extend_from_slice(&bits.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// Check if data can be converted from one format to another.
+pub fn can_convert(
+    from: VertexFormat,
+    to: VertexFormat,
+) -> bool {
+    let (from, to) = (Layout::of(from), Layout::of(to));
+    from.is_supported()
+        && to.is_supported()
+        && from.n_components() == to.n_components()
+        && from.is_integer() == to.is_integer()
+}
+
+/// Range of the values that the components of a format can hold
+/// (normalized formats are scaled to 0.0..=1.0 or -1.0..=1.0).
+///
+/// Returns `None` for float formats, where values that are too large
+/// become infinite instead.
+pub fn value_range(format: VertexFormat) -> Option<(f64, f64)> {
+    Layout::of(format).range()
+}
+
+/// Count the component values that are clamped when converting data from
+/// one format to another, because they are outside of the [`value_range`]
+/// of the target format (NaN included).
+///
+/// Returns 0 if the conversion is not supported.
+pub fn count_clamped(
+    from: VertexFormat,
+    to: VertexFormat,
+    data: &[u8],
+) -> usize {
+    if !can_convert(from, to) {
+        return 0;
+    }
+    let Some((min, max)) = value_range(to) else {
+        return 0;
+    };
+    let layout = Layout::of(from);
+    let mut values = [0.0; 4];
+    let mut n = 0;
+    for element in data.chunks_exact(from.size()) {
+        layout.read(element, &mut values);
+        n += values
+            .iter()
+            .take(layout.n_components())
+            .filter(|v| !(min..=max).contains(*v))
+            .count();
+    }
+    n
+}
+
+/// Convert vertex data from one format to another, appending the result
+/// to `out`.
+///
+/// Returns `false` (without writing anything) if the conversion is not
+/// supported.
+pub fn convert_vertex_data(
+    from: VertexFormat,
+    to: VertexFormat,
+    data: &[u8],
+    out: &mut Vec<u8>,
+) -> bool {
+    convert_color_data(from, to, false, data, out)
+}
+
+/// Like [`convert_vertex_data`], optionally applying the sRGB transfer
+/// function.
+///
+/// If `srgb` is true, float data is treated as linear and normalized data
+/// as sRGB-encoded: converting from float encodes the values, converting to
+/// float decodes them. Only the first three components are affected; alpha
+/// is always linear.
+pub fn convert_color_data(
+    from: VertexFormat,
+    to: VertexFormat,
+    srgb: bool,
+    data: &[u8],
+    out: &mut Vec<u8>,
+) -> bool {
+    if !can_convert(from, to) {
+        return false;
+    }
+    if from == to {
+        out.extend_from_slice(data);
+        return true;
+    }
+    let (lfrom, lto) = (Layout::of(from), Layout::of(to));
+    let transfer: Option<fn(f64) -> f64> = match (lfrom.is_float(), lto.is_float()) {
+        _ if !srgb => None,
+        (true, false) => Some(linear_to_srgb),
+        (false, true) => Some(srgb_to_linear),
+        _ => None,
+    };
+    out.reserve(data.len() / from.size() * to.size());
+    let mut values = [0.0; 4];
+    for element in data.chunks_exact(from.size()) {
+        lfrom.read(element, &mut values);
+        if let Some(transfer) = transfer {
+            for v in values.iter_mut().take(3) {
+                *v = transfer(*v);
+            }
+        }
+        lto.write(&values, out);
+    }
+    true
+}
+
+/// Negate the selected components of vertex data (e.g. to flip normals).
+///
+/// Values are negated exactly, except for the minimum of signed normalized
+/// and integer formats, which saturates. Returns `None` for formats that
+/// cannot hold negative values (unsigned, unsigned normalized and packed
+/// formats).
+pub fn negate_components(
+    format: VertexFormat,
+    data: &[u8],
+    components: [bool; 4],
+) -> Option<Vec<u8>> {
+    let layout @ Layout::Plain(scalar, _) = Layout::of(format) else {
+        return None;
+    };
+    if !scalar.is_signed() || !layout.is_supported() {
+        return None;
+    }
+    let mut out = Vec::with_capacity(data.len());
+    let mut values = [0.0; 4];
+    for element in data.chunks_exact(format.size()) {
+        layout.read(element, &mut values);
+        for (v, negate) in values.iter_mut().zip(components) {
+            if negate {
+                *v = -*v;
+            }
+        }
+        layout.write(&values, &mut out);
+    }
+    Some(out)
+}
+
+/// Lazily decode vertex data as floats, keeping the first `N` components.
+///
+/// Float formats are converted as-is, normalized formats are scaled to
+/// their range (0.0..=1.0 or -1.0..=1.0). Returns `None` for integer
+/// formats, and for formats with fewer than `N` components.
+pub fn iter_f32<const N: usize>(
+    format: VertexFormat,
+    data: &[u8],
+) -> Option<impl Iterator<Item = [f32; N]> + '_> {
+    let layout = Layout::of(format);
+    if layout.is_integer() || !layout.is_supported() || layout.n_components() < N
+    {
+        return None;
+    }
+    Some(data.chunks_exact(format.size()).map(move |element| {
+        let mut values = [0.0; 4];
+        layout.read(element, &mut values);
+        std::array::from_fn(|i| values[i] as f32)
+    }))
+}
+
+/// Apply the sRGB transfer function (encode a linear value).
+///
+/// Values outside of 0.0..=1.0 are clamped.
+pub fn linear_to_srgb(v: f64) -> f64 {
+    let v = v.clamp(0.0, 1.0);
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Remove the sRGB transfer function (decode to a linear value).
+///
+/// Values outside of 0.0..=1.0 are clamped.
+pub fn srgb_to_linear(v: f64) -> f64 {
+    let v = v.clamp(0.0, 1.0);
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
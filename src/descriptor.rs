@@ -1,29 +1,133 @@
 use crate::HashMap;
 
-#[derive(Debug, Clone, bitcode::Encode, bitcode::Decode)]
+#[derive(Debug, Clone, PartialEq, bitcode::Encode, bitcode::Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IyesMeshDescriptor {
     pub n_vertices: u32,
     pub user_data_len: u32,
     pub meshes: Vec<MeshInfo>,
     pub indices: Option<IndicesInfo>,
+    #[cfg_attr(feature = "serde", serde(with = "usage_map"))]
     pub attributes: HashMap<VertexUsage, VertexFormat>,
+    /// Attributes whose data is not stored as plain values of their format.
+    #[cfg_attr(feature = "serde", serde(with = "usage_map"))]
+    pub attribute_encodings: HashMap<VertexUsage, AttributeEncoding>,
+    /// Color space of the values of color attributes.
+    ///
+    /// Attributes not in the map have an unspecified color space.
+    #[cfg_attr(feature = "serde", serde(with = "usage_map"))]
+    pub color_spaces: HashMap<VertexUsage, ColorSpace>,
+    /// Number of elements in each instance attribute buffer.
+    pub n_instances: u32,
+    /// Per-instance attributes (e.g. transforms), stored after the vertex
+    /// attributes. Each mesh uses a range of the instances (see
+    /// [`MeshInfo::first_instance`]).
+    #[cfg_attr(feature = "serde", serde(with = "usage_map"))]
+    pub instance_attributes: HashMap<VertexUsage, VertexFormat>,
+    /// Checksum of the uncompressed user data, if any.
+    ///
+    /// Allows the user data to be verified without hashing the whole
+    /// payload, and files to be compared by their user data without
+    /// decompressing anything.
+    pub user_data_checksum: Option<u64>,
+    /// Nonce the user data is encrypted with, if it is encrypted.
+    ///
+    /// The user data is then encrypted with ChaCha20-Poly1305, with the
+    /// 16-byte authentication tag appended (and counted in
+    /// `user_data_len`). The user data checksum covers the encrypted bytes.
+    pub user_data_nonce: Option<[u8; 12]>,
+    /// The file ends with an Ed25519 signature of everything before it
+    /// ([`crate::SIGNATURE_LEN`] bytes, after the compressed data).
+    pub signed: bool,
+    /// Algorithm of the data and user data checksums, as a
+    /// [`ChecksumKind::id`](crate::checksum::ChecksumKind::id).
+    pub checksum_kind: u8,
 }
 
-#[derive(Default, Debug, Clone, Copy, bitcode::Encode, bitcode::Decode)]
+/// How the values of a color attribute are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, bitcode::Encode, bitcode::Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorSpace {
+    /// Linear values.
+    Linear,
+    /// Values encoded with the sRGB transfer function (except alpha, which
+    /// is always linear).
+    Srgb,
+}
+
+/// Special encoding of attribute data (see [`crate::mesh::encode`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, bitcode::Encode, bitcode::Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AttributeEncoding {
+    /// Octahedral-encoded unit vectors, stored as Snorm16x2.
+    Octahedral,
+    /// Octahedral-encoded unit vectors with handedness, stored as Snorm16x2.
+    OctahedralSigned,
+}
+
+impl AttributeEncoding {
+    /// The format of the data before encoding.
+    pub const fn decoded_format(self) -> VertexFormat {
+        match self {
+            AttributeEncoding::Octahedral => VertexFormat::Float32x3,
+            AttributeEncoding::OctahedralSigned => VertexFormat::Float32x4,
+        }
+    }
+
+    /// The format of the encoded data.
+    pub const fn stored_format(self) -> VertexFormat {
+        match self {
+            AttributeEncoding::Octahedral
+            | AttributeEncoding::OctahedralSigned => VertexFormat::Snorm16x2,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, bitcode::Encode, bitcode::Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MeshInfo {
     pub first_index: u32,
     pub index_count: u32,
     pub first_vertex: u32,
     pub vertex_count: u32,
+    /// If the positions of this mesh are quantized, how to recover the
+    /// original values.
+    pub position_transform: Option<PositionTransform>,
+    pub first_instance: u32,
+    pub instance_count: u32,
+}
+
+/// Dequantization transform for normalized position data.
+///
+/// The original position is `offset + scale * stored`, where `stored` is
+/// the normalized value (in the 0.0..=1.0 range) of each component.
+#[derive(Default, Debug, Clone, Copy, PartialEq, bitcode::Encode, bitcode::Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PositionTransform {
+    pub offset: [f32; 3],
+    pub scale: [f32; 3],
 }
 
-#[derive(Debug, Clone, Copy, bitcode::Encode, bitcode::Decode)]
+impl PositionTransform {
+    pub fn apply(&self, stored: [f32; 3]) -> [f32; 3] {
+        std::array::from_fn(|i| self.offset[i] + self.scale[i] * stored[i])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, bitcode::Encode, bitcode::Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IndicesInfo {
     pub n_indices: u32,
     pub format: IndexFormat,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, bitcode::Encode, bitcode::Decode)]
+/// What a vertex attribute is used for.
+///
+/// The order of the variants is the order of the attributes in the payload
+/// (see [`IyesMeshDescriptor::attribute_order`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(bitcode::Encode, bitcode::Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VertexUsage {
     Custom(u32),
     Position,
@@ -34,15 +138,31 @@ pub enum VertexUsage {
     JointIndex,
     JointWeight,
     Color,
+    // New variants must be added at the end, to keep the encoding of the
+    // existing ones (bitcode encodes the variant index). Bitcode also packs
+    // the index more tightly for up to 16 variants, so version 1 files are
+    // decoded with `v1::VertexUsage`.
+    Uv2,
+    Uv3,
+    Uv4,
+    Uv5,
+    Uv6,
+    Uv7,
+    /// Second set of joint indices, for more than 4 influences per vertex.
+    JointIndex1,
+    /// Weights for [`VertexUsage::JointIndex1`].
+    JointWeight1,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, bitcode::Encode, bitcode::Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IndexFormat {
     U16,
     U32,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, bitcode::Encode, bitcode::Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VertexFormat {
     Float16,
     Float32,
@@ -146,37 +266,327 @@ impl VertexFormat {
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown vertex usage: {0:?}")]
+pub struct ParseVertexUsageError(pub String);
+
+impl VertexUsage {
+    /// The texture coordinate sets, in order.
+    pub const UVS: &[VertexUsage] = &[
+        VertexUsage::Uv0,
+        VertexUsage::Uv1,
+        VertexUsage::Uv2,
+        VertexUsage::Uv3,
+        VertexUsage::Uv4,
+        VertexUsage::Uv5,
+        VertexUsage::Uv6,
+        VertexUsage::Uv7,
+    ];
+
+    /// The texture coordinate set with the given index (e.g. glTF's
+    /// `TEXCOORD_n`), if there is a usage for it.
+    pub fn uv(index: usize) -> Option<VertexUsage> {
+        Self::UVS.get(index).copied()
+    }
+
+    /// The index of a texture coordinate set.
+    pub fn uv_index(self) -> Option<usize> {
+        Self::UVS.iter().position(|usage| *usage == self)
+    }
+
+    /// All the usages with special meaning (everything except `Custom`).
+    pub const NAMED: &[VertexUsage] = &[
+        VertexUsage::Position,
+        VertexUsage::Normal,
+        VertexUsage::Tangent,
+        VertexUsage::Uv0,
+        VertexUsage::Uv1,
+        VertexUsage::Uv2,
+        VertexUsage::Uv3,
+        VertexUsage::Uv4,
+        VertexUsage::Uv5,
+        VertexUsage::Uv6,
+        VertexUsage::Uv7,
+        VertexUsage::JointIndex,
+        VertexUsage::JointWeight,
+        VertexUsage::JointIndex1,
+        VertexUsage::JointWeight1,
+        VertexUsage::Color,
+    ];
+}
+
+/// Formats as the variant name, or `custom:<id>` for custom usages.
+impl std::fmt::Display for VertexUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VertexUsage::Custom(id) => write!(f, "custom:{}", id),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// Parses the variant name (case-insensitive) or `custom:<id>`.
+impl std::str::FromStr for VertexUsage {
+    type Err = ParseVertexUsageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseVertexUsageError(s.to_owned());
+        if let Some((prefix, id)) = s.split_once(':') {
+            if !prefix.eq_ignore_ascii_case("custom") {
+                return Err(err());
+            }
+            return id.parse().map(VertexUsage::Custom).map_err(|_| err());
+        }
+        VertexUsage::NAMED
+            .iter()
+            .find(|usage| usage.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(err)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown color space: {0:?}")]
+pub struct ParseColorSpaceError(pub String);
+
+/// Parses `linear` or `srgb` (case-insensitive).
+impl std::str::FromStr for ColorSpace {
+    type Err = ParseColorSpaceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "linear" => Ok(ColorSpace::Linear),
+            "srgb" => Ok(ColorSpace::Srgb),
+            _ => Err(ParseColorSpaceError(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown vertex format: {0:?}")]
+pub struct ParseVertexFormatError(pub String);
+
+impl VertexFormat {
+    pub const ALL: &[VertexFormat] = &[
+        VertexFormat::Float16,
+        VertexFormat::Float32,
+        VertexFormat::Float64,
+        VertexFormat::Float16x2,
+        VertexFormat::Float16x4,
+        VertexFormat::Float32x2,
+        VertexFormat::Float32x3,
+        VertexFormat::Float32x4,
+        VertexFormat::Float64x2,
+        VertexFormat::Float64x3,
+        VertexFormat::Float64x4,
+        VertexFormat::Sint8,
+        VertexFormat::Sint8x2,
+        VertexFormat::Sint8x4,
+        VertexFormat::Sint16,
+        VertexFormat::Sint32,
+        VertexFormat::Sint16x2,
+        VertexFormat::Sint16x4,
+        VertexFormat::Sint32x2,
+        VertexFormat::Sint32x3,
+        VertexFormat::Sint32x4,
+        VertexFormat::Snorm8,
+        VertexFormat::Snorm8x2,
+        VertexFormat::Snorm8x4,
+        VertexFormat::Snorm16,
+        VertexFormat::Snorm16x2,
+        VertexFormat::Snorm16x4,
+        VertexFormat::Uint8,
+        VertexFormat::Uint8x2,
+        VertexFormat::Uint8x4,
+        VertexFormat::Uint16,
+        VertexFormat::Uint32,
+        VertexFormat::Uint16x2,
+        VertexFormat::Uint16x4,
+        VertexFormat::Uint32x2,
+        VertexFormat::Uint32x3,
+        VertexFormat::Uint32x4,
+        VertexFormat::Unorm8,
+        VertexFormat::Unorm8x2,
+        VertexFormat::Unorm8x4,
+        VertexFormat::Unorm8x4Bgra,
+        VertexFormat::Unorm16,
+        VertexFormat::Unorm10_10_10_2,
+        VertexFormat::Unorm16x2,
+        VertexFormat::Unorm16x4,
+    ];
+}
+
+/// Parses the variant name (case-insensitive).
+impl std::str::FromStr for VertexFormat {
+    type Err = ParseVertexFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        VertexFormat::ALL
+            .iter()
+            .find(|format| format!("{:?}", format).eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| ParseVertexFormatError(s.to_owned()))
+    }
+}
+
+/// Serde helper for maps keyed by [`VertexUsage`].
+///
+/// Keys are written as strings (see the `Display` impl), because formats
+/// like JSON do not support other kinds of keys.
+#[cfg(feature = "serde")]
+mod usage_map {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::VertexUsage;
+    use crate::HashMap;
+
+    pub fn serialize<V: Serialize, S: Serializer>(
+        map: &HashMap<VertexUsage, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(map.iter().map(|(k, v)| (k.to_string(), v)))
+    }
+
+    pub fn deserialize<'de, V: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<VertexUsage, V>, D::Error> {
+        let map = std::collections::HashMap::<String, V>::deserialize(
+            deserializer,
+        )?;
+        map.into_iter()
+            .map(|(k, v)| Ok((k.parse().map_err(D::Error::custom)?, v)))
+            .collect()
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum IyesMeshDescriptorParseError {
     #[error("Bitcode decode error: {0}")]
     Bitcode(#[from] bitcode::Error),
 }
 
+/// A mesh whose range of data does not fit in the buffers of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum MeshRangeError {
+    #[error("Vertices {start}..{end} exceed the {total} vertices of the file")]
+    Vertices { start: u64, end: u64, total: u32 },
+    #[error("Indices {start}..{end} exceed the {total} indices of the file")]
+    Indices { start: u64, end: u64, total: u32 },
+    #[error(
+        "Instances {start}..{end} exceed the {total} instances of the file"
+    )]
+    Instances { start: u64, end: u64, total: u32 },
+}
+
 impl IyesMeshDescriptor {
+    /// Check that the vertex, index and instance ranges of every mesh fit
+    /// in the totals declared by the descriptor.
+    ///
+    /// Returns the index of each mesh with a problem, and the problem.
+    pub fn check_mesh_ranges(&self) -> Vec<(usize, MeshRangeError)> {
+        let n_indices = self.indices.map_or(0, |info| info.n_indices);
+        let exceeds = |first: u32, count: u32, total: u32| {
+            let start = first as u64;
+            let end = start + count as u64;
+            (end > total as u64).then_some((start, end))
+        };
+        let mut r = vec![];
+        for (i, mesh) in self.meshes.iter().enumerate() {
+            let total = self.n_vertices;
+            if let Some((start, end)) =
+                exceeds(mesh.first_vertex, mesh.vertex_count, total)
+            {
+                r.push((i, MeshRangeError::Vertices { start, end, total }));
+            }
+            let total = n_indices;
+            if let Some((start, end)) =
+                exceeds(mesh.first_index, mesh.index_count, total)
+            {
+                r.push((i, MeshRangeError::Indices { start, end, total }));
+            }
+            let total = self.n_instances;
+            if let Some((start, end)) =
+                exceeds(mesh.first_instance, mesh.instance_count, total)
+            {
+                r.push((i, MeshRangeError::Instances { start, end, total }));
+            }
+        }
+        r
+    }
+
     pub const fn encoded_len() -> usize {
         std::mem::size_of::<Self>()
     }
 
     pub fn from_bytes(buf: &[u8]) -> Result<Self, IyesMeshDescriptorParseError> {
-        let descriptor = bitcode::decode(&buf)?;
+        let descriptor = bitcode::decode(buf)?;
         Ok(descriptor)
     }
 
+    /// Decode a descriptor written by the given version of the format.
+    ///
+    /// Older versions are converted to the current representation.
+    pub fn from_bytes_with_version(
+        version: u16,
+        buf: &[u8],
+    ) -> Result<Self, IyesMeshDescriptorParseError> {
+        match version {
+            1 => Ok(bitcode::decode::<v1::IyesMeshDescriptor>(buf)?.into()),
+            _ => Self::from_bytes(buf),
+        }
+    }
+
+    /// The order in which the vertex attributes are stored in the payload.
+    ///
+    /// Attributes are sorted by usage. Files of format version 1 used the
+    /// iteration order of `attributes` instead.
+    pub fn attribute_order(&self) -> Vec<VertexUsage> {
+        let mut r: Vec<_> = self.attributes.keys().copied().collect();
+        r.sort();
+        r
+    }
+
+    /// The order in which the instance attributes are stored in the
+    /// payload (sorted by usage).
+    pub fn instance_attribute_order(&self) -> Vec<VertexUsage> {
+        let mut r: Vec<_> = self.instance_attributes.keys().copied().collect();
+        r.sort();
+        r
+    }
+
     pub fn compute_vertex_buf_size(&self, buf: VertexUsage) -> Option<u32> {
-        self.attributes.get(&buf).map(|fmt| fmt.size() as u32 * self.n_vertices as u32)
+        self.attributes.get(&buf).map(|fmt| fmt.size() as u32 * self.n_vertices)
     }
 
     pub fn compute_index_buf_size(&self) -> Option<u32> {
-        self.indices.map(|info| info.format.size() as u32 * info.n_indices as u32)
+        self.indices.map(|info| info.format.size() as u32 * info.n_indices)
     }
 
     pub fn compute_all_vertex_buf_sizes(&self) -> u64 {
         self.attributes.values().map(|fmt| fmt.size() as u64 * self.n_vertices as u64).sum()
     }
 
+    pub fn compute_instance_buf_size(
+        &self,
+        buf: VertexUsage,
+    ) -> Option<u32> {
+        self.instance_attributes
+            .get(&buf)
+            .map(|fmt| fmt.size() as u32 * self.n_instances)
+    }
+
+    pub fn compute_all_instance_buf_sizes(&self) -> u64 {
+        self.instance_attributes
+            .values()
+            .map(|fmt| fmt.size() as u64 * self.n_instances as u64)
+            .sum()
+    }
+
     pub fn compute_all_buf_sizes(&self) -> u64 {
         self.compute_index_buf_size().unwrap_or(0) as u64
             + self.compute_all_vertex_buf_sizes()
+            + self.compute_all_instance_buf_sizes()
     }
 
     pub fn compute_total_raw_data_size(&self) -> u64 {
@@ -184,3 +594,174 @@ impl IyesMeshDescriptor {
             + self.user_data_len as u64
     }
 }
+
+/// Descriptor layout of format version 1.
+///
+/// Maps are decoded as lists, to rebuild them in the order they were
+/// encoded in: the attributes are stored in the payload in that order.
+mod v1 {
+    use crate::HashMap;
+
+    use super::{IndicesInfo, VertexFormat};
+
+    type UsageMap<V> = Vec<(VertexUsage, V)>;
+
+    #[derive(bitcode::Decode)]
+    pub struct IyesMeshDescriptor {
+        pub n_vertices: u32,
+        pub user_data_len: u32,
+        pub meshes: Vec<MeshInfo>,
+        pub indices: Option<IndicesInfo>,
+        pub attributes: UsageMap<VertexFormat>,
+    }
+
+    #[derive(bitcode::Decode)]
+    pub struct MeshInfo {
+        pub first_index: u32,
+        pub index_count: u32,
+        pub first_vertex: u32,
+        pub vertex_count: u32,
+    }
+
+    #[derive(Clone, Copy, bitcode::Decode)]
+    pub enum VertexUsage {
+        Custom(u32),
+        Position,
+        Normal,
+        Tangent,
+        Uv0,
+        Uv1,
+        JointIndex,
+        JointWeight,
+        Color,
+    }
+
+    impl From<IyesMeshDescriptor> for super::IyesMeshDescriptor {
+        fn from(old: IyesMeshDescriptor) -> Self {
+            Self {
+                n_vertices: old.n_vertices,
+                user_data_len: old.user_data_len,
+                meshes: old.meshes.into_iter().map(Into::into).collect(),
+                indices: old.indices,
+                attributes: convert(old.attributes),
+                attribute_encodings: Default::default(),
+                color_spaces: Default::default(),
+                n_instances: 0,
+                instance_attributes: Default::default(),
+                user_data_checksum: None,
+                user_data_nonce: None,
+                signed: false,
+                checksum_kind: 0,
+            }
+        }
+    }
+
+    impl From<MeshInfo> for super::MeshInfo {
+        fn from(old: MeshInfo) -> Self {
+            Self {
+                first_index: old.first_index,
+                index_count: old.index_count,
+                first_vertex: old.first_vertex,
+                vertex_count: old.vertex_count,
+                position_transform: None,
+                first_instance: 0,
+                instance_count: 0,
+            }
+        }
+    }
+
+    impl From<VertexUsage> for super::VertexUsage {
+        fn from(old: VertexUsage) -> Self {
+            match old {
+                VertexUsage::Custom(id) => Self::Custom(id),
+                VertexUsage::Position => Self::Position,
+                VertexUsage::Normal => Self::Normal,
+                VertexUsage::Tangent => Self::Tangent,
+                VertexUsage::Uv0 => Self::Uv0,
+                VertexUsage::Uv1 => Self::Uv1,
+                VertexUsage::JointIndex => Self::JointIndex,
+                VertexUsage::JointWeight => Self::JointWeight,
+                VertexUsage::Color => Self::Color,
+            }
+        }
+    }
+
+    fn convert<V>(map: UsageMap<V>) -> HashMap<super::VertexUsage, V> {
+        map.into_iter().map(|(usage, v)| (usage.into(), v)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::IyesMeshHeader;
+
+    /// The descriptor of `tests/data/v1.ima`, written by the first release.
+    #[test]
+    fn decode_v1_descriptor() {
+        let file = include_bytes!("../tests/data/v1.ima");
+        let header_len = IyesMeshHeader::encoded_len();
+        let header = IyesMeshHeader::from_bytes(&file[..header_len]).unwrap();
+        let buf = &file[header_len..][..header.descriptor_len as usize];
+        let d = IyesMeshDescriptor::from_bytes_with_version(1, buf).unwrap();
+        assert_eq!(d.n_vertices, 7);
+        assert_eq!(d.user_data_len, 12);
+        let meshes: Vec<_> = d
+            .meshes
+            .iter()
+            .map(|m| {
+                (m.first_index, m.index_count, m.first_vertex, m.vertex_count)
+            })
+            .collect();
+        assert_eq!(meshes, [(0, 6, 0, 4), (6, 3, 4, 3)]);
+        assert!(d.meshes.iter().all(|m| m.position_transform.is_none()));
+        let indices = d.indices.unwrap();
+        assert_eq!((indices.n_indices, indices.format), (9, IndexFormat::U16));
+        let mut attributes: Vec<_> = d.attributes.into_iter().collect();
+        attributes.sort_by_key(|(usage, _)| *usage);
+        assert_eq!(
+            attributes,
+            [
+                (VertexUsage::Position, VertexFormat::Float32x3),
+                (VertexUsage::Normal, VertexFormat::Float32x3),
+                (VertexUsage::Uv0, VertexFormat::Float32x2),
+                (VertexUsage::Color, VertexFormat::Unorm8x4),
+            ]
+        );
+        assert_eq!(d.checksum_kind, 0);
+        assert!(!d.signed);
+    }
+
+    /// All usages, including those added after version 1, survive
+    /// encoding.
+    #[test]
+    fn usages_round_trip() {
+        let usages = VertexUsage::NAMED
+            .iter()
+            .copied()
+            .chain([VertexUsage::Custom(0), VertexUsage::Custom(u32::MAX)]);
+        let d = IyesMeshDescriptor {
+            n_vertices: 0,
+            user_data_len: 0,
+            meshes: vec![],
+            indices: None,
+            attributes: usages
+                .map(|usage| (usage, VertexFormat::Float32))
+                .collect(),
+            attribute_encodings: Default::default(),
+            color_spaces: Default::default(),
+            n_instances: 0,
+            instance_attributes: Default::default(),
+            user_data_checksum: None,
+            user_data_nonce: None,
+            signed: false,
+            checksum_kind: 0,
+        };
+        let decoded = IyesMeshDescriptor::from_bytes_with_version(
+            crate::FORMAT_VERSION,
+            &bitcode::encode(&d),
+        )
+        .unwrap();
+        assert_eq!(decoded.attributes, d.attributes);
+    }
+}
@@ -0,0 +1,44 @@
+//! Encryption of the user data, using ChaCha20-Poly1305.
+//!
+//! Only the user data is encrypted, so that mesh data can still be read
+//! (and edited) without the key. The nonce is stored in the descriptor
+//! ([`IyesMeshDescriptor::user_data_nonce`]), and the authentication tag is
+//! appended to the encrypted data.
+//!
+//! [`IyesMeshDescriptor::user_data_nonce`]:
+//! crate::descriptor::IyesMeshDescriptor::user_data_nonce
+
+use chacha20poly1305::aead::{Aead, Generate, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+/// Length of the authentication tag appended to the encrypted data.
+pub const TAG_LEN: usize = 16;
+
+/// Generate a random nonce, to encrypt one file with.
+pub fn generate_nonce() -> [u8; 12] {
+    Nonce::generate().into()
+}
+
+/// Encrypt data, appending the authentication tag.
+pub fn encrypt(
+    key: &[u8; 32],
+    nonce: &[u8; 12],
+    data: &[u8],
+) -> Vec<u8> {
+    ChaCha20Poly1305::new(&Key::from(*key))
+        .encrypt(&Nonce::from(*nonce), data)
+        .expect("user data too large to encrypt")
+}
+
+/// Decrypt data encrypted with [`encrypt`].
+///
+/// Returns `None` if the key is wrong or the data was modified.
+pub fn decrypt(
+    key: &[u8; 32],
+    nonce: &[u8; 12],
+    data: &[u8],
+) -> Option<Vec<u8>> {
+    ChaCha20Poly1305::new(&Key::from(*key))
+        .decrypt(&Nonce::from(*nonce), data)
+        .ok()
+}
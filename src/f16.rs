@@ -0,0 +1,96 @@
+//! Packing of `f32` values into Float16 vertex data and back.
+//!
+//! Use these to build or consume buffers in the `Float16`, `Float16x2`
+//! and `Float16x4` formats. Values are rounded to nearest (ties to even),
+//! following IEEE 754: values too large for f16 become infinity, values
+//! too small become subnormals or zero, and NaN stays NaN.
+
+/// Encode floats as little-endian f16 values.
+pub fn encode_f32_to_f16_buf(values: &[f32]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(values.len() * 2);
+    for v in values {
+        out.extend_from_slice(&half::f16::from_f32(*v).to_le_bytes());
+    }
+    out
+}
+
+/// Decode little-endian f16 values into floats.
+///
+/// Any trailing odd byte is ignored.
+pub fn decode_f16_buf_to_f32(data: &[u8]) -> Vec<f32> {
+    data.chunks_exact(2)
+        .map(|b| half::f16::from_le_bytes([b[0], b[1]]).to_f32())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(v: f32) -> u16 {
+        let buf = encode_f32_to_f16_buf(&[v]);
+        u16::from_le_bytes([buf[0], buf[1]])
+    }
+
+    #[test]
+    fn encode_known_bit_patterns() {
+        assert_eq!(bits(0.0), 0x0000);
+        assert_eq!(bits(-0.0), 0x8000);
+        assert_eq!(bits(1.0), 0x3c00);
+        assert_eq!(bits(-2.0), 0xc000);
+        assert_eq!(bits(0.1), 0x2e66);
+        assert_eq!(bits(65504.0), 0x7bff);
+        // rounds up past the largest finite value
+        assert_eq!(bits(65520.0), 0x7c00);
+        assert_eq!(bits(f32::INFINITY), 0x7c00);
+        assert_eq!(bits(f32::NEG_INFINITY), 0xfc00);
+        let nan = bits(f32::NAN);
+        assert_eq!(nan & 0x7c00, 0x7c00);
+        assert_ne!(nan & 0x03ff, 0);
+    }
+
+    #[test]
+    fn encode_subnormals() {
+        let min = 2.0f32.powi(-24);
+        assert_eq!(bits(min), 0x0001);
+        assert_eq!(bits(2.0f32.powi(-14)), 0x0400);
+        assert_eq!(bits(2.0f32.powi(-14) - min), 0x03ff);
+        // ties round to even
+        assert_eq!(bits(min * 0.5), 0x0000);
+        assert_eq!(bits(min * 1.5), 0x0002);
+        assert_eq!(bits(min * 2.5), 0x0002);
+        assert_eq!(bits(-min * 1.5), 0x8002);
+    }
+
+    #[test]
+    fn decode_known_bit_patterns() {
+        let buf: Vec<u8> = [0x3c00u16, 0xc000, 0x3555, 0x0001, 0x7bff, 0xfc00]
+            .iter()
+            .flat_map(|b| b.to_le_bytes())
+            .collect();
+        let values = decode_f16_buf_to_f32(&buf);
+        assert_eq!(
+            values,
+            [
+                1.0,
+                -2.0,
+                0.333_251_95,
+                2.0f32.powi(-24),
+                65504.0,
+                f32::NEG_INFINITY
+            ]
+        );
+        let nan = decode_f16_buf_to_f32(&0x7e00u16.to_le_bytes());
+        assert!(nan[0].is_nan());
+        // the trailing odd byte is ignored
+        assert_eq!(decode_f16_buf_to_f32(&[0x00, 0x3c, 0x00]), [1.0]);
+    }
+
+    #[test]
+    fn round_trip_exact_values() {
+        let values = [0.5, -0.25, 1024.0, 3.140625, -65504.0];
+        let buf = encode_f32_to_f16_buf(&values);
+        assert_eq!(buf.len(), values.len() * 2);
+        assert_eq!(decode_f16_buf_to_f32(&buf), values);
+    }
+}
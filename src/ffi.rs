@@ -0,0 +1,426 @@
+//! C API for reading IMA files.
+//!
+//! The C header is generated by cbindgen when building with the `ffi`
+//! feature, and is available as [`C_HEADER`]. To get a C library, build
+//! this crate with `cargo rustc --lib --features ffi --crate-type cdylib`
+//! (or `staticlib`).
+//!
+//! Memory ownership:
+//! - Everything read from a file is owned by its `IyesMeshHandle`, and
+//!   freed by `iyesmesh_close`. The input bytes are not needed after
+//!   `iyesmesh_open` returns.
+//! - Data is only ever copied into caller-provided buffers. Passing a null
+//!   buffer to the `iyesmesh_copy_*` functions returns the required size.
+//! - The string returned by `iyesmesh_last_error_message` is owned by the
+//!   library, and valid until the next call on the same thread. Format
+//!   names are static.
+//!
+//! Vertex usages are passed as `uint32_t`: the named usages are the
+//! `IYESMESH_USAGE_*` constants, and custom usages are
+//! `IYESMESH_USAGE_CUSTOM_BASE + id` (so only ids below `2^31` can be
+//! accessed). Vertex formats are indices into [`VertexFormat::ALL`].
+
+use std::cell::RefCell;
+use std::ffi::{CString, c_char};
+use std::io::Cursor;
+use std::sync::OnceLock;
+
+use crate::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+use crate::mesh::MeshData;
+use crate::read::{IyesMeshReader, ReadError};
+
+/// The C header for this module.
+pub const C_HEADER: &str =
+    include_str!(concat!(env!("OUT_DIR"), "/iyesmesh.h"));
+
+pub const IYESMESH_USAGE_POSITION: u32 = 0;
+pub const IYESMESH_USAGE_NORMAL: u32 = 1;
+pub const IYESMESH_USAGE_TANGENT: u32 = 2;
+pub const IYESMESH_USAGE_UV0: u32 = 3;
+pub const IYESMESH_USAGE_UV1: u32 = 4;
+pub const IYESMESH_USAGE_JOINT_INDEX: u32 = 5;
+pub const IYESMESH_USAGE_JOINT_WEIGHT: u32 = 6;
+pub const IYESMESH_USAGE_COLOR: u32 = 7;
+pub const IYESMESH_USAGE_UV2: u32 = 8;
+pub const IYESMESH_USAGE_UV3: u32 = 9;
+pub const IYESMESH_USAGE_UV4: u32 = 10;
+pub const IYESMESH_USAGE_UV5: u32 = 11;
+pub const IYESMESH_USAGE_UV6: u32 = 12;
+pub const IYESMESH_USAGE_UV7: u32 = 13;
+pub const IYESMESH_USAGE_JOINT_INDEX1: u32 = 14;
+pub const IYESMESH_USAGE_JOINT_WEIGHT1: u32 = 15;
+pub const IYESMESH_USAGE_CUSTOM_BASE: u32 = 0x8000_0000;
+
+/// An opened IMA file.
+pub struct IyesMeshHandle {
+    meshes: Vec<MeshData>,
+    user_data: Vec<u8>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> =
+        const { RefCell::new(None) };
+}
+
+fn set_error(message: impl ToString) {
+    let message = CString::new(message.to_string().replace('\0', ""))
+        .unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+fn usage_from_c(usage: u32) -> Option<VertexUsage> {
+    Some(match usage {
+        IYESMESH_USAGE_POSITION => VertexUsage::Position,
+        IYESMESH_USAGE_NORMAL => VertexUsage::Normal,
+        IYESMESH_USAGE_TANGENT => VertexUsage::Tangent,
+        IYESMESH_USAGE_UV0 => VertexUsage::Uv0,
+        IYESMESH_USAGE_UV1 => VertexUsage::Uv1,
+        IYESMESH_USAGE_JOINT_INDEX => VertexUsage::JointIndex,
+        IYESMESH_USAGE_JOINT_WEIGHT => VertexUsage::JointWeight,
+        IYESMESH_USAGE_COLOR => VertexUsage::Color,
+        IYESMESH_USAGE_UV2 => VertexUsage::Uv2,
+        IYESMESH_USAGE_UV3 => VertexUsage::Uv3,
+        IYESMESH_USAGE_UV4 => VertexUsage::Uv4,
+        IYESMESH_USAGE_UV5 => VertexUsage::Uv5,
+        IYESMESH_USAGE_UV6 => VertexUsage::Uv6,
+        IYESMESH_USAGE_UV7 => VertexUsage::Uv7,
+        IYESMESH_USAGE_JOINT_INDEX1 => VertexUsage::JointIndex1,
+        IYESMESH_USAGE_JOINT_WEIGHT1 => VertexUsage::JointWeight1,
+        id if id >= IYESMESH_USAGE_CUSTOM_BASE => {
+            VertexUsage::Custom(id - IYESMESH_USAGE_CUSTOM_BASE)
+        }
+        _ => return None,
+    })
+}
+
+fn format_to_c(format: VertexFormat) -> i32 {
+    VertexFormat::ALL.iter().position(|f| *f == format).unwrap() as i32
+}
+
+fn format_from_c(format: i32) -> Option<VertexFormat> {
+    VertexFormat::ALL.get(usize::try_from(format).ok()?).copied()
+}
+
+/// Read a whole file, decoding positions, normals and tangents to floats.
+fn open(bytes: &[u8]) -> Result<IyesMeshHandle, ReadError> {
+    let data =
+        IyesMeshReader::init(&mut Cursor::new(bytes))?.read_all_data()?;
+    let buffers = data.into_flat_buffers()?;
+    let split = data.into_split_meshes(&buffers)?;
+    let mut meshes = Vec::with_capacity(split.meshes.len());
+    for (index, mesh) in split.meshes.iter().enumerate() {
+        let mut owned = MeshData::new();
+        for (usage, (format, bytes)) in mesh.attributes.iter() {
+            owned
+                .set_attribute_bytes(*usage, *format, bytes)
+                .map_err(|_| ReadError::NotEnoughData)?;
+        }
+        match mesh.indices {
+            Some((IndexFormat::U16, _)) => {
+                owned.set_indices_u16(&mesh.index_view::<u16>().unwrap());
+            }
+            Some((IndexFormat::U32, _)) => {
+                owned.set_indices_u32(&mesh.index_view::<u32>().unwrap());
+            }
+            None => {}
+        }
+        let info = &data.descriptor().meshes[index];
+        if info.position_transform.is_some()
+            && let Some(positions) = data.decode_positions_f32(&split, index)
+        {
+            owned.set_positions(&positions).ok();
+        }
+        let encodings = &data.descriptor().attribute_encodings;
+        if encodings.contains_key(&VertexUsage::Normal)
+            && let Some(normals) = data.decode_normals_f32(&split, index)
+        {
+            owned.set_normals(&normals).ok();
+        }
+        if encodings.contains_key(&VertexUsage::Tangent)
+            && let Some(tangents) = data.decode_tangents_f32(&split, index)
+        {
+            owned.set_tangents(&tangents).ok();
+        }
+        meshes.push(owned);
+    }
+    Ok(IyesMeshHandle {
+        meshes,
+        user_data: buffers.user_data.unwrap_or_default().to_vec(),
+    })
+}
+
+/// Look up a mesh, recording an error if there is none.
+///
+/// # Safety
+///
+/// `handle` must be null or a live handle.
+unsafe fn get_mesh<'a>(
+    handle: *const IyesMeshHandle,
+    mesh: usize,
+) -> Option<&'a MeshData> {
+    let Some(handle) = (unsafe { handle.as_ref() }) else {
+        set_error("Null handle");
+        return None;
+    };
+    let r = handle.meshes.get(mesh);
+    if r.is_none() {
+        set_error(format!("There is no mesh with index {mesh}"));
+    }
+    r
+}
+
+/// Copy `data` into the caller's buffer, or return its size if `out` is
+/// null. Returns 0 if the buffer is too small.
+///
+/// # Safety
+///
+/// `out` must be null or valid for writes of `out_len` bytes.
+unsafe fn copy_out(
+    data: &[u8],
+    out: *mut u8,
+    out_len: usize,
+) -> usize {
+    if out.is_null() {
+        return data.len();
+    }
+    if out_len < data.len() {
+        set_error(format!(
+            "Buffer too small ({out_len} bytes, need {})",
+            data.len()
+        ));
+        return 0;
+    }
+    unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), out, data.len()) };
+    data.len()
+}
+
+/// Read an IMA file from memory.
+///
+/// Returns null on error (see `iyesmesh_last_error_message`). The handle
+/// must be freed with `iyesmesh_close`.
+///
+/// # Safety
+///
+/// `bytes` must be valid for reads of `len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn iyesmesh_open(
+    bytes: *const u8,
+    len: usize,
+) -> *mut IyesMeshHandle {
+    if bytes.is_null() {
+        set_error("Null input buffer");
+        return std::ptr::null_mut();
+    }
+    let bytes = unsafe { std::slice::from_raw_parts(bytes, len) };
+    match open(bytes) {
+        Ok(handle) => Box::into_raw(Box::new(handle)),
+        Err(e) => {
+            set_error(e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Free a handle and everything it owns. Null is ignored.
+///
+/// # Safety
+///
+/// `handle` must be null or a live handle, which must not be used again.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn iyesmesh_close(handle: *mut IyesMeshHandle) {
+    if !handle.is_null() {
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
+
+/// The message of the last error on this thread, or null.
+#[unsafe(no_mangle)]
+pub extern "C" fn iyesmesh_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|e| {
+        e.borrow().as_ref().map_or(std::ptr::null(), |e| e.as_ptr())
+    })
+}
+
+/// # Safety
+///
+/// `handle` must be null or a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn iyesmesh_mesh_count(
+    handle: *const IyesMeshHandle,
+) -> usize {
+    unsafe { handle.as_ref() }.map_or(0, |h| h.meshes.len())
+}
+
+/// # Safety
+///
+/// `handle` must be null or a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn iyesmesh_vertex_count(
+    handle: *const IyesMeshHandle,
+    mesh: usize,
+) -> usize {
+    unsafe { get_mesh(handle, mesh) }.map_or(0, |m| m.n_vertices())
+}
+
+/// The number of indices of a mesh, or 0 if it is not indexed.
+///
+/// # Safety
+///
+/// `handle` must be null or a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn iyesmesh_index_count(
+    handle: *const IyesMeshHandle,
+    mesh: usize,
+) -> usize {
+    unsafe { get_mesh(handle, mesh) }
+        .and_then(|m| m.as_mesh_ref().n_indices())
+        .unwrap_or(0)
+}
+
+/// The size of each index in bytes (2 or 4), or 0 if not indexed.
+///
+/// # Safety
+///
+/// `handle` must be null or a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn iyesmesh_index_size(
+    handle: *const IyesMeshHandle,
+    mesh: usize,
+) -> u32 {
+    unsafe { get_mesh(handle, mesh) }
+        .and_then(|m| m.as_mesh_ref().indices)
+        .map_or(0, |(format, _)| format.size() as u32)
+}
+
+/// # Safety
+///
+/// `handle` must be null or a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn iyesmesh_has_attribute(
+    handle: *const IyesMeshHandle,
+    mesh: usize,
+    usage: u32,
+) -> bool {
+    unsafe { iyesmesh_attribute_format(handle, mesh, usage) >= 0 }
+}
+
+/// The format of an attribute, or -1 if the mesh does not have it.
+///
+/// Quantized positions and encoded normals/tangents are decoded when
+/// opening the file, so they are reported as the float formats.
+///
+/// # Safety
+///
+/// `handle` must be null or a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn iyesmesh_attribute_format(
+    handle: *const IyesMeshHandle,
+    mesh: usize,
+    usage: u32,
+) -> i32 {
+    let Some(mesh) = (unsafe { get_mesh(handle, mesh) }) else {
+        return -1;
+    };
+    usage_from_c(usage)
+        .and_then(|usage| mesh.as_mesh_ref().attributes.get(&usage).copied())
+        .map_or(-1, |(format, _)| format_to_c(format))
+}
+
+/// The size of one element of a vertex format in bytes, or 0 if invalid.
+#[unsafe(no_mangle)]
+pub extern "C" fn iyesmesh_format_size(format: i32) -> u32 {
+    format_from_c(format).map_or(0, |f| f.size() as u32)
+}
+
+/// The name of a vertex format (such as "Float32x3"), or null if invalid.
+#[unsafe(no_mangle)]
+pub extern "C" fn iyesmesh_format_name(format: i32) -> *const c_char {
+    static NAMES: OnceLock<Vec<CString>> = OnceLock::new();
+    let names = NAMES.get_or_init(|| {
+        VertexFormat::ALL
+            .iter()
+            .map(|f| CString::new(format!("{f:?}")).unwrap())
+            .collect()
+    });
+    usize::try_from(format)
+        .ok()
+        .and_then(|i| names.get(i))
+        .map_or(std::ptr::null(), |name| name.as_ptr())
+}
+
+/// Copy the data of an attribute.
+///
+/// Returns the number of bytes copied, or the required size if `out` is
+/// null. Returns 0 on error.
+///
+/// # Safety
+///
+/// `handle` must be null or a live handle. `out` must be null or valid
+/// for writes of `out_len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn iyesmesh_copy_attribute(
+    handle: *const IyesMeshHandle,
+    mesh: usize,
+    usage: u32,
+    out: *mut u8,
+    out_len: usize,
+) -> usize {
+    let Some(mesh) = (unsafe { get_mesh(handle, mesh) }) else {
+        return 0;
+    };
+    let mesh = mesh.as_mesh_ref();
+    let Some((_, data)) =
+        usage_from_c(usage).and_then(|usage| mesh.attributes.get(&usage))
+    else {
+        set_error(format!("The mesh has no attribute {usage}"));
+        return 0;
+    };
+    unsafe { copy_out(data, out, out_len) }
+}
+
+/// Copy the indices of a mesh (see `iyesmesh_index_size`).
+///
+/// Works like `iyesmesh_copy_attribute`.
+///
+/// # Safety
+///
+/// `handle` must be null or a live handle. `out` must be null or valid
+/// for writes of `out_len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn iyesmesh_copy_indices(
+    handle: *const IyesMeshHandle,
+    mesh: usize,
+    out: *mut u8,
+    out_len: usize,
+) -> usize {
+    let Some(mesh) = (unsafe { get_mesh(handle, mesh) }) else {
+        return 0;
+    };
+    let Some((_, data)) = mesh.as_mesh_ref().indices else {
+        set_error("The mesh is not indexed");
+        return 0;
+    };
+    unsafe { copy_out(data, out, out_len) }
+}
+
+/// Copy the user data of the file.
+///
+/// Works like `iyesmesh_copy_attribute`. A file without user data has
+/// zero bytes of it. Encrypted user data is copied as stored.
+///
+/// # Safety
+///
+/// `handle` must be null or a live handle. `out` must be null or valid
+/// for writes of `out_len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn iyesmesh_copy_user_data(
+    handle: *const IyesMeshHandle,
+    out: *mut u8,
+    out_len: usize,
+) -> usize {
+    let Some(handle) = (unsafe { handle.as_ref() }) else {
+        set_error("Null handle");
+        return 0;
+    };
+    unsafe { copy_out(&handle.user_data, out, out_len) }
+}
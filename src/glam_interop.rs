@@ -0,0 +1,92 @@
+//! Conversions to and from [`glam`] types.
+
+use glam::{Vec2, Vec3, Vec4};
+
+use crate::descriptor::{PositionTransform, VertexUsage};
+use crate::mesh::{MeshData, MeshDataRef, MeshError};
+
+/// Lazily decode the positions of a mesh (see [`MeshDataRef::positions_f32`]).
+pub fn positions_vec3<'s>(
+    mesh: &MeshDataRef<'s>,
+) -> Option<impl Iterator<Item = Vec3> + 's> {
+    Some(mesh.positions_f32()?.map(Vec3::from_array))
+}
+
+pub fn normals_vec3<'s>(
+    mesh: &MeshDataRef<'s>,
+) -> Option<impl Iterator<Item = Vec3> + 's> {
+    Some(mesh.normals_f32()?.map(Vec3::from_array))
+}
+
+pub fn uvs_vec2<'s>(
+    mesh: &MeshDataRef<'s>,
+    usage: VertexUsage,
+) -> Option<impl Iterator<Item = Vec2> + 's> {
+    Some(mesh.uvs_f32(usage)?.map(Vec2::from_array))
+}
+
+pub fn colors_vec4<'s>(
+    mesh: &MeshDataRef<'s>,
+) -> Option<impl Iterator<Item = Vec4> + 's> {
+    Some(mesh.colors_f32()?.map(Vec4::from_array))
+}
+
+/// Compute the bounding box of the positions of a mesh, as `(min, max)`.
+///
+/// Returns `None` if the mesh has no (decodable) positions or no vertices.
+pub fn position_aabb(mesh: &MeshDataRef<'_>) -> Option<(Vec3, Vec3)> {
+    positions_vec3(mesh)?.fold(None, |aabb, p| match aabb {
+        None => Some((p, p)),
+        Some((min, max)) => Some((min.min(p), max.max(p))),
+    })
+}
+
+/// The box covered by quantized positions, as `(min, max)`.
+pub fn transform_aabb(transform: &PositionTransform) -> (Vec3, Vec3) {
+    let offset = Vec3::from_array(transform.offset);
+    (offset, offset + Vec3::from_array(transform.scale))
+}
+
+impl MeshData {
+    pub fn set_positions_vec3(
+        &mut self,
+        data: &[Vec3],
+    ) -> Result<&mut Self, MeshError> {
+        self.set_positions(bytemuck::cast_slice(data))
+    }
+
+    pub fn set_normals_vec3(
+        &mut self,
+        data: &[Vec3],
+    ) -> Result<&mut Self, MeshError> {
+        self.set_normals(bytemuck::cast_slice(data))
+    }
+
+    pub fn set_tangents_vec4(
+        &mut self,
+        data: &[Vec4],
+    ) -> Result<&mut Self, MeshError> {
+        self.set_tangents(bytemuck::cast_slice(data))
+    }
+
+    pub fn set_uv0_vec2(
+        &mut self,
+        data: &[Vec2],
+    ) -> Result<&mut Self, MeshError> {
+        self.set_uv0(bytemuck::cast_slice(data))
+    }
+
+    pub fn set_uv1_vec2(
+        &mut self,
+        data: &[Vec2],
+    ) -> Result<&mut Self, MeshError> {
+        self.set_uv1(bytemuck::cast_slice(data))
+    }
+
+    pub fn set_colors_vec4(
+        &mut self,
+        data: &[Vec4],
+    ) -> Result<&mut Self, MeshError> {
+        self.set_colors_f32(bytemuck::cast_slice(data))
+    }
+}
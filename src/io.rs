@@ -12,6 +12,11 @@ pub trait WriteSeek: Write + Seek {
 
 impl<T: Write + Seek> WriteSeek for T {}
 
+pub trait ReadWriteSeek: Read + Write + Seek {
+}
+
+impl<T: Read + Write + Seek> ReadWriteSeek for T {}
+
 pub fn new_zstd_encoder<W: Write>(
     writer: W,
     level: i32,
@@ -35,3 +40,27 @@ pub fn new_zstd_decoder<R: Read>(
     decoder.include_magicbytes(false)?;
     Ok(decoder)
 }
+
+/// Wrapper that keeps track of how many bytes have been read.
+pub(crate) struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R> CountingReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    pub(crate) fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
@@ -1,16 +1,47 @@
+pub mod cancel;
 pub mod checksum;
+pub mod convert;
 pub mod descriptor;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+#[cfg(feature = "f16")]
+pub mod f16;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod header;
 
 pub mod read;
+#[cfg(feature = "signing")]
+pub mod signing;
 pub mod write;
+#[cfg(feature = "tokio")]
+pub mod async_write;
 
 pub mod io;
 
 pub mod mesh;
+pub mod patch;
+pub mod view;
 
-pub const FORMAT_VERSION: u16 = 1;
+#[cfg(feature = "bevy")]
+pub mod bevy;
+#[cfg(feature = "bevy_loader")]
+pub mod bevy_loader;
+#[cfg(feature = "glam")]
+pub mod glam_interop;
+#[cfg(feature = "meshopt")]
+pub mod meshopt;
+#[cfg(feature = "mint")]
+pub mod mint_interop;
+#[cfg(feature = "wgpu")]
+pub mod wgpu;
+
+pub const FORMAT_VERSION: u16 = 2;
+/// Oldest version of the file format that can still be read.
+pub const MIN_FORMAT_VERSION: u16 = 1;
 pub const MAGIC: [u8; 4] = [b'I', b'y', b'M', b'A'];
+/// Length of the signature at the end of signed files.
+pub const SIGNATURE_LEN: usize = 64;
 
 pub type HashMap<K, V> = rapidhash::RapidHashMap<K, V>;
 pub type HashSet<T> = rapidhash::RapidHashSet<T>;
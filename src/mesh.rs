@@ -1,5 +1,257 @@
 use crate::HashMap;
 use crate::descriptor::*;
+use crate::view::{AttrView, ViewError};
+
+mod color;
+mod components;
+mod concat;
+pub mod encode;
+mod finite;
+mod indices;
+mod normals;
+mod skin;
+mod transform;
+mod uv;
+mod weld;
+
+pub use color::convert_color_space;
+pub use components::split_connected_components;
+pub use concat::concatenate;
+pub use finite::{find_non_finite, scrub_non_finite};
+pub use indices::downconvert_indices;
+pub use normals::{NormalMode, generate_normals, invert_normals};
+pub use skin::{check_joint_weights, normalize_joint_weights};
+pub use transform::{
+    AppliedTransform, CenterMode, normalize_scale, recenter, transform,
+};
+pub use uv::transform_uvs;
+pub use weld::weld;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum MeshError {
+    #[error("Number of indices ({0}) is not a multiple of 3")]
+    IndicesNotTriangles(usize),
+    #[error("Number of vertices ({0}) is not a multiple of 3")]
+    VerticesNotTriangles(usize),
+    #[error("The mesh has no positions that can be decoded as floats")]
+    NoPositions,
+    #[error("Attribute {0:?} cannot be decoded as floats")]
+    NotFloat(VertexUsage),
+    #[error("The mesh has no normals")]
+    NoNormals,
+    #[error("The mesh has no {0:?} attribute")]
+    MissingAttribute(VertexUsage),
+    #[error("Attribute {usage:?} in format {format:?} is not supported here")]
+    UnsupportedFormat {
+        usage: VertexUsage,
+        format: VertexFormat,
+    },
+    #[error("Attribute {usage:?} is stored as {format:?}, which is unsigned")]
+    NotSigned {
+        usage: VertexUsage,
+        format: VertexFormat,
+    },
+    #[error("The mesh has no indices")]
+    NotIndexed,
+    #[error("Index {index} is out of range for {n_vertices} vertices")]
+    IndexOutOfRange { index: u32, n_vertices: usize },
+    #[error("Index {max_index} does not fit in 16 bits")]
+    IndexTooLarge { max_index: u32 },
+    #[error("Elements of {element_size} bytes do not match format {format:?}")]
+    ElementSize {
+        format: VertexFormat,
+        element_size: usize,
+    },
+    #[error("Attribute {usage:?} has {found} vertices, expected {expected}")]
+    VertexCount {
+        usage: VertexUsage,
+        expected: usize,
+        found: usize,
+    },
+    #[error(
+        "Attribute {usage:?} of mesh {index} is {found:?}, \
+         but {expected:?} in the first mesh"
+    )]
+    AttributesDiffer {
+        index: usize,
+        usage: VertexUsage,
+        expected: Option<VertexFormat>,
+        found: Option<VertexFormat>,
+    },
+}
+
+/// Mesh data owning its buffers.
+///
+/// The setters check the data as it is added, so that the mesh is always
+/// consistent. Use [`as_mesh_ref`](Self::as_mesh_ref) to pass it to the
+/// writer.
+#[derive(Default, Clone)]
+pub struct MeshData {
+    indices: Option<(IndexFormat, Vec<u8>)>,
+    attributes: HashMap<VertexUsage, (VertexFormat, Vec<u8>)>,
+}
+
+impl MeshData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn as_mesh_ref(&self) -> MeshDataRef<'_> {
+        MeshDataRef {
+            indices: self.indices.as_ref().map(|(f, b)| (*f, &b[..])),
+            attributes: self
+                .attributes
+                .iter()
+                .map(|(usage, (f, b))| (*usage, (*f, &b[..])))
+                .collect(),
+        }
+    }
+
+    pub fn n_vertices(&self) -> usize {
+        self.as_mesh_ref().n_vertices()
+    }
+
+    /// Set an attribute from elements of any plain data type.
+    ///
+    /// The size of `T` must be the size of `format`, and the number of
+    /// elements must match the other attributes (except the one being
+    /// replaced).
+    pub fn set_attribute_pod<T: bytemuck::Pod>(
+        &mut self,
+        usage: VertexUsage,
+        format: VertexFormat,
+        data: &[T],
+    ) -> Result<&mut Self, MeshError> {
+        let element_size = std::mem::size_of::<T>();
+        if element_size != format.size() {
+            return Err(MeshError::ElementSize {
+                format,
+                element_size,
+            });
+        }
+        self.set_attribute_bytes(usage, format, bytemuck::cast_slice(data))
+    }
+
+    /// Set an attribute from raw little-endian data.
+    ///
+    /// Works like [`set_attribute_pod`](Self::set_attribute_pod); the
+    /// length of `data` must be a multiple of the size of `format`.
+    pub fn set_attribute_bytes(
+        &mut self,
+        usage: VertexUsage,
+        format: VertexFormat,
+        data: &[u8],
+    ) -> Result<&mut Self, MeshError> {
+        if !data.len().is_multiple_of(format.size()) {
+            return Err(MeshError::ElementSize {
+                format,
+                element_size: data.len(),
+            });
+        }
+        let n_vertices = data.len() / format.size();
+        if let Some((_, (f, b))) =
+            self.attributes.iter().find(|(u, _)| **u != usage)
+        {
+            let expected = b.len() / f.size();
+            if n_vertices != expected {
+                return Err(MeshError::VertexCount {
+                    usage,
+                    expected,
+                    found: n_vertices,
+                });
+            }
+        }
+        self.attributes.insert(usage, (format, data.to_vec()));
+        Ok(self)
+    }
+
+    pub fn remove_attribute(
+        &mut self,
+        usage: VertexUsage,
+    ) -> &mut Self {
+        self.attributes.remove(&usage);
+        self
+    }
+
+    pub fn set_positions(
+        &mut self,
+        data: &[[f32; 3]],
+    ) -> Result<&mut Self, MeshError> {
+        self.set_attribute_pod(VertexUsage::Position, VertexFormat::Float32x3, data)
+    }
+
+    pub fn set_normals(
+        &mut self,
+        data: &[[f32; 3]],
+    ) -> Result<&mut Self, MeshError> {
+        self.set_attribute_pod(VertexUsage::Normal, VertexFormat::Float32x3, data)
+    }
+
+    pub fn set_tangents(
+        &mut self,
+        data: &[[f32; 4]],
+    ) -> Result<&mut Self, MeshError> {
+        self.set_attribute_pod(VertexUsage::Tangent, VertexFormat::Float32x4, data)
+    }
+
+    pub fn set_uv0(
+        &mut self,
+        data: &[[f32; 2]],
+    ) -> Result<&mut Self, MeshError> {
+        self.set_attribute_pod(VertexUsage::Uv0, VertexFormat::Float32x2, data)
+    }
+
+    pub fn set_uv1(
+        &mut self,
+        data: &[[f32; 2]],
+    ) -> Result<&mut Self, MeshError> {
+        self.set_attribute_pod(VertexUsage::Uv1, VertexFormat::Float32x2, data)
+    }
+
+    pub fn set_colors_f32(
+        &mut self,
+        data: &[[f32; 4]],
+    ) -> Result<&mut Self, MeshError> {
+        self.set_attribute_pod(VertexUsage::Color, VertexFormat::Float32x4, data)
+    }
+
+    pub fn set_indices_u16(
+        &mut self,
+        data: &[u16],
+    ) -> &mut Self {
+        self.indices =
+            Some((IndexFormat::U16, bytemuck::cast_slice(data).to_vec()));
+        self
+    }
+
+    pub fn set_indices_u32(
+        &mut self,
+        data: &[u32],
+    ) -> &mut Self {
+        self.indices =
+            Some((IndexFormat::U32, bytemuck::cast_slice(data).to_vec()));
+        self
+    }
+
+    pub fn clear_indices(&mut self) -> &mut Self {
+        self.indices = None;
+        self
+    }
+}
+
+/// Copy the buffers, without checking them.
+impl From<&MeshDataRef<'_>> for MeshData {
+    fn from(mesh: &MeshDataRef<'_>) -> Self {
+        Self {
+            indices: mesh.indices.map(|(f, b)| (f, b.to_vec())),
+            attributes: mesh
+                .attributes
+                .iter()
+                .map(|(usage, (f, b))| (*usage, (*f, b.to_vec())))
+                .collect(),
+        }
+    }
+}
 
 #[derive(Default, Clone)]
 pub struct MeshDataRef<'s> {
@@ -8,6 +260,44 @@ pub struct MeshDataRef<'s> {
 }
 
 impl<'s> MeshDataRef<'s> {
+    /// View an attribute as a slice of `T`, copying it if misaligned.
+    ///
+    /// The size of `T` must be the size of the attribute's format.
+    pub fn attr_view<T: bytemuck::Pod>(
+        &self,
+        usage: VertexUsage,
+    ) -> Result<AttrView<'s, T>, ViewError> {
+        let (format, bytes) =
+            *self.attributes.get(&usage).ok_or(ViewError::Missing)?;
+        crate::view::view_of(format.size(), bytes)
+    }
+
+    /// View an attribute as a slice of `T`, without copying.
+    pub fn attr_slice<T: bytemuck::Pod>(
+        &self,
+        usage: VertexUsage,
+    ) -> Result<&'s [T], ViewError> {
+        let (format, bytes) =
+            *self.attributes.get(&usage).ok_or(ViewError::Missing)?;
+        crate::view::slice_of(format.size(), bytes)
+    }
+
+    /// View the indices as a slice of `T`, copying them if misaligned.
+    pub fn index_view<T: bytemuck::Pod>(
+        &self,
+    ) -> Result<AttrView<'s, T>, ViewError> {
+        let (format, bytes) = self.indices.ok_or(ViewError::Missing)?;
+        crate::view::view_of(format.size(), bytes)
+    }
+
+    /// View the indices as a slice of `T`, without copying.
+    pub fn index_slice<T: bytemuck::Pod>(
+        &self,
+    ) -> Result<&'s [T], ViewError> {
+        let (format, bytes) = self.indices.ok_or(ViewError::Missing)?;
+        crate::view::slice_of(format.size(), bytes)
+    }
+
     pub fn n_vertices(&self) -> usize {
         let Some(first) = self.attributes.values().next() else {
             return 0;
@@ -19,6 +309,135 @@ impl<'s> MeshDataRef<'s> {
         self.indices.map(|b| b.1.len() / b.0.size())
     }
 
+    /// Iterate over the indices as `u32`, whatever their format.
+    ///
+    /// Like [`n_indices`](Self::n_indices), ignores any incomplete index
+    /// at the end of the buffer.
+    pub fn iter_indices(&self) -> Option<impl Iterator<Item = u32> + 's> {
+        let (format, bytes) = self.indices?;
+        Some(iter_indices(format, bytes))
+    }
+
+    pub fn indices_to_vec_u32(&self) -> Option<Vec<u32>> {
+        Some(self.iter_indices()?.collect())
+    }
+
+    /// Iterate over the vertex indices of each triangle.
+    ///
+    /// For meshes without indices, every 3 consecutive vertices form a
+    /// triangle. Degenerate triangles are yielded as-is (see
+    /// [`is_degenerate_triangle`]).
+    pub fn iter_triangles(
+        &self,
+    ) -> Result<impl Iterator<Item = [u32; 3]> + 's, MeshError> {
+        let indices = match self.indices {
+            Some((format, bytes)) => {
+                let n_indices = bytes.len() / format.size();
+                if !n_indices.is_multiple_of(3) {
+                    return Err(MeshError::IndicesNotTriangles(n_indices));
+                }
+                index_iter(format, bytes)
+            }
+            None => {
+                let n_vertices = self.n_vertices();
+                if !n_vertices.is_multiple_of(3) {
+                    return Err(MeshError::VerticesNotTriangles(n_vertices));
+                }
+                IndexIter::Sequential(0..n_vertices as u32)
+            }
+        };
+        Ok(TriangleIter(indices))
+    }
+
+    /// Iterate over the positions of the corners of each triangle.
+    ///
+    /// Works like [`iter_triangles`](Self::iter_triangles), with the
+    /// positions decoded like [`positions_f32`](Self::positions_f32).
+    pub fn iter_triangle_positions(
+        &self,
+    ) -> Result<impl Iterator<Item = [[f32; 3]; 3]> + 's, MeshError> {
+        let positions: Vec<[f32; 3]> =
+            self.positions_f32().ok_or(MeshError::NoPositions)?.collect();
+        let triangles = self.iter_triangles()?;
+        if let Some(index) = self
+            .iter_indices()
+            .into_iter()
+            .flatten()
+            .find(|i| *i as usize >= positions.len())
+        {
+            return Err(MeshError::IndexOutOfRange {
+                index,
+                n_vertices: positions.len(),
+            });
+        }
+        Ok(triangles.map(move |t| t.map(|i| positions[i as usize])))
+    }
+
+    /// Lazily decode an attribute as floats, keeping the first `N`
+    /// components.
+    ///
+    /// Works with float and normalized formats (see
+    /// [`crate::convert::iter_f32`]). Returns `None` if the attribute is
+    /// missing, is stored in an integer format, or has fewer than `N`
+    /// components. Quantization and special encodings are not undone; for
+    /// that, use the `decode_*` methods of
+    /// [`IyesMeshReaderWithData`](crate::read::IyesMeshReaderWithData).
+    pub fn attribute_f32<const N: usize>(
+        &self,
+        usage: VertexUsage,
+    ) -> Option<impl Iterator<Item = [f32; N]> + 's> {
+        let (format, bytes) = *self.attributes.get(&usage)?;
+        crate::convert::iter_f32(format, bytes)
+    }
+
+    pub fn positions_f32(&self) -> Option<impl Iterator<Item = [f32; 3]> + 's> {
+        self.attribute_f32(VertexUsage::Position)
+    }
+
+    /// The bounding box of the positions, as `(min, max)`.
+    ///
+    /// Returns `None` if the mesh has no (decodable) positions or no
+    /// vertices.
+    pub fn position_bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        self.positions_f32()?.fold(None, |bounds, p| match bounds {
+            None => Some((p, p)),
+            Some((min, max)) => Some((
+                std::array::from_fn(|i| min[i].min(p[i])),
+                std::array::from_fn(|i| max[i].max(p[i])),
+            )),
+        })
+    }
+
+    pub fn normals_f32(&self) -> Option<impl Iterator<Item = [f32; 3]> + 's> {
+        self.attribute_f32(VertexUsage::Normal)
+    }
+
+    /// Texture coordinates of the given usage (such as `Uv0`).
+    pub fn uvs_f32(
+        &self,
+        usage: VertexUsage,
+    ) -> Option<impl Iterator<Item = [f32; 2]> + 's> {
+        self.attribute_f32(usage)
+    }
+
+    pub fn colors_f32(&self) -> Option<impl Iterator<Item = [f32; 4]> + 's> {
+        self.attribute_f32(VertexUsage::Color)
+    }
+
+    /// Find the indices that refer to vertices the mesh does not have.
+    ///
+    /// Returns the position of each such index in the index buffer, and its
+    /// value.
+    pub fn find_out_of_range_indices(&self) -> Vec<(usize, u32)> {
+        let n_vertices = self.n_vertices();
+        self.iter_indices()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .filter(|(_, index)| *index as usize >= n_vertices)
+            .collect()
+    }
+
     pub fn validate(&self) -> bool {
         if self.attributes.is_empty() {
             return false;
@@ -29,6 +448,9 @@ impl<'s> MeshDataRef<'s> {
             if !validate_buf(n_indices, b.0.size(), b.1) {
                 return false;
             }
+            if iter_indices(b.0, b.1).any(|i| i as usize >= n_vertices) {
+                return false;
+            }
         }
         for b in self.attributes.values() {
             if !validate_buf(n_vertices, b.0.size(), b.1) {
@@ -44,5 +466,87 @@ fn validate_buf(
     fmt_size: usize,
     buf: &[u8],
 ) -> bool {
-    buf.len() % fmt_size == 0 && buf.len() / fmt_size == n_vertices
+    buf.len().is_multiple_of(fmt_size) && buf.len() / fmt_size == n_vertices
+}
+
+/// Check if a triangle has repeated vertex indices.
+pub fn is_degenerate_triangle(t: [u32; 3]) -> bool {
+    t[0] == t[1] || t[1] == t[2] || t[2] == t[0]
+}
+
+/// Iterate over index data as `u32`, ignoring any incomplete trailing index.
+///
+/// Aligned data is read through a cast slice; otherwise, the values are
+/// decoded byte-wise.
+pub fn iter_indices(
+    format: IndexFormat,
+    bytes: &[u8],
+) -> impl Iterator<Item = u32> + '_ {
+    index_iter(format, bytes)
+}
+
+fn index_iter(
+    format: IndexFormat,
+    bytes: &[u8],
+) -> IndexIter<'_> {
+    let bytes = &bytes[..bytes.len() - bytes.len() % format.size()];
+    match format {
+        IndexFormat::U16 => match bytemuck::try_cast_slice::<u8, u16>(bytes) {
+            Ok(slice) => IndexIter::U16(slice.iter()),
+            Err(_) => IndexIter::Bytes(format, bytes.chunks_exact(2)),
+        },
+        IndexFormat::U32 => match bytemuck::try_cast_slice::<u8, u32>(bytes) {
+            Ok(slice) => IndexIter::U32(slice.iter()),
+            Err(_) => IndexIter::Bytes(format, bytes.chunks_exact(4)),
+        },
+    }
+}
+
+enum IndexIter<'s> {
+    U16(std::slice::Iter<'s, u16>),
+    U32(std::slice::Iter<'s, u32>),
+    Bytes(IndexFormat, std::slice::ChunksExact<'s, u8>),
+    Sequential(std::ops::Range<u32>),
+}
+
+impl Iterator for IndexIter<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        match self {
+            IndexIter::U16(iter) => iter.next().map(|i| u16::from_le(*i) as u32),
+            IndexIter::U32(iter) => iter.next().map(|i| u32::from_le(*i)),
+            IndexIter::Bytes(IndexFormat::U16, iter) => {
+                iter.next().map(|b| u16::from_le_bytes([b[0], b[1]]) as u32)
+            }
+            IndexIter::Bytes(IndexFormat::U32, iter) => iter
+                .next()
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            IndexIter::Sequential(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            IndexIter::U16(iter) => iter.size_hint(),
+            IndexIter::U32(iter) => iter.size_hint(),
+            IndexIter::Bytes(_, iter) => iter.size_hint(),
+            IndexIter::Sequential(iter) => iter.size_hint(),
+        }
+    }
+}
+
+struct TriangleIter<'s>(IndexIter<'s>);
+
+impl Iterator for TriangleIter<'_> {
+    type Item = [u32; 3];
+
+    fn next(&mut self) -> Option<[u32; 3]> {
+        Some([self.0.next()?, self.0.next()?, self.0.next()?])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (min, max) = self.0.size_hint();
+        (min / 3, max.map(|max| max / 3))
+    }
 }
@@ -0,0 +1,57 @@
+use crate::convert::{linear_to_srgb, srgb_to_linear};
+use crate::descriptor::{ColorSpace, VertexFormat, VertexUsage};
+
+use super::{MeshData, MeshDataRef, MeshError};
+
+/// Convert the Color attribute of a mesh from one color space to another.
+///
+/// Applies (or removes) the sRGB transfer function to the first three
+/// components; alpha is always linear. The format is kept, so converting
+/// Unorm8x4 colors loses some precision. Converting to the same color
+/// space copies the mesh unchanged.
+///
+/// Only Float32x4 and Unorm8x4 colors are supported.
+pub fn convert_color_space(
+    mesh: &MeshDataRef<'_>,
+    from: ColorSpace,
+    to: ColorSpace,
+) -> Result<MeshData, MeshError> {
+    let usage = VertexUsage::Color;
+    let &(format, bytes) = mesh
+        .attributes
+        .get(&usage)
+        .ok_or(MeshError::MissingAttribute(usage))?;
+    let transfer: fn(f64) -> f64 = match (from, to) {
+        (ColorSpace::Linear, ColorSpace::Srgb) => linear_to_srgb,
+        (ColorSpace::Srgb, ColorSpace::Linear) => srgb_to_linear,
+        _ => |v| v,
+    };
+    let data: Vec<u8> = match format {
+        VertexFormat::Float32x4 => bytes
+            .chunks_exact(16)
+            .flat_map(|b| {
+                let mut c: [f32; 4] = bytemuck::pod_read_unaligned(b);
+                for v in c.iter_mut().take(3) {
+                    *v = transfer(*v as f64) as f32;
+                }
+                c
+            })
+            .flat_map(f32::to_le_bytes)
+            .collect(),
+        VertexFormat::Unorm8x4 => bytes
+            .chunks_exact(4)
+            .flat_map(|b| {
+                let mut c: [u8; 4] = b.try_into().unwrap();
+                for v in c.iter_mut().take(3) {
+                    let value = transfer(*v as f64 / 255.0);
+                    *v = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+                }
+                c
+            })
+            .collect(),
+        _ => return Err(MeshError::UnsupportedFormat { usage, format }),
+    };
+    let mut r = MeshData::from(mesh);
+    r.set_attribute_bytes(usage, format, &data)?;
+    Ok(r)
+}
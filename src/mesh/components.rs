@@ -0,0 +1,136 @@
+use crate::HashMap;
+use crate::descriptor::{IndexFormat, VertexUsage};
+
+use super::{MeshData, MeshDataRef, MeshError};
+
+/// Split a mesh into its connected components.
+///
+/// Two triangles are connected if they share a vertex. Vertices with
+/// bit-identical positions are treated as shared, so that seams (vertices
+/// duplicated for different normals or UVs) and non-indexed meshes do not
+/// fall apart into single faces.
+///
+/// Components are ordered by their first triangle, and keep the order of
+/// their triangles and vertices. Vertices not used by any triangle are
+/// dropped. Indexed meshes keep their index format; non-indexed meshes give
+/// non-indexed components.
+pub fn split_connected_components(
+    mesh: &MeshDataRef<'_>,
+) -> Result<Vec<MeshData>, MeshError> {
+    let n_vertices = mesh.n_vertices();
+    let triangles: Vec<[u32; 3]> = mesh.iter_triangles()?.collect();
+    if let Some(&index) =
+        triangles.iter().flatten().find(|i| **i as usize >= n_vertices)
+    {
+        return Err(MeshError::IndexOutOfRange { index, n_vertices });
+    }
+
+    let mut sets = DisjointSets::new(n_vertices);
+    for t in triangles.iter() {
+        sets.union(t[0], t[1]);
+        sets.union(t[0], t[2]);
+    }
+    if let Some((format, bytes)) = mesh.attributes.get(&VertexUsage::Position)
+    {
+        let size = format.size();
+        let mut first: HashMap<&[u8], u32> = HashMap::default();
+        for v in 0..n_vertices {
+            let position = &bytes[v * size..(v + 1) * size];
+            let other = *first.entry(position).or_insert(v as u32);
+            sets.union(v as u32, other);
+        }
+    }
+
+    let mut component_of_root: HashMap<u32, usize> = HashMap::default();
+    let mut components: Vec<Vec<[u32; 3]>> = vec![];
+    for t in triangles {
+        let root = sets.find(t[0]);
+        let id = *component_of_root.entry(root).or_insert_with(|| {
+            components.push(vec![]);
+            components.len() - 1
+        });
+        components[id].push(t);
+    }
+
+    components
+        .iter()
+        .map(|triangles| build_component(mesh, triangles))
+        .collect()
+}
+
+fn build_component(
+    mesh: &MeshDataRef<'_>,
+    triangles: &[[u32; 3]],
+) -> Result<MeshData, MeshError> {
+    // Old index of every output vertex.
+    let mut kept: Vec<u32> = vec![];
+    let mut indices: Vec<u32> = vec![];
+    if mesh.indices.is_some() {
+        kept.extend(triangles.iter().flatten());
+        kept.sort_unstable();
+        kept.dedup();
+        let remap: HashMap<u32, u32> =
+            kept.iter().enumerate().map(|(n, v)| (*v, n as u32)).collect();
+        indices.extend(triangles.iter().flatten().map(|i| remap[i]));
+    } else {
+        kept.extend(triangles.iter().flatten());
+    }
+
+    let mut r = MeshData::new();
+    for (usage, (format, bytes)) in mesh.attributes.iter() {
+        let size = format.size();
+        let data: Vec<u8> = kept
+            .iter()
+            .map(|v| *v as usize)
+            .flat_map(|v| &bytes[v * size..(v + 1) * size])
+            .copied()
+            .collect();
+        r.set_attribute_bytes(*usage, *format, &data)?;
+    }
+    match mesh.indices {
+        Some((IndexFormat::U16, _)) => {
+            let indices: Vec<u16> = indices.iter().map(|i| *i as u16).collect();
+            r.set_indices_u16(&indices);
+        }
+        Some((IndexFormat::U32, _)) => {
+            r.set_indices_u32(&indices);
+        }
+        None => {}
+    }
+    Ok(r)
+}
+
+/// Union-find over vertex indices, with path halving.
+struct DisjointSets {
+    parent: Vec<u32>,
+}
+
+impl DisjointSets {
+    fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n as u32).collect(),
+        }
+    }
+
+    fn find(
+        &mut self,
+        mut i: u32,
+    ) -> u32 {
+        while self.parent[i as usize] != i {
+            let grandparent = self.parent[self.parent[i as usize] as usize];
+            self.parent[i as usize] = grandparent;
+            i = grandparent;
+        }
+        i
+    }
+
+    fn union(
+        &mut self,
+        a: u32,
+        b: u32,
+    ) {
+        let a = self.find(a);
+        let b = self.find(b);
+        self.parent[a.max(b) as usize] = a.min(b);
+    }
+}
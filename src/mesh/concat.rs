@@ -0,0 +1,86 @@
+use crate::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+use crate::{HashMap, HashSet};
+
+use super::{MeshData, MeshDataRef, MeshError};
+
+/// Combine several meshes into one, to render them in a single draw call.
+///
+/// All meshes must have the same attributes in the same formats, otherwise
+/// [`MeshError::AttributesDiffer`] reports the first difference. Vertex
+/// buffers are appended in order, and the indices of each mesh are rebased
+/// onto its first vertex.
+///
+/// The result is indexed if any input is: non-indexed meshes then get
+/// sequential indices. The indices are U16 if all inputs use U16 (or no
+/// indices) and the combined vertex count allows it, U32 otherwise.
+pub fn concatenate(meshes: &[MeshDataRef<'_>]) -> Result<MeshData, MeshError> {
+    let Some(first) = meshes.first() else {
+        return Ok(MeshData::new());
+    };
+    let formats: HashMap<VertexUsage, VertexFormat> = first
+        .attributes
+        .iter()
+        .map(|(usage, (format, _))| (*usage, *format))
+        .collect();
+    for (index, mesh) in meshes.iter().enumerate().skip(1) {
+        let usages: HashSet<VertexUsage> = formats
+            .keys()
+            .chain(mesh.attributes.keys())
+            .copied()
+            .collect();
+        let differing = usages.into_iter().find(|usage| {
+            formats.get(usage) != mesh.attributes.get(usage).map(|a| &a.0)
+        });
+        if let Some(usage) = differing {
+            return Err(MeshError::AttributesDiffer {
+                index,
+                usage,
+                expected: formats.get(&usage).copied(),
+                found: mesh.attributes.get(&usage).map(|a| a.0),
+            });
+        }
+    }
+
+    let mut r = MeshData::new();
+    for (usage, format) in formats.iter() {
+        let data: Vec<u8> = meshes
+            .iter()
+            .flat_map(|m| m.attributes[usage].1)
+            .copied()
+            .collect();
+        r.set_attribute_bytes(*usage, *format, &data)?;
+    }
+    if meshes.iter().all(|m| m.indices.is_none()) {
+        return Ok(r);
+    }
+    let mut indices: Vec<u32> = vec![];
+    let mut base = 0;
+    for mesh in meshes {
+        let n_vertices = mesh.n_vertices();
+        match mesh.iter_indices() {
+            Some(iter) => {
+                for i in iter {
+                    if i as usize >= n_vertices {
+                        return Err(MeshError::IndexOutOfRange {
+                            index: i,
+                            n_vertices,
+                        });
+                    }
+                    indices.push(base + i);
+                }
+            }
+            None => indices.extend(base..base + n_vertices as u32),
+        }
+        base += n_vertices as u32;
+    }
+    let any_u32 = meshes
+        .iter()
+        .any(|m| matches!(m.indices, Some((IndexFormat::U32, _))));
+    if !any_u32 && base as usize <= u16::MAX as usize + 1 {
+        let indices: Vec<u16> = indices.iter().map(|i| *i as u16).collect();
+        r.set_indices_u16(&indices);
+    } else {
+        r.set_indices_u32(&indices);
+    }
+    Ok(r)
+}
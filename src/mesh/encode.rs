@@ -0,0 +1,149 @@
+//! Compact encodings for vertex attributes.
+//!
+//! Octahedral encoding maps unit vectors onto the faces of an octahedron,
+//! unfolded into a square. Stored as Snorm16x2, it takes 4 bytes instead
+//! of 12, with a maximum angular error of a few hundredths of a degree.
+//!
+//! For tangents, the handedness (the sign of the w component) is stored in
+//! the sign of the second component, whose magnitude holds the y value
+//! remapped to 1..=32767. This halves its precision, which is still far
+//! more than needed.
+
+/// Map a direction onto the octahedral square (both components in -1..=1).
+///
+/// The input does not need to be normalized. A zero vector is encoded
+/// as `[0.0, 0.0]`, which decodes to +Z.
+pub fn oct_encode(v: [f32; 3]) -> [f32; 2] {
+    let l1 = v[0].abs() + v[1].abs() + v[2].abs();
+    if l1 <= 0.0 || l1.is_nan() {
+        return [0.0, 0.0];
+    }
+    let (x, y, z) = (v[0] / l1, v[1] / l1, v[2] / l1);
+    if z >= 0.0 {
+        [x, y]
+    } else {
+        [(1.0 - y.abs()) * sign(x), (1.0 - x.abs()) * sign(y)]
+    }
+}
+
+/// Recover a unit vector from octahedral coordinates.
+pub fn oct_decode(e: [f32; 2]) -> [f32; 3] {
+    let (mut x, mut y) = (e[0].clamp(-1.0, 1.0), e[1].clamp(-1.0, 1.0));
+    let z = 1.0 - x.abs() - y.abs();
+    if z < 0.0 {
+        (x, y) = ((1.0 - y.abs()) * sign(x), (1.0 - x.abs()) * sign(y));
+    }
+    let len = (x * x + y * y + z * z).sqrt();
+    [x / len, y / len, z / len]
+}
+
+fn sign(v: f32) -> f32 {
+    if v < 0.0 { -1.0 } else { 1.0 }
+}
+
+fn snorm16(v: f32) -> i16 {
+    (v.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+fn from_snorm16(v: i16) -> f32 {
+    (v as f32 / i16::MAX as f32).max(-1.0)
+}
+
+/// Encode a normal as Snorm16x2 components.
+pub fn oct_encode_normal(v: [f32; 3]) -> [i16; 2] {
+    oct_encode(v).map(snorm16)
+}
+
+pub fn oct_decode_normal(e: [i16; 2]) -> [f32; 3] {
+    oct_decode(e.map(from_snorm16))
+}
+
+/// Encode a tangent (with handedness in `w`) as Snorm16x2 components.
+pub fn oct_encode_tangent(v: [f32; 4]) -> [i16; 2] {
+    let [x, y] = oct_encode([v[0], v[1], v[2]]);
+    let magnitude = 1 + ((y.clamp(-1.0, 1.0) + 1.0) * 0.5 * 32766.0).round() as i16;
+    [snorm16(x), if v[3] < 0.0 { -magnitude } else { magnitude }]
+}
+
+pub fn oct_decode_tangent(e: [i16; 2]) -> [f32; 4] {
+    let y = (e[1].unsigned_abs().max(1) - 1) as f32 / 32766.0 * 2.0 - 1.0;
+    let [x, y, z] = oct_decode([from_snorm16(e[0]), y]);
+    [x, y, z, if e[1] < 0 { -1.0 } else { 1.0 }]
+}
+
+/// Encode packed XYZ normals into Snorm16x2 vertex data.
+pub fn oct_encode_normals(normals: &[f32]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(normals.len() / 3 * 4);
+    for v in normals.chunks_exact(3) {
+        for c in oct_encode_normal([v[0], v[1], v[2]]) {
+            out.extend_from_slice(&c.to_le_bytes());
+        }
+    }
+    out
+}
+
+/// Decode Snorm16x2 vertex data into packed XYZ normals.
+pub fn oct_decode_normals(data: &[u8]) -> Vec<f32> {
+    let mut out = Vec::with_capacity(data.len() / 4 * 3);
+    for b in data.chunks_exact(4) {
+        let e = [i16::from_le_bytes([b[0], b[1]]), i16::from_le_bytes([b[2], b[3]])];
+        out.extend_from_slice(&oct_decode_normal(e));
+    }
+    out
+}
+
+/// Encode packed XYZW tangents into Snorm16x2 vertex data.
+pub fn oct_encode_tangents(tangents: &[f32]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(tangents.len() / 4 * 4);
+    for v in tangents.chunks_exact(4) {
+        for c in oct_encode_tangent([v[0], v[1], v[2], v[3]]) {
+            out.extend_from_slice(&c.to_le_bytes());
+        }
+    }
+    out
+}
+
+/// Decode Snorm16x2 vertex data into packed XYZW tangents.
+pub fn oct_decode_tangents(data: &[u8]) -> Vec<f32> {
+    let mut out = Vec::with_capacity(data.len());
+    for b in data.chunks_exact(4) {
+        let e = [i16::from_le_bytes([b[0], b[1]]), i16::from_le_bytes([b[2], b[3]])];
+        out.extend_from_slice(&oct_decode_tangent(e));
+    }
+    out
+}
+
+/// Encode vertex data in the given encoding (see
+/// [`AttributeEncoding`](crate::descriptor::AttributeEncoding)),
+/// appending the result to `out`.
+///
+/// The input must be Float32x3 for `Octahedral` and Float32x4 for
+/// `OctahedralSigned`.
+pub(crate) fn encode_bytes(
+    encoding: crate::descriptor::AttributeEncoding,
+    data: &[u8],
+    out: &mut Vec<u8>,
+) {
+    use crate::descriptor::AttributeEncoding;
+    let f = |b: &[u8]| f32::from_le_bytes(b.try_into().unwrap());
+    match encoding {
+        AttributeEncoding::Octahedral => {
+            out.reserve(data.len() / 3);
+            for b in data.chunks_exact(12) {
+                let v = [f(&b[0..4]), f(&b[4..8]), f(&b[8..12])];
+                for c in oct_encode_normal(v) {
+                    out.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+        }
+        AttributeEncoding::OctahedralSigned => {
+            out.reserve(data.len() / 4);
+            for b in data.chunks_exact(16) {
+                let v = [f(&b[0..4]), f(&b[4..8]), f(&b[8..12]), f(&b[12..16])];
+                for c in oct_encode_tangent(v) {
+                    out.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::descriptor::{VertexFormat, VertexUsage};
+
+use super::{MeshData, MeshDataRef, MeshError};
+
+/// Find NaN and infinite values in the float attributes of a mesh.
+///
+/// Every attribute stored in a Float16, Float32 or Float64 format is
+/// scanned. Returns the usage, vertex and component of each non-finite
+/// value, ordered by vertex within each attribute (but attributes are in
+/// no particular order).
+pub fn find_non_finite(
+    mesh: &MeshDataRef<'_>,
+) -> Vec<(VertexUsage, usize, usize)> {
+    let mut r = vec![];
+    for (usage, (format, bytes)) in mesh.attributes.iter() {
+        let Some(scalar) = FloatScalar::of(*format) else {
+            continue;
+        };
+        let n_components = format.size() / scalar.size();
+        for (i, value) in bytes.chunks_exact(scalar.size()).enumerate() {
+            if !scalar.is_finite(value) {
+                r.push((*usage, i / n_components, i % n_components));
+            }
+        }
+    }
+    r
+}
+
+/// Replace NaN and infinite values in the float attributes of a mesh.
+///
+/// Works like [`find_non_finite`], writing `replacement` (converted to the
+/// format of each attribute) in place of every non-finite value. Returns
+/// the new mesh and the number of replaced values.
+///
+/// Replacing Float16 values needs the `f16` feature; without it, this
+/// fails with [`MeshError::UnsupportedFormat`] if there are any.
+pub fn scrub_non_finite(
+    mesh: &MeshDataRef<'_>,
+    replacement: f32,
+) -> Result<(MeshData, usize), MeshError> {
+    let mut r = MeshData::from(mesh);
+    let mut n_replaced = 0;
+    for (usage, (format, bytes)) in mesh.attributes.iter() {
+        let Some(scalar) = FloatScalar::of(*format) else {
+            continue;
+        };
+        let mut data = bytes.to_vec();
+        for value in data.chunks_exact_mut(scalar.size()) {
+            if !scalar.is_finite(value) {
+                let encoded = scalar.encode(replacement).ok_or(
+                    MeshError::UnsupportedFormat {
+                        usage: *usage,
+                        format: *format,
+                    },
+                )?;
+                value.copy_from_slice(&encoded[..scalar.size()]);
+                n_replaced += 1;
+            }
+        }
+        if data != *bytes {
+            r.set_attribute_bytes(*usage, *format, &data)?;
+        }
+    }
+    Ok((r, n_replaced))
+}
+
+#[derive(Clone, Copy)]
+enum FloatScalar {
+    F16,
+    F32,
+    F64,
+}
+
+impl FloatScalar {
+    fn of(format: VertexFormat) -> Option<Self> {
+        use VertexFormat as F;
+        match format {
+            F::Float16 | F::Float16x2 | F::Float16x4 => Some(Self::F16),
+            F::Float32 | F::Float32x2 | F::Float32x3 | F::Float32x4 => {
+                Some(Self::F32)
+            }
+            F::Float64 | F::Float64x2 | F::Float64x3 | F::Float64x4 => {
+                Some(Self::F64)
+            }
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::F16 => 2,
+            Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    /// Check the exponent bits of a little-endian value: all ones means
+    /// NaN or infinity.
+    fn is_finite(
+        self,
+        b: &[u8],
+    ) -> bool {
+        match self {
+            Self::F16 => {
+                let bits = u16::from_le_bytes([b[0], b[1]]);
+                bits & 0x7c00 != 0x7c00
+            }
+            Self::F32 => {
+                let bits = u32::from_le_bytes(b[..4].try_into().unwrap());
+                bits & 0x7f80_0000 != 0x7f80_0000
+            }
+            Self::F64 => {
+                let bits = u64::from_le_bytes(b[..8].try_into().unwrap());
+                bits & 0x7ff0_0000_0000_0000 != 0x7ff0_0000_0000_0000
+            }
+        }
+    }
+
+    /// `None` for Float16 without the `f16` feature.
+    fn encode(
+        self,
+        v: f32,
+    ) -> Option<[u8; 8]> {
+        let mut r = [0; 8];
+        match self {
+            #[cfg(feature = "f16")]
+            Self::F16 => {
+                r[..2].copy_from_slice(&half::f16::from_f32(v).to_le_bytes())
+            }
+            #[cfg(not(feature = "f16"))]
+            Self::F16 => return None,
+            Self::F32 => r[..4].copy_from_slice(&v.to_le_bytes()),
+            Self::F64 => r.copy_from_slice(&(v as f64).to_le_bytes()),
+        }
+        Some(r)
+    }
+}
@@ -0,0 +1,25 @@
+use crate::descriptor::IndexFormat;
+
+use super::{MeshData, MeshDataRef, MeshError};
+
+/// Store the indices of a mesh as U16.
+///
+/// Fails with [`MeshError::IndexTooLarge`], giving the largest index, if
+/// some index does not fit in 16 bits. U16 indices are kept as they are.
+pub fn downconvert_indices(
+    mesh: &MeshDataRef<'_>,
+) -> Result<MeshData, MeshError> {
+    let indices = mesh.iter_indices().ok_or(MeshError::NotIndexed)?;
+    let mut r = MeshData::from(mesh);
+    if let Some((IndexFormat::U16, _)) = mesh.indices {
+        return Ok(r);
+    }
+    let indices: Vec<_> = indices.collect();
+    let max_index = indices.iter().copied().max().unwrap_or(0);
+    if max_index > u16::MAX as u32 {
+        return Err(MeshError::IndexTooLarge { max_index });
+    }
+    let indices: Vec<_> = indices.into_iter().map(|i| i as u16).collect();
+    r.set_indices_u16(&indices);
+    Ok(r)
+}
@@ -0,0 +1,116 @@
+use crate::convert::negate_components;
+use crate::descriptor::VertexUsage;
+
+use super::{MeshData, MeshDataRef, MeshError, is_degenerate_triangle};
+
+/// How to compute vertex normals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NormalMode {
+    /// Each vertex gets the normal of its triangle.
+    ///
+    /// This only gives faceted shading if triangles do not share vertices
+    /// (e.g. non-indexed meshes). A shared vertex gets the normal of the
+    /// last triangle using it, so do not [`weld`](super::weld) the mesh
+    /// before generating flat normals.
+    Flat,
+    /// Each vertex gets the average normal of the triangles using it,
+    /// weighted by their area.
+    Smooth,
+}
+
+/// Compute vertex normals from the positions, as Float32x3 data.
+///
+/// Degenerate triangles are skipped. Vertices that are not part of any
+/// (non-degenerate) triangle get a zero normal.
+pub fn generate_normals(
+    mesh: &MeshDataRef<'_>,
+    mode: NormalMode,
+) -> Result<Vec<u8>, MeshError> {
+    let positions: Vec<[f32; 3]> =
+        mesh.positions_f32().ok_or(MeshError::NoPositions)?.collect();
+    let n_vertices = positions.len();
+    let mut normals = vec![[0.0f32; 3]; n_vertices];
+    for t in mesh.iter_triangles()? {
+        if let Some(&index) = t.iter().find(|i| **i as usize >= n_vertices) {
+            return Err(MeshError::IndexOutOfRange { index, n_vertices });
+        }
+        if is_degenerate_triangle(t) {
+            continue;
+        }
+        let [a, b, c] = t.map(|i| positions[i as usize]);
+        // Its length is twice the area, which gives the weighting.
+        let n = cross(sub(b, a), sub(c, a));
+        match mode {
+            NormalMode::Flat => {
+                let Some(n) = normalize(n) else {
+                    continue;
+                };
+                for i in t {
+                    normals[i as usize] = n;
+                }
+            }
+            NormalMode::Smooth => {
+                for i in t {
+                    let v = &mut normals[i as usize];
+                    *v = std::array::from_fn(|k| v[k] + n[k]);
+                }
+            }
+        }
+    }
+    if mode == NormalMode::Smooth {
+        for v in normals.iter_mut() {
+            *v = normalize(*v).unwrap_or_default();
+        }
+    }
+    Ok(bytemuck::cast_slice(&normals).to_vec())
+}
+
+/// Flip the normals of a mesh, without changing the winding.
+///
+/// Every normal is negated, and the handedness (w) of tangents is flipped
+/// to keep the tangent basis consistent. Works with float, signed and
+/// signed normalized formats; special encodings (such as octahedral
+/// normals) must be decoded first. Returns [`MeshError::NotSigned`] for
+/// other formats.
+pub fn invert_normals(mesh: &MeshDataRef<'_>) -> Result<MeshData, MeshError> {
+    if !mesh.attributes.contains_key(&VertexUsage::Normal) {
+        return Err(MeshError::NoNormals);
+    }
+    let mut r = MeshData::from(mesh);
+    let negate = [
+        (VertexUsage::Normal, [true; 4]),
+        (VertexUsage::Tangent, [false, false, false, true]),
+    ];
+    for (usage, components) in negate {
+        let Some(&(format, bytes)) = mesh.attributes.get(&usage) else {
+            continue;
+        };
+        let data = negate_components(format, bytes, components)
+            .ok_or(MeshError::NotSigned { usage, format })?;
+        r.set_attribute_bytes(usage, format, &data)?;
+    }
+    Ok(r)
+}
+
+fn sub(
+    a: [f32; 3],
+    b: [f32; 3],
+) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(
+    a: [f32; 3],
+    b: [f32; 3],
+) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: [f32; 3]) -> Option<[f32; 3]> {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    (len > 0.0 && len.is_finite()).then(|| v.map(|c| c / len))
+}
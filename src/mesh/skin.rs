@@ -0,0 +1,194 @@
+use crate::convert::convert_vertex_data;
+use crate::descriptor::{VertexFormat, VertexUsage};
+
+use super::{MeshData, MeshDataRef, MeshError};
+
+/// The joint index and weight attributes of each influence set.
+const SETS: [(VertexUsage, VertexUsage); 2] = [
+    (VertexUsage::JointIndex, VertexUsage::JointWeight),
+    (VertexUsage::JointIndex1, VertexUsage::JointWeight1),
+];
+
+/// Find vertices whose joint weights do not sum to 1.
+///
+/// Weights can be stored in any float or normalized format with 4
+/// components. If the mesh has a second set of weights
+/// ([`VertexUsage::JointWeight1`]), the sum is over both sets. Returns the
+/// indices of the vertices whose sum differs from 1 by more than
+/// `tolerance` (or is not finite).
+pub fn check_joint_weights(
+    mesh: &MeshDataRef<'_>,
+    tolerance: f32,
+) -> Result<Vec<usize>, MeshError> {
+    let weights = joint_weights(mesh)?;
+    Ok(weights
+        .iter()
+        .enumerate()
+        .filter(|(_, w)| {
+            let sum: f32 = w.iter().sum();
+            let ok = (sum - 1.0).abs() <= tolerance;
+            !ok
+        })
+        .map(|(v, _)| v)
+        .collect())
+}
+
+/// Rescale the joint weights of every vertex so that they sum to 1.
+///
+/// Following the usual glTF sanitization rules, negative and non-finite
+/// weights are set to zero first, as are the weights of slots whose joint
+/// index repeats an earlier slot with a non-zero weight (if the mesh has
+/// joint indices). Vertices whose weights are all zero get a weight of 1
+/// on their first slot. With a second set of weights, both sets are
+/// treated as one list of 8 slots; the second set must then be in the same
+/// format as the first.
+///
+/// Weights stored as Float16x4 or Float32x4 are rescaled directly.
+/// Weights stored as Unorm8x4 or Unorm16x4 are re-quantized so that the
+/// stored integers sum exactly to the maximum value, rounding the weights
+/// with the largest remainders up.
+pub fn normalize_joint_weights(
+    mesh: &MeshDataRef<'_>,
+) -> Result<MeshData, MeshError> {
+    let usage = VertexUsage::JointWeight;
+    let &(format, _) = mesh
+        .attributes
+        .get(&usage)
+        .ok_or(MeshError::MissingAttribute(usage))?;
+    let n_sets = match mesh.attributes.get(&VertexUsage::JointWeight1) {
+        Some(&(format1, _)) if format1 != format => {
+            return Err(MeshError::UnsupportedFormat {
+                usage: VertexUsage::JointWeight1,
+                format: format1,
+            });
+        }
+        Some(_) => 2,
+        None => 1,
+    };
+    let joints = joint_indices(mesh)?;
+    let weights = joint_weights(mesh)?;
+    let weights = weights.into_iter().zip(joints).map(|(mut w, joints)| {
+        for k in 0..8 {
+            let duplicate = joints[k].is_some_and(|joint| {
+                (0..k).any(|j| joints[j] == Some(joint) && w[j] > 0.0)
+            });
+            let valid = w[k].is_finite() && w[k] > 0.0;
+            if duplicate || !valid {
+                w[k] = 0.0;
+            }
+        }
+        let sum: f32 = w.iter().sum();
+        if sum > 0.0 {
+            w.map(|w| w / sum)
+        } else {
+            std::array::from_fn(|k| if k == 0 { 1.0 } else { 0.0 })
+        }
+    });
+    let weights: Vec<[f32; 8]> = match format {
+        VertexFormat::Float32x4 | VertexFormat::Float16x4 => weights.collect(),
+        VertexFormat::Unorm8x4 => weights
+            .map(|w| quantize(w, u8::MAX as u32).map(|v| v as f32))
+            .collect(),
+        VertexFormat::Unorm16x4 => weights
+            .map(|w| quantize(w, u16::MAX as u32).map(|v| v as f32))
+            .collect(),
+        _ => return Err(MeshError::UnsupportedFormat { usage, format }),
+    };
+
+    let mut r = MeshData::from(mesh);
+    for (set, (_, usage)) in SETS.into_iter().enumerate().take(n_sets) {
+        let values = weights.iter().flat_map(|w| &w[set * 4..set * 4 + 4]);
+        let data: Vec<u8> = match format {
+            VertexFormat::Unorm8x4 => values.map(|v| *v as u8).collect(),
+            VertexFormat::Unorm16x4 => values
+                .flat_map(|v| (*v as u16).to_le_bytes())
+                .collect(),
+            _ => {
+                let floats: Vec<f32> = values.copied().collect();
+                let mut data = vec![];
+                convert_vertex_data(
+                    VertexFormat::Float32x4,
+                    format,
+                    bytemuck::cast_slice(&floats),
+                    &mut data,
+                );
+                data
+            }
+        };
+        r.set_attribute_bytes(usage, format, &data)?;
+    }
+    Ok(r)
+}
+
+/// The weights of both sets (the second one is zero if the mesh has none).
+fn joint_weights(mesh: &MeshDataRef<'_>) -> Result<Vec<[f32; 8]>, MeshError> {
+    let mut r = vec![[0.0; 8]; mesh.n_vertices()];
+    for (set, (_, usage)) in SETS.into_iter().enumerate() {
+        let Some(&(format, _)) = mesh.attributes.get(&usage) else {
+            if set == 0 {
+                return Err(MeshError::MissingAttribute(usage));
+            }
+            continue;
+        };
+        let weights = mesh
+            .attribute_f32::<4>(usage)
+            .ok_or(MeshError::UnsupportedFormat { usage, format })?;
+        for (r, w) in r.iter_mut().zip(weights) {
+            r[set * 4..set * 4 + 4].copy_from_slice(&w);
+        }
+    }
+    Ok(r)
+}
+
+/// The joint index of every slot of both sets (`None` for the slots of a
+/// set without joint indices).
+fn joint_indices(
+    mesh: &MeshDataRef<'_>,
+) -> Result<Vec<[Option<u32>; 8]>, MeshError> {
+    let mut r = vec![[None; 8]; mesh.n_vertices()];
+    for (set, (usage, _)) in SETS.into_iter().enumerate() {
+        let Some(&(format, bytes)) = mesh.attributes.get(&usage) else {
+            continue;
+        };
+        let joints: Vec<[u32; 4]> = match format {
+            VertexFormat::Uint8x4 => bytes
+                .chunks_exact(4)
+                .map(|b| std::array::from_fn(|k| b[k] as u32))
+                .collect(),
+            VertexFormat::Uint16x4 => bytes
+                .chunks_exact(8)
+                .map(|b| {
+                    std::array::from_fn(|k| {
+                        u16::from_le_bytes([b[k * 2], b[k * 2 + 1]]) as u32
+                    })
+                })
+                .collect(),
+            _ => return Err(MeshError::UnsupportedFormat { usage, format }),
+        };
+        for (r, j) in r.iter_mut().zip(joints) {
+            for (slot, joint) in r[set * 4..].iter_mut().zip(j) {
+                *slot = Some(joint);
+            }
+        }
+    }
+    Ok(r)
+}
+
+/// Quantize weights that sum to 1 into integers that sum to `max`.
+fn quantize<const N: usize>(
+    w: [f32; N],
+    max: u32,
+) -> [u32; N] {
+    let scaled = w.map(|w| w as f64 * max as f64);
+    let mut r = scaled.map(|s| s.floor() as u32);
+    let missing = max.saturating_sub(r.iter().sum());
+    let mut order: [usize; N] = std::array::from_fn(|k| k);
+    order.sort_by(|a, b| {
+        let fract = |k: usize| scaled[k] - r[k] as f64;
+        fract(*b).total_cmp(&fract(*a))
+    });
+    for k in order.into_iter().take(missing as usize) {
+        r[k] += 1;
+    }
+    r
+}
@@ -0,0 +1,270 @@
+use crate::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+
+use super::{MeshData, MeshDataRef, MeshError};
+
+/// Bake a transform into the vertex data.
+///
+/// `matrix` is column-major (`matrix[column][row]`, the translation is
+/// `matrix[3]`), like `glam::Mat4::to_cols_array_2d`. Positions are
+/// transformed as points, normals by the inverse-transpose and tangents as
+/// directions. Normals and tangents are renormalized.
+///
+/// If the transform mirrors the mesh (negative determinant), the winding
+/// of every triangle is reversed, so that front faces stay front faces,
+/// and the handedness (w) of tangents is negated, so that bitangents stay
+/// consistent. Non-indexed meshes have their vertices reordered for that.
+///
+/// Positions, normals and tangents are decoded as floats and written as
+/// Float32x3, Float32x3 and Float32x4. Other attributes are copied as-is.
+pub fn transform(
+    mesh: &MeshDataRef<'_>,
+    matrix: [[f32; 4]; 4],
+) -> Result<MeshData, MeshError> {
+    let linear: [[f32; 3]; 3] =
+        std::array::from_fn(|c| std::array::from_fn(|r| matrix[c][r]));
+    let cofactor = cofactor(linear);
+    let det: f32 = (0..3).map(|r| linear[0][r] * cofactor[0][r]).sum();
+    // The cofactor matrix is the inverse-transpose scaled by the
+    // determinant, which does not matter after normalizing, except for
+    // its sign. It also works for non-invertible matrices.
+    let normal_matrix = cofactor.map(|c| c.map(|v| v * det.signum()));
+    let mirror = det < 0.0;
+
+    let mut r = MeshData::new();
+    for (usage, (format, bytes)) in mesh.attributes.iter() {
+        match usage {
+            VertexUsage::Position => {
+                let data: Vec<[f32; 3]> = mesh
+                    .positions_f32()
+                    .ok_or(MeshError::NoPositions)?
+                    .map(|p| transform_point(matrix, p))
+                    .collect();
+                r.set_attribute_pod(*usage, VertexFormat::Float32x3, &data)?;
+            }
+            VertexUsage::Normal => {
+                let data: Vec<[f32; 3]> = mesh
+                    .normals_f32()
+                    .ok_or(MeshError::NotFloat(*usage))?
+                    .map(|n| normalize(mul(normal_matrix, n)))
+                    .collect();
+                r.set_attribute_pod(*usage, VertexFormat::Float32x3, &data)?;
+            }
+            VertexUsage::Tangent => {
+                let data: Vec<[f32; 4]> = mesh
+                    .attribute_f32::<4>(*usage)
+                    .ok_or(MeshError::NotFloat(*usage))?
+                    .map(|[x, y, z, w]| {
+                        let [x, y, z] = normalize(mul(linear, [x, y, z]));
+                        [x, y, z, if mirror { -w } else { w }]
+                    })
+                    .collect();
+                r.set_attribute_pod(*usage, VertexFormat::Float32x4, &data)?;
+            }
+            _ => {
+                r.set_attribute_bytes(*usage, *format, bytes)?;
+            }
+        }
+    }
+
+    if mirror {
+        let triangles = mesh.iter_triangles()?;
+        match mesh.indices {
+            Some((IndexFormat::U16, _)) => {
+                let indices: Vec<u16> = triangles
+                    .flat_map(|[a, b, c]| [a, c, b])
+                    .map(|i| i as u16)
+                    .collect();
+                r.set_indices_u16(&indices);
+            }
+            Some((IndexFormat::U32, _)) => {
+                let indices: Vec<u32> =
+                    triangles.flat_map(|[a, b, c]| [a, c, b]).collect();
+                r.set_indices_u32(&indices);
+            }
+            None => {
+                let order: Vec<usize> = triangles
+                    .flat_map(|[a, b, c]| [a, c, b])
+                    .map(|i| i as usize)
+                    .collect();
+                let unordered = r;
+                r = MeshData::new();
+                for (usage, (format, bytes)) in
+                    unordered.as_mesh_ref().attributes.iter()
+                {
+                    let size = format.size();
+                    let data: Vec<u8> = order
+                        .iter()
+                        .flat_map(|v| &bytes[v * size..(v + 1) * size])
+                        .copied()
+                        .collect();
+                    r.set_attribute_bytes(*usage, *format, &data)?;
+                }
+            }
+        }
+    } else if let Some(iter) = mesh.iter_indices() {
+        match mesh.indices {
+            Some((IndexFormat::U16, _)) => {
+                let indices: Vec<u16> = iter.map(|i| i as u16).collect();
+                r.set_indices_u16(&indices);
+            }
+            _ => {
+                r.set_indices_u32(&iter.collect::<Vec<_>>());
+            }
+        }
+    }
+    Ok(r)
+}
+
+/// Which point of a mesh [`recenter`] moves to the origin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CenterMode {
+    /// The center of the bounding box.
+    #[default]
+    Bounds,
+    /// The average of the vertex positions.
+    Centroid,
+}
+
+/// A translation followed by a uniform scale, as applied by [`recenter`]
+/// and [`normalize_scale`].
+///
+/// Apply it to other data (or record it, e.g. in user data) to keep it
+/// matching the mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AppliedTransform {
+    pub translation: [f32; 3],
+    pub scale: f32,
+}
+
+impl Default for AppliedTransform {
+    fn default() -> Self {
+        Self {
+            translation: [0.0; 3],
+            scale: 1.0,
+        }
+    }
+}
+
+impl AppliedTransform {
+    /// The column-major matrix, as accepted by [`transform`].
+    pub fn to_matrix(&self) -> [[f32; 4]; 4] {
+        let s = self.scale;
+        let t = self.translation.map(|v| v * s);
+        [
+            [s, 0.0, 0.0, 0.0],
+            [0.0, s, 0.0, 0.0],
+            [0.0, 0.0, s, 0.0],
+            [t[0], t[1], t[2], 1.0],
+        ]
+    }
+
+    /// Map a point the way the mesh was transformed.
+    pub fn apply(
+        &self,
+        p: [f32; 3],
+    ) -> [f32; 3] {
+        std::array::from_fn(|i| (p[i] + self.translation[i]) * self.scale)
+    }
+}
+
+/// Move a mesh so that its center is at the origin.
+///
+/// Returns the new mesh (written like [`transform`] does) and the
+/// translation that was applied. Meshes without vertices are unchanged.
+pub fn recenter(
+    mesh: &MeshDataRef<'_>,
+    mode: CenterMode,
+) -> Result<(MeshData, AppliedTransform), MeshError> {
+    let center = match mode {
+        CenterMode::Bounds => mesh
+            .position_bounds()
+            .map(|(min, max)| std::array::from_fn(|i| (min[i] + max[i]) / 2.0)),
+        CenterMode::Centroid => {
+            let (sum, n) = mesh
+                .positions_f32()
+                .ok_or(MeshError::NoPositions)?
+                .fold(([0.0f64; 3], 0), |(sum, n), p| {
+                    (std::array::from_fn(|i| sum[i] + p[i] as f64), n + 1)
+                });
+            (n > 0).then(|| sum.map(|v| (v / n as f64) as f32))
+        }
+    };
+    let applied = AppliedTransform {
+        translation: center.unwrap_or_default().map(|v| -v),
+        scale: 1.0,
+    };
+    Ok((transform(mesh, applied.to_matrix())?, applied))
+}
+
+/// Scale a mesh uniformly (around the origin) so that the largest extent
+/// of its bounding box is 1.
+///
+/// Returns the new mesh (written like [`transform`] does) and the scale
+/// factor that was applied. Meshes with zero or non-finite extent are not
+/// scaled (the factor is 1). Use [`recenter`] first to fit the mesh into
+/// -0.5..=0.5.
+pub fn normalize_scale(
+    mesh: &MeshDataRef<'_>,
+) -> Result<(MeshData, f32), MeshError> {
+    let extent = mesh
+        .position_bounds()
+        .map(|(min, max)| (0..3).map(|i| max[i] - min[i]).fold(0.0, f32::max))
+        .unwrap_or(0.0);
+    let scale = if extent > 0.0 && extent.is_finite() {
+        1.0 / extent
+    } else {
+        1.0
+    };
+    let applied = AppliedTransform {
+        translation: [0.0; 3],
+        scale,
+    };
+    Ok((transform(mesh, applied.to_matrix())?, scale))
+}
+
+fn transform_point(
+    m: [[f32; 4]; 4],
+    p: [f32; 3],
+) -> [f32; 3] {
+    let row = |r: usize| {
+        m[0][r] * p[0] + m[1][r] * p[1] + m[2][r] * p[2] + m[3][r]
+    };
+    let w = row(3);
+    let w = if w != 0.0 { w } else { 1.0 };
+    [row(0) / w, row(1) / w, row(2) / w]
+}
+
+/// Multiply a column-major 3x3 matrix by a vector.
+fn mul(
+    m: [[f32; 3]; 3],
+    v: [f32; 3],
+) -> [f32; 3] {
+    std::array::from_fn(|r| m[0][r] * v[0] + m[1][r] * v[1] + m[2][r] * v[2])
+}
+
+/// The cofactor matrix of a column-major 3x3 matrix.
+///
+/// Each column is the cross product of the other two columns.
+fn cofactor(m: [[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    [cross(m[1], m[2]), cross(m[2], m[0]), cross(m[0], m[1])]
+}
+
+fn cross(
+    a: [f32; 3],
+    b: [f32; 3],
+) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if len > 0.0 && len.is_finite() {
+        v.map(|c| c / len)
+    } else {
+        v
+    }
+}
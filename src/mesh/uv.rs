@@ -0,0 +1,46 @@
+use crate::convert::convert_vertex_data;
+use crate::descriptor::{VertexFormat, VertexUsage};
+
+use super::{MeshData, MeshDataRef, MeshError};
+
+/// Flip and/or scale and offset texture coordinates.
+///
+/// If `flip_v` is true, V is replaced by `1 - V` first (for engines where V
+/// points the other way). Then every coordinate is multiplied by `scale`
+/// and `offset` is added (e.g. to map into a region of a texture atlas).
+/// Values are not clamped, so tiling UVs outside of 0..=1 keep working.
+///
+/// `usage` must be stored as Float32x2 or Float16x2, and keeps its format.
+pub fn transform_uvs(
+    mesh: &MeshDataRef<'_>,
+    usage: VertexUsage,
+    scale: [f32; 2],
+    offset: [f32; 2],
+    flip_v: bool,
+) -> Result<MeshData, MeshError> {
+    let &(format, _) = mesh
+        .attributes
+        .get(&usage)
+        .ok_or(MeshError::MissingAttribute(usage))?;
+    if !matches!(format, VertexFormat::Float32x2 | VertexFormat::Float16x2) {
+        return Err(MeshError::UnsupportedFormat { usage, format });
+    }
+    let uvs: Vec<[f32; 2]> = mesh
+        .uvs_f32(usage)
+        .ok_or(MeshError::UnsupportedFormat { usage, format })?
+        .map(|[u, v]| {
+            let v = if flip_v { 1.0 - v } else { v };
+            [u * scale[0] + offset[0], v * scale[1] + offset[1]]
+        })
+        .collect();
+    let mut data = Vec::with_capacity(uvs.len() * format.size());
+    convert_vertex_data(
+        VertexFormat::Float32x2,
+        format,
+        bytemuck::cast_slice(&uvs),
+        &mut data,
+    );
+    let mut r = MeshData::from(mesh);
+    r.set_attribute_bytes(usage, format, &data)?;
+    Ok(r)
+}
@@ -0,0 +1,126 @@
+use crate::HashMap;
+use crate::descriptor::VertexUsage;
+
+use super::{MeshData, MeshDataRef, MeshError};
+
+/// Merge duplicate vertices, producing an indexed mesh.
+///
+/// Vertices are merged if all their attributes are bit-identical. With an
+/// `epsilon`, positions only need to be within `epsilon` of each other in
+/// every axis (compared as floats, see [`MeshDataRef::positions_f32`]),
+/// while the other attributes must still be identical. A spatial grid
+/// keeps this linear in the number of vertices.
+///
+/// Each group of duplicates is replaced by its first vertex, so the result
+/// is deterministic, and vertices keep their relative order. The indices
+/// are U16 if possible, U32 otherwise. Unreferenced vertices are kept.
+pub fn weld(
+    mesh: &MeshDataRef<'_>,
+    epsilon: Option<f32>,
+) -> Result<MeshData, MeshError> {
+    let n_vertices = mesh.n_vertices();
+    let epsilon = epsilon.filter(|e| *e > 0.0);
+    let positions = match epsilon {
+        Some(_) => Some(
+            mesh.positions_f32()
+                .ok_or(MeshError::NoPositions)?
+                .collect::<Vec<_>>(),
+        ),
+        None => None,
+    };
+    // Everything that must match exactly, one fixed-size record per vertex.
+    let exact: Vec<_> = mesh
+        .attributes
+        .iter()
+        .filter(|(usage, _)| {
+            positions.is_none() || **usage != VertexUsage::Position
+        })
+        .map(|(_, (format, bytes))| (format.size(), *bytes))
+        .collect();
+    let stride: usize = exact.iter().map(|(size, _)| size).sum();
+    let mut records = Vec::with_capacity(stride * n_vertices);
+    for v in 0..n_vertices {
+        for (size, bytes) in exact.iter() {
+            records.extend_from_slice(&bytes[v * size..(v + 1) * size]);
+        }
+    }
+    let record = |v: usize| &records[v * stride..(v + 1) * stride];
+
+    let mut kept: Vec<usize> = Vec::new();
+    let mut remap: Vec<u32> = Vec::with_capacity(n_vertices);
+    match (positions, epsilon) {
+        (Some(positions), Some(epsilon)) => {
+            let cell_of = |p: [f32; 3]| p.map(|c| (c / epsilon).floor() as i64);
+            let mut grid: HashMap<[i64; 3], Vec<u32>> = HashMap::default();
+            for (v, p) in positions.iter().enumerate() {
+                let cell = cell_of(*p);
+                let is_duplicate = |id: &&u32| {
+                    let other = kept[**id as usize];
+                    let q = positions[other];
+                    (0..3).all(|i| (q[i] - p[i]).abs() <= epsilon)
+                        && record(other) == record(v)
+                };
+                let found = neighbor_cells(cell).find_map(|c| {
+                    grid.get(&c)?.iter().find(is_duplicate).copied()
+                });
+                let id = found.unwrap_or_else(|| {
+                    let id = kept.len() as u32;
+                    kept.push(v);
+                    grid.entry(cell).or_default().push(id);
+                    id
+                });
+                remap.push(id);
+            }
+        }
+        _ => {
+            let mut seen: HashMap<&[u8], u32> = HashMap::default();
+            for v in 0..n_vertices {
+                let id = *seen.entry(record(v)).or_insert_with(|| {
+                    kept.push(v);
+                    kept.len() as u32 - 1
+                });
+                remap.push(id);
+            }
+        }
+    }
+
+    let mut r = MeshData::new();
+    for (usage, (format, bytes)) in mesh.attributes.iter() {
+        let size = format.size();
+        let data: Vec<u8> = kept
+            .iter()
+            .flat_map(|v| &bytes[v * size..(v + 1) * size])
+            .copied()
+            .collect();
+        r.set_attribute_bytes(*usage, *format, &data)?;
+    }
+    let indices = match mesh.iter_indices() {
+        Some(indices) => indices
+            .map(|i| {
+                remap.get(i as usize).copied().ok_or(
+                    MeshError::IndexOutOfRange {
+                        index: i,
+                        n_vertices,
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => remap,
+    };
+    if kept.len() <= u16::MAX as usize + 1 {
+        let indices: Vec<u16> = indices.iter().map(|i| *i as u16).collect();
+        r.set_indices_u16(&indices);
+    } else {
+        r.set_indices_u32(&indices);
+    }
+    Ok(r)
+}
+
+/// The 27 grid cells around (and including) a cell.
+fn neighbor_cells(cell: [i64; 3]) -> impl Iterator<Item = [i64; 3]> {
+    (-1..=1).flat_map(move |x| {
+        (-1..=1).flat_map(move |y| {
+            (-1..=1).map(move |z| [cell[0] + x, cell[1] + y, cell[2] + z])
+        })
+    })
+}
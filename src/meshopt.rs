@@ -0,0 +1,208 @@
+//! Mesh optimization using [meshoptimizer](https://meshoptimizer.org).
+//!
+//! Reorders triangles and vertices for faster rendering, without changing
+//! the triangles themselves, and simplifies meshes for LODs.
+
+use crate::HashMap;
+use crate::descriptor::IndexFormat;
+use crate::mesh::{MeshData, MeshDataRef, MeshError};
+
+/// Which optimizations to run, in this order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OptimizePasses {
+    /// Reorder triangles to improve the hit rate of the vertex cache.
+    pub vertex_cache: bool,
+    /// Reorder triangles to reduce overdraw, using the positions.
+    ///
+    /// The value is how much vertex cache efficiency may be sacrificed
+    /// (e.g. 1.05 for up to 5%).
+    pub overdraw: Option<f32>,
+    /// Reorder vertices in the order they are used, for better locality of
+    /// memory accesses. Vertices not used by any triangle are removed.
+    pub vertex_fetch: bool,
+}
+
+impl Default for OptimizePasses {
+    fn default() -> Self {
+        Self {
+            vertex_cache: true,
+            overdraw: Some(1.05),
+            vertex_fetch: true,
+        }
+    }
+}
+
+/// Size of the simulated vertex cache for [`acmr`].
+const CACHE_SIZE: u32 = 16;
+
+/// Optimize an indexed mesh.
+///
+/// All attribute buffers are reordered consistently, so the mesh renders
+/// the same triangles. The index format is kept. Non-indexed meshes are
+/// rejected with [`MeshError::NotIndexed`] (use [`crate::mesh::weld`] to
+/// index them first).
+pub fn optimize_mesh(
+    mesh: &MeshDataRef<'_>,
+    passes: OptimizePasses,
+) -> Result<MeshData, MeshError> {
+    let (index_format, _) = mesh.indices.ok_or(MeshError::NotIndexed)?;
+    let n_vertices = mesh.n_vertices();
+    let mut indices: Vec<u32> = mesh.iter_triangles()?.flatten().collect();
+    if let Some(&index) = indices.iter().find(|i| **i as usize >= n_vertices)
+    {
+        return Err(MeshError::IndexOutOfRange { index, n_vertices });
+    }
+    if passes.vertex_cache {
+        indices = meshopt::optimize_vertex_cache(&indices, n_vertices);
+    }
+    if let Some(threshold) = passes.overdraw {
+        let positions: Vec<[f32; 3]> =
+            mesh.positions_f32().ok_or(MeshError::NoPositions)?.collect();
+        meshopt::optimize_overdraw_in_place_decoder(
+            &mut indices,
+            &positions,
+            threshold,
+        );
+    }
+    // Old index of every output vertex.
+    let kept: Vec<usize> = if passes.vertex_fetch {
+        remap_first_use(&mut indices)
+    } else {
+        (0..n_vertices).collect()
+    };
+    build_mesh(mesh, &kept, &indices, index_format)
+}
+
+/// Settings for [`simplify`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimplifySettings {
+    /// Fraction of the triangles to aim for (e.g. 0.25 for a quarter).
+    pub target_ratio: f32,
+    /// Maximum deviation from the original surface, relative to the size
+    /// of the mesh (e.g. 0.01 for 1%).
+    ///
+    /// Simplification stops before reaching the target if it would need
+    /// to exceed this.
+    pub max_error: f32,
+    /// Do not move vertices on the border of the mesh (edges used by only
+    /// one triangle), so it still fits with adjacent meshes.
+    pub lock_border: bool,
+}
+
+impl Default for SimplifySettings {
+    fn default() -> Self {
+        Self {
+            target_ratio: 0.5,
+            max_error: 0.01,
+            lock_border: false,
+        }
+    }
+}
+
+/// Reduce the number of triangles of an indexed mesh, for LODs.
+///
+/// Uses meshoptimizer's edge collapse simplifier, which only ever collapses
+/// vertices into other existing vertices. Therefore, all attributes (UVs,
+/// normals, colors, ...) keep valid values. Vertices not used anymore are
+/// removed. The index format is kept.
+///
+/// Vertices with the same position but different attributes (seams) are
+/// separate vertices, which limits how much the mesh can be simplified
+/// around them. Non-indexed meshes are rejected with
+/// [`MeshError::NotIndexed`] (use [`crate::mesh::weld`] to index them
+/// first).
+pub fn simplify(
+    mesh: &MeshDataRef<'_>,
+    settings: SimplifySettings,
+) -> Result<MeshData, MeshError> {
+    let (index_format, _) = mesh.indices.ok_or(MeshError::NotIndexed)?;
+    let n_vertices = mesh.n_vertices();
+    let indices: Vec<u32> = mesh.iter_triangles()?.flatten().collect();
+    if let Some(&index) = indices.iter().find(|i| **i as usize >= n_vertices)
+    {
+        return Err(MeshError::IndexOutOfRange { index, n_vertices });
+    }
+    let positions: Vec<[f32; 3]> =
+        mesh.positions_f32().ok_or(MeshError::NoPositions)?.collect();
+    let n_triangles = indices.len() / 3;
+    let target = (n_triangles as f32 * settings.target_ratio.clamp(0.0, 1.0))
+        .round() as usize;
+    let mut options = meshopt::SimplifyOptions::None;
+    if settings.lock_border {
+        options |= meshopt::SimplifyOptions::LockBorder;
+    }
+    let mut indices = meshopt::simplify_decoder(
+        &indices,
+        &positions,
+        target * 3,
+        settings.max_error,
+        options,
+        None,
+    );
+    let kept = remap_first_use(&mut indices);
+    build_mesh(mesh, &kept, &indices, index_format)
+}
+
+/// Renumber vertices in the order they are first used.
+///
+/// Returns the old index of every new vertex.
+fn remap_first_use(indices: &mut [u32]) -> Vec<usize> {
+    let mut remap: HashMap<u32, u32> = HashMap::default();
+    let mut kept = vec![];
+    for i in indices.iter_mut() {
+        *i = *remap.entry(*i).or_insert_with(|| {
+            kept.push(*i as usize);
+            kept.len() as u32 - 1
+        });
+    }
+    kept
+}
+
+/// Build a mesh from the `kept` vertices of `mesh` and new indices.
+fn build_mesh(
+    mesh: &MeshDataRef<'_>,
+    kept: &[usize],
+    indices: &[u32],
+    index_format: IndexFormat,
+) -> Result<MeshData, MeshError> {
+    let mut r = MeshData::new();
+    for (usage, (format, bytes)) in mesh.attributes.iter() {
+        let size = format.size();
+        let data: Vec<u8> = kept
+            .iter()
+            .flat_map(|v| &bytes[v * size..(v + 1) * size])
+            .copied()
+            .collect();
+        r.set_attribute_bytes(*usage, *format, &data)?;
+    }
+    match index_format {
+        IndexFormat::U16 => {
+            let indices: Vec<u16> = indices.iter().map(|i| *i as u16).collect();
+            r.set_indices_u16(&indices);
+        }
+        IndexFormat::U32 => {
+            r.set_indices_u32(indices);
+        }
+    }
+    Ok(r)
+}
+
+/// Average cache miss ratio: vertex shader invocations per triangle.
+///
+/// Computed by meshoptimizer for a FIFO cache of 16 vertices. Lower is
+/// better; the minimum is about 0.5 and the maximum 3. Returns `None` for
+/// non-indexed meshes, or if the indices are out of range.
+pub fn acmr(mesh: &MeshDataRef<'_>) -> Option<f32> {
+    let indices = mesh.indices_to_vec_u32()?;
+    if indices.iter().any(|i| *i as usize >= mesh.n_vertices()) {
+        return None;
+    }
+    let stats = meshopt::analyze_vertex_cache(
+        &indices,
+        mesh.n_vertices(),
+        CACHE_SIZE,
+        0,
+        0,
+    );
+    Some(stats.acmr)
+}
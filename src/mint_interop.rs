@@ -0,0 +1,110 @@
+//! Conversions to and from [`mint`] types, for any math library that
+//! supports them.
+//!
+//! The setters accept anything convertible into the mint types, so the
+//! types of such libraries can be passed directly.
+
+use mint::{Point3, Vector2, Vector3, Vector4};
+
+use crate::descriptor::{PositionTransform, VertexUsage};
+use crate::mesh::{MeshData, MeshDataRef, MeshError};
+
+/// Lazily decode the positions of a mesh (see [`MeshDataRef::positions_f32`]).
+pub fn positions_point3<'s>(
+    mesh: &MeshDataRef<'s>,
+) -> Option<impl Iterator<Item = Point3<f32>> + 's> {
+    Some(mesh.positions_f32()?.map(Point3::from))
+}
+
+pub fn normals_vector3<'s>(
+    mesh: &MeshDataRef<'s>,
+) -> Option<impl Iterator<Item = Vector3<f32>> + 's> {
+    Some(mesh.normals_f32()?.map(Vector3::from))
+}
+
+pub fn uvs_vector2<'s>(
+    mesh: &MeshDataRef<'s>,
+    usage: VertexUsage,
+) -> Option<impl Iterator<Item = Vector2<f32>> + 's> {
+    Some(mesh.uvs_f32(usage)?.map(Vector2::from))
+}
+
+pub fn colors_vector4<'s>(
+    mesh: &MeshDataRef<'s>,
+) -> Option<impl Iterator<Item = Vector4<f32>> + 's> {
+    Some(mesh.colors_f32()?.map(Vector4::from))
+}
+
+/// Compute the bounding box of the positions of a mesh, as `(min, max)`.
+///
+/// Returns `None` if the mesh has no (decodable) positions or no vertices.
+pub fn position_aabb(
+    mesh: &MeshDataRef<'_>,
+) -> Option<(Point3<f32>, Point3<f32>)> {
+    let (min, max) = mesh.positions_f32()?.fold(None, |aabb, p| match aabb {
+        None => Some((p, p)),
+        Some((min, max)) => Some((
+            std::array::from_fn(|i| f32::min(min[i], p[i])),
+            std::array::from_fn(|i| f32::max(max[i], p[i])),
+        )),
+    })?;
+    Some((min.into(), max.into()))
+}
+
+/// The box covered by quantized positions, as `(min, max)`.
+pub fn transform_aabb(
+    transform: &PositionTransform,
+) -> (Point3<f32>, Point3<f32>) {
+    let max = std::array::from_fn(|i| transform.offset[i] + transform.scale[i]);
+    (transform.offset.into(), max.into())
+}
+
+fn to_arrays<T: Into<M>, M: Into<[f32; N]>, const N: usize>(
+    data: impl IntoIterator<Item = T>,
+) -> Vec<[f32; N]> {
+    data.into_iter().map(|v| v.into().into()).collect()
+}
+
+impl MeshData {
+    pub fn set_positions_mint<T: Into<Point3<f32>>>(
+        &mut self,
+        data: impl IntoIterator<Item = T>,
+    ) -> Result<&mut Self, MeshError> {
+        self.set_positions(&to_arrays::<T, Point3<f32>, 3>(data))
+    }
+
+    pub fn set_normals_mint<T: Into<Vector3<f32>>>(
+        &mut self,
+        data: impl IntoIterator<Item = T>,
+    ) -> Result<&mut Self, MeshError> {
+        self.set_normals(&to_arrays::<T, Vector3<f32>, 3>(data))
+    }
+
+    pub fn set_tangents_mint<T: Into<Vector4<f32>>>(
+        &mut self,
+        data: impl IntoIterator<Item = T>,
+    ) -> Result<&mut Self, MeshError> {
+        self.set_tangents(&to_arrays::<T, Vector4<f32>, 4>(data))
+    }
+
+    pub fn set_uv0_mint<T: Into<Vector2<f32>>>(
+        &mut self,
+        data: impl IntoIterator<Item = T>,
+    ) -> Result<&mut Self, MeshError> {
+        self.set_uv0(&to_arrays::<T, Vector2<f32>, 2>(data))
+    }
+
+    pub fn set_uv1_mint<T: Into<Vector2<f32>>>(
+        &mut self,
+        data: impl IntoIterator<Item = T>,
+    ) -> Result<&mut Self, MeshError> {
+        self.set_uv1(&to_arrays::<T, Vector2<f32>, 2>(data))
+    }
+
+    pub fn set_colors_mint<T: Into<Vector4<f32>>>(
+        &mut self,
+        data: impl IntoIterator<Item = T>,
+    ) -> Result<&mut Self, MeshError> {
+        self.set_colors_f32(&to_arrays::<T, Vector4<f32>, 4>(data))
+    }
+}
@@ -0,0 +1,489 @@
+//! Binary patches between two versions of a file.
+//!
+//! A [`Patch`] turns one file into another, byte for byte. It is made from
+//! the contents of the files, rather than their compressed bytes: the
+//! uncompressed payload is split into chunks (the user data, the part of
+//! each buffer used by each mesh, and each instance buffer), and every
+//! chunk of the new file is either a reference to an identical chunk of
+//! the old file (by hash), a delta against the same buffer of the old
+//! file, or stored in full. The header and descriptor of the new
+//! file are stored as-is.
+//!
+//! To reproduce the compressed data, the patch records the compression
+//! level, found by compressing the new payload again with the levels of
+//! the [`IyesMeshWriterSettings`] presets (or a given level). If none of
+//! them reproduces it (e.g. the file was written with another version of
+//! zstd), the compressed data is stored in full instead.
+
+use std::io::{Read, Write};
+
+use crate::HashMap;
+use crate::checksum::checksum_data;
+use crate::descriptor::{IyesMeshDescriptor, VertexUsage};
+use crate::header::IyesMeshHeader;
+use crate::io::{new_zstd_decoder, new_zstd_encoder};
+use crate::read::{IyesMeshReader, ReadError};
+use crate::write::{ENCODE_CHUNK_SIZE, IyesMeshWriterSettings};
+
+/// Magic bytes at the start of encoded patches.
+pub const PATCH_MAGIC: [u8; 4] = [b'I', b'y', b'M', b'P'];
+/// Version of the encoding of patches.
+pub const PATCH_VERSION: u16 = 1;
+
+/// Size of the blocks of the old chunk that deltas look for.
+const DELTA_BLOCK: usize = 16;
+/// Approximate encoded size of a delta operation, besides its data.
+const DELTA_OP_COST: usize = 8;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PatchError {
+    #[error("Cannot read file: {0}")]
+    Read(#[from] ReadError),
+    #[error("I/O: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Did not find magic bytes at start of patch")]
+    BadMagic,
+    #[error("Incompatible version of the patch format: {0}")]
+    BadVersion(u16),
+    #[error("Cannot decode patch: {0}")]
+    Decode(#[from] bitcode::Error),
+    #[error("The patch was made for a different file")]
+    WrongBase,
+    #[error("The patch refers to data not found in the old file")]
+    MissingChunk,
+    #[error("The patched file does not match the expected result")]
+    Mismatch,
+}
+
+/// The differences between two files.
+///
+/// Made with [`diff`], applied with [`apply`].
+#[derive(Debug, Clone, bitcode::Encode, bitcode::Decode)]
+pub struct Patch {
+    /// Checksum of the whole old file.
+    old_checksum: u64,
+    /// Checksum of the whole new file.
+    new_checksum: u64,
+    /// Header and descriptor of the new file.
+    metadata: Vec<u8>,
+    payload: PatchPayload,
+    /// Everything after the compressed data of the new file (the
+    /// signature, if any).
+    trailer: Vec<u8>,
+}
+
+#[derive(Debug, Clone, bitcode::Encode, bitcode::Decode)]
+enum PatchPayload {
+    /// The uncompressed payload, to be compressed with this level.
+    Chunks { level: i32, chunks: Vec<Chunk> },
+    /// The compressed data, as-is.
+    Compressed(Vec<u8>),
+}
+
+#[derive(Debug, Clone, bitcode::Encode, bitcode::Decode)]
+enum Chunk {
+    /// Identical to the chunk of the old file with this hash.
+    Same(u64),
+    /// Delta against the chunk or buffer of the old file with this hash.
+    Delta { base: u64, ops: Vec<DeltaOp> },
+    /// Stored in full.
+    Data(Vec<u8>),
+}
+
+#[derive(Debug, Clone, bitcode::Encode, bitcode::Decode)]
+enum DeltaOp {
+    /// Copy a range of the base chunk.
+    Copy { offset: u32, len: u32 },
+    /// Insert new bytes.
+    Insert(Vec<u8>),
+}
+
+/// The buffer of the payload that a chunk is part of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BufferId {
+    UserData,
+    Indices,
+    Attribute(VertexUsage),
+    Instances(VertexUsage),
+}
+
+impl Patch {
+    /// Encode the patch, for saving it to a file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, PatchError> {
+        let mut r = PATCH_MAGIC.to_vec();
+        r.extend_from_slice(&PATCH_VERSION.to_le_bytes());
+        let level = IyesMeshWriterSettings::smallest().compression_level;
+        r.extend(zstd::encode_all(bitcode::encode(self).as_slice(), level)?);
+        Ok(r)
+    }
+
+    /// Decode a patch encoded with [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, PatchError> {
+        if buf.get(..4) != Some(&PATCH_MAGIC) {
+            return Err(PatchError::BadMagic);
+        }
+        let version = buf
+            .get(4..6)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .ok_or(PatchError::BadMagic)?;
+        if version != PATCH_VERSION {
+            return Err(PatchError::BadVersion(version));
+        }
+        let encoded = zstd::decode_all(&buf[6..])?;
+        Ok(bitcode::decode(&encoded)?)
+    }
+
+    /// Whether the payload of the new file is stored compressed, as-is.
+    ///
+    /// Such patches are about as large as the new file.
+    pub fn is_verbatim(&self) -> bool {
+        matches!(self.payload, PatchPayload::Compressed(_))
+    }
+}
+
+/// Make a patch that turns `old` into `new` (both whole files).
+///
+/// If `compression_level` is given, it is tried first to reproduce the
+/// compressed data of the new file. Both files are fully decoded, and their
+/// checksums verified.
+pub fn diff(
+    old: &[u8],
+    new: &[u8],
+    compression_level: Option<i32>,
+) -> Result<Patch, PatchError> {
+    let old_file = SplitFile::new(old)?;
+    let new_file = SplitFile::new(new)?;
+    let old_buffers = old_file.buffers();
+    let old_hashes: HashMap<u64, usize> = old_file
+        .pieces()
+        .iter()
+        .chain(old_buffers.values())
+        .map(|bytes| (checksum_data(bytes), bytes.len()))
+        .collect();
+    let new_chunks = new_file.chunks();
+
+    let presets = [
+        IyesMeshWriterSettings::smallest(),
+        IyesMeshWriterSettings::balanced(),
+        IyesMeshWriterSettings::fastest(),
+    ];
+    let mut levels: Vec<i32> = compression_level.into_iter().collect();
+    for preset in presets {
+        if !levels.contains(&preset.compression_level) {
+            levels.push(preset.compression_level);
+        }
+    }
+    let pieces = new_file.pieces();
+    let mut level = None;
+    for candidate in levels {
+        if compress(candidate, &pieces)? == new_file.compressed {
+            level = Some(candidate);
+            break;
+        }
+    }
+
+    let payload = match level {
+        Some(level) => {
+            let mut indices: HashMap<BufferId, BlockIndex> =
+                HashMap::default();
+            let chunks = new_chunks
+                .iter()
+                .map(|(id, bytes)| {
+                    let hash = checksum_data(bytes);
+                    if old_hashes.get(&hash) == Some(&bytes.len()) {
+                        return Chunk::Same(hash);
+                    }
+                    old_buffers
+                        .get(id)
+                        .and_then(|base| {
+                            indices
+                                .entry(*id)
+                                .or_insert_with(|| BlockIndex::new(base))
+                                .delta(bytes)
+                        })
+                        .unwrap_or_else(|| Chunk::Data(bytes.to_vec()))
+                })
+                .collect();
+            PatchPayload::Chunks { level, chunks }
+        }
+        None => PatchPayload::Compressed(new_file.compressed.to_vec()),
+    };
+    Ok(Patch {
+        old_checksum: checksum_data(old),
+        new_checksum: checksum_data(new),
+        metadata: new_file.metadata.to_vec(),
+        payload,
+        trailer: new_file.trailer.to_vec(),
+    })
+}
+
+/// Apply a patch to the file it was made from, returning the new file.
+pub fn apply(
+    old: &[u8],
+    patch: &Patch,
+) -> Result<Vec<u8>, PatchError> {
+    if checksum_data(old) != patch.old_checksum {
+        return Err(PatchError::WrongBase);
+    }
+    let mut r = patch.metadata.clone();
+    match &patch.payload {
+        PatchPayload::Chunks { level, chunks } => {
+            let old_file = SplitFile::new(old)?;
+            let old_by_hash: HashMap<u64, &[u8]> = old_file
+                .pieces()
+                .into_iter()
+                .chain(old_file.buffers().into_values())
+                .map(|bytes| (checksum_data(bytes), bytes))
+                .collect();
+            let pieces = chunks
+                .iter()
+                .map(|chunk| apply_chunk(chunk, &old_by_hash))
+                .collect::<Result<Vec<_>, _>>()?;
+            let pieces: Vec<&[u8]> = pieces.iter().map(|p| &**p).collect();
+            r.extend(compress(*level, &pieces)?);
+        }
+        PatchPayload::Compressed(compressed) => {
+            r.extend_from_slice(compressed);
+        }
+    }
+    r.extend_from_slice(&patch.trailer);
+    if checksum_data(&r) != patch.new_checksum {
+        return Err(PatchError::Mismatch);
+    }
+    Ok(r)
+}
+
+fn apply_chunk<'a>(
+    chunk: &'a Chunk,
+    old_by_hash: &HashMap<u64, &'a [u8]>,
+) -> Result<std::borrow::Cow<'a, [u8]>, PatchError> {
+    let find = |hash| old_by_hash.get(hash).ok_or(PatchError::MissingChunk);
+    match chunk {
+        Chunk::Same(hash) => Ok((*find(hash)?).into()),
+        Chunk::Delta { base, ops } => {
+            let base = find(base)?;
+            let mut r = vec![];
+            for op in ops {
+                match op {
+                    DeltaOp::Copy { offset, len } => {
+                        let range = *offset as usize..(offset + len) as usize;
+                        let bytes =
+                            base.get(range).ok_or(PatchError::MissingChunk)?;
+                        r.extend_from_slice(bytes);
+                    }
+                    DeltaOp::Insert(bytes) => r.extend_from_slice(bytes),
+                }
+            }
+            Ok(r.into())
+        }
+        Chunk::Data(bytes) => Ok(bytes.as_slice().into()),
+    }
+}
+
+/// The blocks of an old buffer (at multiples of [`DELTA_BLOCK`]), to
+/// encode new chunks as ranges of it and inserted bytes.
+struct BlockIndex<'a> {
+    base: &'a [u8],
+    blocks: HashMap<&'a [u8], usize>,
+}
+
+impl<'a> BlockIndex<'a> {
+    fn new(base: &'a [u8]) -> Self {
+        let mut blocks = HashMap::default();
+        for (i, block) in base.chunks_exact(DELTA_BLOCK).enumerate() {
+            blocks.entry(block).or_insert(i * DELTA_BLOCK);
+        }
+        Self { base, blocks }
+    }
+
+    /// Look for the blocks at every position of `new`, and extend each
+    /// match as far as it goes. `None` if that is not smaller than `new`.
+    fn delta(
+        &self,
+        new: &[u8],
+    ) -> Option<Chunk> {
+        let mut ops = vec![];
+        let mut insert = vec![];
+        let mut size = 0;
+        let mut i = 0;
+        while i < new.len() {
+            let found =
+                new.get(i..i + DELTA_BLOCK).and_then(|w| self.blocks.get(w));
+            let Some(&offset) = found else {
+                insert.push(new[i]);
+                i += 1;
+                continue;
+            };
+            let len = self.base[offset..]
+                .iter()
+                .zip(&new[i..])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if !insert.is_empty() {
+                size += DELTA_OP_COST + insert.len();
+                ops.push(DeltaOp::Insert(std::mem::take(&mut insert)));
+            }
+            size += DELTA_OP_COST;
+            ops.push(DeltaOp::Copy {
+                offset: offset as u32,
+                len: len as u32,
+            });
+            i += len;
+        }
+        if !insert.is_empty() {
+            size += DELTA_OP_COST + insert.len();
+            ops.push(DeltaOp::Insert(insert));
+        }
+        (size < new.len()).then(|| Chunk::Delta {
+            base: checksum_data(self.base),
+            ops,
+        })
+    }
+}
+
+/// Compress the payload, feeding it to the encoder like the writer does.
+fn compress(
+    level: i32,
+    pieces: &[&[u8]],
+) -> Result<Vec<u8>, PatchError> {
+    let total: usize = pieces.iter().map(|p| p.len()).sum();
+    let mut encoder = new_zstd_encoder(vec![], level, total as u64)?;
+    for piece in pieces {
+        for chunk in piece.chunks(ENCODE_CHUNK_SIZE) {
+            encoder.write_all(chunk)?;
+        }
+    }
+    Ok(encoder.finish()?)
+}
+
+/// A file, split into its parts, with the payload decompressed.
+struct SplitFile<'a> {
+    version: u16,
+    descriptor: IyesMeshDescriptor,
+    metadata: &'a [u8],
+    compressed: &'a [u8],
+    trailer: &'a [u8],
+    payload: Vec<u8>,
+}
+
+impl<'a> SplitFile<'a> {
+    fn new(bytes: &'a [u8]) -> Result<Self, ReadError> {
+        let mut cursor = std::io::Cursor::new(bytes);
+        let reader = IyesMeshReader::init(&mut cursor)?;
+        let header = *reader.header();
+        let descriptor = reader.descriptor().clone();
+        reader.verify_data_checksum()?;
+        let payload_start = IyesMeshHeader::encoded_len()
+            + header.descriptor_len as usize;
+        let mut payload_end = bytes.len();
+        if descriptor.signed {
+            payload_end = payload_end
+                .checked_sub(crate::SIGNATURE_LEN)
+                .ok_or(ReadError::NotEnoughData)?;
+        }
+        let compressed = bytes
+            .get(payload_start..payload_end)
+            .ok_or(ReadError::NotEnoughData)?;
+        let mut payload = vec![];
+        new_zstd_decoder(compressed)?.read_to_end(&mut payload)?;
+        let expected = descriptor.compute_total_raw_data_size() as usize;
+        if payload.len() < expected {
+            return Err(ReadError::NotEnoughData);
+        }
+        if payload.len() > expected {
+            return Err(ReadError::TooMuchData);
+        }
+        Ok(Self {
+            version: header.version,
+            descriptor,
+            metadata: &bytes[..payload_start],
+            compressed,
+            trailer: &bytes[payload_end..],
+            payload,
+        })
+    }
+
+    /// Split the payload into chunks: each buffer, split by mesh if the
+    /// meshes use consecutive ranges of it.
+    fn chunks(&self) -> Vec<(BufferId, &[u8])> {
+        let d = &self.descriptor;
+        let mut r = vec![];
+        let mut remain = &self.payload[..];
+        let mut take = |id, len: usize| {
+            let (chunk, rest) = remain.split_at(len);
+            r.push((id, chunk));
+            remain = rest;
+        };
+        take(BufferId::UserData, d.user_data_len as usize);
+        if let Some(info) = d.indices {
+            let ranges: Vec<_> = d
+                .meshes
+                .iter()
+                .map(|m| (m.first_index, m.index_count))
+                .collect();
+            let counts = split_contiguous(&ranges, info.n_indices);
+            for count in counts {
+                take(BufferId::Indices, count * info.format.size());
+            }
+        }
+        let order = if self.version < 2 {
+            d.attributes.keys().copied().collect()
+        } else {
+            d.attribute_order()
+        };
+        let ranges: Vec<_> = d
+            .meshes
+            .iter()
+            .map(|m| (m.first_vertex, m.vertex_count))
+            .collect();
+        let counts = split_contiguous(&ranges, d.n_vertices);
+        for usage in order {
+            let size = d.attributes[&usage].size();
+            for count in counts.iter() {
+                take(BufferId::Attribute(usage), count * size);
+            }
+        }
+        for usage in d.instance_attribute_order() {
+            let size = d.instance_attributes[&usage].size();
+            take(BufferId::Instances(usage), d.n_instances as usize * size);
+        }
+        r
+    }
+
+    fn pieces(&self) -> Vec<&[u8]> {
+        self.chunks().into_iter().map(|(_, bytes)| bytes).collect()
+    }
+
+    /// The whole buffers (the chunks of each buffer are consecutive).
+    fn buffers(&self) -> HashMap<BufferId, &[u8]> {
+        let mut r = HashMap::default();
+        let mut start = 0;
+        for (id, bytes) in self.chunks() {
+            let end = start + bytes.len();
+            r.entry(id).or_insert(start..end).end = end;
+            start = end;
+        }
+        r.into_iter()
+            .map(|(id, range)| (id, &self.payload[range]))
+            .collect()
+    }
+}
+
+/// The length of each range, if they follow each other and cover exactly
+/// `0..total`, or just `total` otherwise.
+fn split_contiguous(
+    ranges: &[(u32, u32)],
+    total: u32,
+) -> Vec<usize> {
+    let mut end = 0;
+    for &(first, count) in ranges {
+        if first != end {
+            return vec![total as usize];
+        }
+        end = first.saturating_add(count);
+    }
+    if end != total {
+        return vec![total as usize];
+    }
+    ranges.iter().map(|r| r.1 as usize).collect()
+}
@@ -1,11 +1,13 @@
-use std::io::{Read, SeekFrom};
+use std::io::{BufRead, Read, SeekFrom};
 
-use crate::checksum::checksum_data;
+use crate::checksum::{ChecksumKind, ChecksummingReader};
 use crate::HashMap;
+use crate::cancel::CancelToken;
 use crate::descriptor::*;
 use crate::header::{IyesMeshHeader, IyesMeshHeaderParseError};
 use crate::io::*;
-use crate::mesh::MeshDataRef;
+use crate::mesh::{encode, MeshDataRef};
+use crate::view::{AttrView, ViewError};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ReadError {
@@ -25,12 +27,55 @@ pub enum ReadError {
     NotEnoughData,
     #[error("Unexpected extra data")]
     TooMuchData,
+    #[error("Operation was cancelled")]
+    Cancelled,
+    #[error("User data is encrypted, but no key was provided")]
+    UserDataEncrypted,
+    #[error("Cannot decrypt user data (wrong key or corrupted data)")]
+    UserDataDecryption,
+    #[error("File is not signed, but a signature was required")]
+    SignatureMissing,
+    #[error("Signature does not match the file or the public key")]
+    SignatureInvalid,
+    #[error(
+        "Unsupported checksum algorithm: {name} (id {id})",
+        name = ChecksumKind::name_of_id(*.0).unwrap_or("unknown"),
+        id = .0
+    )]
+    UnsupportedChecksum(u8),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct IyesMeshReaderSettings {
     pub verify_metadata_checksum: bool,
     pub verify_data_checksum: bool,
+    /// Check the user data against its own checksum, if the file has one.
+    ///
+    /// [`IyesMeshReader::read_user_data`] then skips the data checksum, so
+    /// that only the user data has to be hashed.
+    pub verify_user_data_checksum: bool,
+    /// Ignore data decompressed beyond what the descriptor declares,
+    /// instead of failing with [`ReadError::TooMuchData`].
+    ///
+    /// The extra bytes are available from
+    /// [`IyesMeshReaderWithData::trailing_data`]. Leave this off to catch
+    /// corrupted files.
+    pub allow_trailing_data: bool,
+    /// Key to decrypt the user data with, if it is encrypted.
+    ///
+    /// See [`crate::encryption`].
+    #[cfg(feature = "encryption")]
+    pub user_data_key: Option<[u8; 32]>,
+    /// Require the file to be signed with the secret key matching this
+    /// Ed25519 public key.
+    ///
+    /// See [`crate::signing`]. Unsigned files are rejected with
+    /// [`ReadError::SignatureMissing`]. The signature covers the whole
+    /// file, so it is verified whenever the data is read (even
+    /// [`IyesMeshReader::read_user_data`] then reads all of it). If this is
+    /// not set, signatures are ignored.
+    #[cfg(feature = "signing")]
+    pub verifying_key: Option<[u8; 32]>,
 }
 
 impl Default for IyesMeshReaderSettings {
@@ -38,6 +83,12 @@ impl Default for IyesMeshReaderSettings {
         Self {
             verify_metadata_checksum: true,
             verify_data_checksum: true,
+            verify_user_data_checksum: true,
+            allow_trailing_data: false,
+            #[cfg(feature = "encryption")]
+            user_data_key: None,
+            #[cfg(feature = "signing")]
+            verifying_key: None,
         }
     }
 }
@@ -47,24 +98,194 @@ pub struct DecodedBuffers<'s> {
     pub user_data: Option<&'s [u8]>,
     pub buf_index: Option<(IndexFormat, &'s [u8])>,
     pub buf_attrs: HashMap<VertexUsage, (VertexFormat, &'s [u8])>,
+    /// Per-instance attributes, with data for all instances.
+    pub buf_instances: HashMap<VertexUsage, (VertexFormat, &'s [u8])>,
+}
+
+impl<'s> DecodedBuffers<'s> {
+    /// View an attribute as a slice of `T`, copying it if misaligned.
+    ///
+    /// The size of `T` must be the size of the attribute's format.
+    pub fn attr_view<T: bytemuck::Pod>(
+        &self,
+        usage: VertexUsage,
+    ) -> Result<AttrView<'s, T>, ViewError> {
+        let (format, bytes) =
+            *self.buf_attrs.get(&usage).ok_or(ViewError::Missing)?;
+        crate::view::view_of(format.size(), bytes)
+    }
+
+    /// View an attribute as a slice of `T`, without copying.
+    pub fn attr_slice<T: bytemuck::Pod>(
+        &self,
+        usage: VertexUsage,
+    ) -> Result<&'s [T], ViewError> {
+        let (format, bytes) =
+            *self.buf_attrs.get(&usage).ok_or(ViewError::Missing)?;
+        crate::view::slice_of(format.size(), bytes)
+    }
+
+    /// View the indices as a slice of `T`, copying them if misaligned.
+    pub fn index_view<T: bytemuck::Pod>(
+        &self,
+    ) -> Result<AttrView<'s, T>, ViewError> {
+        let (format, bytes) = self.buf_index.ok_or(ViewError::Missing)?;
+        crate::view::view_of(format.size(), bytes)
+    }
+
+    /// View the indices as a slice of `T`, without copying.
+    pub fn index_slice<T: bytemuck::Pod>(
+        &self,
+    ) -> Result<&'s [T], ViewError> {
+        let (format, bytes) = self.buf_index.ok_or(ViewError::Missing)?;
+        crate::view::slice_of(format.size(), bytes)
+    }
+
+    /// Iterate over the whole index buffer as `u32`.
+    ///
+    /// See [`MeshDataRef::iter_indices`]. Note that the indices of each
+    /// mesh are relative to its first vertex.
+    pub fn iter_indices(&self) -> Option<impl Iterator<Item = u32> + 's> {
+        let (format, bytes) = self.buf_index?;
+        Some(crate::mesh::iter_indices(format, bytes))
+    }
+
+    /// Lazily decode an attribute (for all meshes) as floats.
+    ///
+    /// See [`MeshDataRef::attribute_f32`].
+    pub fn attribute_f32<const N: usize>(
+        &self,
+        usage: VertexUsage,
+    ) -> Option<impl Iterator<Item = [f32; N]> + 's> {
+        let (format, bytes) = *self.buf_attrs.get(&usage)?;
+        crate::convert::iter_f32(format, bytes)
+    }
+
+    pub fn positions_f32(&self) -> Option<impl Iterator<Item = [f32; 3]> + 's> {
+        self.attribute_f32(VertexUsage::Position)
+    }
+
+    pub fn normals_f32(&self) -> Option<impl Iterator<Item = [f32; 3]> + 's> {
+        self.attribute_f32(VertexUsage::Normal)
+    }
+
+    pub fn uvs_f32(
+        &self,
+        usage: VertexUsage,
+    ) -> Option<impl Iterator<Item = [f32; 2]> + 's> {
+        self.attribute_f32(usage)
+    }
+
+    pub fn colors_f32(&self) -> Option<impl Iterator<Item = [f32; 4]> + 's> {
+        self.attribute_f32(VertexUsage::Color)
+    }
 }
 
 #[derive(Default, Clone)]
 pub struct DecodedMeshes<'s> {
     pub meshes: Vec<MeshDataRef<'s>>,
+    /// The per-instance attributes of each mesh, with data for the
+    /// instances in its range.
+    pub instances: Vec<HashMap<VertexUsage, (VertexFormat, &'s [u8])>>,
+}
+
+/// What [`IyesMeshReader::recover_data`] could decode.
+#[derive(Debug)]
+pub struct Recovery {
+    /// Number of bytes of the uncompressed payload that were decoded.
+    pub recovered_len: u64,
+    /// Expected size of the uncompressed payload.
+    pub total_len: u64,
+    /// The error that stopped decompression, if any.
+    pub error: Option<std::io::Error>,
+    /// Whether the user data was fully recovered.
+    pub user_data: bool,
+    /// For each mesh, the buffers that were not fully recovered (none if
+    /// the mesh is intact).
+    pub lost: Vec<Vec<MeshBuffer>>,
+}
+
+impl Recovery {
+    /// Whether the whole payload was decoded.
+    pub fn is_complete(&self) -> bool {
+        self.recovered_len == self.total_len
+    }
+
+    /// The indices of the meshes that were fully recovered.
+    pub fn intact_meshes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.lost
+            .iter()
+            .enumerate()
+            .filter(|(_, lost)| lost.is_empty())
+            .map(|(i, _)| i)
+    }
+}
+
+/// A buffer of the payload, as used by a mesh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MeshBuffer {
+    Indices,
+    Attribute(VertexUsage),
+    Instances(VertexUsage),
 }
 
+/// Which stage of the decoding process is currently running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReadPhase {
+    /// Verifying the data checksum.
+    Checksumming,
+    /// Decompressing the data.
+    Decompressing,
+    /// All requested data has been read successfully.
+    Done,
+}
+
+/// Progress information reported during reading.
+///
+/// `compressed_*` refer to the bytes of the file being read. They are
+/// reported during every phase. `decompressed_*` refer to the decoded
+/// payload, and are only meaningful while decompressing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReadProgress {
+    pub phase: ReadPhase,
+    pub compressed_bytes: u64,
+    pub compressed_total: u64,
+    pub decompressed_bytes: u64,
+    pub decompressed_total: u64,
+}
+
+/// Granularity of reads from the input (and progress reports).
+const DECODE_CHUNK_SIZE: usize = 4 << 20;
+/// The most memory reserved up front for the decompressed data, per byte
+/// of compressed data.
+///
+/// The sizes in the descriptor cannot be trusted, so the buffer only
+/// grows beyond that as data actually comes out of the decoder.
+const MAX_RESERVE_RATIO: u64 = 32;
+/// The most memory reserved up front for the decompressed data.
+const MAX_RESERVE: u64 = 256 << 20;
+/// The most zeros [`IyesMeshReader::recover_data`] puts in place of the
+/// data it could not decode.
+const MAX_RECOVERY_PADDING: u64 = 1 << 30;
+
 pub struct IyesMeshReader<'s> {
     read: Option<&'s mut dyn ReadSeek>,
     header: IyesMeshHeader,
     descriptor: IyesMeshDescriptor,
     buf: Vec<u8>,
     settings: IyesMeshReaderSettings,
+    progress: Option<Box<dyn FnMut(ReadProgress) + Send>>,
+    cancel: Option<CancelToken>,
+    /// Hash of the file so far, if the signature is to be verified.
+    #[cfg(feature = "signing")]
+    signature: Option<crate::signing::FileHasher>,
 }
 
 pub struct IyesMeshReaderWithData {
     descriptor: IyesMeshDescriptor,
     buf: Vec<u8>,
+    version: u16,
+    settings: IyesMeshReaderSettings,
 }
 
 impl<'s> IyesMeshReader<'s> {
@@ -76,14 +297,21 @@ impl<'s> IyesMeshReader<'s> {
         settings: IyesMeshReaderSettings,
         read: &'s mut dyn ReadSeek,
     ) -> Result<Self, ReadError> {
-        let mut buf = vec![];
-        buf.resize(IyesMeshHeader::encoded_len(), 0);
+        let mut buf = vec![0; IyesMeshHeader::encoded_len()];
         read.read_exact(&mut buf)?;
         let header = IyesMeshHeader::from_bytes(&buf)?;
+        #[cfg(feature = "signing")]
+        let mut signature = settings.verifying_key.map(|_| {
+            let mut hasher = crate::signing::FileHasher::default();
+            hasher.update(&buf);
+            hasher
+        });
         if header.magic != crate::MAGIC {
             return Err(ReadError::BadMagic);
         }
-        if header.version != crate::FORMAT_VERSION {
+        if !(crate::MIN_FORMAT_VERSION..=crate::FORMAT_VERSION)
+            .contains(&{ header.version })
+        {
             return Err(ReadError::BadVersion(header.version));
         }
         buf.resize(header.descriptor_len as usize, 0);
@@ -95,13 +323,25 @@ impl<'s> IyesMeshReader<'s> {
                 return Err(ReadError::InvalidChecksums);
             }
         }
-        let descriptor = IyesMeshDescriptor::from_bytes(&buf)?;
+        let descriptor =
+            IyesMeshDescriptor::from_bytes_with_version(header.version, &buf)?;
+        #[cfg(feature = "signing")]
+        if let Some(hasher) = &mut signature {
+            if !descriptor.signed {
+                return Err(ReadError::SignatureMissing);
+            }
+            hasher.update(&buf);
+        }
         Ok(Self {
             header,
             descriptor,
             read: Some(read),
             buf,
             settings,
+            progress: None,
+            cancel: None,
+            #[cfg(feature = "signing")]
+            signature,
         })
     }
 
@@ -113,17 +353,53 @@ impl<'s> IyesMeshReader<'s> {
         &self.descriptor
     }
 
+    /// Install a callback to be notified about the progress of decoding.
+    ///
+    /// It is called periodically while verifying checksums and
+    /// decompressing data.
+    pub fn set_progress_callback(
+        &mut self,
+        callback: Box<dyn FnMut(ReadProgress) + Send>,
+    ) {
+        self.progress = Some(callback);
+    }
+
+    pub fn clear_progress_callback(&mut self) {
+        self.progress = None;
+    }
+
+    /// Allow the decoding to be aborted using the given token.
+    ///
+    /// If cancelled, the operation in progress returns
+    /// [`ReadError::Cancelled`].
+    pub fn set_cancel_token(
+        &mut self,
+        token: CancelToken,
+    ) {
+        self.cancel = Some(token);
+    }
+
+    pub fn clear_cancel_token(&mut self) {
+        self.cancel = None;
+    }
+
+    fn check_cancelled(&self) -> Result<(), ReadError> {
+        if self.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+            return Err(ReadError::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Check the data checksum (and the signature, if a verifying key is
+    /// set), without decoding anything.
     pub fn verify_data_checksum(mut self) -> Result<(), ReadError> {
-        if self.header.data_checksum == 0 {
+        let check_checksum = self.header.data_checksum != 0;
+        if !check_checksum && !self.verifying_signature() {
             return Ok(());
         }
         let read = self.read.take().unwrap();
-        self.buf.clear();
-        read.read_to_end(&mut self.buf)?;
-        let actual_data_checksum = checksum_data(&self.buf);
-        if self.header.data_checksum != actual_data_checksum {
-            return Err(ReadError::InvalidChecksums);
-        }
+        self.check_data_checksum(read, check_checksum)?;
+        self.report_progress(ReadPhase::Done, 0, 0);
         Ok(())
     }
 
@@ -131,47 +407,311 @@ impl<'s> IyesMeshReader<'s> {
         mut self
     ) -> Result<IyesMeshReaderWithData, ReadError> {
         let read = self.read.take().unwrap();
-        if self.settings.verify_data_checksum && self.header.data_checksum != 0
-        {
-            self.buf.clear();
-            read.read_to_end(&mut self.buf)?;
-            let actual_data_checksum = checksum_data(&self.buf);
-            if self.header.data_checksum != actual_data_checksum {
-                return Err(ReadError::InvalidChecksums);
-            }
-            read.seek(SeekFrom::Start(
-                IyesMeshHeader::encoded_len() as u64
-                    + self.header.descriptor_len as u64,
-            ))?;
+        let check_checksum = self.settings.verify_data_checksum
+            && self.header.data_checksum != 0;
+        if check_checksum || self.verifying_signature() {
+            self.check_data_checksum(read, check_checksum)?;
         }
-        let mut decoder = new_zstd_decoder(read)?;
-        self.buf.clear();
-        decoder.read_to_end(&mut self.buf)?;
+        self.decode_data(read, None)?;
+        let len = self.descriptor.user_data_len as usize;
+        if let Some(user_data) = self.buf.get(..len) {
+            self.check_user_data_checksum(user_data)?;
+        }
+        self.report_progress(ReadPhase::Done, 0, 0);
         Ok(IyesMeshReaderWithData {
             descriptor: self.descriptor,
             buf: self.buf,
+            version: self.header.version,
+            settings: self.settings,
         })
     }
 
+    /// Decode as much of the data as possible, from a corrupted or
+    /// truncated file.
+    ///
+    /// No data checksum is verified (nor the user data checksum, or the
+    /// signature). Decompression stops at the first error, keeping what was
+    /// decoded until then, and the rest of the payload is filled with
+    /// zeros. The [`Recovery`] tells which meshes were fully recovered;
+    /// the others contain zeros.
+    ///
+    /// The sizes in the descriptor may be corrupted too, so at most 1 GiB
+    /// of zeros is added. If more is missing, the payload is left short,
+    /// and only the [`Recovery`] is of any use (the buffers cannot be
+    /// decoded).
+    pub fn recover_data(
+        mut self
+    ) -> Result<(IyesMeshReaderWithData, Recovery), ReadError> {
+        let read = self.read.take().unwrap();
+        let error = match self.decode_data(read, None) {
+            Ok(()) => None,
+            Err(ReadError::Io(e)) => Some(e),
+            Err(e) => return Err(e),
+        };
+        let total_len = self.descriptor.compute_total_raw_data_size();
+        let recovered_len = (self.buf.len() as u64).min(total_len);
+        if total_len - recovered_len <= MAX_RECOVERY_PADDING {
+            self.buf.resize(total_len as usize, 0);
+        }
+        let recovery = Recovery {
+            lost: self.lost_buffers(recovered_len),
+            user_data: self.descriptor.user_data_len as u64 <= recovered_len,
+            recovered_len,
+            total_len,
+            error,
+        };
+        self.report_progress(ReadPhase::Done, 0, 0);
+        Ok((
+            IyesMeshReaderWithData {
+                descriptor: self.descriptor,
+                buf: self.buf,
+                version: self.header.version,
+                settings: self.settings,
+            },
+            recovery,
+        ))
+    }
+
+    /// The buffers of each mesh that do not fit in the first
+    /// `recovered_len` bytes of the payload.
+    fn lost_buffers(
+        &self,
+        recovered_len: u64,
+    ) -> Vec<Vec<MeshBuffer>> {
+        let d = &self.descriptor;
+        // Each buffer, with its offset in the payload and element size.
+        let mut buffers = vec![];
+        let mut start = d.user_data_len as u64;
+        if let Some(info) = d.indices {
+            let size = info.format.size() as u64;
+            buffers.push((MeshBuffer::Indices, start, size));
+            start += info.n_indices as u64 * size;
+        }
+        let order = if self.header.version < 2 {
+            d.attributes.keys().copied().collect()
+        } else {
+            d.attribute_order()
+        };
+        for usage in order {
+            let size = d.attributes[&usage].size() as u64;
+            buffers.push((MeshBuffer::Attribute(usage), start, size));
+            start += d.n_vertices as u64 * size;
+        }
+        for usage in d.instance_attribute_order() {
+            let size = d.instance_attributes[&usage].size() as u64;
+            buffers.push((MeshBuffer::Instances(usage), start, size));
+            start += d.n_instances as u64 * size;
+        }
+        d.meshes
+            .iter()
+            .map(|m| {
+                buffers
+                    .iter()
+                    .filter(|(buffer, start, size)| {
+                        let (first, count) = match buffer {
+                            MeshBuffer::Indices => {
+                                (m.first_index, m.index_count)
+                            }
+                            MeshBuffer::Attribute(_) => {
+                                (m.first_vertex, m.vertex_count)
+                            }
+                            MeshBuffer::Instances(_) => {
+                                (m.first_instance, m.instance_count)
+                            }
+                        };
+                        let end = first as u64 + count as u64;
+                        start + end * size > recovered_len
+                    })
+                    .map(|(buffer, ..)| *buffer)
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Decode only the user data, without the mesh data.
+    ///
+    /// If the file has a user data checksum (and it is to be verified),
+    /// it is checked instead of the data checksum, which would require
+    /// reading the whole payload. Encrypted user data is decrypted with
+    /// the key from the settings.
     pub fn read_user_data(mut self) -> Result<Vec<u8>, ReadError> {
         let read = self.read.take().unwrap();
-        if self.settings.verify_data_checksum && self.header.data_checksum != 0
+        let has_own_checksum = self.settings.verify_user_data_checksum
+            && self.descriptor.user_data_checksum.is_some();
+        let check_checksum = self.settings.verify_data_checksum
+            && self.header.data_checksum != 0
+            && !has_own_checksum;
+        if check_checksum || self.verifying_signature() {
+            self.check_data_checksum(read, check_checksum)?;
+        }
+        let len = self.descriptor.user_data_len as u64;
+        self.decode_data(read, Some(len))?;
+        if (self.buf.len() as u64) < len {
+            return Err(ReadError::NotEnoughData);
+        }
+        self.check_user_data_checksum(&self.buf)?;
+        self.report_progress(ReadPhase::Done, 0, 0);
+        decrypt_user_data(&self.descriptor, &self.settings, self.buf)
+    }
+
+    fn check_user_data_checksum(
+        &self,
+        user_data: &[u8],
+    ) -> Result<(), ReadError> {
+        if let Some(expected) = self.descriptor.user_data_checksum
+            && self.settings.verify_user_data_checksum
+            && self.checksum_kind()?.checksum(user_data) != expected
         {
-            self.buf.clear();
-            read.read_to_end(&mut self.buf)?;
-            let actual_data_checksum = checksum_data(&self.buf);
-            if self.header.data_checksum != actual_data_checksum {
-                return Err(ReadError::InvalidChecksums);
+            return Err(ReadError::InvalidChecksums);
+        }
+        Ok(())
+    }
+
+    /// The algorithm of the data checksums, if this build supports it.
+    fn checksum_kind(&self) -> Result<ChecksumKind, ReadError> {
+        let id = self.descriptor.checksum_kind;
+        ChecksumKind::from_id(id).ok_or(ReadError::UnsupportedChecksum(id))
+    }
+
+    fn payload_start(&self) -> u64 {
+        IyesMeshHeader::encoded_len() as u64 + self.header.descriptor_len as u64
+    }
+
+    fn verifying_signature(&self) -> bool {
+        #[cfg(feature = "signing")]
+        return self.signature.is_some();
+        #[cfg(not(feature = "signing"))]
+        false
+    }
+
+    /// Find the length of the compressed data (without the signature, if
+    /// any), leaving the read position at the start of it.
+    fn compressed_len(
+        &self,
+        read: &mut dyn ReadSeek,
+    ) -> Result<u64, ReadError> {
+        let mut end = read.seek(SeekFrom::End(0))?;
+        if self.descriptor.signed {
+            end = end.saturating_sub(crate::SIGNATURE_LEN as u64);
+        }
+        let start = read.seek(SeekFrom::Start(self.payload_start()))?;
+        Ok(end.saturating_sub(start))
+    }
+
+    /// Read all the compressed data, compare its hash against the header
+    /// (if `check_checksum`) and verify the signature (if required),
+    /// leaving the read position at the start of the data for decoding.
+    fn check_data_checksum(
+        &mut self,
+        read: &mut dyn ReadSeek,
+        check_checksum: bool,
+    ) -> Result<(), ReadError> {
+        let compressed_total = self.compressed_len(read)?;
+        // Only the signature needs the data otherwise, so any algorithm works.
+        let kind = if check_checksum {
+            self.checksum_kind()?
+        } else {
+            ChecksumKind::RapidHash
+        };
+        let mut reader =
+            ChecksummingReader::with_kind(&mut *read, kind, compressed_total);
+        self.buf.resize(DECODE_CHUNK_SIZE.min(compressed_total as usize), 0);
+        while reader.remaining() > 0 {
+            self.check_cancelled()?;
+            let n = reader.read(&mut self.buf)?;
+            if n == 0 {
+                return Err(ReadError::NotEnoughData);
             }
-            read.seek(SeekFrom::Start(
-                IyesMeshHeader::encoded_len() as u64
-                    + self.header.descriptor_len as u64,
-            ))?;
+            #[cfg(feature = "signing")]
+            if let Some(hasher) = &mut self.signature {
+                hasher.update(&self.buf[..n]);
+            }
+            self.report_progress(
+                ReadPhase::Checksumming,
+                compressed_total - reader.remaining(),
+                compressed_total,
+            );
+        }
+        if check_checksum && self.header.data_checksum != reader.finish() {
+            return Err(ReadError::InvalidChecksums);
+        }
+        #[cfg(feature = "signing")]
+        if let Some(hasher) = self.signature.take() {
+            let mut signature = [0; crate::SIGNATURE_LEN];
+            read.read_exact(&mut signature)
+                .map_err(|_| ReadError::SignatureInvalid)?;
+            let key = self.settings.verifying_key.unwrap();
+            if !hasher.verify(&key, &signature) {
+                return Err(ReadError::SignatureInvalid);
+            }
+        }
+        read.seek(SeekFrom::Start(self.payload_start()))?;
+        Ok(())
+    }
+
+    /// Decompress the data into `self.buf`.
+    ///
+    /// If `limit` is provided, stop after that many bytes.
+    fn decode_data(
+        &mut self,
+        read: &mut dyn ReadSeek,
+        limit: Option<u64>,
+    ) -> Result<(), ReadError> {
+        let compressed_total = self.compressed_len(read)?;
+        let decompressed_total =
+            limit.unwrap_or(self.descriptor.compute_total_raw_data_size());
+        let mut decoder =
+            new_zstd_decoder(CountingReader::new(read.take(compressed_total)))?;
+        self.buf.clear();
+        self.buf.reserve(
+            decompressed_total
+                .min(compressed_total.saturating_mul(MAX_RESERVE_RATIO))
+                .min(MAX_RESERVE) as usize,
+        );
+        loop {
+            self.check_cancelled()?;
+            let want = match limit {
+                Some(limit) => {
+                    let remain = limit - self.buf.len() as u64;
+                    if remain == 0 {
+                        break;
+                    }
+                    remain.min(DECODE_CHUNK_SIZE as u64)
+                }
+                None => DECODE_CHUNK_SIZE as u64,
+            };
+            let n = (&mut decoder).take(want).read_to_end(&mut self.buf)?;
+            if n == 0 {
+                break;
+            }
+            if let Some(cb) = &mut self.progress {
+                cb(ReadProgress {
+                    phase: ReadPhase::Decompressing,
+                    compressed_bytes: decoder.get_ref().get_ref().count(),
+                    compressed_total,
+                    decompressed_bytes: self.buf.len() as u64,
+                    decompressed_total,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn report_progress(
+        &mut self,
+        phase: ReadPhase,
+        compressed_bytes: u64,
+        compressed_total: u64,
+    ) {
+        if let Some(cb) = &mut self.progress {
+            cb(ReadProgress {
+                phase,
+                compressed_bytes,
+                compressed_total,
+                decompressed_bytes: 0,
+                decompressed_total: 0,
+            });
         }
-        let mut decoder = new_zstd_decoder(read)?;
-        self.buf.resize(self.descriptor.user_data_len as usize, 0);
-        decoder.read_exact(&mut self.buf)?;
-        Ok(self.buf)
     }
 }
 
@@ -180,6 +720,29 @@ impl IyesMeshReaderWithData {
         &self.descriptor
     }
 
+    /// Get the user data, decrypting it if it is encrypted.
+    ///
+    /// [`DecodedBuffers::user_data`] has the bytes as stored in the file.
+    /// This uses the key from the reader settings.
+    pub fn decode_user_data(&self) -> Result<Option<Vec<u8>>, ReadError> {
+        let len = self.descriptor.user_data_len as usize;
+        if len == 0 {
+            return Ok(None);
+        }
+        let stored = self.buf.get(..len).ok_or(ReadError::NotEnoughData)?;
+        decrypt_user_data(&self.descriptor, &self.settings, stored.to_vec())
+            .map(Some)
+    }
+
+    /// The decompressed data beyond what the descriptor declares (empty
+    /// for valid files).
+    ///
+    /// See [`IyesMeshReaderSettings::allow_trailing_data`].
+    pub fn trailing_data(&self) -> &[u8] {
+        let len = self.descriptor.compute_total_raw_data_size() as usize;
+        self.buf.get(len..).unwrap_or_default()
+    }
+
     pub fn into_flat_buffers(&self) -> Result<DecodedBuffers<'_>, ReadError> {
         let mut out = DecodedBuffers::default();
         let mut data_remain = &self.buf[..];
@@ -202,15 +765,30 @@ impl IyesMeshReaderWithData {
             ));
             data_remain = &data_remain[size..];
         }
-        for (usage, format) in self.descriptor.attributes.iter() {
+        let order = if self.version < 2 {
+            self.descriptor.attributes.keys().copied().collect()
+        } else {
+            self.descriptor.attribute_order()
+        };
+        for usage in order {
+            let format = self.descriptor.attributes[&usage];
             let size = format.size() * self.descriptor.n_vertices as usize;
             if data_remain.len() < size {
                 return Err(ReadError::NotEnoughData);
             }
-            out.buf_attrs.insert(*usage, (*format, &data_remain[..size]));
+            out.buf_attrs.insert(usage, (format, &data_remain[..size]));
+            data_remain = &data_remain[size..];
+        }
+        for usage in self.descriptor.instance_attribute_order() {
+            let format = self.descriptor.instance_attributes[&usage];
+            let size = format.size() * self.descriptor.n_instances as usize;
+            if data_remain.len() < size {
+                return Err(ReadError::NotEnoughData);
+            }
+            out.buf_instances.insert(usage, (format, &data_remain[..size]));
             data_remain = &data_remain[size..];
         }
-        if !data_remain.is_empty() {
+        if !data_remain.is_empty() && !self.settings.allow_trailing_data {
             return Err(ReadError::TooMuchData);
         }
         Ok(out)
@@ -233,8 +811,8 @@ impl IyesMeshReaderWithData {
                     &idata[index_offset..(index_offset + index_len)];
                 mesh.indices = Some((ifmt, mesh_idata));
                 for (vusage, (vfmt, vdata)) in buffers.buf_attrs.iter() {
-                    let vertex_offset = m.first_vertex as usize * ifmt.size();
-                    let vertex_len = m.vertex_count as usize * ifmt.size();
+                    let vertex_offset = m.first_vertex as usize * vfmt.size();
+                    let vertex_len = m.vertex_count as usize * vfmt.size();
                     if vdata.len() < vertex_offset + vertex_len {
                         return Err(ReadError::NotEnoughData);
                     }
@@ -260,15 +838,217 @@ impl IyesMeshReaderWithData {
                 }
             }
             r.meshes.push(mesh);
+            let mut instances = HashMap::default();
+            for (usage, (format, data)) in buffers.buf_instances.iter() {
+                let offset = m.first_instance as usize * format.size();
+                let len = m.instance_count as usize * format.size();
+                if data.len() < offset + len {
+                    return Err(ReadError::NotEnoughData);
+                }
+                let data = &data[offset..offset + len];
+                instances.insert(*usage, (*format, data));
+            }
+            r.instances.push(instances);
         }
         Ok(r)
     }
+
+    /// Get the positions of a mesh as floats, undoing any quantization.
+    ///
+    /// `meshes` must come from [`into_split_meshes`](Self::into_split_meshes)
+    /// and `index` refers to the mesh in it. Returns `None` if the mesh has
+    /// no positions, or they are stored in a format that cannot be
+    /// converted to floats.
+    pub fn decode_positions_f32(
+        &self,
+        meshes: &DecodedMeshes<'_>,
+        index: usize,
+    ) -> Option<Vec<[f32; 3]>> {
+        let info = self.descriptor.meshes.get(index)?;
+        let mesh = meshes.meshes.get(index)?;
+        let (format, bytes) = *mesh.attributes.get(&VertexUsage::Position)?;
+        let r = decode_floats::<3>(format, bytes)?;
+        Some(match info.position_transform {
+            Some(transform) => r.into_iter().map(|p| transform.apply(p)).collect(),
+            None => r,
+        })
+    }
+
+    /// Get any attribute of a mesh as floats, converting from its format.
+    ///
+    /// Works like [`decode_positions_f32`](Self::decode_positions_f32),
+    /// keeping the first `N` components. Formats with fewer components are
+    /// not accepted. Also returns `None` if the data is quantized or
+    /// specially encoded; use the dedicated methods for that.
+    pub fn decode_attribute_f32<const N: usize>(
+        &self,
+        meshes: &DecodedMeshes<'_>,
+        index: usize,
+        usage: VertexUsage,
+    ) -> Option<Vec<[f32; N]>> {
+        let info = self.descriptor.meshes.get(index)?;
+        if self.descriptor.attribute_encodings.contains_key(&usage)
+            || (usage == VertexUsage::Position
+                && info.position_transform.is_some())
+        {
+            return None;
+        }
+        let mesh = meshes.meshes.get(index)?;
+        let (format, bytes) = *mesh.attributes.get(&usage)?;
+        decode_floats::<N>(format, bytes)
+    }
+
+    /// Get the normals of a mesh as floats, undoing any special encoding.
+    ///
+    /// Works like [`decode_positions_f32`](Self::decode_positions_f32).
+    pub fn decode_normals_f32(
+        &self,
+        meshes: &DecodedMeshes<'_>,
+        index: usize,
+    ) -> Option<Vec<[f32; 3]>> {
+        let mesh = meshes.meshes.get(index)?;
+        let (format, bytes) = *mesh.attributes.get(&VertexUsage::Normal)?;
+        match self.descriptor.attribute_encodings.get(&VertexUsage::Normal) {
+            Some(AttributeEncoding::Octahedral) => Some(
+                encode::oct_decode_normals(bytes)
+                    .chunks_exact(3)
+                    .map(|v| [v[0], v[1], v[2]])
+                    .collect(),
+            ),
+            Some(_) => None,
+            None => decode_floats::<3>(format, bytes),
+        }
+    }
+
+    /// Get the tangents of a mesh as floats, undoing any special encoding.
+    ///
+    /// Works like [`decode_positions_f32`](Self::decode_positions_f32).
+    pub fn decode_tangents_f32(
+        &self,
+        meshes: &DecodedMeshes<'_>,
+        index: usize,
+    ) -> Option<Vec<[f32; 4]>> {
+        let mesh = meshes.meshes.get(index)?;
+        let (format, bytes) = *mesh.attributes.get(&VertexUsage::Tangent)?;
+        match self.descriptor.attribute_encodings.get(&VertexUsage::Tangent) {
+            Some(AttributeEncoding::OctahedralSigned) => Some(
+                encode::oct_decode_tangents(bytes)
+                    .chunks_exact(4)
+                    .map(|v| [v[0], v[1], v[2], v[3]])
+                    .collect(),
+            ),
+            Some(_) => None,
+            None => decode_floats::<4>(format, bytes),
+        }
+    }
 }
 
+/// Undo the encryption of the user data, if it is encrypted.
+#[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+fn decrypt_user_data(
+    descriptor: &IyesMeshDescriptor,
+    settings: &IyesMeshReaderSettings,
+    stored: Vec<u8>,
+) -> Result<Vec<u8>, ReadError> {
+    let Some(nonce) = descriptor.user_data_nonce else {
+        return Ok(stored);
+    };
+    #[cfg(feature = "encryption")]
+    if let Some(key) = &settings.user_data_key {
+        return crate::encryption::decrypt(key, &nonce, &stored)
+            .ok_or(ReadError::UserDataDecryption);
+    }
+    Err(ReadError::UserDataEncrypted)
+}
+
+/// Convert vertex data to floats, keeping the first `N` components.
+fn decode_floats<const N: usize>(
+    format: VertexFormat,
+    bytes: &[u8],
+) -> Option<Vec<[f32; N]>> {
+    Some(crate::convert::iter_f32(format, bytes)?.collect())
+}
+
+/// Check if the first 4 bytes of a file are the IMA magic.
+pub fn is_iyes_mesh_magic(magic: &[u8; 4]) -> bool {
+    *magic == crate::MAGIC
+}
+
+/// Check if a file is an IMA file, without consuming any input.
+///
+/// Works on non-seekable inputs like pipes: the magic is peeked from the
+/// buffer of `read`. Inputs shorter than the magic are not IMA files. If
+/// the buffer holds fewer than 4 bytes (a reader with a tiny buffer, or
+/// a pipe that delivers less than that at first), this also returns
+/// `false`.
+pub fn detect_format(read: &mut impl BufRead) -> Result<bool, ReadError> {
+    let buf = read.fill_buf()?;
+    Ok(buf.first_chunk().is_some_and(is_iyes_mesh_magic))
+}
+
+/// Check if a file is an IMA file, by reading its magic from the start
+/// and rewinding.
+///
+/// See [`detect_format`] for inputs that cannot seek.
 pub fn is_iyes_mesh_file(read: &mut dyn ReadSeek) -> Result<bool, ReadError> {
     read.rewind()?;
     let mut magic = [0; 4];
     read.read_exact(&mut magic)?;
     read.rewind()?;
-    Ok(magic == crate::MAGIC)
+    Ok(is_iyes_mesh_magic(&magic))
+}
+
+/// The header fields of a file, as returned by [`probe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProbeInfo {
+    /// Version of the file format.
+    pub version: u16,
+    /// Length of the encoded descriptor, which follows the header.
+    pub descriptor_len: u16,
+    pub metadata_checksum: u64,
+    /// Checksum of the compressed data (zero if the file has none).
+    pub data_checksum: u64,
+}
+
+impl ProbeInfo {
+    pub fn has_data_checksum(&self) -> bool {
+        self.data_checksum != 0
+    }
+
+    /// Whether this version of the library can read the file.
+    pub fn is_version_supported(&self) -> bool {
+        (crate::MIN_FORMAT_VERSION..=crate::FORMAT_VERSION)
+            .contains(&self.version)
+    }
+}
+
+/// Read the header of a file, without decoding or verifying anything else.
+///
+/// Reads exactly [`IyesMeshHeader::encoded_len`] bytes. Fails with
+/// [`ReadError::BadMagic`] if the input is not an IMA file, and with
+/// [`ReadError::NotEnoughData`] if it is shorter than the header. The
+/// version is not checked (see [`ProbeInfo::is_version_supported`]).
+pub fn probe(read: &mut dyn Read) -> Result<ProbeInfo, ReadError> {
+    let mut buf = [0; IyesMeshHeader::encoded_len()];
+    read.read_exact(&mut buf).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => ReadError::NotEnoughData,
+        _ => e.into(),
+    })?;
+    let header = IyesMeshHeader::from_bytes(&buf)?;
+    if !is_iyes_mesh_magic(&header.magic) {
+        return Err(ReadError::BadMagic);
+    }
+    Ok(ProbeInfo {
+        version: header.version,
+        descriptor_len: header.descriptor_len,
+        metadata_checksum: header.metadata_checksum,
+        data_checksum: header.data_checksum,
+    })
+}
+
+/// Open a file and [`probe`] it.
+pub fn probe_file(
+    path: impl AsRef<std::path::Path>,
+) -> Result<ProbeInfo, ReadError> {
+    probe(&mut std::fs::File::open(path)?)
 }
@@ -0,0 +1,83 @@
+//! Ed25519 signatures of whole files.
+//!
+//! A signed file has [`IyesMeshDescriptor::signed`] set, and ends with a
+//! signature ([`crate::SIGNATURE_LEN`] bytes) of everything before it:
+//! header, descriptor and compressed data. The signature is Ed25519ph
+//! (the file is hashed with SHA-512), with the context `iyes_mesh`.
+//!
+//! Keys are passed around as raw bytes: the 32-byte secret key for
+//! signing, and the 32-byte public key for verifying. Use the functions
+//! here to get them from PEM files.
+//!
+//! [`IyesMeshDescriptor::signed`]:
+//! crate::descriptor::IyesMeshDescriptor::signed
+
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use ed25519_dalek::{Signature, SigningKey, VerifyingKey};
+use sha2::{Digest, Sha512};
+
+const CONTEXT: &[u8] = b"iyes_mesh";
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeyError {
+    #[error("Cannot decode private key: {0}")]
+    Private(ed25519_dalek::pkcs8::Error),
+    #[error("Cannot decode public key: {0}")]
+    Public(ed25519_dalek::pkcs8::spki::Error),
+}
+
+/// Get the secret key from a PKCS#8 PEM file (`BEGIN PRIVATE KEY`).
+pub fn signing_key_from_pem(pem: &str) -> Result<[u8; 32], KeyError> {
+    let key = SigningKey::from_pkcs8_pem(pem).map_err(KeyError::Private)?;
+    Ok(key.to_bytes())
+}
+
+/// Get the public key from a PEM file (`BEGIN PUBLIC KEY`).
+pub fn verifying_key_from_pem(pem: &str) -> Result<[u8; 32], KeyError> {
+    let key =
+        VerifyingKey::from_public_key_pem(pem).map_err(KeyError::Public)?;
+    Ok(key.to_bytes())
+}
+
+/// The public key matching a secret key.
+pub fn verifying_key(signing_key: &[u8; 32]) -> [u8; 32] {
+    SigningKey::from_bytes(signing_key).verifying_key().to_bytes()
+}
+
+/// Hash of the signed bytes of a file, fed as they are written or read.
+#[derive(Clone, Default)]
+pub(crate) struct FileHasher(Sha512);
+
+impl FileHasher {
+    pub(crate) fn update(
+        &mut self,
+        bytes: &[u8],
+    ) {
+        self.0.update(bytes);
+    }
+
+    pub(crate) fn sign(
+        self,
+        signing_key: &[u8; 32],
+    ) -> [u8; 64] {
+        SigningKey::from_bytes(signing_key)
+            .sign_prehashed(self.0, Some(CONTEXT))
+            .expect("context is short enough")
+            .to_bytes()
+    }
+
+    /// Check the signature; false if it does not match, or the key is not
+    /// a valid public key.
+    pub(crate) fn verify(
+        self,
+        verifying_key: &[u8; 32],
+        signature: &[u8; 64],
+    ) -> bool {
+        let Ok(key) = VerifyingKey::from_bytes(verifying_key) else {
+            return false;
+        };
+        let signature = Signature::from_bytes(signature);
+        key.verify_prehashed_strict(self.0, Some(CONTEXT), &signature)
+            .is_ok()
+    }
+}
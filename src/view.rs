@@ -0,0 +1,85 @@
+//! Typed access to vertex and index data without unsafe casting.
+
+use std::ops::Deref;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ViewError {
+    #[error("The buffer does not exist")]
+    Missing,
+    #[error(
+        "Elements of {format_size} bytes cannot be viewed as a type of \
+         {type_size} bytes"
+    )]
+    WrongFormat { format_size: usize, type_size: usize },
+    #[error("The buffer is not aligned for the requested type")]
+    Misaligned,
+}
+
+/// Data viewed as a slice of `T`.
+///
+/// Borrows the original buffer if it is suitably aligned, otherwise holds
+/// an aligned copy. Either way, it derefs to `[T]`.
+#[derive(Debug, Clone)]
+pub enum AttrView<'s, T> {
+    Borrowed(&'s [T]),
+    Owned(Vec<T>),
+}
+
+impl<T> AttrView<'_, T> {
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self, AttrView::Borrowed(_))
+    }
+}
+
+impl<T> Deref for AttrView<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            AttrView::Borrowed(slice) => slice,
+            AttrView::Owned(vec) => vec,
+        }
+    }
+}
+
+/// View data with elements of `format_size` bytes as a slice of `T`.
+///
+/// Fails with [`ViewError::Misaligned`] instead of copying.
+pub fn slice_of<T: bytemuck::Pod>(
+    format_size: usize,
+    bytes: &[u8],
+) -> Result<&[T], ViewError> {
+    check_size::<T>(format_size, bytes)?;
+    bytemuck::try_cast_slice(bytes).map_err(|_| ViewError::Misaligned)
+}
+
+/// View data with elements of `format_size` bytes as `T`, copying it if
+/// it is misaligned.
+pub fn view_of<T: bytemuck::Pod>(
+    format_size: usize,
+    bytes: &[u8],
+) -> Result<AttrView<'_, T>, ViewError> {
+    match slice_of(format_size, bytes) {
+        Ok(slice) => Ok(AttrView::Borrowed(slice)),
+        Err(ViewError::Misaligned) => {
+            let mut vec = vec![T::zeroed(); bytes.len() / format_size];
+            bytemuck::cast_slice_mut::<T, u8>(&mut vec).copy_from_slice(bytes);
+            Ok(AttrView::Owned(vec))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn check_size<T>(
+    format_size: usize,
+    bytes: &[u8],
+) -> Result<(), ViewError> {
+    let type_size = std::mem::size_of::<T>();
+    if type_size != format_size || !bytes.len().is_multiple_of(format_size) {
+        return Err(ViewError::WrongFormat {
+            format_size,
+            type_size,
+        });
+    }
+    Ok(())
+}
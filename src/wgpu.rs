@@ -0,0 +1,178 @@
+//! Conversions to and from [`wgpu_types`] (re-exported by `wgpu`).
+//!
+//! The formats map one-to-one. Both directions are exhaustive matches, so
+//! a variant added on either side fails to compile until it is mapped.
+
+use wgpu_types as wgt;
+
+use crate::HashMap;
+use crate::descriptor::{
+    IndexFormat, IyesMeshDescriptor, VertexFormat, VertexUsage,
+};
+
+/// A vertex buffer layout that owns its attributes.
+///
+/// `wgpu::VertexBufferLayout` borrows its attributes, so create it from
+/// this when building the pipeline:
+///
+/// ```ignore
+/// wgpu::VertexBufferLayout {
+///     array_stride: layout.array_stride,
+///     step_mode: layout.step_mode,
+///     attributes: &layout.attributes,
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedVertexBufferLayout {
+    pub array_stride: wgt::BufferAddress,
+    pub step_mode: wgt::VertexStepMode,
+    pub attributes: Vec<wgt::VertexAttribute>,
+    /// The usage of each entry in `attributes`.
+    pub usages: Vec<VertexUsage>,
+}
+
+impl IyesMeshDescriptor {
+    /// Layouts for binding each attribute as a separate vertex buffer.
+    ///
+    /// Only attributes present in `location_map` are included, each with
+    /// the shader location given there. The layouts follow the payload
+    /// order (see [`attribute_order`](Self::attribute_order)); bind the
+    /// buffer of `layout.usages[0]` from
+    /// [`DecodedBuffers`](crate::read::DecodedBuffers) to each slot.
+    pub fn vertex_buffer_layouts(
+        &self,
+        location_map: &HashMap<VertexUsage, u32>,
+    ) -> Vec<OwnedVertexBufferLayout> {
+        self.mapped_attributes(location_map)
+            .map(|(usage, attribute)| OwnedVertexBufferLayout {
+                array_stride: attribute.format.size(),
+                step_mode: wgt::VertexStepMode::Vertex,
+                attributes: vec![attribute],
+                usages: vec![usage],
+            })
+            .collect()
+    }
+
+    /// Layout for a single buffer with all attributes interleaved.
+    ///
+    /// Only attributes present in `location_map` are included, in payload
+    /// order, each following the previous one within the vertex.
+    pub fn interleaved_vertex_buffer_layout(
+        &self,
+        location_map: &HashMap<VertexUsage, u32>,
+    ) -> OwnedVertexBufferLayout {
+        let mut r = OwnedVertexBufferLayout {
+            array_stride: 0,
+            step_mode: wgt::VertexStepMode::Vertex,
+            attributes: vec![],
+            usages: vec![],
+        };
+        for (usage, mut attribute) in self.mapped_attributes(location_map) {
+            attribute.offset = r.array_stride;
+            r.array_stride += attribute.format.size();
+            r.attributes.push(attribute);
+            r.usages.push(usage);
+        }
+        r
+    }
+
+    fn mapped_attributes(
+        &self,
+        location_map: &HashMap<VertexUsage, u32>,
+    ) -> impl Iterator<Item = (VertexUsage, wgt::VertexAttribute)> {
+        self.attribute_order().into_iter().filter_map(|usage| {
+            let attribute = wgt::VertexAttribute {
+                format: self.attributes[&usage].into(),
+                offset: 0,
+                shader_location: *location_map.get(&usage)?,
+            };
+            Some((usage, attribute))
+        })
+    }
+}
+
+macro_rules! vertex_formats {
+    ($($name:ident),* $(,)?) => {
+        impl From<VertexFormat> for wgt::VertexFormat {
+            fn from(format: VertexFormat) -> Self {
+                match format {
+                    $(VertexFormat::$name => wgt::VertexFormat::$name,)*
+                }
+            }
+        }
+
+        impl From<wgt::VertexFormat> for VertexFormat {
+            fn from(format: wgt::VertexFormat) -> Self {
+                match format {
+                    $(wgt::VertexFormat::$name => VertexFormat::$name,)*
+                }
+            }
+        }
+    };
+}
+
+vertex_formats!(
+    Float16,
+    Float32,
+    Float64,
+    Float16x2,
+    Float16x4,
+    Float32x2,
+    Float32x3,
+    Float32x4,
+    Float64x2,
+    Float64x3,
+    Float64x4,
+    Sint8,
+    Sint8x2,
+    Sint8x4,
+    Sint16,
+    Sint32,
+    Sint16x2,
+    Sint16x4,
+    Sint32x2,
+    Sint32x3,
+    Sint32x4,
+    Snorm8,
+    Snorm8x2,
+    Snorm8x4,
+    Snorm16,
+    Snorm16x2,
+    Snorm16x4,
+    Uint8,
+    Uint8x2,
+    Uint8x4,
+    Uint16,
+    Uint32,
+    Uint16x2,
+    Uint16x4,
+    Uint32x2,
+    Uint32x3,
+    Uint32x4,
+    Unorm8,
+    Unorm8x2,
+    Unorm8x4,
+    Unorm8x4Bgra,
+    Unorm16,
+    Unorm10_10_10_2,
+    Unorm16x2,
+    Unorm16x4,
+);
+
+impl From<IndexFormat> for wgt::IndexFormat {
+    fn from(format: IndexFormat) -> Self {
+        match format {
+            IndexFormat::U16 => wgt::IndexFormat::Uint16,
+            IndexFormat::U32 => wgt::IndexFormat::Uint32,
+        }
+    }
+}
+
+impl From<wgt::IndexFormat> for IndexFormat {
+    fn from(format: wgt::IndexFormat) -> Self {
+        match format {
+            wgt::IndexFormat::Uint16 => IndexFormat::U16,
+            wgt::IndexFormat::Uint32 => IndexFormat::U32,
+        }
+    }
+}
@@ -1,6 +1,8 @@
-use std::io::Write;
+use std::io::{Read, Seek, SeekFrom, Write};
 
-use crate::HashMap;
+use crate::{HashMap, HashSet};
+use crate::cancel::CancelToken;
+use crate::checksum::{ChecksumKind, ChecksummingReader, ChecksummingWriter};
 use crate::descriptor::*;
 use crate::header::IyesMeshHeader;
 use crate::io::*;
@@ -16,6 +18,34 @@ pub enum WriteError {
     IncompatibleMeshes,
     #[error("No source meshes provided")]
     NoMeshes,
+    #[error("Invalid compression level {given} (must be in range {min}..={max})")]
+    InvalidCompressionLevel { given: i32, min: i32, max: i32 },
+    #[error("Operation was cancelled")]
+    Cancelled,
+    #[error("There is no mesh with index {0}")]
+    NoSuchMesh(usize),
+    #[error("The attribute filter would remove the Position attribute")]
+    PositionFiltered,
+    #[error("Cannot convert {usage:?} from {from:?} to {to:?}")]
+    UnsupportedConversion {
+        usage: VertexUsage,
+        from: VertexFormat,
+        to: VertexFormat,
+    },
+    #[error("Instance data for {usage:?} is {len} bytes, expected {expected}")]
+    InstanceDataSize {
+        usage: VertexUsage,
+        len: usize,
+        expected: usize,
+    },
+    #[error("Instance data has {found} instances, expected {expected}")]
+    InstanceCount { expected: u32, found: u32 },
+    #[error("Mesh {mesh} uses instances {range:?}, but there are {n_instances}")]
+    InstanceRange {
+        mesh: usize,
+        range: std::ops::Range<u32>,
+        n_instances: u32,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -26,27 +56,326 @@ pub struct IyesMeshWriterSettings {
     ///
     /// Disabling this is a big performance improvement, as there is no need
     /// to go through all the data again after encoding, to compute a
-    /// checksum. It also allows the file to be written as a single pass.
+    /// checksum. It also allows the file to be written as a single pass,
+    /// without seeking or buffering.
     pub write_data_checksum: bool,
+    /// Algorithm of the data and user data checksums.
+    pub checksum_kind: ChecksumKind,
     /// Zstd compression level.
+    ///
+    /// Must be within `zstd::compression_level_range()`. Use the
+    /// [`fastest`](Self::fastest), [`balanced`](Self::balanced), or
+    /// [`smallest`](Self::smallest) constructors if you don't care about
+    /// the exact value.
     pub compression_level: i32,
+    /// Spill the compressed data to a temporary file above this size.
+    ///
+    /// When writing the data checksum to a non-seekable output (using
+    /// `write_to_stream`), the whole compressed payload must be buffered
+    /// before anything can be written. By default, this happens in memory.
+    /// If this is set, a temporary file is used instead, once the data
+    /// grows beyond the given number of bytes.
+    ///
+    /// [`write_to`](IyesMeshWriter::write_to) always spools to a temporary
+    /// file when it cannot hash the data as it is written, keeping only
+    /// this many bytes in memory (none by default).
+    pub spool_threshold: Option<usize>,
+    /// Store Float32x3 positions as Unorm16x4, relative to each mesh's
+    /// bounding box.
+    ///
+    /// The transform needed to recover the original values is stored in
+    /// [`MeshInfo::position_transform`]. The error of each component is at
+    /// most half of the box extent divided by 65535. This takes precedence
+    /// over any conversion set for the Position attribute.
+    pub quantize_positions: bool,
+    /// Store Float32x3 normals octahedral-encoded as Snorm16x2.
+    ///
+    /// See [`crate::mesh::encode`]. This takes precedence over any
+    /// conversion set for the Normal attribute.
+    pub octahedral_normals: bool,
+    /// Store Float32x4 tangents octahedral-encoded as Snorm16x2.
+    ///
+    /// See [`crate::mesh::encode`]. This takes precedence over any
+    /// conversion set for the Tangent attribute.
+    pub octahedral_tangents: bool,
+    /// Apply the sRGB transfer function when converting the Color attribute.
+    ///
+    /// If set, a conversion of colors from float to normalized formats
+    /// encodes them as sRGB (and the other way round decodes them), using
+    /// [`crate::convert::convert_color_data`]. Alpha is always linear.
+    pub srgb_colors: bool,
+    /// Encrypt the user data with this key.
+    ///
+    /// See [`crate::encryption`]. The mesh data is not encrypted.
+    #[cfg(feature = "encryption")]
+    pub user_data_key: Option<[u8; 32]>,
+    /// Sign the file with this Ed25519 secret key.
+    ///
+    /// See [`crate::signing`]. The whole file must be hashed in order, so
+    /// [`write_to`](IyesMeshWriter::write_to) spools the compressed data
+    /// of signed files to a temporary file (see
+    /// [`spool_threshold`](Self::spool_threshold)).
+    #[cfg(feature = "signing")]
+    pub signing_key: Option<[u8; 32]>,
 }
 
 impl Default for IyesMeshWriterSettings {
     fn default() -> Self {
+        Self::smallest()
+    }
+}
+
+impl IyesMeshWriterSettings {
+    /// Settings optimized for encoding speed.
+    pub fn fastest() -> Self {
         Self {
             upconvert_indices: false,
             write_data_checksum: true,
-            compression_level: *zstd::compression_level_range().end(),
+            checksum_kind: ChecksumKind::RapidHash,
+            compression_level: 1,
+            spool_threshold: None,
+            quantize_positions: false,
+            octahedral_normals: false,
+            octahedral_tangents: false,
+            srgb_colors: false,
+            #[cfg(feature = "encryption")]
+            user_data_key: None,
+            #[cfg(feature = "signing")]
+            signing_key: None,
+        }
+    }
+
+    /// Settings for a reasonable tradeoff between speed and file size.
+    pub fn balanced() -> Self {
+        Self {
+            upconvert_indices: false,
+            write_data_checksum: true,
+            checksum_kind: ChecksumKind::RapidHash,
+            compression_level: zstd::DEFAULT_COMPRESSION_LEVEL,
+            spool_threshold: None,
+            quantize_positions: false,
+            octahedral_normals: false,
+            octahedral_tangents: false,
+            srgb_colors: false,
+            #[cfg(feature = "encryption")]
+            user_data_key: None,
+            #[cfg(feature = "signing")]
+            signing_key: None,
+        }
+    }
+
+    /// Settings optimized for the smallest possible file size (default).
+    pub fn smallest() -> Self {
+        Self {
+            upconvert_indices: false,
+            write_data_checksum: true,
+            checksum_kind: ChecksumKind::RapidHash,
+            compression_level: *Self::compression_level_range().end(),
+            spool_threshold: None,
+            quantize_positions: false,
+            octahedral_normals: false,
+            octahedral_tangents: false,
+            srgb_colors: false,
+            #[cfg(feature = "encryption")]
+            user_data_key: None,
+            #[cfg(feature = "signing")]
+            signing_key: None,
+        }
+    }
+
+    /// The range of supported values for `compression_level`.
+    pub fn compression_level_range() -> std::ops::RangeInclusive<i32> {
+        zstd::compression_level_range()
+    }
+
+    /// Check that the settings are usable for encoding.
+    pub fn validate(&self) -> Result<(), WriteError> {
+        let range = Self::compression_level_range();
+        if !range.contains(&self.compression_level) {
+            return Err(WriteError::InvalidCompressionLevel {
+                given: self.compression_level,
+                min: *range.start(),
+                max: *range.end(),
+            });
         }
+        Ok(())
+    }
+}
+
+/// Which vertex attributes get written into the file.
+///
+/// Attributes not allowed by the filter are ignored completely: they are
+/// not stored, and do not need to be compatible between meshes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttributeFilter {
+    /// If set, only these attributes are written.
+    pub keep: Option<HashSet<VertexUsage>>,
+    /// These attributes are never written.
+    pub drop: HashSet<VertexUsage>,
+    /// Allow the filter to remove the Position attribute.
+    ///
+    /// Without this, writing fails with [`WriteError::PositionFiltered`],
+    /// because a file without positions is most likely a mistake.
+    pub allow_dropping_position: bool,
+}
+
+impl AttributeFilter {
+    pub fn allows(&self, usage: VertexUsage) -> bool {
+        !self.drop.contains(&usage)
+            && self.keep.as_ref().is_none_or(|keep| keep.contains(&usage))
     }
 }
 
+/// Reason why a mesh cannot be added together with other meshes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MeshMismatch {
+    /// The mesh data itself is invalid (buffer sizes are inconsistent).
+    InvalidMesh,
+    /// The mesh lacks an attribute that the other meshes have.
+    MissingAttribute(VertexUsage),
+    /// The mesh has an attribute that the other meshes lack.
+    UnexpectedAttribute(VertexUsage),
+    /// The mesh has an attribute in a different format.
+    AttributeFormat {
+        usage: VertexUsage,
+        expected: VertexFormat,
+        found: VertexFormat,
+    },
+    /// The mesh has a different (or missing) index buffer.
+    IndexFormat {
+        expected: Option<IndexFormat>,
+        found: Option<IndexFormat>,
+    },
+    /// The requested attribute conversion is not possible for this mesh.
+    UnsupportedConversion {
+        usage: VertexUsage,
+        from: VertexFormat,
+        to: VertexFormat,
+    },
+}
+
+impl std::fmt::Display for MeshMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MeshMismatch::InvalidMesh => write!(f, "Invalid Mesh Data"),
+            MeshMismatch::MissingAttribute(usage) => {
+                write!(f, "Missing attribute {:?}", usage)
+            }
+            MeshMismatch::UnexpectedAttribute(usage) => {
+                write!(f, "Unexpected attribute {:?}", usage)
+            }
+            MeshMismatch::AttributeFormat {
+                usage,
+                expected,
+                found,
+            } => {
+                write!(
+                    f,
+                    "Attribute {:?} has format {:?}, expected {:?}",
+                    usage, found, expected
+                )
+            }
+            MeshMismatch::IndexFormat { expected, found } => {
+                write!(
+                    f,
+                    "Indices are {:?}, expected {:?}",
+                    found, expected
+                )
+            }
+            MeshMismatch::UnsupportedConversion { usage, from, to } => {
+                write!(
+                    f,
+                    "Cannot convert attribute {:?} from {:?} to {:?}",
+                    usage, from, to
+                )
+            }
+        }
+    }
+}
+
+/// A mesh that was rejected by [`IyesMeshWriter::add_meshes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedMesh {
+    /// Position of the mesh among the meshes that were being added.
+    pub index: usize,
+    pub problems: Vec<MeshMismatch>,
+}
+
+/// Error returned by [`IyesMeshWriter::add_meshes`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, thiserror::Error)]
+#[error("{} meshes are incompatible", rejected.len())]
+pub struct AddMeshesReport {
+    pub rejected: Vec<RejectedMesh>,
+}
+
+/// Which stage of the encoding process is currently running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WritePhase {
+    /// Checking the meshes and computing the file metadata.
+    Scanning,
+    /// Compressing the data.
+    Compressing,
+    /// Computing the data checksum.
+    Checksumming,
+    /// Writing out the final output.
+    Flushing,
+    /// The file has been written successfully.
+    Done,
+}
+
+/// Progress information reported during writing.
+///
+/// The byte counts refer to uncompressed payload data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WriteProgress {
+    pub phase: WritePhase,
+    pub bytes_processed: u64,
+    pub bytes_total: u64,
+}
+
+/// Information about the file that would be written.
+///
+/// See [`IyesMeshWriter::plan`].
+#[derive(Debug, Clone)]
+pub struct WritePlan {
+    pub descriptor: IyesMeshDescriptor,
+    /// Size of the encoded descriptor in bytes.
+    pub descriptor_len: usize,
+    /// Size of all the data (before compression) in bytes.
+    pub total_uncompressed_len: u64,
+    /// Whether any U16 index data will be converted to U32.
+    pub upconverting_indices: bool,
+}
+
 pub struct IyesMeshWriter<'s> {
     user_data: Option<&'s [u8]>,
-    settings: IyesMeshWriterSettings,
+    /// Nonce of user data that is already encrypted.
+    user_data_nonce: Option<[u8; 12]>,
+    /// Nonce to encrypt the user data with, if a key is set.
+    ///
+    /// Generated along with the user data, so that every write of it gives
+    /// the same bytes, and a new one is used for different data.
+    #[cfg(feature = "encryption")]
+    encryption_nonce: Option<[u8; 12]>,
+    pub(crate) settings: IyesMeshWriterSettings,
     src_meshes: Vec<MeshDataRef<'s>>,
     scratch: Vec<u8>,
+    progress: Option<Box<dyn FnMut(WriteProgress) + Send>>,
+    cancel: Option<CancelToken>,
+    attribute_filter: AttributeFilter,
+    attribute_conversions: HashMap<VertexUsage, VertexFormat>,
+    color_spaces: HashMap<VertexUsage, ColorSpace>,
+    instance_data: HashMap<VertexUsage, (VertexFormat, &'s [u8])>,
+    n_instances: u32,
+    instance_ranges: HashMap<usize, std::ops::Range<u32>>,
+    /// Result of `prepare`, kept around for subsequent writes.
+    prepared: Option<PreparedFile>,
+}
+
+impl Default for IyesMeshWriter<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<'s> IyesMeshWriter<'s> {
@@ -58,20 +387,301 @@ impl<'s> IyesMeshWriter<'s> {
         Self {
             settings,
             user_data: None,
+            user_data_nonce: None,
+            #[cfg(feature = "encryption")]
+            encryption_nonce: None,
             src_meshes: vec![],
             scratch: vec![],
+            progress: None,
+            cancel: None,
+            attribute_filter: Default::default(),
+            attribute_conversions: Default::default(),
+            color_spaces: Default::default(),
+            instance_data: Default::default(),
+            n_instances: 0,
+            instance_ranges: Default::default(),
+            prepared: None,
         }
     }
 
+    /// Choose which vertex attributes to write, without changing the meshes.
+    pub fn set_attribute_filter(
+        &mut self,
+        filter: AttributeFilter,
+    ) {
+        self.attribute_filter = filter;
+        self.prepared = None;
+    }
+
+    pub fn attribute_filter(&self) -> &AttributeFilter {
+        &self.attribute_filter
+    }
+
+    /// Store the given attribute in a different format.
+    ///
+    /// The data is converted while encoding (see [`crate::convert`]),
+    /// so the source meshes do not need to be modified. With a conversion
+    /// set, the source meshes may have the attribute in different formats,
+    /// as long as all of them can be converted to `format`.
+    ///
+    /// Meshes that don't have the attribute are not affected.
+    pub fn set_attribute_conversion(
+        &mut self,
+        usage: VertexUsage,
+        format: VertexFormat,
+    ) {
+        self.attribute_conversions.insert(usage, format);
+        self.prepared = None;
+    }
+
+    pub fn clear_attribute_conversion(
+        &mut self,
+        usage: VertexUsage,
+    ) {
+        self.attribute_conversions.remove(&usage);
+        self.prepared = None;
+    }
+
+    pub fn attribute_conversions(&self) -> &HashMap<VertexUsage, VertexFormat> {
+        &self.attribute_conversions
+    }
+
+    /// Record the color space of an attribute in the file.
+    ///
+    /// This only tags the data; it is not converted (see
+    /// [`crate::mesh::convert_color_space`]). Tags for attributes that are
+    /// not written are ignored.
+    pub fn set_color_space(
+        &mut self,
+        usage: VertexUsage,
+        color_space: ColorSpace,
+    ) {
+        self.color_spaces.insert(usage, color_space);
+        self.prepared = None;
+    }
+
+    /// Leave the color space of an attribute unspecified.
+    pub fn clear_color_space(
+        &mut self,
+        usage: VertexUsage,
+    ) {
+        self.color_spaces.remove(&usage);
+        self.prepared = None;
+    }
+
+    pub fn color_spaces(&self) -> &HashMap<VertexUsage, ColorSpace> {
+        &self.color_spaces
+    }
+
+    /// Store a per-instance attribute in the file.
+    ///
+    /// `data` holds `n_instances` values of `format`, and is written as-is
+    /// (attribute filters and conversions only apply to vertex attributes).
+    /// All instance attributes must have the same number of instances.
+    pub fn set_instance_data(
+        &mut self,
+        usage: VertexUsage,
+        format: VertexFormat,
+        data: &'s [u8],
+        n_instances: u32,
+    ) -> Result<(), WriteError> {
+        let expected = format.size() * n_instances as usize;
+        if data.len() != expected {
+            return Err(WriteError::InstanceDataSize {
+                usage,
+                len: data.len(),
+                expected,
+            });
+        }
+        let others = self.instance_data.keys().any(|u| *u != usage);
+        if others && n_instances != self.n_instances {
+            return Err(WriteError::InstanceCount {
+                expected: self.n_instances,
+                found: n_instances,
+            });
+        }
+        self.instance_data.insert(usage, (format, data));
+        self.n_instances = n_instances;
+        self.prepared = None;
+        Ok(())
+    }
+
+    pub fn clear_instance_data(
+        &mut self,
+        usage: VertexUsage,
+    ) {
+        self.instance_data.remove(&usage);
+        if self.instance_data.is_empty() {
+            self.n_instances = 0;
+        }
+        self.prepared = None;
+    }
+
+    pub fn instance_data(
+        &self,
+    ) -> &HashMap<VertexUsage, (VertexFormat, &'s [u8])> {
+        &self.instance_data
+    }
+
+    pub fn n_instances(&self) -> u32 {
+        self.n_instances
+    }
+
+    /// Choose which instances a mesh uses.
+    ///
+    /// By default, every mesh uses all instances. The range is checked
+    /// against the instance count when writing.
+    pub fn set_mesh_instances(
+        &mut self,
+        index: usize,
+        range: std::ops::Range<u32>,
+    ) -> Result<(), WriteError> {
+        if index >= self.src_meshes.len() {
+            return Err(WriteError::NoSuchMesh(index));
+        }
+        self.instance_ranges.insert(index, range);
+        self.prepared = None;
+        Ok(())
+    }
+
+    /// The instances a mesh will use.
+    pub fn mesh_instances(
+        &self,
+        index: usize,
+    ) -> std::ops::Range<u32> {
+        self.instance_ranges
+            .get(&index)
+            .cloned()
+            .unwrap_or(0..self.n_instances)
+    }
+
+    /// The special encoding an attribute will be stored with, if any.
+    fn output_encoding(
+        &self,
+        usage: VertexUsage,
+    ) -> Option<AttributeEncoding> {
+        match usage {
+            VertexUsage::Normal if self.settings.octahedral_normals => {
+                Some(AttributeEncoding::Octahedral)
+            }
+            VertexUsage::Tangent if self.settings.octahedral_tangents => {
+                Some(AttributeEncoding::OctahedralSigned)
+            }
+            _ => None,
+        }
+    }
+
+    /// The format an attribute will be stored as.
+    fn output_format(
+        &self,
+        usage: VertexUsage,
+        format: VertexFormat,
+    ) -> Result<VertexFormat, WriteError> {
+        if usage == VertexUsage::Position && self.settings.quantize_positions {
+            if format != VertexFormat::Float32x3 {
+                return Err(WriteError::UnsupportedConversion {
+                    usage,
+                    from: format,
+                    to: QUANTIZED_POSITION_FORMAT,
+                });
+            }
+            return Ok(QUANTIZED_POSITION_FORMAT);
+        }
+        if let Some(encoding) = self.output_encoding(usage) {
+            if format != encoding.decoded_format() {
+                return Err(WriteError::UnsupportedConversion {
+                    usage,
+                    from: format,
+                    to: encoding.stored_format(),
+                });
+            }
+            return Ok(encoding.stored_format());
+        }
+        match self.attribute_conversions.get(&usage) {
+            None => Ok(format),
+            Some(&to) if crate::convert::can_convert(format, to) => Ok(to),
+            Some(&to) => Err(WriteError::UnsupportedConversion {
+                usage,
+                from: format,
+                to,
+            }),
+        }
+    }
+
+    /// Allow the encoding to be aborted using the given token.
+    ///
+    /// If cancelled, `write_to` returns [`WriteError::Cancelled`]. Any
+    /// output written up to that point is incomplete and must be treated
+    /// as invalid.
+    pub fn set_cancel_token(
+        &mut self,
+        token: CancelToken,
+    ) {
+        self.cancel = Some(token);
+    }
+
+    pub fn clear_cancel_token(&mut self) {
+        self.cancel = None;
+    }
+
+    /// Install a callback to be notified about the progress of encoding.
+    ///
+    /// It is called at the start of every phase and periodically
+    /// while compressing.
+    pub fn set_progress_callback(
+        &mut self,
+        callback: Box<dyn FnMut(WriteProgress) + Send>,
+    ) {
+        self.progress = Some(callback);
+    }
+
+    pub fn clear_progress_callback(&mut self) {
+        self.progress = None;
+    }
+
     pub fn set_user_data(
         &mut self,
         user_data: &'s [u8],
     ) {
         self.user_data = Some(user_data);
+        self.user_data_nonce = None;
+        #[cfg(feature = "encryption")]
+        {
+            self.encryption_nonce = self
+                .settings
+                .user_data_key
+                .map(|_| crate::encryption::generate_nonce());
+        }
+        self.prepared = None;
+    }
+
+    /// Store user data that is already encrypted with the given nonce.
+    ///
+    /// Used to copy the user data of a file without decrypting it (see
+    /// [`IyesMeshDescriptor::user_data_nonce`]). The data is written as-is,
+    /// even if a key is set.
+    pub fn set_encrypted_user_data(
+        &mut self,
+        user_data: &'s [u8],
+        nonce: [u8; 12],
+    ) {
+        self.user_data = Some(user_data);
+        self.user_data_nonce = Some(nonce);
+        #[cfg(feature = "encryption")]
+        {
+            self.encryption_nonce = None;
+        }
+        self.prepared = None;
     }
 
     pub fn clear_user_data(&mut self) {
         self.user_data = None;
+        self.user_data_nonce = None;
+        #[cfg(feature = "encryption")]
+        {
+            self.encryption_nonce = None;
+        }
+        self.prepared = None;
     }
 
     pub fn with_user_data(
@@ -87,6 +697,11 @@ impl<'s> IyesMeshWriter<'s> {
         self
     }
 
+    /// Add a mesh to be written into the file.
+    ///
+    /// The mesh data is checked for validity, but compatibility with the
+    /// other meshes is only checked when writing. Use `add_meshes` to also
+    /// check compatibility up front.
     pub fn add_mesh(
         &mut self,
         mesh: MeshDataRef<'s>,
@@ -95,6 +710,7 @@ impl<'s> IyesMeshWriter<'s> {
             return Err(WriteError::InvalidMesh);
         }
         self.src_meshes.push(mesh);
+        self.prepared = None;
         Ok(())
     }
 
@@ -106,13 +722,168 @@ impl<'s> IyesMeshWriter<'s> {
         Ok(self)
     }
 
+    /// Remove a previously-added mesh.
+    ///
+    /// The meshes after it move down to fill the gap.
+    pub fn remove_mesh(
+        &mut self,
+        index: usize,
+    ) -> Result<MeshDataRef<'s>, WriteError> {
+        if index >= self.src_meshes.len() {
+            return Err(WriteError::NoSuchMesh(index));
+        }
+        self.prepared = None;
+        self.instance_ranges = std::mem::take(&mut self.instance_ranges)
+            .into_iter()
+            .filter(|(i, _)| *i != index)
+            .map(|(i, range)| (if i > index { i - 1 } else { i }, range))
+            .collect();
+        Ok(self.src_meshes.remove(index))
+    }
+
+    /// Swap out a previously-added mesh for a different one.
+    ///
+    /// Returns the old mesh. As with `add_mesh`, compatibility with the
+    /// other meshes is not checked until writing.
+    pub fn replace_mesh(
+        &mut self,
+        index: usize,
+        mesh: MeshDataRef<'s>,
+    ) -> Result<MeshDataRef<'s>, WriteError> {
+        if !mesh.validate() {
+            return Err(WriteError::InvalidMesh);
+        }
+        let slot = self
+            .src_meshes
+            .get_mut(index)
+            .ok_or(WriteError::NoSuchMesh(index))?;
+        self.prepared = None;
+        Ok(std::mem::replace(slot, mesh))
+    }
+
+    pub fn n_meshes(&self) -> usize {
+        self.src_meshes.len()
+    }
+
+    pub fn meshes(&self) -> &[MeshDataRef<'s>] {
+        &self.src_meshes
+    }
+
+    /// Add many meshes at once, checking that they are all compatible.
+    ///
+    /// Each mesh is compared against the first mesh already in the writer
+    /// (or the first of the new meshes, if the writer is empty). If any
+    /// of them are invalid or incompatible, none are added, and the report
+    /// describes the problems with each rejected mesh.
+    pub fn add_meshes(
+        &mut self,
+        meshes: impl IntoIterator<Item = MeshDataRef<'s>>,
+    ) -> Result<(), AddMeshesReport> {
+        let meshes: Vec<_> = meshes.into_iter().collect();
+        let reference = self.src_meshes.first().or(meshes.first());
+        let mut report = AddMeshesReport::default();
+        for (index, mesh) in meshes.iter().enumerate() {
+            let problems = match reference {
+                Some(reference) => self.mesh_problems(reference, mesh),
+                None => vec![],
+            };
+            if !problems.is_empty() {
+                report.rejected.push(RejectedMesh { index, problems });
+            }
+        }
+        if !report.rejected.is_empty() {
+            return Err(report);
+        }
+        self.src_meshes.extend(meshes);
+        self.prepared = None;
+        Ok(())
+    }
+
+    /// Find everything that prevents `mesh` from being stored in the same
+    /// file as `reference`.
+    fn mesh_problems(
+        &self,
+        reference: &MeshDataRef<'_>,
+        mesh: &MeshDataRef<'_>,
+    ) -> Vec<MeshMismatch> {
+        let mut r = vec![];
+        if !mesh.validate() {
+            r.push(MeshMismatch::InvalidMesh);
+        }
+        let expected = reference.indices.map(|b| b.0);
+        let found = mesh.indices.map(|b| b.0);
+        let indices_ok = match (expected, found) {
+            (Some(_), Some(_)) if self.settings.upconvert_indices => true,
+            (expected, found) => expected == found,
+        };
+        if !indices_ok {
+            r.push(MeshMismatch::IndexFormat { expected, found });
+        }
+        for (usage, (found, _)) in mesh.attributes.iter() {
+            if !self.attribute_filter.allows(*usage) {
+                continue;
+            }
+            if let Err(WriteError::UnsupportedConversion { usage, from, to }) =
+                self.output_format(*usage, *found)
+            {
+                r.push(MeshMismatch::UnsupportedConversion { usage, from, to });
+            }
+        }
+        for (usage, (expected, _)) in reference.attributes.iter() {
+            if !self.attribute_filter.allows(*usage) {
+                continue;
+            }
+            match mesh.attributes.get(usage) {
+                None => r.push(MeshMismatch::MissingAttribute(*usage)),
+                Some((found, _)) if found != expected => {
+                    let converted = self
+                        .output_format(*usage, *expected)
+                        .ok()
+                        .filter(|to| {
+                            self.output_format(*usage, *found).ok() == Some(*to)
+                        });
+                    if converted.is_none() {
+                        r.push(MeshMismatch::AttributeFormat {
+                            usage: *usage,
+                            expected: *expected,
+                            found: *found,
+                        });
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        for usage in mesh.attributes.keys() {
+            if self.attribute_filter.allows(*usage)
+                && !reference.attributes.contains_key(usage)
+            {
+                r.push(MeshMismatch::UnexpectedAttribute(*usage));
+            }
+        }
+        r
+    }
+
     fn scan_needed_buffers(&self) -> Result<HaveBuffers, WriteError> {
         let mut iter = self.src_meshes.iter();
         let first = iter.next().ok_or(WriteError::NoMeshes)?;
         let mut r = HaveBuffers {
             indices: first.indices.map(|b| b.0),
-            attrs: first.attributes.iter().map(|b| (*b.0, b.1.0)).collect(),
+            attrs: Default::default(),
         };
+        for (usage, (format, _)) in first.attributes.iter() {
+            if self.attribute_filter.allows(*usage) {
+                r.attrs.insert(*usage, self.output_format(*usage, *format)?);
+            }
+        }
+        if !self.attribute_filter.allow_dropping_position
+            && !self.attribute_filter.allows(VertexUsage::Position)
+            && self
+                .src_meshes
+                .iter()
+                .any(|m| m.attributes.contains_key(&VertexUsage::Position))
+        {
+            return Err(WriteError::PositionFiltered);
+        }
         for m in iter {
             match (m.indices.map(|b| b.0), r.indices) {
                 (None, None)
@@ -131,15 +902,18 @@ impl<'s> IyesMeshWriter<'s> {
                 }
                 _ => return Err(WriteError::IncompatibleMeshes),
             }
-            if !m.attributes.iter().all(|b| r.attrs.get(b.0) == Some(&b.1.0)) {
-                return Err(WriteError::IncompatibleMeshes);
+            let mut n_attrs = 0;
+            for (usage, (format, _)) in m.attributes.iter() {
+                if !self.attribute_filter.allows(*usage) {
+                    continue;
+                }
+                let format = self.output_format(*usage, *format)?;
+                if r.attrs.get(usage) != Some(&format) {
+                    return Err(WriteError::IncompatibleMeshes);
+                }
+                n_attrs += 1;
             }
-            if !r.attrs.iter().all(|br| {
-                m.attributes
-                    .iter()
-                    .find(|bm| bm.0 == br.0 && bm.1.0 == *br.1)
-                    .is_some()
-            }) {
+            if n_attrs != r.attrs.len() {
                 return Err(WriteError::IncompatibleMeshes);
             }
         }
@@ -148,6 +922,7 @@ impl<'s> IyesMeshWriter<'s> {
 
     fn compute_uncompressed_sizes(
         &self,
+        havebufs: &HaveBuffers,
         upconverting_indices: bool,
     ) -> u64 {
         let mut total = 0;
@@ -159,21 +934,33 @@ impl<'s> IyesMeshWriter<'s> {
                     total += b.1.len() as u64;
                 }
             }
-            for b in m.attributes.iter() {
-                total += b.1.1.len() as u64;
+            for format in havebufs.attrs.values() {
+                total += (m.n_vertices() * format.size()) as u64;
             }
         }
+        for (_, data) in self.instance_data.values() {
+            total += data.len() as u64;
+        }
         total
     }
 
     fn gen_meshinfo(
         &self,
         has_indices: bool,
+        quantizing_positions: bool,
     ) -> Vec<MeshInfo> {
         let mut r = Vec::with_capacity(self.src_meshes.len());
         let mut base_vertex = 0;
         let mut first = 0;
-        for m in self.src_meshes.iter() {
+        for (i, m) in self.src_meshes.iter().enumerate() {
+            let instances = self.mesh_instances(i);
+            let position_transform = if quantizing_positions {
+                m.attributes
+                    .get(&VertexUsage::Position)
+                    .map(|(_, bytes)| compute_position_transform(bytes))
+            } else {
+                None
+            };
             if has_indices {
                 let n_indices = m.n_indices().unwrap() as u32;
                 let n_vertices = m.n_vertices() as u32;
@@ -182,6 +969,9 @@ impl<'s> IyesMeshWriter<'s> {
                     index_count: n_indices,
                     first_vertex: base_vertex,
                     vertex_count: n_vertices,
+                    position_transform,
+                    first_instance: instances.start,
+                    instance_count: instances.len() as u32,
                 });
                 first += n_indices;
                 base_vertex += n_vertices;
@@ -192,6 +982,9 @@ impl<'s> IyesMeshWriter<'s> {
                     index_count: 0,
                     first_vertex: first,
                     vertex_count: n_vertices,
+                    position_transform,
+                    first_instance: instances.start,
+                    instance_count: instances.len() as u32,
                 });
                 first += n_vertices;
             }
@@ -199,31 +992,121 @@ impl<'s> IyesMeshWriter<'s> {
         r
     }
 
-    pub fn write_to(
-        mut self,
-        write: &'s mut dyn WriteSeek,
-    ) -> Result<(), WriteError> {
+    /// Compute what the file will look like, without encoding anything.
+    ///
+    /// This performs all the validation that writing would, so any errors
+    /// (except I/O) will be reported here.
+    pub fn plan(&self) -> Result<WritePlan, WriteError> {
+        let prepared = match &self.prepared {
+            Some(prepared) => prepared.clone(),
+            None => self.compute_prepared()?,
+        };
+        Ok(WritePlan {
+            descriptor_len: prepared.bytes_descriptor.len(),
+            descriptor: prepared.descriptor,
+            total_uncompressed_len: prepared.total_uncompressed_len,
+            upconverting_indices: prepared.upconverting_indices,
+        })
+    }
+
+    /// Validate everything and compute the file metadata.
+    ///
+    /// The result is cached until the contents of the writer are changed.
+    pub(crate) fn prepare(&mut self) -> Result<PreparedFile, WriteError> {
+        self.report_progress(WritePhase::Scanning, 0, 0);
+        if let Some(prepared) = &self.prepared {
+            return Ok(prepared.clone());
+        }
+        let prepared = self.compute_prepared()?;
+        self.prepared = Some(prepared.clone());
+        Ok(prepared)
+    }
+
+    /// The length, checksum and nonce of the user data, as stored in the
+    /// file, and the encrypted data if it has to be encrypted (a key is set).
+    fn stored_user_data(&self) -> StoredUserData {
+        let Some(data) = self.user_data else {
+            return StoredUserData::default();
+        };
+        #[cfg(feature = "encryption")]
+        if let (Some(key), Some(nonce)) =
+            (self.settings.user_data_key, self.encryption_nonce)
+        {
+            let encrypted = crate::encryption::encrypt(&key, &nonce, data);
+            let checksum = self.settings.checksum_kind.checksum(&encrypted);
+            return StoredUserData {
+                len: encrypted.len() as u32,
+                checksum: Some(checksum),
+                nonce: Some(nonce),
+                encrypted: Some(encrypted),
+            };
+        }
+        StoredUserData {
+            len: data.len() as u32,
+            checksum: Some(self.settings.checksum_kind.checksum(data)),
+            nonce: self.user_data_nonce,
+            encrypted: None,
+        }
+    }
+
+    fn compute_prepared(&self) -> Result<PreparedFile, WriteError> {
+        self.settings.validate()?;
         let havebufs = self.scan_needed_buffers()?;
-        let computed_bufsizes = self.compute_uncompressed_sizes(
-            self.settings.upconvert_indices
-                && havebufs.indices == Some(IndexFormat::U32),
-        );
+        for (mesh, range) in self.instance_ranges.iter() {
+            if range.start > range.end || range.end > self.n_instances {
+                return Err(WriteError::InstanceRange {
+                    mesh: *mesh,
+                    range: range.clone(),
+                    n_instances: self.n_instances,
+                });
+            }
+        }
+        let upconverting_indices = self.settings.upconvert_indices
+            && havebufs.indices == Some(IndexFormat::U32);
+        let computed_bufsizes =
+            self.compute_uncompressed_sizes(&havebufs, upconverting_indices);
         let n_vertices: usize =
             self.src_meshes.iter().map(|m| m.n_vertices()).sum();
         let n_indices: usize =
             self.src_meshes.iter().filter_map(|m| m.n_indices()).sum();
+        let user_data = self.stored_user_data();
         let descriptor = IyesMeshDescriptor {
             n_vertices: n_vertices as u32,
-            user_data_len: self.user_data.map(|b| b.len() as u32).unwrap_or(0),
-            meshes: self.gen_meshinfo(havebufs.indices.is_some()),
+            user_data_len: user_data.len,
+            meshes: self.gen_meshinfo(
+                havebufs.indices.is_some(),
+                self.settings.quantize_positions
+                    && havebufs.attrs.contains_key(&VertexUsage::Position),
+            ),
             indices: havebufs.indices.map(|format| IndicesInfo {
                 n_indices: n_indices as u32,
                 format,
             }),
             attributes: havebufs.attrs.clone(),
+            attribute_encodings: havebufs
+                .attrs
+                .keys()
+                .filter_map(|usage| Some((*usage, self.output_encoding(*usage)?)))
+                .collect(),
+            color_spaces: self
+                .color_spaces
+                .iter()
+                .filter(|(usage, _)| havebufs.attrs.contains_key(usage))
+                .map(|(usage, color_space)| (*usage, *color_space))
+                .collect(),
+            n_instances: self.n_instances,
+            instance_attributes: self
+                .instance_data
+                .iter()
+                .map(|(usage, (format, _))| (*usage, *format))
+                .collect(),
+            user_data_checksum: user_data.checksum,
+            user_data_nonce: user_data.nonce,
+            signed: FileSigner::new(&self.settings).is_active(),
+            checksum_kind: self.settings.checksum_kind.id(),
         };
         let bytes_descriptor = bitcode::encode(&descriptor);
-        let mut header = IyesMeshHeader {
+        let header = IyesMeshHeader {
             magic: crate::MAGIC,
             version: crate::FORMAT_VERSION,
             descriptor_len: bytes_descriptor.len() as u16,
@@ -232,42 +1115,399 @@ impl<'s> IyesMeshWriter<'s> {
         };
         let total_uncompressed_len =
             computed_bufsizes + descriptor.user_data_len as u64;
+        Ok(PreparedFile {
+            descriptor,
+            bytes_descriptor,
+            header,
+            total_uncompressed_len,
+            encrypted_user_data: user_data.encrypted,
+            upconverting_indices: upconverting_indices
+                && self.src_meshes.iter().any(|m| {
+                    m.indices.is_some_and(|b| b.0 == IndexFormat::U16)
+                }),
+        })
+    }
+
+    /// Encode the file into a seekable output.
+    ///
+    /// The writer can be used again afterwards, to produce identical output
+    /// into another destination.
+    ///
+    /// If the data checksum is enabled and the algorithm does not need to
+    /// know the length of the data in advance (see
+    /// [`ChecksumKind::needs_len`]), the data is hashed as it is written,
+    /// and the header is patched afterwards. Otherwise (and for signed
+    /// files), the compressed data is spooled to a temporary file first,
+    /// so that it is never held in memory all at once (see
+    /// [`IyesMeshWriterSettings::spool_threshold`]). Use
+    /// [`write_to_readable`](Self::write_to_readable) to avoid the copy.
+    pub fn write_to(
+        &mut self,
+        write: &mut dyn WriteSeek,
+    ) -> Result<(), WriteError> {
+        let prepared = self.prepare()?;
+        let total_uncompressed_len = prepared.total_uncompressed_len;
+        let mut signer = FileSigner::new(&self.settings);
+        if self.settings.write_data_checksum
+            && !self.settings.checksum_kind.needs_len()
+            && !signer.is_active()
+        {
+            self.write_hashing(prepared, write)?;
+        } else {
+            let mut tee = TeeWrite {
+                inner: &mut *write,
+                signer: &mut signer,
+            };
+            if self.settings.write_data_checksum {
+                let threshold = self.settings.spool_threshold.unwrap_or(0);
+                self.write_buffered(prepared, &mut tee, Some(threshold))?;
+            } else {
+                self.write_single_pass(prepared, &mut tee)?;
+            }
+        }
+        if let Some(signature) = signer.finish() {
+            write.write_all(&signature)?;
+        }
+        write.flush()?;
+        self.report_progress(
+            WritePhase::Done,
+            total_uncompressed_len,
+            total_uncompressed_len,
+        );
+        Ok(())
+    }
+
+    /// Encode the file into an output that can also be read back (e.g. a
+    /// `File` opened for reading and writing).
+    ///
+    /// Nothing is buffered: if the data checksum is enabled, the data is
+    /// written out in one go, then read back to compute the checksum, and
+    /// the header is patched afterwards. Signed files are read back once
+    /// more to compute the signature.
+    pub fn write_to_readable(
+        &mut self,
+        write: &mut dyn ReadWriteSeek,
+    ) -> Result<(), WriteError> {
+        let prepared = self.prepare()?;
+        let total_uncompressed_len = prepared.total_uncompressed_len;
+        let start = write.stream_position()?;
         if self.settings.write_data_checksum {
-            let mut comprbuf = vec![];
+            self.write_patched(prepared, write)?;
+        } else {
+            self.write_single_pass(prepared, write)?;
+        }
+        let mut signer = FileSigner::new(&self.settings);
+        if signer.is_active() {
+            let end = write.stream_position()?;
+            write.seek(SeekFrom::Start(start))?;
+            std::io::copy(&mut (&mut *write).take(end - start), &mut signer)?;
+            write.seek(SeekFrom::Start(end))?;
+        }
+        if let Some(signature) = signer.finish() {
+            write.write_all(&signature)?;
+        }
+        write.flush()?;
+        self.report_progress(
+            WritePhase::Done,
+            total_uncompressed_len,
+            total_uncompressed_len,
+        );
+        Ok(())
+    }
+
+    /// Encode the file into any output, without seeking.
+    ///
+    /// If the data checksum is enabled, the compressed data has to be
+    /// buffered before anything can be written (see
+    /// [`IyesMeshWriterSettings::spool_threshold`]).
+    pub fn write_to_stream(
+        &mut self,
+        write: &mut dyn Write,
+    ) -> Result<(), WriteError> {
+        let prepared = self.prepare()?;
+        let total_uncompressed_len = prepared.total_uncompressed_len;
+        let mut signer = FileSigner::new(&self.settings);
+        let mut tee = TeeWrite {
+            inner: &mut *write,
+            signer: &mut signer,
+        };
+        if self.settings.write_data_checksum {
+            let threshold = self.settings.spool_threshold;
+            self.write_buffered(prepared, &mut tee, threshold)?;
+        } else {
+            self.write_single_pass(prepared, &mut tee)?;
+        }
+        if let Some(signature) = signer.finish() {
+            write.write_all(&signature)?;
+        }
+        write.flush()?;
+        self.report_progress(
+            WritePhase::Done,
+            total_uncompressed_len,
+            total_uncompressed_len,
+        );
+        Ok(())
+    }
+
+    /// Write everything in one go, without a data checksum.
+    fn write_single_pass<W: Write + ?Sized>(
+        &mut self,
+        prepared: PreparedFile,
+        write: &mut W,
+    ) -> Result<(), WriteError> {
+        let PreparedFile {
+            descriptor,
+            bytes_descriptor,
+            encrypted_user_data,
+            mut header,
+            total_uncompressed_len,
+            ..
+        } = prepared;
+        header.metadata_checksum =
+            crate::checksum::checksum_metadata(header, &bytes_descriptor);
+        write.write_all(header.as_bytes())?;
+        write.write_all(&bytes_descriptor)?;
+        let encoder = new_zstd_encoder(
+            &mut *write,
+            self.settings.compression_level,
+            total_uncompressed_len,
+        )?;
+        self.do_encode_data(
+                &descriptor,
+                encrypted_user_data.as_deref(),
+                encoder,
+                total_uncompressed_len,
+            )?;
+        self.report_progress(
+            WritePhase::Flushing,
+            total_uncompressed_len,
+            total_uncompressed_len,
+        );
+        Ok(())
+    }
+
+    /// Write the data directly, hashing it on the way, then seek back to
+    /// fill in the header.
+    ///
+    /// The checksum algorithm must not need the length in advance.
+    fn write_hashing(
+        &mut self,
+        prepared: PreparedFile,
+        write: &mut dyn WriteSeek,
+    ) -> Result<(), WriteError> {
+        let PreparedFile {
+            descriptor,
+            bytes_descriptor,
+            encrypted_user_data,
+            mut header,
+            total_uncompressed_len,
+            ..
+        } = prepared;
+        let header_start = write.stream_position()?;
+        write.write_all(header.as_bytes())?;
+        write.write_all(&bytes_descriptor)?;
+        let hashing = ChecksummingWriter::without_len(
+            &mut *write,
+            self.settings.checksum_kind,
+        )
+        .expect("the checksum algorithm does not need the length");
+        let encoder = new_zstd_encoder(
+            hashing,
+            self.settings.compression_level,
+            total_uncompressed_len,
+        )?;
+        let hashing =
+            self.do_encode_data(
+                &descriptor,
+                encrypted_user_data.as_deref(),
+                encoder,
+                total_uncompressed_len,
+            )?;
+        header.data_checksum = hashing.finish();
+        header.metadata_checksum =
+            crate::checksum::checksum_metadata(header, &bytes_descriptor);
+        self.report_progress(
+            WritePhase::Flushing,
+            total_uncompressed_len,
+            total_uncompressed_len,
+        );
+        let data_end = write.stream_position()?;
+        write.seek(SeekFrom::Start(header_start))?;
+        write.write_all(header.as_bytes())?;
+        write.seek(SeekFrom::Start(data_end))?;
+        Ok(())
+    }
+
+    /// Write the data directly, then read it back to compute the checksum
+    /// and seek back to fill in the header.
+    fn write_patched(
+        &mut self,
+        prepared: PreparedFile,
+        write: &mut dyn ReadWriteSeek,
+    ) -> Result<(), WriteError> {
+        let PreparedFile {
+            descriptor,
+            bytes_descriptor,
+            encrypted_user_data,
+            mut header,
+            total_uncompressed_len,
+            ..
+        } = prepared;
+        let header_start = write.stream_position()?;
+        write.write_all(header.as_bytes())?;
+        write.write_all(&bytes_descriptor)?;
+        let data_start = write.stream_position()?;
+        let encoder = new_zstd_encoder(
+            &mut *write,
+            self.settings.compression_level,
+            total_uncompressed_len,
+        )?;
+        self.do_encode_data(
+                &descriptor,
+                encrypted_user_data.as_deref(),
+                encoder,
+                total_uncompressed_len,
+            )?;
+        self.report_progress(
+            WritePhase::Checksumming,
+            total_uncompressed_len,
+            total_uncompressed_len,
+        );
+        let data_end = write.stream_position()?;
+        write.seek(SeekFrom::Start(data_start))?;
+        header.data_checksum =
+            self.hash_compressed(&mut *write, data_end - data_start)?;
+        header.metadata_checksum =
+            crate::checksum::checksum_metadata(header, &bytes_descriptor);
+        self.report_progress(
+            WritePhase::Flushing,
+            total_uncompressed_len,
+            total_uncompressed_len,
+        );
+        write.seek(SeekFrom::Start(header_start))?;
+        write.write_all(header.as_bytes())?;
+        write.seek(SeekFrom::Start(data_end))?;
+        Ok(())
+    }
+
+    /// Buffer all the compressed data, so that the header can be written
+    /// first.
+    ///
+    /// With a `spool_threshold`, the data is moved to a temporary file
+    /// once it grows beyond that many bytes. Otherwise, it stays in memory.
+    fn write_buffered(
+        &mut self,
+        prepared: PreparedFile,
+        write: &mut dyn Write,
+        spool_threshold: Option<usize>,
+    ) -> Result<(), WriteError> {
+        let PreparedFile {
+            descriptor,
+            bytes_descriptor,
+            encrypted_user_data,
+            mut header,
+            total_uncompressed_len,
+            ..
+        } = prepared;
+        if let Some(threshold) = spool_threshold {
+            let mut spool = tempfile::spooled_tempfile(threshold);
             let encoder = new_zstd_encoder(
-                &mut comprbuf,
+                &mut spool,
                 self.settings.compression_level,
                 total_uncompressed_len,
             )?;
-            self.do_encode_data(&descriptor, encoder)?;
-            header.data_checksum = crate::checksum::checksum_data(&comprbuf);
+            self.do_encode_data(
+                &descriptor,
+                encrypted_user_data.as_deref(),
+                encoder,
+                total_uncompressed_len,
+            )?;
+            self.report_progress(
+                WritePhase::Checksumming,
+                total_uncompressed_len,
+                total_uncompressed_len,
+            );
+            let compressed_len = spool.stream_position()?;
+            spool.rewind()?;
+            header.data_checksum =
+                self.hash_compressed(&mut spool, compressed_len)?;
             header.metadata_checksum =
                 crate::checksum::checksum_metadata(header, &bytes_descriptor);
+            self.report_progress(
+                WritePhase::Flushing,
+                total_uncompressed_len,
+                total_uncompressed_len,
+            );
             write.write_all(header.as_bytes())?;
             write.write_all(&bytes_descriptor)?;
-            write.write_all(&comprbuf)?;
+            spool.rewind()?;
+            std::io::copy(&mut spool, write)?;
         } else {
-            header.metadata_checksum =
-                crate::checksum::checksum_metadata(header, &bytes_descriptor);
-            write.write_all(header.as_bytes())?;
-            write.write_all(&bytes_descriptor)?;
+            let mut comprbuf = vec![];
             let encoder = new_zstd_encoder(
-                write,
+                &mut comprbuf,
                 self.settings.compression_level,
                 total_uncompressed_len,
             )?;
-            self.do_encode_data(&descriptor, encoder)?;
+            self.do_encode_data(
+                &descriptor,
+                encrypted_user_data.as_deref(),
+                encoder,
+                total_uncompressed_len,
+            )?;
+            self.report_progress(
+                WritePhase::Checksumming,
+                total_uncompressed_len,
+                total_uncompressed_len,
+            );
+            header.data_checksum =
+                self.settings.checksum_kind.checksum(&comprbuf);
+            header.metadata_checksum =
+                crate::checksum::checksum_metadata(header, &bytes_descriptor);
+            self.report_progress(
+                WritePhase::Flushing,
+                total_uncompressed_len,
+                total_uncompressed_len,
+            );
+            write.write_all(header.as_bytes())?;
+            write.write_all(&bytes_descriptor)?;
+            write.write_all(&comprbuf)?;
         }
         Ok(())
     }
 
-    fn do_encode_data<W: Write>(
-        &mut self,
+    /// Compute the data checksum by reading back `len` bytes of
+    /// compressed data.
+    fn hash_compressed(
+        &self,
+        read: &mut dyn Read,
+        len: u64,
+    ) -> Result<u64, WriteError> {
+        let kind = self.settings.checksum_kind;
+        let mut reader = ChecksummingReader::with_kind(read, kind, len);
+        let mut chunk = vec![0; HASH_CHUNK_SIZE.min(len as usize)];
+        while reader.remaining() > 0 {
+            self.check_cancelled()?;
+            let n = (reader.remaining() as usize).min(chunk.len());
+            reader.read_exact(&mut chunk[..n])?;
+        }
+        Ok(reader.finish())
+    }
+
+    /// The sequence of data that makes up the uncompressed payload.
+    ///
+    /// `encrypted_user_data` is written in place of the user data, if set.
+    pub(crate) fn payload_segments<'a>(
+        &self,
         descriptor: &IyesMeshDescriptor,
-        mut encoder: zstd::Encoder<'static, W>,
-    ) -> Result<W, WriteError> {
-        if let Some(user_data) = self.user_data {
-            encoder.write_all(user_data)?;
+        encrypted_user_data: Option<&'a [u8]>,
+    ) -> Vec<PayloadSegment<'a>>
+    where
+        's: 'a,
+    {
+        let mut r = vec![];
+        if let Some(user_data) = encrypted_user_data.or(self.user_data) {
+            r.push(PayloadSegment::Raw(user_data));
         }
         if let Some(info) = &descriptor.indices {
             for bb in self.src_meshes.iter() {
@@ -276,28 +1516,318 @@ impl<'s> IyesMeshWriter<'s> {
                     && fmt == IndexFormat::U16
                     && info.format == IndexFormat::U32
                 {
-                    self.scratch.clear();
-                    self.scratch.reserve(bytes.len() * 2);
-                    for rb in bytes.chunks_exact(2) {
-                        let nb = (u16::from_le_bytes([rb[0], rb[1]]) as u32)
-                            .to_le_bytes();
-                        self.scratch.extend_from_slice(&nb);
-                    }
-                    encoder.write_all(&self.scratch)?;
+                    r.push(PayloadSegment::UpconvertU16(bytes));
                 } else {
-                    encoder.write_all(bytes)?;
+                    r.push(PayloadSegment::Raw(bytes));
                 }
             }
         }
-        for attr in descriptor.attributes.iter() {
-            for bb in self.src_meshes.iter() {
-                let (_, bytes) = bb.attributes[attr.0];
-                encoder.write_all(bytes)?;
+        for usage in descriptor.attribute_order() {
+            let to = &descriptor.attributes[&usage];
+            for (bb, info) in self.src_meshes.iter().zip(&descriptor.meshes) {
+                let (from, bytes) = bb.attributes[&usage];
+                if usage == VertexUsage::Position
+                    && let Some(transform) = info.position_transform
+                {
+                    r.push(PayloadSegment::QuantizePositions {
+                        bytes,
+                        transform,
+                    });
+                } else if let Some(&encoding) =
+                    descriptor.attribute_encodings.get(&usage)
+                {
+                    r.push(PayloadSegment::Encode { encoding, bytes });
+                } else if from == *to {
+                    r.push(PayloadSegment::Raw(bytes));
+                } else {
+                    r.push(PayloadSegment::Convert {
+                        from,
+                        to: *to,
+                        srgb: usage == VertexUsage::Color
+                            && self.settings.srgb_colors,
+                        bytes,
+                    });
+                }
             }
         }
+        for usage in descriptor.instance_attribute_order() {
+            r.push(PayloadSegment::Raw(self.instance_data[&usage].1));
+        }
+        r
+    }
+
+    pub(crate) fn do_encode_data<W: Write>(
+        &mut self,
+        descriptor: &IyesMeshDescriptor,
+        encrypted_user_data: Option<&[u8]>,
+        mut encoder: zstd::Encoder<'static, W>,
+        total_uncompressed_len: u64,
+    ) -> Result<W, WriteError> {
+        let mut processed = 0;
+        self.report_progress(
+            WritePhase::Compressing,
+            processed,
+            total_uncompressed_len,
+        );
+        let mut scratch = std::mem::take(&mut self.scratch);
+        for segment in self.payload_segments(descriptor, encrypted_user_data) {
+            let bytes = segment.prepare(&mut scratch);
+            for chunk in bytes.chunks(ENCODE_CHUNK_SIZE) {
+                self.check_cancelled()?;
+                encoder.write_all(chunk)?;
+                processed += chunk.len() as u64;
+                self.report_progress(
+                    WritePhase::Compressing,
+                    processed,
+                    total_uncompressed_len,
+                );
+            }
+        }
+        self.scratch = scratch;
         let write = encoder.finish()?;
         Ok(write)
     }
+
+    pub(crate) fn check_cancelled(&self) -> Result<(), WriteError> {
+        if self.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+            return Err(WriteError::Cancelled);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn report_progress(
+        &mut self,
+        phase: WritePhase,
+        bytes_processed: u64,
+        bytes_total: u64,
+    ) {
+        if let Some(cb) = &mut self.progress {
+            cb(WriteProgress {
+                phase,
+                bytes_processed,
+                bytes_total,
+            });
+        }
+    }
+}
+
+/// Granularity of writes into the compressor (and progress reports).
+pub(crate) const ENCODE_CHUNK_SIZE: usize = 4 << 20;
+
+/// Size of the reads when hashing the compressed data back.
+const HASH_CHUNK_SIZE: usize = 64 << 10;
+
+#[derive(Clone)]
+pub(crate) struct PreparedFile {
+    pub(crate) descriptor: IyesMeshDescriptor,
+    pub(crate) bytes_descriptor: Vec<u8>,
+    /// Header with the checksums not filled in yet.
+    pub(crate) header: IyesMeshHeader,
+    pub(crate) total_uncompressed_len: u64,
+    /// The user data, encrypted once when preparing the file, to be written
+    /// instead of the plaintext.
+    pub(crate) encrypted_user_data: Option<Vec<u8>>,
+    pub(crate) upconverting_indices: bool,
+}
+
+/// How the user data is stored in the file.
+#[derive(Default)]
+struct StoredUserData {
+    len: u32,
+    checksum: Option<u64>,
+    nonce: Option<[u8; 12]>,
+    encrypted: Option<Vec<u8>>,
+}
+
+/// Hashes the bytes of the file as they are written, if it is signed.
+///
+/// Does nothing if no signing key is set (or without the `signing`
+/// feature).
+#[derive(Default)]
+pub(crate) struct FileSigner {
+    #[cfg(feature = "signing")]
+    state: Option<([u8; 32], crate::signing::FileHasher)>,
+}
+
+impl FileSigner {
+    #[cfg_attr(not(feature = "signing"), allow(unused_variables))]
+    pub(crate) fn new(settings: &IyesMeshWriterSettings) -> Self {
+        Self {
+            #[cfg(feature = "signing")]
+            state: settings.signing_key.map(|key| (key, Default::default())),
+        }
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        #[cfg(feature = "signing")]
+        return self.state.is_some();
+        #[cfg(not(feature = "signing"))]
+        false
+    }
+
+    #[cfg_attr(not(feature = "signing"), allow(unused_variables))]
+    pub(crate) fn update(
+        &mut self,
+        bytes: &[u8],
+    ) {
+        #[cfg(feature = "signing")]
+        if let Some((_, hasher)) = &mut self.state {
+            hasher.update(bytes);
+        }
+    }
+
+    /// The signature to append to the file, if it is signed.
+    pub(crate) fn finish(self) -> Option<[u8; crate::SIGNATURE_LEN]> {
+        #[cfg(feature = "signing")]
+        return self.state.map(|(key, hasher)| hasher.sign(&key));
+        #[cfg(not(feature = "signing"))]
+        None
+    }
+}
+
+impl Write for FileSigner {
+    fn write(
+        &mut self,
+        buf: &[u8],
+    ) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Output that also feeds everything written into a [`FileSigner`].
+struct TeeWrite<'a> {
+    inner: &'a mut dyn Write,
+    signer: &'a mut FileSigner,
+}
+
+impl Write for TeeWrite<'_> {
+    fn write(
+        &mut self,
+        buf: &[u8],
+    ) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.signer.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub(crate) enum PayloadSegment<'s> {
+    /// Bytes to be written as-is.
+    Raw(&'s [u8]),
+    /// U16 index data to be written as U32.
+    UpconvertU16(&'s [u8]),
+    /// Float32x3 positions to be quantized.
+    QuantizePositions {
+        bytes: &'s [u8],
+        transform: PositionTransform,
+    },
+    /// Vertex data to be stored with a special encoding.
+    Encode {
+        encoding: AttributeEncoding,
+        bytes: &'s [u8],
+    },
+    /// Vertex data to be converted to another format.
+    Convert {
+        from: VertexFormat,
+        to: VertexFormat,
+        srgb: bool,
+        bytes: &'s [u8],
+    },
+}
+
+impl<'s> PayloadSegment<'s> {
+    /// Get the final bytes to be encoded, using `scratch` if conversion
+    /// is needed.
+    pub(crate) fn prepare<'a>(
+        &self,
+        scratch: &'a mut Vec<u8>,
+    ) -> &'a [u8]
+    where
+        's: 'a,
+    {
+        match *self {
+            PayloadSegment::Raw(bytes) => bytes,
+            PayloadSegment::UpconvertU16(bytes) => {
+                scratch.clear();
+                scratch.reserve(bytes.len() * 2);
+                for rb in bytes.chunks_exact(2) {
+                    let nb = (u16::from_le_bytes([rb[0], rb[1]]) as u32)
+                        .to_le_bytes();
+                    scratch.extend_from_slice(&nb);
+                }
+                scratch
+            }
+            PayloadSegment::QuantizePositions { bytes, transform } => {
+                scratch.clear();
+                scratch.reserve(bytes.len() / 12 * 8);
+                for rb in bytes.chunks_exact(12) {
+                    for (i, cb) in rb.chunks_exact(4).enumerate() {
+                        let v = f32::from_le_bytes(cb.try_into().unwrap());
+                        let n = if transform.scale[i] > 0.0 {
+                            (v - transform.offset[i]) / transform.scale[i]
+                        } else {
+                            0.0
+                        };
+                        let q = (n.clamp(0.0, 1.0) * 65535.0).round() as u16;
+                        scratch.extend_from_slice(&q.to_le_bytes());
+                    }
+                    scratch.extend_from_slice(&0u16.to_le_bytes());
+                }
+                scratch
+            }
+            PayloadSegment::Encode { encoding, bytes } => {
+                scratch.clear();
+                crate::mesh::encode::encode_bytes(encoding, bytes, scratch);
+                scratch
+            }
+            PayloadSegment::Convert {
+                from,
+                to,
+                srgb,
+                bytes,
+            } => {
+                scratch.clear();
+                crate::convert::convert_color_data(
+                    from, to, srgb, bytes, scratch,
+                );
+                scratch
+            }
+        }
+    }
+}
+
+/// Format positions are stored as, if quantization is enabled.
+const QUANTIZED_POSITION_FORMAT: VertexFormat = VertexFormat::Unorm16x4;
+
+/// Compute the bounding box of Float32x3 positions, as a transform from
+/// normalized coordinates.
+fn compute_position_transform(bytes: &[u8]) -> PositionTransform {
+    let mut min = [f32::INFINITY; 3];
+    let mut max = [f32::NEG_INFINITY; 3];
+    for rb in bytes.chunks_exact(12) {
+        for (i, cb) in rb.chunks_exact(4).enumerate() {
+            let v = f32::from_le_bytes(cb.try_into().unwrap());
+            min[i] = min[i].min(v);
+            max[i] = max[i].max(v);
+        }
+    }
+    let mut r = PositionTransform::default();
+    for i in 0..3 {
+        if min[i] <= max[i] {
+            r.offset[i] = min[i];
+            r.scale[i] = max[i] - min[i];
+        }
+    }
+    r
 }
 
 struct HaveBuffers {
@@ -0,0 +1,41 @@
+#![cfg(feature = "tokio")]
+
+use iyes_mesh::async_write::AsyncIyesMeshWriter;
+use iyes_mesh::write::IyesMeshWriterSettings;
+use tokio::io::AsyncReadExt;
+
+mod common;
+use common::*;
+
+/// Write through a small `duplex` pipe, collecting the other end into a
+/// `Vec`, like a client would receive it.
+async fn write_through_pipe(
+    meshes: &[TestMesh],
+    settings: IyesMeshWriterSettings,
+) -> Vec<u8> {
+    let (mut tx, mut rx) = tokio::io::duplex(4096);
+    let mut writer: AsyncIyesMeshWriter = writer_for(meshes, settings).into();
+    let write = async move {
+        writer.write_to(&mut tx).await.unwrap();
+    };
+    let read = async move {
+        let mut received = vec![];
+        rx.read_to_end(&mut received).await.unwrap();
+        received
+    };
+    tokio::join!(write, read).1
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn async_write_matches_sync_write() {
+    let meshes = large_meshes();
+    for write_data_checksum in [true, false] {
+        let settings = IyesMeshWriterSettings {
+            write_data_checksum,
+            ..IyesMeshWriterSettings::fastest()
+        };
+        let received = write_through_pipe(&meshes, settings).await;
+        assert!(received == encode(&meshes, settings));
+        decode(&received);
+    }
+}
@@ -0,0 +1,250 @@
+#![cfg(feature = "bevy")]
+
+use std::io::Cursor;
+
+use bevy_asset::RenderAssetUsages;
+use bevy_mesh::{
+    Indices, Mesh, MeshBuilder, MeshVertexAttribute, Meshable,
+    PrimitiveTopology, VertexAttributeValues, VertexFormat as BevyVertexFormat,
+};
+use bevy_shape::Cuboid;
+use iyes_mesh::bevy::{
+    ATTRIBUTE_JOINT_INDEX_1, ATTRIBUTE_JOINT_WEIGHT_1, ATTRIBUTE_UV_2,
+    ATTRIBUTE_UV_3, ConvertError, CustomAttributeMap, from_bevy_mesh,
+    from_bevy_mesh_with, to_bevy_mesh,
+};
+use iyes_mesh::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+use iyes_mesh::mesh::MeshData;
+use iyes_mesh::read::IyesMeshReaderWithData;
+use iyes_mesh::write::IyesMeshWriter;
+
+mod common;
+use common::*;
+
+const ATTRIBUTE_BARYCENTRIC: MeshVertexAttribute = MeshVertexAttribute::new(
+    "Vertex_Barycentric",
+    0x6261_7279,
+    BevyVertexFormat::Float32x3,
+);
+
+/// A cuboid with positions, normals and UVs, plus vertex colors and a
+/// custom attribute.
+fn cube() -> Mesh {
+    let mut mesh = Cuboid::new(1.0, 2.0, 3.0).mesh().build();
+    let n = mesh.count_vertices();
+    let colors: Vec<[u8; 4]> =
+        (0..n).map(|i| [i as u8, 255 - i as u8, 0, 255]).collect();
+    let barycentric: Vec<[f32; 3]> = (0..n)
+        .map(|i| {
+            let mut b = [0.0; 3];
+            b[i % 3] = 1.0;
+            b
+        })
+        .collect();
+    mesh.insert_attribute(
+        MeshVertexAttribute {
+            format: BevyVertexFormat::Unorm8x4,
+            ..Mesh::ATTRIBUTE_COLOR
+        },
+        VertexAttributeValues::Unorm8x4(colors),
+    );
+    mesh.insert_attribute(ATTRIBUTE_BARYCENTRIC, barycentric);
+    mesh
+}
+
+fn custom() -> CustomAttributeMap {
+    CustomAttributeMap::new().with(3, ATTRIBUTE_BARYCENTRIC)
+}
+
+fn encode_mesh(mesh: &MeshData) -> IyesMeshReaderWithData {
+    let mut out = Cursor::new(vec![]);
+    IyesMeshWriter::new()
+        .with_mesh(mesh.as_mesh_ref())
+        .unwrap()
+        .write_to(&mut out)
+        .unwrap();
+    decode(out.get_ref())
+}
+
+#[test]
+fn bevy_cube_to_mesh_data() {
+    let cube = cube();
+    let data = encode_mesh(&from_bevy_mesh_with(&cube, &custom()).unwrap());
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    let mesh = &meshes.meshes[0];
+
+    let expected = [
+        (
+            VertexUsage::Position,
+            VertexFormat::Float32x3,
+            Mesh::ATTRIBUTE_POSITION,
+        ),
+        (VertexUsage::Normal, VertexFormat::Float32x3, Mesh::ATTRIBUTE_NORMAL),
+        (VertexUsage::Uv0, VertexFormat::Float32x2, Mesh::ATTRIBUTE_UV_0),
+        (VertexUsage::Color, VertexFormat::Unorm8x4, Mesh::ATTRIBUTE_COLOR),
+        (
+            VertexUsage::Custom(3),
+            VertexFormat::Float32x3,
+            ATTRIBUTE_BARYCENTRIC,
+        ),
+    ];
+    assert_eq!(mesh.attributes.len(), expected.len());
+    for (usage, format, attribute) in expected {
+        let values = cube.attribute(attribute).unwrap();
+        assert_eq!(mesh.attributes[&usage], (format, values.get_bytes()));
+    }
+    let Some(Indices::U32(indices)) = cube.indices() else {
+        panic!("cuboid indices are not U32");
+    };
+    let (format, bytes) = mesh.indices.unwrap();
+    assert_eq!(format, IndexFormat::U32);
+    assert_eq!(bytes, bytemuck::cast_slice::<u32, u8>(indices));
+}
+
+#[test]
+fn unsupported_bevy_meshes() {
+    let Err(err) = from_bevy_mesh(&cube()) else {
+        panic!("converted a mesh with an unmapped attribute");
+    };
+    assert!(
+        matches!(err, ConvertError::UnmappedAttribute("Vertex_Barycentric")),
+        "{err}"
+    );
+
+    let lines = Mesh::new(PrimitiveTopology::LineList, Default::default())
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![[0.0f32; 3]; 2],
+        );
+    let Err(err) = from_bevy_mesh(&lines) else {
+        panic!("converted a line list");
+    };
+    assert!(
+        matches!(err, ConvertError::Topology(PrimitiveTopology::LineList)),
+        "{err}"
+    );
+}
+
+#[test]
+fn bevy_cube_round_trip() {
+    let cube = cube();
+    let data = encode_mesh(&from_bevy_mesh_with(&cube, &custom()).unwrap());
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    let mesh = to_bevy_mesh(
+        &meshes.meshes[0],
+        &custom(),
+        RenderAssetUsages::default(),
+    )
+    .unwrap();
+
+    assert_eq!(mesh.primitive_topology(), PrimitiveTopology::TriangleList);
+    assert_eq!(mesh.attributes().count(), cube.attributes().count());
+    for (attribute, values) in cube.attributes() {
+        assert_eq!(mesh.attribute(attribute.id), Some(values));
+        let (a, _) =
+            mesh.attributes().find(|(a, _)| a.id == attribute.id).unwrap();
+        assert_eq!(a.format, attribute.format);
+    }
+    assert_eq!(mesh.indices(), cube.indices());
+}
+
+#[test]
+fn unmapped_custom_attributes_are_skipped() {
+    let cube = cube();
+    let data = encode_mesh(&from_bevy_mesh_with(&cube, &custom()).unwrap());
+    let buffers = data.into_flat_buffers().unwrap();
+    let meshes = data.into_split_meshes(&buffers).unwrap();
+    let mesh = to_bevy_mesh(
+        &meshes.meshes[0],
+        &CustomAttributeMap::new(),
+        RenderAssetUsages::default(),
+    )
+    .unwrap();
+    assert_eq!(mesh.attributes().count(), cube.attributes().count() - 1);
+    assert!(mesh.attribute(ATTRIBUTE_BARYCENTRIC).is_none());
+    assert_eq!(
+        mesh.attribute(Mesh::ATTRIBUTE_COLOR),
+        cube.attribute(Mesh::ATTRIBUTE_COLOR)
+    );
+}
+
+#[test]
+fn extra_uv_sets_round_trip() {
+    let mut mesh = Cuboid::new(1.0, 1.0, 1.0).mesh().build();
+    let n = mesh.count_vertices();
+    let attributes = [
+        (VertexUsage::Uv1, Mesh::ATTRIBUTE_UV_1),
+        (VertexUsage::Uv2, ATTRIBUTE_UV_2),
+        (VertexUsage::Uv3, ATTRIBUTE_UV_3),
+    ];
+    for (set, (_, attribute)) in attributes.iter().enumerate() {
+        let uvs: Vec<[f32; 2]> =
+            (0..n).map(|i| [i as f32, set as f32]).collect();
+        mesh.insert_attribute(*attribute, uvs);
+    }
+    let converted = from_bevy_mesh(&mesh).unwrap();
+    let converted = converted.as_mesh_ref();
+    for (set, (usage, _)) in attributes.iter().enumerate() {
+        let uvs: Vec<[f32; 2]> = converted.uvs_f32(*usage).unwrap().collect();
+        let expected: Vec<[f32; 2]> =
+            (0..n).map(|i| [i as f32, set as f32]).collect();
+        assert_eq!(uvs, expected, "{usage}");
+    }
+
+    let back = to_bevy_mesh(
+        &converted,
+        &CustomAttributeMap::new(),
+        RenderAssetUsages::default(),
+    )
+    .unwrap();
+    for (_, attribute) in attributes {
+        assert_eq!(back.attribute(attribute.id), mesh.attribute(attribute.id));
+    }
+}
+
+#[test]
+fn second_joint_set_round_trip() {
+    let mut mesh = Cuboid::new(1.0, 1.0, 1.0).mesh().build();
+    let n = mesh.count_vertices() as u16;
+    let joints = |first: u16| -> Vec<[u16; 4]> {
+        (0..n).map(|i| std::array::from_fn(|k| first + i + k as u16)).collect()
+    };
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_JOINT_INDEX,
+        VertexAttributeValues::Uint16x4(joints(0)),
+    );
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_JOINT_WEIGHT,
+        vec![[0.125f32; 4]; n as usize],
+    );
+    mesh.insert_attribute(
+        ATTRIBUTE_JOINT_INDEX_1,
+        VertexAttributeValues::Uint16x4(joints(100)),
+    );
+    mesh.insert_attribute(
+        ATTRIBUTE_JOINT_WEIGHT_1,
+        vec![[0.125f32; 4]; n as usize],
+    );
+
+    let converted = from_bevy_mesh(&mesh).unwrap();
+    let converted = converted.as_mesh_ref();
+    assert_eq!(
+        converted.attributes[&VertexUsage::JointIndex1].0,
+        VertexFormat::Uint16x4
+    );
+    assert_eq!(
+        converted.attributes[&VertexUsage::JointWeight1].0,
+        VertexFormat::Float32x4
+    );
+    let back = to_bevy_mesh(
+        &converted,
+        &CustomAttributeMap::new(),
+        RenderAssetUsages::default(),
+    )
+    .unwrap();
+    for attribute in [ATTRIBUTE_JOINT_INDEX_1, ATTRIBUTE_JOINT_WEIGHT_1] {
+        assert_eq!(back.attribute(attribute.id), mesh.attribute(attribute.id));
+    }
+}
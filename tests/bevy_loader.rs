@@ -0,0 +1,201 @@
+#![cfg(feature = "bevy_loader")]
+
+use std::path::Path;
+
+use bevy_app::{App, TaskPoolPlugin};
+use bevy_asset::{
+    AssetApp, AssetMode, AssetPlugin, AssetServer, Assets, Handle, LoadState,
+    UntypedAssetId,
+};
+use bevy_mesh::{Indices, Mesh};
+use iyes_mesh::bevy_loader::{
+    CollectMeshes, IyesMeshAsset, IyesMeshAssetPlugin, IyesMeshLoader,
+    IyesMeshLoaderSettings, IyesMeshSaver, MeshesToIma,
+};
+use iyes_mesh::checksum::checksum_metadata;
+use iyes_mesh::descriptor::IndexFormat;
+use iyes_mesh::header::IyesMeshHeader;
+use iyes_mesh::mesh::MeshData;
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+
+mod common;
+use common::*;
+
+const USER_DATA: &[u8] = b"level geometry";
+
+/// A headless app loading assets from `dir`.
+fn app(dir: &Path) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        TaskPoolPlugin::default(),
+        AssetPlugin {
+            file_path: dir.to_str().unwrap().to_owned(),
+            ..Default::default()
+        },
+        IyesMeshAssetPlugin::default(),
+    ));
+    app.init_asset::<Mesh>();
+    app
+}
+
+/// Update the app until the asset is loaded, or has failed to load.
+fn wait(
+    app: &mut App,
+    id: impl Into<UntypedAssetId>,
+) -> LoadState {
+    let id = id.into();
+    for _ in 0..10_000 {
+        app.update();
+        let server = app.world().resource::<AssetServer>();
+        if server.is_loaded_with_dependencies(id) {
+            return LoadState::Loaded;
+        }
+        if let state @ LoadState::Failed(_) = server.load_state(id) {
+            return state;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    panic!("asset did not load");
+}
+
+fn encode_with_user_data(meshes: &[TestMesh]) -> Vec<u8> {
+    let mut writer = writer_for(meshes, IyesMeshWriterSettings::default());
+    writer.set_user_data(USER_DATA);
+    let mut bytes = vec![];
+    writer.write_to_stream(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn load_meshes_and_user_data() {
+    let dir = tempfile::tempdir().unwrap();
+    let meshes = test_meshes();
+    let bytes = encode_with_user_data(&meshes);
+    std::fs::write(dir.path().join("test.ima"), bytes).unwrap();
+
+    let mut app = app(dir.path());
+    let server = app.world().resource::<AssetServer>().clone();
+    let file: Handle<IyesMeshAsset> = server.load("test.ima");
+    let mesh1: Handle<Mesh> = server.load("test.ima#mesh1");
+    assert!(matches!(wait(&mut app, file.id()), LoadState::Loaded));
+    assert!(matches!(wait(&mut app, mesh1.id()), LoadState::Loaded));
+
+    let files = app.world().resource::<Assets<IyesMeshAsset>>();
+    let file = files.get(&file).unwrap();
+    assert_eq!(file.user_data.as_deref(), Some(USER_DATA));
+    assert_eq!(file.meshes.len(), meshes.len());
+    assert_eq!(file.meshes[1], mesh1);
+    let assets = app.world().resource::<Assets<Mesh>>();
+    for (handle, expected) in file.meshes.iter().zip(&meshes) {
+        let mesh = assets.get(handle).unwrap();
+        let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap();
+        assert_eq!(positions.get_bytes(), expected.positions);
+        let normals = mesh.attribute(Mesh::ATTRIBUTE_NORMAL).unwrap();
+        assert_eq!(normals.get_bytes(), expected.normals);
+        let Some(Indices::U16(indices)) = mesh.indices() else {
+            panic!("indices are not U16");
+        };
+        assert_eq!(bytemuck::cast_slice::<u16, u8>(indices), expected.indices);
+    }
+}
+
+#[test]
+fn loader_settings_are_applied() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut bytes = encode_with_user_data(&test_meshes());
+    // Corrupt the data checksum, but not the metadata
+    let header_len = IyesMeshHeader::encoded_len();
+    let mut header = IyesMeshHeader::from_bytes(&bytes[..header_len]).unwrap();
+    let descriptor_end = header_len + header.descriptor_len as usize;
+    header.data_checksum ^= 1;
+    header.metadata_checksum =
+        checksum_metadata(header, &bytes[header_len..descriptor_end]);
+    bytes[..header_len].copy_from_slice(header.as_bytes());
+    // Bevy reuses the asset of a path, whatever the settings
+    std::fs::write(dir.path().join("strict.ima"), &bytes).unwrap();
+    std::fs::write(dir.path().join("lenient.ima"), &bytes).unwrap();
+
+    let mut app = app(dir.path());
+    let server = app.world().resource::<AssetServer>().clone();
+    let strict: Handle<IyesMeshAsset> = server.load("strict.ima");
+    assert!(matches!(wait(&mut app, strict.id()), LoadState::Failed(_)));
+
+    let lenient: Handle<IyesMeshAsset> = server
+        .load_builder()
+        .with_settings(|s: &mut IyesMeshLoaderSettings| {
+            s.verify_data_checksum = false;
+        })
+        .load("lenient.ima");
+    assert!(matches!(wait(&mut app, lenient.id()), LoadState::Loaded));
+}
+
+/// Meshes with U32 indices, that all fit in U16.
+fn u32_meshes(meshes: &[TestMesh]) -> Vec<MeshData> {
+    meshes
+        .iter()
+        .map(|mesh| {
+            let mesh = mesh.as_ref();
+            let indices: Vec<u32> = mesh.iter_indices().unwrap().collect();
+            let mut r = MeshData::new();
+            for (usage, (format, bytes)) in mesh.attributes.iter() {
+                r.set_attribute_bytes(*usage, *format, bytes).unwrap();
+            }
+            r.set_indices_u32(&indices);
+            r
+        })
+        .collect()
+}
+
+#[test]
+fn process_ima_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("source");
+    let processed = dir.path().join("processed");
+    std::fs::create_dir(&source).unwrap();
+    let meshes = test_meshes();
+    let u32_meshes = u32_meshes(&meshes);
+    let mut writer = IyesMeshWriter::new();
+    for mesh in u32_meshes.iter() {
+        writer.add_mesh(mesh.as_mesh_ref()).unwrap();
+    }
+    writer.set_user_data(USER_DATA);
+    let mut bytes = vec![];
+    writer.write_to_stream(&mut bytes).unwrap();
+    let indices = decode(&bytes).descriptor().indices.unwrap();
+    assert_eq!(indices.format, IndexFormat::U32);
+    std::fs::write(source.join("test.ima"), bytes).unwrap();
+
+    let mut app = App::new();
+    app.add_plugins((
+        TaskPoolPlugin::default(),
+        AssetPlugin {
+            file_path: source.to_str().unwrap().to_owned(),
+            processed_file_path: processed.to_str().unwrap().to_owned(),
+            mode: AssetMode::Processed,
+            use_asset_processor_override: Some(true),
+            ..Default::default()
+        },
+        IyesMeshAssetPlugin::default(),
+    ));
+    app.init_asset::<Mesh>();
+    app.register_asset_processor(MeshesToIma::<IyesMeshLoader>::new(
+        CollectMeshes::default(),
+        IyesMeshSaver::default(),
+    ));
+    app.set_default_asset_processor::<MeshesToIma<IyesMeshLoader>>("ima");
+    let server = app.world().resource::<AssetServer>().clone();
+    let handle: Handle<IyesMeshAsset> = server.load("test.ima");
+    assert!(matches!(wait(&mut app, handle.id()), LoadState::Loaded));
+
+    let data = decode(&std::fs::read(processed.join("test.ima")).unwrap());
+    assert_eq!(data.decode_user_data().unwrap().as_deref(), Some(USER_DATA));
+    let buffers = data.into_flat_buffers().unwrap();
+    let decoded = data.into_split_meshes(&buffers).unwrap();
+    assert_eq!(decoded.meshes.len(), meshes.len());
+    for (mesh, expected) in decoded.meshes.iter().zip(&meshes) {
+        let expected = expected.as_ref();
+        // The saver stores the indices as U16 again
+        assert_eq!(mesh.indices, expected.indices);
+        assert_eq!(mesh.attributes, expected.attributes);
+    }
+}
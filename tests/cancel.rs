@@ -0,0 +1,87 @@
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use iyes_mesh::cancel::CancelToken;
+use iyes_mesh::read::{IyesMeshReader, ReadError, ReadPhase};
+use iyes_mesh::write::{IyesMeshWriterSettings, WriteError, WritePhase};
+
+mod common;
+use common::*;
+
+#[test]
+fn cancel_write_mid_way() {
+    let meshes = large_meshes();
+    let mut writer = writer_for(&meshes, IyesMeshWriterSettings::fastest());
+    let token = CancelToken::new();
+    writer.set_cancel_token(token.clone());
+    // Number of chunks compressed after cancelling
+    let late = Arc::new(Mutex::new(None));
+    let late_cb = late.clone();
+    writer.set_progress_callback(Box::new(move |p| {
+        if p.phase != WritePhase::Compressing || p.bytes_processed == 0 {
+            return;
+        }
+        let mut late = late_cb.lock().unwrap();
+        match &mut *late {
+            None => {
+                token.cancel();
+                *late = Some(0);
+            }
+            Some(n) => *n += 1,
+        }
+    }));
+    let err = writer.write_to(&mut Cursor::new(vec![])).unwrap_err();
+    assert!(matches!(err, WriteError::Cancelled), "{err}");
+    assert_eq!(*late.lock().unwrap(), Some(0));
+}
+
+#[test]
+fn cancel_read_mid_way() {
+    let bytes = encode(&large_meshes(), IyesMeshWriterSettings::fastest());
+    let mut read = Cursor::new(&bytes);
+    let mut reader = IyesMeshReader::init(&mut read).unwrap();
+    let token = CancelToken::new();
+    reader.set_cancel_token(token.clone());
+    let late = Arc::new(Mutex::new(None));
+    let late_cb = late.clone();
+    reader.set_progress_callback(Box::new(move |p| {
+        if p.phase != ReadPhase::Decompressing || p.decompressed_bytes == 0 {
+            return;
+        }
+        let mut late = late_cb.lock().unwrap();
+        match &mut *late {
+            None => {
+                token.cancel();
+                *late = Some(0);
+            }
+            Some(n) => *n += 1,
+        }
+    }));
+    assert!(matches!(reader.read_all_data(), Err(ReadError::Cancelled)));
+    assert_eq!(*late.lock().unwrap(), Some(0));
+}
+
+#[test]
+fn cancel_before_start() {
+    let meshes = test_meshes();
+    let token = CancelToken::new();
+    token.cancel();
+
+    let mut writer = writer_for(&meshes, Default::default());
+    writer.set_cancel_token(token.clone());
+    assert!(matches!(
+        writer.write_to(&mut Cursor::new(vec![])),
+        Err(WriteError::Cancelled)
+    ));
+    writer.clear_cancel_token();
+    let bytes = {
+        let mut out = Cursor::new(vec![]);
+        writer.write_to(&mut out).unwrap();
+        out.into_inner()
+    };
+
+    let mut read = Cursor::new(&bytes);
+    let mut reader = IyesMeshReader::init(&mut read).unwrap();
+    reader.set_cancel_token(token);
+    assert!(matches!(reader.read_all_data(), Err(ReadError::Cancelled)));
+}
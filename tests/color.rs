@@ -0,0 +1,169 @@
+use std::io::Cursor;
+
+use iyes_mesh::descriptor::{ColorSpace, VertexFormat, VertexUsage};
+use iyes_mesh::header::IyesMeshHeader;
+use iyes_mesh::mesh::{MeshData, MeshError, convert_color_space};
+use iyes_mesh::read::{IyesMeshReader, ReadError};
+use iyes_mesh::write::IyesMeshWriter;
+
+mod common;
+use common::*;
+
+/// sRGB encoding of linear 0.5 and 0.25.
+const SRGB_HALF: f32 = 0.735_356_9;
+const SRGB_QUARTER: f32 = 0.537_098_7;
+
+fn colored(
+    format: VertexFormat,
+    colors: &[u8],
+) -> MeshData {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&CUBE_POSITIONS[..colors.len() / format.size()])
+        .unwrap()
+        .set_attribute_bytes(VertexUsage::Color, format, colors)
+        .unwrap();
+    mesh
+}
+
+fn float_colors(mesh: &MeshData) -> Vec<[f32; 4]> {
+    let mesh = mesh.as_mesh_ref();
+    mesh.attribute_f32::<4>(VertexUsage::Color).unwrap().collect()
+}
+
+fn assert_close(
+    a: &[[f32; 4]],
+    b: &[[f32; 4]],
+) {
+    assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter().zip(b) {
+        assert!((0..4).all(|i| (a[i] - b[i]).abs() < 1e-6), "{a:?} != {b:?}");
+    }
+}
+
+#[test]
+fn convert_float_colors() {
+    let linear = [[0.5, 0.25, 0.0, 0.5], [1.0, 1.0, 1.0, 0.25]];
+    let mesh = colored(VertexFormat::Float32x4, bytemuck::cast_slice(&linear));
+    let srgb = convert_color_space(
+        &mesh.as_mesh_ref(),
+        ColorSpace::Linear,
+        ColorSpace::Srgb,
+    )
+    .unwrap();
+    // Alpha is kept as-is
+    assert_close(
+        &float_colors(&srgb),
+        &[[SRGB_HALF, SRGB_QUARTER, 0.0, 0.5], [1.0, 1.0, 1.0, 0.25]],
+    );
+    let back = convert_color_space(
+        &srgb.as_mesh_ref(),
+        ColorSpace::Srgb,
+        ColorSpace::Linear,
+    )
+    .unwrap();
+    assert_close(&float_colors(&back), &linear);
+
+    let same = convert_color_space(
+        &mesh.as_mesh_ref(),
+        ColorSpace::Srgb,
+        ColorSpace::Srgb,
+    )
+    .unwrap();
+    assert_eq!(same.as_mesh_ref().attributes, mesh.as_mesh_ref().attributes);
+}
+
+#[test]
+fn convert_unorm_colors() {
+    let mesh =
+        colored(VertexFormat::Unorm8x4, &[128, 64, 0, 128, 255, 255, 255, 7]);
+    let srgb = convert_color_space(
+        &mesh.as_mesh_ref(),
+        ColorSpace::Linear,
+        ColorSpace::Srgb,
+    )
+    .unwrap();
+    let (format, bytes) = srgb.as_mesh_ref().attributes[&VertexUsage::Color];
+    assert_eq!(format, VertexFormat::Unorm8x4);
+    assert_eq!(bytes, [188, 137, 0, 128, 255, 255, 255, 7]);
+    let back = convert_color_space(
+        &srgb.as_mesh_ref(),
+        ColorSpace::Srgb,
+        ColorSpace::Linear,
+    )
+    .unwrap();
+    let (_, bytes) = back.as_mesh_ref().attributes[&VertexUsage::Color];
+    assert_eq!(bytes, [128, 64, 0, 128, 255, 255, 255, 7]);
+}
+
+#[test]
+fn convert_color_space_errors() {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(CUBE_POSITIONS).unwrap();
+    let r = convert_color_space(
+        &mesh.as_mesh_ref(),
+        ColorSpace::Linear,
+        ColorSpace::Srgb,
+    );
+    assert!(matches!(r, Err(MeshError::MissingAttribute(VertexUsage::Color))));
+    let mesh = colored(VertexFormat::Unorm16x4, &[0; 16]);
+    let r = convert_color_space(
+        &mesh.as_mesh_ref(),
+        ColorSpace::Linear,
+        ColorSpace::Srgb,
+    );
+    assert!(matches!(
+        r,
+        Err(MeshError::UnsupportedFormat {
+            usage: VertexUsage::Color,
+            format: VertexFormat::Unorm16x4,
+        })
+    ));
+}
+
+fn encode_tagged(color_space: Option<ColorSpace>) -> Vec<u8> {
+    let mesh = colored(VertexFormat::Unorm8x4, &[255; 12]);
+    let mut writer = IyesMeshWriter::new();
+    writer.add_mesh(mesh.as_mesh_ref()).unwrap();
+    if let Some(color_space) = color_space {
+        writer.set_color_space(VertexUsage::Color, color_space);
+    }
+    // Tags of other attributes are not written
+    writer.set_color_space(VertexUsage::Uv0, ColorSpace::Srgb);
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+    out.into_inner()
+}
+
+#[test]
+fn color_space_tags_are_stored() {
+    for color_space in [ColorSpace::Linear, ColorSpace::Srgb] {
+        let data = decode(&encode_tagged(Some(color_space)));
+        let tags = &data.descriptor().color_spaces;
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags.get(&VertexUsage::Color), Some(&color_space));
+    }
+    let data = decode(&encode_tagged(None));
+    assert!(data.descriptor().color_spaces.is_empty());
+
+    let mut writer = IyesMeshWriter::new();
+    writer.set_color_space(VertexUsage::Color, ColorSpace::Srgb);
+    writer.clear_color_space(VertexUsage::Color);
+    assert!(writer.color_spaces().is_empty());
+}
+
+#[test]
+fn color_space_tags_are_checksummed() {
+    let linear = encode_tagged(Some(ColorSpace::Linear));
+    let srgb = encode_tagged(Some(ColorSpace::Srgb));
+    assert_eq!(linear.len(), srgb.len());
+    // The header of one file with the descriptor of the other
+    let header_len = IyesMeshHeader::encoded_len();
+    let mut spliced = linear[..header_len].to_vec();
+    spliced.extend_from_slice(&srgb[header_len..]);
+    assert_ne!(spliced, srgb);
+    let mut read = Cursor::new(&spliced);
+    assert!(matches!(
+        IyesMeshReader::init(&mut read),
+        Err(ReadError::InvalidChecksums)
+    ));
+}
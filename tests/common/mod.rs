@@ -0,0 +1,166 @@
+#![allow(dead_code)]
+
+use std::io::Cursor;
+
+use iyes_mesh::HashMap;
+use iyes_mesh::checksum::checksum_metadata;
+use iyes_mesh::descriptor::{
+    IndexFormat, IyesMeshDescriptor, VertexFormat, VertexUsage,
+};
+use iyes_mesh::header::IyesMeshHeader;
+use iyes_mesh::mesh::MeshDataRef;
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderWithData};
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+
+/// Owned data of a triangle mesh, to build [`MeshDataRef`]s from.
+pub struct TestMesh {
+    pub positions: Vec<u8>,
+    pub normals: Vec<u8>,
+    pub indices: Vec<u8>,
+}
+
+impl TestMesh {
+    /// A grid of `size` x `size` vertices, with noisy heights, so that it
+    /// does not compress down to nothing.
+    pub fn grid(
+        size: u16,
+        seed: u64,
+    ) -> Self {
+        let mut rng = seed;
+        let mut positions = vec![];
+        let mut normals = vec![];
+        for y in 0..size {
+            for x in 0..size {
+                rng = rng
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let height = (rng >> 40) as f32 / (1 << 24) as f32;
+                for c in [x as f32, height, y as f32] {
+                    positions.extend_from_slice(&c.to_le_bytes());
+                }
+                for c in [0.0f32, 1.0, 0.0] {
+                    normals.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+        }
+        let mut indices = vec![];
+        for y in 0..(size - 1) {
+            for x in 0..(size - 1) {
+                let i = y * size + x;
+                for index in [i, i + size, i + 1, i + 1, i + size, i + size + 1]
+                {
+                    indices.extend_from_slice(&index.to_le_bytes());
+                }
+            }
+        }
+        Self {
+            positions,
+            normals,
+            indices,
+        }
+    }
+
+    pub fn as_ref(&self) -> MeshDataRef<'_> {
+        let mut attributes = HashMap::default();
+        attributes.insert(
+            VertexUsage::Position,
+            (VertexFormat::Float32x3, self.positions.as_slice()),
+        );
+        attributes.insert(
+            VertexUsage::Normal,
+            (VertexFormat::Float32x3, self.normals.as_slice()),
+        );
+        MeshDataRef {
+            indices: Some((IndexFormat::U16, self.indices.as_slice())),
+            attributes,
+        }
+    }
+}
+
+/// A few meshes of different sizes.
+pub fn test_meshes() -> Vec<TestMesh> {
+    vec![
+        TestMesh::grid(16, 1),
+        TestMesh::grid(40, 2),
+        TestMesh::grid(3, 3),
+    ]
+}
+
+pub fn writer_for<'s>(
+    meshes: &'s [TestMesh],
+    settings: IyesMeshWriterSettings,
+) -> IyesMeshWriter<'s> {
+    let mut writer = IyesMeshWriter::new_with_settings(settings);
+    for mesh in meshes {
+        writer.add_mesh(mesh.as_ref()).unwrap();
+    }
+    writer
+}
+
+/// Encode the meshes with `write_to`, into memory.
+pub fn encode(
+    meshes: &[TestMesh],
+    settings: IyesMeshWriterSettings,
+) -> Vec<u8> {
+    let mut out = Cursor::new(vec![]);
+    writer_for(meshes, settings).write_to(&mut out).unwrap();
+    out.into_inner()
+}
+
+/// Replace the descriptor of a file, keeping its data and checksums valid.
+pub fn with_descriptor(
+    bytes: &[u8],
+    edit: impl FnOnce(&mut IyesMeshDescriptor),
+) -> Vec<u8> {
+    let header_len = IyesMeshHeader::encoded_len();
+    let mut header = IyesMeshHeader::from_bytes(&bytes[..header_len]).unwrap();
+    let data_start = header_len + header.descriptor_len as usize;
+    let mut descriptor =
+        IyesMeshDescriptor::from_bytes(&bytes[header_len..data_start]).unwrap();
+    edit(&mut descriptor);
+    let encoded = bitcode::encode(&descriptor);
+    header.descriptor_len = encoded.len() as u16;
+    header.metadata_checksum = checksum_metadata(header, &encoded);
+    let mut r = header.as_bytes().to_vec();
+    r.extend_from_slice(&encoded);
+    r.extend_from_slice(&bytes[data_start..]);
+    r
+}
+
+/// Read a whole file, verifying its checksums.
+pub fn decode(bytes: &[u8]) -> IyesMeshReaderWithData {
+    let mut read = Cursor::new(bytes);
+    IyesMeshReader::init(&mut read).unwrap().read_all_data().unwrap()
+}
+
+/// Meshes with several megabytes of data, enough for the reader and the
+/// writer to process them in multiple chunks.
+pub fn large_meshes() -> Vec<TestMesh> {
+    (0..4).map(|seed| TestMesh::grid(255, seed)).collect()
+}
+
+/// The cube of `examples/simple_encode.rs`.
+pub static CUBE_POSITIONS: &[[f32; 3]] = &[
+    [-1.0, -1.0, 1.0],
+    [1.0, -1.0, 1.0],
+    [1.0, 1.0, 1.0],
+    [-1.0, 1.0, 1.0],
+    [-1.0, -1.0, -1.0],
+    [1.0, -1.0, -1.0],
+    [1.0, 1.0, -1.0],
+    [-1.0, 1.0, -1.0],
+];
+
+pub static CUBE_INDICES: &[u16] = &[
+    0, 1, 2, 2, 3, 0, // Front
+    4, 5, 6, 6, 7, 4, // Back
+    4, 0, 3, 3, 7, 4, // Left
+    1, 5, 6, 6, 2, 1, // Right
+    3, 2, 6, 6, 7, 3, // Top
+    4, 5, 1, 1, 0, 4, // Bottom
+];
+
+/// The vertices of the triangles of the cube, without sharing any.
+pub fn cube_soup() -> Vec<[f32; 3]> {
+    CUBE_INDICES.iter().map(|i| CUBE_POSITIONS[*i as usize]).collect()
+}
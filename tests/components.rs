@@ -0,0 +1,76 @@
+use iyes_mesh::descriptor::IndexFormat;
+use iyes_mesh::mesh::{MeshData, split_connected_components, weld};
+
+mod common;
+use common::*;
+
+/// The cube, moved by `offset` along X.
+fn cube_at(offset: f32) -> Vec<[f32; 3]> {
+    CUBE_POSITIONS.iter().map(|p| [p[0] + offset, p[1], p[2]]).collect()
+}
+
+fn positions(mesh: &MeshData) -> Vec<[f32; 3]> {
+    mesh.as_mesh_ref().positions_f32().unwrap().collect()
+}
+
+#[test]
+fn split_two_cubes() {
+    // The second cube first, then an unused vertex, then the first cube
+    let mut positions_in = cube_at(5.0);
+    positions_in.push([100.0; 3]);
+    positions_in.extend(cube_at(0.0));
+    let mut indices: Vec<u16> = CUBE_INDICES.iter().map(|i| i + 9).collect();
+    indices.extend(CUBE_INDICES);
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&positions_in).unwrap().set_indices_u16(&indices);
+
+    let components = split_connected_components(&mesh.as_mesh_ref()).unwrap();
+    assert_eq!(components.len(), 2);
+    // Ordered by first triangle, keeping the order of the vertices
+    let expected = [cube_at(0.0), cube_at(5.0)];
+    for (component, expected) in components.iter().zip(&expected) {
+        let mesh = component.as_mesh_ref();
+        assert_eq!(mesh.indices.unwrap().0, IndexFormat::U16);
+        let corners: Vec<[f32; 3]> = mesh
+            .iter_indices()
+            .unwrap()
+            .map(|i| positions(component)[i as usize])
+            .collect();
+        let expected_corners: Vec<[f32; 3]> =
+            CUBE_INDICES.iter().map(|i| expected[*i as usize]).collect();
+        assert_eq!(corners, expected_corners);
+        assert_eq!(mesh.n_vertices(), 8);
+    }
+}
+
+#[test]
+fn split_non_indexed_cubes() {
+    let soup = |offset: f32| -> Vec<[f32; 3]> {
+        cube_soup().iter().map(|p| [p[0] + offset, p[1], p[2]]).collect()
+    };
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&[soup(0.0), soup(5.0)].concat()).unwrap();
+    // Triangles sharing positions are connected, though not indexed
+    let components = split_connected_components(&mesh.as_mesh_ref()).unwrap();
+    assert_eq!(components.len(), 2);
+    for (component, offset) in components.iter().zip([0.0, 5.0]) {
+        assert!(component.as_mesh_ref().indices.is_none());
+        assert_eq!(positions(component), soup(offset));
+    }
+}
+
+#[test]
+fn seams_do_not_split() {
+    // One vertex per face corner, as for a cube with flat normals
+    let soup = cube_soup();
+    let normals: Vec<[f32; 3]> =
+        (0..36).map(|v| [(v / 6) as f32, 0.0, 0.0]).collect();
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&soup).unwrap().set_normals(&normals).unwrap();
+    let indexed = weld(&mesh.as_mesh_ref(), None).unwrap();
+    assert_eq!(indexed.n_vertices(), 24);
+    let components =
+        split_connected_components(&indexed.as_mesh_ref()).unwrap();
+    assert_eq!(components.len(), 1);
+    assert_eq!(components[0].n_vertices(), 24);
+}
@@ -0,0 +1,131 @@
+use iyes_mesh::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+use iyes_mesh::mesh::{MeshData, MeshError, concatenate};
+
+mod common;
+use common::*;
+
+fn cube() -> MeshData {
+    let mut cube = MeshData::new();
+    cube.set_positions(CUBE_POSITIONS).unwrap().set_indices_u16(CUBE_INDICES);
+    cube
+}
+
+#[test]
+fn concatenate_meshes() {
+    let grid = TestMesh::grid(5, 1);
+    let mut cube = cube();
+    cube.set_normals(&[[0.0, 1.0, 0.0]; 8]).unwrap();
+    let meshes = [cube.as_mesh_ref(), grid.as_ref(), cube.as_mesh_ref()];
+    let combined = concatenate(&meshes).unwrap();
+    let combined = combined.as_mesh_ref();
+
+    assert_eq!(combined.n_vertices(), 8 + 25 + 8);
+    let triangles: Vec<[u32; 3]> = combined.iter_triangles().unwrap().collect();
+    assert_eq!(triangles.len(), 12 + 32 + 12);
+    // Each mesh is rebased onto its first vertex
+    assert_eq!(triangles[0], [0, 1, 2]);
+    let first_grid = grid.as_ref().iter_triangles().unwrap().next().unwrap();
+    assert_eq!(triangles[12], first_grid.map(|i| i + 8));
+    assert_eq!(triangles[44], [33, 34, 35]);
+    assert_eq!(triangles[55], [34, 33, 37]);
+    assert_eq!(combined.indices.unwrap().0, IndexFormat::U16);
+    let (_, positions) = combined.attributes[&VertexUsage::Position];
+    assert_eq!(
+        positions,
+        [
+            cube.as_mesh_ref().attributes[&VertexUsage::Position].1,
+            &grid.positions,
+            cube.as_mesh_ref().attributes[&VertexUsage::Position].1,
+        ]
+        .concat()
+    );
+}
+
+#[test]
+fn concatenate_to_u32_indices() {
+    // Two meshes of 40000 vertices, using their first and last vertices
+    let positions: Vec<[f32; 3]> =
+        (0..40000).map(|v| [v as f32, 0.0, 0.0]).collect();
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&positions)
+        .unwrap()
+        .set_indices_u16(&[0, 1, 2, 39997, 39998, 39999]);
+    let combined =
+        concatenate(&[mesh.as_mesh_ref(), mesh.as_mesh_ref()]).unwrap();
+    let combined = combined.as_mesh_ref();
+    assert_eq!(combined.indices.unwrap().0, IndexFormat::U32);
+    let indices: Vec<u32> = combined.iter_indices().unwrap().collect();
+    assert_eq!(
+        indices,
+        [
+            0, 1, 2, 39997, 39998, 39999, 40000, 40001, 40002, 79997, 79998,
+            79999
+        ]
+    );
+}
+
+#[test]
+fn concatenate_with_non_indexed_meshes() {
+    let mut soup = MeshData::new();
+    soup.set_positions(&cube_soup()).unwrap();
+    let only_soups =
+        concatenate(&[soup.as_mesh_ref(), soup.as_mesh_ref()]).unwrap();
+    assert!(only_soups.as_mesh_ref().indices.is_none());
+    assert_eq!(only_soups.n_vertices(), 72);
+
+    let cube = cube();
+    let mixed = concatenate(&[soup.as_mesh_ref(), cube.as_mesh_ref()]).unwrap();
+    let indices: Vec<u32> =
+        mixed.as_mesh_ref().iter_indices().unwrap().collect();
+    let expected: Vec<u32> =
+        (0..36).chain(CUBE_INDICES.iter().map(|i| *i as u32 + 36)).collect();
+    assert_eq!(indices, expected);
+}
+
+#[test]
+fn concatenate_checks_attributes() {
+    let cube = cube();
+    let mut with_normals = self::cube();
+    with_normals.set_normals(&[[0.0, 1.0, 0.0]; 8]).unwrap();
+    let r = concatenate(&[
+        cube.as_mesh_ref(),
+        cube.as_mesh_ref(),
+        with_normals.as_mesh_ref(),
+    ]);
+    assert!(matches!(
+        r,
+        Err(MeshError::AttributesDiffer {
+            index: 2,
+            usage: VertexUsage::Normal,
+            expected: None,
+            found: Some(VertexFormat::Float32x3),
+        })
+    ));
+
+    let mut converted = self::cube();
+    let positions: Vec<u8> = CUBE_POSITIONS
+        .iter()
+        .flatten()
+        .flat_map(|c| (*c as f64).to_le_bytes())
+        .collect();
+    converted
+        .set_attribute_bytes(
+            VertexUsage::Position,
+            VertexFormat::Float64x3,
+            &positions,
+        )
+        .unwrap();
+    let r = concatenate(&[cube.as_mesh_ref(), converted.as_mesh_ref()]);
+    assert!(matches!(
+        r,
+        Err(MeshError::AttributesDiffer {
+            index: 1,
+            usage: VertexUsage::Position,
+            expected: Some(VertexFormat::Float32x3),
+            found: Some(VertexFormat::Float64x3),
+        })
+    ));
+
+    let empty = concatenate(&[]).unwrap();
+    assert_eq!(empty.n_vertices(), 0);
+}
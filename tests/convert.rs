@@ -0,0 +1,261 @@
+use std::io::Cursor;
+
+use iyes_mesh::HashMap;
+use iyes_mesh::convert::{convert_color_data, count_clamped};
+use iyes_mesh::descriptor::{VertexFormat, VertexUsage};
+use iyes_mesh::mesh::MeshDataRef;
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings, WriteError};
+
+mod common;
+use common::*;
+
+fn bytes<T: Copy, const N: usize>(
+    values: &[T],
+    to_le: fn(T) -> [u8; N],
+) -> Vec<u8> {
+    values.iter().flat_map(|v| to_le(*v)).collect()
+}
+
+/// Write a mesh with an attribute converted to another format, and read
+/// back what was stored.
+fn write_converted(
+    usage: VertexUsage,
+    from: VertexFormat,
+    data: &[u8],
+    to: VertexFormat,
+) -> Result<(VertexFormat, Vec<u8>), WriteError> {
+    let positions = vec![0; data.len() / from.size() * 12];
+    let mut attributes = HashMap::default();
+    attributes.insert(
+        VertexUsage::Position,
+        (VertexFormat::Float32x3, positions.as_slice()),
+    );
+    attributes.insert(usage, (from, data));
+    let mut writer = IyesMeshWriter::new();
+    writer
+        .add_mesh(MeshDataRef {
+            indices: None,
+            attributes,
+        })
+        .unwrap();
+    writer.set_attribute_conversion(usage, to);
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out)?;
+
+    let with_data = decode(out.get_ref());
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let meshes = with_data.into_split_meshes(&buffers).unwrap();
+    let (format, data) = meshes.meshes[0].attributes[&usage];
+    Ok((format, data.to_vec()))
+}
+
+#[test]
+fn float_to_normalized() {
+    let uvs = bytes(&[0.0f32, 1.0, 0.5, -0.5, 2.0, 0.25], f32::to_le_bytes);
+    let (format, data) = write_converted(
+        VertexUsage::Uv0,
+        VertexFormat::Float32x2,
+        &uvs,
+        VertexFormat::Unorm16x2,
+    )
+    .unwrap();
+    assert_eq!(format, VertexFormat::Unorm16x2);
+    let expected = [0u16, 65535, 32768, 0, 65535, 16384];
+    assert_eq!(data, bytes(&expected, u16::to_le_bytes));
+    assert_eq!(
+        count_clamped(VertexFormat::Float32x2, VertexFormat::Unorm16x2, &uvs),
+        2
+    );
+
+    let values = bytes(&[-1.0f32, 1.0, 0.5, -2.0, 3.0, 0.0], f32::to_le_bytes);
+    let (_, data) = write_converted(
+        VertexUsage::Custom(0),
+        VertexFormat::Float32x2,
+        &values,
+        VertexFormat::Snorm8x2,
+    )
+    .unwrap();
+    let expected = [-127i8, 127, 64, -127, 127, 0];
+    assert_eq!(data, bytes(&expected, i8::to_le_bytes));
+
+    let colors = bytes(
+        &[0.0f32, 1.0, 0.5, f32::NAN, -1.0, 7.0, 0.2, 1.0],
+        f32::to_le_bytes,
+    );
+    let (_, data) = write_converted(
+        VertexUsage::Color,
+        VertexFormat::Float32x4,
+        &colors,
+        VertexFormat::Unorm8x4,
+    )
+    .unwrap();
+    assert_eq!(data, [0, 255, 128, 0, 0, 255, 51, 255]);
+}
+
+#[test]
+fn normalized_to_float() {
+    let (format, data) = write_converted(
+        VertexUsage::Color,
+        VertexFormat::Unorm8x4,
+        &[0, 255, 51, 128],
+        VertexFormat::Float32x4,
+    )
+    .unwrap();
+    assert_eq!(format, VertexFormat::Float32x4);
+    let expected = [0.0f32, 1.0, 0.2, (128.0f64 / 255.0) as f32];
+    assert_eq!(data, bytes(&expected, f32::to_le_bytes));
+
+    let values = [0.1f32, -3.5, 1e30, 0.0];
+    let (_, data) = write_converted(
+        VertexUsage::Uv0,
+        VertexFormat::Float32x2,
+        &bytes(&values, f32::to_le_bytes),
+        VertexFormat::Float64x2,
+    )
+    .unwrap();
+    let expected = values.map(f64::from);
+    assert_eq!(data, bytes(&expected, f64::to_le_bytes));
+}
+
+#[test]
+fn integer_clamping() {
+    let (_, data) = write_converted(
+        VertexUsage::Custom(1),
+        VertexFormat::Uint32x2,
+        &bytes(&[0u32, 300, 255, 7], u32::to_le_bytes),
+        VertexFormat::Uint8x2,
+    )
+    .unwrap();
+    assert_eq!(data, [0, 255, 255, 7]);
+
+    let (_, data) = write_converted(
+        VertexUsage::Custom(1),
+        VertexFormat::Sint32x2,
+        &bytes(&[-5i32, 70000, 12, 65535], i32::to_le_bytes),
+        VertexFormat::Uint16x2,
+    )
+    .unwrap();
+    let expected = [0u16, 65535, 12, 65535];
+    assert_eq!(data, bytes(&expected, u16::to_le_bytes));
+}
+
+#[test]
+fn unsupported_conversions() {
+    let uvs = bytes(&[0.0f32; 6], f32::to_le_bytes);
+    let pairs = [
+        // Different number of components
+        (VertexFormat::Float32x2, VertexFormat::Float32x4),
+        // Float to integer
+        (VertexFormat::Float32x2, VertexFormat::Uint16x2),
+    ];
+    for (from, to) in pairs {
+        let err =
+            write_converted(VertexUsage::Uv0, from, &uvs, to).unwrap_err();
+        assert!(matches!(
+            err,
+            WriteError::UnsupportedConversion {
+                usage: VertexUsage::Uv0,
+                ..
+            }
+        ));
+    }
+}
+
+fn convert_colors(
+    from: VertexFormat,
+    to: VertexFormat,
+    srgb: bool,
+    data: &[u8],
+) -> Vec<u8> {
+    let mut out = vec![];
+    assert!(convert_color_data(from, to, srgb, data, &mut out));
+    out
+}
+
+#[test]
+fn color_round_trip() {
+    let all_values: Vec<u8> = (0..=255).collect();
+    for srgb in [false, true] {
+        let floats = convert_colors(
+            VertexFormat::Unorm8x4,
+            VertexFormat::Float32x4,
+            srgb,
+            &all_values,
+        );
+        let back = convert_colors(
+            VertexFormat::Float32x4,
+            VertexFormat::Unorm8x4,
+            srgb,
+            &floats,
+        );
+        assert_eq!(back, all_values, "srgb: {srgb}");
+
+        let wide: Vec<u8> =
+            (0..=255u16).flat_map(|v| (v * 257).to_le_bytes()).collect();
+        let floats = convert_colors(
+            VertexFormat::Unorm16x4,
+            VertexFormat::Float32x4,
+            srgb,
+            &wide,
+        );
+        let back = convert_colors(
+            VertexFormat::Float32x4,
+            VertexFormat::Unorm16x4,
+            srgb,
+            &floats,
+        );
+        assert_eq!(back, wide, "srgb: {srgb}");
+    }
+}
+
+#[test]
+fn color_srgb_and_clamping() {
+    let colors =
+        bytes(&[0.5f32, 0.5, 0.5, 0.5, -0.5, 1.5, 0.0, 2.0], f32::to_le_bytes);
+    let linear = convert_colors(
+        VertexFormat::Float32x4,
+        VertexFormat::Unorm8x4,
+        false,
+        &colors,
+    );
+    assert_eq!(linear, [128, 128, 128, 128, 0, 255, 0, 255]);
+    // Alpha is not affected by the transfer function
+    let srgb = convert_colors(
+        VertexFormat::Float32x4,
+        VertexFormat::Unorm8x4,
+        true,
+        &colors,
+    );
+    assert_eq!(srgb, [188, 188, 188, 128, 0, 255, 0, 255]);
+
+    // The writer only applies it to the Color attribute
+    let mut writer =
+        IyesMeshWriter::new_with_settings(IyesMeshWriterSettings {
+            srgb_colors: true,
+            ..Default::default()
+        });
+    let positions = vec![0; 24];
+    let mut attributes = HashMap::default();
+    attributes.insert(
+        VertexUsage::Position,
+        (VertexFormat::Float32x3, positions.as_slice()),
+    );
+    for usage in [VertexUsage::Color, VertexUsage::Custom(0)] {
+        attributes.insert(usage, (VertexFormat::Float32x4, colors.as_slice()));
+        writer.set_attribute_conversion(usage, VertexFormat::Unorm8x4);
+    }
+    writer
+        .add_mesh(MeshDataRef {
+            indices: None,
+            attributes,
+        })
+        .unwrap();
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+    let with_data = decode(out.get_ref());
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let meshes = with_data.into_split_meshes(&buffers).unwrap();
+    let attributes = &meshes.meshes[0].attributes;
+    assert_eq!(attributes[&VertexUsage::Color].1, srgb.as_slice());
+    assert_eq!(attributes[&VertexUsage::Custom(0)].1, linear.as_slice());
+}
@@ -0,0 +1,144 @@
+use std::io::{BufRead, BufReader, Cursor, Read};
+
+use iyes_mesh::header::IyesMeshHeader;
+use iyes_mesh::read::{
+    ReadError, detect_format, is_iyes_mesh_file, is_iyes_mesh_magic, probe,
+    probe_file,
+};
+use iyes_mesh::write::IyesMeshWriterSettings;
+
+mod common;
+use common::*;
+
+/// A reader that cannot seek, and gives at most `chunk` bytes per read,
+/// like a pipe.
+struct Pipe<'a> {
+    bytes: &'a [u8],
+    chunk: usize,
+}
+
+impl Read for Pipe<'_> {
+    fn read(
+        &mut self,
+        buf: &mut [u8],
+    ) -> std::io::Result<usize> {
+        let n = buf.len().min(self.chunk).min(self.bytes.len());
+        buf[..n].copy_from_slice(&self.bytes[..n]);
+        self.bytes = &self.bytes[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn detect_format_without_seeking() {
+    let bytes = encode(&test_meshes(), Default::default());
+    assert!(is_iyes_mesh_magic(bytes[..4].try_into().unwrap()));
+    assert!(!is_iyes_mesh_magic(b"glTF"));
+
+    let mut read = BufReader::new(Pipe {
+        bytes: &bytes,
+        chunk: 100,
+    });
+    assert!(detect_format(&mut read).unwrap());
+    // Nothing was consumed
+    let mut all = vec![];
+    read.read_to_end(&mut all).unwrap();
+    assert!(all == bytes);
+
+    let mut read = BufReader::new(Pipe {
+        bytes: b"v 0 0 0\nv 1 0 0\n",
+        chunk: 100,
+    });
+    assert!(!detect_format(&mut read).unwrap());
+    assert_eq!(read.fill_buf().unwrap(), b"v 0 0 0\nv 1 0 0\n");
+}
+
+#[test]
+fn detect_short_inputs() {
+    let bytes = encode(&test_meshes(), Default::default());
+    for len in 0..4 {
+        let mut read = &bytes[..len];
+        assert!(!detect_format(&mut read).unwrap(), "{len}");
+        let mut read = Cursor::new(&bytes[..len]);
+        assert!(is_iyes_mesh_file(&mut read).is_err(), "{len}");
+    }
+    // Only the magic is needed
+    let mut read = &bytes[..4];
+    assert!(detect_format(&mut read).unwrap());
+    // Less than the magic in the buffer is not detected
+    let mut read = BufReader::with_capacity(
+        2,
+        Pipe {
+            bytes: &bytes,
+            chunk: 100,
+        },
+    );
+    assert!(!detect_format(&mut read).unwrap());
+}
+
+#[test]
+fn is_iyes_mesh_file_rewinds() {
+    let bytes = encode(&test_meshes(), Default::default());
+    let mut read = Cursor::new(&bytes);
+    read.set_position(10);
+    assert!(is_iyes_mesh_file(&mut read).unwrap());
+    assert_eq!(read.position(), 0);
+    let mut read = Cursor::new(b"not a mesh file");
+    assert!(!is_iyes_mesh_file(&mut read).unwrap());
+}
+
+#[test]
+fn probe_header() {
+    let meshes = test_meshes();
+    let bytes = encode(&meshes, Default::default());
+    let header_len = IyesMeshHeader::encoded_len();
+    let header = IyesMeshHeader::from_bytes(&bytes[..header_len]).unwrap();
+    let mut read = &bytes[..];
+    let info = probe(&mut read).unwrap();
+    // Only the header was read
+    assert_eq!(read.len(), bytes.len() - header_len);
+    assert_eq!(info.version, { header.version });
+    assert_eq!(info.descriptor_len, { header.descriptor_len });
+    assert_eq!(info.metadata_checksum, { header.metadata_checksum });
+    assert!(info.has_data_checksum());
+    assert!(info.is_version_supported());
+
+    let settings = IyesMeshWriterSettings {
+        write_data_checksum: false,
+        ..Default::default()
+    };
+    let bytes = encode(&meshes, settings);
+    assert!(!probe(&mut &bytes[..]).unwrap().has_data_checksum());
+
+    // Nothing after the header is decoded
+    let mut corrupted = bytes.clone();
+    corrupted[header_len..].fill(0xff);
+    assert_eq!(
+        probe(&mut &corrupted[..]).unwrap(),
+        probe(&mut &bytes[..]).unwrap()
+    );
+    let mut future = bytes.clone();
+    future[4..6].copy_from_slice(&u16::MAX.to_le_bytes());
+    let info = probe(&mut &future[..]).unwrap();
+    assert_eq!(info.version, u16::MAX);
+    assert!(!info.is_version_supported());
+}
+
+#[test]
+fn probe_errors() {
+    let bytes = encode(&test_meshes(), Default::default());
+    assert!(matches!(probe(&mut &bytes[..10]), Err(ReadError::NotEnoughData)));
+    assert!(matches!(probe(&mut &[][..]), Err(ReadError::NotEnoughData)));
+    let mut not_ima = bytes.clone();
+    not_ima[..4].copy_from_slice(b"glTF");
+    assert!(matches!(probe(&mut &not_ima[..]), Err(ReadError::BadMagic)));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.ima");
+    std::fs::write(&path, &bytes).unwrap();
+    assert_eq!(probe_file(&path).unwrap(), probe(&mut &bytes[..]).unwrap());
+    assert!(matches!(
+        probe_file(dir.path().join("missing.ima")),
+        Err(ReadError::Io(_))
+    ));
+}
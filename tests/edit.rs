@@ -0,0 +1,150 @@
+use std::io::Cursor;
+
+use iyes_mesh::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+use iyes_mesh::write::{
+    AttributeFilter, IyesMeshWriter, MeshMismatch, RejectedMesh, WriteError,
+};
+
+mod common;
+use common::*;
+
+#[test]
+fn add_meshes_reports_each_mismatch() {
+    let meshes = test_meshes();
+    let mut no_normals = meshes[1].as_ref();
+    no_normals.attributes.remove(&VertexUsage::Normal);
+    let mut other_format = meshes[2].as_ref();
+    other_format.attributes.insert(
+        VertexUsage::Normal,
+        (VertexFormat::Unorm8x4, &meshes[2].normals[..(9 * 4)]),
+    );
+    let mut no_indices = meshes[2].as_ref();
+    no_indices.indices = None;
+    no_indices.attributes.insert(
+        VertexUsage::Color,
+        (VertexFormat::Unorm8x4, &meshes[2].normals[..(9 * 4)]),
+    );
+
+    let mut writer = IyesMeshWriter::new();
+    writer.add_mesh(meshes[0].as_ref()).unwrap();
+    let report = writer
+        .add_meshes([
+            meshes[1].as_ref(),
+            no_normals,
+            other_format,
+            meshes[2].as_ref(),
+            no_indices,
+        ])
+        .unwrap_err();
+    assert_eq!(
+        report.rejected,
+        [
+            RejectedMesh {
+                index: 1,
+                problems: vec![MeshMismatch::MissingAttribute(
+                    VertexUsage::Normal
+                )],
+            },
+            RejectedMesh {
+                index: 2,
+                problems: vec![MeshMismatch::AttributeFormat {
+                    usage: VertexUsage::Normal,
+                    expected: VertexFormat::Float32x3,
+                    found: VertexFormat::Unorm8x4,
+                }],
+            },
+            RejectedMesh {
+                index: 4,
+                problems: vec![
+                    MeshMismatch::IndexFormat {
+                        expected: Some(IndexFormat::U16),
+                        found: None,
+                    },
+                    MeshMismatch::UnexpectedAttribute(VertexUsage::Color),
+                ],
+            },
+        ]
+    );
+    // Nothing is added if any mesh is rejected
+    assert_eq!(writer.n_meshes(), 1);
+
+    writer.add_meshes(meshes[1..].iter().map(|m| m.as_ref())).unwrap();
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+    assert!(out.into_inner() == encode(&meshes, Default::default()));
+}
+
+#[test]
+fn remove_and_replace_meshes() {
+    let meshes = test_meshes();
+    let mut writer = writer_for(&meshes, Default::default());
+    let removed = writer.remove_mesh(1).unwrap();
+    assert!(removed.attributes == meshes[1].as_ref().attributes);
+    assert_eq!(writer.n_meshes(), 2);
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+    let without = [TestMesh::grid(16, 1), TestMesh::grid(3, 3)];
+    assert!(out.into_inner() == encode(&without, Default::default()));
+
+    // Temporarily incompatible, until the mesh is replaced
+    let mut no_normals = meshes[0].as_ref();
+    no_normals.attributes.remove(&VertexUsage::Normal);
+    writer.add_mesh(no_normals).unwrap();
+    assert!(matches!(
+        writer.write_to(&mut Cursor::new(vec![])),
+        Err(WriteError::IncompatibleMeshes)
+    ));
+    writer.replace_mesh(2, meshes[1].as_ref()).unwrap();
+    writer.replace_mesh(1, meshes[2].as_ref()).unwrap();
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+    let reordered = [
+        TestMesh::grid(16, 1),
+        TestMesh::grid(3, 3),
+        TestMesh::grid(40, 2),
+    ];
+    assert!(out.into_inner() == encode(&reordered, Default::default()));
+    assert!(matches!(writer.remove_mesh(3), Err(WriteError::NoSuchMesh(3))));
+}
+
+#[test]
+fn attribute_filter() {
+    let meshes = test_meshes();
+    let mut no_normals = meshes[1].as_ref();
+    no_normals.attributes.remove(&VertexUsage::Normal);
+    let mut writer = IyesMeshWriter::new();
+    writer.add_mesh(meshes[0].as_ref()).unwrap();
+    writer.add_mesh(no_normals).unwrap();
+    // The meshes only differ in an attribute that is not written
+    writer.set_attribute_filter(AttributeFilter {
+        keep: Some([VertexUsage::Position].into_iter().collect()),
+        ..Default::default()
+    });
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+    let with_data = decode(out.get_ref());
+    let descriptor = with_data.descriptor();
+    assert_eq!(descriptor.attributes.len(), 1);
+    assert_eq!(
+        descriptor.attributes[&VertexUsage::Position],
+        VertexFormat::Float32x3
+    );
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let split = with_data.into_split_meshes(&buffers).unwrap();
+    for (mesh, expected) in split.meshes.iter().zip(&meshes) {
+        assert_eq!(mesh.attributes.len(), 1);
+        let (_, positions) = mesh.attributes[&VertexUsage::Position];
+        assert_eq!(positions, expected.positions.as_slice());
+    }
+
+    writer.set_attribute_filter(AttributeFilter {
+        drop: [VertexUsage::Position, VertexUsage::Normal]
+            .into_iter()
+            .collect(),
+        ..Default::default()
+    });
+    assert!(matches!(
+        writer.write_to(&mut Cursor::new(vec![])),
+        Err(WriteError::PositionFiltered)
+    ));
+}
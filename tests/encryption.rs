@@ -0,0 +1,148 @@
+#![cfg(feature = "encryption")]
+
+use std::io::Cursor;
+
+use iyes_mesh::descriptor::{ColorSpace, VertexUsage};
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings, ReadError};
+use iyes_mesh::write::IyesMeshWriterSettings;
+
+mod common;
+use common::*;
+
+const KEY: [u8; 32] = [3; 32];
+const USER_DATA: &[u8] = b"some secret user data";
+
+fn read_user_data(
+    bytes: &[u8],
+    user_data_key: Option<[u8; 32]>,
+) -> Result<Option<Vec<u8>>, ReadError> {
+    let settings = IyesMeshReaderSettings {
+        user_data_key,
+        ..Default::default()
+    };
+    let mut read = Cursor::new(bytes);
+    IyesMeshReader::init_with_settings(settings, &mut read)?
+        .read_all_data()?
+        .decode_user_data()
+}
+
+/// The nonce is generated once along with the user data, so every write of
+/// the same writer must give the same bytes.
+#[test]
+fn encrypted_write_paths_are_identical() {
+    let meshes = test_meshes();
+    let settings = IyesMeshWriterSettings {
+        user_data_key: Some(KEY),
+        ..Default::default()
+    };
+    let mut writer = writer_for(&meshes, settings);
+    writer.set_user_data(USER_DATA);
+
+    let mut stream = vec![];
+    writer.write_to_stream(&mut stream).unwrap();
+    let mut seekable = Cursor::new(vec![]);
+    writer.write_to(&mut seekable).unwrap();
+    let mut readable = Cursor::new(vec![]);
+    writer.write_to_readable(&mut readable).unwrap();
+    assert!(seekable.get_ref() == &stream);
+    assert!(readable.get_ref() == &stream);
+
+    let with_data = decode(&stream);
+    let stored = with_data.descriptor().user_data_len as usize;
+    assert_ne!(stored, USER_DATA.len());
+    assert_eq!(
+        read_user_data(&stream, Some(KEY)).unwrap().as_deref(),
+        Some(USER_DATA)
+    );
+}
+
+/// The nonce is chosen along with the user data, so planning, and preparing
+/// the file again after a change, must not pick a different one.
+#[test]
+fn plan_matches_encrypted_file() {
+    let meshes = test_meshes();
+    let settings = IyesMeshWriterSettings {
+        user_data_key: Some(KEY),
+        ..Default::default()
+    };
+    let mut writer = writer_for(&meshes, settings);
+    writer.set_user_data(USER_DATA);
+    let plan = writer.plan().unwrap();
+    assert!(plan.descriptor.user_data_nonce.is_some());
+
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+    let bytes = out.into_inner();
+    assert_eq!(decode(&bytes).descriptor(), &plan.descriptor);
+
+    // Invalidates the prepared file
+    writer.set_color_space(VertexUsage::Color, ColorSpace::Srgb);
+    writer.clear_color_space(VertexUsage::Color);
+    let mut again = Cursor::new(vec![]);
+    writer.write_to(&mut again).unwrap();
+    assert!(again.into_inner() == bytes);
+
+    // New user data gets a new nonce
+    writer.set_user_data(USER_DATA);
+    let replanned = writer.plan().unwrap().descriptor;
+    assert_ne!(replanned.user_data_nonce, plan.descriptor.user_data_nonce);
+}
+
+#[test]
+fn encrypted_user_data_needs_the_key() {
+    let meshes = test_meshes();
+    let settings = IyesMeshWriterSettings {
+        user_data_key: Some(KEY),
+        ..Default::default()
+    };
+    let mut writer = writer_for(&meshes, settings);
+    writer.set_user_data(USER_DATA);
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+    let bytes = out.into_inner();
+
+    assert!(matches!(
+        read_user_data(&bytes, None),
+        Err(ReadError::UserDataEncrypted)
+    ));
+    assert!(matches!(
+        read_user_data(&bytes, Some([4; 32])),
+        Err(ReadError::UserDataDecryption)
+    ));
+}
+
+/// Only the user data is encrypted, so the meshes read without the key.
+#[test]
+fn read_user_data_with_the_key() {
+    let meshes = test_meshes();
+    let settings = IyesMeshWriterSettings {
+        user_data_key: Some(KEY),
+        ..Default::default()
+    };
+    let mut writer = writer_for(&meshes, settings);
+    writer.set_user_data(USER_DATA);
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+    let bytes = out.into_inner();
+
+    let read = |user_data_key| {
+        let settings = IyesMeshReaderSettings {
+            user_data_key,
+            ..Default::default()
+        };
+        let mut read = Cursor::new(&bytes);
+        IyesMeshReader::init_with_settings(settings, &mut read)?
+            .read_user_data()
+    };
+    assert_eq!(read(Some(KEY)).unwrap(), USER_DATA);
+    assert!(matches!(read(None), Err(ReadError::UserDataEncrypted)));
+    assert!(matches!(read(Some([4; 32])), Err(ReadError::UserDataDecryption)));
+
+    let with_data = decode(&bytes);
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let expected = encode(&meshes, Default::default());
+    let expected = decode(&expected);
+    let expected = expected.into_flat_buffers().unwrap();
+    assert!(buffers.buf_index == expected.buf_index);
+    assert!(buffers.buf_attrs == expected.buf_attrs);
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "f16")]
+
+use std::io::Cursor;
+
+use iyes_mesh::HashMap;
+use iyes_mesh::descriptor::{VertexFormat, VertexUsage};
+use iyes_mesh::f16::encode_f32_to_f16_buf;
+use iyes_mesh::mesh::MeshDataRef;
+use iyes_mesh::write::IyesMeshWriter;
+
+mod common;
+use common::*;
+
+const UVS: [[f32; 2]; 3] = [[0.0, 0.0], [1.0, 0.5], [0.25, 0.75]];
+
+fn read_back_uvs(
+    uvs: &[u8],
+    format: VertexFormat,
+    stored_as: Option<VertexFormat>,
+) -> (VertexFormat, Vec<[f32; 2]>) {
+    let positions: Vec<u8> =
+        (0..9).flat_map(|i| (i as f32).to_le_bytes()).collect();
+    let mut attributes = HashMap::default();
+    attributes.insert(
+        VertexUsage::Position,
+        (VertexFormat::Float32x3, positions.as_slice()),
+    );
+    attributes.insert(VertexUsage::Uv0, (format, uvs));
+    let mut writer = IyesMeshWriter::new();
+    writer
+        .add_mesh(MeshDataRef {
+            indices: None,
+            attributes,
+        })
+        .unwrap();
+    if let Some(to) = stored_as {
+        writer.set_attribute_conversion(VertexUsage::Uv0, to);
+    }
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+
+    let with_data = decode(out.get_ref());
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let meshes = with_data.into_split_meshes(&buffers).unwrap();
+    let stored = with_data.descriptor().attributes[&VertexUsage::Uv0];
+    let uvs = with_data
+        .decode_attribute_f32::<2>(&meshes, 0, VertexUsage::Uv0)
+        .unwrap();
+    (stored, uvs)
+}
+
+#[test]
+fn read_float16_uvs_as_f32() {
+    let flat: Vec<f32> = UVS.iter().flatten().copied().collect();
+    let encoded = encode_f32_to_f16_buf(&flat);
+    let (stored, uvs) = read_back_uvs(&encoded, VertexFormat::Float16x2, None);
+    assert_eq!(stored, VertexFormat::Float16x2);
+    assert_eq!(uvs, UVS);
+}
+
+#[test]
+fn writer_converts_to_float16() {
+    let flat: Vec<u8> =
+        UVS.iter().flatten().flat_map(|v| v.to_le_bytes()).collect();
+    let (stored, uvs) = read_back_uvs(
+        &flat,
+        VertexFormat::Float32x2,
+        Some(VertexFormat::Float16x2),
+    );
+    assert_eq!(stored, VertexFormat::Float16x2);
+    assert_eq!(uvs, UVS);
+}
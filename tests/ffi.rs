@@ -0,0 +1,52 @@
+#![cfg(feature = "ffi")]
+
+use iyes_mesh::write::IyesMeshWriterSettings;
+
+mod common;
+use common::*;
+
+unsafe extern "C" {
+    /// In `tests/ffi/check.c`. Returns the line of the failed check, or 0.
+    fn iyesmesh_check_file(
+        bytes: *const u8,
+        len: usize,
+        n_meshes: usize,
+        vertex_counts: *const usize,
+        index_counts: *const usize,
+        positions: *const *const u8,
+        user_data: *const u8,
+        user_data_len: usize,
+    ) -> i32;
+}
+
+#[test]
+fn c_api() {
+    let meshes = test_meshes();
+    let user_data = b"user data";
+    let mut writer = writer_for(&meshes, IyesMeshWriterSettings::default());
+    writer.set_user_data(user_data);
+    let mut bytes = vec![];
+    writer.write_to_stream(&mut bytes).unwrap();
+
+    let vertex_counts: Vec<usize> =
+        meshes.iter().map(|m| m.positions.len() / 12).collect();
+    let index_counts: Vec<usize> =
+        meshes.iter().map(|m| m.indices.len() / 2).collect();
+    let positions: Vec<*const u8> =
+        meshes.iter().map(|m| m.positions.as_ptr()).collect();
+    // SAFETY: all the pointers are valid for the lengths given with them,
+    // or for one element per mesh.
+    let line = unsafe {
+        iyesmesh_check_file(
+            bytes.as_ptr(),
+            bytes.len(),
+            meshes.len(),
+            vertex_counts.as_ptr(),
+            index_counts.as_ptr(),
+            positions.as_ptr(),
+            user_data.as_ptr(),
+            user_data.len(),
+        )
+    };
+    assert_eq!(line, 0, "check failed at tests/ffi/check.c:{line}");
+}
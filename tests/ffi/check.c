@@ -0,0 +1,75 @@
+/*
+ * Checks of the C API, called by `tests/ffi.rs` with a file and what it
+ * is expected to contain. Compiled by the build script.
+ */
+
+#include <stdlib.h>
+#include <string.h>
+
+#include "iyesmesh.h"
+
+/* Return the line of the failed check */
+#define CHECK(c)            \
+    do {                    \
+        if (!(c)) {         \
+            return __LINE__; \
+        }                   \
+    } while (0)
+
+int iyesmesh_check_file(const uint8_t *bytes, size_t len, size_t n_meshes,
+                        const size_t *vertex_counts,
+                        const size_t *index_counts,
+                        const uint8_t *const *positions,
+                        const uint8_t *user_data, size_t user_data_len) {
+    CHECK(iyesmesh_open(bytes, len / 2) == NULL);
+    CHECK(iyesmesh_last_error_message() != NULL);
+
+    IyesMeshHandle *handle = iyesmesh_open(bytes, len);
+    CHECK(handle != NULL);
+    CHECK(iyesmesh_mesh_count(handle) == n_meshes);
+    for (size_t m = 0; m < n_meshes; m++) {
+        CHECK(iyesmesh_vertex_count(handle, m) == vertex_counts[m]);
+        CHECK(iyesmesh_index_count(handle, m) == index_counts[m]);
+        CHECK(iyesmesh_index_size(handle, m) == 2);
+        CHECK(iyesmesh_has_attribute(handle, m, IYESMESH_USAGE_NORMAL));
+        CHECK(!iyesmesh_has_attribute(handle, m, IYESMESH_USAGE_COLOR));
+        CHECK(iyesmesh_copy_attribute(handle, m, IYESMESH_USAGE_COLOR,
+                                      NULL, 0) == 0);
+
+        int32_t format =
+            iyesmesh_attribute_format(handle, m, IYESMESH_USAGE_POSITION);
+        CHECK(strcmp(iyesmesh_format_name(format), "Float32x3") == 0);
+        CHECK(iyesmesh_format_size(format) == 12);
+        size_t size = iyesmesh_copy_attribute(
+            handle, m, IYESMESH_USAGE_POSITION, NULL, 0);
+        CHECK(size == vertex_counts[m] * 12);
+        uint8_t *data = malloc(size);
+        CHECK(iyesmesh_copy_attribute(handle, m, IYESMESH_USAGE_POSITION,
+                                      data, size) == size);
+        int same = memcmp(data, positions[m], size) == 0;
+        free(data);
+        CHECK(same);
+
+        size = iyesmesh_copy_indices(handle, m, NULL, 0);
+        CHECK(size == index_counts[m] * 2);
+        data = malloc(size);
+        /* Too small buffers are an error */
+        CHECK(iyesmesh_copy_indices(handle, m, data, size - 1) == 0);
+        CHECK(iyesmesh_copy_indices(handle, m, data, size) == size);
+        free(data);
+    }
+    CHECK(iyesmesh_vertex_count(handle, n_meshes) == 0);
+    CHECK(iyesmesh_attribute_format(handle, n_meshes,
+                                    IYESMESH_USAGE_POSITION) == -1);
+
+    CHECK(iyesmesh_copy_user_data(handle, NULL, 0) == user_data_len);
+    uint8_t *data = malloc(user_data_len);
+    CHECK(iyesmesh_copy_user_data(handle, data, user_data_len) ==
+          user_data_len);
+    int same = memcmp(data, user_data, user_data_len) == 0;
+    free(data);
+    CHECK(same);
+
+    iyesmesh_close(handle);
+    return 0;
+}
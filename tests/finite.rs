@@ -0,0 +1,174 @@
+use iyes_mesh::descriptor::{VertexFormat, VertexUsage};
+use iyes_mesh::mesh::{MeshData, find_non_finite, scrub_non_finite};
+
+mod common;
+use common::*;
+
+/// The cube, with NaN and infinite values in its positions and UVs.
+fn broken_cube() -> MeshData {
+    let mut positions = CUBE_POSITIONS.to_vec();
+    positions[2][1] = f32::NAN;
+    positions[5] = [f32::INFINITY, 0.0, f32::NEG_INFINITY];
+    let uvs: Vec<u8> = (0..8)
+        .flat_map(|v| {
+            [
+                v as f64,
+                if v == 7 {
+                    f64::NAN
+                } else {
+                    0.5
+                },
+            ]
+        })
+        .flat_map(|c| c.to_le_bytes())
+        .collect();
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&positions)
+        .unwrap()
+        .set_attribute_bytes(VertexUsage::Uv0, VertexFormat::Float64x2, &uvs)
+        .unwrap()
+        .set_indices_u16(CUBE_INDICES);
+    mesh
+}
+
+fn sorted(
+    mut v: Vec<(VertexUsage, usize, usize)>
+) -> Vec<(VertexUsage, usize, usize)> {
+    v.sort();
+    v
+}
+
+#[test]
+fn find_non_finite_floats() {
+    let mut cube = broken_cube();
+    // Integer attributes are not scanned
+    cube.set_attribute_bytes(
+        VertexUsage::JointIndex,
+        VertexFormat::Uint32,
+        &[0xff; 32],
+    )
+    .unwrap();
+    assert_eq!(
+        sorted(find_non_finite(&cube.as_mesh_ref())),
+        [
+            (VertexUsage::Position, 2, 1),
+            (VertexUsage::Position, 5, 0),
+            (VertexUsage::Position, 5, 2),
+            (VertexUsage::Uv0, 7, 1),
+        ]
+    );
+
+    let mut clean = MeshData::new();
+    clean.set_positions(CUBE_POSITIONS).unwrap();
+    assert!(find_non_finite(&clean.as_mesh_ref()).is_empty());
+}
+
+/// Float16 bit patterns: NaN, -NaN, infinity, -infinity, the largest
+/// finite value, and the smallest subnormal.
+const F16_VALUES: [u16; 6] = [0x7e00, 0xfe01, 0x7c00, 0xfc00, 0x7bff, 0x0001];
+
+fn with_f16_normals() -> MeshData {
+    let normals: Vec<u8> = F16_VALUES
+        .iter()
+        .chain(&[0x3c00; 2])
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&CUBE_POSITIONS[..2])
+        .unwrap()
+        .set_attribute_bytes(
+            VertexUsage::Normal,
+            VertexFormat::Float16x4,
+            &normals,
+        )
+        .unwrap();
+    mesh
+}
+
+#[test]
+fn find_non_finite_f16() {
+    let mesh = with_f16_normals();
+    assert_eq!(
+        sorted(find_non_finite(&mesh.as_mesh_ref())),
+        [
+            (VertexUsage::Normal, 0, 0),
+            (VertexUsage::Normal, 0, 1),
+            (VertexUsage::Normal, 0, 2),
+            (VertexUsage::Normal, 0, 3),
+        ]
+    );
+}
+
+#[test]
+fn scrub_non_finite_floats() {
+    let cube = broken_cube();
+    let (scrubbed, n_replaced) =
+        scrub_non_finite(&cube.as_mesh_ref(), 0.0).unwrap();
+    assert_eq!(n_replaced, 4);
+    let scrubbed = scrubbed.as_mesh_ref();
+    assert!(find_non_finite(&scrubbed).is_empty());
+    let mut expected = CUBE_POSITIONS.to_vec();
+    expected[2][1] = 0.0;
+    expected[5] = [0.0; 3];
+    let positions: Vec<_> = scrubbed.positions_f32().unwrap().collect();
+    assert_eq!(positions, expected);
+    let uvs: Vec<_> = scrubbed.uvs_f32(VertexUsage::Uv0).unwrap().collect();
+    assert_eq!(uvs[7], [7.0, 0.0]);
+    assert_eq!(
+        scrubbed.attributes[&VertexUsage::Uv0].0,
+        VertexFormat::Float64x2
+    );
+    assert_eq!(scrubbed.indices, cube.as_mesh_ref().indices);
+
+    // With a replacement value
+    let (scrubbed, _) = scrub_non_finite(&cube.as_mesh_ref(), -1.5).unwrap();
+    let positions: Vec<_> =
+        scrubbed.as_mesh_ref().positions_f32().unwrap().collect();
+    assert_eq!(positions[5], [-1.5, 0.0, -1.5]);
+
+    // Nothing to replace
+    let (unchanged, n_replaced) =
+        scrub_non_finite(&scrubbed.as_mesh_ref(), 0.0).unwrap();
+    assert_eq!(n_replaced, 0);
+    assert_eq!(
+        unchanged.as_mesh_ref().attributes,
+        scrubbed.as_mesh_ref().attributes
+    );
+}
+
+#[cfg(feature = "f16")]
+#[test]
+fn scrub_non_finite_f16() {
+    let mesh = with_f16_normals();
+    let (scrubbed, n_replaced) =
+        scrub_non_finite(&mesh.as_mesh_ref(), 1.0).unwrap();
+    assert_eq!(n_replaced, 4);
+    let (format, bytes) =
+        scrubbed.as_mesh_ref().attributes[&VertexUsage::Normal];
+    assert_eq!(format, VertexFormat::Float16x4);
+    let values: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|v| u16::from_le_bytes([v[0], v[1]]))
+        .collect();
+    assert_eq!(
+        values,
+        [
+            0x3c00, 0x3c00, 0x3c00, 0x3c00, 0x7bff, 0x0001, 0x3c00, 0x3c00
+        ]
+    );
+}
+
+#[cfg(not(feature = "f16"))]
+#[test]
+fn scrub_non_finite_f16_needs_the_feature() {
+    use iyes_mesh::mesh::MeshError;
+
+    let mesh = with_f16_normals();
+    assert!(matches!(
+        scrub_non_finite(&mesh.as_mesh_ref(), 1.0),
+        Err(MeshError::UnsupportedFormat {
+            usage: VertexUsage::Normal,
+            format: VertexFormat::Float16x4,
+        })
+    ));
+}
@@ -0,0 +1,93 @@
+#![cfg(feature = "glam")]
+
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use iyes_mesh::descriptor::{PositionTransform, VertexUsage};
+use iyes_mesh::glam_interop::*;
+use iyes_mesh::mesh::{MeshData, transform};
+
+const POSITIONS: [[f32; 3]; 3] =
+    [[0.0, 1.0, 2.0], [-3.0, 4.0, 5.0], [6.0, -7.0, 8.0]];
+const NORMALS: [[f32; 3]; 3] =
+    [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]];
+const TANGENTS: [[f32; 4]; 3] = [[1.0, 0.0, 0.0, 1.0]; 3];
+const UVS: [[f32; 2]; 3] = [[0.0, 0.5], [1.0, 0.25], [0.75, 1.0]];
+const COLORS: [[f32; 4]; 3] = [[1.0, 0.5, 0.0, 1.0]; 3];
+
+fn raw_mesh() -> MeshData {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&POSITIONS)
+        .unwrap()
+        .set_normals(&NORMALS)
+        .unwrap()
+        .set_tangents(&TANGENTS)
+        .unwrap()
+        .set_uv0(&UVS)
+        .unwrap()
+        .set_uv1(&UVS)
+        .unwrap()
+        .set_colors_f32(&COLORS)
+        .unwrap();
+    mesh
+}
+
+#[test]
+fn glam_setters_match_raw_setters() {
+    let mut mesh = MeshData::new();
+    mesh.set_positions_vec3(&POSITIONS.map(Vec3::from_array))
+        .unwrap()
+        .set_normals_vec3(&NORMALS.map(Vec3::from_array))
+        .unwrap()
+        .set_tangents_vec4(&TANGENTS.map(Vec4::from_array))
+        .unwrap()
+        .set_uv0_vec2(&UVS.map(Vec2::from_array))
+        .unwrap()
+        .set_uv1_vec2(&UVS.map(Vec2::from_array))
+        .unwrap()
+        .set_colors_vec4(&COLORS.map(Vec4::from_array))
+        .unwrap();
+    let raw = raw_mesh();
+    assert!(mesh.as_mesh_ref().attributes == raw.as_mesh_ref().attributes);
+}
+
+#[test]
+fn glam_readers() {
+    let mesh = raw_mesh();
+    let mesh = mesh.as_mesh_ref();
+    let positions: Vec<Vec3> = positions_vec3(&mesh).unwrap().collect();
+    assert_eq!(positions, POSITIONS.map(Vec3::from_array));
+    let normals: Vec<Vec3> = normals_vec3(&mesh).unwrap().collect();
+    assert_eq!(normals, NORMALS.map(Vec3::from_array));
+    let uvs: Vec<Vec2> = uvs_vec2(&mesh, VertexUsage::Uv1).unwrap().collect();
+    assert_eq!(uvs, UVS.map(Vec2::from_array));
+    let colors: Vec<Vec4> = colors_vec4(&mesh).unwrap().collect();
+    assert_eq!(colors, COLORS.map(Vec4::from_array));
+
+    assert_eq!(
+        position_aabb(&mesh),
+        Some((Vec3::new(-3.0, -7.0, 2.0), Vec3::new(6.0, 4.0, 8.0)))
+    );
+    assert_eq!(position_aabb(&MeshData::new().as_mesh_ref()), None);
+    let quantized = PositionTransform {
+        offset: [-3.0, -7.0, 2.0],
+        scale: [9.0, 11.0, 6.0],
+    };
+    assert_eq!(transform_aabb(&quantized), position_aabb(&mesh).unwrap());
+}
+
+#[test]
+fn bake_glam_transform() {
+    let mesh = raw_mesh();
+    let matrix = Mat4::from_scale_rotation_translation(
+        Vec3::splat(2.0),
+        glam::Quat::from_rotation_y(1.0),
+        Vec3::new(1.0, 2.0, 3.0),
+    );
+    let baked =
+        transform(&mesh.as_mesh_ref(), matrix.to_cols_array_2d()).unwrap();
+    let positions: Vec<Vec3> =
+        positions_vec3(&baked.as_mesh_ref()).unwrap().collect();
+    for (p, original) in positions.iter().zip(POSITIONS) {
+        let expected = matrix.transform_point3(Vec3::from_array(original));
+        assert!(p.abs_diff_eq(expected, 1e-5), "{p} vs {expected}");
+    }
+}
@@ -0,0 +1,157 @@
+use std::io::Cursor;
+
+use iyes_mesh::descriptor::{VertexFormat, VertexUsage};
+use iyes_mesh::write::WriteError;
+
+mod common;
+use common::*;
+
+/// Per-instance translations, as Float32x3.
+fn offsets(n: u32) -> Vec<u8> {
+    (0..n)
+        .flat_map(|i| [i as f32, 0.0, -(i as f32)])
+        .flat_map(f32::to_le_bytes)
+        .collect()
+}
+
+/// Per-instance colors, as Unorm8x4.
+fn tints(n: u32) -> Vec<u8> {
+    (0..n).flat_map(|i| [i as u8, 0, 255, 255]).collect()
+}
+
+#[test]
+fn instance_data_round_trip() {
+    let meshes = test_meshes();
+    let (offsets, tints) = (offsets(10), tints(10));
+    let mut writer = writer_for(&meshes, Default::default());
+    writer
+        .set_instance_data(
+            VertexUsage::Custom(0),
+            VertexFormat::Float32x3,
+            &offsets,
+            10,
+        )
+        .unwrap();
+    writer
+        .set_instance_data(
+            VertexUsage::Color,
+            VertexFormat::Unorm8x4,
+            &tints,
+            10,
+        )
+        .unwrap();
+    writer.set_mesh_instances(1, 2..5).unwrap();
+    writer.set_mesh_instances(2, 10..10).unwrap();
+    assert_eq!(writer.n_instances(), 10);
+    assert_eq!(writer.mesh_instances(0), 0..10);
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+
+    let data = decode(out.get_ref());
+    let descriptor = data.descriptor();
+    assert_eq!(descriptor.n_instances, 10);
+    assert_eq!(descriptor.instance_attributes.len(), 2);
+    let ranges: Vec<_> = descriptor
+        .meshes
+        .iter()
+        .map(|m| (m.first_instance, m.instance_count))
+        .collect();
+    assert_eq!(ranges, [(0, 10), (2, 3), (10, 0)]);
+    // Vertex counts are independent of the instance count
+    assert_eq!(descriptor.meshes[2].vertex_count, 9);
+
+    let buffers = data.into_flat_buffers().unwrap();
+    assert_eq!(
+        buffers.buf_instances[&VertexUsage::Custom(0)],
+        (VertexFormat::Float32x3, offsets.as_slice())
+    );
+    assert_eq!(
+        buffers.buf_instances[&VertexUsage::Color],
+        (VertexFormat::Unorm8x4, tints.as_slice())
+    );
+    // Instance attributes are not vertex attributes
+    assert!(!buffers.buf_attrs.contains_key(&VertexUsage::Color));
+
+    let split = data.into_split_meshes(&buffers).unwrap();
+    assert_eq!(
+        split.instances[0][&VertexUsage::Color],
+        (VertexFormat::Unorm8x4, tints.as_slice())
+    );
+    assert_eq!(
+        split.instances[1][&VertexUsage::Custom(0)],
+        (VertexFormat::Float32x3, &offsets[2 * 12..5 * 12])
+    );
+    assert_eq!(split.instances[2][&VertexUsage::Color].1, &[] as &[u8]);
+    for (mesh, original) in split.meshes.iter().zip(&meshes) {
+        assert_eq!(mesh.attributes, original.as_ref().attributes);
+    }
+}
+
+#[test]
+fn instance_data_is_validated() {
+    let meshes = test_meshes();
+    let offsets = offsets(4);
+    let mut writer = writer_for(&meshes, Default::default());
+    assert!(matches!(
+        writer.set_instance_data(
+            VertexUsage::Custom(0),
+            VertexFormat::Float32x3,
+            &offsets,
+            5,
+        ),
+        Err(WriteError::InstanceDataSize {
+            len: 48,
+            expected: 60,
+            ..
+        })
+    ));
+    writer
+        .set_instance_data(
+            VertexUsage::Custom(0),
+            VertexFormat::Float32x3,
+            &offsets,
+            4,
+        )
+        .unwrap();
+    let tints = tints(3);
+    assert!(matches!(
+        writer.set_instance_data(
+            VertexUsage::Color,
+            VertexFormat::Unorm8x4,
+            &tints,
+            3,
+        ),
+        Err(WriteError::InstanceCount {
+            expected: 4,
+            found: 3,
+        })
+    ));
+    // Replacing the only attribute may change the count
+    writer
+        .set_instance_data(
+            VertexUsage::Custom(0),
+            VertexFormat::Float32x3,
+            &offsets[..36],
+            3,
+        )
+        .unwrap();
+    assert_eq!(writer.n_instances(), 3);
+
+    assert!(matches!(
+        writer.set_mesh_instances(3, 0..1),
+        Err(WriteError::NoSuchMesh(3))
+    ));
+    writer.set_mesh_instances(1, 2..4).unwrap();
+    assert!(matches!(
+        writer.write_to(&mut Cursor::new(vec![])),
+        Err(WriteError::InstanceRange {
+            mesh: 1,
+            n_instances: 3,
+            ..
+        })
+    ));
+
+    writer.clear_instance_data(VertexUsage::Custom(0));
+    assert_eq!(writer.n_instances(), 0);
+    assert!(writer.instance_data().is_empty());
+}
@@ -0,0 +1,346 @@
+use iyes_mesh::HashMap;
+use iyes_mesh::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+use iyes_mesh::mesh::{
+    MeshData, MeshDataRef, MeshError, downconvert_indices,
+    is_degenerate_triangle, iter_indices,
+};
+
+mod common;
+use common::*;
+
+fn le_bytes<T: Copy, const N: usize>(
+    values: &[T],
+    to_le: fn(T) -> [u8; N],
+) -> Vec<u8> {
+    values.iter().flat_map(|v| to_le(*v)).collect()
+}
+
+/// A mesh with no indices and a single attribute.
+fn mesh_with(
+    usage: VertexUsage,
+    format: VertexFormat,
+    data: &[u8],
+) -> MeshDataRef<'_> {
+    let mut attributes = HashMap::default();
+    attributes.insert(usage, (format, data));
+    MeshDataRef {
+        indices: None,
+        attributes,
+    }
+}
+
+#[test]
+fn decode_attributes_of_each_family() {
+    let usage = VertexUsage::Uv1;
+    let cases: Vec<(VertexFormat, Vec<u8>, [f32; 4])> = vec![
+        (
+            VertexFormat::Float32x2,
+            le_bytes(&[0.25f32, -3.0, 1.0, 0.5], f32::to_le_bytes),
+            [0.25, -3.0, 1.0, 0.5],
+        ),
+        (
+            VertexFormat::Float64x2,
+            le_bytes(&[0.25f64, -3.0, 1.0, 0.5], f64::to_le_bytes),
+            [0.25, -3.0, 1.0, 0.5],
+        ),
+        (VertexFormat::Unorm8x2, vec![51, 0, 255, 102], [0.2, 0.0, 1.0, 0.4]),
+        (
+            VertexFormat::Snorm8x2,
+            le_bytes(&[-127i8, -128, 127, 0], i8::to_le_bytes),
+            [-1.0, -1.0, 1.0, 0.0],
+        ),
+        (
+            VertexFormat::Unorm16x2,
+            le_bytes(&[0u16, 65535, 13107, 52428], u16::to_le_bytes),
+            [0.0, 1.0, 0.2, 0.8],
+        ),
+        (
+            VertexFormat::Snorm16x2,
+            le_bytes(&[-32767i16, 32767, 0, -32768], i16::to_le_bytes),
+            [-1.0, 1.0, 0.0, -1.0],
+        ),
+    ];
+    for (format, data, expected) in &cases {
+        let mesh = mesh_with(usage, *format, data);
+        let values: Vec<[f32; 2]> = mesh.uvs_f32(usage).unwrap().collect();
+        assert_eq!(values.as_flattened(), expected, "{format:?}");
+    }
+
+    // Packed formats have 4 components
+    let mesh = mesh_with(
+        VertexUsage::Color,
+        VertexFormat::Unorm8x4Bgra,
+        &[0, 51, 255, 102],
+    );
+    let colors: Vec<_> = mesh.colors_f32().unwrap().collect();
+    assert_eq!(colors, [[1.0, 0.2, 0.0, 0.4]]);
+    // R and B at the maximum, G at zero
+    let bits = (1023u32 | (1023 << 20) | (1 << 30)).to_le_bytes();
+    let mesh =
+        mesh_with(VertexUsage::Color, VertexFormat::Unorm10_10_10_2, &bits);
+    let colors: Vec<_> = mesh.colors_f32().unwrap().collect();
+    assert_eq!(colors, [[1.0, 0.0, 1.0, 1.0 / 3.0]]);
+}
+
+#[test]
+fn decode_attributes_component_counts() {
+    let data = le_bytes(&[1.0f32, 2.0, 3.0, 4.0], f32::to_le_bytes);
+    let mesh = mesh_with(VertexUsage::Color, VertexFormat::Float32x4, &data);
+    // Fewer components are fine, the rest are ignored
+    let first: Vec<[f32; 3]> =
+        mesh.attribute_f32(VertexUsage::Color).unwrap().collect();
+    assert_eq!(first, [[1.0, 2.0, 3.0]]);
+
+    let mesh = mesh_with(VertexUsage::Uv0, VertexFormat::Float32x2, &data);
+    assert!(mesh.attribute_f32::<3>(VertexUsage::Uv0).is_none());
+    assert!(mesh.positions_f32().is_none());
+
+    // Integer data is not converted
+    let mesh = mesh_with(VertexUsage::Uv0, VertexFormat::Uint32x2, &data);
+    assert!(mesh.uvs_f32(VertexUsage::Uv0).is_none());
+}
+
+#[test]
+fn decode_attributes_from_buffers() {
+    let meshes = test_meshes();
+    let with_data = decode(&encode(&meshes, Default::default()));
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let positions: Vec<[f32; 3]> = buffers.positions_f32().unwrap().collect();
+    let expected: Vec<u8> =
+        meshes.iter().flat_map(|m| m.positions.clone()).collect();
+    assert_eq!(le_bytes(positions.as_flattened(), f32::to_le_bytes), expected);
+    assert_eq!(buffers.normals_f32().unwrap().count(), positions.len());
+    assert!(buffers.colors_f32().is_none());
+}
+
+#[test]
+fn iterate_indices_as_u32() {
+    let values = [0u32, 1, 2, 70000, 3, 65535];
+    let wide = le_bytes(&values, u32::to_le_bytes);
+    let narrow: Vec<u8> =
+        values.iter().flat_map(|i| (*i as u16).to_le_bytes()).collect();
+    for (format, bytes, expected) in [
+        (IndexFormat::U32, &wide, values.to_vec()),
+        (
+            IndexFormat::U16,
+            &narrow,
+            values.iter().map(|i| *i as u16 as u32).collect(),
+        ),
+    ] {
+        // Also starting at an odd address, which cannot be cast
+        let mut unaligned = vec![0];
+        unaligned.extend_from_slice(bytes);
+        // An incomplete index at the end is ignored
+        unaligned.push(7);
+        for data in [bytes.as_slice(), &unaligned[1..]] {
+            let indices: Vec<u32> = iter_indices(format, data).collect();
+            assert_eq!(indices, expected, "{format:?}");
+        }
+    }
+
+    let positions = vec![0; 4 * 12];
+    let mut mesh =
+        mesh_with(VertexUsage::Position, VertexFormat::Float32x3, &positions);
+    assert!(mesh.iter_indices().is_none());
+    mesh.indices = Some((IndexFormat::U32, &wide[..12]));
+    assert_eq!(mesh.indices_to_vec_u32(), Some(vec![0, 1, 2]));
+    assert!(mesh.validate());
+    // 70000 is not a vertex of the mesh
+    mesh.indices = Some((IndexFormat::U32, &wide[..16]));
+    assert!(!mesh.validate());
+    assert_eq!(mesh.find_out_of_range_indices(), [(3, 70000)]);
+}
+
+#[test]
+fn iterate_indices_of_decoded_meshes() {
+    let meshes = test_meshes();
+    let with_data = decode(&encode(&meshes, Default::default()));
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let split = with_data.into_split_meshes(&buffers).unwrap();
+    let mut all = vec![];
+    for (mesh, expected) in split.meshes.iter().zip(&meshes) {
+        let indices = mesh.indices_to_vec_u32().unwrap();
+        let expected: Vec<u32> = expected
+            .indices
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as u32)
+            .collect();
+        assert_eq!(indices, expected);
+        all.extend(indices);
+    }
+    let from_buffers: Vec<u32> = buffers.iter_indices().unwrap().collect();
+    assert_eq!(from_buffers, all);
+}
+
+#[test]
+fn cube_triangles() {
+    let mut cube = MeshData::new();
+    cube.set_positions(CUBE_POSITIONS).unwrap().set_indices_u16(CUBE_INDICES);
+    let cube = cube.as_mesh_ref();
+
+    let triangles: Vec<[u32; 3]> = cube.iter_triangles().unwrap().collect();
+    assert_eq!(triangles.len(), 12);
+    assert_eq!(triangles[0], [0, 1, 2]);
+    assert_eq!(triangles[11], [1, 0, 4]);
+    assert!(!triangles.iter().any(|t| is_degenerate_triangle(*t)));
+
+    let corners: Vec<[[f32; 3]; 3]> =
+        cube.iter_triangle_positions().unwrap().collect();
+    assert_eq!(corners.len(), 12);
+    for (t, c) in triangles.iter().zip(&corners) {
+        assert_eq!(*c, t.map(|i| CUBE_POSITIONS[i as usize]));
+    }
+    // Front face triangles are on z = 1, the back ones on z = -1
+    assert!(corners[..2].iter().flatten().all(|p| p[2] == 1.0));
+    assert!(corners[2..4].iter().flatten().all(|p| p[2] == -1.0));
+}
+
+#[test]
+fn triangles_without_indices() {
+    let positions = le_bytes(&[0.0f32; 6 * 3], f32::to_le_bytes);
+    let mut mesh =
+        mesh_with(VertexUsage::Position, VertexFormat::Float32x3, &positions);
+    let triangles: Vec<_> = mesh.iter_triangles().unwrap().collect();
+    assert_eq!(triangles, [[0, 1, 2], [3, 4, 5]]);
+    // Repeated indices are easy to filter out
+    assert!(is_degenerate_triangle([2, 0, 2]));
+
+    let indices = le_bytes(&[0u16, 1, 2, 3], u16::to_le_bytes);
+    mesh.indices = Some((IndexFormat::U16, &indices));
+    assert!(matches!(
+        mesh.iter_triangles(),
+        Err(MeshError::IndicesNotTriangles(4))
+    ));
+    mesh.indices = None;
+    mesh.attributes.insert(
+        VertexUsage::Position,
+        (VertexFormat::Float32x3, &positions[..(4 * 12)]),
+    );
+    assert!(matches!(
+        mesh.iter_triangles(),
+        Err(MeshError::VerticesNotTriangles(4))
+    ));
+
+    let indices = le_bytes(&[0u16, 1, 9], u16::to_le_bytes);
+    mesh.indices = Some((IndexFormat::U16, &indices));
+    assert!(matches!(
+        mesh.iter_triangle_positions(),
+        Err(MeshError::IndexOutOfRange {
+            index: 9,
+            n_vertices: 4
+        })
+    ));
+}
+
+#[test]
+fn typed_setters() {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(CUBE_POSITIONS)
+        .unwrap()
+        .set_uv0(&[[0.5, 0.25]; 8])
+        .unwrap()
+        .set_indices_u16(CUBE_INDICES);
+    let r = mesh.as_mesh_ref();
+    assert!(r.validate());
+    assert_eq!(mesh.n_vertices(), 8);
+    let positions: Vec<f32> =
+        CUBE_POSITIONS.iter().flatten().copied().collect();
+    assert_eq!(
+        r.attributes[&VertexUsage::Position],
+        (
+            VertexFormat::Float32x3,
+            le_bytes(&positions, f32::to_le_bytes).as_slice()
+        )
+    );
+    assert_eq!(r.attributes[&VertexUsage::Uv0].0, VertexFormat::Float32x2);
+    assert_eq!(
+        r.indices,
+        Some((
+            IndexFormat::U16,
+            le_bytes(CUBE_INDICES, u16::to_le_bytes).as_slice()
+        ))
+    );
+
+    // The same through the generic setter
+    let mut generic = MeshData::new();
+    generic
+        .set_attribute_pod(
+            VertexUsage::Position,
+            VertexFormat::Float32x3,
+            CUBE_POSITIONS,
+        )
+        .unwrap();
+    assert_eq!(
+        generic.as_mesh_ref().attributes[&VertexUsage::Position],
+        r.attributes[&VertexUsage::Position]
+    );
+}
+
+#[test]
+fn setters_check_the_data() {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(CUBE_POSITIONS).unwrap();
+    // 2 components where the format has 3
+    assert!(matches!(
+        mesh.set_attribute_pod(
+            VertexUsage::Normal,
+            VertexFormat::Float32x3,
+            &[[0.0f32; 2]; 8]
+        ),
+        Err(MeshError::ElementSize {
+            format: VertexFormat::Float32x3,
+            element_size: 8
+        })
+    ));
+    assert!(matches!(
+        mesh.set_attribute_bytes(
+            VertexUsage::Color,
+            VertexFormat::Unorm8x4,
+            &[0; 6]
+        ),
+        Err(MeshError::ElementSize { .. })
+    ));
+    assert!(matches!(
+        mesh.set_normals(&[[0.0, 1.0, 0.0]; 7]),
+        Err(MeshError::VertexCount {
+            usage: VertexUsage::Normal,
+            expected: 8,
+            found: 7
+        })
+    ));
+    // Nothing was added
+    assert_eq!(mesh.as_mesh_ref().attributes.len(), 1);
+    // The only attribute can be replaced with a different number of vertices
+    mesh.set_positions(&CUBE_POSITIONS[..3]).unwrap();
+    assert_eq!(mesh.n_vertices(), 3);
+}
+
+#[test]
+fn downconvert_indices_at_the_limit() {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&vec![[0.0; 3]; 65537]).unwrap();
+    mesh.set_indices_u32(&[0, 65534, 65535]);
+    let converted = downconvert_indices(&mesh.as_mesh_ref()).unwrap();
+    let converted = converted.as_mesh_ref();
+    assert_eq!(
+        converted.indices,
+        Some((
+            IndexFormat::U16,
+            &le_bytes(&[0u16, 65534, 65535], u16::to_le_bytes)[..]
+        ))
+    );
+    assert_eq!(converted.attributes, mesh.as_mesh_ref().attributes);
+
+    mesh.set_indices_u32(&[0, 65535, 65536]);
+    assert!(matches!(
+        downconvert_indices(&mesh.as_mesh_ref()),
+        Err(MeshError::IndexTooLarge {
+            max_index: 65536
+        })
+    ));
+    mesh.clear_indices();
+    assert!(matches!(
+        downconvert_indices(&mesh.as_mesh_ref()),
+        Err(MeshError::NotIndexed)
+    ));
+}
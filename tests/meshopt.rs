@@ -0,0 +1,213 @@
+#![cfg(feature = "meshopt")]
+
+use iyes_mesh::descriptor::IndexFormat;
+use iyes_mesh::mesh::{MeshData, MeshDataRef, MeshError};
+use iyes_mesh::meshopt::{
+    OptimizePasses, SimplifySettings, acmr, optimize_mesh, simplify,
+};
+
+mod common;
+use common::*;
+
+/// The triangles of a mesh, as the bytes of all the attributes of their
+/// corners, in a canonical order (keeping the winding).
+fn triangles(mesh: &MeshDataRef<'_>) -> Vec<[Vec<u8>; 3]> {
+    let mut usages: Vec<_> = mesh.attributes.keys().copied().collect();
+    usages.sort();
+    let vertex = |v: u32| -> Vec<u8> {
+        usages
+            .iter()
+            .flat_map(|usage| {
+                let (format, bytes) = mesh.attributes[usage];
+                let size = format.size();
+                &bytes[v as usize * size..(v as usize + 1) * size]
+            })
+            .copied()
+            .collect()
+    };
+    let mut r: Vec<[Vec<u8>; 3]> = mesh
+        .iter_triangles()
+        .unwrap()
+        .map(|t| {
+            let mut corners = t.map(vertex);
+            let first = (0..3).min_by_key(|i| corners[*i].clone()).unwrap();
+            corners.rotate_left(first);
+            corners
+        })
+        .collect();
+    r.sort();
+    r
+}
+
+/// A grid with its triangles in a scrambled order, and an unused vertex.
+fn scrambled_grid() -> MeshData {
+    let grid = TestMesh::grid(30, 1);
+    let mesh = grid.as_ref();
+    let triangles: Vec<[u32; 3]> = mesh.iter_triangles().unwrap().collect();
+    let n = triangles.len();
+    // 7919 is prime, and does not divide the number of triangles
+    let indices: Vec<u16> = (0..n)
+        .flat_map(|t| triangles[t * 7919 % n])
+        .map(|i| i as u16)
+        .collect();
+    let mut r = MeshData::new();
+    for (usage, (format, bytes)) in mesh.attributes.iter() {
+        let bytes = [bytes, &bytes[..format.size()]].concat();
+        r.set_attribute_bytes(*usage, *format, &bytes).unwrap();
+    }
+    r.set_indices_u16(&indices);
+    r
+}
+
+#[test]
+fn optimize_keeps_the_triangles() {
+    let mesh = scrambled_grid();
+    let mesh = mesh.as_mesh_ref();
+    let optimized = optimize_mesh(&mesh, OptimizePasses::default()).unwrap();
+    let optimized = optimized.as_mesh_ref();
+    assert_eq!(triangles(&optimized), triangles(&mesh));
+    assert_eq!(optimized.indices.unwrap().0, IndexFormat::U16);
+    // The unused vertex is removed
+    assert_eq!(optimized.n_vertices(), mesh.n_vertices() - 1);
+    // Vertices are in the order of first use
+    let first_use: Vec<u32> = {
+        let mut seen = vec![];
+        for i in optimized.iter_indices().unwrap() {
+            if !seen.contains(&i) {
+                seen.push(i);
+            }
+        }
+        seen
+    };
+    assert_eq!(
+        first_use,
+        (0..optimized.n_vertices() as u32).collect::<Vec<_>>()
+    );
+    let (before, after) = (acmr(&mesh).unwrap(), acmr(&optimized).unwrap());
+    assert!(after < before, "ACMR {before} -> {after}");
+}
+
+#[test]
+fn optimize_single_passes() {
+    let mesh = scrambled_grid();
+    let mesh = mesh.as_mesh_ref();
+    let passes = OptimizePasses {
+        vertex_cache: true,
+        overdraw: None,
+        vertex_fetch: false,
+    };
+    let optimized = optimize_mesh(&mesh, passes).unwrap();
+    let optimized = optimized.as_mesh_ref();
+    assert_eq!(triangles(&optimized), triangles(&mesh));
+    // Without the vertex fetch pass, vertices are not reordered
+    assert_eq!(optimized.attributes, mesh.attributes);
+    assert!(acmr(&optimized).unwrap() < acmr(&mesh).unwrap());
+}
+
+#[test]
+fn optimize_needs_indices() {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&cube_soup()).unwrap();
+    let r = optimize_mesh(&mesh.as_mesh_ref(), OptimizePasses::default());
+    assert!(matches!(r, Err(MeshError::NotIndexed)));
+    assert_eq!(acmr(&mesh.as_mesh_ref()), None);
+}
+
+/// A `size` x `size` grid of unit squares, with `height` giving the Y of
+/// each vertex.
+fn plane(
+    size: u32,
+    height: impl Fn(f32, f32) -> f32,
+) -> MeshData {
+    let n = size + 1;
+    let positions: Vec<[f32; 3]> = (0..n * n)
+        .map(|v| {
+            let (x, z) = ((v % n) as f32, (v / n) as f32);
+            [x, height(x, z), z]
+        })
+        .collect();
+    let indices: Vec<u32> = (0..size * size)
+        .flat_map(|q| {
+            let i = q / size * n + q % size;
+            [i, i + n, i + 1, i + 1, i + n, i + n + 1]
+        })
+        .collect();
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&positions).unwrap().set_indices_u32(&indices);
+    mesh
+}
+
+fn n_triangles(mesh: &MeshData) -> usize {
+    mesh.as_mesh_ref().n_indices().unwrap() / 3
+}
+
+#[test]
+fn simplify_a_plane() {
+    let mesh = plane(40, |_, _| 0.0);
+    let settings = SimplifySettings {
+        target_ratio: 0.25,
+        ..Default::default()
+    };
+    let simplified = simplify(&mesh.as_mesh_ref(), settings).unwrap();
+    let target = n_triangles(&mesh) / 4;
+    let n = n_triangles(&simplified);
+    assert!(n <= target && n * 10 >= target * 9, "{n} for {target}");
+    assert_eq!(simplified.as_mesh_ref().indices.unwrap().0, IndexFormat::U32);
+    // Vertices are only ever removed, and all of them are used
+    let original: Vec<[f32; 3]> =
+        mesh.as_mesh_ref().positions_f32().unwrap().collect();
+    let positions: Vec<[f32; 3]> =
+        simplified.as_mesh_ref().positions_f32().unwrap().collect();
+    assert!(positions.iter().all(|p| original.contains(p)));
+    let mut used = vec![false; positions.len()];
+    for i in simplified.as_mesh_ref().iter_indices().unwrap() {
+        used[i as usize] = true;
+    }
+    assert!(used.iter().all(|u| *u));
+}
+
+#[test]
+fn simplify_within_max_error() {
+    let mesh = plane(40, |x, _| (x / 3.0).sin() * 2.0);
+    let simplify_with = |max_error| {
+        let settings = SimplifySettings {
+            target_ratio: 0.0,
+            max_error,
+            lock_border: false,
+        };
+        n_triangles(&simplify(&mesh.as_mesh_ref(), settings).unwrap())
+    };
+    let (fine, coarse) = (simplify_with(0.001), simplify_with(0.1));
+    assert!(fine < n_triangles(&mesh), "{fine}");
+    assert!(coarse < fine, "{coarse} >= {fine}");
+}
+
+#[test]
+fn simplify_with_locked_border() {
+    let mesh = plane(20, |x, z| ((x * 7.0 + z * 3.0) % 5.0) * 0.01);
+    let settings = SimplifySettings {
+        target_ratio: 0.1,
+        max_error: 1.0,
+        lock_border: true,
+    };
+    let simplified = simplify(&mesh.as_mesh_ref(), settings).unwrap();
+    assert!(n_triangles(&simplified) < n_triangles(&mesh));
+    let positions: Vec<[f32; 3]> =
+        simplified.as_mesh_ref().positions_f32().unwrap().collect();
+    let border = mesh
+        .as_mesh_ref()
+        .positions_f32()
+        .unwrap()
+        .filter(|p| [p[0], p[2]].iter().any(|c| *c == 0.0 || *c == 20.0));
+    for p in border {
+        assert!(positions.contains(&p), "{p:?} was removed");
+    }
+}
+
+#[test]
+fn simplify_needs_indices() {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&cube_soup()).unwrap();
+    let r = simplify(&mesh.as_mesh_ref(), SimplifySettings::default());
+    assert!(matches!(r, Err(MeshError::NotIndexed)));
+}
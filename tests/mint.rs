@@ -0,0 +1,76 @@
+#![cfg(feature = "mint")]
+
+use std::io::Cursor;
+
+use iyes_mesh::descriptor::VertexUsage;
+use iyes_mesh::mesh::MeshData;
+use iyes_mesh::mint_interop::*;
+use iyes_mesh::write::IyesMeshWriter;
+use nalgebra::{Point3, Vector2, Vector3, Vector4};
+
+mod common;
+use common::*;
+
+#[test]
+fn nalgebra_through_mint() {
+    let positions = [
+        Point3::new(0.0f32, 1.0, 2.0),
+        Point3::new(-3.0, 4.0, 5.0),
+        Point3::new(6.0, -7.0, 8.0),
+    ];
+    let normals = [Vector3::<f32>::y(), Vector3::x(), -Vector3::z()];
+    let uvs = [
+        Vector2::new(0.0f32, 0.5),
+        Vector2::new(1.0, 0.25),
+        Vector2::new(0.75, 1.0),
+    ];
+    let colors = [Vector4::new(1.0f32, 0.5, 0.0, 1.0); 3];
+    let mut mesh = MeshData::new();
+    mesh.set_positions_mint(positions)
+        .unwrap()
+        .set_normals_mint(normals)
+        .unwrap()
+        .set_uv0_mint(uvs)
+        .unwrap()
+        .set_colors_mint(colors)
+        .unwrap()
+        .set_indices_u16(&[0, 1, 2]);
+
+    let mut raw = MeshData::new();
+    raw.set_positions(&positions.map(|p| p.coords.into()))
+        .unwrap()
+        .set_normals(&normals.map(Into::into))
+        .unwrap()
+        .set_uv0(&uvs.map(Into::into))
+        .unwrap()
+        .set_colors_f32(&colors.map(Into::into))
+        .unwrap();
+    assert!(mesh.as_mesh_ref().attributes == raw.as_mesh_ref().attributes);
+
+    let mut out = Cursor::new(vec![]);
+    IyesMeshWriter::new()
+        .with_mesh(mesh.as_mesh_ref())
+        .unwrap()
+        .write_to(&mut out)
+        .unwrap();
+    let with_data = decode(out.get_ref());
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let split = with_data.into_split_meshes(&buffers).unwrap();
+    let read = &split.meshes[0];
+    let read_positions: Vec<Point3<f32>> =
+        positions_point3(read).unwrap().map(Into::into).collect();
+    assert_eq!(read_positions, positions);
+    let read_normals: Vec<Vector3<f32>> =
+        normals_vector3(read).unwrap().map(Into::into).collect();
+    assert_eq!(read_normals, normals);
+    let read_uvs: Vec<Vector2<f32>> =
+        uvs_vector2(read, VertexUsage::Uv0).unwrap().map(Into::into).collect();
+    assert_eq!(read_uvs, uvs);
+    let read_colors: Vec<Vector4<f32>> =
+        colors_vector4(read).unwrap().map(Into::into).collect();
+    assert_eq!(read_colors, colors);
+
+    let (min, max) = position_aabb(read).unwrap();
+    assert_eq!(Point3::from(min), Point3::new(-3.0, -7.0, 2.0));
+    assert_eq!(Point3::from(max), Point3::new(6.0, 4.0, 8.0));
+}
@@ -0,0 +1,201 @@
+use iyes_mesh::descriptor::{VertexFormat, VertexUsage};
+use iyes_mesh::mesh::{
+    MeshData, MeshError, NormalMode, generate_normals, invert_normals,
+};
+
+mod common;
+use common::*;
+
+/// [`CUBE_INDICES`], with all the faces wound counter-clockwise.
+static WOUND_CUBE_INDICES: &[u16] = &[
+    0, 1, 2, 2, 3, 0, // Front
+    4, 7, 6, 6, 5, 4, // Back
+    4, 0, 3, 3, 7, 4, // Left
+    1, 5, 6, 6, 2, 1, // Right
+    3, 2, 6, 6, 7, 3, // Top
+    4, 5, 1, 1, 0, 4, // Bottom
+];
+
+/// The normals of the faces of the cube.
+static FACE_NORMALS: &[[f32; 3]] = &[
+    [0.0, 0.0, 1.0],
+    [0.0, 0.0, -1.0],
+    [-1.0, 0.0, 0.0],
+    [1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0],
+    [0.0, -1.0, 0.0],
+];
+
+fn normals(bytes: &[u8]) -> Vec<[f32; 3]> {
+    bytes
+        .chunks_exact(12)
+        .map(|v| {
+            std::array::from_fn(|i| {
+                f32::from_le_bytes(v[i * 4..(i + 1) * 4].try_into().unwrap())
+            })
+        })
+        .collect()
+}
+
+fn assert_close(
+    a: &[[f32; 3]],
+    b: &[[f32; 3]],
+) {
+    assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter().zip(b) {
+        assert!((0..3).all(|i| (a[i] - b[i]).abs() < 1e-6), "{a:?} != {b:?}");
+    }
+}
+
+#[test]
+fn flat_normals_of_a_cube() {
+    let soup: Vec<[f32; 3]> = WOUND_CUBE_INDICES
+        .iter()
+        .map(|i| CUBE_POSITIONS[*i as usize])
+        .collect();
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&soup).unwrap();
+    let expected: Vec<[f32; 3]> =
+        (0..36).map(|v| FACE_NORMALS[v / 6]).collect();
+    for mode in [NormalMode::Flat, NormalMode::Smooth] {
+        // Without shared vertices, smooth normals are flat too
+        let generated = generate_normals(&mesh.as_mesh_ref(), mode).unwrap();
+        assert_close(&normals(&generated), &expected);
+    }
+}
+
+#[test]
+fn smooth_normals_of_a_cube() {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(CUBE_POSITIONS)
+        .unwrap()
+        .set_indices_u16(WOUND_CUBE_INDICES);
+    // All triangles have the same area, so each face counts once for each
+    // of its triangles using the vertex.
+    let mut sums = [[0.0f32; 3]; 8];
+    for (t, triangle) in WOUND_CUBE_INDICES.chunks_exact(3).enumerate() {
+        for i in triangle {
+            let n = FACE_NORMALS[t / 2];
+            sums[*i as usize] =
+                std::array::from_fn(|k| sums[*i as usize][k] + n[k]);
+        }
+    }
+    let expected: Vec<[f32; 3]> = sums
+        .iter()
+        .map(|s| {
+            let len = s.iter().map(|c| c * c).sum::<f32>().sqrt();
+            s.map(|c| c / len)
+        })
+        .collect();
+    let generated =
+        generate_normals(&mesh.as_mesh_ref(), NormalMode::Smooth).unwrap();
+    let generated = normals(&generated);
+    assert_close(&generated, &expected);
+    // Every normal points away from the center
+    for (n, p) in generated.iter().zip(CUBE_POSITIONS) {
+        assert!((0..3).all(|i| n[i] * p[i] > 0.0), "{n:?} at {p:?}");
+    }
+}
+
+#[test]
+fn degenerate_triangles_are_skipped() {
+    let mut positions = CUBE_POSITIONS.to_vec();
+    // Unused by any proper triangle
+    positions.push([5.0, 5.0, 5.0]);
+    let mut indices = WOUND_CUBE_INDICES.to_vec();
+    indices.extend([0, 0, 1, 8, 8, 8]);
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&positions).unwrap().set_indices_u16(&indices);
+    for mode in [NormalMode::Flat, NormalMode::Smooth] {
+        let generated = generate_normals(&mesh.as_mesh_ref(), mode).unwrap();
+        let generated = normals(&generated);
+        assert!(generated[..8].iter().all(|n| n != &[0.0; 3]));
+        assert_eq!(generated[8], [0.0; 3]);
+    }
+}
+
+#[test]
+fn invert_normals_of_a_cube() {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(CUBE_POSITIONS)
+        .unwrap()
+        .set_indices_u16(WOUND_CUBE_INDICES);
+    let smooth =
+        generate_normals(&mesh.as_mesh_ref(), NormalMode::Smooth).unwrap();
+    let tangents: Vec<[f32; 4]> =
+        (0..8).map(|v| [1.0, 0.0, 0.0, [1.0, -1.0][v % 2]]).collect();
+    mesh.set_attribute_bytes(
+        VertexUsage::Normal,
+        VertexFormat::Float32x3,
+        &smooth,
+    )
+    .unwrap()
+    .set_tangents(&tangents)
+    .unwrap();
+    let mesh = mesh.as_mesh_ref();
+
+    let inverted = invert_normals(&mesh).unwrap();
+    let inverted = inverted.as_mesh_ref();
+    let expected: Vec<[f32; 3]> =
+        normals(&smooth).iter().map(|n| n.map(|c| -c)).collect();
+    assert_eq!(inverted.normals_f32().unwrap().collect::<Vec<_>>(), expected);
+    let expected: Vec<[f32; 4]> =
+        tangents.iter().map(|[x, y, z, w]| [*x, *y, *z, -w]).collect();
+    assert_eq!(
+        inverted
+            .attribute_f32::<4>(VertexUsage::Tangent)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        expected
+    );
+    assert_eq!(
+        inverted.attributes[&VertexUsage::Position],
+        mesh.attributes[&VertexUsage::Position]
+    );
+    assert_eq!(inverted.indices, mesh.indices);
+
+    // Inverting twice gives back the exact same data
+    let back = invert_normals(&inverted).unwrap();
+    assert_eq!(back.as_mesh_ref().attributes, mesh.attributes);
+}
+
+#[test]
+fn invert_snorm_normals() {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&CUBE_POSITIONS[..2])
+        .unwrap()
+        .set_attribute_bytes(
+            VertexUsage::Normal,
+            VertexFormat::Snorm8x4,
+            &[0, 127, 0, 0, 91, 0, 165, 0],
+        )
+        .unwrap();
+    let inverted = invert_normals(&mesh.as_mesh_ref()).unwrap();
+    assert_eq!(
+        inverted.as_mesh_ref().attributes[&VertexUsage::Normal],
+        (VertexFormat::Snorm8x4, &[0, 129, 0, 0, 165, 0, 91, 0][..])
+    );
+}
+
+#[test]
+fn invert_normals_errors() {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(CUBE_POSITIONS).unwrap();
+    assert!(matches!(
+        invert_normals(&mesh.as_mesh_ref()),
+        Err(MeshError::NoNormals)
+    ));
+    mesh.set_attribute_bytes(
+        VertexUsage::Normal,
+        VertexFormat::Unorm8x4,
+        &[255; 32],
+    )
+    .unwrap();
+    assert!(matches!(
+        invert_normals(&mesh.as_mesh_ref()),
+        Err(MeshError::NotSigned {
+            usage: VertexUsage::Normal,
+            format: VertexFormat::Unorm8x4,
+        })
+    ));
+}
@@ -0,0 +1,149 @@
+use std::io::Cursor;
+
+use iyes_mesh::HashMap;
+use iyes_mesh::descriptor::{AttributeEncoding, VertexFormat, VertexUsage};
+use iyes_mesh::mesh::MeshDataRef;
+use iyes_mesh::mesh::encode::{
+    oct_decode_normal, oct_decode_tangent, oct_encode_normal,
+    oct_encode_tangent,
+};
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+
+mod common;
+use common::*;
+
+/// Directions spread evenly over the sphere, plus the axes and diagonals,
+/// where the encoding folds.
+fn sphere_directions() -> Vec<[f32; 3]> {
+    let n = 20_000;
+    let golden = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+    let mut r: Vec<_> = (0..n)
+        .map(|i| {
+            let y = 1.0 - 2.0 * (i as f32 + 0.5) / n as f32;
+            let radius = (1.0 - y * y).sqrt();
+            let theta = golden * i as f32;
+            [radius * theta.cos(), y, radius * theta.sin()]
+        })
+        .collect();
+    for x in [-1.0f32, 0.0, 1.0] {
+        for y in [-1.0f32, 0.0, 1.0] {
+            for z in [-1.0f32, 0.0, 1.0] {
+                let len = (x * x + y * y + z * z).sqrt();
+                if len > 0.0 {
+                    r.push([x / len, y / len, z / len]);
+                }
+            }
+        }
+    }
+    r
+}
+
+fn angle_degrees(
+    a: [f32; 3],
+    b: [f32; 3],
+) -> f32 {
+    let dot = a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+    dot.clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+#[test]
+fn normal_precision() {
+    let max_error = sphere_directions()
+        .into_iter()
+        .map(|v| angle_degrees(v, oct_decode_normal(oct_encode_normal(v))))
+        .fold(0.0, f32::max);
+    assert!(max_error < 0.5, "{max_error}");
+}
+
+#[test]
+fn tangent_precision_and_handedness() {
+    for w in [1.0, -1.0] {
+        let mut max_error = 0.0f32;
+        for [x, y, z] in sphere_directions() {
+            let decoded = oct_decode_tangent(oct_encode_tangent([x, y, z, w]));
+            assert_eq!(decoded[3], w);
+            let angle =
+                angle_degrees([x, y, z], [decoded[0], decoded[1], decoded[2]]);
+            max_error = max_error.max(angle);
+        }
+        assert!(max_error < 0.5, "{max_error}");
+    }
+}
+
+#[test]
+fn write_encoded_normals_and_tangents() {
+    let directions = sphere_directions();
+    let normals: Vec<u8> =
+        directions.iter().flatten().flat_map(|c| c.to_le_bytes()).collect();
+    let tangents: Vec<u8> = directions
+        .iter()
+        .enumerate()
+        .flat_map(|(i, [x, y, z])| {
+            let w = if i % 3 == 0 {
+                -1.0f32
+            } else {
+                1.0
+            };
+            [*x, *y, *z, w]
+        })
+        .flat_map(|c| c.to_le_bytes())
+        .collect();
+    let positions = vec![0; directions.len() * 12];
+    let mut attributes = HashMap::default();
+    attributes.insert(
+        VertexUsage::Position,
+        (VertexFormat::Float32x3, positions.as_slice()),
+    );
+    attributes.insert(
+        VertexUsage::Normal,
+        (VertexFormat::Float32x3, normals.as_slice()),
+    );
+    attributes.insert(
+        VertexUsage::Tangent,
+        (VertexFormat::Float32x4, tangents.as_slice()),
+    );
+    let mut writer =
+        IyesMeshWriter::new_with_settings(IyesMeshWriterSettings {
+            octahedral_normals: true,
+            octahedral_tangents: true,
+            ..Default::default()
+        });
+    writer
+        .add_mesh(MeshDataRef {
+            indices: None,
+            attributes,
+        })
+        .unwrap();
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+
+    let with_data = decode(out.get_ref());
+    let descriptor = with_data.descriptor();
+    assert_eq!(
+        descriptor.attribute_encodings[&VertexUsage::Normal],
+        AttributeEncoding::Octahedral
+    );
+    assert_eq!(
+        descriptor.attribute_encodings[&VertexUsage::Tangent],
+        AttributeEncoding::OctahedralSigned
+    );
+    for usage in [VertexUsage::Normal, VertexUsage::Tangent] {
+        assert_eq!(descriptor.attributes[&usage], VertexFormat::Snorm16x2);
+    }
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let split = with_data.into_split_meshes(&buffers).unwrap();
+    let decoded_normals = with_data.decode_normals_f32(&split, 0).unwrap();
+    let decoded_tangents = with_data.decode_tangents_f32(&split, 0).unwrap();
+    // Encoded data is not decoded as plain floats
+    assert!(
+        with_data
+            .decode_attribute_f32::<2>(&split, 0, VertexUsage::Normal)
+            .is_none()
+    );
+    for (i, v) in directions.iter().enumerate() {
+        assert!(angle_degrees(*v, decoded_normals[i]) < 0.5);
+        let [x, y, z, w] = decoded_tangents[i];
+        assert!(angle_degrees(*v, [x, y, z]) < 0.5);
+        assert_eq!(w < 0.0, i % 3 == 0);
+    }
+}
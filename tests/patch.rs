@@ -0,0 +1,121 @@
+use std::io::Cursor;
+
+use iyes_mesh::descriptor::{VertexFormat, VertexUsage};
+use iyes_mesh::patch::{Patch, PatchError, apply, diff};
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings};
+
+mod common;
+use common::*;
+
+/// UVs for the vertices of a grid mesh, scaled by `scale`.
+fn grid_uvs(
+    mesh: &TestMesh,
+    scale: f32,
+) -> Vec<u8> {
+    mesh.positions
+        .chunks_exact(12)
+        .flat_map(|v| {
+            let c = |i: usize| {
+                f32::from_le_bytes(v[i..(i + 4)].try_into().unwrap())
+            };
+            [c(0) * scale, c(8) * scale]
+        })
+        .flat_map(|c| c.to_le_bytes())
+        .collect()
+}
+
+fn encode_with_uvs(
+    meshes: &[TestMesh],
+    uvs: &[Vec<u8>],
+    settings: IyesMeshWriterSettings,
+) -> Vec<u8> {
+    let mut writer = IyesMeshWriter::new_with_settings(settings);
+    for (mesh, uvs) in meshes.iter().zip(uvs) {
+        let mut mesh = mesh.as_ref();
+        mesh.attributes
+            .insert(VertexUsage::Uv0, (VertexFormat::Float32x2, uvs));
+        writer.add_mesh(mesh).unwrap();
+    }
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+    out.into_inner()
+}
+
+fn round_trip(
+    old: &[u8],
+    new: &[u8],
+    compression_level: Option<i32>,
+) -> (Patch, usize) {
+    let patch = diff(old, new, compression_level).unwrap();
+    let bytes = patch.to_bytes().unwrap();
+    let patch = Patch::from_bytes(&bytes).unwrap();
+    assert!(apply(old, &patch).unwrap() == new);
+    (patch, bytes.len())
+}
+
+#[test]
+fn patch_changed_uvs() {
+    let meshes: Vec<_> = (0..50).map(|seed| TestMesh::grid(12, seed)).collect();
+    let mut uvs: Vec<_> = meshes.iter().map(|m| grid_uvs(m, 0.1)).collect();
+    for settings in [
+        IyesMeshWriterSettings::default(),
+        IyesMeshWriterSettings::fastest(),
+    ] {
+        let old = encode_with_uvs(&meshes, &uvs, settings);
+        uvs[17] = grid_uvs(&meshes[17], 0.2);
+        let new = encode_with_uvs(&meshes, &uvs, settings);
+        uvs[17] = grid_uvs(&meshes[17], 0.1);
+
+        let (patch, len) = round_trip(&old, &new, None);
+        assert!(!patch.is_verbatim());
+        assert!(len * 20 < new.len(), "{len} of {}", new.len());
+    }
+}
+
+#[test]
+fn patch_added_and_removed_meshes() {
+    let meshes = test_meshes();
+    let old = encode(&meshes, Default::default());
+    let new = encode(&meshes[1..], Default::default());
+    round_trip(&old, &new, None);
+    round_trip(&new, &old, None);
+    // Nothing changed
+    let (_, len) = round_trip(&old, &old, None);
+    assert!(len * 10 < old.len(), "{len} of {}", old.len());
+}
+
+/// Files written with another compression level need it to be given, or
+/// the patch stores all of the compressed data.
+#[test]
+fn patch_with_compression_level() {
+    let meshes = test_meshes();
+    let old = encode(&meshes, Default::default());
+    let settings = IyesMeshWriterSettings {
+        compression_level: 7,
+        ..Default::default()
+    };
+    let new = encode(&meshes[..2], settings);
+    let (patch, _) = round_trip(&old, &new, None);
+    assert!(patch.is_verbatim());
+    let (patch, _) = round_trip(&old, &new, Some(7));
+    assert!(!patch.is_verbatim());
+}
+
+#[test]
+fn patch_errors() {
+    let meshes = test_meshes();
+    let old = encode(&meshes, Default::default());
+    let new = encode(&meshes[..1], Default::default());
+    let patch = diff(&old, &new, None).unwrap();
+    assert!(matches!(apply(&new, &patch), Err(PatchError::WrongBase)));
+
+    let mut bytes = patch.to_bytes().unwrap();
+    bytes[4] = 99;
+    assert!(matches!(
+        Patch::from_bytes(&bytes),
+        Err(PatchError::BadVersion(99))
+    ));
+    bytes[0] = b'X';
+    assert!(matches!(Patch::from_bytes(&bytes), Err(PatchError::BadMagic)));
+    assert!(matches!(diff(&old, &new[..40], None), Err(PatchError::Read(_))));
+}
@@ -0,0 +1,138 @@
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use iyes_mesh::header::IyesMeshHeader;
+use iyes_mesh::read::{IyesMeshReader, ReadPhase, ReadProgress};
+use iyes_mesh::write::{IyesMeshWriterSettings, WritePhase, WriteProgress};
+
+mod common;
+use common::*;
+
+fn write_with_progress(
+    meshes: &[TestMesh],
+    settings: IyesMeshWriterSettings,
+) -> (Vec<u8>, Vec<WriteProgress>) {
+    let events = Arc::new(Mutex::new(vec![]));
+    let mut writer = writer_for(meshes, settings);
+    let sink = events.clone();
+    writer.set_progress_callback(Box::new(move |progress| {
+        sink.lock().unwrap().push(progress);
+    }));
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+    let events = events.lock().unwrap().clone();
+    (out.into_inner(), events)
+}
+
+/// Size of the uncompressed payload of the file of the meshes.
+fn payload_len(meshes: &[TestMesh]) -> u64 {
+    meshes
+        .iter()
+        .map(|m| (m.positions.len() + m.normals.len() + m.indices.len()) as u64)
+        .sum()
+}
+
+#[test]
+fn write_progress_is_monotonic() {
+    let meshes = large_meshes();
+    let total = payload_len(&meshes);
+    for write_data_checksum in [true, false] {
+        let settings = IyesMeshWriterSettings {
+            write_data_checksum,
+            ..IyesMeshWriterSettings::fastest()
+        };
+        let (_, events) = write_with_progress(&meshes, settings);
+        assert_eq!(events[0].phase, WritePhase::Scanning);
+        for pair in events.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert!(a.phase as u8 <= b.phase as u8, "{a:?} then {b:?}");
+            if a.phase == b.phase {
+                assert!(a.bytes_processed <= b.bytes_processed);
+            }
+        }
+        let compressing: Vec<_> = events
+            .iter()
+            .filter(|e| e.phase == WritePhase::Compressing)
+            .collect();
+        assert!(compressing.len() > 3, "{}", compressing.len());
+        assert!(compressing.iter().all(|e| e.bytes_total == total));
+        assert_eq!(
+            events.iter().any(|e| e.phase == WritePhase::Checksumming),
+            write_data_checksum
+        );
+        let last = events.last().unwrap();
+        assert_eq!(last.phase, WritePhase::Done);
+        assert_eq!(last.bytes_processed, total);
+        assert_eq!(last.bytes_total, total);
+    }
+}
+
+#[test]
+fn progress_callback_does_not_change_output() {
+    let meshes = test_meshes();
+    let (bytes, events) = write_with_progress(&meshes, Default::default());
+    assert!(!events.is_empty());
+    assert!(bytes == encode(&meshes, Default::default()));
+}
+
+fn read_with_progress(bytes: &[u8]) -> Vec<ReadProgress> {
+    let events = Arc::new(Mutex::new(vec![]));
+    let mut read = Cursor::new(bytes);
+    let mut reader = IyesMeshReader::init(&mut read).unwrap();
+    let sink = events.clone();
+    reader.set_progress_callback(Box::new(move |progress| {
+        sink.lock().unwrap().push(progress);
+    }));
+    reader.read_all_data().unwrap();
+    events.lock().unwrap().clone()
+}
+
+#[test]
+fn read_progress_is_monotonic() {
+    let meshes = large_meshes();
+    for write_data_checksum in [true, false] {
+        let settings = IyesMeshWriterSettings {
+            write_data_checksum,
+            ..IyesMeshWriterSettings::fastest()
+        };
+        let bytes = encode(&meshes, settings);
+        let header_len = IyesMeshHeader::encoded_len();
+        let header = IyesMeshHeader::from_bytes(&bytes[..header_len]).unwrap();
+        let compressed_len =
+            (bytes.len() - header_len - header.descriptor_len as usize) as u64;
+        let decompressed_len = payload_len(&meshes);
+
+        let events = read_with_progress(&bytes);
+        for pair in events.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert!(a.phase as u8 <= b.phase as u8, "{a:?} then {b:?}");
+            if a.phase == b.phase {
+                assert!(a.compressed_bytes <= b.compressed_bytes);
+                assert!(a.decompressed_bytes <= b.decompressed_bytes);
+            }
+        }
+        let checksumming: Vec<_> = events
+            .iter()
+            .filter(|e| e.phase == ReadPhase::Checksumming)
+            .collect();
+        assert_eq!(!checksumming.is_empty(), write_data_checksum);
+        if let Some(last) = checksumming.last() {
+            assert_eq!(last.compressed_bytes, compressed_len);
+        }
+        let decompressing: Vec<_> = events
+            .iter()
+            .filter(|e| e.phase == ReadPhase::Decompressing)
+            .collect();
+        assert!(decompressing.len() > 2, "{}", decompressing.len());
+        assert!(
+            decompressing
+                .iter()
+                .all(|e| e.decompressed_total == decompressed_len
+                    && e.compressed_total == compressed_len)
+        );
+        let last = decompressing.last().unwrap();
+        assert_eq!(last.decompressed_bytes, decompressed_len);
+        assert!(last.compressed_bytes <= compressed_len);
+        assert_eq!(events.last().unwrap().phase, ReadPhase::Done);
+    }
+}
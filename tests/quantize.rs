@@ -0,0 +1,68 @@
+use iyes_mesh::descriptor::{VertexFormat, VertexUsage};
+use iyes_mesh::write::IyesMeshWriterSettings;
+
+mod common;
+use common::*;
+
+fn positions(mesh: &TestMesh) -> Vec<[f32; 3]> {
+    mesh.positions
+        .chunks_exact(12)
+        .map(|v| {
+            std::array::from_fn(|i| {
+                f32::from_le_bytes(v[(i * 4)..(i * 4 + 4)].try_into().unwrap())
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn quantized_positions_error_is_bounded() {
+    let meshes = test_meshes();
+    let settings = IyesMeshWriterSettings {
+        quantize_positions: true,
+        ..Default::default()
+    };
+    let bytes = encode(&meshes, settings);
+    assert!(bytes.len() < encode(&meshes, Default::default()).len());
+
+    let with_data = decode(&bytes);
+    let descriptor = with_data.descriptor();
+    assert_eq!(
+        descriptor.attributes[&VertexUsage::Position],
+        VertexFormat::Unorm16x4
+    );
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let split = with_data.into_split_meshes(&buffers).unwrap();
+    for (i, mesh) in meshes.iter().enumerate() {
+        let original = positions(mesh);
+        let decoded = with_data.decode_positions_f32(&split, i).unwrap();
+        assert_eq!(decoded.len(), original.len());
+        let transform = descriptor.meshes[i].position_transform.unwrap();
+        for c in 0..3 {
+            let min = original.iter().map(|p| p[c]).fold(f32::MAX, f32::min);
+            let max = original.iter().map(|p| p[c]).fold(f32::MIN, f32::max);
+            assert_eq!(transform.offset[c], min);
+            let bound = (max - min) / 65535.0 + f32::EPSILON * max.abs();
+            for (d, o) in decoded.iter().zip(&original) {
+                assert!((d[c] - o[c]).abs() <= bound, "{d:?} vs {o:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn unquantized_positions_have_no_transform() {
+    let meshes = test_meshes();
+    let with_data = decode(&encode(&meshes, Default::default()));
+    assert!(
+        with_data
+            .descriptor()
+            .meshes
+            .iter()
+            .all(|m| m.position_transform.is_none())
+    );
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let split = with_data.into_split_meshes(&buffers).unwrap();
+    let decoded = with_data.decode_positions_f32(&split, 1).unwrap();
+    assert_eq!(decoded, positions(&meshes[1]));
+}
@@ -0,0 +1,353 @@
+use std::io::{Cursor, Read, Write};
+
+use iyes_mesh::HashMap;
+use iyes_mesh::checksum::{checksum_data, checksum_metadata};
+use iyes_mesh::descriptor::{
+    IndexFormat, MeshRangeError, VertexFormat, VertexUsage,
+};
+use iyes_mesh::header::IyesMeshHeader;
+use iyes_mesh::io::{new_zstd_decoder, new_zstd_encoder};
+use iyes_mesh::mesh::MeshDataRef;
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings, ReadError};
+use iyes_mesh::write::IyesMeshWriter;
+
+mod common;
+use common::*;
+
+fn positions_bytes(positions: &[[f32; 3]]) -> Vec<u8> {
+    positions.iter().flatten().flat_map(|c| c.to_le_bytes()).collect()
+}
+
+fn indices_bytes(indices: &[u16]) -> Vec<u8> {
+    indices.iter().flat_map(|i| i.to_le_bytes()).collect()
+}
+
+/// The vertices of each mesh must be sliced with the size of the vertex
+/// format, not of the index format (they differ here: 12 and 2 bytes).
+#[test]
+fn split_indexed_meshes_with_vertex_offsets() {
+    let first =
+        positions_bytes(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+    let second =
+        positions_bytes(&[[5.0, 5.0, 5.0], [6.0, 5.0, 5.0], [5.0, 6.0, 5.0]]);
+    let indices = indices_bytes(&[0, 1, 2]);
+
+    let mut writer = IyesMeshWriter::new();
+    for positions in [&first, &second] {
+        let mut attributes = HashMap::default();
+        attributes.insert(
+            VertexUsage::Position,
+            (VertexFormat::Float32x3, positions.as_slice()),
+        );
+        let mesh = MeshDataRef {
+            indices: Some((IndexFormat::U16, indices.as_slice())),
+            attributes,
+        };
+        writer.add_mesh(mesh).unwrap();
+    }
+    let mut file = Cursor::new(vec![]);
+    writer.write_to(&mut file).unwrap();
+
+    file.set_position(0);
+    let reader = IyesMeshReader::init(&mut file).unwrap();
+    let with_data = reader.read_all_data().unwrap();
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let meshes = with_data.into_split_meshes(&buffers).unwrap();
+    assert_eq!(meshes.meshes.len(), 2);
+    for (mesh, positions) in meshes.meshes.iter().zip([&first, &second]) {
+        let (format, data) = mesh.attributes[&VertexUsage::Position];
+        assert_eq!(format, VertexFormat::Float32x3);
+        assert_eq!(data, positions.as_slice());
+        assert_eq!(mesh.indices, Some((IndexFormat::U16, indices.as_slice())));
+    }
+}
+
+/// A descriptor declaring far more data than the file holds must not
+/// make the reader allocate all of it.
+#[test]
+fn huge_declared_sizes() {
+    let bytes = encode(&test_meshes(), Default::default());
+    let bytes = with_descriptor(&bytes, |d| {
+        d.n_vertices = u32::MAX;
+        d.meshes[0].vertex_count = u32::MAX;
+    });
+    let with_data = decode(&bytes);
+    assert!(matches!(
+        with_data.into_flat_buffers(),
+        Err(ReadError::NotEnoughData)
+    ));
+}
+
+/// Random positions, which barely compress.
+fn noise_positions(
+    n_vertices: usize,
+    seed: u64,
+) -> Vec<u8> {
+    let mut rng = seed;
+    let mut r = vec![];
+    for _ in 0..(n_vertices * 3) {
+        rng = rng
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let c = (rng >> 40) as f32 / (1 << 24) as f32;
+        r.extend_from_slice(&c.to_le_bytes());
+    }
+    r
+}
+
+#[test]
+fn recover_truncated_file() {
+    const N_MESHES: usize = 4;
+    // 360 KB per mesh, more than a zstd block
+    const N_VERTICES: usize = 30000;
+    let meshes: Vec<_> =
+        (0..N_MESHES).map(|i| noise_positions(N_VERTICES, i as u64)).collect();
+    let mut writer = IyesMeshWriter::new();
+    for positions in meshes.iter() {
+        let mut attributes = HashMap::default();
+        attributes.insert(
+            VertexUsage::Position,
+            (VertexFormat::Float32x3, positions.as_slice()),
+        );
+        writer
+            .add_mesh(MeshDataRef {
+                indices: None,
+                attributes,
+            })
+            .unwrap();
+    }
+    let mut file = Cursor::new(vec![]);
+    writer.write_to(&mut file).unwrap();
+    let bytes = file.into_inner();
+    let header =
+        IyesMeshHeader::from_bytes(&bytes[..IyesMeshHeader::encoded_len()])
+            .unwrap();
+    let data_start =
+        IyesMeshHeader::encoded_len() + header.descriptor_len as usize;
+    let compressed_len = bytes.len() - data_start;
+
+    let mut salvaged = vec![];
+    for eighths in 0..=8 {
+        let end = data_start + compressed_len * eighths / 8;
+        let mut read = Cursor::new(&bytes[..end]);
+        let (with_data, recovery) =
+            IyesMeshReader::init(&mut read).unwrap().recover_data().unwrap();
+        let intact: Vec<_> = recovery.intact_meshes().collect();
+        let expected = recovery.recovered_len as usize / meshes[0].len();
+        assert_eq!(intact, (0..expected).collect::<Vec<_>>());
+        assert_eq!(recovery.is_complete(), eighths == 8);
+        assert_eq!(recovery.error.is_none(), eighths == 8);
+        let buffers = with_data.into_flat_buffers().unwrap();
+        let decoded = with_data.into_split_meshes(&buffers).unwrap();
+        for i in intact {
+            let (_, data) =
+                decoded.meshes[i].attributes[&VertexUsage::Position];
+            assert!(data == meshes[i].as_slice());
+        }
+        salvaged.push(expected);
+    }
+    assert_eq!(salvaged[0], 0);
+    assert!(salvaged[4] > 0 && salvaged[4] < N_MESHES);
+    assert_eq!(salvaged[8], N_MESHES);
+    assert!(salvaged.is_sorted());
+}
+
+/// Missing data is filled with zeros, but not as much as a corrupted
+/// descriptor may declare.
+#[test]
+fn recover_huge_declared_sizes() {
+    let bytes = encode(&test_meshes(), Default::default());
+    let bytes = with_descriptor(&bytes, |d| {
+        d.n_vertices = u32::MAX;
+        d.meshes[0].vertex_count = u32::MAX;
+    });
+    let mut read = Cursor::new(&bytes);
+    let (with_data, recovery) =
+        IyesMeshReader::init(&mut read).unwrap().recover_data().unwrap();
+    assert!(!recovery.is_complete());
+    assert_eq!(recovery.intact_meshes().count(), 0);
+    assert!(matches!(
+        with_data.into_flat_buffers(),
+        Err(ReadError::NotEnoughData)
+    ));
+}
+
+/// `data/v1.ima` was written by the first release of the library (format
+/// version 1). It has two meshes (a quad and a triangle) with positions,
+/// normals, UVs and colors, and some user data.
+#[test]
+fn read_version_1_file() {
+    let bytes = include_bytes!("data/v1.ima");
+    let mut read = Cursor::new(&bytes[..]);
+    let reader = IyesMeshReader::init(&mut read).unwrap();
+    assert_eq!({ reader.header().version }, 1);
+    let with_data = reader.read_all_data().unwrap();
+    let buffers = with_data.into_flat_buffers().unwrap();
+    assert_eq!(buffers.user_data, Some(&b"v1 user data"[..]));
+    let meshes = with_data.into_split_meshes(&buffers).unwrap();
+    let indices: [&[u16]; 2] = [&[0, 1, 2, 0, 2, 3], &[0, 1, 2]];
+    assert_eq!(meshes.meshes.len(), 2);
+    for (m, mesh) in meshes.meshes.iter().enumerate() {
+        let n_vertices = indices[m].len().min(4);
+        let mut positions = vec![];
+        let mut normals = vec![];
+        let mut uvs = vec![];
+        let mut colors = vec![];
+        for v in 0..n_vertices {
+            let x = (m * 10 + v) as f32;
+            positions.extend([x, x * 2.0, x * 3.0]);
+            normals.extend([0.0f32, 0.0, 1.0]);
+            uvs.extend([x / 10.0, 1.0 - x / 10.0]);
+            colors.extend([(m * 10 + v) as u8, 255, 0, 128]);
+        }
+        let f32_bytes = |v: Vec<f32>| -> Vec<u8> {
+            v.iter().flat_map(|c| c.to_le_bytes()).collect()
+        };
+        let expected = [
+            (
+                VertexUsage::Position,
+                VertexFormat::Float32x3,
+                f32_bytes(positions),
+            ),
+            (VertexUsage::Normal, VertexFormat::Float32x3, f32_bytes(normals)),
+            (VertexUsage::Uv0, VertexFormat::Float32x2, f32_bytes(uvs)),
+            (VertexUsage::Color, VertexFormat::Unorm8x4, colors),
+        ];
+        assert_eq!(mesh.attributes.len(), expected.len());
+        for (usage, format, bytes) in expected {
+            assert_eq!(mesh.attributes[&usage], (format, bytes.as_slice()));
+        }
+        let index_bytes: Vec<u8> =
+            indices[m].iter().flat_map(|i| i.to_le_bytes()).collect();
+        assert_eq!(
+            mesh.indices,
+            Some((IndexFormat::U16, index_bytes.as_slice()))
+        );
+    }
+}
+
+#[test]
+fn unknown_checksum_kind() {
+    let bytes = encode(&test_meshes(), Default::default());
+    let bytes = with_descriptor(&bytes, |d| d.checksum_kind = 200);
+    let mut read = Cursor::new(&bytes);
+    let reader = IyesMeshReader::init(&mut read).unwrap();
+    assert!(matches!(
+        reader.read_all_data(),
+        Err(ReadError::UnsupportedChecksum(200))
+    ));
+}
+
+/// Append `extra` to the payload of a file, keeping its checksums valid.
+fn with_trailing_data(
+    bytes: &[u8],
+    extra: &[u8],
+) -> Vec<u8> {
+    let header_len = IyesMeshHeader::encoded_len();
+    let mut header = IyesMeshHeader::from_bytes(&bytes[..header_len]).unwrap();
+    let data_start = header_len + header.descriptor_len as usize;
+    let mut payload = vec![];
+    new_zstd_decoder(&bytes[data_start..])
+        .unwrap()
+        .read_to_end(&mut payload)
+        .unwrap();
+    payload.extend_from_slice(extra);
+    let mut encoder =
+        new_zstd_encoder(vec![], 3, payload.len() as u64).unwrap();
+    encoder.write_all(&payload).unwrap();
+    let compressed = encoder.finish().unwrap();
+    header.data_checksum = checksum_data(&compressed);
+    header.metadata_checksum =
+        checksum_metadata(header, &bytes[header_len..data_start]);
+    let mut r = header.as_bytes().to_vec();
+    r.extend_from_slice(&bytes[header_len..data_start]);
+    r.extend(compressed);
+    r
+}
+
+#[test]
+fn trailing_data() {
+    let meshes = test_meshes();
+    let bytes = encode(&meshes, Default::default());
+    let extra: Vec<u8> = (100..116).collect();
+    let bytes = with_trailing_data(&bytes, &extra);
+
+    // Strict by default, to catch corrupted files
+    let with_data = decode(&bytes);
+    assert!(matches!(
+        with_data.into_flat_buffers(),
+        Err(ReadError::TooMuchData)
+    ));
+    assert_eq!(with_data.trailing_data(), extra);
+
+    let settings = IyesMeshReaderSettings {
+        allow_trailing_data: true,
+        ..Default::default()
+    };
+    let mut read = Cursor::new(&bytes);
+    let with_data = IyesMeshReader::init_with_settings(settings, &mut read)
+        .unwrap()
+        .read_all_data()
+        .unwrap();
+    assert_eq!(with_data.trailing_data(), extra);
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let decoded = with_data.into_split_meshes(&buffers).unwrap();
+    assert_eq!(decoded.meshes.len(), meshes.len());
+    for (mesh, expected) in decoded.meshes.iter().zip(&meshes) {
+        let (_, positions) = mesh.attributes[&VertexUsage::Position];
+        assert!(positions == expected.positions.as_slice());
+    }
+
+    // Valid files have none
+    let valid = encode(&meshes, Default::default());
+    assert!(decode(&valid).trailing_data().is_empty());
+}
+
+#[test]
+fn check_mesh_ranges() {
+    let bytes = encode(&test_meshes(), Default::default());
+    let with_data = decode(&bytes);
+    assert!(with_data.descriptor().check_mesh_ranges().is_empty());
+
+    // The last mesh has an extra vertex
+    let bytes = with_descriptor(&bytes, |d| {
+        d.meshes[1].first_index = u32::MAX;
+        d.meshes[1].instance_count = 1;
+        d.meshes[2].vertex_count += 1;
+    });
+    let with_data = decode(&bytes);
+    let descriptor = with_data.descriptor();
+    let n_vertices = descriptor.n_vertices;
+    let n_indices = descriptor.indices.unwrap().n_indices;
+    let first_vertex = descriptor.meshes[2].first_vertex as u64;
+    let index_count = descriptor.meshes[1].index_count as u64;
+    assert_eq!(
+        descriptor.check_mesh_ranges(),
+        [
+            (
+                1,
+                MeshRangeError::Indices {
+                    start: u32::MAX as u64,
+                    end: u32::MAX as u64 + index_count,
+                    total: n_indices,
+                }
+            ),
+            (
+                1,
+                MeshRangeError::Instances {
+                    start: 0,
+                    end: 1,
+                    total: 0,
+                }
+            ),
+            (
+                2,
+                MeshRangeError::Vertices {
+                    start: first_vertex,
+                    end: n_vertices as u64 + 1,
+                    total: n_vertices,
+                }
+            ),
+        ]
+    );
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "serde")]
+
+use iyes_mesh::descriptor::{
+    IndexFormat, IyesMeshDescriptor, VertexFormat, VertexUsage,
+};
+use iyes_mesh::write::IyesMeshWriterSettings;
+use serde_json::json;
+
+mod common;
+use common::*;
+
+#[test]
+fn enum_representations() {
+    assert_eq!(
+        serde_json::to_value(VertexUsage::Position).unwrap(),
+        json!("Position")
+    );
+    assert_eq!(
+        serde_json::to_value(VertexUsage::Custom(7)).unwrap(),
+        json!({ "Custom": 7 })
+    );
+    assert_eq!(serde_json::to_value(IndexFormat::U32).unwrap(), json!("U32"));
+    for format in VertexFormat::ALL {
+        let name = format!("{format:?}");
+        let value = serde_json::to_value(format).unwrap();
+        assert_eq!(value, json!(name));
+        let parsed: VertexFormat = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, *format);
+        assert_eq!(name.parse::<VertexFormat>().unwrap(), *format);
+    }
+}
+
+#[test]
+fn descriptor_json_round_trip() {
+    let meshes = test_meshes();
+    let settings = IyesMeshWriterSettings {
+        quantize_positions: true,
+        ..Default::default()
+    };
+    let bytes = encode(&meshes, settings);
+    let with_data = decode(&bytes);
+    let descriptor = with_data.descriptor();
+
+    let value = serde_json::to_value(descriptor).unwrap();
+    assert_eq!(value["attributes"]["Position"], json!("Unorm16x4"));
+    assert_eq!(value["attributes"]["Normal"], json!("Float32x3"));
+    assert_eq!(value["indices"]["format"], json!("U16"));
+    assert_eq!(value["meshes"].as_array().unwrap().len(), meshes.len());
+
+    let parsed: IyesMeshDescriptor = serde_json::from_value(value).unwrap();
+    assert_eq!(parsed.attributes, descriptor.attributes);
+    assert_eq!(parsed.n_vertices, descriptor.n_vertices);
+    assert_eq!(
+        format!("{:?}", parsed.meshes),
+        format!("{:?}", descriptor.meshes)
+    );
+    assert_eq!(
+        format!("{:?}", parsed.indices),
+        format!("{:?}", descriptor.indices)
+    );
+    assert_eq!(parsed.checksum_kind, descriptor.checksum_kind);
+}
+
+#[test]
+fn custom_usages_as_map_keys() {
+    let json = json!({
+        "n_vertices": 3,
+        "user_data_len": 0,
+        "meshes": [],
+        "indices": null,
+        "attributes": { "Position": "Float32x3", "custom:7": "Uint8x4" },
+        "attribute_encodings": {},
+        "color_spaces": {},
+        "n_instances": 0,
+        "instance_attributes": {},
+        "user_data_checksum": null,
+        "user_data_nonce": null,
+        "signed": false,
+        "checksum_kind": 0,
+    });
+    let descriptor: IyesMeshDescriptor = serde_json::from_value(json).unwrap();
+    assert_eq!(
+        descriptor.attributes[&VertexUsage::Custom(7)],
+        VertexFormat::Uint8x4
+    );
+    let mut bad = serde_json::to_value(&descriptor).unwrap();
+    bad["attributes"] = json!({ "Nonsense": "Float32" });
+    assert!(serde_json::from_value::<IyesMeshDescriptor>(bad).is_err());
+}
@@ -0,0 +1,294 @@
+use iyes_mesh::descriptor::{VertexFormat, VertexUsage};
+use iyes_mesh::mesh::{
+    MeshData, MeshError, check_joint_weights, normalize_joint_weights,
+};
+
+/// A mesh of one vertex per element of `weights`, with joints 0, 1, 2, 3.
+fn skinned(
+    format: VertexFormat,
+    weights: &[u8],
+) -> MeshData {
+    let n = weights.len() / format.size();
+    let positions: Vec<[f32; 3]> =
+        (0..n).map(|v| [v as f32, 0.0, 0.0]).collect();
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&positions)
+        .unwrap()
+        .set_attribute_bytes(
+            VertexUsage::JointIndex,
+            VertexFormat::Uint8x4,
+            &[0, 1, 2, 3].repeat(n),
+        )
+        .unwrap()
+        .set_attribute_bytes(VertexUsage::JointWeight, format, weights)
+        .unwrap();
+    mesh
+}
+
+fn float_weights(weights: &[[f32; 4]]) -> MeshData {
+    skinned(VertexFormat::Float32x4, bytemuck::cast_slice(weights))
+}
+
+fn weights(mesh: &MeshData) -> Vec<[f32; 4]> {
+    let mesh = mesh.as_mesh_ref();
+    mesh.attribute_f32::<4>(VertexUsage::JointWeight).unwrap().collect()
+}
+
+#[test]
+fn check_float_weights() {
+    let mesh = float_weights(&[
+        [1.0, 0.0, 0.0, 0.0],
+        [0.25, 0.25, 0.25, 0.25],
+        [0.5, 0.6, 0.0, 0.0],
+        [0.0; 4],
+        [0.7, 0.3, f32::NAN, 0.0],
+        [0.5, 0.499, 0.0, 0.0],
+    ]);
+    let mesh = mesh.as_mesh_ref();
+    assert_eq!(check_joint_weights(&mesh, 0.01).unwrap(), [2, 3, 4]);
+    assert_eq!(check_joint_weights(&mesh, 0.0001).unwrap(), [2, 3, 4, 5]);
+}
+
+#[test]
+fn check_unorm_weights() {
+    let mesh = skinned(
+        VertexFormat::Unorm8x4,
+        &[255, 0, 0, 0, 128, 127, 0, 0, 100, 100, 0, 0],
+    );
+    let mesh = mesh.as_mesh_ref();
+    assert_eq!(check_joint_weights(&mesh, 0.001).unwrap(), [2]);
+
+    let weights: Vec<u8> = [u16::MAX, 0, 0, 0, 30000, 30000, 0, 0]
+        .iter()
+        .flat_map(|w| w.to_le_bytes())
+        .collect();
+    let mesh = skinned(VertexFormat::Unorm16x4, &weights);
+    let mesh = mesh.as_mesh_ref();
+    assert_eq!(check_joint_weights(&mesh, 0.001).unwrap(), [1]);
+}
+
+#[test]
+fn normalize_float_weights() {
+    let mesh = float_weights(&[
+        [0.5, 0.5, 0.5, 0.5],
+        [2.0, -1.0, f32::INFINITY, 2.0],
+        [0.0; 4],
+        [0.25, 0.25, 0.25, 0.25],
+    ]);
+    let normalized = normalize_joint_weights(&mesh.as_mesh_ref()).unwrap();
+    assert_eq!(
+        weights(&normalized),
+        [
+            [0.25, 0.25, 0.25, 0.25],
+            // Negative and infinite weights are dropped
+            [0.5, 0.0, 0.0, 0.5],
+            // All-zero weights go to the first slot
+            [1.0, 0.0, 0.0, 0.0],
+            [0.25, 0.25, 0.25, 0.25],
+        ]
+    );
+    assert!(
+        check_joint_weights(&normalized.as_mesh_ref(), 0.0).unwrap().is_empty()
+    );
+    let (normalized, mesh) = (normalized.as_mesh_ref(), mesh.as_mesh_ref());
+    assert_eq!(
+        normalized.attributes[&VertexUsage::JointIndex],
+        mesh.attributes[&VertexUsage::JointIndex]
+    );
+}
+
+#[test]
+fn duplicate_joints_are_dropped() {
+    let mut mesh =
+        float_weights(&[[0.5, 0.25, 0.25, 0.0], [0.0, 0.5, 0.5, 0.5]]);
+    mesh.set_attribute_bytes(
+        VertexUsage::JointIndex,
+        VertexFormat::Uint8x4,
+        // Joint 4 twice, and joint 7 twice after a zero weight
+        &[4, 2, 4, 3, 7, 7, 1, 0],
+    )
+    .unwrap();
+    let normalized = normalize_joint_weights(&mesh.as_mesh_ref()).unwrap();
+    let third = 1.0 / 3.0;
+    let w = weights(&normalized);
+    assert_eq!(w[0], [2.0 * third, third, 0.0, 0.0]);
+    assert_eq!(w[1], [0.0, third, third, third]);
+}
+
+#[test]
+fn normalize_requantizes_unorm_weights() {
+    let mesh = skinned(
+        VertexFormat::Unorm8x4,
+        &[
+            200, 100, 0, 0, // 2:1
+            100, 60, 40, 0, // 0.5, 0.3, 0.2, with a rounding tie
+            1, 1, 1, 0, // Thirds
+            0, 0, 0, 0,
+        ],
+    );
+    let normalized = normalize_joint_weights(&mesh.as_mesh_ref()).unwrap();
+    let (format, bytes) =
+        normalized.as_mesh_ref().attributes[&VertexUsage::JointWeight];
+    assert_eq!(format, VertexFormat::Unorm8x4);
+    assert_eq!(
+        bytes,
+        [170, 85, 0, 0, 128, 76, 51, 0, 85, 85, 85, 0, 255, 0, 0, 0]
+    );
+
+    let weights: Vec<u8> =
+        [1000u16, 1000, 1000, 0].iter().flat_map(|w| w.to_le_bytes()).collect();
+    let mesh = skinned(VertexFormat::Unorm16x4, &weights);
+    let normalized = normalize_joint_weights(&mesh.as_mesh_ref()).unwrap();
+    let (format, bytes) =
+        normalized.as_mesh_ref().attributes[&VertexUsage::JointWeight];
+    assert_eq!(format, VertexFormat::Unorm16x4);
+    let weights: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|w| u16::from_le_bytes([w[0], w[1]]))
+        .collect();
+    assert_eq!(weights, [21845, 21845, 21845, 0]);
+}
+
+#[test]
+fn normalize_joint_weights_errors() {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&[[0.0; 3]]).unwrap();
+    assert!(matches!(
+        normalize_joint_weights(&mesh.as_mesh_ref()),
+        Err(MeshError::MissingAttribute(VertexUsage::JointWeight))
+    ));
+    let mesh = skinned(VertexFormat::Uint8x4, &[1, 0, 0, 0]);
+    assert!(matches!(
+        normalize_joint_weights(&mesh.as_mesh_ref()),
+        Err(MeshError::UnsupportedFormat {
+            usage: VertexUsage::JointWeight,
+            format: VertexFormat::Uint8x4,
+        })
+    ));
+}
+
+/// Vertices with 8 influences, over two sets of Uint16x4 joints and
+/// Float32x4 weights.
+fn eight_influences(weights: &[[f32; 8]]) -> MeshData {
+    let n = weights.len();
+    let positions: Vec<[f32; 3]> =
+        (0..n).map(|v| [v as f32, 0.0, 0.0]).collect();
+    let joints: Vec<[u16; 8]> =
+        (0..n).map(|v| std::array::from_fn(|k| (v + k * 10) as u16)).collect();
+    let set = |data: &[[f32; 8]], set: usize| -> Vec<[f32; 4]> {
+        data.iter().map(|w| std::array::from_fn(|k| w[set * 4 + k])).collect()
+    };
+    let joint_set = |set: usize| -> Vec<[u16; 4]> {
+        joints.iter().map(|j| std::array::from_fn(|k| j[set * 4 + k])).collect()
+    };
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&positions)
+        .unwrap()
+        .set_attribute_pod(
+            VertexUsage::JointIndex,
+            VertexFormat::Uint16x4,
+            &joint_set(0),
+        )
+        .unwrap()
+        .set_attribute_pod(
+            VertexUsage::JointIndex1,
+            VertexFormat::Uint16x4,
+            &joint_set(1),
+        )
+        .unwrap()
+        .set_attribute_pod(
+            VertexUsage::JointWeight,
+            VertexFormat::Float32x4,
+            &set(weights, 0),
+        )
+        .unwrap()
+        .set_attribute_pod(
+            VertexUsage::JointWeight1,
+            VertexFormat::Float32x4,
+            &set(weights, 1),
+        )
+        .unwrap();
+    mesh
+}
+
+#[test]
+fn weights_sum_over_both_sets() {
+    let mesh = eight_influences(&[
+        [0.125; 8],
+        [0.25, 0.25, 0.25, 0.25, 0.25, 0.0, 0.0, 0.0],
+        [0.5, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0],
+        [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    ]);
+    assert_eq!(check_joint_weights(&mesh.as_mesh_ref(), 0.01).unwrap(), [1, 3]);
+
+    let normalized = normalize_joint_weights(&mesh.as_mesh_ref()).unwrap();
+    let normalized = normalized.as_mesh_ref();
+    assert!(check_joint_weights(&normalized, 0.0).unwrap().is_empty());
+    let first: Vec<[f32; 4]> = normalized
+        .attribute_f32::<4>(VertexUsage::JointWeight)
+        .unwrap()
+        .collect();
+    let second: Vec<[f32; 4]> = normalized
+        .attribute_f32::<4>(VertexUsage::JointWeight1)
+        .unwrap()
+        .collect();
+    assert_eq!(
+        first,
+        [
+            [0.125; 4],
+            [0.2; 4],
+            [0.5, 0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0, 0.0]
+        ]
+    );
+    assert_eq!(
+        second,
+        [
+            [0.125; 4],
+            [0.2, 0.0, 0.0, 0.0],
+            [0.5, 0.0, 0.0, 0.0],
+            [0.0; 4]
+        ]
+    );
+}
+
+#[test]
+fn duplicate_joints_across_sets_are_dropped() {
+    let mut mesh = eight_influences(&[[0.125; 8]]);
+    // Joint 0 again in the second set
+    mesh.set_attribute_pod(
+        VertexUsage::JointIndex1,
+        VertexFormat::Uint16x4,
+        &[[0u16, 50, 60, 70]],
+    )
+    .unwrap();
+    let normalized = normalize_joint_weights(&mesh.as_mesh_ref()).unwrap();
+    let normalized = normalized.as_mesh_ref();
+    let second: Vec<[f32; 4]> = normalized
+        .attribute_f32::<4>(VertexUsage::JointWeight1)
+        .unwrap()
+        .collect();
+    let w = 1.0 / 7.0;
+    assert_eq!(second, [[0.0, w, w, w]]);
+}
+
+#[test]
+fn weight_sets_must_have_the_same_format() {
+    let mut mesh = eight_influences(&[[0.125; 8]]);
+    mesh.set_attribute_bytes(
+        VertexUsage::JointWeight1,
+        VertexFormat::Unorm8x4,
+        &[32; 4],
+    )
+    .unwrap();
+    assert!(matches!(
+        normalize_joint_weights(&mesh.as_mesh_ref()),
+        Err(MeshError::UnsupportedFormat {
+            usage: VertexUsage::JointWeight1,
+            format: VertexFormat::Unorm8x4,
+        })
+    ));
+    // Checking works with any formats
+    let bad = check_joint_weights(&mesh.as_mesh_ref(), 0.01).unwrap();
+    assert!(bad.is_empty(), "{bad:?}");
+}
@@ -0,0 +1,244 @@
+use iyes_mesh::descriptor::VertexUsage;
+use iyes_mesh::mesh::{
+    AppliedTransform, CenterMode, MeshData, MeshDataRef, normalize_scale,
+    recenter, transform,
+};
+
+mod common;
+use common::*;
+
+fn scale(s: [f32; 3]) -> [[f32; 4]; 4] {
+    [
+        [s[0], 0.0, 0.0, 0.0],
+        [0.0, s[1], 0.0, 0.0],
+        [0.0, 0.0, s[2], 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]
+}
+
+fn translation(t: [f32; 3]) -> [[f32; 4]; 4] {
+    let mut m = scale([1.0; 3]);
+    m[3] = [t[0], t[1], t[2], 1.0];
+    m
+}
+
+/// The cube, with normals pointing away from the YZ plane and tangents
+/// along Z.
+fn cube() -> MeshData {
+    let normals: Vec<[f32; 3]> =
+        CUBE_POSITIONS.iter().map(|p| [p[0], 0.0, 0.0]).collect();
+    let mut mesh = MeshData::new();
+    mesh.set_positions(CUBE_POSITIONS)
+        .unwrap()
+        .set_normals(&normals)
+        .unwrap()
+        .set_tangents(&[[0.0, 0.0, 1.0, 1.0]; 8])
+        .unwrap()
+        .set_indices_u16(CUBE_INDICES);
+    mesh
+}
+
+fn positions(mesh: &MeshDataRef) -> Vec<[f32; 3]> {
+    mesh.positions_f32().unwrap().collect()
+}
+
+fn normals(mesh: &MeshDataRef) -> Vec<[f32; 3]> {
+    mesh.normals_f32().unwrap().collect()
+}
+
+fn tangents(mesh: &MeshDataRef) -> Vec<[f32; 4]> {
+    mesh.attribute_f32::<4>(VertexUsage::Tangent).unwrap().collect()
+}
+
+fn assert_close(
+    a: &[[f32; 3]],
+    b: &[[f32; 3]],
+) {
+    assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter().zip(b) {
+        assert!((0..3).all(|i| (a[i] - b[i]).abs() < 1e-6), "{a:?} != {b:?}");
+    }
+}
+
+#[test]
+fn uniform_scale() {
+    let cube = cube();
+    let cube = cube.as_mesh_ref();
+    let scaled = transform(&cube, scale([2.5; 3])).unwrap();
+    let scaled = scaled.as_mesh_ref();
+    let expected: Vec<[f32; 3]> =
+        CUBE_POSITIONS.iter().map(|p| p.map(|c| c * 2.5)).collect();
+    assert_eq!(positions(&scaled), expected);
+    // Normals and tangents are renormalized
+    assert_eq!(normals(&scaled), normals(&cube));
+    assert_eq!(tangents(&scaled), tangents(&cube));
+    assert_eq!(scaled.indices, cube.indices);
+}
+
+#[test]
+fn non_uniform_scale_uses_the_inverse_transpose() {
+    let mut mesh = MeshData::new();
+    let s = 1.0 / 2.0f32.sqrt();
+    mesh.set_positions(&[[1.0, 0.0, 0.0]])
+        .unwrap()
+        .set_normals(&[[s, s, 0.0]])
+        .unwrap();
+    let scaled =
+        transform(&mesh.as_mesh_ref(), scale([2.0, 1.0, 1.0])).unwrap();
+    let scaled = scaled.as_mesh_ref();
+    assert_eq!(positions(&scaled), [[2.0, 0.0, 0.0]]);
+    // The normal of the plane x + y = 1, scaled to x / 2 + y = 1
+    let len = 1.25f32.sqrt();
+    assert_close(&normals(&scaled), &[[0.5 / len, 1.0 / len, 0.0]]);
+}
+
+#[test]
+fn mirror_flips_the_winding() {
+    let cube = cube();
+    let cube = cube.as_mesh_ref();
+    let mirrored = transform(&cube, scale([-1.0, 1.0, 1.0])).unwrap();
+    let mirrored = mirrored.as_mesh_ref();
+    let flip = |p: &[f32; 3]| [-p[0], p[1], p[2]];
+    let expected: Vec<[f32; 3]> = CUBE_POSITIONS.iter().map(flip).collect();
+    assert_eq!(positions(&mirrored), expected);
+    let expected: Vec<[f32; 3]> = normals(&cube).iter().map(flip).collect();
+    assert_eq!(normals(&mirrored), expected);
+    assert_eq!(tangents(&mirrored), [[0.0, 0.0, 1.0, -1.0]; 8]);
+
+    let triangles: Vec<_> = mirrored.iter_triangles().unwrap().collect();
+    let expected: Vec<_> =
+        cube.iter_triangles().unwrap().map(|[a, b, c]| [a, c, b]).collect();
+    assert_eq!(triangles, expected);
+
+    // Mirroring twice gives back the cube
+    let back = transform(&mirrored, scale([-1.0, 1.0, 1.0])).unwrap();
+    assert_eq!(back.as_mesh_ref().indices, cube.indices);
+    assert_eq!(tangents(&back.as_mesh_ref()), tangents(&cube));
+}
+
+#[test]
+fn mirror_reorders_non_indexed_vertices() {
+    let mut soup = MeshData::new();
+    soup.set_positions(&cube_soup()).unwrap();
+    let mirrored =
+        transform(&soup.as_mesh_ref(), scale([1.0, 1.0, -1.0])).unwrap();
+    let mirrored = mirrored.as_mesh_ref();
+    assert!(mirrored.indices.is_none());
+    let expected: Vec<[f32; 3]> = cube_soup()
+        .chunks_exact(3)
+        .flat_map(|t| [t[0], t[2], t[1]])
+        .map(|p| [p[0], p[1], -p[2]])
+        .collect();
+    assert_eq!(positions(&mirrored), expected);
+}
+
+#[test]
+fn translation_leaves_normals_untouched() {
+    let cube = cube();
+    let cube = cube.as_mesh_ref();
+    let moved = transform(&cube, translation([1.0, -2.0, 3.0])).unwrap();
+    let moved = moved.as_mesh_ref();
+    let expected: Vec<[f32; 3]> = CUBE_POSITIONS
+        .iter()
+        .map(|p| [p[0] + 1.0, p[1] - 2.0, p[2] + 3.0])
+        .collect();
+    assert_eq!(positions(&moved), expected);
+    for usage in [VertexUsage::Normal, VertexUsage::Tangent] {
+        assert_eq!(moved.attributes[&usage], cube.attributes[&usage]);
+    }
+    assert_eq!(moved.indices, cube.indices);
+}
+
+/// A box from (1, -2, 3) to (5, 0, 4).
+fn offset_box() -> MeshData {
+    let positions: Vec<[f32; 3]> = CUBE_POSITIONS
+        .iter()
+        .map(|p| [3.0 + p[0] * 2.0, -1.0 + p[1], 3.5 + p[2] * 0.5])
+        .collect();
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&positions).unwrap().set_indices_u16(CUBE_INDICES);
+    mesh
+}
+
+#[test]
+fn recenter_on_bounds() {
+    let mesh = offset_box();
+    let (centered, applied) =
+        recenter(&mesh.as_mesh_ref(), CenterMode::Bounds).unwrap();
+    assert_eq!(
+        applied,
+        AppliedTransform {
+            translation: [-3.0, 1.0, -3.5],
+            scale: 1.0,
+        }
+    );
+    assert_eq!(
+        centered.as_mesh_ref().position_bounds(),
+        Some(([-2.0, -1.0, -0.5], [2.0, 1.0, 0.5]))
+    );
+    // The applied transform maps the original positions to the new ones
+    let moved: Vec<[f32; 3]> = positions(&mesh.as_mesh_ref())
+        .into_iter()
+        .map(|p| applied.apply(p))
+        .collect();
+    assert_eq!(positions(&centered.as_mesh_ref()), moved);
+}
+
+#[test]
+fn recenter_on_centroid() {
+    // Most vertices on one side, so the centroid is not the center
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&[
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+    ])
+    .unwrap();
+    let (centered, applied) =
+        recenter(&mesh.as_mesh_ref(), CenterMode::Centroid).unwrap();
+    assert_eq!(applied.translation, [-0.75, 0.0, 0.0]);
+    assert_eq!(
+        positions(&centered.as_mesh_ref()),
+        [
+            [-0.75, 0.0, 0.0],
+            [0.25, 0.0, 0.0],
+            [0.25, 0.0, 0.0],
+            [0.25, 0.0, 0.0],
+        ]
+    );
+}
+
+#[test]
+fn recenter_and_normalize_scale() {
+    let mesh = offset_box();
+    let (centered, _) =
+        recenter(&mesh.as_mesh_ref(), CenterMode::Bounds).unwrap();
+    let (normalized, scale) = normalize_scale(&centered.as_mesh_ref()).unwrap();
+    assert_eq!(scale, 0.25);
+    let (min, max) = normalized.as_mesh_ref().position_bounds().unwrap();
+    assert_eq!((min, max), ([-0.5, -0.25, -0.125], [0.5, 0.25, 0.125]));
+    assert_eq!(normalized.as_mesh_ref().indices, mesh.as_mesh_ref().indices);
+}
+
+#[test]
+fn degenerate_meshes_are_not_scaled() {
+    let mut point = MeshData::new();
+    point.set_positions(&[[2.0, 3.0, 4.0]; 3]).unwrap();
+    let (scaled, scale) = normalize_scale(&point.as_mesh_ref()).unwrap();
+    assert_eq!(scale, 1.0);
+    assert_eq!(positions(&scaled.as_mesh_ref()), [[2.0, 3.0, 4.0]; 3]);
+    let (centered, applied) =
+        recenter(&point.as_mesh_ref(), CenterMode::Bounds).unwrap();
+    assert_eq!(applied.translation, [-2.0, -3.0, -4.0]);
+    assert_eq!(positions(&centered.as_mesh_ref()), [[0.0; 3]; 3]);
+
+    let mut empty = MeshData::new();
+    empty.set_positions(&[]).unwrap();
+    for mode in [CenterMode::Bounds, CenterMode::Centroid] {
+        let (centered, applied) = recenter(&empty.as_mesh_ref(), mode).unwrap();
+        assert_eq!(applied, AppliedTransform::default());
+        assert_eq!(centered.n_vertices(), 0);
+    }
+    assert_eq!(normalize_scale(&empty.as_mesh_ref()).unwrap().1, 1.0);
+}
@@ -0,0 +1,141 @@
+use std::io::Cursor;
+
+use iyes_mesh::checksum::{ChecksumKind, checksum_metadata};
+use iyes_mesh::header::IyesMeshHeader;
+use iyes_mesh::read::{IyesMeshReader, IyesMeshReaderSettings, ReadError};
+
+mod common;
+use common::*;
+
+/// User data that does not compress, so that it is stored as-is.
+fn noise(len: usize) -> Vec<u8> {
+    let mut rng = 7u64;
+    (0..len)
+        .map(|_| {
+            rng = rng
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (rng >> 56) as u8
+        })
+        .collect()
+}
+
+fn encode_with_user_data(
+    meshes: &[TestMesh],
+    user_data: &[u8],
+) -> Vec<u8> {
+    let mut writer = writer_for(meshes, Default::default());
+    writer.set_user_data(user_data);
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+    out.into_inner()
+}
+
+fn read_user_data(
+    bytes: &[u8],
+    settings: IyesMeshReaderSettings,
+) -> Result<Vec<u8>, ReadError> {
+    let mut read = Cursor::new(bytes);
+    IyesMeshReader::init_with_settings(settings, &mut read)?.read_user_data()
+}
+
+fn read_all(bytes: &[u8]) -> Result<(), ReadError> {
+    let mut read = Cursor::new(bytes);
+    IyesMeshReader::init(&mut read)?.read_all_data()?;
+    Ok(())
+}
+
+#[test]
+fn user_data_checksum_compares_files() {
+    let user_data = b"level: 3\nspawn: [1, 2, 3]\n";
+    let meshes = test_meshes();
+    let a = encode_with_user_data(&meshes[..1], user_data);
+    let b = encode_with_user_data(&meshes[1..], user_data);
+    let c = encode_with_user_data(&meshes[1..], b"level: 4\n");
+
+    let checksum = |bytes: &[u8]| {
+        let mut read = Cursor::new(bytes);
+        let reader = IyesMeshReader::init(&mut read).unwrap();
+        let descriptor = reader.descriptor();
+        let kind = ChecksumKind::from_id(descriptor.checksum_kind).unwrap();
+        assert_eq!(
+            descriptor.user_data_checksum,
+            Some(kind.checksum(user_data))
+        );
+        descriptor.user_data_checksum
+    };
+    assert_eq!(checksum(&a), checksum(&b));
+    let mut read = Cursor::new(&c);
+    let reader = IyesMeshReader::init(&mut read).unwrap();
+    assert_ne!(reader.descriptor().user_data_checksum, checksum(&a));
+
+    let no_user_data = encode(&meshes, Default::default());
+    let mut read = Cursor::new(&no_user_data);
+    let reader = IyesMeshReader::init(&mut read).unwrap();
+    assert_eq!(reader.descriptor().user_data_checksum, None);
+}
+
+/// Reading the user data only checks its own checksum, not the one of the
+/// whole payload.
+#[test]
+fn read_user_data_skips_the_data_checksum() {
+    let user_data = noise(1000);
+    let mut bytes = encode_with_user_data(&test_meshes(), &user_data);
+    let header_len = IyesMeshHeader::encoded_len();
+    let mut header = IyesMeshHeader::from_bytes(&bytes[..header_len]).unwrap();
+    let descriptor_end = header_len + header.descriptor_len as usize;
+    header.data_checksum ^= 1;
+    header.metadata_checksum =
+        checksum_metadata(header, &bytes[header_len..descriptor_end]);
+    bytes[..header_len].copy_from_slice(header.as_bytes());
+
+    assert!(matches!(read_all(&bytes), Err(ReadError::InvalidChecksums)));
+    assert_eq!(read_user_data(&bytes, Default::default()).unwrap(), user_data);
+    let settings = IyesMeshReaderSettings {
+        verify_user_data_checksum: false,
+        ..Default::default()
+    };
+    assert!(matches!(
+        read_user_data(&bytes, settings),
+        Err(ReadError::InvalidChecksums)
+    ));
+}
+
+#[test]
+fn corrupted_user_data_is_detected() {
+    // More than a zstd block, so that at least one is stored raw
+    let user_data = noise(300_000);
+    let mut bytes = encode_with_user_data(&test_meshes(), &user_data);
+    let window = &user_data[150_000..150_032];
+    let at = bytes
+        .windows(window.len())
+        .position(|w| w == window)
+        .expect("the user data should be stored uncompressed");
+    bytes[at] ^= 0xff;
+
+    assert!(matches!(
+        read_user_data(&bytes, Default::default()),
+        Err(ReadError::InvalidChecksums)
+    ));
+    let settings = IyesMeshReaderSettings {
+        verify_data_checksum: false,
+        verify_user_data_checksum: false,
+        ..Default::default()
+    };
+    let corrupted = read_user_data(&bytes, settings).unwrap();
+    assert_eq!(corrupted.len(), user_data.len());
+    assert_ne!(corrupted, user_data);
+}
+
+/// Files written before the user data checksum existed do not have one.
+#[test]
+fn user_data_without_checksum() {
+    let user_data = noise(100);
+    let bytes = encode_with_user_data(&test_meshes(), &user_data);
+    let bytes = with_descriptor(&bytes, |d| d.user_data_checksum = None);
+    assert_eq!(read_user_data(&bytes, Default::default()).unwrap(), user_data);
+    assert_eq!(
+        decode(&bytes).decode_user_data().unwrap().as_deref(),
+        Some(user_data.as_slice())
+    );
+}
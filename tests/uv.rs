@@ -0,0 +1,168 @@
+use iyes_mesh::descriptor::{VertexFormat, VertexUsage};
+use iyes_mesh::mesh::{MeshData, MeshError, transform_uvs};
+
+/// A unit quad, with UVs covering the texture.
+const QUAD_POSITIONS: [[f32; 3]; 4] = [
+    [0.0, 0.0, 0.0],
+    [1.0, 0.0, 0.0],
+    [1.0, 1.0, 0.0],
+    [0.0, 1.0, 0.0],
+];
+const QUAD_UVS: [[f32; 2]; 4] =
+    [[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]];
+
+fn quad(uvs: &[[f32; 2]]) -> MeshData {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&QUAD_POSITIONS)
+        .unwrap()
+        .set_attribute_pod(VertexUsage::Uv1, VertexFormat::Float32x2, uvs)
+        .unwrap()
+        .set_indices_u16(&[0, 1, 2, 2, 3, 0]);
+    mesh
+}
+
+fn uvs(mesh: &MeshData) -> Vec<[f32; 2]> {
+    mesh.as_mesh_ref().uvs_f32(VertexUsage::Uv1).unwrap().collect()
+}
+
+#[test]
+fn flip_v() {
+    let quad = quad(&QUAD_UVS);
+    let flipped = transform_uvs(
+        &quad.as_mesh_ref(),
+        VertexUsage::Uv1,
+        [1.0; 2],
+        [0.0; 2],
+        true,
+    )
+    .unwrap();
+    assert_eq!(uvs(&flipped), [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+    // Everything else is unchanged
+    let (flipped, quad) = (flipped.as_mesh_ref(), quad.as_mesh_ref());
+    assert_eq!(flipped.indices, quad.indices);
+    assert_eq!(
+        flipped.attributes[&VertexUsage::Position],
+        quad.attributes[&VertexUsage::Position]
+    );
+}
+
+#[test]
+fn scale_and_offset_into_an_atlas() {
+    let quad = quad(&QUAD_UVS);
+    let transformed = transform_uvs(
+        &quad.as_mesh_ref(),
+        VertexUsage::Uv1,
+        [0.5, 0.25],
+        [0.5, 0.75],
+        false,
+    )
+    .unwrap();
+    assert_eq!(
+        uvs(&transformed),
+        [[0.5, 1.0], [1.0, 1.0], [1.0, 0.75], [0.5, 0.75]]
+    );
+    // The flip is done before scaling
+    let transformed = transform_uvs(
+        &quad.as_mesh_ref(),
+        VertexUsage::Uv1,
+        [0.5, 0.25],
+        [0.5, 0.75],
+        true,
+    )
+    .unwrap();
+    assert_eq!(
+        uvs(&transformed),
+        [[0.5, 0.75], [1.0, 0.75], [1.0, 1.0], [0.5, 1.0]]
+    );
+}
+
+#[test]
+fn tiling_uvs_are_not_clamped() {
+    let quad = quad(&[[-1.0, 3.0], [4.0, 3.0], [4.0, -2.0], [-1.0, -2.0]]);
+    let transformed = transform_uvs(
+        &quad.as_mesh_ref(),
+        VertexUsage::Uv1,
+        [2.0, 1.0],
+        [0.0, 0.5],
+        true,
+    )
+    .unwrap();
+    assert_eq!(
+        uvs(&transformed),
+        [[-2.0, -1.5], [8.0, -1.5], [8.0, 3.5], [-2.0, 3.5]]
+    );
+}
+
+#[test]
+fn transform_uvs_errors() {
+    let quad = quad(&QUAD_UVS);
+    assert!(matches!(
+        transform_uvs(
+            &quad.as_mesh_ref(),
+            VertexUsage::Uv0,
+            [1.0; 2],
+            [0.0; 2],
+            true
+        ),
+        Err(MeshError::MissingAttribute(VertexUsage::Uv0))
+    ));
+    let mut unorm = quad.clone();
+    unorm
+        .set_attribute_bytes(
+            VertexUsage::Uv1,
+            VertexFormat::Unorm16x2,
+            &[0; 16],
+        )
+        .unwrap();
+    assert!(matches!(
+        transform_uvs(
+            &unorm.as_mesh_ref(),
+            VertexUsage::Uv1,
+            [1.0; 2],
+            [0.0; 2],
+            true
+        ),
+        Err(MeshError::UnsupportedFormat {
+            usage: VertexUsage::Uv1,
+            format: VertexFormat::Unorm16x2,
+        })
+    ));
+}
+
+#[cfg(feature = "f16")]
+#[test]
+fn float16_uvs_keep_their_format() {
+    use iyes_mesh::f16::encode_f32_to_f16_buf;
+
+    let mut quad = quad(&QUAD_UVS);
+    let flat: Vec<f32> = QUAD_UVS.iter().flatten().copied().collect();
+    quad.set_attribute_bytes(
+        VertexUsage::Uv1,
+        VertexFormat::Float16x2,
+        &encode_f32_to_f16_buf(&flat),
+    )
+    .unwrap();
+    let flipped = transform_uvs(
+        &quad.as_mesh_ref(),
+        VertexUsage::Uv1,
+        [1.0; 2],
+        [0.0; 2],
+        true,
+    )
+    .unwrap();
+    let format = flipped.as_mesh_ref().attributes[&VertexUsage::Uv1].0;
+    assert_eq!(format, VertexFormat::Float16x2);
+    assert_eq!(uvs(&flipped), [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+}
+
+#[test]
+fn uv_set_usages() {
+    for (i, usage) in VertexUsage::UVS.iter().enumerate() {
+        assert_eq!(VertexUsage::uv(i), Some(*usage));
+        assert_eq!(usage.uv_index(), Some(i));
+        assert_eq!(usage.to_string(), format!("Uv{i}"));
+        assert_eq!(format!("uv{i}").parse::<VertexUsage>().unwrap(), *usage);
+    }
+    assert_eq!(VertexUsage::uv(8), None);
+    assert_eq!(VertexUsage::Color.uv_index(), None);
+}
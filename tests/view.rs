@@ -0,0 +1,112 @@
+use iyes_mesh::HashMap;
+use iyes_mesh::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+use iyes_mesh::mesh::MeshDataRef;
+use iyes_mesh::view::ViewError;
+
+mod common;
+use common::*;
+
+/// The positions as bytes, once at an aligned address and once at an odd
+/// address (in the returned storage, after the first byte).
+fn aligned_and_misaligned(positions: &[[f32; 3]]) -> (Vec<u8>, Vec<u8>) {
+    let aligned: Vec<u8> = bytemuck::cast_slice(positions).to_vec();
+    let mut shifted = vec![0xff];
+    shifted.extend_from_slice(&aligned);
+    (aligned, shifted)
+}
+
+fn mesh<'a>(
+    positions: &'a [u8],
+    indices: &'a [u8],
+) -> MeshDataRef<'a> {
+    let mut attributes = HashMap::default();
+    attributes
+        .insert(VertexUsage::Position, (VertexFormat::Float32x3, positions));
+    MeshDataRef {
+        indices: Some((IndexFormat::U16, indices)),
+        attributes,
+    }
+}
+
+#[test]
+fn misaligned_views_are_copied() {
+    let positions = [[1.0f32, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]];
+    let (aligned, shifted) = aligned_and_misaligned(&positions);
+    let indices: Vec<u8> =
+        [0u16, 1, 2].iter().flat_map(|i| i.to_le_bytes()).collect();
+    let mut shifted_indices = vec![0xff];
+    shifted_indices.extend_from_slice(&indices);
+
+    let mesh_aligned = mesh(&aligned, &indices);
+    let view =
+        mesh_aligned.attr_view::<[f32; 3]>(VertexUsage::Position).unwrap();
+    assert!(view.is_borrowed());
+    assert_eq!(&*view, &positions);
+    assert_eq!(
+        mesh_aligned.attr_slice::<[f32; 3]>(VertexUsage::Position).unwrap(),
+        &positions
+    );
+    let index_view = mesh_aligned.index_view::<u16>().unwrap();
+    assert!(index_view.is_borrowed());
+    assert_eq!(&*index_view, &[0, 1, 2]);
+
+    let mesh_misaligned = mesh(&shifted[1..], &shifted_indices[1..]);
+    let view =
+        mesh_misaligned.attr_view::<[f32; 3]>(VertexUsage::Position).unwrap();
+    assert!(!view.is_borrowed());
+    assert_eq!(&*view, &positions);
+    assert_eq!(
+        mesh_misaligned.attr_slice::<[f32; 3]>(VertexUsage::Position),
+        Err(ViewError::Misaligned)
+    );
+    let index_view = mesh_misaligned.index_view::<u16>().unwrap();
+    assert!(!index_view.is_borrowed());
+    assert_eq!(&*index_view, &[0, 1, 2]);
+    assert_eq!(
+        mesh_misaligned.index_slice::<u16>(),
+        Err(ViewError::Misaligned)
+    );
+}
+
+#[test]
+fn view_errors() {
+    let positions = [[1.0f32, 2.0, 3.0]];
+    let (aligned, _) = aligned_and_misaligned(&positions);
+    let indices = [0u8, 0];
+    let m = mesh(&aligned, &indices);
+    // Elements of a different size
+    assert_eq!(
+        m.attr_view::<[f32; 4]>(VertexUsage::Position).unwrap_err(),
+        ViewError::WrongFormat {
+            format_size: 12,
+            type_size: 16
+        }
+    );
+    assert_eq!(
+        m.index_view::<u32>().unwrap_err(),
+        ViewError::WrongFormat {
+            format_size: 2,
+            type_size: 4
+        }
+    );
+    assert_eq!(
+        m.attr_view::<[f32; 3]>(VertexUsage::Normal).unwrap_err(),
+        ViewError::Missing
+    );
+}
+
+#[test]
+fn views_of_decoded_buffers() {
+    let meshes = test_meshes();
+    let with_data = decode(&encode(&meshes, Default::default()));
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let positions =
+        buffers.attr_view::<[f32; 3]>(VertexUsage::Position).unwrap();
+    let expected: Vec<u8> =
+        meshes.iter().flat_map(|m| m.positions.clone()).collect();
+    assert_eq!(bytemuck::cast_slice::<[f32; 3], u8>(&positions), expected);
+    let indices = buffers.index_view::<u16>().unwrap();
+    let expected: Vec<u8> =
+        meshes.iter().flat_map(|m| m.indices.clone()).collect();
+    assert_eq!(bytemuck::cast_slice::<u16, u8>(&indices), expected);
+}
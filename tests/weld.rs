@@ -0,0 +1,95 @@
+use iyes_mesh::descriptor::{IndexFormat, VertexUsage};
+use iyes_mesh::mesh::{MeshData, weld};
+
+mod common;
+use common::*;
+
+fn positions(mesh: &MeshData) -> Vec<[f32; 3]> {
+    mesh.as_mesh_ref().positions_f32().unwrap().collect()
+}
+
+fn indices(mesh: &MeshData) -> Vec<u32> {
+    mesh.as_mesh_ref().iter_indices().unwrap().collect()
+}
+
+#[test]
+fn weld_exact_duplicates() {
+    let mut soup = MeshData::new();
+    soup.set_positions(&cube_soup()).unwrap();
+    let welded = weld(&soup.as_mesh_ref(), None).unwrap();
+    // Vertices are kept in order of first use, which is the original order
+    assert_eq!(positions(&welded), CUBE_POSITIONS);
+    let expected: Vec<u32> = CUBE_INDICES.iter().map(|i| *i as u32).collect();
+    assert_eq!(indices(&welded), expected);
+    assert_eq!(welded.as_mesh_ref().indices.unwrap().0, IndexFormat::U16);
+
+    // Welding an indexed mesh remaps its indices
+    let mut indexed = MeshData::new();
+    let reversed: Vec<u16> = (0..36).rev().collect();
+    indexed.set_positions(&cube_soup()).unwrap().set_indices_u16(&reversed);
+    let welded = weld(&indexed.as_mesh_ref(), None).unwrap();
+    assert_eq!(welded.n_vertices(), 8);
+    let expected: Vec<u32> = expected.into_iter().rev().collect();
+    assert_eq!(indices(&welded), expected);
+}
+
+#[test]
+fn weld_compares_all_attributes() {
+    // One normal per face, so only vertices of the same face are merged
+    let normals: Vec<[f32; 3]> =
+        (0..36).map(|v| [(v / 6) as f32, 0.0, 0.0]).collect();
+    let mut soup = MeshData::new();
+    soup.set_positions(&cube_soup()).unwrap().set_normals(&normals).unwrap();
+    let welded = weld(&soup.as_mesh_ref(), None).unwrap();
+    assert_eq!(welded.n_vertices(), 24);
+    let corners: Vec<[f32; 3]> = indices(&welded)
+        .iter()
+        .map(|i| positions(&welded)[*i as usize])
+        .collect();
+    assert_eq!(corners, cube_soup());
+    let (_, welded_normals) =
+        welded.as_mesh_ref().attributes[&VertexUsage::Normal];
+    assert_eq!(welded_normals.len(), 24 * 12);
+}
+
+#[test]
+fn weld_with_epsilon() {
+    let jittered: Vec<[f32; 3]> = cube_soup()
+        .iter()
+        .enumerate()
+        .map(|(v, p)| p.map(|c| c + v as f32 * 1e-5))
+        .collect();
+    let mut soup = MeshData::new();
+    soup.set_positions(&jittered).unwrap();
+    let mesh = soup.as_mesh_ref();
+    assert_eq!(weld(&mesh, None).unwrap().n_vertices(), 36);
+    assert_eq!(weld(&mesh, Some(1e-6)).unwrap().n_vertices(), 36);
+    let welded = weld(&mesh, Some(1e-3)).unwrap();
+    assert_eq!(welded.n_vertices(), 8);
+    // The first vertex of each group is kept
+    let mut first = vec![];
+    for (v, i) in CUBE_INDICES.iter().enumerate() {
+        if !first.iter().any(|(j, _)| j == i) {
+            first.push((*i, jittered[v]));
+        }
+    }
+    let expected: Vec<[f32; 3]> = first.iter().map(|(_, p)| *p).collect();
+    assert_eq!(positions(&welded), expected);
+    // Deterministic
+    let again = weld(&mesh, Some(1e-3)).unwrap();
+    assert_eq!(positions(&again), positions(&welded));
+    assert_eq!(indices(&again), indices(&welded));
+}
+
+#[test]
+fn weld_to_u32_indices() {
+    let n = u16::MAX as usize + 10;
+    let positions: Vec<[f32; 3]> =
+        (0..n).map(|v| [v as f32, 0.0, 0.0]).collect();
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&positions).unwrap();
+    let welded = weld(&mesh.as_mesh_ref(), None).unwrap();
+    assert_eq!(welded.n_vertices(), n);
+    assert_eq!(welded.as_mesh_ref().indices.unwrap().0, IndexFormat::U32);
+    assert_eq!(indices(&welded), (0..n as u32).collect::<Vec<_>>());
+}
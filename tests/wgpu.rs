@@ -0,0 +1,132 @@
+#![cfg(feature = "wgpu")]
+
+use std::io::Cursor;
+
+use iyes_mesh::HashMap;
+use iyes_mesh::descriptor::{
+    IndexFormat, IyesMeshDescriptor, VertexFormat, VertexUsage,
+};
+use iyes_mesh::mesh::MeshData;
+use iyes_mesh::wgpu::OwnedVertexBufferLayout;
+use iyes_mesh::write::IyesMeshWriter;
+use wgpu_types as wgt;
+
+mod common;
+use common::*;
+
+#[test]
+fn vertex_formats_map_both_ways() {
+    let mut seen = vec![];
+    for format in VertexFormat::ALL.iter().copied() {
+        let converted = wgt::VertexFormat::from(format);
+        assert_eq!(format!("{converted:?}"), format!("{format:?}"));
+        assert_eq!(converted.size(), format.size() as u64, "{format:?}");
+        assert_eq!(VertexFormat::from(converted), format);
+        assert!(!seen.contains(&converted), "{format:?} is listed twice");
+        seen.push(converted);
+    }
+}
+
+#[test]
+fn index_formats_map_both_ways() {
+    for (format, expected) in [
+        (IndexFormat::U16, wgt::IndexFormat::Uint16),
+        (IndexFormat::U32, wgt::IndexFormat::Uint32),
+    ] {
+        assert_eq!(wgt::IndexFormat::from(format), expected);
+        assert_eq!(IndexFormat::from(expected), format);
+    }
+}
+
+/// The descriptor of a file with positions, normals, UVs and colors.
+fn descriptor() -> IyesMeshDescriptor {
+    let mut mesh = MeshData::new();
+    mesh.set_positions(&[[0.0; 3]; 3])
+        .unwrap()
+        .set_normals(&[[0.0, 1.0, 0.0]; 3])
+        .unwrap()
+        .set_uv0(&[[0.5; 2]; 3])
+        .unwrap()
+        .set_colors_f32(&[[1.0; 4]; 3])
+        .unwrap();
+    let mut out = Cursor::new(vec![]);
+    IyesMeshWriter::new()
+        .with_mesh(mesh.as_mesh_ref())
+        .unwrap()
+        .write_to(&mut out)
+        .unwrap();
+    decode(out.get_ref()).descriptor().clone()
+}
+
+/// Colors are left out.
+fn locations() -> HashMap<VertexUsage, u32> {
+    [
+        (VertexUsage::Position, 0),
+        (VertexUsage::Normal, 1),
+        (VertexUsage::Uv0, 2),
+    ]
+    .into_iter()
+    .collect()
+}
+
+#[test]
+fn planar_layouts() {
+    let layouts = descriptor().vertex_buffer_layouts(&locations());
+    let expected = [
+        (VertexUsage::Position, wgt::VertexFormat::Float32x3, 0, 12),
+        (VertexUsage::Normal, wgt::VertexFormat::Float32x3, 1, 12),
+        (VertexUsage::Uv0, wgt::VertexFormat::Float32x2, 2, 8),
+    ];
+    assert_eq!(layouts.len(), expected.len());
+    for (layout, (usage, format, location, stride)) in
+        layouts.iter().zip(expected)
+    {
+        assert_eq!(
+            *layout,
+            OwnedVertexBufferLayout {
+                array_stride: stride,
+                step_mode: wgt::VertexStepMode::Vertex,
+                attributes: vec![wgt::VertexAttribute {
+                    format,
+                    offset: 0,
+                    shader_location: location,
+                }],
+                usages: vec![usage],
+            }
+        );
+    }
+}
+
+#[test]
+fn interleaved_layout() {
+    let layout = descriptor().interleaved_vertex_buffer_layout(&locations());
+    assert_eq!(
+        layout,
+        OwnedVertexBufferLayout {
+            array_stride: 32,
+            step_mode: wgt::VertexStepMode::Vertex,
+            attributes: vec![
+                wgt::VertexAttribute {
+                    format: wgt::VertexFormat::Float32x3,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                wgt::VertexAttribute {
+                    format: wgt::VertexFormat::Float32x3,
+                    offset: 12,
+                    shader_location: 1,
+                },
+                wgt::VertexAttribute {
+                    format: wgt::VertexFormat::Float32x2,
+                    offset: 24,
+                    shader_location: 2,
+                },
+            ],
+            usages: vec![
+                VertexUsage::Position,
+                VertexUsage::Normal,
+                VertexUsage::Uv0
+            ],
+        }
+    );
+}
@@ -0,0 +1,380 @@
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+use iyes_mesh::checksum::ChecksumKind;
+use iyes_mesh::descriptor::{IndexFormat, VertexFormat, VertexUsage};
+use iyes_mesh::header::IyesMeshHeader;
+use iyes_mesh::mesh::MeshDataRef;
+use iyes_mesh::read::{IyesMeshReader, ReadError};
+use iyes_mesh::write::{IyesMeshWriter, IyesMeshWriterSettings, WriteError};
+
+mod common;
+use common::*;
+
+fn settings_with(kind: ChecksumKind) -> IyesMeshWriterSettings {
+    IyesMeshWriterSettings {
+        checksum_kind: kind,
+        ..Default::default()
+    }
+}
+
+/// Encode the same file in every way the writer can.
+fn encode_all_ways(
+    meshes: &[TestMesh],
+    settings: IyesMeshWriterSettings,
+) -> Vec<(&'static str, Vec<u8>)> {
+    let mut r = vec![];
+
+    let mut out = vec![];
+    writer_for(meshes, settings).write_to_stream(&mut out).unwrap();
+    r.push(("write_to_stream", out));
+
+    let spooled = IyesMeshWriterSettings {
+        spool_threshold: Some(64),
+        ..settings
+    };
+    let mut out = vec![];
+    writer_for(meshes, spooled).write_to_stream(&mut out).unwrap();
+    r.push(("write_to_stream (spooled)", out));
+
+    r.push(("write_to", encode(meshes, settings)));
+
+    let mut out = Cursor::new(vec![]);
+    writer_for(meshes, settings).write_to_readable(&mut out).unwrap();
+    r.push(("write_to_readable", out.into_inner()));
+
+    // Not opened for reading
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.iyesmesh");
+    let mut file = std::fs::File::create(&path).unwrap();
+    writer_for(meshes, settings).write_to(&mut file).unwrap();
+    drop(file);
+    r.push(("write_to (write-only file)", std::fs::read(&path).unwrap()));
+
+    r
+}
+
+#[test]
+fn all_write_paths_are_identical() {
+    let meshes = test_meshes();
+    for &kind in ChecksumKind::ALL {
+        for write_data_checksum in [true, false] {
+            let settings = IyesMeshWriterSettings {
+                write_data_checksum,
+                ..settings_with(kind)
+            };
+            let outputs = encode_all_ways(&meshes, settings);
+            let (_, reference) = &outputs[0];
+            for (name, bytes) in &outputs[1..] {
+                assert!(
+                    bytes == reference,
+                    "{name} differs ({kind}, checksum: {write_data_checksum})"
+                );
+            }
+            let header = IyesMeshHeader::from_bytes(
+                &reference[..IyesMeshHeader::encoded_len()],
+            )
+            .unwrap();
+            assert_eq!(header.data_checksum != 0, write_data_checksum);
+            decode(reference);
+        }
+    }
+}
+
+/// With a payload of several chunks, spilling to a temp file must not
+/// change the output either.
+#[test]
+fn spooled_write_matches_in_memory() {
+    let meshes = large_meshes();
+    let settings = IyesMeshWriterSettings::fastest();
+    let mut in_memory = vec![];
+    writer_for(&meshes, settings).write_to_stream(&mut in_memory).unwrap();
+    for spool_threshold in [0, 1 << 20] {
+        let spooled = IyesMeshWriterSettings {
+            spool_threshold: Some(spool_threshold),
+            ..settings
+        };
+        let mut out = vec![];
+        writer_for(&meshes, spooled).write_to_stream(&mut out).unwrap();
+        assert!(out == in_memory, "threshold {spool_threshold}");
+    }
+    assert!(encode(&meshes, settings) == in_memory);
+    decode(&in_memory);
+}
+
+#[test]
+fn data_checksum_detects_corruption() {
+    let meshes = test_meshes();
+    for &kind in ChecksumKind::ALL {
+        let mut bytes = encode(&meshes, settings_with(kind));
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        let mut read = Cursor::new(&bytes);
+        let reader = IyesMeshReader::init(&mut read).unwrap();
+        assert!(matches!(
+            reader.verify_data_checksum(),
+            Err(ReadError::InvalidChecksums)
+        ));
+    }
+}
+
+/// Seekable output that cannot be read, like a `File` opened write-only.
+struct WriteOnly(Cursor<Vec<u8>>);
+
+impl Write for WriteOnly {
+    fn write(
+        &mut self,
+        buf: &[u8],
+    ) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for WriteOnly {
+    fn seek(
+        &mut self,
+        pos: SeekFrom,
+    ) -> std::io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+#[test]
+fn write_to_after_existing_data() {
+    let meshes = test_meshes();
+    for &kind in ChecksumKind::ALL {
+        let expected = encode(&meshes, settings_with(kind));
+        let mut out = WriteOnly(Cursor::new(b"prefix".to_vec()));
+        out.0.seek(SeekFrom::End(0)).unwrap();
+        writer_for(&meshes, settings_with(kind)).write_to(&mut out).unwrap();
+        let mut bytes = vec![];
+        out.0.rewind().unwrap();
+        out.0.read_to_end(&mut bytes).unwrap();
+        assert_eq!(&bytes[..6], b"prefix");
+        assert!(bytes[6..] == expected, "{kind}");
+    }
+}
+
+#[cfg(feature = "signing")]
+#[test]
+fn signed_write_paths_are_identical() {
+    use iyes_mesh::read::IyesMeshReaderSettings;
+
+    let meshes = test_meshes();
+    let signing_key = [7; 32];
+    for &kind in ChecksumKind::ALL {
+        let settings = IyesMeshWriterSettings {
+            signing_key: Some(signing_key),
+            ..settings_with(kind)
+        };
+        let outputs = encode_all_ways(&meshes, settings);
+        let (_, reference) = &outputs[0];
+        for (name, bytes) in &outputs[1..] {
+            assert!(bytes == reference, "{name} differs ({kind})");
+        }
+        let settings = IyesMeshReaderSettings {
+            verifying_key: Some(iyes_mesh::signing::verifying_key(
+                &signing_key,
+            )),
+            ..Default::default()
+        };
+        let mut read = Cursor::new(reference);
+        IyesMeshReader::init_with_settings(settings, &mut read)
+            .unwrap()
+            .read_all_data()
+            .unwrap();
+    }
+}
+
+#[test]
+fn round_trip_four_uv_sets() {
+    let positions: Vec<u8> =
+        (0..9).flat_map(|i| (i as f32).to_le_bytes()).collect();
+    let uvs: Vec<Vec<u8>> = (0..4)
+        .map(|set| {
+            (0..6)
+                .flat_map(|i| (set as f32 * 10.0 + i as f32).to_le_bytes())
+                .collect()
+        })
+        .collect();
+    let mut attributes = iyes_mesh::HashMap::default();
+    attributes.insert(
+        VertexUsage::Position,
+        (VertexFormat::Float32x3, positions.as_slice()),
+    );
+    for (set, data) in uvs.iter().enumerate() {
+        attributes.insert(
+            VertexUsage::uv(set).unwrap(),
+            (VertexFormat::Float32x2, data.as_slice()),
+        );
+    }
+    let mut writer = IyesMeshWriter::new();
+    writer
+        .add_mesh(MeshDataRef {
+            indices: None,
+            attributes,
+        })
+        .unwrap();
+    let mut out = Cursor::new(vec![]);
+    writer.write_to(&mut out).unwrap();
+
+    let with_data = decode(out.get_ref());
+    let buffers = with_data.into_flat_buffers().unwrap();
+    let meshes = with_data.into_split_meshes(&buffers).unwrap();
+    let mesh = &meshes.meshes[0];
+    for (set, data) in uvs.iter().enumerate() {
+        let usage = VertexUsage::uv(set).unwrap();
+        assert_eq!(
+            mesh.attributes[&usage],
+            (VertexFormat::Float32x2, data.as_slice())
+        );
+    }
+    assert_eq!(mesh.attributes.len(), 5);
+}
+
+#[cfg(not(feature = "f16"))]
+#[test]
+fn float16_conversion_needs_the_feature() {
+    use iyes_mesh::convert::can_convert;
+
+    assert!(!can_convert(VertexFormat::Float32x2, VertexFormat::Float16x2));
+    assert!(!can_convert(VertexFormat::Float16x4, VertexFormat::Float32x4));
+    assert!(can_convert(VertexFormat::Float32x2, VertexFormat::Unorm16x2));
+}
+
+#[test]
+fn invalid_compression_level() {
+    let range = IyesMeshWriterSettings::compression_level_range();
+    let meshes = test_meshes();
+    for level in [*range.end() + 1, *range.start() - 1, 9000] {
+        let settings = IyesMeshWriterSettings {
+            compression_level: level,
+            ..Default::default()
+        };
+        let err = writer_for(&meshes, settings)
+            .write_to(&mut Cursor::new(vec![]))
+            .unwrap_err();
+        let WriteError::InvalidCompressionLevel {
+            given,
+            min,
+            max,
+        } = err
+        else {
+            panic!("{err}");
+        };
+        assert_eq!((given, min, max), (level, *range.start(), *range.end()));
+    }
+}
+
+#[test]
+fn settings_presets() {
+    let presets = [
+        IyesMeshWriterSettings::fastest(),
+        IyesMeshWriterSettings::balanced(),
+        IyesMeshWriterSettings::smallest(),
+    ];
+    for pair in presets.windows(2) {
+        assert!(pair[0].compression_level < pair[1].compression_level);
+    }
+    assert_eq!(presets[2], IyesMeshWriterSettings::default());
+    let meshes = test_meshes();
+    let sizes: Vec<_> = presets
+        .iter()
+        .map(|settings| {
+            settings.validate().unwrap();
+            let bytes = encode(&meshes, *settings);
+            decode(&bytes);
+            bytes.len()
+        })
+        .collect();
+    assert!(sizes[2] <= sizes[0], "{sizes:?}");
+}
+
+#[test]
+fn writer_is_reusable() {
+    let meshes = test_meshes();
+    for write_data_checksum in [true, false] {
+        let settings = IyesMeshWriterSettings {
+            write_data_checksum,
+            ..Default::default()
+        };
+        let expected = encode(&meshes, settings);
+        let mut writer = IyesMeshWriter::new_with_settings(settings)
+            .with_mesh(meshes[0].as_ref())
+            .unwrap();
+        for mesh in &meshes[1..] {
+            writer = writer.with_mesh(mesh.as_ref()).unwrap();
+        }
+        for _ in 0..2 {
+            let mut out = Cursor::new(vec![]);
+            writer.write_to(&mut out).unwrap();
+            assert!(out.into_inner() == expected);
+            let mut out = vec![];
+            writer.write_to_stream(&mut out).unwrap();
+            assert!(out == expected);
+        }
+        // Changes after a write are picked up by the next one
+        writer.add_mesh(meshes[0].as_ref()).unwrap();
+        let mut out = Cursor::new(vec![]);
+        writer.write_to(&mut out).unwrap();
+        let with_data = decode(out.get_ref());
+        assert_eq!(with_data.descriptor().meshes.len(), meshes.len() + 1);
+    }
+}
+
+#[test]
+fn plan_matches_write() {
+    let meshes = test_meshes();
+    let wide: Vec<u8> = meshes[0]
+        .indices
+        .chunks_exact(2)
+        .flat_map(|i| (u16::from_le_bytes([i[0], i[1]]) as u32).to_le_bytes())
+        .collect();
+    let wide_mesh = MeshDataRef {
+        indices: Some((IndexFormat::U32, wide.as_slice())),
+        ..meshes[0].as_ref()
+    };
+    for upconvert in [false, true] {
+        let settings = IyesMeshWriterSettings {
+            upconvert_indices: true,
+            ..Default::default()
+        };
+        let mut writer = writer_for(&meshes, settings);
+        if upconvert {
+            writer.add_mesh(wide_mesh.clone()).unwrap();
+        }
+        let plan = writer.plan().unwrap();
+        assert_eq!(plan.upconverting_indices, upconvert);
+        // All indices are U32 (twice the size) if any mesh has U32 indices
+        let index_scale = if upconvert {
+            2
+        } else {
+            1
+        };
+        let expected_len: usize = meshes
+            .iter()
+            .chain(upconvert.then_some(&meshes[0]))
+            .map(|m| {
+                m.positions.len()
+                    + m.normals.len()
+                    + m.indices.len() * index_scale
+            })
+            .sum();
+        assert_eq!(plan.total_uncompressed_len, expected_len as u64);
+
+        let mut out = Cursor::new(vec![]);
+        writer.write_to(&mut out).unwrap();
+        let bytes = out.into_inner();
+        let header =
+            IyesMeshHeader::from_bytes(&bytes[..IyesMeshHeader::encoded_len()])
+                .unwrap();
+        assert_eq!(header.descriptor_len as usize, plan.descriptor_len);
+        assert_eq!(
+            format!("{:?}", decode(&bytes).descriptor()),
+            format!("{:?}", plan.descriptor)
+        );
+    }
+}
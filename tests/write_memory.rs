@@ -0,0 +1,86 @@
+//! Checks how much memory `write_to` uses, with an allocator that keeps
+//! track of it. This is its own test binary, so that other tests running
+//! at the same time do not count.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::{Read, Seek};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use iyes_mesh::write::IyesMeshWriterSettings;
+
+mod common;
+use common::*;
+
+struct TrackingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for TrackingAlloc {
+    unsafe fn alloc(
+        &self,
+        layout: Layout,
+    ) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let now = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+            PEAK.fetch_max(now + layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(
+        &self,
+        ptr: *mut u8,
+        layout: Layout,
+    ) {
+        unsafe { System.dealloc(ptr, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: TrackingAlloc = TrackingAlloc;
+
+/// The most memory allocated at once while running `f`, in addition to
+/// what was already allocated.
+fn peak_during(f: impl FnOnce()) -> usize {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    f();
+    PEAK.load(Ordering::Relaxed) - before
+}
+
+/// With the default checksum (which must know the length of the data in
+/// advance), writing to a seekable output must not keep the compressed
+/// data in memory, whether or not it can be read back.
+#[test]
+fn seekable_writes_do_not_buffer_the_payload() {
+    let meshes = large_meshes();
+    let settings = IyesMeshWriterSettings::fastest();
+    assert!(settings.checksum_kind.needs_len());
+    let mut buffered = vec![];
+    writer_for(&meshes, settings).write_to_stream(&mut buffered).unwrap();
+    let header_len = iyes_mesh::header::IyesMeshHeader::encoded_len();
+    let payload_len = buffered.len() - header_len;
+    assert!(payload_len > 1 << 20, "{payload_len}");
+
+    for readable in [false, true] {
+        let mut writer = writer_for(&meshes, settings);
+        let mut file = tempfile::tempfile().unwrap();
+        let peak = peak_during(|| {
+            if readable {
+                writer.write_to_readable(&mut file).unwrap();
+            } else {
+                writer.write_to(&mut file).unwrap();
+            }
+        });
+        assert!(peak < payload_len / 4, "{peak} of {payload_len}");
+
+        let mut written = vec![];
+        file.rewind().unwrap();
+        file.read_to_end(&mut written).unwrap();
+        assert!(written == buffered, "readable: {readable}");
+        decode(&written);
+    }
+}